@@ -0,0 +1,138 @@
+//! 關聯資料 (AAD) 建構模組
+//!
+//! 將對話 ID、寄件者、時間戳、訊息類型等欄位以固定順序、長度前綴的方式
+//! 組成唯一的位元組字串，搭配 `encryptWithAad` / `decryptWithAad` 使用。
+//! 各自實作的客戶端只要設定相同欄位就會得到相同的 AAD，與設定順序無關
+//!
+//! 格式：version (1) || 依 tag 排序的已知欄位 || 依名稱排序的自訂欄位
+//! - 已知欄位：tag (1) || len (u32 BE) || value
+//! - 自訂欄位：0x80 || len(name) (u32 BE) || name || len(value) (u32 BE) || value
+
+use wasm_bindgen::prelude::*;
+use std::collections::BTreeMap;
+
+/// 目前的 AAD 格式版本
+pub const AAD_VERSION: u8 = 1;
+
+const TAG_CONVERSATION_ID: u8 = 0x01;
+const TAG_SENDER: u8 = 0x02;
+const TAG_TIMESTAMP: u8 = 0x03;
+const TAG_MESSAGE_TYPE: u8 = 0x04;
+const TAG_CUSTOM: u8 = 0x80;
+
+/// 標準 AAD 建構器
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default)]
+pub struct AadBuilder {
+    fields: BTreeMap<u8, Vec<u8>>,
+    custom: BTreeMap<String, Vec<u8>>,
+}
+
+fn push_length_prefixed(output: &mut Vec<u8>, value: &[u8]) {
+    output.extend_from_slice(&(value.len() as u32).to_be_bytes());
+    output.extend_from_slice(value);
+}
+
+#[wasm_bindgen]
+impl AadBuilder {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 對話 ID
+    #[wasm_bindgen(js_name = conversationId)]
+    pub fn conversation_id(&mut self, conversation_id: &str) {
+        self.fields.insert(TAG_CONVERSATION_ID, conversation_id.as_bytes().to_vec());
+    }
+
+    /// 寄件者 (身份公鑰)
+    pub fn sender(&mut self, sender: &[u8]) {
+        self.fields.insert(TAG_SENDER, sender.to_vec());
+    }
+
+    /// 時間戳 (毫秒)
+    pub fn timestamp(&mut self, timestamp: u64) {
+        self.fields.insert(TAG_TIMESTAMP, timestamp.to_be_bytes().to_vec());
+    }
+
+    /// 訊息類型
+    #[wasm_bindgen(js_name = messageType)]
+    pub fn message_type(&mut self, message_type: &str) {
+        self.fields.insert(TAG_MESSAGE_TYPE, message_type.as_bytes().to_vec());
+    }
+
+    /// 自訂欄位 (相同名稱會被取代)
+    pub fn custom(&mut self, name: &str, value: &[u8]) {
+        self.custom.insert(name.to_string(), value.to_vec());
+    }
+
+    /// 組出 AAD
+    pub fn build(&self) -> Vec<u8> {
+        let mut output = vec![AAD_VERSION];
+        for (tag, value) in &self.fields {
+            output.push(*tag);
+            push_length_prefixed(&mut output, value);
+        }
+        for (name, value) in &self.custom {
+            output.push(TAG_CUSTOM);
+            push_length_prefixed(&mut output, name.as_bytes());
+            push_length_prefixed(&mut output, value);
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::to_hex;
+
+    #[test]
+    fn test_canonical_encoding() {
+        let mut builder = AadBuilder::new();
+        builder.message_type("text");
+        builder.timestamp(1);
+        builder.sender(&[0xaa, 0xbb]);
+        builder.conversation_id("c1");
+
+        assert_eq!(
+            to_hex(&builder.build()),
+            concat!(
+                "01",
+                "01", "00000002", "6331",
+                "02", "00000002", "aabb",
+                "03", "00000008", "0000000000000001",
+                "04", "00000004", "74657874"
+            )
+        );
+
+        // 設定順序不影響結果
+        let mut other = AadBuilder::new();
+        other.conversation_id("c1");
+        other.sender(&[0xaa, 0xbb]);
+        other.timestamp(1);
+        other.message_type("text");
+        assert_eq!(other.build(), builder.build());
+    }
+
+    #[test]
+    fn test_custom_fields_and_boundaries() {
+        let mut first = AadBuilder::new();
+        first.custom("b", b"2");
+        first.custom("a", b"1");
+        let mut second = AadBuilder::new();
+        second.custom("a", b"1");
+        second.custom("b", b"2");
+        assert_eq!(first.build(), second.build());
+
+        // 長度前綴避免欄位邊界被移動
+        let mut shifted = AadBuilder::new();
+        shifted.custom("a", b"12");
+        let mut unshifted = AadBuilder::new();
+        unshifted.custom("a1", b"2");
+        assert_ne!(shifted.build(), unshifted.build());
+
+        assert_eq!(AadBuilder::new().build(), vec![AAD_VERSION]);
+    }
+}
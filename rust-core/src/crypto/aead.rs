@@ -0,0 +1,245 @@
+//! AEAD 抽象模組
+//!
+//! Double Ratchet 與附件加密透過共同的 `Aead` trait 使用對稱加密，
+//! 實際演算法由密文標頭中的 cipher suite 位元組決定：
+//! - AES-256-GCM：有 AES-NI 的原生平台最快
+//! - ChaCha20-Poly1305：純 WASM 環境下較快，且不依賴查表，沒有快取時序問題
+//!
+//! 雙方各自公告支援的 cipher suite (依偏好排序)，以 `negotiateCipherSuite` 選出共同的一種
+
+use wasm_bindgen::prelude::*;
+use aes_gcm::{
+    aead::{generic_array::GenericArray, AeadInPlace, KeyInit},
+    Aes256Gcm,
+};
+use chacha20poly1305::ChaCha20Poly1305;
+use serde::{Deserialize, Serialize};
+
+/// AEAD nonce 長度 (AES-256-GCM 與 ChaCha20-Poly1305 相同)
+pub const AEAD_NONCE_SIZE: usize = 12;
+/// AEAD 金鑰長度
+pub const AEAD_KEY_SIZE: usize = 32;
+/// AEAD 驗證標籤長度
+pub const AEAD_TAG_SIZE: usize = 16;
+
+/// 對稱加密 cipher suite (以單一位元組記錄在標頭中)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum CipherSuite {
+    #[default]
+    Aes256Gcm = 1,
+    ChaCha20Poly1305 = 2,
+}
+
+impl From<CipherSuite> for u8 {
+    fn from(suite: CipherSuite) -> u8 {
+        suite as u8
+    }
+}
+
+impl TryFrom<u8> for CipherSuite {
+    type Error = String;
+
+    fn try_from(byte: u8) -> Result<Self, String> {
+        match byte {
+            1 => Ok(CipherSuite::Aes256Gcm),
+            2 => Ok(CipherSuite::ChaCha20Poly1305),
+            _ => Err(format!("Unsupported cipher suite: {}", byte)),
+        }
+    }
+}
+
+impl CipherSuite {
+    /// 本端支援的 cipher suite，依偏好排序
+    ///
+    /// WASM 環境沒有 AES-NI，優先使用 ChaCha20-Poly1305
+    pub fn supported() -> &'static [CipherSuite] {
+        if cfg!(target_arch = "wasm32") {
+            &[CipherSuite::ChaCha20Poly1305, CipherSuite::Aes256Gcm]
+        } else {
+            &[CipherSuite::Aes256Gcm, CipherSuite::ChaCha20Poly1305]
+        }
+    }
+
+    /// 以金鑰建立加密器
+    pub fn cipher(self, key: &[u8]) -> Result<Box<dyn Aead>, String> {
+        if key.len() != AEAD_KEY_SIZE {
+            return Err(format!("Key must be {} bytes, got {}", AEAD_KEY_SIZE, key.len()));
+        }
+        let cipher: Box<dyn Aead> = match self {
+            CipherSuite::Aes256Gcm => Box::new(
+                Aes256Gcm::new_from_slice(key).map_err(|e| format!("Failed to create cipher: {}", e))?,
+            ),
+            CipherSuite::ChaCha20Poly1305 => Box::new(
+                ChaCha20Poly1305::new_from_slice(key).map_err(|e| format!("Failed to create cipher: {}", e))?,
+            ),
+        };
+        Ok(cipher)
+    }
+}
+
+/// 共同的 AEAD 介面
+pub trait Aead {
+    /// 使用的 cipher suite
+    fn suite(&self) -> CipherSuite;
+
+    /// 原地加密：密文覆寫明文，驗證標籤附加在最後
+    fn seal_in_place(&self, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), String>;
+
+    /// 原地驗證並解密：明文覆寫密文，並移除驗證標籤
+    fn open_in_place(&self, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), String>;
+
+    /// 加密，回傳密文 || 驗證標籤
+    fn seal(&self, nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let mut buffer = Vec::with_capacity(plaintext.len() + AEAD_TAG_SIZE);
+        buffer.extend_from_slice(plaintext);
+        self.seal_in_place(nonce, aad, &mut buffer)?;
+        Ok(buffer)
+    }
+
+    /// 驗證並解密
+    fn open(&self, nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        let mut buffer = ciphertext.to_vec();
+        self.open_in_place(nonce, aad, &mut buffer)?;
+        Ok(buffer)
+    }
+}
+
+fn check_nonce(nonce: &[u8]) -> Result<(), String> {
+    if nonce.len() != AEAD_NONCE_SIZE {
+        return Err(format!("Nonce must be {} bytes, got {}", AEAD_NONCE_SIZE, nonce.len()));
+    }
+    Ok(())
+}
+
+fn seal_in_place_with<A: AeadInPlace>(cipher: &A, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), String> {
+    check_nonce(nonce)?;
+    cipher
+        .encrypt_in_place(GenericArray::from_slice(nonce), aad, buffer)
+        .map_err(|e| format!("Encryption failed: {}", e))
+}
+
+fn open_in_place_with<A: AeadInPlace>(cipher: &A, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), String> {
+    check_nonce(nonce)?;
+    cipher
+        .decrypt_in_place(GenericArray::from_slice(nonce), aad, buffer)
+        .map_err(|e| format!("Decryption failed: {}", e))
+}
+
+impl Aead for Aes256Gcm {
+    fn suite(&self) -> CipherSuite {
+        CipherSuite::Aes256Gcm
+    }
+
+    fn seal_in_place(&self, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), String> {
+        seal_in_place_with(self, nonce, aad, buffer)
+    }
+
+    fn open_in_place(&self, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), String> {
+        open_in_place_with(self, nonce, aad, buffer)
+    }
+}
+
+impl Aead for ChaCha20Poly1305 {
+    fn suite(&self) -> CipherSuite {
+        CipherSuite::ChaCha20Poly1305
+    }
+
+    fn seal_in_place(&self, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), String> {
+        seal_in_place_with(self, nonce, aad, buffer)
+    }
+
+    fn open_in_place(&self, nonce: &[u8], aad: &[u8], buffer: &mut Vec<u8>) -> Result<(), String> {
+        open_in_place_with(self, nonce, aad, buffer)
+    }
+}
+
+/// 從本端與對方的偏好清單中選出 cipher suite (Rust 端使用)
+///
+/// 以本端的偏好順序為準，選出第一個對方也支援的；無法辨識的位元組會被忽略
+pub fn negotiate_suite(local: &[u8], remote: &[u8]) -> Option<CipherSuite> {
+    local
+        .iter()
+        .filter(|byte| remote.contains(byte))
+        .find_map(|&byte| CipherSuite::try_from(byte).ok())
+}
+
+/// 本端支援的 cipher suite 位元組 (依偏好排序，用於公告給對方)
+#[wasm_bindgen(js_name = supportedCipherSuites)]
+pub fn supported_cipher_suites() -> Vec<u8> {
+    CipherSuite::supported().iter().map(|&suite| suite.into()).collect()
+}
+
+/// 協商 cipher suite，沒有共同支援的時回傳 undefined
+#[wasm_bindgen(js_name = negotiateCipherSuite)]
+pub fn negotiate_cipher_suite(local: &[u8], remote: &[u8]) -> Option<CipherSuite> {
+    negotiate_suite(local, remote)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::to_hex;
+
+    #[test]
+    fn test_chacha20_poly1305_rfc8439_vector() {
+        // RFC 8439 §2.8.2
+        let key: Vec<u8> = (0x80..=0x9f).collect();
+        let nonce = [0x07, 0x00, 0x00, 0x00, 0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47];
+        let aad = [0x50, 0x51, 0x52, 0x53, 0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7];
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+
+        let cipher = CipherSuite::ChaCha20Poly1305.cipher(&key).unwrap();
+        let sealed = cipher.seal(&nonce, &aad, plaintext).unwrap();
+        assert_eq!(to_hex(&sealed[sealed.len() - AEAD_TAG_SIZE..]), "1ae10b594f09e26a7e902ecbd0600691");
+        assert_eq!(to_hex(&sealed[..16]), "d31a8d34648e60db7b86afbc53ef7ec2");
+        assert_eq!(cipher.open(&nonce, &aad, &sealed).unwrap(), plaintext);
+        assert!(cipher.open(&nonce, b"other", &sealed).is_err());
+    }
+
+    #[test]
+    fn test_suites_are_not_interchangeable() {
+        let key = [5u8; 32];
+        let nonce = [1u8; AEAD_NONCE_SIZE];
+        let aes = CipherSuite::Aes256Gcm.cipher(&key).unwrap();
+        let chacha = CipherSuite::ChaCha20Poly1305.cipher(&key).unwrap();
+        assert_eq!(aes.suite(), CipherSuite::Aes256Gcm);
+
+        let sealed = aes.seal(&nonce, b"", b"hello").unwrap();
+        assert!(chacha.open(&nonce, b"", &sealed).is_err());
+        assert!(aes.seal(&[0u8; 24], b"", b"hello").is_err());
+        assert!(CipherSuite::Aes256Gcm.cipher(&[0u8; 16]).is_err());
+
+        assert_eq!(CipherSuite::try_from(2), Ok(CipherSuite::ChaCha20Poly1305));
+        assert!(CipherSuite::try_from(0).is_err());
+        assert_eq!(serde_json::to_string(&CipherSuite::ChaCha20Poly1305).unwrap(), "2");
+    }
+
+    #[test]
+    fn test_in_place_matches_allocating_api() {
+        let nonce = [2u8; AEAD_NONCE_SIZE];
+        for suite in [CipherSuite::Aes256Gcm, CipherSuite::ChaCha20Poly1305] {
+            let cipher = suite.cipher(&[6u8; 32]).unwrap();
+            let mut buffer = b"large buffer".to_vec();
+            cipher.seal_in_place(&nonce, b"aad", &mut buffer).unwrap();
+            assert_eq!(buffer, cipher.seal(&nonce, b"aad", b"large buffer").unwrap());
+
+            let mut tampered = buffer.clone();
+            tampered[0] ^= 1;
+            assert!(cipher.open_in_place(&nonce, b"aad", &mut tampered).is_err());
+
+            cipher.open_in_place(&nonce, b"aad", &mut buffer).unwrap();
+            assert_eq!(buffer, b"large buffer");
+        }
+    }
+
+    #[test]
+    fn test_negotiate_suite() {
+        assert_eq!(negotiate_suite(&[2, 1], &[1, 2]), Some(CipherSuite::ChaCha20Poly1305));
+        assert_eq!(negotiate_suite(&[1, 2], &[2]), Some(CipherSuite::ChaCha20Poly1305));
+        assert_eq!(negotiate_suite(&[9, 1], &[9, 1]), Some(CipherSuite::Aes256Gcm));
+        assert_eq!(negotiate_suite(&[1], &[2]), None);
+        assert_eq!(supported_cipher_suites().len(), 2);
+    }
+}
@@ -1,17 +1,26 @@
 //! AES-GCM 對稱加密模組
 //!
-//! 提供 AES-256-GCM 加密/解密功能
+//! 提供 AES-256-GCM 加密/解密功能，以及大型檔案用的 STREAM 分段加密
+//! (STREAM 可選用 `CipherSuite` 中的任一種 AEAD)
 
 use wasm_bindgen::prelude::*;
 use aes_gcm::{
-    aead::{Aead, KeyInit, OsRng},
+    aead::{Aead, AeadInPlace, KeyInit, OsRng},
     Aes256Gcm, Nonce,
 };
 use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::collections::{HashSet, VecDeque};
+
+use super::aead::{Aead as AeadCipher, CipherSuite, AEAD_TAG_SIZE};
 
 const NONCE_SIZE: usize = 12;
 const KEY_SIZE: usize = 32;
+/// 計數器 nonce 的固定前綴長度
+pub const COUNTER_NONCE_PREFIX_SIZE: usize = 4;
+/// nonce 重用防護預設記住的隨機 nonce 數量
+pub const DEFAULT_NONCE_GUARD_CAPACITY: usize = 1024;
 
 /// 加密後的訊息結構
 #[wasm_bindgen]
@@ -23,6 +32,14 @@ pub struct EncryptedMessage {
     nonce: Vec<u8>,
 }
 
+#[cfg(any(test, feature = "webcrypto"))]
+impl EncryptedMessage {
+    /// 從 nonce 與密文建立 (Rust 端使用)
+    pub(crate) fn from_parts(nonce: Vec<u8>, ciphertext: Vec<u8>) -> Self {
+        Self { ciphertext, nonce }
+    }
+}
+
 #[wasm_bindgen]
 impl EncryptedMessage {
     /// 取得密文
@@ -70,10 +87,92 @@ impl EncryptedMessage {
     }
 }
 
+/// 計數器 nonce：prefix (4) || counter (u64 BE)
+struct CounterNonce {
+    prefix: [u8; COUNTER_NONCE_PREFIX_SIZE],
+    next: Cell<u64>,
+}
+
+/// nonce 重用防護 (開發期偵測用)
+///
+/// 隨機 nonce 模式下記住最近 `capacity` 個 nonce；計數器模式下只需確認計數器嚴格遞增
+struct NonceGuard {
+    capacity: usize,
+    recent: HashSet<[u8; NONCE_SIZE]>,
+    order: VecDeque<[u8; NONCE_SIZE]>,
+    last_counter: Option<u64>,
+}
+
+impl NonceGuard {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            recent: HashSet::new(),
+            order: VecDeque::new(),
+            last_counter: None,
+        }
+    }
+
+    /// 記錄並檢查隨機 nonce，超過容量時淘汰最舊的
+    fn check_random(&mut self, nonce: &[u8; NONCE_SIZE]) -> Result<(), String> {
+        if !self.recent.insert(*nonce) {
+            return Err("Nonce reuse detected".to_string());
+        }
+        self.order.push_back(*nonce);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.recent.remove(&oldest);
+            }
+        }
+        Ok(())
+    }
+
+    /// 檢查計數器是否嚴格遞增
+    fn check_counter(&mut self, counter: u64) -> Result<(), String> {
+        if self.last_counter.is_some_and(|last| counter <= last) {
+            return Err("Nonce reuse detected: counter did not increase".to_string());
+        }
+        self.last_counter = Some(counter);
+        Ok(())
+    }
+}
+
 /// AES-256-GCM 加密器
 #[wasm_bindgen]
 pub struct AesGcmCipher {
     cipher: Aes256Gcm,
+    /// 未設定時使用隨機 nonce
+    counter_nonce: Option<CounterNonce>,
+    /// 未啟用時不檢查 nonce 重用
+    nonce_guard: Option<RefCell<NonceGuard>>,
+}
+
+impl AesGcmCipher {
+    /// 下一個 nonce；計數器模式下到達上限時回傳錯誤
+    fn next_nonce(&self) -> Result<[u8; NONCE_SIZE], String> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        match &self.counter_nonce {
+            None => {
+                OsRng.fill_bytes(&mut nonce);
+                if let Some(guard) = &self.nonce_guard {
+                    guard.borrow_mut().check_random(&nonce)?;
+                }
+            }
+            Some(counter_nonce) => {
+                let counter = counter_nonce.next.get();
+                if counter == u64::MAX {
+                    return Err("Nonce counter exhausted, rotate the key".to_string());
+                }
+                if let Some(guard) = &self.nonce_guard {
+                    guard.borrow_mut().check_counter(counter)?;
+                }
+                nonce[..COUNTER_NONCE_PREFIX_SIZE].copy_from_slice(&counter_nonce.prefix);
+                nonce[COUNTER_NONCE_PREFIX_SIZE..].copy_from_slice(&counter.to_be_bytes());
+                counter_nonce.next.set(counter + 1);
+            }
+        }
+        Ok(nonce)
+    }
 }
 
 #[wasm_bindgen]
@@ -90,13 +189,58 @@ impl AesGcmCipher {
         }
         let cipher = Aes256Gcm::new_from_slice(key)
             .map_err(|e| JsError::new(&format!("Failed to create cipher: {}", e)))?;
-        Ok(Self { cipher })
+        Ok(Self { cipher, counter_nonce: None, nonce_guard: None })
+    }
+
+    /// 建立使用計數器 nonce 的加密器
+    ///
+    /// nonce 為 prefix (4) || counter (u64 BE)，不需隨機數也不會碰撞，
+    /// 適合高流量的通道。同一把金鑰下每個發送端需使用不同的 prefix，
+    /// 且必須保存 `counter` 並在重新建立時以 `start_counter` 傳回，
+    /// 否則會重複使用 nonce。計數器用盡時加密一律失敗
+    #[wasm_bindgen(js_name = withCounterNonce)]
+    pub fn with_counter_nonce(key: &[u8], prefix: &[u8], start_counter: Option<u64>) -> Result<AesGcmCipher, JsError> {
+        let prefix: [u8; COUNTER_NONCE_PREFIX_SIZE] = prefix.try_into().map_err(|_| {
+            JsError::new(&format!("Nonce prefix must be {} bytes", COUNTER_NONCE_PREFIX_SIZE))
+        })?;
+        let mut cipher = Self::new(key)?;
+        cipher.counter_nonce = Some(CounterNonce {
+            prefix,
+            next: Cell::new(start_counter.unwrap_or(0)),
+        });
+        Ok(cipher)
+    }
+
+    /// 啟用 nonce 重用防護：偵測到重複的 nonce 時加密一律失敗，而不是默默洩漏明文
+    ///
+    /// 隨機 nonce 模式下記住最近 `capacity` 個 nonce (預設 1024)；
+    /// 計數器模式下改為確認計數器嚴格遞增，不需額外記憶體。
+    /// 主要用於開發與測試期間找出錯誤的金鑰/計數器管理
+    #[wasm_bindgen(js_name = enableNonceGuard)]
+    pub fn enable_nonce_guard(&mut self, capacity: Option<usize>) -> Result<(), JsError> {
+        let capacity = capacity.unwrap_or(DEFAULT_NONCE_GUARD_CAPACITY);
+        if capacity == 0 {
+            return Err(JsError::new("Nonce guard capacity must be positive"));
+        }
+        self.nonce_guard = Some(RefCell::new(NonceGuard::new(capacity)));
+        Ok(())
+    }
+
+    /// 是否已啟用 nonce 重用防護
+    #[wasm_bindgen(getter, js_name = nonceGuardEnabled)]
+    pub fn nonce_guard_enabled(&self) -> bool {
+        self.nonce_guard.is_some()
+    }
+
+    /// 下一個要使用的計數器值 (需持久化)；隨機 nonce 模式下為 undefined
+    #[wasm_bindgen(getter)]
+    pub fn counter(&self) -> Option<u64> {
+        self.counter_nonce.as_ref().map(|counter_nonce| counter_nonce.next.get())
     }
 
     /// 加密訊息
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<EncryptedMessage, JsError> {
-        let mut nonce_bytes = [0u8; NONCE_SIZE];
-        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce_bytes = self.next_nonce().map_err(|e| JsError::new(&e))?;
         let nonce = Nonce::from_slice(&nonce_bytes);
 
         let ciphertext = self
@@ -119,8 +263,7 @@ impl AesGcmCipher {
     ) -> Result<EncryptedMessage, JsError> {
         use aes_gcm::aead::Payload;
 
-        let mut nonce_bytes = [0u8; NONCE_SIZE];
-        OsRng.fill_bytes(&mut nonce_bytes);
+        let nonce_bytes = self.next_nonce().map_err(|e| JsError::new(&e))?;
         let nonce = Nonce::from_slice(&nonce_bytes);
 
         let payload = Payload {
@@ -176,6 +319,33 @@ impl AesGcmCipher {
             .decrypt(nonce, payload)
             .map_err(|e| JsError::new(&format!("Decryption failed: {}", e)))
     }
+
+    /// 原地加密：密文直接覆寫 `data`，回傳 nonce (12) || 驗證標籤 (16)
+    ///
+    /// 大型緩衝區不需在 WASM 記憶體中另外配置密文
+    #[wasm_bindgen(js_name = encryptInPlace)]
+    pub fn encrypt_in_place(&self, data: &mut [u8], aad: &[u8]) -> Result<Vec<u8>, JsError> {
+        let nonce = self.next_nonce().map_err(|e| JsError::new(&e))?;
+        let tag = self
+            .cipher
+            .encrypt_in_place_detached(Nonce::from_slice(&nonce), aad, data)
+            .map_err(|e| JsError::new(&format!("Encryption failed: {}", e)))?;
+        let mut nonce_and_tag = nonce.to_vec();
+        nonce_and_tag.extend_from_slice(&tag);
+        Ok(nonce_and_tag)
+    }
+
+    /// 原地解密 `encryptInPlace` 的結果，驗證失敗時 `data` 內容未定義
+    #[wasm_bindgen(js_name = decryptInPlace)]
+    pub fn decrypt_in_place(&self, data: &mut [u8], nonce_and_tag: &[u8], aad: &[u8]) -> Result<(), JsError> {
+        if nonce_and_tag.len() != NONCE_SIZE + AEAD_TAG_SIZE {
+            return Err(JsError::new("Invalid nonce and tag size"));
+        }
+        let (nonce, tag) = nonce_and_tag.split_at(NONCE_SIZE);
+        self.cipher
+            .decrypt_in_place_detached(Nonce::from_slice(nonce), aad, data, tag.into())
+            .map_err(|e| JsError::new(&format!("Decryption failed: {}", e)))
+    }
 }
 
 /// 快速加密函式 (不需建立 Cipher 物件)
@@ -199,6 +369,197 @@ pub fn aes_decrypt_bytes(key: &[u8], encrypted_bytes: &[u8]) -> Result<Vec<u8>,
     aes_decrypt(key, &encrypted)
 }
 
+/// STREAM 格式版本
+pub const STREAM_VERSION: u8 = 1;
+/// STREAM 標頭長度：version (1) || cipher suite (1) || chunk size (4) || nonce prefix (7)
+pub const STREAM_HEADER_SIZE: usize = 13;
+/// 預設分段大小 (64 KiB)
+pub const DEFAULT_STREAM_CHUNK_SIZE: u32 = 64 * 1024;
+/// 分段大小上限 (16 MiB)
+pub const MAX_STREAM_CHUNK_SIZE: u32 = 16 * 1024 * 1024;
+
+const STREAM_NONCE_PREFIX_SIZE: usize = 7;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum StreamDirection {
+    Encrypt,
+    Decrypt,
+}
+
+/// STREAM 分段 AEAD
+///
+/// 依 Hoang-Reyhanitabar-Rogaway-Vizár 的 STREAM 建構切成固定大小的分段，
+/// 每段 nonce 為 prefix (7) || counter (u32 BE) || last flag (1)，
+/// 與 RustCrypto `aead::stream::StreamBE32` 相同。最後一段帶有結束旗標，
+/// 截斷、重排或拼接分段都會解密失敗。每段都以標頭作為 AAD
+///
+/// `push` 可傳入任意長度的資料，內部只緩衝不滿一段的部分，
+/// 數百 MB 的附件不需整份載入 WASM 記憶體
+#[wasm_bindgen]
+pub struct StreamCipher {
+    cipher: Box<dyn AeadCipher>,
+    direction: StreamDirection,
+    header: [u8; STREAM_HEADER_SIZE],
+    chunk_size: usize,
+    counter: u32,
+    buffer: Vec<u8>,
+    finished: bool,
+}
+
+impl StreamCipher {
+    fn with_header(key: &[u8], direction: StreamDirection, header: [u8; STREAM_HEADER_SIZE]) -> Result<Self, String> {
+        let suite = CipherSuite::try_from(header[1])?;
+        let chunk_size = u32::from_be_bytes([header[2], header[3], header[4], header[5]]);
+        if chunk_size == 0 || chunk_size > MAX_STREAM_CHUNK_SIZE {
+            return Err(format!("Invalid stream chunk size: {}", chunk_size));
+        }
+        let cipher = suite.cipher(key)?;
+        Ok(Self {
+            cipher,
+            direction,
+            header,
+            chunk_size: chunk_size as usize,
+            counter: 0,
+            buffer: Vec::new(),
+            finished: false,
+        })
+    }
+
+    /// 建立加密端 (Rust 端使用)
+    pub fn new_encryptor(key: &[u8], suite: CipherSuite, chunk_size: u32) -> Result<Self, String> {
+        let mut header = [0u8; STREAM_HEADER_SIZE];
+        header[0] = STREAM_VERSION;
+        header[1] = suite.into();
+        header[2..6].copy_from_slice(&chunk_size.to_be_bytes());
+        OsRng.fill_bytes(&mut header[6..]);
+        Self::with_header(key, StreamDirection::Encrypt, header)
+    }
+
+    /// 以加密端的標頭建立解密端 (Rust 端使用)
+    pub fn new_decryptor(key: &[u8], header: &[u8]) -> Result<Self, String> {
+        let header: [u8; STREAM_HEADER_SIZE] = header
+            .try_into()
+            .map_err(|_| format!("Stream header must be {} bytes", STREAM_HEADER_SIZE))?;
+        if header[0] != STREAM_VERSION {
+            return Err(format!("Unsupported stream version: {}", header[0]));
+        }
+        Self::with_header(key, StreamDirection::Decrypt, header)
+    }
+
+    fn nonce(&self, last: bool) -> [u8; NONCE_SIZE] {
+        let mut nonce = [0u8; NONCE_SIZE];
+        nonce[..STREAM_NONCE_PREFIX_SIZE].copy_from_slice(&self.header[6..]);
+        nonce[STREAM_NONCE_PREFIX_SIZE..NONCE_SIZE - 1].copy_from_slice(&self.counter.to_be_bytes());
+        nonce[NONCE_SIZE - 1] = last as u8;
+        nonce
+    }
+
+    /// 處理一段 (加密或解密) 並遞增計數器
+    fn process_segment(&mut self, mut segment: Vec<u8>, last: bool) -> Result<Vec<u8>, String> {
+        let nonce = self.nonce(last);
+        match self.direction {
+            StreamDirection::Encrypt => self.cipher.seal_in_place(&nonce, &self.header, &mut segment)?,
+            StreamDirection::Decrypt => self
+                .cipher
+                .open_in_place(&nonce, &self.header, &mut segment)
+                .map_err(|_| format!("Stream chunk {} failed to decrypt", self.counter))?,
+        }
+        if !last {
+            self.counter = self
+                .counter
+                .checked_add(1)
+                .ok_or_else(|| "Stream chunk counter exhausted".to_string())?;
+        }
+        Ok(segment)
+    }
+
+    /// 輸入端每段的長度
+    fn segment_size(&self) -> usize {
+        match self.direction {
+            StreamDirection::Encrypt => self.chunk_size,
+            StreamDirection::Decrypt => self.chunk_size + AEAD_TAG_SIZE,
+        }
+    }
+
+    /// 輸入資料，回傳目前可輸出的完整分段 (Rust 端使用)
+    pub fn push_bytes(&mut self, data: &[u8]) -> Result<Vec<u8>, String> {
+        if self.finished {
+            return Err("Stream already finished".to_string());
+        }
+        self.buffer.extend_from_slice(data);
+
+        // 一律保留最後一段，交給 finish 以結束旗標處理
+        let segment_size = self.segment_size();
+        let mut output = Vec::new();
+        let mut offset = 0;
+        while self.buffer.len() - offset > segment_size {
+            let segment = self.buffer[offset..offset + segment_size].to_vec();
+            output.extend(self.process_segment(segment, false)?);
+            offset += segment_size;
+        }
+        self.buffer.drain(..offset);
+        Ok(output)
+    }
+
+    /// 處理最後一段 (Rust 端使用)
+    pub fn finish_bytes(&mut self) -> Result<Vec<u8>, String> {
+        if self.finished {
+            return Err("Stream already finished".to_string());
+        }
+        self.finished = true;
+        let segment = std::mem::take(&mut self.buffer);
+        self.process_segment(segment, true)
+    }
+}
+
+#[wasm_bindgen]
+impl StreamCipher {
+    /// 建立加密端 (`chunk_size` 未提供時為 64 KiB，`suite` 未提供時為 AES-256-GCM)
+    #[wasm_bindgen(js_name = encryptor)]
+    pub fn encryptor(key: &[u8], chunk_size: Option<u32>, suite: Option<CipherSuite>) -> Result<StreamCipher, JsError> {
+        Self::new_encryptor(key, suite.unwrap_or_default(), chunk_size.unwrap_or(DEFAULT_STREAM_CHUNK_SIZE))
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 以加密端的標頭建立解密端
+    #[wasm_bindgen(js_name = decryptor)]
+    pub fn decryptor(key: &[u8], header: &[u8]) -> Result<StreamCipher, JsError> {
+        Self::new_decryptor(key, header).map_err(|e| JsError::new(&e))
+    }
+
+    /// 串流標頭 (需與密文一起保存，放在密文最前面)
+    #[wasm_bindgen(getter)]
+    pub fn header(&self) -> Vec<u8> {
+        self.header.to_vec()
+    }
+
+    /// 使用的 cipher suite
+    #[wasm_bindgen(getter, js_name = cipherSuite)]
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher.suite()
+    }
+
+    /// 分段大小
+    #[wasm_bindgen(getter, js_name = chunkSize)]
+    pub fn chunk_size(&self) -> u32 {
+        self.chunk_size as u32
+    }
+
+    /// 輸入任意長度的資料，回傳已處理完成的輸出 (可能為空)
+    #[wasm_bindgen(js_name = push)]
+    pub fn push(&mut self, data: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.push_bytes(data).map_err(|e| JsError::new(&e))
+    }
+
+    /// 結束串流並回傳最後的輸出
+    ///
+    /// 解密端在最後一段驗證失敗 (例如密文被截斷) 時回傳錯誤
+    #[wasm_bindgen(js_name = finish)]
+    pub fn finish(&mut self) -> Result<Vec<u8>, JsError> {
+        self.finish_bytes().map_err(|e| JsError::new(&e))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -244,4 +605,149 @@ mod tests {
         let decrypted = aes_decrypt(&key, &restored).unwrap();
         assert_eq!(plaintext.to_vec(), decrypted);
     }
+
+    #[test]
+    fn test_counter_nonce() {
+        let key = [0u8; 32];
+        let cipher = AesGcmCipher::with_counter_nonce(&key, &[1, 2, 3, 4], None).unwrap();
+        let first = cipher.encrypt(b"one").unwrap();
+        let second = cipher.encrypt_with_aad(b"two", b"aad").unwrap();
+        assert_eq!(first.nonce(), [1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(second.nonce(), [1, 2, 3, 4, 0, 0, 0, 0, 0, 0, 0, 1]);
+        assert_eq!(cipher.counter(), Some(2));
+        // 一般的 AesGcmCipher 即可解密
+        assert_eq!(AesGcmCipher::new(&key).unwrap().decrypt(&first).unwrap(), b"one");
+
+        // 以保存的計數器恢復
+        let restored = AesGcmCipher::with_counter_nonce(&key, &[1, 2, 3, 4], cipher.counter()).unwrap();
+        assert_eq!(restored.encrypt(b"three").unwrap().nonce()[4..], 2u64.to_be_bytes());
+
+        let exhausted = AesGcmCipher::with_counter_nonce(&key, &[1, 2, 3, 4], Some(u64::MAX - 1)).unwrap();
+        assert!(exhausted.next_nonce().is_ok());
+        assert!(exhausted.next_nonce().is_err());
+        assert!(exhausted.next_nonce().is_err());
+        assert_eq!(AesGcmCipher::new(&key).unwrap().counter(), None);
+    }
+
+    #[test]
+    fn test_encrypt_in_place() {
+        let key = [0u8; 32];
+        let cipher = AesGcmCipher::new(&key).unwrap();
+        let mut data = b"in place".to_vec();
+        let nonce_and_tag = cipher.encrypt_in_place(&mut data, b"aad").unwrap();
+        assert_ne!(data, b"in place");
+
+        // 與一般的 nonce || 密文 || 標籤格式相容
+        let encrypted = EncryptedMessage::from_parts(
+            nonce_and_tag[..NONCE_SIZE].to_vec(),
+            [&data[..], &nonce_and_tag[NONCE_SIZE..]].concat(),
+        );
+        assert_eq!(cipher.decrypt_with_aad(&encrypted, b"aad").unwrap(), b"in place");
+
+        cipher.decrypt_in_place(&mut data, &nonce_and_tag, b"aad").unwrap();
+        assert_eq!(data, b"in place");
+    }
+
+    #[test]
+    fn test_nonce_guard() {
+        let key = [0u8; 32];
+        let mut cipher = AesGcmCipher::new(&key).unwrap();
+        assert!(!cipher.nonce_guard_enabled());
+        cipher.enable_nonce_guard(Some(2)).unwrap();
+        assert!(cipher.nonce_guard_enabled());
+        let encrypted = cipher.encrypt(b"guarded").unwrap();
+        assert_eq!(cipher.decrypt(&encrypted).unwrap(), b"guarded");
+
+        // 模擬 RNG 故障：重複的隨機 nonce 被拒絕，超出容量的舊 nonce 會被淘汰
+        let mut guard = NonceGuard::new(2);
+        guard.check_random(&[1; NONCE_SIZE]).unwrap();
+        assert!(guard.check_random(&[1; NONCE_SIZE]).is_err());
+        guard.check_random(&[2; NONCE_SIZE]).unwrap();
+        guard.check_random(&[3; NONCE_SIZE]).unwrap();
+        assert!(guard.check_random(&[2; NONCE_SIZE]).is_err());
+        guard.check_random(&[1; NONCE_SIZE]).unwrap();
+
+        // 計數器模式：必須嚴格遞增
+        let mut counter_cipher = AesGcmCipher::with_counter_nonce(&key, &[1, 2, 3, 4], Some(5)).unwrap();
+        counter_cipher.enable_nonce_guard(None).unwrap();
+        counter_cipher.encrypt(b"one").unwrap();
+        counter_cipher.counter_nonce.as_ref().unwrap().next.set(5);
+        assert!(counter_cipher.next_nonce().is_err());
+        counter_cipher.counter_nonce.as_ref().unwrap().next.set(6);
+        assert!(counter_cipher.next_nonce().is_ok());
+    }
+
+    fn stream_encrypt(key: &[u8], suite: CipherSuite, chunk_size: u32, data: &[u8], piece: usize) -> (Vec<u8>, Vec<u8>) {
+        let mut encryptor = StreamCipher::new_encryptor(key, suite, chunk_size).unwrap();
+        let mut ciphertext = Vec::new();
+        for part in data.chunks(piece) {
+            ciphertext.extend(encryptor.push_bytes(part).unwrap());
+        }
+        ciphertext.extend(encryptor.finish_bytes().unwrap());
+        (encryptor.header().to_vec(), ciphertext)
+    }
+
+    fn stream_decrypt(key: &[u8], header: &[u8], ciphertext: &[u8], piece: usize) -> Result<Vec<u8>, String> {
+        let mut decryptor = StreamCipher::new_decryptor(key, header)?;
+        let mut plaintext = Vec::new();
+        for part in ciphertext.chunks(piece) {
+            plaintext.extend(decryptor.push_bytes(part)?);
+        }
+        plaintext.extend(decryptor.finish_bytes()?);
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn test_stream_round_trip() {
+        let key = [7u8; 32];
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+
+        // 輸入切法與分段大小無關；剛好整除的長度也要正確處理
+        for suite in [CipherSuite::Aes256Gcm, CipherSuite::ChaCha20Poly1305] {
+            for (chunk_size, piece) in [(1024, 333), (1000, 1000), (64, 5000), (20_000, 7)] {
+                let (header, ciphertext) = stream_encrypt(&key, suite, chunk_size, &data, piece);
+                let segments = (data.len() as u32).div_ceil(chunk_size).max(1) as usize;
+                assert_eq!(ciphertext.len(), data.len() + segments * AEAD_TAG_SIZE);
+                assert_eq!(stream_decrypt(&key, &header, &ciphertext, 777).unwrap(), data);
+            }
+        }
+
+        let (header, ciphertext) = stream_encrypt(&key, CipherSuite::ChaCha20Poly1305, 1024, b"", 1);
+        assert_eq!(StreamCipher::new_decryptor(&key, &header).unwrap().cipher_suite(), CipherSuite::ChaCha20Poly1305);
+        assert_eq!(stream_decrypt(&key, &header, &ciphertext, 1).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_stream_detects_truncation_and_reordering() {
+        let key = [7u8; 32];
+        let data = vec![42u8; 4096];
+        let (header, ciphertext) = stream_encrypt(&key, CipherSuite::Aes256Gcm, 1024, &data, 4096);
+        let segment = 1024 + AEAD_TAG_SIZE;
+
+        // 截斷在分段邊界：最後一段缺少結束旗標
+        assert!(stream_decrypt(&key, &header, &ciphertext[..3 * segment], 100).is_err());
+
+        // 交換兩段
+        let mut swapped = ciphertext.clone();
+        swapped[..segment].copy_from_slice(&ciphertext[segment..2 * segment]);
+        swapped[segment..2 * segment].copy_from_slice(&ciphertext[..segment]);
+        assert!(stream_decrypt(&key, &header, &swapped, 100).is_err());
+
+        // 竄改標頭 (AAD)
+        let mut other_header = header.clone();
+        other_header[11] ^= 1;
+        assert!(stream_decrypt(&key, &other_header, &ciphertext, 100).is_err());
+
+        // 改寫 cipher suite 位元組
+        let mut other_suite = header.clone();
+        other_suite[1] = CipherSuite::ChaCha20Poly1305.into();
+        assert!(stream_decrypt(&key, &other_suite, &ciphertext, 100).is_err());
+        other_suite[1] = 9;
+        assert!(StreamCipher::new_decryptor(&key, &other_suite).is_err());
+
+        let mut encryptor = StreamCipher::new_encryptor(&key, CipherSuite::Aes256Gcm, 1024).unwrap();
+        encryptor.finish_bytes().unwrap();
+        assert!(encryptor.push_bytes(b"late").is_err());
+        assert!(StreamCipher::new_encryptor(&key, CipherSuite::Aes256Gcm, 0).is_err());
+    }
 }
@@ -0,0 +1,334 @@
+//! age 檔案加密模組
+//!
+//! 以 age v1 格式 (age-encryption.org/v1) 與 X25519 收件者匯出、匯入檔案，
+//! 使用者即使不再使用本應用程式，也能以標準的 `age` / `rage` 工具解開匯出的備份與附件
+//!
+//! 格式：
+//!
+//! ```text
+//! age-encryption.org/v1
+//! -> X25519 <臨時公鑰>
+//! <以 ChaCha20-Poly1305 包裝的 16 bytes 檔案金鑰>
+//! --- <標頭 HMAC-SHA256>
+//! nonce (16) || STREAM 密文 (64 KiB 區塊，ChaCha20-Poly1305)
+//! ```
+//!
+//! 收件者為 Bech32 編碼的 `age1...` 公鑰，身份為 `AGE-SECRET-KEY-1...` 私鑰
+
+use wasm_bindgen::prelude::*;
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine as _};
+use bech32::{Bech32, Hrp};
+use chacha20poly1305::{
+    aead::{generic_array::GenericArray, Aead, KeyInit},
+    ChaCha20Poly1305,
+};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use sha2::Sha256;
+
+use super::constant_time::constant_time_eq;
+use super::keys::X25519KeyPair;
+use super::mac::hmac_sha256_bytes;
+use super::secret::SecretBytes;
+
+const AGE_VERSION_LINE: &str = "age-encryption.org/v1";
+const X25519_STANZA: &str = "X25519";
+const X25519_INFO: &[u8] = b"age-encryption.org/v1/X25519";
+const RECIPIENT_HRP: &str = "age";
+const IDENTITY_HRP: &str = "age-secret-key-";
+
+const FILE_KEY_SIZE: usize = 16;
+const PAYLOAD_NONCE_SIZE: usize = 16;
+const CHUNK_SIZE: usize = 64 * 1024;
+const TAG_SIZE: usize = 16;
+/// 標頭中 base64 的行寬
+const COLUMNS: usize = 64;
+
+/// 編碼 age 收件者 (`age1...`)
+pub fn age_recipient_string(public_key: &[u8; 32]) -> String {
+    bech32::encode::<Bech32>(Hrp::parse_unchecked(RECIPIENT_HRP), public_key).expect("recipient fits in bech32")
+}
+
+/// 解析 age 收件者 (`age1...`)，回傳 X25519 公鑰
+pub fn parse_age_recipient(recipient: &str) -> Result<[u8; 32], String> {
+    let (hrp, data) = bech32::decode(recipient).map_err(|e| format!("Invalid age recipient: {}", e))?;
+    if hrp.as_str() != RECIPIENT_HRP || recipient.chars().any(|c| c.is_ascii_uppercase()) {
+        return Err("Invalid age recipient".to_string());
+    }
+    data.try_into().map_err(|_| "age recipient must be 32 bytes".to_string())
+}
+
+fn parse_age_identity(identity: &str) -> Result<[u8; 32], String> {
+    let (hrp, data) = bech32::decode(identity).map_err(|e| format!("Invalid age identity: {}", e))?;
+    if hrp.to_lowercase() != IDENTITY_HRP {
+        return Err("Invalid age identity".to_string());
+    }
+    data.try_into().map_err(|_| "age identity must be 32 bytes".to_string())
+}
+
+fn hkdf_32(salt: &[u8], ikm: &[u8], info: &[u8]) -> [u8; 32] {
+    let mut okm = [0u8; 32];
+    Hkdf::<Sha256>::new(Some(salt), ikm).expand(info, &mut okm).expect("32 bytes is a valid HKDF length");
+    okm
+}
+
+/// 以 X25519 收件者包裝檔案金鑰，回傳 (臨時公鑰, 包裝後的檔案金鑰)
+fn wrap_file_key(file_key: &[u8; FILE_KEY_SIZE], recipient: &[u8; 32]) -> Result<([u8; 32], Vec<u8>), String> {
+    let ephemeral = X25519KeyPair::new();
+    let share: [u8; 32] = ephemeral.public_key_bytes().try_into().expect("X25519 public key is 32 bytes");
+    let shared = ephemeral.shared_secret(recipient)?;
+    if shared == [0u8; 32] {
+        return Err("Invalid age recipient".to_string());
+    }
+    let wrap_key = hkdf_32(&[share, *recipient].concat(), &shared, X25519_INFO);
+    let body = ChaCha20Poly1305::new(&wrap_key.into())
+        .encrypt(&GenericArray::default(), file_key.as_slice())
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+    Ok((share, body))
+}
+
+/// 嘗試以身份解開 X25519 stanza 中的檔案金鑰
+fn unwrap_file_key(identity: &X25519KeyPair, share: &[u8], body: &[u8]) -> Option<[u8; FILE_KEY_SIZE]> {
+    let shared = identity.shared_secret(share).ok()?;
+    if shared == [0u8; 32] {
+        return None;
+    }
+    let wrap_key = hkdf_32(&[share, &identity.public_key_bytes()].concat(), &shared, X25519_INFO);
+    let file_key = ChaCha20Poly1305::new(&wrap_key.into()).decrypt(&GenericArray::default(), body).ok()?;
+    file_key.try_into().ok()
+}
+
+/// 標頭 MAC：涵蓋到 `---` 為止的整個標頭
+fn header_mac(file_key: &[u8; FILE_KEY_SIZE], header: &[u8]) -> [u8; 32] {
+    hmac_sha256_bytes(&hkdf_32(&[], file_key, b"header"), header)
+}
+
+/// 依 64 欄換行的 base64 (最後一行一定短於 64 欄，必要時為空行)
+fn wrap_base64(data: &[u8]) -> String {
+    let encoded = STANDARD_NO_PAD.encode(data);
+    let mut lines = String::new();
+    for line in encoded.as_bytes().chunks(COLUMNS) {
+        lines.push_str(std::str::from_utf8(line).expect("base64 is ASCII"));
+        lines.push('\n');
+    }
+    if encoded.len().is_multiple_of(COLUMNS) {
+        lines.push('\n');
+    }
+    lines
+}
+
+fn payload_nonce(counter: u64, last: bool) -> [u8; 12] {
+    let mut nonce = [0u8; 12];
+    nonce[3..11].copy_from_slice(&counter.to_be_bytes());
+    nonce[11] = last as u8;
+    nonce
+}
+
+/// 以 age 格式加密給一或多位 X25519 收件者 (Rust 端使用)
+pub fn age_encrypt_to(plaintext: &[u8], recipients: &[[u8; 32]]) -> Result<Vec<u8>, String> {
+    if recipients.is_empty() {
+        return Err("At least one age recipient is required".to_string());
+    }
+    let mut file_key = [0u8; FILE_KEY_SIZE];
+    OsRng.fill_bytes(&mut file_key);
+
+    let mut header = format!("{}\n", AGE_VERSION_LINE);
+    for recipient in recipients {
+        let (share, body) = wrap_file_key(&file_key, recipient)?;
+        header.push_str(&format!("-> {} {}\n", X25519_STANZA, STANDARD_NO_PAD.encode(share)));
+        header.push_str(&wrap_base64(&body));
+    }
+    header.push_str("---");
+    let mac = header_mac(&file_key, header.as_bytes());
+    header.push_str(&format!(" {}\n", STANDARD_NO_PAD.encode(mac)));
+
+    let mut nonce = [0u8; PAYLOAD_NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    let cipher = ChaCha20Poly1305::new(&hkdf_32(&nonce, &file_key, b"payload").into());
+
+    let mut output = header.into_bytes();
+    output.extend_from_slice(&nonce);
+    let chunks: Vec<&[u8]> = if plaintext.is_empty() { vec![&[]] } else { plaintext.chunks(CHUNK_SIZE).collect() };
+    for (index, chunk) in chunks.iter().enumerate() {
+        let nonce = payload_nonce(index as u64, index + 1 == chunks.len());
+        let sealed = cipher
+            .encrypt(GenericArray::from_slice(&nonce), *chunk)
+            .map_err(|e| format!("Encryption failed: {}", e))?;
+        output.extend(sealed);
+    }
+    Ok(output)
+}
+
+/// 讀取一行 (不含 `\n`)，回傳該行與剩餘資料
+fn read_line(input: &[u8]) -> Result<(&str, &[u8]), String> {
+    let end = input.iter().position(|&b| b == b'\n').ok_or_else(|| "Truncated age header".to_string())?;
+    let line = std::str::from_utf8(&input[..end]).map_err(|_| "Invalid age header".to_string())?;
+    Ok((line, &input[end + 1..]))
+}
+
+/// 以 X25519 身份解密 age 檔案 (Rust 端使用)
+///
+/// 其他類型的 stanza 會被略過；標頭 MAC 或任何區塊驗證失敗時回傳錯誤
+pub fn age_decrypt_with(ciphertext: &[u8], identity: &X25519KeyPair) -> Result<Vec<u8>, String> {
+    let (version, mut rest) = read_line(ciphertext)?;
+    if version != AGE_VERSION_LINE {
+        return Err("Not an age file".to_string());
+    }
+
+    let mut file_key = None;
+    let mac_line = loop {
+        let (line, after) = read_line(rest)?;
+        let Some(arguments) = line.strip_prefix("-> ") else {
+            break line;
+        };
+        rest = after;
+
+        let mut body = Vec::new();
+        loop {
+            let (line, after) = read_line(rest)?;
+            rest = after;
+            if line.len() > COLUMNS {
+                return Err("Invalid age stanza".to_string());
+            }
+            body.extend(STANDARD_NO_PAD.decode(line).map_err(|_| "Invalid age stanza".to_string())?);
+            if line.len() < COLUMNS {
+                break;
+            }
+        }
+
+        let arguments: Vec<&str> = arguments.split(' ').collect();
+        if let [X25519_STANZA, share] = arguments.as_slice() {
+            let share = STANDARD_NO_PAD.decode(share).map_err(|_| "Invalid age stanza".to_string())?;
+            if share.len() != 32 || body.len() != FILE_KEY_SIZE + TAG_SIZE {
+                return Err("Invalid age X25519 stanza".to_string());
+            }
+            if file_key.is_none() {
+                file_key = unwrap_file_key(identity, &share, &body);
+            }
+        }
+    };
+
+    let mac = mac_line
+        .strip_prefix("--- ")
+        .and_then(|mac| STANDARD_NO_PAD.decode(mac).ok())
+        .ok_or_else(|| "Invalid age header".to_string())?;
+    let file_key = file_key.ok_or_else(|| "No matching age identity".to_string())?;
+    // MAC 涵蓋到 "---" 為止 (不含後面的空白與 MAC)
+    let header_len = ciphertext.len() - rest.len() + 3;
+    if !constant_time_eq(&header_mac(&file_key, &ciphertext[..header_len]), &mac) {
+        return Err("age header MAC mismatch".to_string());
+    }
+
+    let (_, payload) = read_line(rest)?;
+    if payload.len() < PAYLOAD_NONCE_SIZE + TAG_SIZE {
+        return Err("Truncated age payload".to_string());
+    }
+    let (nonce, mut body) = payload.split_at(PAYLOAD_NONCE_SIZE);
+    let cipher = ChaCha20Poly1305::new(&hkdf_32(nonce, &file_key, b"payload").into());
+
+    let mut plaintext = Vec::with_capacity(body.len());
+    let mut counter = 0u64;
+    loop {
+        let last = body.len() <= CHUNK_SIZE + TAG_SIZE;
+        let (chunk, remaining) = body.split_at(body.len().min(CHUNK_SIZE + TAG_SIZE));
+        // 只有整個檔案為空時，最後一個區塊才可以是空的
+        if last && counter > 0 && chunk.len() == TAG_SIZE {
+            return Err("Invalid age payload: empty final chunk".to_string());
+        }
+        let opened = cipher
+            .decrypt(GenericArray::from_slice(&payload_nonce(counter, last)), chunk)
+            .map_err(|_| "age payload authentication failed".to_string())?;
+        plaintext.extend(opened);
+        if last {
+            return Ok(plaintext);
+        }
+        body = remaining;
+        counter += 1;
+    }
+}
+
+/// 以 age 格式加密給一或多位收件者 (`age1...`)
+#[wasm_bindgen(js_name = ageEncrypt)]
+pub fn age_encrypt(plaintext: &[u8], recipients: Vec<String>) -> Result<Vec<u8>, JsError> {
+    let recipients = recipients
+        .iter()
+        .map(|recipient| parse_age_recipient(recipient))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| JsError::new(&e))?;
+    age_encrypt_to(plaintext, &recipients).map_err(|e| JsError::new(&e))
+}
+
+/// 以 X25519 身份解密 age 檔案
+#[wasm_bindgen(js_name = ageDecrypt)]
+pub fn age_decrypt(ciphertext: &[u8], identity: &X25519KeyPair) -> Result<Vec<u8>, JsError> {
+    age_decrypt_with(ciphertext, identity).map_err(|e| JsError::new(&e))
+}
+
+#[wasm_bindgen]
+impl X25519KeyPair {
+    /// age 收件者 (`age1...`)
+    #[wasm_bindgen(getter, js_name = ageRecipient)]
+    pub fn age_recipient(&self) -> String {
+        age_recipient_string(&self.public_key_bytes().try_into().expect("X25519 public key is 32 bytes"))
+    }
+
+    /// age 身份 (`AGE-SECRET-KEY-1...`，敏感！以 UTF-8 `SecretBytes` 回傳)
+    #[wasm_bindgen(js_name = ageIdentity)]
+    pub fn age_identity(&self) -> SecretBytes {
+        let identity = bech32::encode_upper::<Bech32>(Hrp::parse_unchecked(IDENTITY_HRP), self.private_key_bytes().expose())
+            .expect("identity fits in bech32");
+        identity.into_bytes().into()
+    }
+
+    /// 從 age 身份 (`AGE-SECRET-KEY-1...`) 還原
+    #[wasm_bindgen(js_name = fromAgeIdentity)]
+    pub fn from_age_identity(identity: &str) -> Result<X25519KeyPair, JsError> {
+        parse_age_identity(identity).map(X25519KeyPair::from_secret_bytes).map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_age_round_trip() {
+        let alice = X25519KeyPair::new();
+        let bob = X25519KeyPair::new();
+        let recipients = [
+            parse_age_recipient(&alice.age_recipient()).unwrap(),
+            parse_age_recipient(&bob.age_recipient()).unwrap(),
+        ];
+
+        for len in [0usize, 1, CHUNK_SIZE, CHUNK_SIZE + 1, 3 * CHUNK_SIZE] {
+            let plaintext: Vec<u8> = (0..len).map(|i| i as u8).collect();
+            let encrypted = age_encrypt_to(&plaintext, &recipients).unwrap();
+            assert!(encrypted.starts_with(b"age-encryption.org/v1\n-> X25519 "));
+            assert_eq!(age_decrypt_with(&encrypted, &alice).unwrap(), plaintext);
+            assert_eq!(age_decrypt_with(&encrypted, &bob).unwrap(), plaintext);
+            assert!(age_decrypt_with(&encrypted, &X25519KeyPair::new()).is_err());
+        }
+
+        let encrypted = age_encrypt_to(b"backup", &recipients[..1]).unwrap();
+        let mut tampered = encrypted.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(age_decrypt_with(&tampered, &alice).is_err());
+        // 標頭被竄改 (臨時公鑰的第一個字元) 時 MAC 驗證失敗
+        let mut tampered = encrypted;
+        tampered[32] = if tampered[32] == b'A' { b'B' } else { b'A' };
+        assert!(age_decrypt_with(&tampered, &alice).is_err());
+    }
+
+    #[test]
+    fn test_age_key_encoding() {
+        let identity = X25519KeyPair::from_secret_bytes([7u8; 32]);
+        let encoded = String::from_utf8(identity.age_identity().expose().to_vec()).unwrap();
+        assert!(encoded.starts_with("AGE-SECRET-KEY-1"));
+        assert_eq!(parse_age_identity(&encoded).unwrap(), [7u8; 32]);
+        assert!(identity.age_recipient().starts_with("age1"));
+
+        assert!(parse_age_recipient(&identity.age_recipient().to_uppercase()).is_err());
+        assert!(parse_age_recipient(&encoded).is_err());
+        assert!(parse_age_identity(&identity.age_recipient()).is_err());
+    }
+}
@@ -0,0 +1,591 @@
+//! 附件加密模組
+//!
+//! 檔案與其 manifest (檔名、MIME 類型、大小、明文摘要) 一起以隨機的附件金鑰加密，
+//! 輸出密文、附件金鑰與密文摘要。密文上傳到附件伺服器，
+//! 位置、金鑰、密文長度與摘要組成 `AttachmentPointer` 放在訊息內傳給對方；
+//! 下載後先比對長度與密文摘要 (不必解密即可發現截斷或替換)，解密後再驗證大小與明文摘要
+//!
+//! 密文格式：STREAM 標頭 (含 cipher suite) || STREAM 密文 (`StreamCipher`)，
+//! 明文為 len(manifest) (u32 BE) || manifest JSON || 檔案內容 || Padmé 填充，
+//! 伺服器看到的密文長度只洩漏檔案大小的數量級
+//!
+//! 摘要預設為 SHA-256，可以 `setDigestAlgorithm` 改用 BLAKE3 (WASM 中處理大型檔案較快)，
+//! 演算法記錄在 manifest 與 `AttachmentPointer` 中
+//!
+//! 以 `setCompression` 明確啟用時，檔案內容在加密前壓縮，所用的演算法記錄在 manifest 中；
+//! 壓縮會透過長度洩漏內容，只應用於不受攻擊者影響的檔案 (見 `compression` 模組)
+//!
+//! 同一則附件的縮圖、預覽與完整檔案可以分別以附件根金鑰的 HKDF 子金鑰加密，
+//! 引用回覆或連結預覽只需轉交縮圖子金鑰，對方無法解密完整檔案
+
+use wasm_bindgen::prelude::*;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+
+use super::aead::CipherSuite;
+use super::aes::{StreamCipher, DEFAULT_STREAM_CHUNK_SIZE, STREAM_HEADER_SIZE};
+use super::compression::Compression;
+use super::hash::HashAlgorithm;
+use super::constant_time::constant_time_eq;
+use super::padding::{padme_padding, padme_unpad_bytes};
+
+/// 附件金鑰長度
+pub const ATTACHMENT_KEY_SIZE: usize = 32;
+
+const INFO_ATTACHMENT_SUBKEY: &[u8] = b"SafeTalk_AttachmentSubkey_";
+
+/// 附件子金鑰用途
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AttachmentPart {
+    FullFile = 1,
+    Thumbnail = 2,
+    Preview = 3,
+}
+
+impl AttachmentPart {
+    fn label(self) -> &'static [u8] {
+        match self {
+            AttachmentPart::FullFile => b"full",
+            AttachmentPart::Thumbnail => b"thumbnail",
+            AttachmentPart::Preview => b"preview",
+        }
+    }
+}
+
+/// 從附件根金鑰衍生指定用途的子金鑰 (Rust 端使用)
+pub fn attachment_subkey(root_key: &[u8], part: AttachmentPart) -> Result<[u8; ATTACHMENT_KEY_SIZE], String> {
+    if root_key.len() != ATTACHMENT_KEY_SIZE {
+        return Err(format!("Attachment key must be {} bytes, got {}", ATTACHMENT_KEY_SIZE, root_key.len()));
+    }
+    let mut subkey = [0u8; ATTACHMENT_KEY_SIZE];
+    Hkdf::<Sha256>::new(None, root_key)
+        .expand(&[INFO_ATTACHMENT_SUBKEY, part.label()].concat(), &mut subkey)
+        .map_err(|e| format!("HKDF failed: {}", e))?;
+    Ok(subkey)
+}
+
+/// 從附件根金鑰衍生指定用途的子金鑰
+#[wasm_bindgen(js_name = deriveAttachmentSubkey)]
+pub fn derive_attachment_subkey(root_key: &[u8], part: AttachmentPart) -> Result<Vec<u8>, JsError> {
+    attachment_subkey(root_key, part).map(|subkey| subkey.to_vec()).map_err(|e| JsError::new(&e))
+}
+
+/// 附件 manifest
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AttachmentManifest {
+    pub file_name: String,
+    pub mime_type: String,
+    /// 明文大小 (bytes)
+    pub size: u64,
+    /// 明文摘要
+    pub digest: Vec<u8>,
+    /// 明文與密文摘要的演算法 (舊版 manifest 沒有此欄位，視為 SHA-256)
+    #[serde(default)]
+    pub digest_algorithm: HashAlgorithm,
+    /// 檔案內容加密前的壓縮 (未壓縮時省略)
+    #[serde(default, skip_serializing_if = "Compression::is_none")]
+    pub compression: Compression,
+}
+
+/// 加密後的附件
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct EncryptedAttachment {
+    ciphertext: Vec<u8>,
+    key: Vec<u8>,
+    digest: Vec<u8>,
+    digest_algorithm: HashAlgorithm,
+}
+
+#[wasm_bindgen]
+impl EncryptedAttachment {
+    /// 要上傳的密文
+    #[wasm_bindgen(getter)]
+    pub fn ciphertext(&self) -> Vec<u8> {
+        self.ciphertext.clone()
+    }
+
+    /// 附件金鑰 (敏感！只能透過端對端加密的訊息傳送)
+    #[wasm_bindgen(getter)]
+    pub fn key(&self) -> Vec<u8> {
+        self.key.clone()
+    }
+
+    /// 密文摘要
+    #[wasm_bindgen(getter)]
+    pub fn digest(&self) -> Vec<u8> {
+        self.digest.clone()
+    }
+
+    /// 摘要演算法
+    #[wasm_bindgen(getter, js_name = digestAlgorithm)]
+    pub fn digest_algorithm(&self) -> HashAlgorithm {
+        self.digest_algorithm
+    }
+
+    /// 密文長度
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> u64 {
+        self.ciphertext.len() as u64
+    }
+
+    /// 上傳完成後，以伺服器回傳的位置建立附件指標
+    pub fn pointer(&self, location: &str) -> AttachmentPointer {
+        AttachmentPointer {
+            location: location.to_string(),
+            key: self.key.clone(),
+            size: self.size(),
+            digest: self.digest.clone(),
+            digest_algorithm: self.digest_algorithm,
+        }
+    }
+}
+
+/// 附件指標 (放在訊息內傳送)
+///
+/// 承諾密文的長度與摘要，接收端在解密前即可驗證下載內容
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct AttachmentPointer {
+    location: String,
+    key: Vec<u8>,
+    /// 密文長度
+    size: u64,
+    /// 密文摘要
+    digest: Vec<u8>,
+    /// 摘要演算法 (舊版指標沒有此欄位，視為 SHA-256)
+    #[serde(default)]
+    digest_algorithm: HashAlgorithm,
+}
+
+impl AttachmentPointer {
+    /// 驗證下載的密文符合承諾的長度與摘要 (Rust 端使用)
+    pub fn verify_ciphertext(&self, ciphertext: &[u8]) -> Result<(), String> {
+        if ciphertext.len() as u64 != self.size {
+            return Err(format!(
+                "Attachment ciphertext length mismatch: expected {}, got {}",
+                self.size,
+                ciphertext.len()
+            ));
+        }
+        if !constant_time_eq(&self.digest_algorithm.digest(ciphertext), &self.digest) {
+            return Err("Attachment ciphertext digest mismatch".to_string());
+        }
+        Ok(())
+    }
+
+    /// 驗證並解密下載的密文 (Rust 端使用)
+    pub fn open(&self, ciphertext: &[u8]) -> Result<Attachment, String> {
+        self.verify_ciphertext(ciphertext)?;
+        Attachment::decrypt_and_verify_with(ciphertext, &self.key, &self.digest, self.digest_algorithm)
+    }
+}
+
+#[wasm_bindgen]
+impl AttachmentPointer {
+    /// 附件伺服器上的位置
+    #[wasm_bindgen(getter)]
+    pub fn location(&self) -> String {
+        self.location.clone()
+    }
+
+    /// 附件金鑰
+    #[wasm_bindgen(getter)]
+    pub fn key(&self) -> Vec<u8> {
+        self.key.clone()
+    }
+
+    /// 密文長度 (可在下載時用於提前中止)
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// 密文摘要
+    #[wasm_bindgen(getter)]
+    pub fn digest(&self) -> Vec<u8> {
+        self.digest.clone()
+    }
+
+    /// 摘要演算法
+    #[wasm_bindgen(getter, js_name = digestAlgorithm)]
+    pub fn digest_algorithm(&self) -> HashAlgorithm {
+        self.digest_algorithm
+    }
+
+    /// 驗證下載的密文 (不解密)
+    #[wasm_bindgen(js_name = verifyCiphertext)]
+    pub fn verify(&self, ciphertext: &[u8]) -> Result<(), JsError> {
+        self.verify_ciphertext(ciphertext).map_err(|e| JsError::new(&e))
+    }
+
+    /// 驗證並解密下載的密文
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Attachment, JsError> {
+        self.open(ciphertext).map_err(|e| JsError::new(&e))
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<AttachmentPointer, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// 附件 (明文與 manifest)
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Attachment {
+    manifest: AttachmentManifest,
+    data: Vec<u8>,
+}
+
+impl Attachment {
+    /// manifest
+    pub fn manifest(&self) -> &AttachmentManifest {
+        &self.manifest
+    }
+
+    /// 以指定的附件金鑰加密 (Rust 端使用)
+    pub fn encrypt_with_key(&self, key: &[u8], suite: CipherSuite) -> Result<EncryptedAttachment, String> {
+        let (compression, data) = self.manifest.compression.compress_if_smaller(&self.data);
+        let manifest = AttachmentManifest { compression, ..self.manifest.clone() };
+        let manifest = serde_json::to_vec(&manifest).map_err(|e| e.to_string())?;
+        let mut encryptor = StreamCipher::new_encryptor(key, suite, DEFAULT_STREAM_CHUNK_SIZE)?;
+
+        let mut ciphertext = encryptor.header();
+        ciphertext.extend(encryptor.push_bytes(&(manifest.len() as u32).to_be_bytes())?);
+        ciphertext.extend(encryptor.push_bytes(&manifest)?);
+        ciphertext.extend(encryptor.push_bytes(&data)?);
+        ciphertext.extend(encryptor.push_bytes(&padme_padding(4 + manifest.len() + data.len()))?);
+        ciphertext.extend(encryptor.finish_bytes()?);
+
+        Ok(EncryptedAttachment {
+            digest: self.manifest.digest_algorithm.digest(&ciphertext),
+            digest_algorithm: self.manifest.digest_algorithm,
+            ciphertext,
+            key: key.to_vec(),
+        })
+    }
+
+    /// 以根金鑰的子金鑰加密附件的某一部分 (Rust 端使用)
+    ///
+    /// 回傳的 `EncryptedAttachment` 帶的是子金鑰
+    pub fn encrypt_part_with_key(
+        &self,
+        root_key: &[u8],
+        part: AttachmentPart,
+        suite: CipherSuite,
+    ) -> Result<EncryptedAttachment, String> {
+        self.encrypt_with_key(&attachment_subkey(root_key, part)?, suite)
+    }
+
+    /// 驗證密文 SHA-256 摘要後解密，並驗證 manifest 中的大小與明文摘要 (Rust 端使用)
+    pub fn decrypt_and_verify(ciphertext: &[u8], key: &[u8], digest: &[u8]) -> Result<Self, String> {
+        Self::decrypt_and_verify_with(ciphertext, key, digest, HashAlgorithm::Sha256)
+    }
+
+    /// 以指定的摘要演算法驗證密文後解密 (Rust 端使用)
+    pub fn decrypt_and_verify_with(
+        ciphertext: &[u8],
+        key: &[u8],
+        digest: &[u8],
+        digest_algorithm: HashAlgorithm,
+    ) -> Result<Self, String> {
+        if !constant_time_eq(&digest_algorithm.digest(ciphertext), digest) {
+            return Err("Attachment ciphertext digest mismatch".to_string());
+        }
+        if ciphertext.len() < STREAM_HEADER_SIZE {
+            return Err("Attachment ciphertext too short".to_string());
+        }
+        let (header, body) = ciphertext.split_at(STREAM_HEADER_SIZE);
+        let mut decryptor = StreamCipher::new_decryptor(key, header)?;
+        let mut padded = decryptor.push_bytes(body)?;
+        padded.extend(decryptor.finish_bytes()?);
+        let plaintext = padme_unpad_bytes(&padded)?;
+
+        if plaintext.len() < 4 {
+            return Err("Attachment plaintext too short".to_string());
+        }
+        let manifest_len = u32::from_be_bytes([plaintext[0], plaintext[1], plaintext[2], plaintext[3]]) as usize;
+        let manifest = plaintext
+            .get(4..4 + manifest_len)
+            .ok_or_else(|| "Attachment manifest truncated".to_string())?;
+        let manifest: AttachmentManifest =
+            serde_json::from_slice(manifest).map_err(|e| format!("Invalid attachment manifest: {}", e))?;
+        let max_len = usize::try_from(manifest.size).map_err(|_| "Attachment too large".to_string())?;
+        let data = manifest.compression.decompress(&plaintext[4 + manifest_len..], max_len)?;
+
+        if data.len() as u64 != manifest.size {
+            return Err("Attachment size does not match manifest".to_string());
+        }
+        if !constant_time_eq(&manifest.digest_algorithm.digest(&data), &manifest.digest) {
+            return Err("Attachment plaintext digest mismatch".to_string());
+        }
+        Ok(Self { manifest, data })
+    }
+}
+
+#[wasm_bindgen]
+impl Attachment {
+    /// 建立附件
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: &[u8], file_name: &str, mime_type: &str) -> Self {
+        Self {
+            manifest: AttachmentManifest {
+                file_name: file_name.to_string(),
+                mime_type: mime_type.to_string(),
+                size: data.len() as u64,
+                digest: Sha256::digest(data).to_vec(),
+                digest_algorithm: HashAlgorithm::Sha256,
+                compression: Compression::None,
+            },
+            data: data.to_vec(),
+        }
+    }
+
+    /// 以新的隨機附件金鑰加密 (`suite` 未提供時為 AES-256-GCM)
+    #[wasm_bindgen(js_name = encrypt)]
+    pub fn encrypt(&self, suite: Option<CipherSuite>) -> Result<EncryptedAttachment, JsError> {
+        let mut key = [0u8; ATTACHMENT_KEY_SIZE];
+        OsRng.fill_bytes(&mut key);
+        self.encrypt_with_key(&key, suite.unwrap_or_default()).map_err(|e| JsError::new(&e))
+    }
+
+    /// 以根金鑰的子金鑰加密 (縮圖、預覽或完整檔案)
+    #[wasm_bindgen(js_name = encryptPart)]
+    pub fn encrypt_part(
+        &self,
+        root_key: &[u8],
+        part: AttachmentPart,
+        suite: Option<CipherSuite>,
+    ) -> Result<EncryptedAttachment, JsError> {
+        self.encrypt_part_with_key(root_key, part, suite.unwrap_or_default())
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 驗證並解密下載的附件 (`digest_algorithm` 未提供時為 SHA-256)
+    #[wasm_bindgen(js_name = decrypt)]
+    pub fn decrypt(
+        ciphertext: &[u8],
+        key: &[u8],
+        digest: &[u8],
+        digest_algorithm: Option<HashAlgorithm>,
+    ) -> Result<Attachment, JsError> {
+        Self::decrypt_and_verify_with(ciphertext, key, digest, digest_algorithm.unwrap_or_default())
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 檔案內容
+    #[wasm_bindgen(getter)]
+    pub fn data(&self) -> Vec<u8> {
+        self.data.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = fileName)]
+    pub fn file_name(&self) -> String {
+        self.manifest.file_name.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = mimeType)]
+    pub fn mime_type(&self) -> String {
+        self.manifest.mime_type.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> u64 {
+        self.manifest.size
+    }
+
+    /// 明文摘要
+    #[wasm_bindgen(getter)]
+    pub fn digest(&self) -> Vec<u8> {
+        self.manifest.digest.clone()
+    }
+
+    /// 摘要演算法
+    #[wasm_bindgen(getter, js_name = digestAlgorithm)]
+    pub fn digest_algorithm(&self) -> HashAlgorithm {
+        self.manifest.digest_algorithm
+    }
+
+    /// 改用指定的摘要演算法 (重新計算明文摘要，密文摘要也使用同一演算法)
+    #[wasm_bindgen(js_name = setDigestAlgorithm)]
+    pub fn set_digest_algorithm(&mut self, algorithm: HashAlgorithm) {
+        self.manifest.digest = algorithm.digest(&self.data);
+        self.manifest.digest_algorithm = algorithm;
+    }
+
+    /// 加密前的壓縮
+    #[wasm_bindgen(getter)]
+    pub fn compression(&self) -> Compression {
+        self.manifest.compression
+    }
+
+    /// 啟用加密前壓縮 (預設不壓縮；壓縮後沒有變短時仍以未壓縮加密)
+    ///
+    /// 只可用於不受攻擊者影響的檔案，例如使用者自己選擇傳送的檔案
+    #[wasm_bindgen(js_name = setCompression)]
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.manifest.compression = compression;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::hash::blake3;
+
+    #[test]
+    fn test_attachment_round_trip() {
+        let data: Vec<u8> = (0..200_000u32).map(|i| (i % 251) as u8).collect();
+        let attachment = Attachment::new(&data, "photo.jpg", "image/jpeg");
+        for suite in [CipherSuite::Aes256Gcm, CipherSuite::ChaCha20Poly1305] {
+            let encrypted = attachment.encrypt_with_key(&[3u8; 32], suite).unwrap();
+            let decrypted =
+                Attachment::decrypt_and_verify(&encrypted.ciphertext(), &encrypted.key(), &encrypted.digest())
+                    .unwrap();
+            assert_eq!(decrypted, attachment);
+            assert_eq!(decrypted.file_name(), "photo.jpg");
+            assert_eq!(decrypted.size(), 200_000);
+        }
+
+        // 密文長度為 Padmé 長度
+        let small = Attachment::new(&[1u8; 1000], "a.bin", "application/octet-stream");
+        let larger = Attachment::new(&[1u8; 1010], "a.bin", "application/octet-stream");
+        let key = [3u8; 32];
+        assert_eq!(
+            small.encrypt_with_key(&key, CipherSuite::Aes256Gcm).unwrap().size(),
+            larger.encrypt_with_key(&key, CipherSuite::Aes256Gcm).unwrap().size()
+        );
+    }
+
+    #[test]
+    fn test_blake3_digests() {
+        let mut attachment = Attachment::new(&[9u8; 5000], "video.mp4", "video/mp4");
+        attachment.set_digest_algorithm(HashAlgorithm::Blake3);
+        assert_eq!(attachment.digest(), blake3(&[9u8; 5000]));
+
+        let encrypted = attachment.encrypt_with_key(&[3u8; 32], CipherSuite::ChaCha20Poly1305).unwrap();
+        assert_eq!(encrypted.digest_algorithm(), HashAlgorithm::Blake3);
+        assert_eq!(encrypted.digest(), blake3(&encrypted.ciphertext()));
+
+        let pointer = encrypted.pointer("cdn/blob");
+        let json = serde_json::to_string(&pointer).unwrap();
+        let restored: AttachmentPointer = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.open(&encrypted.ciphertext()).unwrap(), attachment);
+
+        // 以 SHA-256 驗證 BLAKE3 摘要會失敗
+        assert!(Attachment::decrypt_and_verify(&encrypted.ciphertext(), &encrypted.key(), &encrypted.digest()).is_err());
+
+        // 沒有演算法欄位的舊指標視為 SHA-256
+        let mut legacy: serde_json::Value = serde_json::to_value(&pointer).unwrap();
+        legacy.as_object_mut().unwrap().remove("digest_algorithm");
+        let legacy: AttachmentPointer = serde_json::from_value(legacy).unwrap();
+        assert_eq!(legacy.digest_algorithm(), HashAlgorithm::Sha256);
+    }
+
+    #[test]
+    fn test_compressed_attachment() {
+        let text = "line of a text-heavy log file\n".repeat(2000);
+        let mut attachment = Attachment::new(text.as_bytes(), "log.txt", "text/plain");
+        let uncompressed = attachment.encrypt_with_key(&[3u8; 32], CipherSuite::Aes256Gcm).unwrap();
+
+        attachment.set_compression(Compression::Deflate);
+        let compressed = attachment.encrypt_with_key(&[3u8; 32], CipherSuite::Aes256Gcm).unwrap();
+        assert!(compressed.ciphertext().len() * 10 < uncompressed.ciphertext().len());
+
+        let decrypted =
+            Attachment::decrypt_and_verify(&compressed.ciphertext(), &compressed.key(), &compressed.digest()).unwrap();
+        assert_eq!(decrypted, attachment);
+        assert_eq!(decrypted.compression(), Compression::Deflate);
+
+        // 無法壓縮的內容以未壓縮加密，manifest 也記錄為未壓縮
+        let mut random = Attachment::new(&[0x5au8, 0xc3, 0x11, 0x97], "r.bin", "application/octet-stream");
+        random.set_compression(Compression::Deflate);
+        let encrypted = random.encrypt_with_key(&[3u8; 32], CipherSuite::Aes256Gcm).unwrap();
+        let decrypted =
+            Attachment::decrypt_and_verify(&encrypted.ciphertext(), &encrypted.key(), &encrypted.digest()).unwrap();
+        assert_eq!(decrypted.compression(), Compression::None);
+        assert_eq!(decrypted.data(), random.data());
+    }
+
+    #[test]
+    fn test_pointer_commits_to_ciphertext() {
+        let attachment = Attachment::new(&[7u8; 1000], "doc.pdf", "application/pdf");
+        let encrypted = attachment.encrypt_with_key(&[3u8; 32], CipherSuite::Aes256Gcm).unwrap();
+        let pointer = encrypted.pointer("cdn/abc123");
+        assert_eq!(pointer.size(), encrypted.ciphertext().len() as u64);
+
+        let restored: AttachmentPointer = serde_json::from_str(&serde_json::to_string(&pointer).unwrap()).unwrap();
+        assert_eq!(restored.open(&encrypted.ciphertext()).unwrap(), attachment);
+
+        let ciphertext = encrypted.ciphertext();
+        assert_eq!(
+            pointer.verify_ciphertext(&ciphertext[..ciphertext.len() - 1]),
+            Err(format!(
+                "Attachment ciphertext length mismatch: expected {}, got {}",
+                ciphertext.len(),
+                ciphertext.len() - 1
+            ))
+        );
+        let substituted = attachment.encrypt_with_key(&[4u8; 32], CipherSuite::Aes256Gcm).unwrap();
+        assert_eq!(
+            pointer.verify_ciphertext(&substituted.ciphertext()),
+            Err("Attachment ciphertext digest mismatch".to_string())
+        );
+    }
+
+    #[test]
+    fn test_thumbnail_subkey_cannot_open_full_file() {
+        let root_key = [8u8; 32];
+        let full = Attachment::new(&[1u8; 5000], "video.mp4", "video/mp4");
+        let thumbnail = Attachment::new(&[2u8; 100], "video.jpg", "image/jpeg");
+        let full_encrypted = full.encrypt_part_with_key(&root_key, AttachmentPart::FullFile, CipherSuite::Aes256Gcm).unwrap();
+        let thumbnail_encrypted =
+            thumbnail.encrypt_part_with_key(&root_key, AttachmentPart::Thumbnail, CipherSuite::Aes256Gcm).unwrap();
+
+        let thumbnail_key = attachment_subkey(&root_key, AttachmentPart::Thumbnail).unwrap();
+        assert_eq!(thumbnail_encrypted.key(), thumbnail_key);
+        assert_ne!(thumbnail_key, attachment_subkey(&root_key, AttachmentPart::Preview).unwrap());
+
+        let opened = thumbnail_encrypted.pointer("cdn/thumb").open(&thumbnail_encrypted.ciphertext()).unwrap();
+        assert_eq!(opened, thumbnail);
+        assert!(Attachment::decrypt_and_verify(&full_encrypted.ciphertext(), &thumbnail_key, &full_encrypted.digest()).is_err());
+        // 根金鑰本身也不能直接解密子金鑰加密的部分
+        assert!(Attachment::decrypt_and_verify(&full_encrypted.ciphertext(), &root_key, &full_encrypted.digest()).is_err());
+    }
+
+    #[test]
+    fn test_attachment_verification_failures() {
+        let attachment = Attachment::new(b"hello", "note.txt", "text/plain");
+        let encrypted = attachment.encrypt_with_key(&[3u8; 32], CipherSuite::Aes256Gcm).unwrap();
+        let (ciphertext, key, digest) = (encrypted.ciphertext(), encrypted.key(), encrypted.digest());
+
+        let mut tampered = ciphertext.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert_eq!(
+            Attachment::decrypt_and_verify(&tampered, &key, &digest),
+            Err("Attachment ciphertext digest mismatch".to_string())
+        );
+        // 伺服器同時替換密文與摘要也無法通過解密
+        let tampered_digest = Sha256::digest(&tampered).to_vec();
+        assert!(Attachment::decrypt_and_verify(&tampered, &key, &tampered_digest).is_err());
+        assert!(Attachment::decrypt_and_verify(&ciphertext, &[4u8; 32], &digest).is_err());
+
+        // manifest 與內容不符
+        let mut forged = attachment.clone();
+        forged.manifest.size = 6;
+        let encrypted = forged.encrypt_with_key(&[3u8; 32], CipherSuite::Aes256Gcm).unwrap();
+        assert_eq!(
+            Attachment::decrypt_and_verify(&encrypted.ciphertext(), &encrypted.key(), &encrypted.digest()),
+            Err("Attachment size does not match manifest".to_string())
+        );
+    }
+}
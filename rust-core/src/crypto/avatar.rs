@@ -0,0 +1,117 @@
+//! 頭像加密模組
+//!
+//! 聯絡人頭像以個人資料金鑰 (profile key，見 `KeyDerivation::profile_key`) 加密，
+//! 群組頭像以群組共享的 32 bytes 金鑰加密，伺服器只看到固定大小的密文：
+//! - 加密金鑰以 HKDF 從 profile key 衍生，與 profile key 的其他用途分開
+//! - 明文為 len (u32 BE) || 頭像 || 零填充，填充到 `AVATAR_PADDED_SIZES` 中
+//!   第一個放得下的大小，密文長度只洩漏頭像屬於哪個大小級距
+//!
+//! 密文格式：version (1) || nonce (12) || AES-256-GCM 密文 (AAD 為 version)
+//!
+//! 下載的密文可依摘要快取並在聯絡人、群組間共用，見 `storage::avatars`
+
+use rand::{rngs::OsRng, RngCore};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+use super::aead::{CipherSuite, AEAD_KEY_SIZE, AEAD_NONCE_SIZE};
+use super::kdf::hkdf_sha256;
+
+/// 目前的頭像密文版本
+pub const AVATAR_VERSION: u8 = 1;
+/// 填充後的明文大小級距 (bytes)
+pub const AVATAR_PADDED_SIZES: [usize; 3] = [16 * 1024, 64 * 1024, 256 * 1024];
+const LENGTH_PREFIX_SIZE: usize = 4;
+const INFO_AVATAR_KEY: &[u8] = b"SafeTalk_AvatarKey";
+
+fn avatar_key(profile_key: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    if profile_key.len() != AEAD_KEY_SIZE {
+        return Err(format!("Profile key must be {} bytes", AEAD_KEY_SIZE));
+    }
+    Ok(Zeroizing::new(hkdf_sha256(&[], profile_key, INFO_AVATAR_KEY, AEAD_KEY_SIZE)?))
+}
+
+/// 加密頭像 (Rust 端使用)
+pub fn seal_avatar(profile_key: &[u8], avatar: &[u8]) -> Result<Vec<u8>, String> {
+    let padded_size = AVATAR_PADDED_SIZES
+        .iter()
+        .copied()
+        .find(|size| avatar.len() + LENGTH_PREFIX_SIZE <= *size)
+        .ok_or_else(|| {
+            format!("Avatar must be at most {} bytes", AVATAR_PADDED_SIZES[AVATAR_PADDED_SIZES.len() - 1] - LENGTH_PREFIX_SIZE)
+        })?;
+    let mut plaintext = Zeroizing::new(Vec::with_capacity(padded_size));
+    plaintext.extend_from_slice(&(avatar.len() as u32).to_be_bytes());
+    plaintext.extend_from_slice(avatar);
+    plaintext.resize(padded_size, 0);
+
+    let mut nonce = [0u8; AEAD_NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    let sealed = CipherSuite::Aes256Gcm
+        .cipher(&avatar_key(profile_key)?)?
+        .seal(&nonce, &[AVATAR_VERSION], &plaintext)?;
+    Ok([&[AVATAR_VERSION], nonce.as_slice(), &sealed].concat())
+}
+
+/// 驗證並解密頭像 (Rust 端使用)
+pub fn open_avatar(profile_key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    let (version, rest) = ciphertext.split_first().ok_or("Avatar ciphertext is empty")?;
+    if *version != AVATAR_VERSION {
+        return Err(format!("Unsupported avatar version: {}", version));
+    }
+    if rest.len() < AEAD_NONCE_SIZE {
+        return Err("Avatar ciphertext too short".to_string());
+    }
+    let (nonce, sealed) = rest.split_at(AEAD_NONCE_SIZE);
+    let plaintext = Zeroizing::new(
+        CipherSuite::Aes256Gcm
+            .cipher(&avatar_key(profile_key)?)?
+            .open(nonce, &[AVATAR_VERSION], sealed)
+            .map_err(|_| "Failed to decrypt avatar (wrong profile key or tampered data)".to_string())?,
+    );
+    // 只接受標準的填充級距，避免不同用戶端產生可區分的密文長度
+    if !AVATAR_PADDED_SIZES.contains(&plaintext.len()) {
+        return Err("Invalid avatar padding".to_string());
+    }
+    let (length, rest) = plaintext.split_at(LENGTH_PREFIX_SIZE);
+    let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+    if length > rest.len() || rest[length..].iter().any(|b| *b != 0) {
+        return Err("Invalid avatar padding".to_string());
+    }
+    Ok(rest[..length].to_vec())
+}
+
+/// 以 profile key (或群組金鑰) 加密頭像，輸出固定大小級距的密文
+#[wasm_bindgen(js_name = encryptAvatar)]
+pub fn encrypt_avatar(profile_key: &[u8], avatar: &[u8]) -> Result<Vec<u8>, JsError> {
+    seal_avatar(profile_key, avatar).map_err(|e| JsError::new(&e))
+}
+
+/// 驗證並解密頭像
+#[wasm_bindgen(js_name = decryptAvatar)]
+pub fn decrypt_avatar(profile_key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, JsError> {
+    open_avatar(profile_key, ciphertext).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_avatar_padding_and_round_trip() {
+        let profile_key = [5u8; 32];
+        let small = seal_avatar(&profile_key, b"tiny png").unwrap();
+        let other = seal_avatar(&profile_key, &[1u8; 10_000]).unwrap();
+        // 同一級距的頭像密文長度相同
+        assert_eq!(small.len(), other.len());
+        assert_eq!(small.len(), 1 + AEAD_NONCE_SIZE + AVATAR_PADDED_SIZES[0] + 16);
+        assert_eq!(seal_avatar(&profile_key, &[1u8; 20_000]).unwrap().len(), 1 + AEAD_NONCE_SIZE + AVATAR_PADDED_SIZES[1] + 16);
+        assert!(seal_avatar(&profile_key, &vec![0u8; AVATAR_PADDED_SIZES[2]]).is_err());
+
+        assert_eq!(open_avatar(&profile_key, &small).unwrap(), b"tiny png");
+        assert!(open_avatar(&[6u8; 32], &small).is_err());
+        let mut tampered = small.clone();
+        tampered[20] ^= 1;
+        assert!(open_avatar(&profile_key, &tampered).is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! 加密前壓縮模組
+//!
+//! 以 DEFLATE (RFC 1951) 在加密前壓縮明文，文字為主的內容可省下大量頻寬
+//!
+//! 注意：壓縮後的長度會洩漏明文內容的資訊 (CRIME / BREACH 類攻擊)，
+//! 若明文同時包含秘密與攻擊者可影響的內容 (例如引用對方訊息、連結預覽)，
+//! 攻擊者可藉由觀察密文長度逐步猜出秘密。因此壓縮一律需要明確啟用，
+//! 只應用於不受攻擊者影響的內容 (例如使用者自己傳送的檔案)
+//!
+//! 解壓縮一律指定輸出上限，避免壓縮炸彈耗盡記憶體
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// DEFLATE 壓縮等級 (0-10，6 為 zlib 預設)
+const DEFLATE_LEVEL: u8 = 6;
+
+/// 壓縮演算法 (以單一位元組記錄在標頭中)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum Compression {
+    #[default]
+    None = 0,
+    Deflate = 1,
+}
+
+impl From<Compression> for u8 {
+    fn from(compression: Compression) -> u8 {
+        compression as u8
+    }
+}
+
+impl TryFrom<u8> for Compression {
+    type Error = String;
+
+    fn try_from(byte: u8) -> Result<Self, String> {
+        match byte {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Deflate),
+            _ => Err(format!("Unsupported compression: {}", byte)),
+        }
+    }
+}
+
+impl Compression {
+    /// 是否未壓縮
+    pub fn is_none(&self) -> bool {
+        *self == Compression::None
+    }
+
+    /// 壓縮資料
+    pub fn compress(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Compression::None => data.to_vec(),
+            Compression::Deflate => miniz_oxide::deflate::compress_to_vec(data, DEFLATE_LEVEL),
+        }
+    }
+
+    /// 解壓縮資料，deflate 輸出超過 `max_len` bytes 時回傳錯誤 (未壓縮的資料不受限制)
+    pub fn decompress(self, data: &[u8], max_len: usize) -> Result<Vec<u8>, String> {
+        match self {
+            Compression::None => Ok(data.to_vec()),
+            Compression::Deflate => miniz_oxide::inflate::decompress_to_vec_with_limit(data, max_len)
+                .map_err(|e| format!("Decompression failed: {}", e)),
+        }
+    }
+
+    /// 壓縮後較短時才壓縮，回傳實際使用的演算法與資料
+    pub fn compress_if_smaller(self, data: &[u8]) -> (Compression, Vec<u8>) {
+        if self.is_none() {
+            return (Compression::None, data.to_vec());
+        }
+        let compressed = self.compress(data);
+        if compressed.len() < data.len() {
+            (self, compressed)
+        } else {
+            (Compression::None, data.to_vec())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deflate_round_trip() {
+        let text = "SafeTalk ".repeat(1000);
+        let compressed = Compression::Deflate.compress(text.as_bytes());
+        assert!(compressed.len() < text.len() / 10);
+        assert_eq!(Compression::Deflate.decompress(&compressed, text.len()).unwrap(), text.as_bytes());
+
+        // 超過上限的輸出 (壓縮炸彈) 被拒絕
+        assert!(Compression::Deflate.decompress(&compressed, text.len() - 1).is_err());
+        assert!(Compression::Deflate.decompress(b"not deflate data", 1024).is_err());
+        assert_eq!(Compression::None.decompress(b"abc", 2).unwrap(), b"abc");
+    }
+
+    #[test]
+    fn test_compress_if_smaller() {
+        let random: Vec<u8> = (0..64u32).map(|i| (i.wrapping_mul(2654435761) >> 13) as u8).collect();
+        assert_eq!(Compression::Deflate.compress_if_smaller(&random), (Compression::None, random.clone()));
+        assert_eq!(Compression::None.compress_if_smaller(b"aaaaaaaaaaaaaaaa").0, Compression::None);
+        assert_eq!(Compression::Deflate.compress_if_smaller(&[0u8; 1000]).0, Compression::Deflate);
+
+        assert_eq!(Compression::try_from(1), Ok(Compression::Deflate));
+        assert!(Compression::try_from(2).is_err());
+    }
+}
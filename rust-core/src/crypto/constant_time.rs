@@ -0,0 +1,36 @@
+//! 常數時間比對模組
+//!
+//! MAC、指紋、摘要等秘密值的比對執行時間只與長度有關，與內容無關；
+//! JS 端不應再以 `===` 比對 base64 字串
+
+use wasm_bindgen::prelude::*;
+use subtle::ConstantTimeEq;
+
+/// 常數時間比對兩段位元組 (Rust 端使用)
+///
+/// 長度不同時直接回傳 false (長度本身不視為秘密)
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// 常數時間比對兩段位元組
+#[wasm_bindgen(js_name = constantTimeEquals)]
+pub fn constant_time_equals(a: &[u8], b: &[u8]) -> bool {
+    constant_time_eq(a, b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"", b""));
+        assert!(constant_time_eq(&[7u8; 32], &[7u8; 32]));
+        assert!(!constant_time_eq(&[7u8; 32], &[7u8; 31]));
+
+        let mut other = [7u8; 32];
+        other[31] ^= 0x80;
+        assert!(!constant_time_equals(&[7u8; 32], &other));
+    }
+}
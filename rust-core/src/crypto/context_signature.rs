@@ -0,0 +1,89 @@
+//! 網域分隔簽章模組
+//!
+//! 在訊息前加上長度前綴的用途字串再簽署，
+//! 同一把身份金鑰為預金鑰、使用者名稱、群組操作、備份等不同用途產生的簽章
+//! 無法被挪用到其他用途
+//!
+//! 簽章內容：`SafeTalk_ContextSignature_v1` || len(context) (u32 BE) || context || message
+
+use wasm_bindgen::prelude::*;
+
+use super::keys::IdentityKeyPair;
+
+const CONTEXT_SIGNATURE_PREFIX: &[u8] = b"SafeTalk_ContextSignature_v1";
+
+/// 用途字串長度上限
+pub const MAX_SIGNATURE_CONTEXT_LEN: usize = 255;
+
+/// 組出帶用途字串的簽章內容
+pub fn context_signing_payload(context: &str, message: &[u8]) -> Result<Vec<u8>, String> {
+    if context.is_empty() {
+        return Err("Signature context must not be empty".to_string());
+    }
+    if context.len() > MAX_SIGNATURE_CONTEXT_LEN {
+        return Err(format!("Signature context exceeds {} bytes", MAX_SIGNATURE_CONTEXT_LEN));
+    }
+    let mut payload = CONTEXT_SIGNATURE_PREFIX.to_vec();
+    payload.extend_from_slice(&(context.len() as u32).to_be_bytes());
+    payload.extend_from_slice(context.as_bytes());
+    payload.extend_from_slice(message);
+    Ok(payload)
+}
+
+impl IdentityKeyPair {
+    /// 以指定用途簽署 (Rust 端使用)
+    pub fn sign_for_context(&self, context: &str, message: &[u8]) -> Result<Vec<u8>, String> {
+        Ok(self.sign(&context_signing_payload(context, message)?))
+    }
+
+    /// 驗證指定用途的簽章 (Rust 端使用)
+    pub fn verify_for_context(public_key: &[u8], context: &str, message: &[u8], signature: &[u8]) -> bool {
+        context_signing_payload(context, message)
+            .is_ok_and(|payload| IdentityKeyPair::verify_signature(public_key, &payload, signature))
+    }
+}
+
+#[wasm_bindgen]
+impl IdentityKeyPair {
+    /// 以指定用途簽署訊息
+    #[wasm_bindgen(js_name = signWithContext)]
+    pub fn sign_with_context(&self, context: &str, message: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.sign_for_context(context, message).map_err(|e| JsError::new(&e))
+    }
+
+    /// 驗證指定用途的簽章 (用途不同時一律失敗)
+    #[wasm_bindgen(js_name = verifyWithContext)]
+    pub fn verify_with_context(public_key: &[u8], context: &str, message: &[u8], signature: &[u8]) -> bool {
+        Self::verify_for_context(public_key, context, message, signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_context_separates_signatures() {
+        let identity = IdentityKeyPair::new();
+        let public_key = identity.public_key_bytes();
+        let signature = identity.sign_for_context("safetalk.backup", b"manifest").unwrap();
+
+        assert!(IdentityKeyPair::verify_for_context(&public_key, "safetalk.backup", b"manifest", &signature));
+        assert!(!IdentityKeyPair::verify_for_context(&public_key, "safetalk.group", b"manifest", &signature));
+        assert!(!IdentityKeyPair::verify_for_context(&public_key, "safetalk.backup", b"other", &signature));
+        // 不帶用途的一般驗證不會通過
+        assert!(!IdentityKeyPair::verify_signature(&public_key, b"manifest", &signature));
+
+        // 長度前綴避免用途與訊息的邊界被移動
+        let shifted = identity.sign_for_context("ab", b"c").unwrap();
+        assert!(!IdentityKeyPair::verify_for_context(&public_key, "a", b"bc", &shifted));
+    }
+
+    #[test]
+    fn test_rejects_invalid_contexts() {
+        let identity = IdentityKeyPair::new();
+        assert!(identity.sign_for_context("", b"message").is_err());
+        assert!(identity.sign_for_context(&"x".repeat(256), b"message").is_err());
+        assert!(identity.sign_for_context(&"x".repeat(255), b"message").is_ok());
+    }
+}
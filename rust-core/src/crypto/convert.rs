@@ -0,0 +1,122 @@
+//! Ed25519 ↔ X25519 金鑰轉換模組
+//!
+//! 使用 Edwards 與 Montgomery 曲線之間的雙有理映射，
+//! 讓應用程式可以發布身份金鑰的 X25519 形式，或驗證對方轉換後的公鑰
+//!
+//! 公鑰轉換採嚴格驗證：拒絕非標準編碼、無法解壓縮的點與小階點
+
+use wasm_bindgen::prelude::*;
+use curve25519_dalek::scalar::clamp_integer;
+use ed25519_dalek::{SigningKey, VerifyingKey};
+
+/// Ed25519 公鑰轉 X25519 公鑰 (u 座標)
+pub fn ed25519_public_to_x25519_bytes(ed_public: &[u8]) -> Result<[u8; 32], String> {
+    let bytes: [u8; 32] = ed_public
+        .try_into()
+        .map_err(|_| "Invalid Ed25519 public key length".to_string())?;
+
+    let verifying_key =
+        VerifyingKey::from_bytes(&bytes).map_err(|_| "Invalid Ed25519 public key".to_string())?;
+    if verifying_key.to_edwards().compress().to_bytes() != bytes {
+        return Err("Non-canonical Ed25519 public key encoding".to_string());
+    }
+    if verifying_key.is_weak() {
+        return Err("Ed25519 public key has small order".to_string());
+    }
+
+    Ok(verifying_key.to_montgomery().to_bytes())
+}
+
+/// Ed25519 私鑰 (seed) 轉 X25519 私鑰
+///
+/// 取 `ed25519-dalek` 展開私鑰時使用的純量位元組 (SHA-512(seed) 前 32 bytes) 並 clamping，
+/// 對應的 X25519 公鑰等於 [`ed25519_public_to_x25519_bytes`] 的結果
+pub fn ed25519_private_to_x25519_bytes(ed_private: &[u8]) -> Result<[u8; 32], String> {
+    let seed: [u8; 32] = ed_private
+        .try_into()
+        .map_err(|_| "Invalid Ed25519 private key length".to_string())?;
+    Ok(clamp_integer(SigningKey::from_bytes(&seed).to_scalar_bytes()))
+}
+
+/// Ed25519 公鑰轉 X25519 公鑰
+///
+/// 非 32 bytes、非標準編碼、不在曲線上或小階的公鑰會被拒絕
+#[wasm_bindgen(js_name = ed25519PublicToX25519)]
+pub fn ed25519_public_to_x25519(ed_public: &[u8]) -> Result<Vec<u8>, JsError> {
+    ed25519_public_to_x25519_bytes(ed_public)
+        .map(|key| key.to_vec())
+        .map_err(|e| JsError::new(&e))
+}
+
+/// Ed25519 私鑰 (32 bytes seed) 轉 X25519 私鑰 (敏感！)
+#[wasm_bindgen(js_name = ed25519PrivateToX25519)]
+pub fn ed25519_private_to_x25519(ed_private: &[u8]) -> Result<Vec<u8>, JsError> {
+    ed25519_private_to_x25519_bytes(ed_private)
+        .map(|key| key.to_vec())
+        .map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+    use super::super::keys::{IdentityKeyPair, X25519KeyPair};
+
+    #[test]
+    fn test_conversion_known_answers() {
+        // RFC 8032 TEST 1 / TEST 2 金鑰，X25519 形式以 u = (1 + y) / (1 - y)
+        // 與 clamp(SHA-512(seed)[..32]) 獨立計算
+        let vectors = [
+            (
+                "9d61b19deffd5a60ba844af492ec2cc44449c5697b326919703bac031cae7f60",
+                "d75a980182b10ab7d54bfed3c964073a0ee172f3daa62325af021a68f707511a",
+                "307c83864f2833cb427a2ef1c00a013cfdff2768d980c0a3a520f006904de94f",
+                "d85e07ec22b0ad881537c2f44d662d1a143cf830c57aca4305d85c7a90f6b62e",
+            ),
+            (
+                "4ccd089b28ff96da9db6c346ec114e0f5b8a319f35aba624da8cf6ed4fb8a6fb",
+                "3d4017c3e843895a92b70aa74d1b7ebc9c982ccf2ec4968cc0cd55f12af4660c",
+                "68bd9ed75882d52815a97585caf4790a7f6c6b3b7f821c5e259a24b02e502e51",
+                "25c704c594b88afc00a76b69d1ed2b984d7e22550f3ed0802d04fbcd07d38d47",
+            ),
+        ];
+
+        for (ed_private, ed_public, x_private, x_public) in vectors {
+            assert_eq!(ed25519_private_to_x25519_bytes(&hex(ed_private)).unwrap().to_vec(), hex(x_private));
+            assert_eq!(ed25519_public_to_x25519_bytes(&hex(ed_public)).unwrap().to_vec(), hex(x_public));
+
+            let x_keypair = X25519KeyPair::from_bytes(&hex(x_private)).unwrap();
+            assert_eq!(x_keypair.public_key_bytes(), hex(x_public));
+        }
+    }
+
+    #[test]
+    fn test_converted_keys_match() {
+        let identity = IdentityKeyPair::new();
+        let x_private = ed25519_private_to_x25519_bytes(identity.private_key_bytes().expose()).unwrap();
+        let x_public = ed25519_public_to_x25519_bytes(&identity.public_key_bytes()).unwrap();
+
+        let x_keypair = X25519KeyPair::from_bytes(&x_private).unwrap();
+        assert_eq!(x_keypair.public_key_bytes(), x_public.to_vec());
+    }
+
+    #[test]
+    fn test_public_conversion_rejects_weak_keys() {
+        // 單位元素 (小階點)
+        let mut identity_point = [0u8; 32];
+        identity_point[0] = 1;
+        assert_eq!(
+            ed25519_public_to_x25519_bytes(&identity_point),
+            Err("Ed25519 public key has small order".to_string())
+        );
+
+        // y = p + 1 是 y = 1 的非標準編碼
+        let mut non_canonical = [0xffu8; 32];
+        non_canonical[0] = 0xee;
+        non_canonical[31] = 0x7f;
+        assert!(ed25519_public_to_x25519_bytes(&non_canonical).is_err());
+
+        assert!(ed25519_public_to_x25519_bytes(&[1u8; 31]).is_err());
+        assert!(ed25519_private_to_x25519_bytes(&[1u8; 33]).is_err());
+    }
+}
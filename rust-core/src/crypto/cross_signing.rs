@@ -0,0 +1,209 @@
+//! 裝置交叉簽署模組
+//!
+//! 帳號的身份金鑰為每台裝置的金鑰簽發裝置憑證。
+//! 對方收到新裝置時，只要憑證由已信任的身份金鑰簽署，
+//! 即可確認該裝置屬於此聯絡人，而不會被當成身份變更
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::keys::IdentityKeyPair;
+use crate::storage::{IdentityStatus, IdentityTrustStore};
+
+const DEVICE_CERT_CONTEXT: &[u8] = b"SafeTalk_DeviceCertificate_v1";
+
+/// 裝置憑證
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct DeviceCertificate {
+    /// 簽發者 (帳號) 身份公鑰
+    identity_key: Vec<u8>,
+    /// 裝置 ID
+    device_id: u32,
+    /// 裝置的 Ed25519 簽章公鑰
+    device_signing_key: Vec<u8>,
+    /// 裝置的 X25519 公鑰
+    device_dh_key: Vec<u8>,
+    /// 簽發時間 (Unix 毫秒)
+    issued_at: u64,
+    /// 身份金鑰對以上欄位的簽章
+    signature: Vec<u8>,
+}
+
+impl DeviceCertificate {
+    /// 簽章內容：context 後接長度前綴 (u32 BE) 的各欄位
+    fn signing_payload(
+        identity_key: &[u8],
+        device_id: u32,
+        device_signing_key: &[u8],
+        device_dh_key: &[u8],
+        issued_at: u64,
+    ) -> Vec<u8> {
+        let mut payload = DEVICE_CERT_CONTEXT.to_vec();
+        for field in [
+            identity_key,
+            &device_id.to_be_bytes(),
+            device_signing_key,
+            device_dh_key,
+            &issued_at.to_be_bytes(),
+        ] {
+            payload.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            payload.extend_from_slice(field);
+        }
+        payload
+    }
+
+    /// 驗證憑證是否由指定身份公鑰簽發
+    pub fn verify_issuer(&self, identity_key: &[u8]) -> Result<(), String> {
+        if self.identity_key != identity_key {
+            return Err("Certificate was issued by a different identity".to_string());
+        }
+        if self.device_signing_key.len() != 32 || self.device_dh_key.len() != 32 {
+            return Err("Device keys must be 32 bytes".to_string());
+        }
+        let payload = Self::signing_payload(
+            &self.identity_key,
+            self.device_id,
+            &self.device_signing_key,
+            &self.device_dh_key,
+            self.issued_at,
+        );
+        if IdentityKeyPair::verify_signature(&self.identity_key, &payload, &self.signature) {
+            Ok(())
+        } else {
+            Err("Invalid device certificate signature".to_string())
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl DeviceCertificate {
+    /// 以帳號身份金鑰為裝置簽發憑證
+    #[wasm_bindgen(js_name = issue)]
+    pub fn issue(
+        identity: &IdentityKeyPair,
+        device_id: u32,
+        device_signing_key: &[u8],
+        device_dh_key: &[u8],
+        issued_at: u64,
+    ) -> DeviceCertificate {
+        let identity_key = identity.public_key_bytes();
+        let payload = Self::signing_payload(
+            &identity_key,
+            device_id,
+            device_signing_key,
+            device_dh_key,
+            issued_at,
+        );
+        Self {
+            signature: identity.sign(&payload),
+            identity_key,
+            device_id,
+            device_signing_key: device_signing_key.to_vec(),
+            device_dh_key: device_dh_key.to_vec(),
+            issued_at,
+        }
+    }
+
+    /// 驗證憑證是否由指定身份公鑰簽發
+    #[wasm_bindgen(js_name = verify)]
+    pub fn verify(&self, identity_key: &[u8]) -> bool {
+        self.verify_issuer(identity_key).is_ok()
+    }
+
+    #[wasm_bindgen(getter, js_name = identityKey)]
+    pub fn identity_key(&self) -> Vec<u8> {
+        self.identity_key.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = deviceId)]
+    pub fn device_id(&self) -> u32 {
+        self.device_id
+    }
+
+    #[wasm_bindgen(getter, js_name = deviceSigningKey)]
+    pub fn device_signing_key(&self) -> Vec<u8> {
+        self.device_signing_key.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = deviceDhKey)]
+    pub fn device_dh_key(&self) -> Vec<u8> {
+        self.device_dh_key.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = issuedAt)]
+    pub fn issued_at(&self) -> u64 {
+        self.issued_at
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<DeviceCertificate, JsError> {
+        bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[wasm_bindgen]
+impl IdentityTrustStore {
+    /// 檢查聯絡人的新裝置
+    ///
+    /// 憑證必須有效，並由已記錄的身份公鑰簽發才回傳 `Trusted`；
+    /// 未記錄過的聯絡人回傳 `NewIdentity`，其他情況視為 `Changed`
+    #[wasm_bindgen(js_name = checkDeviceCertificate)]
+    pub fn check_device_certificate(&self, contact_id: &str, certificate: &DeviceCertificate) -> IdentityStatus {
+        if certificate.verify_issuer(&certificate.identity_key).is_err() {
+            return IdentityStatus::Changed;
+        }
+        self.check_identity(contact_id, &certificate.identity_key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::X25519KeyPair;
+
+    #[test]
+    fn test_device_certificate() {
+        let identity = IdentityKeyPair::new();
+        let device_signing = IdentityKeyPair::new();
+        let device_dh = X25519KeyPair::new();
+
+        let cert = DeviceCertificate::issue(
+            &identity,
+            2,
+            &device_signing.public_key_bytes(),
+            &device_dh.public_key_bytes(),
+            1000,
+        );
+        assert!(cert.verify(&identity.public_key_bytes()));
+        assert!(!cert.verify(&IdentityKeyPair::new().public_key_bytes()));
+
+        // 竄改裝置 ID
+        let mut forged = cert.clone();
+        forged.device_id = 3;
+        assert!(!forged.verify(&identity.public_key_bytes()));
+    }
+
+    #[test]
+    fn test_trust_store_accepts_certified_device() {
+        let identity = IdentityKeyPair::new();
+        let mut store = IdentityTrustStore::new();
+        store.save_identity("bob", &identity.public_key_bytes(), 1);
+
+        let cert = DeviceCertificate::issue(&identity, 2, &[1u8; 32], &[2u8; 32], 1000);
+        assert_eq!(store.check_device_certificate("bob", &cert), IdentityStatus::Trusted);
+        assert_eq!(store.check_device_certificate("carol", &cert), IdentityStatus::NewIdentity);
+
+        let other = DeviceCertificate::issue(&IdentityKeyPair::new(), 2, &[1u8; 32], &[2u8; 32], 1000);
+        assert_eq!(store.check_device_certificate("bob", &other), IdentityStatus::Changed);
+
+        let mut forged = cert;
+        forged.issued_at = 2000;
+        assert_eq!(store.check_device_certificate("bob", &forged), IdentityStatus::Changed);
+    }
+}
@@ -0,0 +1,246 @@
+//! 階層式金鑰衍生模組
+//!
+//! 從單一主種子 (master seed) 以 HKDF 路徑衍生各用途的子金鑰，
+//! 例如 `identity`、`storage`、`device/3`。每一層以上一層的鏈金鑰為 IKM，
+//! 因此可把某個子樹 (例如 `device/3`) 交給裝置而不洩漏其他分支
+
+use wasm_bindgen::prelude::*;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use super::keys::{IdentityKeyPair, X25519KeyPair};
+
+const DERIVATION_SALT: &[u8] = b"SafeTalk_KeyDerivation_v1";
+const INFO_CHILD: &[u8] = b"SafeTalk_KeyDerivation_Child";
+const INFO_KEY: &[u8] = b"SafeTalk_KeyDerivation_Key";
+
+/// 身份金鑰路徑
+pub const PATH_IDENTITY: &str = "identity";
+/// 主儲存金鑰路徑
+pub const PATH_STORAGE: &str = "storage";
+/// 個人資料金鑰路徑
+pub const PATH_PROFILE: &str = "profile";
+/// 備份金鑰路徑
+pub const PATH_BACKUP: &str = "backup";
+/// 裝置子樹前綴 (`device/<id>`)
+pub const PATH_DEVICE: &str = "device";
+/// 依序號衍生的 X25519 金鑰子樹前綴 (`x25519/<index>`)
+pub const PATH_X25519: &str = "x25519";
+
+/// 階層式金鑰衍生
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct KeyDerivation {
+    /// 此節點的鏈金鑰
+    chain_key: [u8; 32],
+}
+
+impl KeyDerivation {
+    /// 從已驗證長度的鏈金鑰建立
+    fn from_chain_key(chain_key: [u8; 32]) -> Self {
+        Self { chain_key }
+    }
+
+    /// 從主種子建立 (Rust 端使用)
+    pub fn from_seed(master_seed: &[u8]) -> Result<Self, String> {
+        if master_seed.len() < 32 {
+            return Err("Master seed must be at least 32 bytes".to_string());
+        }
+        let hkdf = Hkdf::<Sha256>::new(Some(DERIVATION_SALT), master_seed);
+        let mut chain_key = [0u8; 32];
+        hkdf.expand(INFO_CHILD, &mut chain_key)
+            .map_err(|e| format!("HKDF failed: {}", e))?;
+        Ok(Self::from_chain_key(chain_key))
+    }
+
+    /// 以 HKDF 從鏈金鑰展開 32 bytes，info 綁定用途與標籤
+    fn expand(&self, info: &[u8], label: &[u8]) -> [u8; 32] {
+        let hkdf = Hkdf::<Sha256>::new(Some(DERIVATION_SALT), &self.chain_key);
+        let mut full_info = info.to_vec();
+        full_info.extend_from_slice(&(label.len() as u32).to_be_bytes());
+        full_info.extend_from_slice(label);
+        let mut output = [0u8; 32];
+        hkdf.expand(&full_info, &mut output)
+            .expect("32 bytes is a valid HKDF output length");
+        output
+    }
+
+    /// 衍生下一層節點
+    pub fn child(&self, label: &str) -> Self {
+        Self::from_chain_key(self.expand(INFO_CHILD, label.as_bytes()))
+    }
+
+    /// 依路徑衍生節點 (以 `/` 分隔)
+    pub fn node(&self, path: &str) -> Result<Self, String> {
+        let mut node = self.clone();
+        for label in path.split('/') {
+            if label.is_empty() {
+                return Err(format!("Invalid derivation path: {:?}", path));
+            }
+            node = node.child(label);
+        }
+        Ok(node)
+    }
+
+    /// 依路徑衍生 32 bytes 子金鑰
+    pub fn key_at(&self, path: &str) -> Result<[u8; 32], String> {
+        Ok(self.node(path)?.expand(INFO_KEY, &[]))
+    }
+}
+
+#[wasm_bindgen]
+impl KeyDerivation {
+    /// 從主種子建立 (至少 32 bytes)
+    #[wasm_bindgen(constructor)]
+    pub fn new(master_seed: &[u8]) -> Result<KeyDerivation, JsError> {
+        Self::from_seed(master_seed).map_err(|e| JsError::new(&e))
+    }
+
+    /// 依路徑衍生 32 bytes 子金鑰
+    #[wasm_bindgen(js_name = deriveKey)]
+    pub fn derive_key(&self, path: &str) -> Result<Vec<u8>, JsError> {
+        self.key_at(path).map(|k| k.to_vec()).map_err(|e| JsError::new(&e))
+    }
+
+    /// 依路徑取得子樹 (可交給其他元件繼續衍生)
+    #[wasm_bindgen(js_name = deriveNode)]
+    pub fn derive_node(&self, path: &str) -> Result<KeyDerivation, JsError> {
+        self.node(path).map_err(|e| JsError::new(&e))
+    }
+
+    /// 身份金鑰對
+    #[wasm_bindgen(js_name = identityKeyPair)]
+    pub fn identity_key_pair(&self) -> Result<IdentityKeyPair, JsError> {
+        IdentityKeyPair::from_bytes(&self.key_at(PATH_IDENTITY).map_err(|e| JsError::new(&e))?)
+    }
+
+    /// 主儲存金鑰
+    #[wasm_bindgen(js_name = storageKey)]
+    pub fn storage_key(&self) -> Vec<u8> {
+        self.child(PATH_STORAGE).expand(INFO_KEY, &[]).to_vec()
+    }
+
+    /// 個人資料金鑰
+    #[wasm_bindgen(js_name = profileKey)]
+    pub fn profile_key(&self) -> Vec<u8> {
+        self.child(PATH_PROFILE).expand(INFO_KEY, &[]).to_vec()
+    }
+
+    /// 備份金鑰
+    #[wasm_bindgen(js_name = backupKey)]
+    pub fn backup_key(&self) -> Vec<u8> {
+        self.child(PATH_BACKUP).expand(INFO_KEY, &[]).to_vec()
+    }
+
+    /// 裝置子樹 (`device/<id>`)
+    #[wasm_bindgen(js_name = deviceNode)]
+    pub fn device_node(&self, device_id: u32) -> KeyDerivation {
+        self.child(PATH_DEVICE).child(&device_id.to_string())
+    }
+
+    /// 裝置 X25519 金鑰對 (`device/<id>/x25519`)
+    #[wasm_bindgen(js_name = deviceKeyPair)]
+    pub fn device_key_pair(&self, device_id: u32) -> Result<X25519KeyPair, JsError> {
+        let key = self.device_node(device_id).key_at("x25519").map_err(|e| JsError::new(&e))?;
+        X25519KeyPair::from_bytes(&key)
+    }
+}
+
+impl IdentityKeyPair {
+    /// 從種子確定性地產生身份金鑰 (Rust 端使用)
+    ///
+    /// 等同 `KeyDerivation::new(seed).identityKeyPair()`，與助記詞復原得到相同的身份
+    pub fn derive_from_seed(seed: &[u8]) -> Result<Self, String> {
+        let key = KeyDerivation::from_seed(seed)?.key_at(PATH_IDENTITY)?;
+        Ok(Self::from_signing_key_bytes(&key))
+    }
+}
+
+#[wasm_bindgen]
+impl IdentityKeyPair {
+    /// 從種子 (至少 32 bytes) 確定性地產生身份金鑰
+    #[wasm_bindgen(js_name = fromSeed)]
+    pub fn from_seed(seed: &[u8]) -> Result<IdentityKeyPair, JsError> {
+        Self::derive_from_seed(seed).map_err(|e| JsError::new(&e))
+    }
+}
+
+impl X25519KeyPair {
+    /// 從種子與序號確定性地產生 X25519 金鑰 (`x25519/<index>`，Rust 端使用)
+    pub fn derive_from_seed(seed: &[u8], index: u32) -> Result<Self, String> {
+        let path = format!("{}/{}", PATH_X25519, index);
+        Ok(Self::from_secret_bytes(KeyDerivation::from_seed(seed)?.key_at(&path)?))
+    }
+}
+
+#[wasm_bindgen]
+impl X25519KeyPair {
+    /// 從種子 (至少 32 bytes) 與序號確定性地產生 X25519 金鑰
+    #[wasm_bindgen(js_name = fromSeed)]
+    pub fn from_seed(seed: &[u8], index: u32) -> Result<X25519KeyPair, JsError> {
+        Self::derive_from_seed(seed, index).map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+
+    fn derivation() -> KeyDerivation {
+        KeyDerivation::from_seed(&[0x11u8; 32]).unwrap()
+    }
+
+    #[test]
+    fn test_paths_are_deterministic_and_independent() {
+        let kd = derivation();
+        assert_eq!(kd.key_at("identity").unwrap(), derivation().key_at("identity").unwrap());
+        assert_eq!(kd.storage_key(), kd.key_at(PATH_STORAGE).unwrap().to_vec());
+
+        let keys = [kd.storage_key(), kd.profile_key(), kd.backup_key()];
+        assert_ne!(keys[0], keys[1]);
+        assert_ne!(keys[1], keys[2]);
+
+        // 節點金鑰不等於同路徑的子金鑰
+        assert_ne!(kd.child("storage").chain_key.to_vec(), kd.storage_key());
+        assert!(kd.key_at("device//1").is_err());
+        assert!(kd.key_at("").is_err());
+        assert!(KeyDerivation::from_seed(&[0u8; 16]).is_err());
+    }
+
+    #[test]
+    fn test_subtree_matches_full_path() {
+        let kd = derivation();
+        let device = kd.device_node(3);
+        assert_eq!(device.key_at("x25519").unwrap(), kd.key_at("device/3/x25519").unwrap());
+        assert_ne!(kd.device_node(3).chain_key, kd.device_node(4).chain_key);
+        assert_eq!(
+            kd.device_key_pair(3).unwrap().public_key_bytes(),
+            kd.device_key_pair(3).unwrap().public_key_bytes()
+        );
+    }
+
+    #[test]
+    fn test_keys_from_seed() {
+        // 以 Python (cryptography) 依相同 HKDF 路徑獨立計算
+        let seed: Vec<u8> = (0u8..32).collect();
+        let identity = IdentityKeyPair::derive_from_seed(&seed).unwrap();
+        assert_eq!(
+            identity.public_key_bytes(),
+            hex("87b9bdc9175dba6418cafe9ce2307bc9c76b588c82ea29a25f9195b8986fb90a")
+        );
+        assert_eq!(
+            X25519KeyPair::derive_from_seed(&seed, 0).unwrap().public_key_bytes(),
+            hex("a56afa60e0de5a8fae7248bb213ed4ee04abbd272a2c3817da51b7ad118db779")
+        );
+        assert_eq!(
+            X25519KeyPair::derive_from_seed(&seed, 1).unwrap().public_key_bytes(),
+            hex("b78a8f2d365abbc10277e4b5676bffe3a00c9cff5d0f1086e7476770ca58da59")
+        );
+
+        // 與階層式衍生的身份路徑一致
+        let from_tree = KeyDerivation::from_seed(&seed).unwrap().identity_key_pair().unwrap();
+        assert_eq!(from_tree.public_key_bytes(), identity.public_key_bytes());
+        assert!(IdentityKeyPair::derive_from_seed(&[0u8; 31]).is_err());
+    }
+}
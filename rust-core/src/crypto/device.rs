@@ -0,0 +1,66 @@
+//! 註冊 ID 與裝置 ID 模組
+//!
+//! 與 Signal 相同：每次安裝產生一個隨機 14-bit 註冊 ID，
+//! 同一身份的每台裝置各自分配裝置 ID (主裝置固定為 1)，
+//! 讓伺服器與對方可以區分同一身份的不同裝置
+
+use wasm_bindgen::prelude::*;
+use rand::{rngs::OsRng, Rng};
+
+/// 註冊 ID 上限 (14 bits，與 Signal 相同保留 16381-16383)
+pub const MAX_REGISTRATION_ID: u32 = 16380;
+
+/// 主裝置的裝置 ID
+pub const PRIMARY_DEVICE_ID: u32 = 1;
+
+/// 裝置 ID 上限
+pub const MAX_DEVICE_ID: u32 = 127;
+
+/// 產生隨機註冊 ID (1..=16380)
+#[wasm_bindgen(js_name = generateRegistrationId)]
+pub fn generate_registration_id() -> u32 {
+    OsRng.gen_range(1..=MAX_REGISTRATION_ID)
+}
+
+/// 註冊 ID 是否在有效範圍內
+#[wasm_bindgen(js_name = isValidRegistrationId)]
+pub fn is_valid_registration_id(registration_id: u32) -> bool {
+    (1..=MAX_REGISTRATION_ID).contains(&registration_id)
+}
+
+/// 為新連結的裝置分配裝置 ID (Rust 端使用)
+///
+/// 回傳未被使用的最小 ID，主裝置 ID 保留不分配
+pub fn next_device_id(existing: &[u32]) -> Result<u32, String> {
+    (PRIMARY_DEVICE_ID + 1..=MAX_DEVICE_ID)
+        .find(|id| !existing.contains(id))
+        .ok_or_else(|| format!("All {} device slots are in use", MAX_DEVICE_ID - 1))
+}
+
+/// 為新連結的裝置分配裝置 ID
+#[wasm_bindgen(js_name = allocateDeviceId)]
+pub fn allocate_device_id(existing: Vec<u32>) -> Result<u32, JsError> {
+    next_device_id(&existing).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registration_id_range() {
+        for _ in 0..1000 {
+            assert!(is_valid_registration_id(generate_registration_id()));
+        }
+        assert!(!is_valid_registration_id(0));
+        assert!(!is_valid_registration_id(MAX_REGISTRATION_ID + 1));
+    }
+
+    #[test]
+    fn test_next_device_id() {
+        assert_eq!(next_device_id(&[]), Ok(2));
+        assert_eq!(next_device_id(&[1, 2, 3, 5]), Ok(4));
+        let all: Vec<u32> = (1..=MAX_DEVICE_ID).collect();
+        assert!(next_device_id(&all).is_err());
+    }
+}
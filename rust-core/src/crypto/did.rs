@@ -0,0 +1,230 @@
+//! DID (did:key) 模組
+//!
+//! 依 did:key 規範將 Ed25519 身份公鑰編碼為
+//! `did:key:z` + base58btc(multicodec `ed25519-pub` || 公鑰)，
+//! 並產生對應的 DID 文件 (含轉換後的 X25519 keyAgreement 金鑰)，
+//! 讓 SafeTalk 身份可以直接接上去中心化身份工具
+
+use wasm_bindgen::prelude::*;
+use serde_json::json;
+
+use super::convert::ed25519_public_to_x25519_bytes;
+use super::keys::IdentityKeyPair;
+
+/// DID 前綴
+const DID_KEY_PREFIX: &str = "did:key:";
+/// multibase base58btc 前綴
+const MULTIBASE_BASE58BTC: char = 'z';
+/// multicodec `ed25519-pub` (0xed, varint)
+const MULTICODEC_ED25519_PUB: [u8; 2] = [0xed, 0x01];
+/// multicodec `x25519-pub` (0xec, varint)
+const MULTICODEC_X25519_PUB: [u8; 2] = [0xec, 0x01];
+
+/// base58btc 字母表 (Bitcoin)
+const BASE58_ALPHABET: &[u8; 58] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+/// base58btc 編碼
+fn base58_encode(data: &[u8]) -> String {
+    let zeros = data.iter().take_while(|b| **b == 0).count();
+    // 以 58 進位逐位累加 (小端)
+    let mut digits: Vec<u8> = Vec::new();
+    for byte in &data[zeros..] {
+        let mut carry = *byte as u32;
+        for digit in digits.iter_mut() {
+            carry += (*digit as u32) << 8;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+        }
+        while carry > 0 {
+            digits.push((carry % 58) as u8);
+            carry /= 58;
+        }
+    }
+    std::iter::repeat_n(b'1', zeros)
+        .chain(digits.iter().rev().map(|d| BASE58_ALPHABET[*d as usize]))
+        .map(char::from)
+        .collect()
+}
+
+/// base58btc 解碼
+fn base58_decode(encoded: &str) -> Result<Vec<u8>, String> {
+    let zeros = encoded.bytes().take_while(|c| *c == b'1').count();
+    let mut bytes: Vec<u8> = Vec::new();
+    for c in encoded.bytes().skip(zeros) {
+        let mut carry = BASE58_ALPHABET
+            .iter()
+            .position(|a| *a == c)
+            .ok_or_else(|| format!("Invalid base58 character '{}'", c as char))? as u32;
+        for byte in bytes.iter_mut() {
+            carry += (*byte as u32) * 58;
+            *byte = carry as u8;
+            carry >>= 8;
+        }
+        while carry > 0 {
+            bytes.push(carry as u8);
+            carry >>= 8;
+        }
+    }
+    let mut out = vec![0u8; zeros];
+    out.extend(bytes.iter().rev());
+    Ok(out)
+}
+
+/// 以 multicodec 前綴 + base58btc multibase 編碼公鑰
+fn multibase_key(codec: [u8; 2], key: &[u8]) -> String {
+    let mut data = codec.to_vec();
+    data.extend_from_slice(key);
+    format!("{}{}", MULTIBASE_BASE58BTC, base58_encode(&data))
+}
+
+/// Ed25519 身份公鑰轉為 did:key
+pub fn did_key_from_ed25519(identity_key: &[u8]) -> Result<String, String> {
+    // 與 keyAgreement 使用相同的嚴格驗證
+    ed25519_public_to_x25519_bytes(identity_key)?;
+    Ok(format!("{}{}", DID_KEY_PREFIX, multibase_key(MULTICODEC_ED25519_PUB, identity_key)))
+}
+
+/// 從 did:key (可帶 `#fragment`) 取回 Ed25519 身份公鑰
+pub fn ed25519_from_did_key(did: &str) -> Result<[u8; 32], String> {
+    let identifier = did
+        .split('#')
+        .next()
+        .and_then(|did| did.strip_prefix(DID_KEY_PREFIX))
+        .ok_or_else(|| "Not a did:key identifier".to_string())?;
+    let encoded = identifier
+        .strip_prefix(MULTIBASE_BASE58BTC)
+        .ok_or_else(|| "did:key must use base58btc multibase".to_string())?;
+    let data = base58_decode(encoded)?;
+    let key = data
+        .strip_prefix(&MULTICODEC_ED25519_PUB[..])
+        .ok_or_else(|| "did:key is not an Ed25519 public key".to_string())?;
+    let key: [u8; 32] = key
+        .try_into()
+        .map_err(|_| "Invalid Ed25519 public key length".to_string())?;
+    ed25519_public_to_x25519_bytes(&key)?;
+    Ok(key)
+}
+
+/// 產生 did:key 的 DID 文件
+pub fn did_document_for(identity_key: &[u8]) -> Result<serde_json::Value, String> {
+    let did = did_key_from_ed25519(identity_key)?;
+    let signing_id = format!("{}#{}", did, multibase_key(MULTICODEC_ED25519_PUB, identity_key));
+    let x25519_key = ed25519_public_to_x25519_bytes(identity_key)?;
+    let agreement_key = multibase_key(MULTICODEC_X25519_PUB, &x25519_key);
+    let agreement_id = format!("{}#{}", did, agreement_key);
+
+    Ok(json!({
+        "@context": [
+            "https://www.w3.org/ns/did/v1",
+            "https://w3id.org/security/suites/ed25519-2020/v1",
+            "https://w3id.org/security/suites/x25519-2020/v1",
+        ],
+        "id": did,
+        "verificationMethod": [{
+            "id": signing_id,
+            "type": "Ed25519VerificationKey2020",
+            "controller": did,
+            "publicKeyMultibase": multibase_key(MULTICODEC_ED25519_PUB, identity_key),
+        }],
+        "authentication": [signing_id],
+        "assertionMethod": [signing_id],
+        "capabilityDelegation": [signing_id],
+        "capabilityInvocation": [signing_id],
+        "keyAgreement": [{
+            "id": agreement_id,
+            "type": "X25519KeyAgreementKey2020",
+            "controller": did,
+            "publicKeyMultibase": agreement_key,
+        }],
+    }))
+}
+
+#[wasm_bindgen]
+impl IdentityKeyPair {
+    /// 身份公鑰的 did:key
+    #[wasm_bindgen(js_name = toDidKey)]
+    pub fn to_did_key(&self) -> Result<String, JsError> {
+        did_key_from_ed25519(&self.public_key_bytes()).map_err(|e| JsError::new(&e))
+    }
+
+    /// 身份公鑰的 DID 文件 (JSON)
+    #[wasm_bindgen(js_name = didDocument)]
+    pub fn did_document(&self) -> Result<String, JsError> {
+        did_document_json(&self.public_key_bytes())
+    }
+}
+
+/// Ed25519 公鑰轉為 did:key
+#[wasm_bindgen(js_name = didKeyFromPublicKey)]
+pub fn did_key_from_public_key(identity_key: &[u8]) -> Result<String, JsError> {
+    did_key_from_ed25519(identity_key).map_err(|e| JsError::new(&e))
+}
+
+/// 解析 did:key，回傳 Ed25519 公鑰
+#[wasm_bindgen(js_name = resolveDidKey)]
+pub fn resolve_did_key(did: &str) -> Result<Vec<u8>, JsError> {
+    ed25519_from_did_key(did)
+        .map(|key| key.to_vec())
+        .map_err(|e| JsError::new(&e))
+}
+
+/// Ed25519 公鑰的 DID 文件 (JSON)
+#[wasm_bindgen(js_name = didDocumentFromPublicKey)]
+pub fn did_document_json(identity_key: &[u8]) -> Result<String, JsError> {
+    let document = did_document_for(identity_key).map_err(|e| JsError::new(&e))?;
+    serde_json::to_string(&document).map_err(|e| JsError::new(&e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_did_key_spec_example() {
+        // did:key 規範範例文件
+        let did = "did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+        let key = ed25519_from_did_key(did).unwrap();
+        assert_eq!(
+            key.to_vec(),
+            hex("2e6fcce36701dc791488e0d0b1745cc1e33a4c1c9fcc41c63bd343dbbe0970e6")
+        );
+        assert_eq!(did_key_from_ed25519(&key).unwrap(), did);
+
+        let document = did_document_for(&key).unwrap();
+        assert_eq!(document["id"], did);
+        assert_eq!(
+            document["verificationMethod"][0]["id"],
+            format!("{}#z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK", did)
+        );
+        assert_eq!(
+            document["keyAgreement"][0]["publicKeyMultibase"],
+            "z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p"
+        );
+    }
+
+    #[test]
+    fn test_did_key_round_trip() {
+        let identity = IdentityKeyPair::new();
+        let did = identity.to_did_key().unwrap();
+        assert!(did.starts_with("did:key:z6Mk"));
+        assert_eq!(ed25519_from_did_key(&did).unwrap().to_vec(), identity.public_key_bytes());
+        assert_eq!(
+            ed25519_from_did_key(&format!("{}#key-1", did)).unwrap().to_vec(),
+            identity.public_key_bytes()
+        );
+
+        let document: serde_json::Value = serde_json::from_str(&identity.did_document().unwrap()).unwrap();
+        assert_eq!(document["authentication"][0], document["verificationMethod"][0]["id"]);
+
+        assert_eq!(base58_decode(&base58_encode(&[0, 0, 1, 2, 255])).unwrap(), vec![0, 0, 1, 2, 255]);
+    }
+
+    #[test]
+    fn test_rejects_other_did_methods() {
+        assert!(ed25519_from_did_key("did:web:example.com").is_err());
+        // X25519 keyAgreement 金鑰不是身份金鑰
+        assert!(ed25519_from_did_key("did:key:z6LSj72tK8brWgZja8NLRwPigth2T9QRiG1uH9oKZuKjdh9p").is_err());
+        assert!(ed25519_from_did_key("did:key:z0OIl").is_err());
+    }
+}
@@ -0,0 +1,190 @@
+//! 聯絡人探索模組
+//!
+//! 不上傳原始電話號碼或 email：每個識別碼先正規化，
+//! 再以伺服器公告的 salt 計算 SHA-256 並截斷為固定長度，
+//! 以批次方式查詢，最後把伺服器回應的雜湊對應回本機聯絡人
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use sha2::{Digest, Sha256};
+
+const DISCOVERY_CONTEXT: &[u8] = b"SafeTalk_ContactDiscovery_v1";
+
+/// 截斷後的雜湊長度
+pub const DISCOVERY_HASH_LEN: usize = 10;
+
+/// 單次查詢的最大識別碼數量
+pub const MAX_DISCOVERY_BATCH: usize = 500;
+
+/// 正規化識別碼
+///
+/// email 轉為小寫並去除前後空白；電話號碼只保留數字與開頭的 `+`
+pub fn normalize_identifier(identifier: &str) -> String {
+    let trimmed = identifier.trim();
+    if trimmed.contains('@') {
+        return trimmed.to_lowercase();
+    }
+    let mut normalized = String::new();
+    for (i, c) in trimmed.chars().enumerate() {
+        if c.is_ascii_digit() || (i == 0 && c == '+') {
+            normalized.push(c);
+        }
+    }
+    normalized
+}
+
+/// 計算識別碼的截斷雜湊
+pub fn discovery_hash(salt: &[u8], identifier: &str) -> [u8; DISCOVERY_HASH_LEN] {
+    let normalized = normalize_identifier(identifier);
+    let mut hasher = Sha256::new();
+    hasher.update(DISCOVERY_CONTEXT);
+    hasher.update((salt.len() as u32).to_be_bytes());
+    hasher.update(salt);
+    hasher.update(normalized.as_bytes());
+    let digest = hasher.finalize();
+    let mut hash = [0u8; DISCOVERY_HASH_LEN];
+    hash.copy_from_slice(&digest[..DISCOVERY_HASH_LEN]);
+    hash
+}
+
+/// 聯絡人探索查詢
+///
+/// 保存雜湊與本機識別碼的對應，用來比對伺服器回應
+#[wasm_bindgen]
+pub struct DiscoveryQuery {
+    salt: Vec<u8>,
+    /// 雜湊 -> 原始識別碼
+    hashes: BTreeMap<[u8; DISCOVERY_HASH_LEN], String>,
+}
+
+impl DiscoveryQuery {
+    /// 加入一個識別碼 (重複或正規化後為空的識別碼會被忽略)
+    pub fn add(&mut self, identifier: &str) {
+        if normalize_identifier(identifier).is_empty() {
+            return;
+        }
+        self.hashes
+            .entry(discovery_hash(&self.salt, identifier))
+            .or_insert_with(|| identifier.to_string());
+    }
+
+    /// 依 [`MAX_DISCOVERY_BATCH`] 分批，每批為串接的截斷雜湊
+    pub fn batches(&self) -> Vec<Vec<u8>> {
+        let hashes: Vec<&[u8; DISCOVERY_HASH_LEN]> = self.hashes.keys().collect();
+        hashes
+            .chunks(MAX_DISCOVERY_BATCH)
+            .map(|chunk| chunk.iter().flat_map(|h| h.iter().copied()).collect())
+            .collect()
+    }
+
+    /// 比對伺服器回應 (串接的截斷雜湊)，回傳已註冊的本機識別碼
+    pub fn match_response(&self, response: &[u8]) -> Result<Vec<String>, String> {
+        if !response.len().is_multiple_of(DISCOVERY_HASH_LEN) {
+            return Err(format!(
+                "Response length must be a multiple of {}",
+                DISCOVERY_HASH_LEN
+            ));
+        }
+        Ok(response
+            .chunks(DISCOVERY_HASH_LEN)
+            .filter_map(|chunk| {
+                let mut hash = [0u8; DISCOVERY_HASH_LEN];
+                hash.copy_from_slice(chunk);
+                self.hashes.get(&hash).cloned()
+            })
+            .collect())
+    }
+}
+
+#[wasm_bindgen]
+impl DiscoveryQuery {
+    /// 以伺服器公告的 salt 建立查詢
+    #[wasm_bindgen(constructor)]
+    pub fn new(salt: &[u8]) -> Self {
+        Self {
+            salt: salt.to_vec(),
+            hashes: BTreeMap::new(),
+        }
+    }
+
+    /// 加入多個識別碼
+    #[wasm_bindgen(js_name = addIdentifiers)]
+    pub fn add_identifiers(&mut self, identifiers: Vec<String>) {
+        for identifier in &identifiers {
+            self.add(identifier);
+        }
+    }
+
+    /// 不重複的識別碼數量
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.hashes.len()
+    }
+
+    /// 批次數量
+    #[wasm_bindgen(getter, js_name = batchCount)]
+    pub fn batch_count(&self) -> usize {
+        self.hashes.len().div_ceil(MAX_DISCOVERY_BATCH)
+    }
+
+    /// 取得第 `index` 批的查詢內容
+    #[wasm_bindgen(js_name = batch)]
+    pub fn batch(&self, index: usize) -> Option<Vec<u8>> {
+        self.batches().into_iter().nth(index)
+    }
+
+    /// 比對伺服器回應，回傳已註冊的本機識別碼
+    #[wasm_bindgen(js_name = matchResponse)]
+    pub fn match_response_js(&self, response: &[u8]) -> Result<Vec<String>, JsError> {
+        self.match_response(response).map_err(|e| JsError::new(&e))
+    }
+}
+
+/// WASM 輔助函式：計算單一識別碼的截斷雜湊 (伺服器端註冊用)
+#[wasm_bindgen(js_name = contactDiscoveryHash)]
+pub fn contact_discovery_hash(salt: &[u8], identifier: &str) -> Vec<u8> {
+    discovery_hash(salt, identifier).to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalization() {
+        assert_eq!(normalize_identifier(" +1 (415) 555-0100 "), "+14155550100");
+        assert_eq!(normalize_identifier("Alice@Example.COM "), "alice@example.com");
+        assert_eq!(
+            discovery_hash(b"salt", "+1 415 555 0100"),
+            discovery_hash(b"salt", "+14155550100")
+        );
+        assert_ne!(
+            discovery_hash(b"salt", "+14155550100"),
+            discovery_hash(b"pepper", "+14155550100")
+        );
+    }
+
+    #[test]
+    fn test_query_batches_and_matching() {
+        let mut query = DiscoveryQuery::new(b"epoch-42");
+        let contacts: Vec<String> = (0..1203).map(|i| format!("+1415555{:04}", i)).collect();
+        query.add_identifiers(contacts.clone());
+        query.add("+1 415 555 0000");
+        query.add("not a number");
+
+        assert_eq!(query.size(), 1203);
+        assert_eq!(query.batch_count(), 3);
+        assert_eq!(query.batch(2).unwrap().len(), 203 * DISCOVERY_HASH_LEN);
+        assert!(query.batch(3).is_none());
+
+        // 伺服器回傳其中兩個已註冊的雜湊與一個未知雜湊
+        let mut response = discovery_hash(b"epoch-42", &contacts[7]).to_vec();
+        response.extend_from_slice(&[0u8; DISCOVERY_HASH_LEN]);
+        response.extend_from_slice(&discovery_hash(b"epoch-42", &contacts[1000]));
+        let mut found = query.match_response(&response).unwrap();
+        found.sort();
+        assert_eq!(found, vec![contacts[7].clone(), contacts[1000].clone()]);
+        assert!(query.match_response(&[0u8; 3]).is_err());
+    }
+}
@@ -0,0 +1,365 @@
+//! 密碼保護的私鑰匯出模組
+//!
+//! 以 Argon2id 從密碼衍生金鑰，再用 AES-256-GCM 加密私鑰，
+//! 取代直接把 `privateKeyBytes()` 寫入 localStorage 的做法
+//!
+//! 格式 (版本 1，固定為 Argon2id)：
+//!
+//! ```text
+//! magic "STKE" (4) || version 1 || key type (1)
+//! || memory KiB (4, BE) || iterations (4, BE) || parallelism (4, BE)
+//! || salt (16) || nonce (12) || ciphertext + tag (48)
+//! ```
+//!
+//! 版本 2 以密碼 KDF 參數字串 (見 `password` 模組) 明確記錄 KDF，
+//! 可解開以 scrypt 或 PBKDF2 保護的匯出檔，不需要猜測演算法：
+//!
+//! ```text
+//! magic "STKE" (4) || version 2 || key type (1)
+//! || params length (2, BE) || params string || nonce (12) || ciphertext + tag (48)
+//! ```
+//!
+//! 整個標頭 (直到 nonce) 作為 AES-GCM 的 AAD，任何參數被竄改都會解密失敗
+
+use wasm_bindgen::prelude::*;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+
+use super::keys::{IdentityKeyPair, X25519KeyPair};
+use super::password::{PasswordKdf, PasswordKdfParams};
+
+const EXPORT_MAGIC: &[u8; 4] = b"STKE";
+
+/// 目前的匯出格式版本
+pub const KEY_EXPORT_VERSION: u8 = 1;
+/// 以參數字串記錄 KDF 的匯出格式版本
+pub const KEY_EXPORT_VERSION_KDF: u8 = 2;
+
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+const HEADER_SIZE: usize = 4 + 1 + 1 + 4 * 3 + SALT_SIZE + NONCE_SIZE;
+
+/// 匯入時接受的最大記憶體參數 (避免惡意檔案耗盡記憶體)
+const MAX_MEMORY_KIB: u32 = 512 * 1024;
+/// 匯入時接受的最大迭代次數
+const MAX_ITERATIONS: u32 = 64;
+
+/// 匯出的私鑰類型
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportedKeyType {
+    /// Ed25519 身份金鑰
+    Identity = 1,
+    /// X25519 金鑰
+    X25519 = 2,
+}
+
+/// Argon2id 參數
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Argon2idParams {
+    /// 記憶體用量 (KiB)
+    pub memory_kib: u32,
+    /// 迭代次數
+    pub iterations: u32,
+    /// 平行度
+    pub parallelism: u32,
+}
+
+impl Default for Argon2idParams {
+    /// OWASP 建議值：19 MiB、2 次迭代、平行度 1
+    fn default() -> Self {
+        Self {
+            memory_kib: 19 * 1024,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+impl Argon2idParams {
+    /// 以 Argon2id 從密碼衍生 32 bytes 金鑰
+    pub fn derive_key(&self, passphrase: &[u8], salt: &[u8]) -> Result<[u8; 32], String> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| format!("Invalid Argon2 parameters: {}", e))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase, salt, &mut key)
+            .map_err(|e| format!("Argon2 failed: {}", e))?;
+        Ok(key)
+    }
+
+    /// 檢查外部提供的參數是否在允許範圍內
+    pub(crate) fn check_limits(&self) -> Result<(), String> {
+        if self.memory_kib > MAX_MEMORY_KIB || self.iterations > MAX_ITERATIONS {
+            return Err("Argon2 parameters exceed allowed limits".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 以密碼加密 32 bytes 私鑰
+pub fn encrypt_private_key(
+    key_type: ExportedKeyType,
+    private_key: &[u8; 32],
+    passphrase: &str,
+    params: &Argon2idParams,
+) -> Result<Vec<u8>, String> {
+    let mut salt = [0u8; SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+
+    let mut blob = Vec::with_capacity(HEADER_SIZE + 48);
+    blob.extend_from_slice(EXPORT_MAGIC);
+    blob.push(KEY_EXPORT_VERSION);
+    blob.push(key_type as u8);
+    blob.extend_from_slice(&params.memory_kib.to_be_bytes());
+    blob.extend_from_slice(&params.iterations.to_be_bytes());
+    blob.extend_from_slice(&params.parallelism.to_be_bytes());
+    blob.extend_from_slice(&salt);
+    blob.extend_from_slice(&nonce);
+
+    let key = params.derive_key(passphrase.as_bytes(), &salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: private_key, aad: &blob })
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+/// 以指定的密碼 KDF 加密 32 bytes 私鑰 (版本 2 格式)
+pub fn encrypt_private_key_with_kdf(
+    key_type: ExportedKeyType,
+    private_key: &[u8; 32],
+    passphrase: &str,
+    params: &PasswordKdfParams,
+) -> Result<Vec<u8>, String> {
+    let mut salt = [0u8; SALT_SIZE];
+    OsRng.fill_bytes(&mut salt);
+    let mut nonce = [0u8; NONCE_SIZE];
+    OsRng.fill_bytes(&mut nonce);
+    let encoded = params.encode(&salt);
+
+    let mut blob = Vec::with_capacity(8 + encoded.len() + NONCE_SIZE + 48);
+    blob.extend_from_slice(EXPORT_MAGIC);
+    blob.push(KEY_EXPORT_VERSION_KDF);
+    blob.push(key_type as u8);
+    blob.extend_from_slice(&(encoded.len() as u16).to_be_bytes());
+    blob.extend_from_slice(encoded.as_bytes());
+    blob.extend_from_slice(&nonce);
+
+    let key = params.derive_key(passphrase.as_bytes(), &salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), Payload { msg: private_key, aad: &blob })
+        .map_err(|e| format!("Encryption failed: {}", e))?;
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+/// 解析版本 2 標頭，回傳 KDF 參數、salt 與標頭長度 (含 nonce)
+fn parse_kdf_header(blob: &[u8]) -> Result<(PasswordKdfParams, Vec<u8>, usize), String> {
+    let truncated = || "Not an encrypted key export".to_string();
+    let length = u16::from_be_bytes([*blob.get(6).ok_or_else(truncated)?, *blob.get(7).ok_or_else(truncated)?]) as usize;
+    let header_size = 8 + length + NONCE_SIZE;
+    if blob.len() < header_size {
+        return Err(truncated());
+    }
+    let encoded = std::str::from_utf8(&blob[8..8 + length]).map_err(|_| "Invalid KDF parameters".to_string())?;
+    let (params, salt) = PasswordKdfParams::decode(encoded)?;
+    Ok((params, salt, header_size))
+}
+
+/// 以密碼解密私鑰 (版本 1 與版本 2)
+pub fn decrypt_private_key(
+    key_type: ExportedKeyType,
+    passphrase: &str,
+    blob: &[u8],
+) -> Result<[u8; 32], String> {
+    if blob.len() < 6 || &blob[..4] != EXPORT_MAGIC {
+        return Err("Not an encrypted key export".to_string());
+    }
+    if blob[4] != KEY_EXPORT_VERSION && blob[4] != KEY_EXPORT_VERSION_KDF {
+        return Err(format!("Unsupported key export version: {}", blob[4]));
+    }
+    if blob[5] != key_type as u8 {
+        return Err(format!("Key type mismatch: expected {:?}", key_type));
+    }
+
+    let (params, salt, header_size) = if blob[4] == KEY_EXPORT_VERSION_KDF {
+        parse_kdf_header(blob)?
+    } else {
+        parse_argon2id_header(blob)?
+    };
+    let (header, ciphertext) = blob.split_at(header_size);
+    let nonce = &header[header_size - NONCE_SIZE..];
+
+    let key = params.derive_key(passphrase.as_bytes(), &salt)?;
+    let cipher = Aes256Gcm::new_from_slice(&key).map_err(|e| e.to_string())?;
+    let plaintext = cipher
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: header })
+        .map_err(|_| "Wrong passphrase or corrupted export".to_string())?;
+
+    plaintext
+        .as_slice()
+        .try_into()
+        .map_err(|_| "Decrypted key must be 32 bytes".to_string())
+}
+
+/// 解析版本 1 標頭，回傳 Argon2id 參數、salt 與標頭長度 (含 nonce)
+fn parse_argon2id_header(blob: &[u8]) -> Result<(PasswordKdfParams, Vec<u8>, usize), String> {
+    if blob.len() < HEADER_SIZE {
+        return Err("Not an encrypted key export".to_string());
+    }
+    let read_u32 = |offset: usize| {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&blob[offset..offset + 4]);
+        u32::from_be_bytes(bytes)
+    };
+    let params = Argon2idParams {
+        memory_kib: read_u32(6),
+        iterations: read_u32(10),
+        parallelism: read_u32(14),
+    };
+    params.check_limits()?;
+    let salt = blob[18..18 + SALT_SIZE].to_vec();
+    Ok((PasswordKdfParams::Argon2id(params), salt, HEADER_SIZE))
+}
+
+#[wasm_bindgen]
+impl IdentityKeyPair {
+    /// 以密碼加密匯出私鑰
+    #[wasm_bindgen(js_name = exportEncrypted)]
+    pub fn export_encrypted(&self, passphrase: &str) -> Result<Vec<u8>, JsError> {
+        encrypt_private_key(
+            ExportedKeyType::Identity,
+            &self.signing_key().to_bytes(),
+            passphrase,
+            &Argon2idParams::default(),
+        )
+        .map_err(|e| JsError::new(&e))
+    }
+
+    /// 以指定的密碼 KDF 加密匯出私鑰
+    #[wasm_bindgen(js_name = exportEncryptedWithKdf)]
+    pub fn export_encrypted_with_kdf(&self, passphrase: &str, kdf: &PasswordKdf) -> Result<Vec<u8>, JsError> {
+        encrypt_private_key_with_kdf(
+            ExportedKeyType::Identity,
+            &self.signing_key().to_bytes(),
+            passphrase,
+            &kdf.params(),
+        )
+        .map_err(|e| JsError::new(&e))
+    }
+
+    /// 以密碼匯入私鑰
+    #[wasm_bindgen(js_name = importEncrypted)]
+    pub fn import_encrypted(passphrase: &str, blob: &[u8]) -> Result<IdentityKeyPair, JsError> {
+        let key = decrypt_private_key(ExportedKeyType::Identity, passphrase, blob)
+            .map_err(|e| JsError::new(&e))?;
+        IdentityKeyPair::from_bytes(&key)
+    }
+}
+
+#[wasm_bindgen]
+impl X25519KeyPair {
+    /// 以密碼加密匯出私鑰
+    #[wasm_bindgen(js_name = exportEncrypted)]
+    pub fn export_encrypted(&self, passphrase: &str) -> Result<Vec<u8>, JsError> {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(self.private_key_bytes().expose());
+        encrypt_private_key(ExportedKeyType::X25519, &key, passphrase, &Argon2idParams::default())
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 以指定的密碼 KDF 加密匯出私鑰
+    #[wasm_bindgen(js_name = exportEncryptedWithKdf)]
+    pub fn export_encrypted_with_kdf(&self, passphrase: &str, kdf: &PasswordKdf) -> Result<Vec<u8>, JsError> {
+        let mut key = [0u8; 32];
+        key.copy_from_slice(self.private_key_bytes().expose());
+        encrypt_private_key_with_kdf(ExportedKeyType::X25519, &key, passphrase, &kdf.params())
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 以密碼匯入私鑰
+    #[wasm_bindgen(js_name = importEncrypted)]
+    pub fn import_encrypted(passphrase: &str, blob: &[u8]) -> Result<X25519KeyPair, JsError> {
+        let key = decrypt_private_key(ExportedKeyType::X25519, passphrase, blob)
+            .map_err(|e| JsError::new(&e))?;
+        X25519KeyPair::from_bytes(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PARAMS: Argon2idParams = Argon2idParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_encrypted_export_roundtrip() {
+        let identity = IdentityKeyPair::new();
+        let secret: [u8; 32] = identity.private_key_bytes().expose().try_into().unwrap();
+
+        let blob = encrypt_private_key(ExportedKeyType::Identity, &secret, "correct horse", &TEST_PARAMS).unwrap();
+        assert_eq!(&blob[..4], EXPORT_MAGIC);
+        assert_eq!(blob.len(), HEADER_SIZE + 48);
+
+        assert_eq!(decrypt_private_key(ExportedKeyType::Identity, "correct horse", &blob), Ok(secret));
+        assert!(decrypt_private_key(ExportedKeyType::Identity, "wrong", &blob).is_err());
+        assert!(decrypt_private_key(ExportedKeyType::X25519, "correct horse", &blob).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_export_rejects_tampered_header() {
+        let blob = encrypt_private_key(ExportedKeyType::X25519, &[9u8; 32], "pw", &TEST_PARAMS).unwrap();
+
+        // 降低迭代次數的竄改會被 AAD 偵測
+        let mut tampered = blob.clone();
+        tampered[13] ^= 0x01;
+        assert!(decrypt_private_key(ExportedKeyType::X25519, "pw", &tampered).is_err());
+
+        // 過大的記憶體參數直接拒絕
+        let mut huge = blob.clone();
+        huge[6..10].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_eq!(
+            decrypt_private_key(ExportedKeyType::X25519, "pw", &huge),
+            Err("Argon2 parameters exceed allowed limits".to_string())
+        );
+
+        let mut future = blob;
+        future[4] = 3;
+        assert!(decrypt_private_key(ExportedKeyType::X25519, "pw", &future).is_err());
+    }
+
+    #[test]
+    fn test_kdf_export_identifies_kdf_from_header() {
+        use crate::crypto::password::{Pbkdf2Params, ScryptParams};
+
+        for params in [
+            PasswordKdfParams::Argon2id(TEST_PARAMS),
+            PasswordKdfParams::Scrypt(ScryptParams { log_n: 4, r: 1, p: 1 }),
+            PasswordKdfParams::Pbkdf2Sha256(Pbkdf2Params { iterations: 1000 }),
+        ] {
+            let blob = encrypt_private_key_with_kdf(ExportedKeyType::X25519, &[7u8; 32], "pw", &params).unwrap();
+            assert_eq!(blob[4], KEY_EXPORT_VERSION_KDF);
+            assert_eq!(decrypt_private_key(ExportedKeyType::X25519, "pw", &blob), Ok([7u8; 32]));
+            assert!(decrypt_private_key(ExportedKeyType::X25519, "wrong", &blob).is_err());
+
+            // 竄改參數字串 (salt 的最後一個字元) 會解密失敗
+            let mut tampered = blob.clone();
+            let last = 8 + blob[7] as usize - 1;
+            tampered[last] = if tampered[last] == b'A' { b'B' } else { b'A' };
+            assert!(decrypt_private_key(ExportedKeyType::X25519, "pw", &tampered).is_err());
+        }
+    }
+}
@@ -0,0 +1,275 @@
+//! 安全碼 (Safety Number) 模組
+//!
+//! 依照 Signal 的 NumericFingerprintGenerator 產生 60 位數字安全碼，
+//! 讓雙方可以面對面或透過其他管道比對身份公鑰，
+//! 並提供 QR 碼掃描驗證用的二進位格式
+
+use wasm_bindgen::prelude::*;
+use sha2::{Digest, Sha512};
+
+use super::constant_time::constant_time_eq;
+
+/// Signal 使用的雜湊迭代次數
+pub const DEFAULT_FINGERPRINT_ITERATIONS: u32 = 5200;
+
+/// 雜湊格式版本 (固定為 0，與 Signal 相同)
+const FINGERPRINT_HASH_VERSION: [u8; 2] = [0, 0];
+
+/// 每一方安全碼使用的雜湊位元組數 (6 組 × 5 bytes)
+const DISPLAY_HASH_BYTES: usize = 30;
+
+/// 單方安全碼雜湊
+///
+/// 初始值為 version || key || stable_id，之後重複 `iterations` 次 hash = SHA-512(hash || key)
+pub fn fingerprint_hash(iterations: u32, stable_id: &[u8], identity_key: &[u8]) -> [u8; 64] {
+    let mut hasher = Sha512::new();
+    hasher.update(FINGERPRINT_HASH_VERSION);
+    hasher.update(identity_key);
+    hasher.update(stable_id);
+    hasher.update(identity_key);
+    let mut hash: [u8; 64] = hasher.finalize().into();
+
+    for _ in 1..iterations {
+        let mut hasher = Sha512::new();
+        hasher.update(hash);
+        hasher.update(identity_key);
+        hash = hasher.finalize().into();
+    }
+    hash
+}
+
+/// QR 驗證格式版本
+pub const SCANNABLE_FINGERPRINT_VERSION: u8 = 1;
+
+/// QR 驗證格式中每一方雜湊的長度
+const SCANNABLE_HASH_BYTES: usize = 32;
+
+/// QR 驗證格式總長度：version (1) || local hash (32) || remote hash (32)
+const SCANNABLE_PAYLOAD_LEN: usize = 1 + SCANNABLE_HASH_BYTES * 2;
+
+/// QR 掃描比對結果
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanResult {
+    /// 雙方身份公鑰相符
+    Match = 0,
+    /// 身份公鑰不符 (可能遭到中間人攻擊)
+    Mismatch = 1,
+    /// 對方使用不同版本的格式，無法比對
+    VersionSkew = 2,
+}
+
+/// 將雜湊的前 30 bytes 轉為 30 位數字 (每 5 bytes 取模 100000)
+fn display_digits(hash: &[u8]) -> String {
+    hash[..DISPLAY_HASH_BYTES]
+        .chunks(5)
+        .map(|chunk| {
+            let value = chunk.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+            format!("{:05}", value % 100_000)
+        })
+        .collect()
+}
+
+/// 雙方的安全碼
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct Fingerprint {
+    local_hash: [u8; 64],
+    remote_hash: [u8; 64],
+    local_digits: String,
+    remote_digits: String,
+}
+
+impl Fingerprint {
+    /// 使用指定迭代次數計算安全碼
+    pub fn with_iterations(
+        iterations: u32,
+        local_stable_id: &[u8],
+        local_identity_key: &[u8],
+        remote_stable_id: &[u8],
+        remote_identity_key: &[u8],
+    ) -> Self {
+        let local_hash = fingerprint_hash(iterations, local_stable_id, local_identity_key);
+        let remote_hash = fingerprint_hash(iterations, remote_stable_id, remote_identity_key);
+        Self {
+            local_digits: display_digits(&local_hash),
+            remote_digits: display_digits(&remote_hash),
+            local_hash,
+            remote_hash,
+        }
+    }
+
+    /// 我方完整雜湊 (供 QR 驗證使用)
+    pub fn local_hash(&self) -> &[u8; 64] {
+        &self.local_hash
+    }
+
+    /// 對方完整雜湊 (供 QR 驗證使用)
+    pub fn remote_hash(&self) -> &[u8; 64] {
+        &self.remote_hash
+    }
+
+    /// 比對掃描到的 QR 資料
+    ///
+    /// 對方的 QR 內容是從對方視角產生的，
+    /// 因此其 local 應等於我方的 remote，其 remote 應等於我方的 local
+    pub fn compare_scanned(&self, scanned: &[u8]) -> Result<ScanResult, String> {
+        let version = *scanned.first().ok_or_else(|| "Empty scanned payload".to_string())?;
+        if version != SCANNABLE_FINGERPRINT_VERSION {
+            return Ok(ScanResult::VersionSkew);
+        }
+        if scanned.len() != SCANNABLE_PAYLOAD_LEN {
+            return Err(format!(
+                "Scanned payload must be {} bytes, got {}",
+                SCANNABLE_PAYLOAD_LEN,
+                scanned.len()
+            ));
+        }
+
+        let (their_local, their_remote) = scanned[1..].split_at(SCANNABLE_HASH_BYTES);
+        let local_matches = constant_time_eq(their_local, &self.remote_hash[..SCANNABLE_HASH_BYTES]);
+        let remote_matches = constant_time_eq(their_remote, &self.local_hash[..SCANNABLE_HASH_BYTES]);
+        if local_matches & remote_matches {
+            Ok(ScanResult::Match)
+        } else {
+            Ok(ScanResult::Mismatch)
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl Fingerprint {
+    /// 計算雙方安全碼
+    ///
+    /// # 參數
+    /// - `local_stable_id` / `remote_stable_id`: 穩定識別碼 (例如使用者 ID 或電話號碼)
+    /// - `local_identity_key` / `remote_identity_key`: 身份公鑰
+    ///   (與 Signal 比對時需傳入含 0x05 前綴的 33 bytes 序列化格式)
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        local_stable_id: &str,
+        local_identity_key: &[u8],
+        remote_stable_id: &str,
+        remote_identity_key: &[u8],
+    ) -> Self {
+        Self::with_iterations(
+            DEFAULT_FINGERPRINT_ITERATIONS,
+            local_stable_id.as_bytes(),
+            local_identity_key,
+            remote_stable_id.as_bytes(),
+            remote_identity_key,
+        )
+    }
+
+    /// 60 位數字安全碼
+    ///
+    /// 雙方的 30 位數字依字典序排列後串接，因此兩端顯示相同結果
+    #[wasm_bindgen(getter, js_name = displayText)]
+    pub fn display_text(&self) -> String {
+        if self.local_digits <= self.remote_digits {
+            format!("{}{}", self.local_digits, self.remote_digits)
+        } else {
+            format!("{}{}", self.remote_digits, self.local_digits)
+        }
+    }
+
+    /// 分組顯示 (12 組 5 位數，以空白分隔)
+    #[wasm_bindgen(getter, js_name = displayGroups)]
+    pub fn display_groups(&self) -> String {
+        let text = self.display_text();
+        text.as_bytes()
+            .chunks(5)
+            .map(|c| String::from_utf8_lossy(c).into_owned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// 我方 30 位數字
+    #[wasm_bindgen(getter, js_name = localDigits)]
+    pub fn local_digits(&self) -> String {
+        self.local_digits.clone()
+    }
+
+    /// 對方 30 位數字
+    #[wasm_bindgen(getter, js_name = remoteDigits)]
+    pub fn remote_digits(&self) -> String {
+        self.remote_digits.clone()
+    }
+
+    /// 產生 QR 驗證資料
+    ///
+    /// 格式：version (1 byte) || 我方雜湊 (32 bytes) || 對方雜湊 (32 bytes)
+    #[wasm_bindgen(getter, js_name = scannablePayload)]
+    pub fn scannable_payload(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(SCANNABLE_PAYLOAD_LEN);
+        payload.push(SCANNABLE_FINGERPRINT_VERSION);
+        payload.extend_from_slice(&self.local_hash[..SCANNABLE_HASH_BYTES]);
+        payload.extend_from_slice(&self.remote_hash[..SCANNABLE_HASH_BYTES]);
+        payload
+    }
+
+    /// 比對掃描到的 QR 資料
+    #[wasm_bindgen(js_name = compareScannedFingerprint)]
+    pub fn compare_scanned_fingerprint(&self, scanned: &[u8]) -> Result<ScanResult, JsError> {
+        self.compare_scanned(scanned).map_err(|e| JsError::new(&e))
+    }
+
+    /// 比對使用者輸入的安全碼 (忽略空白)
+    #[wasm_bindgen(js_name = matchesDisplayText)]
+    pub fn matches_display_text(&self, text: &str) -> bool {
+        let normalized: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+        constant_time_eq(normalized.as_bytes(), self.display_text().as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_signal_known_answer() {
+        // libsignal fingerprint 測試向量
+        let alice_key = hex("0506863bc66d02b40d27b8d49ca7c09e9239236f9d7d25d6fcca5ce13c7064d868");
+        let bob_key = hex("05f781b6fb32fed9ba1cf2de978d4d5da28dc34046ae814402b5c0dbd96fda907b");
+
+        let alice = Fingerprint::new("+14152222222", &alice_key, "+14153333333", &bob_key);
+        let bob = Fingerprint::new("+14153333333", &bob_key, "+14152222222", &alice_key);
+
+        let expected = "300354477692869396892869876765458257569162576843440918079131";
+        assert_eq!(alice.display_text(), expected);
+        assert_eq!(bob.display_text(), expected);
+    }
+
+    #[test]
+    fn test_fingerprint_changes_with_key() {
+        let alice = Fingerprint::with_iterations(10, b"alice", &[1u8; 32], b"bob", &[2u8; 32]);
+        let bob = Fingerprint::with_iterations(10, b"bob", &[2u8; 32], b"alice", &[1u8; 32]);
+        let mallory = Fingerprint::with_iterations(10, b"alice", &[1u8; 32], b"bob", &[3u8; 32]);
+
+        assert_eq!(alice.display_text().len(), 60);
+        assert_eq!(alice.display_text(), bob.display_text());
+        assert_ne!(alice.display_text(), mallory.display_text());
+        assert!(alice.matches_display_text(&bob.display_groups()));
+    }
+
+    #[test]
+    fn test_scannable_payload() {
+        let alice = Fingerprint::with_iterations(10, b"alice", &[1u8; 32], b"bob", &[2u8; 32]);
+        let bob = Fingerprint::with_iterations(10, b"bob", &[2u8; 32], b"alice", &[1u8; 32]);
+        let mallory = Fingerprint::with_iterations(10, b"bob", &[3u8; 32], b"alice", &[1u8; 32]);
+
+        let payload = bob.scannable_payload();
+        assert_eq!(payload.len(), 65);
+        assert_eq!(alice.compare_scanned(&payload), Ok(ScanResult::Match));
+        assert_eq!(alice.compare_scanned(&mallory.scannable_payload()), Ok(ScanResult::Mismatch));
+
+        // 自己掃描自己的 QR 不應通過
+        assert_eq!(alice.compare_scanned(&alice.scannable_payload()), Ok(ScanResult::Mismatch));
+
+        let mut future = payload.clone();
+        future[0] = 2;
+        assert_eq!(alice.compare_scanned(&future), Ok(ScanResult::VersionSkew));
+        assert!(alice.compare_scanned(&payload[..40]).is_err());
+    }
+}
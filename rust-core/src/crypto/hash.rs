@@ -0,0 +1,165 @@
+//! 雜湊模組
+//!
+//! 公開 SHA-256 / SHA-512 / BLAKE3 的單次與串流 (`update` / `finalize`) 介面，
+//! 附件摘要、指紋等不必再透過 WebCrypto 重新讀取整個檔案
+//!
+//! BLAKE3 在 WASM 中處理大型檔案比 SHA-2 快很多，適合附件摘要與內容定址；
+//! 演算法以單一位元組記錄在各自的格式中
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256, Sha512};
+
+/// 雜湊演算法
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum HashAlgorithm {
+    #[default]
+    Sha256 = 1,
+    Sha512 = 2,
+    Blake3 = 3,
+}
+
+impl From<HashAlgorithm> for u8 {
+    fn from(algorithm: HashAlgorithm) -> u8 {
+        algorithm as u8
+    }
+}
+
+impl TryFrom<u8> for HashAlgorithm {
+    type Error = String;
+
+    fn try_from(byte: u8) -> Result<Self, String> {
+        match byte {
+            1 => Ok(HashAlgorithm::Sha256),
+            2 => Ok(HashAlgorithm::Sha512),
+            3 => Ok(HashAlgorithm::Blake3),
+            _ => Err(format!("Unsupported hash algorithm: {}", byte)),
+        }
+    }
+}
+
+impl HashAlgorithm {
+    /// 單次雜湊
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha256 => sha256(data),
+            HashAlgorithm::Sha512 => sha512(data),
+            HashAlgorithm::Blake3 => blake3(data),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum HasherState {
+    Sha256(Sha256),
+    Sha512(Sha512),
+    Blake3(Box<blake3::Hasher>),
+}
+
+/// 串流雜湊
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct Hasher {
+    state: HasherState,
+}
+
+#[wasm_bindgen]
+impl Hasher {
+    #[wasm_bindgen(constructor)]
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        let state = match algorithm {
+            HashAlgorithm::Sha256 => HasherState::Sha256(Sha256::new()),
+            HashAlgorithm::Sha512 => HasherState::Sha512(Sha512::new()),
+            HashAlgorithm::Blake3 => HasherState::Blake3(Box::default()),
+        };
+        Self { state }
+    }
+
+    /// 演算法
+    #[wasm_bindgen(getter)]
+    pub fn algorithm(&self) -> HashAlgorithm {
+        match self.state {
+            HasherState::Sha256(_) => HashAlgorithm::Sha256,
+            HasherState::Sha512(_) => HashAlgorithm::Sha512,
+            HasherState::Blake3(_) => HashAlgorithm::Blake3,
+        }
+    }
+
+    /// 輸入資料
+    pub fn update(&mut self, data: &[u8]) {
+        match &mut self.state {
+            HasherState::Sha256(hasher) => hasher.update(data),
+            HasherState::Sha512(hasher) => hasher.update(data),
+            HasherState::Blake3(hasher) => {
+                hasher.update(data);
+            }
+        }
+    }
+
+    /// 取得摘要並重設，可繼續用於下一份資料
+    pub fn finalize(&mut self) -> Vec<u8> {
+        match &mut self.state {
+            HasherState::Sha256(hasher) => hasher.finalize_reset().to_vec(),
+            HasherState::Sha512(hasher) => hasher.finalize_reset().to_vec(),
+            HasherState::Blake3(hasher) => {
+                let digest = hasher.finalize().as_bytes().to_vec();
+                hasher.reset();
+                digest
+            }
+        }
+    }
+}
+
+/// SHA-256
+#[wasm_bindgen(js_name = sha256)]
+pub fn sha256(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data).to_vec()
+}
+
+/// SHA-512
+#[wasm_bindgen(js_name = sha512)]
+pub fn sha512(data: &[u8]) -> Vec<u8> {
+    Sha512::digest(data).to_vec()
+}
+
+/// BLAKE3 (32 bytes)
+#[wasm_bindgen(js_name = blake3)]
+pub fn blake3(data: &[u8]) -> Vec<u8> {
+    blake3::hash(data).as_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::to_hex;
+
+    #[test]
+    fn test_one_shot_vectors() {
+        assert_eq!(to_hex(&sha256(b"abc")), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(to_hex(&blake3(b"")), "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262");
+        assert_eq!(
+            to_hex(&sha512(b"abc")),
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+             2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+    }
+
+    #[test]
+    fn test_streaming_matches_one_shot() {
+        let data: Vec<u8> = (0..10_000u32).map(|i| i as u8).collect();
+        for algorithm in [HashAlgorithm::Sha256, HashAlgorithm::Sha512, HashAlgorithm::Blake3] {
+            let one_shot = algorithm.digest(&data);
+            let mut hasher = Hasher::new(algorithm);
+            for chunk in data.chunks(333) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.finalize(), one_shot);
+
+            // finalize 後重設
+            hasher.update(b"abc");
+            assert_eq!(hasher.finalize(), algorithm.digest(b"abc"));
+        }
+    }
+}
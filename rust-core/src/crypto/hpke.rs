@@ -0,0 +1,266 @@
+//! HPKE (RFC 9180) 模組
+//!
+//! 單次 (single-shot) 的 base 模式公鑰加密：
+//! - KEM：DHKEM(X25519, HKDF-SHA256)
+//! - KDF：HKDF-SHA256
+//! - AEAD：AES-128-GCM / AES-256-GCM / ChaCha20-Poly1305
+//!
+//! 用於伺服器保存的禮物內容、裝置連結信封、密封的中繼資料等
+//! 只需加密給某個公鑰一次的情境，取代各處自行組合的 DH + AES
+//!
+//! 密文格式：enc (臨時公鑰, 32) || AEAD 密文，
+//! 與 OpenSSL / Python `cryptography` 的 HPKE single-shot API 相同
+
+use wasm_bindgen::prelude::*;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes128Gcm, Aes256Gcm,
+};
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use super::keys::X25519KeyPair;
+
+const HPKE_VERSION_LABEL: &[u8] = b"HPKE-v1";
+/// DHKEM(X25519, HKDF-SHA256)
+const KEM_X25519_HKDF_SHA256: u16 = 0x0020;
+/// HKDF-SHA256
+const KDF_HKDF_SHA256: u16 = 0x0001;
+/// base 模式
+const MODE_BASE: u8 = 0x00;
+
+/// enc (臨時 X25519 公鑰) 長度
+pub const HPKE_ENC_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+
+/// HPKE AEAD 演算法
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HpkeAead {
+    Aes128Gcm = 0x0001,
+    Aes256Gcm = 0x0002,
+    ChaCha20Poly1305 = 0x0003,
+}
+
+impl HpkeAead {
+    fn key_size(self) -> usize {
+        match self {
+            HpkeAead::Aes128Gcm => 16,
+            HpkeAead::Aes256Gcm | HpkeAead::ChaCha20Poly1305 => 32,
+        }
+    }
+
+    fn seal(self, key: &[u8], nonce: &[u8], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let payload = Payload { msg: plaintext, aad };
+        let result = match self {
+            HpkeAead::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .map_err(|e| e.to_string())?
+                .encrypt(nonce.into(), payload),
+            HpkeAead::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .map_err(|e| e.to_string())?
+                .encrypt(nonce.into(), payload),
+            HpkeAead::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key)
+                .map_err(|e| e.to_string())?
+                .encrypt(nonce.into(), payload),
+        };
+        result.map_err(|e| format!("Encryption failed: {}", e))
+    }
+
+    fn open(self, key: &[u8], nonce: &[u8], aad: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        let payload = Payload { msg: ciphertext, aad };
+        let result = match self {
+            HpkeAead::Aes128Gcm => Aes128Gcm::new_from_slice(key)
+                .map_err(|e| e.to_string())?
+                .decrypt(nonce.into(), payload),
+            HpkeAead::Aes256Gcm => Aes256Gcm::new_from_slice(key)
+                .map_err(|e| e.to_string())?
+                .decrypt(nonce.into(), payload),
+            HpkeAead::ChaCha20Poly1305 => ChaCha20Poly1305::new_from_slice(key)
+                .map_err(|e| e.to_string())?
+                .decrypt(nonce.into(), payload),
+        };
+        result.map_err(|_| "HPKE decryption failed".to_string())
+    }
+}
+
+/// LabeledExtract(salt, label, ikm)
+fn labeled_extract(suite_id: &[u8], salt: &[u8], label: &[u8], ikm: &[u8]) -> [u8; 32] {
+    let labeled_ikm = [HPKE_VERSION_LABEL, suite_id, label, ikm].concat();
+    let (prk, _) = Hkdf::<Sha256>::extract(Some(salt), &labeled_ikm);
+    prk.into()
+}
+
+/// LabeledExpand(prk, label, info, L)
+fn labeled_expand(suite_id: &[u8], prk: &[u8], label: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, String> {
+    let labeled_info = [&(len as u16).to_be_bytes()[..], HPKE_VERSION_LABEL, suite_id, label, info].concat();
+    let hkdf = Hkdf::<Sha256>::from_prk(prk).map_err(|e| format!("HKDF failed: {}", e))?;
+    let mut okm = vec![0u8; len];
+    hkdf.expand(&labeled_info, &mut okm)
+        .map_err(|e| format!("HKDF failed: {}", e))?;
+    Ok(okm)
+}
+
+/// DHKEM 的 ExtractAndExpand
+fn kem_shared_secret(dh: &[u8; 32], enc: &[u8], recipient_public: &[u8]) -> Result<Vec<u8>, String> {
+    if dh.iter().all(|b| *b == 0) {
+        return Err("HPKE key agreement produced an all-zero shared secret".to_string());
+    }
+    let suite_id = [&b"KEM"[..], &KEM_X25519_HKDF_SHA256.to_be_bytes()].concat();
+    let kem_context = [enc, recipient_public].concat();
+    let eae_prk = labeled_extract(&suite_id, b"", b"eae_prk", dh);
+    labeled_expand(&suite_id, &eae_prk, b"shared_secret", &kem_context, 32)
+}
+
+/// base 模式 KeySchedule，回傳 (key, base_nonce)
+fn key_schedule(aead: HpkeAead, shared_secret: &[u8], info: &[u8]) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let suite_id = [
+        &b"HPKE"[..],
+        &KEM_X25519_HKDF_SHA256.to_be_bytes(),
+        &KDF_HKDF_SHA256.to_be_bytes(),
+        &(aead as u16).to_be_bytes(),
+    ]
+    .concat();
+    let psk_id_hash = labeled_extract(&suite_id, b"", b"psk_id_hash", b"");
+    let info_hash = labeled_extract(&suite_id, b"", b"info_hash", info);
+    let context = [&[MODE_BASE][..], &psk_id_hash, &info_hash].concat();
+
+    let secret = labeled_extract(&suite_id, shared_secret, b"secret", b"");
+    let key = labeled_expand(&suite_id, &secret, b"key", &context, aead.key_size())?;
+    let base_nonce = labeled_expand(&suite_id, &secret, b"base_nonce", &context, NONCE_SIZE)?;
+    Ok((key, base_nonce))
+}
+
+/// 以臨時金鑰加密給收件者公鑰，回傳 enc || 密文
+pub fn hpke_seal_with(
+    ephemeral: &X25519KeyPair,
+    aead: HpkeAead,
+    recipient_public: &[u8],
+    info: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, String> {
+    let enc = ephemeral.public_key_bytes();
+    let dh = ephemeral.shared_secret(recipient_public)?;
+    let shared_secret = kem_shared_secret(&dh, &enc, recipient_public)?;
+    let (key, nonce) = key_schedule(aead, &shared_secret, info)?;
+
+    let mut sealed = enc;
+    sealed.extend(aead.seal(&key, &nonce, aad, plaintext)?);
+    Ok(sealed)
+}
+
+/// 加密給收件者的 X25519 公鑰 (單次 base 模式)
+pub fn hpke_seal(
+    aead: HpkeAead,
+    recipient_public: &[u8],
+    info: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, String> {
+    hpke_seal_with(&X25519KeyPair::new(), aead, recipient_public, info, aad, plaintext)
+}
+
+/// 以收件者私鑰解密 enc || 密文
+pub fn hpke_open(
+    aead: HpkeAead,
+    recipient: &X25519KeyPair,
+    info: &[u8],
+    aad: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, String> {
+    if sealed.len() < HPKE_ENC_SIZE {
+        return Err("HPKE ciphertext too short".to_string());
+    }
+    let (enc, ciphertext) = sealed.split_at(HPKE_ENC_SIZE);
+    let dh = recipient.shared_secret(enc)?;
+    let shared_secret = kem_shared_secret(&dh, enc, &recipient.public_key_bytes())?;
+    let (key, nonce) = key_schedule(aead, &shared_secret, info)?;
+    aead.open(&key, &nonce, aad, ciphertext)
+}
+
+/// HPKE 加密給收件者的 X25519 公鑰，回傳 enc || 密文
+#[wasm_bindgen(js_name = hpkeSeal)]
+pub fn hpke_seal_js(
+    aead: HpkeAead,
+    recipient_public: &[u8],
+    info: &[u8],
+    aad: &[u8],
+    plaintext: &[u8],
+) -> Result<Vec<u8>, JsError> {
+    hpke_seal(aead, recipient_public, info, aad, plaintext).map_err(|e| JsError::new(&e))
+}
+
+/// HPKE 解密
+#[wasm_bindgen(js_name = hpkeOpen)]
+pub fn hpke_open_js(
+    aead: HpkeAead,
+    recipient: &X25519KeyPair,
+    info: &[u8],
+    aad: &[u8],
+    sealed: &[u8],
+) -> Result<Vec<u8>, JsError> {
+    hpke_open(aead, recipient, info, aad, sealed).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_interop_with_python_cryptography() {
+        // cryptography 48 `hpke.Suite(KEM.X25519, KDF.HKDF_SHA256, aead).encrypt(...)`
+        // 收件者私鑰 00 01 02 ... 1f，info = "SafeTalk test"
+        let recipient = X25519KeyPair::from_bytes(&(0u8..32).collect::<Vec<_>>()).unwrap();
+        let vectors = [
+            (
+                HpkeAead::Aes128Gcm,
+                "c6dd845331a59c5d99ce8ae04a6eeef58d4ffbde0984bcaa8faffaa76f44797f\
+                 ce2faaac8df6ae72b9352ab5156013064fa7e9db7f8d10b893ca843fde",
+            ),
+            (
+                HpkeAead::Aes256Gcm,
+                "a45a9faaae3e2a7d84722bcca1b8c01f366f521f45f33f218f7dfd627be49d24\
+                 3678d363d1135897825bd0f550e9af3272d2bf89180188c8688274adef",
+            ),
+            (
+                HpkeAead::ChaCha20Poly1305,
+                "5f14c21629e4f3cdee7661073300daf3bd3f57b341744147e01200497667147e\
+                 ea0b22166e8619c5ea5218ed2decf10776aac3712b54a00605922c469b",
+            ),
+        ];
+        for (aead, sealed) in vectors {
+            let plaintext = hpke_open(aead, &recipient, b"SafeTalk test", b"", &hex(sealed)).unwrap();
+            assert_eq!(plaintext, b"SafeTalk HPKE");
+        }
+    }
+
+    #[test]
+    fn test_seal_open_round_trip() {
+        let recipient = X25519KeyPair::new();
+        let sealed = hpke_seal(
+            HpkeAead::Aes256Gcm,
+            &recipient.public_key_bytes(),
+            b"gift",
+            b"order-1",
+            b"a gift for you",
+        )
+        .unwrap();
+        assert_eq!(
+            hpke_open(HpkeAead::Aes256Gcm, &recipient, b"gift", b"order-1", &sealed).unwrap(),
+            b"a gift for you"
+        );
+
+        // info、AAD、AEAD 或收件者不同都無法解密
+        assert!(hpke_open(HpkeAead::Aes256Gcm, &recipient, b"other", b"order-1", &sealed).is_err());
+        assert!(hpke_open(HpkeAead::Aes256Gcm, &recipient, b"gift", b"order-2", &sealed).is_err());
+        assert!(hpke_open(HpkeAead::ChaCha20Poly1305, &recipient, b"gift", b"order-1", &sealed).is_err());
+        assert!(hpke_open(HpkeAead::Aes256Gcm, &X25519KeyPair::new(), b"gift", b"order-1", &sealed).is_err());
+    }
+
+    #[test]
+    fn test_rejects_small_order_public_key() {
+        assert!(hpke_seal(HpkeAead::Aes128Gcm, &[0u8; 32], b"", b"", b"secret").is_err());
+    }
+}
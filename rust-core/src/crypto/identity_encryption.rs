@@ -0,0 +1,111 @@
+//! 身份公鑰加密模組 (ECIES)
+//!
+//! 不需要建立 X3DH / Double Ratchet 會話，直接加密給對方的 Ed25519 身份公鑰：
+//! 身份公鑰轉為 X25519 後以 HPKE (臨時 X25519 + HKDF-SHA256 + AES-256-GCM) 加密，
+//! 適合一次性的邀請內容、金鑰伺服器通知等
+//!
+//! 不提供前向保密，也不驗證寄件者身份，需要時請使用會話或另行簽章
+//!
+//! 密文格式：version (1) || HPKE enc (32) || AES-256-GCM 密文
+
+use wasm_bindgen::prelude::*;
+
+use super::convert::{ed25519_private_to_x25519_bytes, ed25519_public_to_x25519_bytes};
+use super::hpke::{hpke_open, hpke_seal, HpkeAead, HPKE_ENC_SIZE};
+use super::keys::{IdentityKeyPair, X25519KeyPair};
+
+const INFO_IDENTITY_ENCRYPTION: &[u8] = b"SafeTalk_IdentityEncryption";
+
+/// 目前的密文版本
+pub const IDENTITY_ENCRYPTION_VERSION: u8 = 1;
+
+/// AAD：版本 || 收件者身份公鑰
+fn associated_data(recipient_identity_key: &[u8]) -> Vec<u8> {
+    let mut aad = vec![IDENTITY_ENCRYPTION_VERSION];
+    aad.extend_from_slice(recipient_identity_key);
+    aad
+}
+
+/// 加密給收件者的身份公鑰
+pub fn encrypt_to_identity_key(recipient_identity_key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let recipient_x25519 = ed25519_public_to_x25519_bytes(recipient_identity_key)?;
+    let sealed = hpke_seal(
+        HpkeAead::Aes256Gcm,
+        &recipient_x25519,
+        INFO_IDENTITY_ENCRYPTION,
+        &associated_data(recipient_identity_key),
+        plaintext,
+    )?;
+
+    let mut ciphertext = vec![IDENTITY_ENCRYPTION_VERSION];
+    ciphertext.extend(sealed);
+    Ok(ciphertext)
+}
+
+/// 以身份金鑰解密
+pub fn decrypt_with_identity(identity: &IdentityKeyPair, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+    if ciphertext.len() < 1 + HPKE_ENC_SIZE {
+        return Err("Identity ciphertext too short".to_string());
+    }
+    if ciphertext[0] != IDENTITY_ENCRYPTION_VERSION {
+        return Err(format!("Unsupported identity ciphertext version: {}", ciphertext[0]));
+    }
+    let x25519_private = ed25519_private_to_x25519_bytes(identity.private_key_bytes().expose())?;
+    let recipient = X25519KeyPair::from_bytes(&x25519_private)
+        .map_err(|_| "Invalid X25519 private key".to_string())?;
+    hpke_open(
+        HpkeAead::Aes256Gcm,
+        &recipient,
+        INFO_IDENTITY_ENCRYPTION,
+        &associated_data(&identity.public_key_bytes()),
+        &ciphertext[1..],
+    )
+    .map_err(|_| "Failed to decrypt identity ciphertext".to_string())
+}
+
+/// 加密給收件者的身份公鑰 (不需會話)
+#[wasm_bindgen(js_name = encryptToIdentity)]
+pub fn encrypt_to_identity(recipient_identity_key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, JsError> {
+    encrypt_to_identity_key(recipient_identity_key, plaintext).map_err(|e| JsError::new(&e))
+}
+
+/// 以自己的身份金鑰解密 `encryptToIdentity` 的密文
+#[wasm_bindgen(js_name = decryptFromIdentity)]
+pub fn decrypt_from_identity(identity: &IdentityKeyPair, ciphertext: &[u8]) -> Result<Vec<u8>, JsError> {
+    decrypt_with_identity(identity, ciphertext).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encrypt_to_identity_round_trip() {
+        let bob = IdentityKeyPair::new();
+        let ciphertext = encrypt_to_identity_key(&bob.public_key_bytes(), b"join my group").unwrap();
+        assert_eq!(ciphertext[0], IDENTITY_ENCRYPTION_VERSION);
+        assert_eq!(decrypt_with_identity(&bob, &ciphertext).unwrap(), b"join my group");
+
+        // 其他身份無法解密
+        assert!(decrypt_with_identity(&IdentityKeyPair::new(), &ciphertext).is_err());
+
+        let mut tampered = ciphertext.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(decrypt_with_identity(&bob, &tampered).is_err());
+
+        let mut wrong_version = ciphertext;
+        wrong_version[0] = 9;
+        assert_eq!(
+            decrypt_with_identity(&bob, &wrong_version),
+            Err("Unsupported identity ciphertext version: 9".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_recipient_key() {
+        let mut identity_point = [0u8; 32];
+        identity_point[0] = 1;
+        assert!(encrypt_to_identity_key(&identity_point, b"secret").is_err());
+        assert!(encrypt_to_identity_key(&[1u8; 31], b"secret").is_err());
+    }
+}
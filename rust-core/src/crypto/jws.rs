@@ -0,0 +1,191 @@
+//! JWS / JWT 模組
+//!
+//! 以 Ed25519 身份金鑰產生 RFC 7515 compact 序列化的 JWS (RFC 8037 `alg: EdDSA`)，
+//! 讓客戶端以短效期的簽署 token 向 SafeTalk 伺服器驗證身份，
+//! 不必在 JS 端另外引入 JOSE 套件
+//!
+//! 驗證時只接受 `EdDSA`，`none` 或其他演算法一律拒絕
+
+use wasm_bindgen::prelude::*;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL};
+use serde_json::{json, Value};
+
+use super::keys::IdentityKeyPair;
+
+/// JWS 演算法名稱
+const JWS_ALG_EDDSA: &str = "EdDSA";
+
+/// 以身份金鑰簽署，輸出 compact JWS
+///
+/// `header` 必須是 JSON 物件，`alg` 會被設為 `EdDSA`
+pub fn jws_sign(identity: &IdentityKeyPair, header: Value, payload: &[u8]) -> Result<String, String> {
+    let Value::Object(mut header) = header else {
+        return Err("JWS header must be a JSON object".to_string());
+    };
+    header.insert("alg".to_string(), Value::String(JWS_ALG_EDDSA.to_string()));
+    let header = serde_json::to_vec(&header).map_err(|e| e.to_string())?;
+
+    let signing_input = format!("{}.{}", BASE64URL.encode(header), BASE64URL.encode(payload));
+    let signature = identity.sign(signing_input.as_bytes());
+    Ok(format!("{}.{}", signing_input, BASE64URL.encode(signature)))
+}
+
+/// 驗證 compact JWS，回傳 (protected header, payload)
+pub fn jws_verify(token: &str, public_key: &[u8]) -> Result<(Value, Vec<u8>), String> {
+    let mut parts = token.split('.');
+    let (Some(header_b64), Some(payload_b64), Some(signature_b64), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err("JWS must have three parts".to_string());
+    };
+
+    let decode = |part: &str, name: &str| {
+        BASE64URL
+            .decode(part)
+            .map_err(|e| format!("Invalid JWS {} encoding: {}", name, e))
+    };
+    let header: Value = serde_json::from_slice(&decode(header_b64, "header")?)
+        .map_err(|e| format!("Invalid JWS header: {}", e))?;
+    if header.get("alg").and_then(Value::as_str) != Some(JWS_ALG_EDDSA) {
+        return Err("JWS algorithm must be EdDSA".to_string());
+    }
+    // 不支援任何 critical 擴充標頭
+    if header.get("crit").is_some() {
+        return Err("Unsupported critical JWS header".to_string());
+    }
+
+    let signature = decode(signature_b64, "signature")?;
+    let signing_input = &token[..header_b64.len() + 1 + payload_b64.len()];
+    if !IdentityKeyPair::verify_signature(public_key, signing_input.as_bytes(), &signature) {
+        return Err("Invalid JWS signature".to_string());
+    }
+    Ok((header, decode(payload_b64, "payload")?))
+}
+
+/// 簽署 JWT (`typ: JWT`)，`claims_json` 必須是 JSON 物件
+pub fn jwt_sign(identity: &IdentityKeyPair, claims_json: &str) -> Result<String, String> {
+    let claims: Value = serde_json::from_str(claims_json).map_err(|e| format!("Invalid JWT claims: {}", e))?;
+    if !claims.is_object() {
+        return Err("JWT claims must be a JSON object".to_string());
+    }
+    jws_sign(identity, json!({ "typ": "JWT" }), claims_json.as_bytes())
+}
+
+/// 驗證 JWT 簽章與 `exp` / `nbf`，回傳 claims
+///
+/// `now` 為目前時間 (Unix 毫秒)；JWT 的時間欄位為 Unix 秒
+pub fn jwt_verify(token: &str, public_key: &[u8], now: u64) -> Result<Value, String> {
+    let (_, payload) = jws_verify(token, public_key)?;
+    let claims: Value = serde_json::from_slice(&payload).map_err(|e| format!("Invalid JWT claims: {}", e))?;
+    if !claims.is_object() {
+        return Err("JWT claims must be a JSON object".to_string());
+    }
+
+    let now_secs = now / 1000;
+    let numeric = |name: &str| match claims.get(name) {
+        None => Ok(None),
+        Some(value) => value
+            .as_u64()
+            .map(Some)
+            .ok_or_else(|| format!("JWT {} must be a NumericDate", name)),
+    };
+    if numeric("exp")?.is_some_and(|exp| now_secs >= exp) {
+        return Err("JWT has expired".to_string());
+    }
+    if numeric("nbf")?.is_some_and(|nbf| now_secs < nbf) {
+        return Err("JWT is not yet valid".to_string());
+    }
+    Ok(claims)
+}
+
+#[wasm_bindgen]
+impl IdentityKeyPair {
+    /// 簽署 JWT (alg = EdDSA)
+    #[wasm_bindgen(js_name = signJwt)]
+    pub fn sign_jwt(&self, claims_json: &str) -> Result<String, JsError> {
+        jwt_sign(self, claims_json).map_err(|e| JsError::new(&e))
+    }
+
+    /// 以 EdDSA 簽署任意 payload，輸出 compact JWS
+    #[wasm_bindgen(js_name = signJws)]
+    pub fn sign_jws(&self, payload: &[u8]) -> Result<String, JsError> {
+        jws_sign(self, json!({}), payload).map_err(|e| JsError::new(&e))
+    }
+}
+
+/// 驗證 compact JWS，回傳 payload
+#[wasm_bindgen(js_name = verifyJws)]
+pub fn verify_jws(token: &str, public_key: &[u8]) -> Result<Vec<u8>, JsError> {
+    jws_verify(token, public_key)
+        .map(|(_, payload)| payload)
+        .map_err(|e| JsError::new(&e))
+}
+
+/// 驗證 JWT (簽章、exp、nbf)，回傳 claims JSON
+#[wasm_bindgen(js_name = verifyJwt)]
+pub fn verify_jwt(token: &str, public_key: &[u8], now: u64) -> Result<String, JsError> {
+    jwt_verify(token, public_key, now)
+        .map(|claims| claims.to_string())
+        .map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc8037_example() {
+        // RFC 8037 Appendix A.4
+        let private_key = BASE64URL.decode("nWGxne_9WmC6hEr0kuwsxERJxWl7MmkZcDusAxyuf2A").unwrap();
+        let public_key = BASE64URL.decode("11qYAYKxCrfVS_7TyWQHOg7hcvPapiMlrwIaaPcHURo").unwrap();
+        let identity = IdentityKeyPair::from_bytes(&private_key).unwrap();
+        assert_eq!(identity.public_key_bytes(), public_key);
+
+        let expected = "eyJhbGciOiJFZERTQSJ9.RXhhbXBsZSBvZiBFZDI1NTE5IHNpZ25pbmc.\
+                        hgyY0il_MGCjP0JzlnLWG1PPOt7-09PGcvMg3AIbQR6dWbhijcNR4ki4iylGjg5BhVsPt9g7sVvpAr_MuM0KAg";
+        let token = identity.sign_jws(b"Example of Ed25519 signing").unwrap();
+        assert_eq!(token, expected);
+        assert_eq!(verify_jws(&token, &public_key).unwrap(), b"Example of Ed25519 signing");
+    }
+
+    #[test]
+    fn test_jwt_claims_and_expiry() {
+        let identity = IdentityKeyPair::new();
+        let token = identity
+            .sign_jwt(r#"{"sub":"alice","iat":1700000000,"nbf":1700000000,"exp":1700000300}"#)
+            .unwrap();
+        let claims = jwt_verify(&token, &identity.public_key_bytes(), 1_700_000_100_000).unwrap();
+        assert_eq!(claims["sub"], "alice");
+
+        assert_eq!(
+            jwt_verify(&token, &identity.public_key_bytes(), 1_700_000_300_000),
+            Err("JWT has expired".to_string())
+        );
+        assert_eq!(
+            jwt_verify(&token, &identity.public_key_bytes(), 1_699_999_999_000),
+            Err("JWT is not yet valid".to_string())
+        );
+        assert!(jwt_verify(&token, &IdentityKeyPair::new().public_key_bytes(), 1_700_000_100_000).is_err());
+        assert!(jwt_sign(&identity, "[1, 2]").is_err());
+    }
+
+    #[test]
+    fn test_rejects_other_algorithms() {
+        let identity = IdentityKeyPair::new();
+        let token = identity.sign_jwt(r#"{"sub":"alice"}"#).unwrap();
+        let (_, rest) = token.split_once('.').unwrap();
+
+        // alg: none
+        let none = format!("{}.{}", BASE64URL.encode(r#"{"alg":"none"}"#), rest);
+        assert_eq!(
+            jws_verify(&none, &identity.public_key_bytes()).unwrap_err(),
+            "JWS algorithm must be EdDSA"
+        );
+        // 換掉 payload
+        let (header, _) = token.split_once('.').unwrap();
+        let signature = token.rsplit('.').next().unwrap();
+        let forged = format!("{}.{}.{}", header, BASE64URL.encode(r#"{"sub":"mallory"}"#), signature);
+        assert!(jws_verify(&forged, &identity.public_key_bytes()).is_err());
+        assert!(jws_verify("a.b", &identity.public_key_bytes()).is_err());
+    }
+}
@@ -0,0 +1,82 @@
+//! 金鑰衍生模組
+//!
+//! 公開與核心內部相同的 HKDF-SHA256 實作，
+//! 應用層不必另外透過 WebCrypto 衍生 (參數細節不同時結果會不一致)
+
+use wasm_bindgen::prelude::*;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+/// HKDF-SHA256 可輸出的最大長度 (255 × 32)
+pub const HKDF_SHA256_MAX_OUTPUT: usize = 255 * 32;
+
+/// HKDF-Extract：從輸入金鑰材料取出 32 bytes PRK
+pub fn hkdf_sha256_extract(salt: &[u8], ikm: &[u8]) -> [u8; 32] {
+    let (prk, _) = Hkdf::<Sha256>::extract(Some(salt), ikm);
+    prk.into()
+}
+
+/// HKDF-Expand：以 PRK 與 info 展開出 `len` bytes
+pub fn hkdf_sha256_expand(prk: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, String> {
+    if len > HKDF_SHA256_MAX_OUTPUT {
+        return Err(format!("HKDF output length must be at most {} bytes", HKDF_SHA256_MAX_OUTPUT));
+    }
+    let hkdf = Hkdf::<Sha256>::from_prk(prk).map_err(|_| "PRK must be at least 32 bytes".to_string())?;
+    let mut okm = vec![0u8; len];
+    hkdf.expand(info, &mut okm).map_err(|e| format!("HKDF failed: {}", e))?;
+    Ok(okm)
+}
+
+/// HKDF-SHA256 (extract + expand)
+pub fn hkdf_sha256(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, String> {
+    hkdf_sha256_expand(&hkdf_sha256_extract(salt, ikm), info, len)
+}
+
+/// HKDF-SHA256 衍生 `len` bytes (空的 salt 等同 32 個 0x00)
+#[wasm_bindgen(js_name = hkdfDerive)]
+pub fn hkdf_derive(salt: &[u8], ikm: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, JsError> {
+    hkdf_sha256(salt, ikm, info, len).map_err(|e| JsError::new(&e))
+}
+
+/// HKDF-Extract
+#[wasm_bindgen(js_name = hkdfExtract)]
+pub fn hkdf_extract(salt: &[u8], ikm: &[u8]) -> Vec<u8> {
+    hkdf_sha256_extract(salt, ikm).to_vec()
+}
+
+/// HKDF-Expand
+#[wasm_bindgen(js_name = hkdfExpand)]
+pub fn hkdf_expand(prk: &[u8], info: &[u8], len: usize) -> Result<Vec<u8>, JsError> {
+    hkdf_sha256_expand(prk, info, len).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::to_hex;
+
+    #[test]
+    fn test_rfc5869_case_1() {
+        let ikm = [0x0b; 22];
+        let salt: Vec<u8> = (0x00..=0x0c).collect();
+        let info: Vec<u8> = (0xf0..=0xf9).collect();
+
+        let prk = hkdf_sha256_extract(&salt, &ikm);
+        assert_eq!(to_hex(&prk), "077709362c2e32df0ddc3f0dc47bba6390b6c73bb50f9c3122ec844ad7c2b3e5");
+        let okm = hkdf_sha256(&salt, &ikm, &info, 42).unwrap();
+        assert_eq!(
+            to_hex(&okm),
+            "3cb25f25faacd57a90434f64d0362f2a2d2d0a90cf1a5a4c5db02d56ecc4c5bf34007208d5b887185865"
+        );
+        assert_eq!(hkdf_sha256_expand(&prk, &info, 42).unwrap(), okm);
+    }
+
+    #[test]
+    fn test_hkdf_limits() {
+        assert_eq!(hkdf_sha256(b"", b"ikm", b"", HKDF_SHA256_MAX_OUTPUT).unwrap().len(), HKDF_SHA256_MAX_OUTPUT);
+        assert!(hkdf_sha256(b"", b"ikm", b"", HKDF_SHA256_MAX_OUTPUT + 1).is_err());
+        assert!(hkdf_sha256_expand(&[0u8; 16], b"", 32).is_err());
+        // 空的 salt 與 32 個 0x00 相同 (RFC 5869 §2.2)
+        assert_eq!(hkdf_sha256_extract(b"", b"ikm"), hkdf_sha256_extract(&[0u8; 32], b"ikm"));
+    }
+}
@@ -0,0 +1,309 @@
+//! 金鑰更新公告模組
+//!
+//! 客戶端更換簽署預金鑰、新增裝置或輪替身份金鑰後，
+//! 透過既有會話向聯絡人廣播以身份金鑰簽署的更新公告，
+//! 對方的 `SessionManager` 據此更新快取的 bundle，
+//! 不必等到下次向伺服器重新取得才發現資料已過時
+//!
+//! 每則公告帶有遞增序號，重放舊公告會被拒絕
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::cross_signing::DeviceCertificate;
+use super::keys::IdentityKeyPair;
+use super::ratchet::{RatchetMessage, RatchetSession};
+use super::revocation::{RevocationCertificate, RevocationReason};
+
+const KEY_UPDATE_CONTEXT: &[u8] = b"SafeTalk_KeyUpdate_v1";
+
+/// 金鑰更新內容
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KeyUpdateContent {
+    /// 裝置換了新的簽署預金鑰
+    SignedPreKey {
+        device_id: u32,
+        key_id: u32,
+        public_key: Vec<u8>,
+        /// 身份金鑰對預金鑰公鑰的簽章
+        signature: Vec<u8>,
+        timestamp: u64,
+    },
+    /// 新增裝置
+    NewDevice { certificate: DeviceCertificate },
+    /// 身份金鑰輪替：撤銷目前的身份並指向後繼金鑰
+    IdentityRotation { revocation: RevocationCertificate },
+}
+
+impl KeyUpdateContent {
+    /// 內容類型名稱
+    pub fn kind(&self) -> &'static str {
+        match self {
+            KeyUpdateContent::SignedPreKey { .. } => "signed_pre_key",
+            KeyUpdateContent::NewDevice { .. } => "new_device",
+            KeyUpdateContent::IdentityRotation { .. } => "identity_rotation",
+        }
+    }
+
+    /// 檢查內容本身是否由 `identity_key` 簽發
+    fn verify_for(&self, identity_key: &[u8]) -> Result<(), String> {
+        match self {
+            KeyUpdateContent::SignedPreKey { public_key, signature, .. } => {
+                if !IdentityKeyPair::verify_signature(identity_key, public_key, signature) {
+                    return Err("Invalid signed prekey signature".to_string());
+                }
+                Ok(())
+            }
+            KeyUpdateContent::NewDevice { certificate } => certificate.verify_issuer(identity_key),
+            KeyUpdateContent::IdentityRotation { revocation } => {
+                if revocation.identity_key() != identity_key {
+                    return Err("Revocation is for a different identity".to_string());
+                }
+                if revocation.successor_key().is_none() {
+                    return Err("Identity rotation requires a successor key".to_string());
+                }
+                revocation.verify_signature()
+            }
+        }
+    }
+}
+
+/// 簽署的金鑰更新公告
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct KeyUpdate {
+    /// 發布者身份公鑰
+    identity_key: Vec<u8>,
+    /// 遞增序號
+    sequence: u64,
+    /// 發布時間 (Unix 毫秒)
+    issued_at: u64,
+    /// 更新內容
+    content: KeyUpdateContent,
+    /// 身份金鑰對以上欄位的簽章
+    signature: Vec<u8>,
+}
+
+impl KeyUpdate {
+    fn signing_payload(
+        identity_key: &[u8],
+        sequence: u64,
+        issued_at: u64,
+        content: &KeyUpdateContent,
+    ) -> Result<Vec<u8>, String> {
+        let content = serde_json::to_vec(content).map_err(|e| e.to_string())?;
+        let mut payload = KEY_UPDATE_CONTEXT.to_vec();
+        for field in [
+            identity_key,
+            &sequence.to_be_bytes(),
+            &issued_at.to_be_bytes(),
+            &content,
+        ] {
+            payload.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            payload.extend_from_slice(field);
+        }
+        Ok(payload)
+    }
+
+    /// 以身份金鑰簽署更新公告
+    pub fn create(
+        identity: &IdentityKeyPair,
+        sequence: u64,
+        issued_at: u64,
+        content: KeyUpdateContent,
+    ) -> Result<Self, String> {
+        let identity_key = identity.public_key_bytes();
+        content.verify_for(&identity_key)?;
+        let signature = identity.sign(&Self::signing_payload(&identity_key, sequence, issued_at, &content)?);
+        Ok(Self {
+            identity_key,
+            sequence,
+            issued_at,
+            content,
+            signature,
+        })
+    }
+
+    /// 驗證公告簽章與內容
+    pub fn verify_signature(&self) -> Result<(), String> {
+        let payload = Self::signing_payload(&self.identity_key, self.sequence, self.issued_at, &self.content)?;
+        if !IdentityKeyPair::verify_signature(&self.identity_key, &payload, &self.signature) {
+            return Err("Invalid key update signature".to_string());
+        }
+        self.content.verify_for(&self.identity_key)
+    }
+
+    /// 更新內容
+    pub fn content(&self) -> &KeyUpdateContent {
+        &self.content
+    }
+}
+
+#[wasm_bindgen]
+impl KeyUpdate {
+    /// 公告新的簽署預金鑰
+    #[wasm_bindgen(js_name = signedPreKey)]
+    pub fn signed_pre_key(
+        identity: &IdentityKeyPair,
+        sequence: u64,
+        issued_at: u64,
+        device_id: u32,
+        key_id: u32,
+        public_key: &[u8],
+        signature: &[u8],
+    ) -> Result<KeyUpdate, JsError> {
+        let content = KeyUpdateContent::SignedPreKey {
+            device_id,
+            key_id,
+            public_key: public_key.to_vec(),
+            signature: signature.to_vec(),
+            timestamp: issued_at,
+        };
+        Self::create(identity, sequence, issued_at, content).map_err(|e| JsError::new(&e))
+    }
+
+    /// 公告新裝置
+    #[wasm_bindgen(js_name = newDevice)]
+    pub fn new_device(
+        identity: &IdentityKeyPair,
+        sequence: u64,
+        issued_at: u64,
+        certificate: &DeviceCertificate,
+    ) -> Result<KeyUpdate, JsError> {
+        let content = KeyUpdateContent::NewDevice { certificate: certificate.clone() };
+        Self::create(identity, sequence, issued_at, content).map_err(|e| JsError::new(&e))
+    }
+
+    /// 公告身份輪替 (以舊身份簽署，指向後繼金鑰)
+    #[wasm_bindgen(js_name = identityRotation)]
+    pub fn identity_rotation(
+        identity: &IdentityKeyPair,
+        sequence: u64,
+        issued_at: u64,
+        successor_key: &[u8],
+    ) -> Result<KeyUpdate, JsError> {
+        let revocation =
+            RevocationCertificate::create(identity, RevocationReason::Superseded, issued_at, Some(successor_key));
+        let content = KeyUpdateContent::IdentityRotation { revocation };
+        Self::create(identity, sequence, issued_at, content).map_err(|e| JsError::new(&e))
+    }
+
+    /// 簽章與內容是否有效
+    #[wasm_bindgen(js_name = verify)]
+    pub fn verify(&self) -> bool {
+        self.verify_signature().is_ok()
+    }
+
+    /// 發布者身份公鑰
+    #[wasm_bindgen(getter, js_name = identityKey)]
+    pub fn identity_key(&self) -> Vec<u8> {
+        self.identity_key.clone()
+    }
+
+    /// 序號
+    #[wasm_bindgen(getter)]
+    pub fn sequence(&self) -> u64 {
+        self.sequence
+    }
+
+    /// 發布時間
+    #[wasm_bindgen(getter, js_name = issuedAt)]
+    pub fn issued_at(&self) -> u64 {
+        self.issued_at
+    }
+
+    /// 內容類型 (`signed_pre_key` / `new_device` / `identity_rotation`)
+    #[wasm_bindgen(getter, js_name = contentType)]
+    pub fn content_type(&self) -> String {
+        self.content.kind().to_string()
+    }
+
+    /// 身份輪替公告中的撤銷憑證 (可交給信任儲存的 `applyRevocation`)
+    #[wasm_bindgen(getter)]
+    pub fn revocation(&self) -> Option<RevocationCertificate> {
+        match &self.content {
+            KeyUpdateContent::IdentityRotation { revocation } => Some(revocation.clone()),
+            _ => None,
+        }
+    }
+
+    /// 序列化為 JSON
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 從 JSON 反序列化
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<KeyUpdate, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 以既有會話加密，廣播給聯絡人
+    #[wasm_bindgen(js_name = encryptForSession)]
+    pub fn encrypt_for_session(&self, session: &mut RatchetSession) -> Result<RatchetMessage, JsError> {
+        let plaintext = serde_json::to_vec(self).map_err(|e| JsError::new(&e.to_string()))?;
+        session.encrypt(&plaintext)
+    }
+
+    /// 以會話解密收到的公告
+    #[wasm_bindgen(js_name = decryptFromSession)]
+    pub fn decrypt_from_session(
+        session: &mut RatchetSession,
+        message: &RatchetMessage,
+    ) -> Result<KeyUpdate, JsError> {
+        let plaintext = session.decrypt(message)?;
+        serde_json::from_slice(&plaintext).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::keys::X25519KeyPair;
+
+    #[test]
+    fn test_key_update_signatures() {
+        let identity = IdentityKeyPair::new();
+        let prekey = X25519KeyPair::new();
+        let prekey_signature = identity.sign(&prekey.public_key_bytes());
+
+        let update = KeyUpdate::signed_pre_key(&identity, 1, 1000, 1, 7, &prekey.public_key_bytes(), &prekey_signature)
+            .unwrap();
+        assert!(update.verify());
+        assert_eq!(update.content_type(), "signed_pre_key");
+
+        let restored = KeyUpdate::from_json(&update.to_json().unwrap()).unwrap();
+        assert_eq!(restored, update);
+
+        // 竄改序號
+        let mut forged = update;
+        forged.sequence = 2;
+        assert!(!forged.verify());
+
+        // 預金鑰簽章不是此身份簽的
+        let other = IdentityKeyPair::new();
+        let content = KeyUpdateContent::SignedPreKey {
+            device_id: 1,
+            key_id: 8,
+            public_key: prekey.public_key_bytes(),
+            signature: other.sign(&prekey.public_key_bytes()),
+            timestamp: 1000,
+        };
+        assert!(KeyUpdate::create(&identity, 3, 1000, content).is_err());
+    }
+
+    #[test]
+    fn test_key_update_over_session() {
+        let identity = IdentityKeyPair::new();
+        let successor = IdentityKeyPair::new();
+        let update = KeyUpdate::identity_rotation(&identity, 5, 2000, &successor.public_key_bytes()).unwrap();
+
+        let (mut alice, mut bob) = RatchetSession::test_pair([3u8; 32]);
+        let message = update.encrypt_for_session(&mut alice).unwrap();
+        let received = KeyUpdate::decrypt_from_session(&mut bob, &message).unwrap();
+        assert_eq!(received, update);
+        assert!(received.verify());
+    }
+}
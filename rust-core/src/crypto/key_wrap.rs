@@ -0,0 +1,241 @@
+//! 金鑰包裝模組 (KEK/DEK)
+//!
+//! 以 AES-256 Key Wrap (RFC 3394) 將附件金鑰、儲存金鑰、備份金鑰等
+//! 包裝在主金鑰之下，儲存與備份子系統共用同一個金鑰包裝原語
+//!
+//! 每種用途各自以 HKDF 從主金鑰衍生 KEK，被包裝的金鑰無法被當成其他用途解開
+//!
+//! `WrappedKey` 格式：version (1) || kind (1) || AES-KW 密文 (金鑰長度 + 8)
+
+use wasm_bindgen::prelude::*;
+use aes::{
+    cipher::{generic_array::GenericArray, BlockDecrypt, BlockEncrypt, KeyInit},
+    Aes256,
+};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use super::constant_time::constant_time_eq;
+
+/// RFC 3394 預設 IV
+const KEY_WRAP_IV: [u8; 8] = [0xa6; 8];
+const INFO_KEY_WRAP: &[u8] = b"SafeTalk_KeyWrap_";
+const KEK_SIZE: usize = 32;
+
+/// 目前的 `WrappedKey` 格式版本
+pub const WRAPPED_KEY_VERSION: u8 = 1;
+
+/// 被包裝的金鑰用途
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WrappedKeyKind {
+    AttachmentKey = 1,
+    StorageKey = 2,
+    BackupKey = 3,
+}
+
+impl WrappedKeyKind {
+    fn from_byte(byte: u8) -> Result<Self, String> {
+        match byte {
+            1 => Ok(WrappedKeyKind::AttachmentKey),
+            2 => Ok(WrappedKeyKind::StorageKey),
+            3 => Ok(WrappedKeyKind::BackupKey),
+            _ => Err(format!("Unknown wrapped key kind: {}", byte)),
+        }
+    }
+
+    /// 此用途的 KEK
+    fn kek(self, master_key: &[u8]) -> Result<[u8; KEK_SIZE], String> {
+        if master_key.len() != KEK_SIZE {
+            return Err(format!("Master key must be {} bytes, got {}", KEK_SIZE, master_key.len()));
+        }
+        let mut kek = [0u8; KEK_SIZE];
+        Hkdf::<Sha256>::new(None, master_key)
+            .expand(&[INFO_KEY_WRAP, &[self as u8]].concat(), &mut kek)
+            .map_err(|e| format!("HKDF failed: {}", e))?;
+        Ok(kek)
+    }
+}
+
+/// AES-256 Key Wrap (RFC 3394)
+///
+/// 被包裝的金鑰長度需為 8 的倍數且至少 16 bytes
+pub fn aes_key_wrap(kek: &[u8], key: &[u8]) -> Result<Vec<u8>, String> {
+    if key.len() < 16 || !key.len().is_multiple_of(8) {
+        return Err("Wrapped key must be a multiple of 8 bytes and at least 16 bytes".to_string());
+    }
+    let cipher = Aes256::new_from_slice(kek).map_err(|_| format!("KEK must be {} bytes", KEK_SIZE))?;
+
+    let n = key.len() / 8;
+    let mut a = KEY_WRAP_IV;
+    let mut r: Vec<[u8; 8]> = key.chunks(8).map(|c| c.try_into().unwrap()).collect();
+    for j in 0..6 {
+        for (i, block) in r.iter_mut().enumerate() {
+            let mut b = GenericArray::clone_from_slice(&[a, *block].concat());
+            cipher.encrypt_block(&mut b);
+            let t = (n * j + i + 1) as u64;
+            a.copy_from_slice(&b[..8]);
+            a.iter_mut().zip(t.to_be_bytes()).for_each(|(x, y)| *x ^= y);
+            block.copy_from_slice(&b[8..]);
+        }
+    }
+
+    let mut wrapped = a.to_vec();
+    r.iter().for_each(|block| wrapped.extend_from_slice(block));
+    Ok(wrapped)
+}
+
+/// AES-256 Key Unwrap (RFC 3394)，完整性檢查失敗時回傳錯誤
+pub fn aes_key_unwrap(kek: &[u8], wrapped: &[u8]) -> Result<Vec<u8>, String> {
+    if wrapped.len() < 24 || !wrapped.len().is_multiple_of(8) {
+        return Err("Invalid wrapped key length".to_string());
+    }
+    let cipher = Aes256::new_from_slice(kek).map_err(|_| format!("KEK must be {} bytes", KEK_SIZE))?;
+
+    let n = wrapped.len() / 8 - 1;
+    let mut a: [u8; 8] = wrapped[..8].try_into().unwrap();
+    let mut r: Vec<[u8; 8]> = wrapped[8..].chunks(8).map(|c| c.try_into().unwrap()).collect();
+    for j in (0..6).rev() {
+        for (i, block) in r.iter_mut().enumerate().rev() {
+            let t = (n * j + i + 1) as u64;
+            a.iter_mut().zip(t.to_be_bytes()).for_each(|(x, y)| *x ^= y);
+            let mut b = GenericArray::clone_from_slice(&[a, *block].concat());
+            cipher.decrypt_block(&mut b);
+            a.copy_from_slice(&b[..8]);
+            block.copy_from_slice(&b[8..]);
+        }
+    }
+
+    if !constant_time_eq(&a, &KEY_WRAP_IV) {
+        return Err("Key unwrap integrity check failed".to_string());
+    }
+    Ok(r.concat())
+}
+
+/// 以主金鑰包裝的金鑰
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WrappedKey {
+    kind: WrappedKeyKind,
+    wrapped: Vec<u8>,
+}
+
+impl WrappedKey {
+    /// 包裝金鑰 (Rust 端使用)
+    pub fn seal(master_key: &[u8], kind: WrappedKeyKind, key: &[u8]) -> Result<Self, String> {
+        Ok(Self {
+            kind,
+            wrapped: aes_key_wrap(&kind.kek(master_key)?, key)?,
+        })
+    }
+
+    /// 解開金鑰 (Rust 端使用)
+    pub fn open(&self, master_key: &[u8]) -> Result<Vec<u8>, String> {
+        aes_key_unwrap(&self.kind.kek(master_key)?, &self.wrapped)
+    }
+
+    /// 從位元組還原 (Rust 端使用)
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < 2 {
+            return Err("Wrapped key too short".to_string());
+        }
+        if bytes[0] != WRAPPED_KEY_VERSION {
+            return Err(format!("Unsupported wrapped key version: {}", bytes[0]));
+        }
+        Ok(Self {
+            kind: WrappedKeyKind::from_byte(bytes[1])?,
+            wrapped: bytes[2..].to_vec(),
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl WrappedKey {
+    /// 以主金鑰包裝金鑰
+    #[wasm_bindgen(js_name = wrap)]
+    pub fn wrap(master_key: &[u8], kind: WrappedKeyKind, key: &[u8]) -> Result<WrappedKey, JsError> {
+        Self::seal(master_key, kind, key).map_err(|e| JsError::new(&e))
+    }
+
+    /// 以主金鑰解開金鑰
+    #[wasm_bindgen(js_name = unwrap)]
+    pub fn unwrap_key(&self, master_key: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.open(master_key).map_err(|e| JsError::new(&e))
+    }
+
+    /// 金鑰用途
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> WrappedKeyKind {
+        self.kind
+    }
+
+    /// 序列化
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![WRAPPED_KEY_VERSION, self.kind as u8];
+        bytes.extend_from_slice(&self.wrapped);
+        bytes
+    }
+
+    /// 從位元組還原
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<WrappedKey, JsError> {
+        Self::from_slice(bytes).map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::to_hex;
+
+    #[test]
+    fn test_rfc3394_vectors() {
+        let kek: Vec<u8> = (0..32).collect();
+
+        // RFC 3394 §4.3：以 256-bit KEK 包裝 128-bit 金鑰
+        let key = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd, 0xee, 0xff];
+        let wrapped = aes_key_wrap(&kek, &key).unwrap();
+        assert_eq!(to_hex(&wrapped), "64e8c3f9ce0f5ba263e9777905818a2a93c8191e7d6e8ae7");
+        assert_eq!(aes_key_unwrap(&kek, &wrapped).unwrap(), key);
+
+        // RFC 3394 §4.6：以 256-bit KEK 包裝 256-bit 金鑰
+        let key = [&key[..], &(0..16).collect::<Vec<u8>>()].concat();
+        let wrapped = aes_key_wrap(&kek, &key).unwrap();
+        assert_eq!(
+            to_hex(&wrapped),
+            "28c9f404c4b810f4cbccb35cfb87f8263f5786e2d80ed326cbc7f0e71a99f43bfb988b9b7a02dd21"
+        );
+        assert_eq!(aes_key_unwrap(&kek, &wrapped).unwrap(), key);
+
+        let mut tampered = wrapped.clone();
+        tampered[10] ^= 1;
+        assert!(aes_key_unwrap(&kek, &tampered).is_err());
+        assert!(aes_key_wrap(&kek, &[0u8; 20]).is_err());
+    }
+
+    #[test]
+    fn test_wrapped_key_round_trip() {
+        let master = [7u8; 32];
+        let attachment_key = [1u8; 32];
+        let wrapped = WrappedKey::seal(&master, WrappedKeyKind::AttachmentKey, &attachment_key).unwrap();
+
+        let bytes = wrapped.to_bytes();
+        assert_eq!(bytes.len(), 2 + 40);
+        let restored = WrappedKey::from_slice(&bytes).unwrap();
+        assert_eq!(restored.kind(), WrappedKeyKind::AttachmentKey);
+        assert_eq!(restored.open(&master).unwrap(), attachment_key);
+        assert!(restored.open(&[8u8; 32]).is_err());
+
+        // 改標成其他用途無法解開
+        let mut relabeled = bytes.clone();
+        relabeled[1] = WrappedKeyKind::BackupKey as u8;
+        assert!(WrappedKey::from_slice(&relabeled).unwrap().open(&master).is_err());
+
+        relabeled[0] = 2;
+        assert_eq!(
+            WrappedKey::from_slice(&relabeled),
+            Err("Unsupported wrapped key version: 2".to_string())
+        );
+    }
+}
@@ -3,12 +3,14 @@
 //! 提供 X25519 (金鑰交換) 和 Ed25519 (簽章) 金鑰對的生成與管理
 
 use wasm_bindgen::prelude::*;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret as X25519SecretKey};
 use ed25519_dalek::{SigningKey, VerifyingKey, Signature, Signer, Verifier};
 use rand::rngs::OsRng;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
+use super::secret::SecretBytes;
+
 /// 身份金鑰對 (Ed25519)
 /// 用於簽章和身份驗證，長期使用
 #[wasm_bindgen]
@@ -51,9 +53,11 @@ impl IdentityKeyPair {
     }
 
     /// 取得私鑰位元組 (敏感！僅用於備份)
+    ///
+    /// 以 `SecretBytes` 回傳，需明確呼叫 `expose()` 才能取得內容
     #[wasm_bindgen(js_name = privateKeyBytes)]
-    pub fn private_key_bytes(&self) -> Vec<u8> {
-        self.signing_key.to_bytes().to_vec()
+    pub fn private_key_bytes(&self) -> SecretBytes {
+        self.signing_key.to_bytes().into()
     }
 
     /// 簽署訊息
@@ -84,6 +88,50 @@ impl IdentityKeyPair {
     }
 }
 
+impl IdentityKeyPair {
+    /// 取得內部簽章金鑰 (crate 內部使用)
+    pub(crate) fn signing_key(&self) -> &SigningKey {
+        &self.signing_key
+    }
+
+    /// 從 32 bytes 私鑰建立 (crate 內部使用)
+    pub(crate) fn from_signing_key_bytes(bytes: &[u8; 32]) -> Self {
+        Self { signing_key: SigningKey::from_bytes(bytes) }
+    }
+
+    /// 批次驗證多個簽章 (Rust 端使用)
+    ///
+    /// 所有簽章皆有效才回傳 true；長度不一致或任何公鑰、簽章格式錯誤也回傳 false。
+    /// 批次驗證只能判斷整批是否有效，若需找出無效項目請逐一呼叫 [`IdentityKeyPair::verify_signature`]
+    pub fn verify_batch(public_keys: &[&[u8]], messages: &[&[u8]], signatures: &[&[u8]]) -> bool {
+        if public_keys.len() != messages.len() || public_keys.len() != signatures.len() {
+            return false;
+        }
+
+        let mut verifying_keys = Vec::with_capacity(public_keys.len());
+        for key in public_keys {
+            let pk_bytes: [u8; 32] = match (*key).try_into() {
+                Ok(bytes) => bytes,
+                Err(_) => return false,
+            };
+            match VerifyingKey::from_bytes(&pk_bytes) {
+                Ok(k) => verifying_keys.push(k),
+                Err(_) => return false,
+            }
+        }
+
+        let mut parsed = Vec::with_capacity(signatures.len());
+        for signature in signatures {
+            match Signature::from_slice(signature) {
+                Ok(sig) => parsed.push(sig),
+                Err(_) => return false,
+            }
+        }
+
+        ed25519_dalek::verify_batch(messages, &parsed, &verifying_keys).is_ok()
+    }
+}
+
 impl Default for IdentityKeyPair {
     fn default() -> Self {
         Self::new()
@@ -93,6 +141,7 @@ impl Default for IdentityKeyPair {
 /// X25519 金鑰對
 /// 用於 Diffie-Hellman 金鑰交換
 #[wasm_bindgen]
+#[derive(Clone)]
 pub struct X25519KeyPair {
     secret: X25519SecretKey,
     public: X25519PublicKey,
@@ -134,9 +183,11 @@ impl X25519KeyPair {
     }
 
     /// 取得私鑰位元組 (敏感！)
+    ///
+    /// 以 `SecretBytes` 回傳，需明確呼叫 `expose()` 才能取得內容
     #[wasm_bindgen(js_name = privateKeyBytes)]
-    pub fn private_key_bytes(&self) -> Vec<u8> {
-        self.secret.to_bytes().to_vec()
+    pub fn private_key_bytes(&self) -> SecretBytes {
+        self.secret.to_bytes().into()
     }
 
     /// 執行 Diffie-Hellman 金鑰交換
@@ -153,12 +204,58 @@ impl X25519KeyPair {
     }
 }
 
+impl X25519KeyPair {
+    /// 從 32 bytes 私鑰建立 (crate 內部使用)
+    pub(crate) fn from_secret_bytes(bytes: [u8; 32]) -> Self {
+        let secret = X25519SecretKey::from(bytes);
+        let public = X25519PublicKey::from(&secret);
+        Self { secret, public }
+    }
+
+    /// 執行 Diffie-Hellman 金鑰交換 (Rust 端使用)
+    pub fn shared_secret(&self, their_public: &[u8]) -> Result<[u8; 32], String> {
+        if their_public.len() != 32 {
+            return Err("Public key must be 32 bytes".to_string());
+        }
+        let mut pk_bytes = [0u8; 32];
+        pk_bytes.copy_from_slice(their_public);
+        let shared = self.secret.diffie_hellman(&X25519PublicKey::from(pk_bytes));
+        Ok(*shared.as_bytes())
+    }
+}
+
 impl Default for X25519KeyPair {
     fn default() -> Self {
         Self::new()
     }
 }
 
+// 金鑰對只序列化 32 bytes 私鑰 (敏感！)，公鑰在還原時重新計算
+
+impl Serialize for IdentityKeyPair {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.signing_key.to_bytes().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for IdentityKeyPair {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::from_signing_key_bytes(&<[u8; 32]>::deserialize(deserializer)?))
+    }
+}
+
+impl Serialize for X25519KeyPair {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.secret.to_bytes().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for X25519KeyPair {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(Self::from_secret_bytes(<[u8; 32]>::deserialize(deserializer)?))
+    }
+}
+
 /// 預簽署金鑰 (Signed PreKey)
 /// 中期使用的 X25519 金鑰，附帶身份金鑰簽章
 #[derive(Serialize, Deserialize, Clone)]
@@ -169,6 +266,65 @@ pub struct SignedPreKey {
     pub timestamp: u64,
 }
 
+/// 簽署預金鑰的本地記錄 (含私鑰)
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SignedPreKeyRecord {
+    key_id: u32,
+    keypair: X25519KeyPair,
+    signature: Vec<u8>,
+    timestamp: u64,
+}
+
+impl SignedPreKeyRecord {
+    /// 公開部分 (放入 PreKeyBundle)
+    pub fn public(&self) -> SignedPreKey {
+        SignedPreKey {
+            key_id: self.key_id,
+            public_key: self.keypair.public_key_bytes(),
+            signature: self.signature.clone(),
+            timestamp: self.timestamp,
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl SignedPreKeyRecord {
+    /// 產生新的簽署預金鑰並以身份金鑰簽署公鑰
+    #[wasm_bindgen(constructor)]
+    pub fn generate(identity: &IdentityKeyPair, key_id: u32, timestamp: u64) -> Self {
+        let keypair = X25519KeyPair::new();
+        let signature = identity.sign(&keypair.public_key_bytes());
+        Self { key_id, keypair, signature, timestamp }
+    }
+
+    #[wasm_bindgen(getter, js_name = keyId)]
+    pub fn key_id(&self) -> u32 {
+        self.key_id
+    }
+
+    /// 金鑰對
+    #[wasm_bindgen(getter, js_name = keyPair)]
+    pub fn key_pair(&self) -> X25519KeyPair {
+        self.keypair.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = publicKey)]
+    pub fn public_key(&self) -> Vec<u8> {
+        self.keypair.public_key_bytes()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn signature(&self) -> Vec<u8> {
+        self.signature.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
+
 /// 一次性預金鑰 (One-Time PreKey)
 /// 用完即丟的 X25519 金鑰
 #[derive(Serialize, Deserialize, Clone)]
@@ -178,15 +334,25 @@ pub struct OneTimePreKey {
 }
 
 /// 金鑰組合 (用於公開發布)
-/// 通過 JSON 序列化傳遞給 JavaScript
+/// 可直接作為 JavaScript 類別使用，或通過 JSON / 位元組序列化傳遞
+#[wasm_bindgen]
 #[derive(Serialize, Deserialize, Clone)]
 pub struct PreKeyBundle {
     /// 身份公鑰 (Ed25519)
+    #[wasm_bindgen(skip)]
     pub identity_key: Vec<u8>,
     /// 簽署過的預金鑰
+    #[wasm_bindgen(skip)]
     pub signed_pre_key: SignedPreKey,
     /// 一次性預金鑰 (可選)
+    #[wasm_bindgen(skip)]
     pub one_time_pre_key: Option<OneTimePreKey>,
+    /// 身份金鑰對整個 bundle 的簽章 (可選)
+    ///
+    /// 防止金鑰伺服器混搭不同時期的組成部分
+    #[wasm_bindgen(skip)]
+    #[serde(default)]
+    pub bundle_signature: Option<Vec<u8>>,
 }
 
 impl PreKeyBundle {
@@ -200,7 +366,47 @@ impl PreKeyBundle {
             identity_key,
             signed_pre_key,
             one_time_pre_key,
+            bundle_signature: None,
+        }
+    }
+
+    /// 產生整個 bundle 的簽章內容
+    ///
+    /// 格式：網域前綴 || 各欄位 (長度前綴)，不包含 bundle 簽章本身。
+    /// 任何欄位的變動 (包括替換一次性預金鑰) 都會使簽章失效。
+    pub fn signing_payload(&self) -> Vec<u8> {
+        fn put(out: &mut Vec<u8>, field: &[u8]) {
+            out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            out.extend_from_slice(field);
+        }
+
+        let spk = &self.signed_pre_key;
+        let mut out = BUNDLE_SIGNATURE_CONTEXT.to_vec();
+        put(&mut out, &self.identity_key);
+        put(&mut out, &spk.key_id.to_be_bytes());
+        put(&mut out, &spk.public_key);
+        put(&mut out, &spk.signature);
+        put(&mut out, &spk.timestamp.to_be_bytes());
+        match &self.one_time_pre_key {
+            Some(otpk) => {
+                out.push(1);
+                put(&mut out, &otpk.key_id.to_be_bytes());
+                put(&mut out, &otpk.public_key);
+            }
+            None => out.push(0),
+        }
+        out
+    }
+
+    /// 使用身份金鑰簽署整個 bundle
+    ///
+    /// 身份金鑰必須與 bundle 中的身份公鑰相符
+    pub fn sign_with(&mut self, identity: &IdentityKeyPair) -> Result<(), String> {
+        if identity.public_key_bytes() != self.identity_key {
+            return Err("Identity key does not match bundle identity key".to_string());
         }
+        self.bundle_signature = Some(identity.sign(&self.signing_payload()));
+        Ok(())
     }
 
     /// 序列化為 JSON
@@ -213,10 +419,352 @@ impl PreKeyBundle {
         serde_json::from_str(json).map_err(|e| e.to_string())
     }
 
+    /// 使用指定策略驗證 PreKeyBundle
+    ///
+    /// 檢查項目：
+    /// - 各金鑰與簽章長度
+    /// - 身份公鑰是否為合法的 Ed25519 點
+    /// - Signed PreKey 簽章是否由身份金鑰簽署
+    /// - Signed PreKey 時間戳是否過期或來自未來
+    ///
+    /// 不會在第一個錯誤就停止，所有失敗項目都會記錄在報告中
+    pub fn validate_with_policy(
+        &self,
+        now: u64,
+        policy: &BundleValidationPolicy,
+    ) -> BundleValidationReport {
+        let mut errors = Vec::new();
+
+        let identity_ok = if self.identity_key.len() != 32 {
+            errors.push(BundleValidationError::InvalidIdentityKeyLength(self.identity_key.len()));
+            false
+        } else {
+            let mut pk_bytes = [0u8; 32];
+            pk_bytes.copy_from_slice(&self.identity_key);
+            if VerifyingKey::from_bytes(&pk_bytes).is_err() {
+                errors.push(BundleValidationError::InvalidIdentityKey);
+                false
+            } else {
+                true
+            }
+        };
+
+        let spk = &self.signed_pre_key;
+        let spk_ok = spk.public_key.len() == 32;
+        if !spk_ok {
+            errors.push(BundleValidationError::InvalidSignedPreKeyLength(spk.public_key.len()));
+        }
+
+        let signature_ok = spk.signature.len() == 64;
+        if !signature_ok {
+            errors.push(BundleValidationError::InvalidSignatureLength(spk.signature.len()));
+        }
+
+        // 只有在長度都正確時才驗證簽章，避免重複回報
+        if identity_ok && spk_ok && signature_ok
+            && !IdentityKeyPair::verify_signature(&self.identity_key, &spk.public_key, &spk.signature)
+        {
+            errors.push(BundleValidationError::InvalidSignature);
+        }
+
+        if spk.timestamp > now.saturating_add(policy.max_clock_skew_ms) {
+            errors.push(BundleValidationError::TimestampInFuture {
+                timestamp: spk.timestamp,
+                now,
+            });
+        } else {
+            let age_ms = now.saturating_sub(spk.timestamp);
+            if age_ms > policy.max_age_ms {
+                errors.push(BundleValidationError::SignedPreKeyExpired {
+                    age_ms,
+                    max_age_ms: policy.max_age_ms,
+                });
+            }
+        }
+
+        if let Some(otpk) = &self.one_time_pre_key {
+            if otpk.public_key.len() != 32 {
+                errors.push(BundleValidationError::InvalidOneTimePreKeyLength(otpk.public_key.len()));
+            }
+        }
+
+        match &self.bundle_signature {
+            Some(_) if identity_ok && !self.verify_bundle_signature() => {
+                errors.push(BundleValidationError::InvalidBundleSignature);
+            }
+            None if policy.require_bundle_signature => {
+                errors.push(BundleValidationError::MissingBundleSignature);
+            }
+            _ => {}
+        }
+
+        BundleValidationReport { errors }
+    }
+}
+
+#[wasm_bindgen]
+impl PreKeyBundle {
+    /// 從各組成部分建立 PreKeyBundle
+    ///
+    /// 一次性預金鑰的 ID 與公鑰需同時提供，否則視為沒有一次性預金鑰
+    #[wasm_bindgen(constructor)]
+    pub fn from_components(
+        identity_key: &[u8],
+        signed_pre_key_id: u32,
+        signed_pre_key_public: &[u8],
+        signed_pre_key_signature: &[u8],
+        signed_pre_key_timestamp: u64,
+        one_time_pre_key_id: Option<u32>,
+        one_time_pre_key_public: Option<Vec<u8>>,
+    ) -> PreKeyBundle {
+        let signed_pre_key = SignedPreKey {
+            key_id: signed_pre_key_id,
+            public_key: signed_pre_key_public.to_vec(),
+            signature: signed_pre_key_signature.to_vec(),
+            timestamp: signed_pre_key_timestamp,
+        };
+
+        let one_time_pre_key = match (one_time_pre_key_id, one_time_pre_key_public) {
+            (Some(id), Some(pk)) => Some(OneTimePreKey {
+                key_id: id,
+                public_key: pk,
+            }),
+            _ => None,
+        };
+
+        Self::new(identity_key.to_vec(), signed_pre_key, one_time_pre_key)
+    }
+
+    /// 取得身份公鑰
+    #[wasm_bindgen(getter, js_name = identityKey)]
+    pub fn identity_key(&self) -> Vec<u8> {
+        self.identity_key.clone()
+    }
+
     /// 取得身份公鑰 (Base64)
+    #[wasm_bindgen(getter, js_name = identityKeyBase64)]
     pub fn identity_key_base64(&self) -> String {
         BASE64.encode(&self.identity_key)
     }
+
+    #[wasm_bindgen(getter, js_name = signedPreKeyId)]
+    pub fn signed_pre_key_id(&self) -> u32 {
+        self.signed_pre_key.key_id
+    }
+
+    #[wasm_bindgen(getter, js_name = signedPreKeyPublic)]
+    pub fn signed_pre_key_public(&self) -> Vec<u8> {
+        self.signed_pre_key.public_key.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = signedPreKeySignature)]
+    pub fn signed_pre_key_signature(&self) -> Vec<u8> {
+        self.signed_pre_key.signature.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = signedPreKeyTimestamp)]
+    pub fn signed_pre_key_timestamp(&self) -> u64 {
+        self.signed_pre_key.timestamp
+    }
+
+    #[wasm_bindgen(getter, js_name = oneTimePreKeyId)]
+    pub fn one_time_pre_key_id(&self) -> Option<u32> {
+        self.one_time_pre_key.as_ref().map(|k| k.key_id)
+    }
+
+    #[wasm_bindgen(getter, js_name = oneTimePreKeyPublic)]
+    pub fn one_time_pre_key_public(&self) -> Option<Vec<u8>> {
+        self.one_time_pre_key.as_ref().map(|k| k.public_key.clone())
+    }
+
+    /// 取得整個 bundle 的簽章 (如有)
+    #[wasm_bindgen(getter, js_name = bundleSignature)]
+    pub fn bundle_signature(&self) -> Option<Vec<u8>> {
+        self.bundle_signature.clone()
+    }
+
+    /// 使用身份金鑰簽署整個 bundle
+    #[wasm_bindgen(js_name = signBundle)]
+    pub fn sign_bundle(&mut self, identity: &IdentityKeyPair) -> Result<(), JsError> {
+        self.sign_with(identity).map_err(|e| JsError::new(&e))
+    }
+
+    /// 驗證整個 bundle 的簽章
+    ///
+    /// 沒有簽章時回傳 false
+    #[wasm_bindgen(js_name = verifyBundleSignature)]
+    pub fn verify_bundle_signature(&self) -> bool {
+        match &self.bundle_signature {
+            Some(signature) => IdentityKeyPair::verify_signature(
+                &self.identity_key,
+                &self.signing_payload(),
+                signature,
+            ),
+            None => false,
+        }
+    }
+
+    /// 使用預設策略驗證 PreKeyBundle
+    ///
+    /// `now` 為目前時間 (Unix 毫秒，與 `Date.now()` 相同)
+    pub fn validate(&self, now: u64) -> BundleValidationReport {
+        self.validate_with_policy(now, &BundleValidationPolicy::default())
+    }
+
+    /// 序列化為 JSON
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json_js(&self) -> Result<String, JsError> {
+        self.to_json().map_err(|e| JsError::new(&e))
+    }
+
+    /// 從 JSON 還原
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json_js(json: &str) -> Result<PreKeyBundle, JsError> {
+        Self::from_json(json).map_err(|e| JsError::new(&e))
+    }
+
+    /// 序列化為位元組
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 從位元組還原
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<PreKeyBundle, JsError> {
+        bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// bundle 簽章的網域分隔前綴
+const BUNDLE_SIGNATURE_CONTEXT: &[u8] = b"SafeTalk_PreKeyBundle_v1";
+
+/// Signed PreKey 預設最長有效期 (30 天，毫秒)
+pub const DEFAULT_SIGNED_PRE_KEY_MAX_AGE_MS: u64 = 30 * 24 * 60 * 60 * 1000;
+
+/// 預設允許的時鐘誤差 (5 分鐘，毫秒)
+pub const DEFAULT_MAX_CLOCK_SKEW_MS: u64 = 5 * 60 * 1000;
+
+/// PreKeyBundle 驗證策略
+#[derive(Clone, Copy, Debug)]
+pub struct BundleValidationPolicy {
+    /// Signed PreKey 最長有效期 (毫秒)
+    pub max_age_ms: u64,
+    /// 允許時間戳領先目前時間的範圍 (毫秒)
+    pub max_clock_skew_ms: u64,
+    /// 是否要求整個 bundle 必須附帶簽章
+    pub require_bundle_signature: bool,
+}
+
+impl Default for BundleValidationPolicy {
+    fn default() -> Self {
+        Self {
+            max_age_ms: DEFAULT_SIGNED_PRE_KEY_MAX_AGE_MS,
+            max_clock_skew_ms: DEFAULT_MAX_CLOCK_SKEW_MS,
+            require_bundle_signature: false,
+        }
+    }
+}
+
+/// PreKeyBundle 驗證失敗項目
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, thiserror::Error)]
+#[serde(tag = "code", rename_all = "snake_case")]
+pub enum BundleValidationError {
+    #[error("Identity key must be 32 bytes, got {0}")]
+    InvalidIdentityKeyLength(usize),
+    #[error("Identity key is not a valid Ed25519 public key")]
+    InvalidIdentityKey,
+    #[error("Signed prekey must be 32 bytes, got {0}")]
+    InvalidSignedPreKeyLength(usize),
+    #[error("Signed prekey signature must be 64 bytes, got {0}")]
+    InvalidSignatureLength(usize),
+    #[error("Invalid signed prekey signature")]
+    InvalidSignature,
+    #[error("Signed prekey expired: age {age_ms}ms exceeds {max_age_ms}ms")]
+    SignedPreKeyExpired { age_ms: u64, max_age_ms: u64 },
+    #[error("Signed prekey timestamp {timestamp} is ahead of current time {now}")]
+    TimestampInFuture { timestamp: u64, now: u64 },
+    #[error("One-time prekey must be 32 bytes, got {0}")]
+    InvalidOneTimePreKeyLength(usize),
+    #[error("Bundle signature is required but missing")]
+    MissingBundleSignature,
+    #[error("Invalid bundle signature")]
+    InvalidBundleSignature,
+}
+
+/// PreKeyBundle 驗證報告
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct BundleValidationReport {
+    errors: Vec<BundleValidationError>,
+}
+
+impl BundleValidationReport {
+    /// 取得所有失敗項目
+    pub fn errors(&self) -> &[BundleValidationError] {
+        &self.errors
+    }
+}
+
+#[wasm_bindgen]
+impl BundleValidationReport {
+    /// 是否通過所有檢查
+    #[wasm_bindgen(getter, js_name = isValid)]
+    pub fn is_valid(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    /// 取得錯誤訊息列表
+    #[wasm_bindgen(getter, js_name = errorMessages)]
+    pub fn error_messages(&self) -> Vec<String> {
+        self.errors.iter().map(|e| e.to_string()).collect()
+    }
+
+    /// 序列化為 JSON
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// WASM 輔助函式：驗證 PreKeyBundle JSON
+///
+/// `max_age_ms` 未提供時使用 [`DEFAULT_SIGNED_PRE_KEY_MAX_AGE_MS`]；
+/// `require_bundle_signature` 為 true 時，未附帶整體簽章的 bundle 視為無效
+#[wasm_bindgen(js_name = validatePreKeyBundleJson)]
+pub fn validate_pre_key_bundle_json(
+    bundle_json: &str,
+    now: u64,
+    max_age_ms: Option<u64>,
+    require_bundle_signature: Option<bool>,
+) -> Result<BundleValidationReport, JsError> {
+    let bundle = PreKeyBundle::from_json(bundle_json).map_err(|e| JsError::new(&e))?;
+    let policy = BundleValidationPolicy {
+        max_age_ms: max_age_ms.unwrap_or(DEFAULT_SIGNED_PRE_KEY_MAX_AGE_MS),
+        require_bundle_signature: require_bundle_signature.unwrap_or(false),
+        ..Default::default()
+    };
+    Ok(bundle.validate_with_policy(now, &policy))
+}
+
+/// WASM 輔助函式：批次驗證 Ed25519 簽章
+///
+/// 三個陣列依索引對應，所有簽章皆有效才回傳 true
+#[wasm_bindgen(js_name = verifySignaturesBatch)]
+pub fn verify_signatures_batch(
+    public_keys: Vec<js_sys::Uint8Array>,
+    messages: Vec<js_sys::Uint8Array>,
+    signatures: Vec<js_sys::Uint8Array>,
+) -> bool {
+    let public_keys: Vec<Vec<u8>> = public_keys.iter().map(|k| k.to_vec()).collect();
+    let messages: Vec<Vec<u8>> = messages.iter().map(|m| m.to_vec()).collect();
+    let signatures: Vec<Vec<u8>> = signatures.iter().map(|s| s.to_vec()).collect();
+
+    IdentityKeyPair::verify_batch(
+        &public_keys.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+        &messages.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+        &signatures.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+    )
 }
 
 /// WASM 輔助函式：建立 PreKeyBundle JSON
@@ -230,22 +778,15 @@ pub fn create_pre_key_bundle_json(
     one_time_pre_key_id: Option<u32>,
     one_time_pre_key_public: Option<Vec<u8>>,
 ) -> Result<String, JsError> {
-    let signed_pre_key = SignedPreKey {
-        key_id: signed_pre_key_id,
-        public_key: signed_pre_key_public.to_vec(),
-        signature: signed_pre_key_signature.to_vec(),
-        timestamp: signed_pre_key_timestamp,
-    };
-
-    let one_time_pre_key = match (one_time_pre_key_id, one_time_pre_key_public) {
-        (Some(id), Some(pk)) => Some(OneTimePreKey {
-            key_id: id,
-            public_key: pk,
-        }),
-        _ => None,
-    };
-
-    let bundle = PreKeyBundle::new(identity_key.to_vec(), signed_pre_key, one_time_pre_key);
+    let bundle = PreKeyBundle::from_components(
+        identity_key,
+        signed_pre_key_id,
+        signed_pre_key_public,
+        signed_pre_key_signature,
+        signed_pre_key_timestamp,
+        one_time_pre_key_id,
+        one_time_pre_key_public,
+    );
     bundle.to_json().map_err(|e| JsError::new(&e))
 }
 
@@ -266,6 +807,42 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_verify_batch() {
+        let keypairs: Vec<IdentityKeyPair> = (0..4).map(|_| IdentityKeyPair::new()).collect();
+        let messages: Vec<Vec<u8>> = (0..4u8).map(|i| vec![i; 16]).collect();
+        let mut signatures: Vec<Vec<u8>> = keypairs
+            .iter()
+            .zip(&messages)
+            .map(|(kp, m)| kp.sign(m))
+            .collect();
+        let public_keys: Vec<Vec<u8>> = keypairs.iter().map(|kp| kp.public_key_bytes()).collect();
+
+        fn as_slices(v: &[Vec<u8>]) -> Vec<&[u8]> {
+            v.iter().map(Vec::as_slice).collect()
+        }
+        assert!(IdentityKeyPair::verify_batch(
+            &as_slices(&public_keys),
+            &as_slices(&messages),
+            &as_slices(&signatures)
+        ));
+
+        // 長度不一致
+        assert!(!IdentityKeyPair::verify_batch(
+            &as_slices(&public_keys[..3]),
+            &as_slices(&messages),
+            &as_slices(&signatures)
+        ));
+
+        // 任一簽章無效即失敗
+        signatures[2][0] ^= 0x01;
+        assert!(!IdentityKeyPair::verify_batch(
+            &as_slices(&public_keys),
+            &as_slices(&messages),
+            &as_slices(&signatures)
+        ));
+    }
+
     #[test]
     fn test_x25519_dh() {
         let alice = X25519KeyPair::new();
@@ -276,4 +853,114 @@ mod tests {
 
         assert_eq!(alice_shared, bob_shared);
     }
+
+    fn make_bundle(identity: &IdentityKeyPair, timestamp: u64) -> PreKeyBundle {
+        let spk = X25519KeyPair::new();
+        let signed_pre_key = SignedPreKey {
+            key_id: 1,
+            public_key: spk.public_key_bytes(),
+            signature: identity.sign(&spk.public_key_bytes()),
+            timestamp,
+        };
+        PreKeyBundle::new(identity.public_key_bytes(), signed_pre_key, None)
+    }
+
+    #[test]
+    fn test_pre_key_bundle_validate() {
+        let identity = IdentityKeyPair::new();
+        let now = 1_700_000_000_000;
+        let bundle = make_bundle(&identity, now - 1000);
+
+        assert!(bundle.validate(now).is_valid());
+
+        // 過期的 Signed PreKey
+        let stale = now + DEFAULT_SIGNED_PRE_KEY_MAX_AGE_MS + 1;
+        assert_eq!(
+            bundle.validate(stale).errors(),
+            &[BundleValidationError::SignedPreKeyExpired {
+                age_ms: DEFAULT_SIGNED_PRE_KEY_MAX_AGE_MS + 1001,
+                max_age_ms: DEFAULT_SIGNED_PRE_KEY_MAX_AGE_MS,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_pre_key_bundle_components_roundtrip() {
+        let identity = IdentityKeyPair::new();
+        let bundle = PreKeyBundle::from_components(
+            &identity.public_key_bytes(),
+            5,
+            &[1u8; 32],
+            &[2u8; 64],
+            1234,
+            Some(9),
+            Some(vec![3u8; 32]),
+        );
+
+        let restored = PreKeyBundle::from_bytes(&bundle.to_bytes().unwrap()).unwrap();
+        assert_eq!(restored.identity_key(), identity.public_key_bytes());
+        assert_eq!(restored.signed_pre_key_id(), 5);
+        assert_eq!(restored.signed_pre_key_timestamp(), 1234);
+        assert_eq!(restored.one_time_pre_key_id(), Some(9));
+        assert_eq!(restored.one_time_pre_key_public(), Some(vec![3u8; 32]));
+
+        // 只提供 ID 時視為沒有一次性預金鑰
+        let partial = PreKeyBundle::from_components(&[0u8; 32], 1, &[1u8; 32], &[2u8; 64], 0, Some(1), None);
+        assert_eq!(partial.one_time_pre_key_id(), None);
+    }
+
+    #[test]
+    fn test_bundle_signature() {
+        let identity = IdentityKeyPair::new();
+        let now = 1_700_000_000_000;
+        let mut bundle = make_bundle(&identity, now);
+        bundle.one_time_pre_key = Some(OneTimePreKey { key_id: 1, public_key: vec![1u8; 32] });
+
+        let strict = BundleValidationPolicy { require_bundle_signature: true, ..Default::default() };
+        assert_eq!(
+            bundle.validate_with_policy(now, &strict).errors(),
+            &[BundleValidationError::MissingBundleSignature]
+        );
+
+        // 不同身份金鑰不能簽署
+        assert!(bundle.sign_with(&IdentityKeyPair::new()).is_err());
+
+        bundle.sign_with(&identity).unwrap();
+        assert!(bundle.verify_bundle_signature());
+        let restored = PreKeyBundle::from_json(&bundle.to_json().unwrap()).unwrap();
+        assert!(restored.validate_with_policy(now, &strict).is_valid());
+
+        // 伺服器替換一次性預金鑰後簽章失效
+        let mut tampered = restored.clone();
+        tampered.one_time_pre_key = Some(OneTimePreKey { key_id: 2, public_key: vec![2u8; 32] });
+        assert!(!tampered.verify_bundle_signature());
+        assert_eq!(
+            tampered.validate(now).errors(),
+            &[BundleValidationError::InvalidBundleSignature]
+        );
+    }
+
+    #[test]
+    fn test_pre_key_bundle_validate_collects_all_errors() {
+        let identity = IdentityKeyPair::new();
+        let other = IdentityKeyPair::new();
+        let now = 1_700_000_000_000;
+
+        // 簽章來自另一把身份金鑰 + 時間戳來自未來
+        let mut bundle = make_bundle(&other, now + DEFAULT_MAX_CLOCK_SKEW_MS + 1);
+        bundle.identity_key = identity.public_key_bytes();
+        bundle.one_time_pre_key = Some(OneTimePreKey { key_id: 7, public_key: vec![0u8; 31] });
+
+        let report = bundle.validate(now);
+        assert!(!report.is_valid());
+        assert_eq!(report.errors().len(), 3);
+        assert!(report.errors().contains(&BundleValidationError::InvalidSignature));
+        assert!(report.errors().contains(&BundleValidationError::InvalidOneTimePreKeyLength(31)));
+
+        // 長度錯誤時不會再回報簽章錯誤
+        bundle.signed_pre_key.signature.truncate(10);
+        let report = bundle.validate(now);
+        assert!(report.errors().contains(&BundleValidationError::InvalidSignatureLength(10)));
+        assert!(!report.errors().contains(&BundleValidationError::InvalidSignature));
+    }
 }
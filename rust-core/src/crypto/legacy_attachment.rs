@@ -0,0 +1,151 @@
+//! 舊式附件加密模組 (Signal 相容)
+//!
+//! 與 Signal 附件格式相同的 encrypt-then-MAC 模式，用於解開從 Signal 相容服務轉傳的附件，
+//! 或把附件轉傳給只支援該格式的用戶端。新附件應使用 `Attachment` (STREAM + AEAD)
+//!
+//! 格式 (金鑰 64 bytes = AES-256 金鑰 || HMAC-SHA256 金鑰)：
+//!
+//! ```text
+//! IV (16) || AES-256-CBC 密文 (PKCS#7) || HMAC-SHA256(IV || 密文) (32)
+//! ```
+//!
+//! Signal 實際使用的是 CBC 而非 CTR，此處依其實作以確保互通；
+//! 摘要為整個密文 blob 的 SHA-256，放在附件指標中傳送
+
+use wasm_bindgen::prelude::*;
+use aes::Aes256;
+use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use super::constant_time::constant_time_eq;
+use super::mac::{hmac_sha256_bytes, hmac_sha256_verify};
+
+/// 舊式附件金鑰長度 (AES-256 金鑰 || HMAC-SHA256 金鑰)
+pub const LEGACY_ATTACHMENT_KEY_SIZE: usize = 64;
+
+const IV_SIZE: usize = 16;
+const MAC_SIZE: usize = 32;
+
+fn split_key(key: &[u8]) -> Result<(&[u8], &[u8]), String> {
+    if key.len() != LEGACY_ATTACHMENT_KEY_SIZE {
+        return Err(format!(
+            "Legacy attachment key must be {} bytes, got {}",
+            LEGACY_ATTACHMENT_KEY_SIZE,
+            key.len()
+        ));
+    }
+    Ok(key.split_at(32))
+}
+
+/// 以指定的 IV 加密 (Rust 端使用)
+pub fn legacy_attachment_encrypt(key: &[u8], iv: &[u8; IV_SIZE], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let (cipher_key, mac_key) = split_key(key)?;
+    let ciphertext = cbc::Encryptor::<Aes256>::new(cipher_key.into(), iv.into()).encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+
+    let mut blob = Vec::with_capacity(IV_SIZE + ciphertext.len() + MAC_SIZE);
+    blob.extend_from_slice(iv);
+    blob.extend(ciphertext);
+    let mac = hmac_sha256_bytes(mac_key, &blob);
+    blob.extend_from_slice(&mac);
+    Ok(blob)
+}
+
+/// 驗證並解密 (Rust 端使用)
+///
+/// 提供 `digest` 時先比對整個 blob 的 SHA-256；`plaintext_len` 用於去除傳送端額外填充的位元組
+pub fn legacy_attachment_decrypt(
+    key: &[u8],
+    blob: &[u8],
+    digest: Option<&[u8]>,
+    plaintext_len: Option<u64>,
+) -> Result<Vec<u8>, String> {
+    let (cipher_key, mac_key) = split_key(key)?;
+    if blob.len() < IV_SIZE + 16 + MAC_SIZE || !(blob.len() - IV_SIZE - MAC_SIZE).is_multiple_of(16) {
+        return Err("Invalid legacy attachment length".to_string());
+    }
+    if let Some(digest) = digest {
+        if !constant_time_eq(&Sha256::digest(blob), digest) {
+            return Err("Attachment ciphertext digest mismatch".to_string());
+        }
+    }
+
+    let (authenticated, mac) = blob.split_at(blob.len() - MAC_SIZE);
+    if !hmac_sha256_verify(mac_key, authenticated, mac) {
+        return Err("Legacy attachment MAC mismatch".to_string());
+    }
+    let (iv, ciphertext) = authenticated.split_at(IV_SIZE);
+    let mut plaintext = cbc::Decryptor::<Aes256>::new(cipher_key.into(), iv.into())
+        .decrypt_padded_vec_mut::<Pkcs7>(ciphertext)
+        .map_err(|_| "Invalid legacy attachment padding".to_string())?;
+
+    if let Some(len) = plaintext_len {
+        if len > plaintext.len() as u64 {
+            return Err("Attachment size exceeds decrypted length".to_string());
+        }
+        plaintext.truncate(len as usize);
+    }
+    Ok(plaintext)
+}
+
+/// 以舊式 (Signal 相容) 格式加密附件，IV 隨機產生
+#[wasm_bindgen(js_name = encryptLegacyAttachment)]
+pub fn encrypt_legacy_attachment(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, JsError> {
+    let mut iv = [0u8; IV_SIZE];
+    OsRng.fill_bytes(&mut iv);
+    legacy_attachment_encrypt(key, &iv, plaintext).map_err(|e| JsError::new(&e))
+}
+
+/// 驗證並解密舊式 (Signal 相容) 格式的附件
+#[wasm_bindgen(js_name = decryptLegacyAttachment)]
+pub fn decrypt_legacy_attachment(
+    key: &[u8],
+    blob: &[u8],
+    digest: Option<Vec<u8>>,
+    plaintext_len: Option<u64>,
+) -> Result<Vec<u8>, JsError> {
+    legacy_attachment_decrypt(key, blob, digest.as_deref(), plaintext_len).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::to_hex;
+
+    const PLAINTEXT: &[u8] = b"attachment re-shared from a Signal-compatible service";
+
+    #[test]
+    fn test_known_answer() {
+        let key: Vec<u8> = (0..64).collect();
+        let iv: [u8; 16] = core::array::from_fn(|i| 0xa0 + i as u8);
+        let blob = legacy_attachment_encrypt(&key, &iv, PLAINTEXT).unwrap();
+        assert_eq!(
+            to_hex(&blob),
+            "a0a1a2a3a4a5a6a7a8a9aaabacadaeaf072a0dfff7836b0157a42f9549081c46ad7ebb1712ebb6c2938438078ff7ac92\
+             d3f63fb02b5589b85f0831f030f007d434fefa9d68504803b13d16e687cf4816f4532bf14016e8bca607c0ece0ab82\
+             2619386df0fc091900f11cf3b23ce2d823"
+        );
+        let digest = Sha256::digest(&blob);
+        assert_eq!(to_hex(&digest), "cff6f045bd18924479320a455050704503b01b20f7462f80dd2796a691182574");
+        assert_eq!(legacy_attachment_decrypt(&key, &blob, Some(&digest), None).unwrap(), PLAINTEXT);
+    }
+
+    #[test]
+    fn test_verification_failures() {
+        let key = [7u8; 64];
+        let blob = encrypt_legacy_attachment(&key, PLAINTEXT).unwrap();
+        assert_eq!(legacy_attachment_decrypt(&key, &blob, None, Some(10)).unwrap(), &PLAINTEXT[..10]);
+        assert!(legacy_attachment_decrypt(&key, &blob, None, Some(1000)).is_err());
+
+        let mut tampered = blob.clone();
+        tampered[20] ^= 1;
+        assert_eq!(
+            legacy_attachment_decrypt(&key, &tampered, None, None),
+            Err("Legacy attachment MAC mismatch".to_string())
+        );
+        assert!(legacy_attachment_decrypt(&key, &blob, Some(&[0u8; 32]), None).is_err());
+        assert!(legacy_attachment_decrypt(&[8u8; 64], &blob, None, None).is_err());
+        assert!(legacy_attachment_decrypt(&key[..32], &blob, None, None).is_err());
+        assert!(legacy_attachment_decrypt(&key, &blob[..blob.len() - 1], None, None).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+//! libsignal-protocol-javascript 會話匯入模組
+//!
+//! 把 libsignal-protocol-javascript / libsignal-protocol-typescript 儲存的會話記錄
+//! (`SessionRecord.serialize()` 的 JSON) 轉換為 `RatchetSession`，
+//! 應用程式把加密核心換成 mist 時，使用者不必同時重新握手：
+//! - 取記錄中開啟中的會話 (`indexInfo.closed == -1`)
+//! - 沿用根金鑰、我方 ratchet 金鑰對、對方最後的 ratchet 公鑰，
+//!   以及目前發送 / 接收鏈的鏈金鑰與下一個訊息編號
+//! - 位元組欄位可為 binary string (javascript 版) 或 base64 (typescript 版)，
+//!   公鑰的 0x05 型別前綴會去掉
+//!
+//! 轉換後的會話以 mist 的 KDF 與訊息格式繼續 ratchet，雙方都需轉換後才能互通；
+//! 轉換前尚未送達的 Signal 格式訊息無法解密，記錄中保存的跳過訊息金鑰不沿用
+//! (數量見 `discardedMessageKeys`)
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde_json::{Map, Value};
+use wasm_bindgen::prelude::*;
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret as X25519SecretKey};
+
+use super::ratchet::{RatchetSession, RatchetState};
+
+/// libsignal 公鑰的型別前綴 (Curve25519)
+const DJB_TYPE: u8 = 0x05;
+
+/// libsignal 的 sending 鏈類型
+const CHAIN_SENDING: i64 = 1;
+/// libsignal 的 receiving 鏈類型
+const CHAIN_RECEIVING: i64 = 2;
+
+/// 匯入的 libsignal 會話
+#[wasm_bindgen]
+pub struct ImportedLibsignalSession {
+    session: RatchetSession,
+    remote_identity_key: Option<Vec<u8>>,
+    registration_id: Option<u32>,
+    pending_pre_key: bool,
+    discarded_message_keys: u32,
+}
+
+#[wasm_bindgen]
+impl ImportedLibsignalSession {
+    /// 轉換後的 ratchet 會話
+    #[wasm_bindgen(getter)]
+    pub fn session(&self) -> RatchetSession {
+        self.session.clone()
+    }
+
+    /// 對方的身份公鑰 (X25519，32 bytes)，應與既有的信任記錄比對
+    #[wasm_bindgen(getter, js_name = remoteIdentityKey)]
+    pub fn remote_identity_key(&self) -> Option<Vec<u8>> {
+        self.remote_identity_key.clone()
+    }
+
+    /// 對方的註冊 ID
+    #[wasm_bindgen(getter, js_name = registrationId)]
+    pub fn registration_id(&self) -> Option<u32> {
+        self.registration_id
+    }
+
+    /// 會話仍在等待對方回覆 (對方尚未收到 PreKey 訊息)
+    ///
+    /// 對方可能尚未建立會話，轉換後應重新握手
+    #[wasm_bindgen(getter, js_name = pendingPreKey)]
+    pub fn pending_pre_key(&self) -> bool {
+        self.pending_pre_key
+    }
+
+    /// 未沿用的跳過訊息金鑰數量
+    #[wasm_bindgen(getter, js_name = discardedMessageKeys)]
+    pub fn discarded_message_keys(&self) -> u32 {
+        self.discarded_message_keys
+    }
+}
+
+/// 解碼位元組欄位：長度符合的 binary string，否則視為 base64
+fn decode_bytes(value: &Value, field: &str, lengths: &[usize]) -> Result<Vec<u8>, String> {
+    let text = value.as_str().ok_or_else(|| format!("Missing {} in libsignal session", field))?;
+    let binary = text.chars().map(|c| u8::try_from(u32::from(c)).ok()).collect::<Option<Vec<u8>>>();
+    let bytes = match binary.filter(|bytes| lengths.contains(&bytes.len())) {
+        Some(bytes) => bytes,
+        None => BASE64.decode(text).map_err(|_| format!("Invalid {} in libsignal session", field))?,
+    };
+    if !lengths.contains(&bytes.len()) {
+        return Err(format!("Invalid {} length in libsignal session", field));
+    }
+    Ok(bytes)
+}
+
+/// 解碼公鑰並去掉型別前綴
+fn decode_public_key(value: &Value, field: &str) -> Result<Vec<u8>, String> {
+    let mut key = decode_bytes(value, field, &[33, 32])?;
+    if key.len() == 33 {
+        if key[0] != DJB_TYPE {
+            return Err(format!("Unsupported {} type in libsignal session", field));
+        }
+        key.remove(0);
+    }
+    Ok(key)
+}
+
+fn decode_key(value: &Value, field: &str) -> Result<[u8; 32], String> {
+    let bytes = decode_bytes(value, field, &[32])?;
+    let mut key = [0u8; 32];
+    key.copy_from_slice(&bytes);
+    Ok(key)
+}
+
+/// 鏈的目前狀態：鏈金鑰 (已封存的鏈沒有) 與下一個訊息編號
+fn chain_state(chain: &Value) -> Result<(Option<[u8; 32]>, u32), String> {
+    let chain_key = &chain["chainKey"];
+    let key = match &chain_key["key"] {
+        Value::Null => None,
+        key => Some(decode_key(key, "chain key")?),
+    };
+    // counter 為最後衍生的訊息編號，尚未衍生時為 -1
+    let counter = chain_key["counter"].as_i64().ok_or("Missing chain counter in libsignal session")?;
+    let next = u32::try_from(counter + 1).map_err(|_| "Invalid chain counter in libsignal session".to_string())?;
+    Ok((key, next))
+}
+
+/// 找出以指定 ratchet 公鑰為鍵、指定類型的鏈
+fn find_chain<'a>(
+    session: &'a Map<String, Value>,
+    ratchet_key: &[u8],
+    chain_type: i64,
+) -> Option<&'a Value> {
+    session.iter().find_map(|(name, chain)| {
+        if chain["chainType"].as_i64() != Some(chain_type) {
+            return None;
+        }
+        let key = decode_public_key(&Value::String(name.clone()), "chain name").ok()?;
+        (key == ratchet_key).then_some(chain)
+    })
+}
+
+/// 所有鏈中保存的跳過訊息金鑰數量
+fn count_message_keys(session: &Map<String, Value>) -> u32 {
+    session
+        .values()
+        .filter(|chain| chain.get("chainType").is_some())
+        .filter_map(|chain| chain["messageKeys"].as_object())
+        .map(|keys| keys.len() as u32)
+        .sum()
+}
+
+/// 轉換 libsignal-protocol-javascript 的會話記錄 JSON (Rust 端使用)
+pub fn import_libsignal_session(record_json: &str) -> Result<ImportedLibsignalSession, String> {
+    let record: Value = serde_json::from_str(record_json).map_err(|e| format!("Invalid libsignal session record: {}", e))?;
+    let sessions = record["sessions"].as_object().ok_or("Missing sessions in libsignal session record")?;
+    let session = sessions
+        .values()
+        .filter_map(Value::as_object)
+        .find(|session| session.get("indexInfo").and_then(|info| info["closed"].as_i64()) == Some(-1))
+        .ok_or("No open session in libsignal session record")?;
+
+    let ratchet = session.get("currentRatchet").ok_or("Missing currentRatchet in libsignal session")?;
+    let keypair = &ratchet["ephemeralKeyPair"];
+    let dh_public = decode_public_key(&keypair["pubKey"], "ephemeral public key")?;
+    let dh_private = decode_bytes(&keypair["privKey"], "ephemeral private key", &[32])?;
+    let mut private_bytes = [0u8; 32];
+    private_bytes.copy_from_slice(&dh_private);
+    let derived = X25519PublicKey::from(&X25519SecretKey::from(private_bytes));
+    if derived.as_bytes().as_slice() != dh_public.as_slice() {
+        return Err("Ephemeral key pair mismatch in libsignal session".to_string());
+    }
+
+    let dh_remote = match &ratchet["lastRemoteEphemeralKey"] {
+        Value::Null => None,
+        key => Some(decode_public_key(key, "remote ephemeral key")?),
+    };
+    let root_key = decode_key(&ratchet["rootKey"], "root key")?;
+
+    let (chain_key_send, send_count) = match find_chain(session, &dh_public, CHAIN_SENDING) {
+        Some(chain) => chain_state(chain)?,
+        None => (None, 0),
+    };
+    let (chain_key_recv, recv_count) = match dh_remote.as_deref().and_then(|remote| find_chain(session, remote, CHAIN_RECEIVING)) {
+        Some(chain) => chain_state(chain)?,
+        None => (None, 0),
+    };
+    // previousCounter 為前一條發送鏈最後的訊息編號
+    let previous_counter = ratchet["previousCounter"].as_i64().unwrap_or(-1);
+    let prev_send_count = u32::try_from((previous_counter + 1).max(0))
+        .map_err(|_| "Invalid previousCounter in libsignal session".to_string())?;
+
+    let index_info = &session["indexInfo"];
+    let remote_identity_key = match &index_info["remoteIdentityKey"] {
+        Value::Null => None,
+        key => Some(decode_public_key(key, "remote identity key")?),
+    };
+    let registration_id = session.get("registrationId").and_then(Value::as_u64).and_then(|id| u32::try_from(id).ok());
+    let pending_pre_key = session.get("pendingPreKey").is_some_and(|pending| !pending.is_null());
+
+    let session_state = RatchetSession::from_state(RatchetState {
+        dh_public,
+        dh_private,
+        dh_remote,
+        root_key,
+        chain_key_send,
+        send_count,
+        chain_key_recv,
+        recv_count,
+        prev_send_count,
+    });
+    Ok(ImportedLibsignalSession {
+        session: session_state,
+        remote_identity_key,
+        registration_id,
+        pending_pre_key,
+        discarded_message_keys: count_message_keys(session),
+    })
+}
+
+/// 轉換 libsignal-protocol-javascript 的會話記錄 (`SessionRecord.serialize()` 的 JSON)
+#[wasm_bindgen(js_name = importLibsignalSession)]
+pub fn import_libsignal_session_js(record_json: &str) -> Result<ImportedLibsignalSession, JsError> {
+    import_libsignal_session(record_json).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::X25519KeyPair;
+    use serde_json::json;
+
+    /// javascript 版以 binary string 表示 ArrayBuffer
+    fn binary(bytes: &[u8]) -> String {
+        bytes.iter().map(|&b| char::from(b)).collect()
+    }
+
+    fn prefixed(public: &[u8]) -> Vec<u8> {
+        [&[DJB_TYPE][..], public].concat()
+    }
+
+    #[test]
+    fn test_import_libsignal_session_pair() {
+        let alice_key = X25519KeyPair::new();
+        let bob_key = X25519KeyPair::new();
+        let (alice_public, bob_public) = (alice_key.public_key_bytes(), bob_key.public_key_bytes());
+        let root_key = [3u8; 32];
+        let chain_key = [4u8; 32];
+
+        // Alice 的發送鏈對應 Bob 的接收鏈，Bob 另保存 1 則跳過訊息金鑰
+        let alice_record = json!({
+            "version": "v1",
+            "sessions": {
+                "closed": {"indexInfo": {"closed": 1700000000000u64}, "currentRatchet": {}},
+                "open": {
+                    "registrationId": 4242,
+                    "indexInfo": {"closed": -1, "remoteIdentityKey": binary(&prefixed(&[7u8; 32]))},
+                    "currentRatchet": {
+                        "rootKey": binary(&root_key),
+                        "ephemeralKeyPair": {
+                            "pubKey": binary(&prefixed(&alice_public)),
+                            "privKey": binary(alice_key.private_key_bytes().expose()),
+                        },
+                        "lastRemoteEphemeralKey": binary(&prefixed(&bob_public)),
+                        "previousCounter": 0,
+                    },
+                    (binary(&prefixed(&alice_public))): {
+                        "chainKey": {"counter": -1, "key": binary(&chain_key)},
+                        "chainType": CHAIN_SENDING,
+                        "messageKeys": {},
+                    },
+                },
+            },
+        });
+        // typescript 版以 base64 表示
+        let bob_record = json!({
+            "sessions": {
+                "open": {
+                    "indexInfo": {"closed": -1},
+                    "currentRatchet": {
+                        "rootKey": BASE64.encode(root_key),
+                        "ephemeralKeyPair": {
+                            "pubKey": BASE64.encode(prefixed(&bob_public)),
+                            "privKey": BASE64.encode(bob_key.private_key_bytes().expose()),
+                        },
+                        "lastRemoteEphemeralKey": BASE64.encode(prefixed(&alice_public)),
+                        "previousCounter": 0,
+                    },
+                    (BASE64.encode(prefixed(&alice_public))): {
+                        "chainKey": {"counter": -1, "key": BASE64.encode(chain_key)},
+                        "chainType": CHAIN_RECEIVING,
+                        "messageKeys": {"3": BASE64.encode([1u8; 32])},
+                    },
+                },
+            },
+        });
+
+        let alice = import_libsignal_session(&alice_record.to_string()).unwrap();
+        assert_eq!(alice.remote_identity_key(), Some(vec![7u8; 32]));
+        assert_eq!(alice.registration_id(), Some(4242));
+        assert!(!alice.pending_pre_key());
+        let bob = import_libsignal_session(&bob_record.to_string()).unwrap();
+        assert_eq!(bob.discarded_message_keys(), 1);
+
+        let (mut alice, mut bob) = (alice.session(), bob.session());
+        let message = alice.encrypt(b"after migration").unwrap();
+        assert_eq!(bob.decrypt(&message).unwrap(), b"after migration");
+        let reply = bob.encrypt(b"reply").unwrap();
+        assert_eq!(alice.decrypt(&reply).unwrap(), b"reply");
+
+        // 金鑰對不符
+        let mut broken = alice_record.clone();
+        broken["sessions"]["open"]["currentRatchet"]["ephemeralKeyPair"]["privKey"] = json!(binary(&[1u8; 32]));
+        assert!(import_libsignal_session(&broken.to_string()).is_err());
+    }
+}
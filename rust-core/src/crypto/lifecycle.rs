@@ -0,0 +1,305 @@
+//! 金鑰生命週期模組
+//!
+//! 追蹤簽署預金鑰、一次性預金鑰與 sender key 的使用次數與存在時間，
+//! 依輪替政策判斷何時需要更換，並在需要時通知客戶端
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// 受管理的金鑰類型
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum ManagedKeyKind {
+    SignedPreKey = 0,
+    OneTimePreKey = 1,
+    SenderKey = 2,
+}
+
+/// 輪替政策 (0 表示不限制)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotationPolicy {
+    /// 最長存在時間 (毫秒)
+    pub max_age_ms: u64,
+    /// 最多使用次數
+    pub max_uses: u64,
+}
+
+impl RotationPolicy {
+    /// 各類型的預設政策
+    ///
+    /// - 簽署預金鑰：7 天
+    /// - 一次性預金鑰：使用 1 次，或發布 30 天仍未使用
+    /// - sender key：7 天或 10000 則訊息
+    pub fn default_for(kind: ManagedKeyKind) -> Self {
+        match kind {
+            ManagedKeyKind::SignedPreKey => Self { max_age_ms: 7 * DAY_MS, max_uses: 0 },
+            ManagedKeyKind::OneTimePreKey => Self { max_age_ms: 30 * DAY_MS, max_uses: 1 },
+            ManagedKeyKind::SenderKey => Self { max_age_ms: 7 * DAY_MS, max_uses: 10_000 },
+        }
+    }
+
+    fn is_due(&self, key: &TrackedKey, now: u64) -> bool {
+        (self.max_age_ms > 0 && now.saturating_sub(key.created_at) >= self.max_age_ms)
+            || (self.max_uses > 0 && key.uses >= self.max_uses)
+    }
+}
+
+/// 被追蹤的金鑰
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct TrackedKey {
+    /// 建立時間 (Unix 毫秒)
+    created_at: u64,
+    /// 使用次數
+    uses: u64,
+    /// 是否已通知需要輪替
+    notified: bool,
+}
+
+/// 金鑰生命週期追蹤
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyLifecycle {
+    policies: BTreeMap<ManagedKeyKind, RotationPolicy>,
+    keys: BTreeMap<(ManagedKeyKind, u32), TrackedKey>,
+    /// 輪替通知 callback (kind, keyId)，不序列化
+    #[serde(skip)]
+    callback: Option<js_sys::Function>,
+}
+
+impl KeyLifecycle {
+    /// 取得政策
+    pub fn policy(&self, kind: ManagedKeyKind) -> RotationPolicy {
+        self.policies
+            .get(&kind)
+            .copied()
+            .unwrap_or_else(|| RotationPolicy::default_for(kind))
+    }
+
+    /// 設定政策
+    pub fn set_rotation_policy(&mut self, kind: ManagedKeyKind, policy: RotationPolicy) {
+        self.policies.insert(kind, policy);
+    }
+
+    /// 開始追蹤金鑰
+    pub fn track(&mut self, kind: ManagedKeyKind, key_id: u32, created_at: u64) {
+        self.keys.insert(
+            (kind, key_id),
+            TrackedKey { created_at, uses: 0, notified: false },
+        );
+    }
+
+    /// 記錄一次使用，回傳該金鑰是否需要輪替
+    pub fn record(&mut self, kind: ManagedKeyKind, key_id: u32, now: u64) -> Result<bool, String> {
+        let policy = self.policy(kind);
+        let key = self
+            .keys
+            .get_mut(&(kind, key_id))
+            .ok_or_else(|| format!("{:?} {} is not tracked", kind, key_id))?;
+        key.uses += 1;
+        Ok(policy.is_due(key, now))
+    }
+
+    /// 需要輪替的金鑰 ID
+    pub fn due_keys(&self, kind: ManagedKeyKind, now: u64) -> Vec<u32> {
+        let policy = self.policy(kind);
+        self.keys
+            .range((kind, 0)..=(kind, u32::MAX))
+            .filter(|(_, key)| policy.is_due(key, now))
+            .map(|((_, id), _)| *id)
+            .collect()
+    }
+
+    /// 取出尚未通知的輪替項目並標記為已通知
+    pub fn take_due_rotations(&mut self, now: u64) -> Vec<(ManagedKeyKind, u32)> {
+        let policies: BTreeMap<_, _> = [
+            ManagedKeyKind::SignedPreKey,
+            ManagedKeyKind::OneTimePreKey,
+            ManagedKeyKind::SenderKey,
+        ]
+        .into_iter()
+        .map(|kind| (kind, self.policy(kind)))
+        .collect();
+
+        self.keys
+            .iter_mut()
+            .filter(|((kind, _), key)| !key.notified && policies[kind].is_due(key, now))
+            .map(|((kind, id), key)| {
+                key.notified = true;
+                (*kind, *id)
+            })
+            .collect()
+    }
+
+    fn notify(&self, kind: ManagedKeyKind, key_id: u32) {
+        if let Some(callback) = &self.callback {
+            let _ = callback.call2(
+                &JsValue::NULL,
+                &JsValue::from(kind as u32),
+                &JsValue::from(key_id),
+            );
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl KeyLifecycle {
+    /// 建立生命週期追蹤 (使用預設政策)
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            policies: BTreeMap::new(),
+            keys: BTreeMap::new(),
+            callback: None,
+        }
+    }
+
+    /// 設定輪替政策 (0 表示不限制)
+    #[wasm_bindgen(js_name = setPolicy)]
+    pub fn set_policy(&mut self, kind: ManagedKeyKind, max_age_ms: u64, max_uses: u64) {
+        self.set_rotation_policy(kind, RotationPolicy { max_age_ms, max_uses });
+    }
+
+    /// 開始追蹤金鑰
+    #[wasm_bindgen(js_name = trackKey)]
+    pub fn track_key(&mut self, kind: ManagedKeyKind, key_id: u32, created_at: u64) {
+        self.track(kind, key_id, created_at);
+    }
+
+    /// 停止追蹤金鑰 (金鑰已輪替或刪除)
+    #[wasm_bindgen(js_name = removeKey)]
+    pub fn remove_key(&mut self, kind: ManagedKeyKind, key_id: u32) -> bool {
+        self.keys.remove(&(kind, key_id)).is_some()
+    }
+
+    /// 記錄一次使用，回傳該金鑰是否需要輪替
+    ///
+    /// 首次達到門檻時會呼叫輪替 callback
+    #[wasm_bindgen(js_name = recordUse)]
+    pub fn record_use(&mut self, kind: ManagedKeyKind, key_id: u32, now: u64) -> Result<bool, JsError> {
+        let due = self.record(kind, key_id, now).map_err(|e| JsError::new(&e))?;
+        if due {
+            if let Some(key) = self.keys.get_mut(&(kind, key_id)) {
+                if !key.notified {
+                    key.notified = true;
+                    self.notify(kind, key_id);
+                }
+            }
+        }
+        Ok(due)
+    }
+
+    /// 使用次數
+    #[wasm_bindgen(js_name = useCount)]
+    pub fn use_count(&self, kind: ManagedKeyKind, key_id: u32) -> Option<u64> {
+        self.keys.get(&(kind, key_id)).map(|key| key.uses)
+    }
+
+    /// 該類型是否有金鑰需要輪替
+    #[wasm_bindgen(js_name = rotationNeeded)]
+    pub fn rotation_needed(&self, kind: ManagedKeyKind, now: u64) -> bool {
+        !self.due_keys(kind, now).is_empty()
+    }
+
+    /// 指定金鑰是否需要輪替
+    #[wasm_bindgen(js_name = keyRotationNeeded)]
+    pub fn key_rotation_needed(&self, kind: ManagedKeyKind, key_id: u32, now: u64) -> bool {
+        self.keys
+            .get(&(kind, key_id))
+            .is_some_and(|key| self.policy(kind).is_due(key, now))
+    }
+
+    /// 需要輪替的金鑰 ID
+    #[wasm_bindgen(js_name = keysNeedingRotation)]
+    pub fn keys_needing_rotation(&self, kind: ManagedKeyKind, now: u64) -> Vec<u32> {
+        self.due_keys(kind, now)
+    }
+
+    /// 設定輪替 callback：`(kind, keyId) => void`
+    #[wasm_bindgen(js_name = onRotationNeeded)]
+    pub fn on_rotation_needed(&mut self, callback: js_sys::Function) {
+        self.callback = Some(callback);
+    }
+
+    /// 檢查所有金鑰 (例如定時或啟動時)，對新到期的金鑰呼叫 callback
+    ///
+    /// 回傳本次通知的數量
+    #[wasm_bindgen(js_name = checkRotation)]
+    pub fn check_rotation(&mut self, now: u64) -> u32 {
+        let due = self.take_due_rotations(now);
+        for (kind, key_id) in &due {
+            self.notify(*kind, *key_id);
+        }
+        due.len() as u32
+    }
+
+    /// 序列化
+    #[wasm_bindgen(js_name = serialize)]
+    pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 還原
+    #[wasm_bindgen(js_name = deserialize)]
+    pub fn deserialize(bytes: &[u8]) -> Result<KeyLifecycle, JsError> {
+        bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+impl Default for KeyLifecycle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation_by_age_and_usage() {
+        let mut lifecycle = KeyLifecycle::new();
+        lifecycle.track_key(ManagedKeyKind::SignedPreKey, 1, 0);
+        lifecycle.track_key(ManagedKeyKind::OneTimePreKey, 10, 0);
+        lifecycle.track_key(ManagedKeyKind::OneTimePreKey, 11, 0);
+        lifecycle.set_policy(ManagedKeyKind::SenderKey, 0, 3);
+        lifecycle.track_key(ManagedKeyKind::SenderKey, 5, 0);
+
+        assert!(!lifecycle.rotation_needed(ManagedKeyKind::SignedPreKey, DAY_MS));
+        assert!(lifecycle.rotation_needed(ManagedKeyKind::SignedPreKey, 7 * DAY_MS));
+
+        // 一次性預金鑰用一次就要換
+        assert_eq!(lifecycle.record(ManagedKeyKind::OneTimePreKey, 10, 1), Ok(true));
+        assert_eq!(lifecycle.keys_needing_rotation(ManagedKeyKind::OneTimePreKey, 1), vec![10]);
+
+        // sender key 到達使用次數
+        for expected in [false, false, true] {
+            assert_eq!(lifecycle.record(ManagedKeyKind::SenderKey, 5, 1), Ok(expected));
+        }
+        // 不受年齡限制
+        assert!(!lifecycle.key_rotation_needed(ManagedKeyKind::SenderKey, 6, 365 * DAY_MS));
+
+        assert!(lifecycle.record(ManagedKeyKind::SenderKey, 99, 1).is_err());
+    }
+
+    #[test]
+    fn test_due_rotations_reported_once_and_persist() {
+        let mut lifecycle = KeyLifecycle::new();
+        lifecycle.track_key(ManagedKeyKind::SignedPreKey, 1, 0);
+        lifecycle.track_key(ManagedKeyKind::SignedPreKey, 2, 5 * DAY_MS);
+
+        assert_eq!(
+            lifecycle.take_due_rotations(8 * DAY_MS),
+            vec![(ManagedKeyKind::SignedPreKey, 1)]
+        );
+        assert!(lifecycle.take_due_rotations(8 * DAY_MS).is_empty());
+
+        let mut restored = KeyLifecycle::deserialize(&lifecycle.serialize().unwrap()).unwrap();
+        assert_eq!(restored.check_rotation(12 * DAY_MS), 1);
+        assert!(restored.remove_key(ManagedKeyKind::SignedPreKey, 1));
+        assert_eq!(restored.keys_needing_rotation(ManagedKeyKind::SignedPreKey, 12 * DAY_MS), vec![2]);
+    }
+}
@@ -0,0 +1,144 @@
+//! 訊息鑑別碼模組
+//!
+//! 公開 HMAC-SHA256，應用層的完整性檢查 (webhook 驗證、儲存記錄 MAC 等)
+//! 與核心共用同一份實作；驗證一律以常數時間比對
+//!
+//! BLAKE3 keyed 模式 (金鑰固定 32 bytes) 可作為較快的替代 MAC，
+//! 以 `MacAlgorithm` 位元組在格式中選擇
+
+use wasm_bindgen::prelude::*;
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::constant_time::constant_time_eq;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// MAC 演算法 (以單一位元組記錄在格式中)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(into = "u8", try_from = "u8")]
+pub enum MacAlgorithm {
+    #[default]
+    HmacSha256 = 1,
+    Blake3Keyed = 2,
+}
+
+impl From<MacAlgorithm> for u8 {
+    fn from(algorithm: MacAlgorithm) -> u8 {
+        algorithm as u8
+    }
+}
+
+impl TryFrom<u8> for MacAlgorithm {
+    type Error = String;
+
+    fn try_from(byte: u8) -> Result<Self, String> {
+        match byte {
+            1 => Ok(MacAlgorithm::HmacSha256),
+            2 => Ok(MacAlgorithm::Blake3Keyed),
+            _ => Err(format!("Unsupported MAC algorithm: {}", byte)),
+        }
+    }
+}
+
+impl MacAlgorithm {
+    /// 計算 32 bytes 標籤 (BLAKE3 keyed 模式的金鑰必須是 32 bytes)
+    pub fn compute(self, key: &[u8], data: &[u8]) -> Result<[u8; 32], String> {
+        match self {
+            MacAlgorithm::HmacSha256 => Ok(hmac_sha256_bytes(key, data)),
+            MacAlgorithm::Blake3Keyed => {
+                let key: &[u8; 32] = key
+                    .try_into()
+                    .map_err(|_| format!("BLAKE3 keyed MAC key must be 32 bytes, got {}", key.len()))?;
+                Ok(*blake3::keyed_hash(key, data).as_bytes())
+            }
+        }
+    }
+
+    /// 以常數時間驗證標籤
+    pub fn verify(self, key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+        self.compute(key, data).is_ok_and(|expected| constant_time_eq(&expected, tag))
+    }
+}
+
+/// HMAC-SHA256 (Rust 端使用)
+pub fn hmac_sha256_bytes(key: &[u8], data: &[u8]) -> [u8; 32] {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.finalize().into_bytes().into()
+}
+
+/// 以常數時間驗證 HMAC-SHA256 (Rust 端使用)
+///
+/// 長度不是 32 bytes 的標籤一律驗證失敗
+pub fn hmac_sha256_verify(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data);
+    mac.verify_slice(tag).is_ok()
+}
+
+/// 計算 HMAC-SHA256
+#[wasm_bindgen(js_name = hmacSha256)]
+pub fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    hmac_sha256_bytes(key, data).to_vec()
+}
+
+/// 驗證 HMAC-SHA256 標籤 (常數時間)
+#[wasm_bindgen(js_name = hmacVerify)]
+pub fn hmac_verify(key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    hmac_sha256_verify(key, data, tag)
+}
+
+/// 以指定的演算法計算 MAC
+#[wasm_bindgen(js_name = computeMac)]
+pub fn compute_mac(algorithm: MacAlgorithm, key: &[u8], data: &[u8]) -> Result<Vec<u8>, JsError> {
+    algorithm.compute(key, data).map(|tag| tag.to_vec()).map_err(|e| JsError::new(&e))
+}
+
+/// 以指定的演算法驗證 MAC (常數時間)
+#[wasm_bindgen(js_name = verifyMac)]
+pub fn verify_mac(algorithm: MacAlgorithm, key: &[u8], data: &[u8], tag: &[u8]) -> bool {
+    algorithm.verify(key, data, tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::to_hex;
+
+    #[test]
+    fn test_rfc4231_case_2() {
+        let tag = hmac_sha256_bytes(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(to_hex(&tag), "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+        assert!(hmac_verify(b"Jefe", b"what do ya want for nothing?", &tag));
+    }
+
+    #[test]
+    fn test_hmac_verify_rejects_mismatches() {
+        let tag = hmac_sha256(b"key", b"record");
+        assert!(!hmac_verify(b"key", b"record2", &tag));
+        assert!(!hmac_verify(b"other", b"record", &tag));
+        assert!(!hmac_verify(b"key", b"record", &tag[..16]));
+        assert!(!hmac_verify(b"key", b"record", &[]));
+    }
+
+    #[test]
+    fn test_mac_algorithm_selection() {
+        let key = [0x42u8; 32];
+        let hmac = MacAlgorithm::HmacSha256.compute(&key, b"record").unwrap();
+        assert_eq!(hmac, hmac_sha256_bytes(&key, b"record"));
+
+        let keyed = MacAlgorithm::Blake3Keyed.compute(&key, b"record").unwrap();
+        assert_eq!(keyed, *blake3::keyed_hash(&key, b"record").as_bytes());
+        assert_ne!(keyed, hmac);
+        assert!(verify_mac(MacAlgorithm::Blake3Keyed, &key, b"record", &keyed));
+        assert!(!verify_mac(MacAlgorithm::HmacSha256, &key, b"record", &keyed));
+        assert!(!verify_mac(MacAlgorithm::Blake3Keyed, &key[..16], b"record", &keyed));
+        assert!(MacAlgorithm::Blake3Keyed.compute(b"short", b"record").is_err());
+
+        assert_eq!(MacAlgorithm::try_from(2), Ok(MacAlgorithm::Blake3Keyed));
+        assert!(MacAlgorithm::try_from(0).is_err());
+    }
+}
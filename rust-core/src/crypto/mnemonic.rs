@@ -0,0 +1,130 @@
+//! 助記詞備份模組
+//!
+//! 以 BIP39 助記詞 (12 或 24 個英文單字) 作為身份金鑰的紙本備份。
+//! BIP39 seed 作為 [`KeyDerivation`] 的主種子，身份金鑰與主儲存金鑰皆由此確定性衍生，
+//! 在新裝置輸入相同助記詞即可還原整個金鑰階層
+
+use wasm_bindgen::prelude::*;
+use rand::{rngs::OsRng, RngCore};
+
+use super::derivation::KeyDerivation;
+use super::keys::IdentityKeyPair;
+
+/// BIP39 助記詞備份
+#[wasm_bindgen]
+pub struct MnemonicBackup {
+    mnemonic: bip39::Mnemonic,
+}
+
+impl MnemonicBackup {
+    /// 產生新的助記詞 (12 或 24 個單字)
+    pub fn generate_words(word_count: usize) -> Result<Self, String> {
+        let entropy_len = match word_count {
+            12 => 16,
+            24 => 32,
+            _ => return Err(format!("Word count must be 12 or 24, got {}", word_count)),
+        };
+        let mut entropy = vec![0u8; entropy_len];
+        OsRng.fill_bytes(&mut entropy);
+        let mnemonic = bip39::Mnemonic::from_entropy(&entropy).map_err(|e| e.to_string())?;
+        Ok(Self { mnemonic })
+    }
+
+    /// 解析使用者輸入的助記詞 (檢查單字與校驗碼)
+    pub fn parse(phrase: &str) -> Result<Self, String> {
+        let normalized = phrase.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        let mnemonic = bip39::Mnemonic::parse_normalized(&normalized)
+            .map_err(|e| format!("Invalid mnemonic: {}", e))?;
+        match mnemonic.word_count() {
+            12 | 24 => Ok(Self { mnemonic }),
+            n => Err(format!("Word count must be 12 or 24, got {}", n)),
+        }
+    }
+
+    /// 以 BIP39 seed 為主種子的金鑰階層
+    pub fn derivation(&self) -> KeyDerivation {
+        KeyDerivation::from_seed(&self.mnemonic.to_seed_normalized(""))
+            .expect("BIP39 seed is 64 bytes")
+    }
+}
+
+#[wasm_bindgen]
+impl MnemonicBackup {
+    /// 產生新的助記詞
+    #[wasm_bindgen(js_name = generate)]
+    pub fn generate(word_count: u32) -> Result<MnemonicBackup, JsError> {
+        Self::generate_words(word_count as usize).map_err(|e| JsError::new(&e))
+    }
+
+    /// 從助記詞還原
+    #[wasm_bindgen(js_name = fromPhrase)]
+    pub fn from_phrase(phrase: &str) -> Result<MnemonicBackup, JsError> {
+        Self::parse(phrase).map_err(|e| JsError::new(&e))
+    }
+
+    /// 助記詞 (以空白分隔，敏感！)
+    #[wasm_bindgen(getter)]
+    pub fn phrase(&self) -> String {
+        self.mnemonic.to_string()
+    }
+
+    /// 單字數量
+    #[wasm_bindgen(getter, js_name = wordCount)]
+    pub fn word_count(&self) -> usize {
+        self.mnemonic.word_count()
+    }
+
+    /// 取得金鑰階層
+    #[wasm_bindgen(js_name = keyDerivation)]
+    pub fn key_derivation(&self) -> KeyDerivation {
+        self.derivation()
+    }
+
+    /// 衍生身份金鑰對
+    #[wasm_bindgen(js_name = identityKeyPair)]
+    pub fn identity_key_pair(&self) -> Result<IdentityKeyPair, JsError> {
+        self.derivation().identity_key_pair()
+    }
+
+    /// 衍生主儲存金鑰 (32 bytes)
+    #[wasm_bindgen(js_name = storageKey)]
+    pub fn storage_key(&self) -> Vec<u8> {
+        self.derivation().storage_key()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mnemonic_restore() {
+        let backup = MnemonicBackup::generate_words(24).unwrap();
+        assert_eq!(backup.word_count(), 24);
+
+        // 使用者輸入時可能有大小寫與多餘空白
+        let typed = format!("  {}  ", backup.phrase().to_uppercase().replace(' ', "   "));
+        let restored = MnemonicBackup::parse(&typed).unwrap();
+
+        assert_eq!(
+            backup.identity_key_pair().unwrap().public_key_bytes(),
+            restored.identity_key_pair().unwrap().public_key_bytes()
+        );
+        assert_eq!(backup.storage_key(), restored.storage_key());
+        assert_ne!(backup.storage_key(), backup.identity_key_pair().unwrap().private_key_bytes().expose());
+    }
+
+    #[test]
+    fn test_mnemonic_rejects_invalid_phrase() {
+        assert!(MnemonicBackup::generate_words(15).is_err());
+
+        // 校驗碼錯誤
+        let phrase = "abandon ".repeat(12);
+        assert!(MnemonicBackup::parse(&phrase).is_err());
+
+        let valid = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+        let backup = MnemonicBackup::parse(valid).unwrap();
+        assert_eq!(backup.phrase(), valid);
+        assert!(MnemonicBackup::parse("abandon abandon notaword").is_err());
+    }
+}
@@ -4,14 +4,175 @@
 //! - 金鑰生成與管理 (Ed25519, X25519)
 //! - X3DH 金鑰交換
 //! - Double Ratchet 協定
+//! - libsignal-protocol-javascript 會話匯入
+//! - Olm 會話互通 (Matrix)
 //! - AES-GCM 對稱加密
+//! - 一次性預金鑰池
+//! - 安全碼 (Safety Number)
+//! - 短驗證字串 (SAS)
+//! - XEdDSA 簽章
+//! - Ed25519ph 預雜湊簽章
+//! - PKCS#8 / PEM 金鑰格式
+//! - 密碼保護的私鑰匯出 (Argon2id + AES-GCM)
+//! - BIP39 助記詞備份
+//! - 階層式金鑰衍生
+//! - 註冊 ID 與裝置 ID
+//! - 裝置連結 (Provisioning)
+//! - 跨裝置同步訊息
+//! - 裝置交叉簽署
+//! - 金鑰透明化日誌驗證
+//! - 聯絡人探索
+//! - 使用者名稱證明
+//! - OPAQUE 密碼驗證
+//! - PIN 安全值復原 (SVR)
+//! - Shamir 秘密分享
+//! - Ristretto255 群運算
+//! - P-256 ECDH/ECDSA (`p256` feature)
+//! - WebAuthn / Passkey 身份綁定
+//! - 金鑰生命週期與輪替
+//! - Ed25519 ↔ X25519 金鑰轉換
+//! - 身份金鑰撤銷
+//! - 金鑰更新公告
+//! - OpenPGP 身份匯出
+//! - DID (did:key) 文件
+//! - JWS / JWT (EdDSA)
+//! - HPKE (RFC 9180) 單次加密
+//! - 身份公鑰加密 (ECIES)
+//! - 網域分隔簽章
+//! - 附件加密
+//! - 舊式附件加密 (Signal 相容 AES-CBC + HMAC-SHA256)
+//! - 貼圖包加密 (manifest、貼圖與分享內容)
+//! - 頭像加密 (profile key、固定大小填充)
+//! - Padmé 長度填充
+//! - AEAD 抽象 (AES-256-GCM / ChaCha20-Poly1305)
+//! - XChaCha20-Poly1305 (隨機 nonce)
+//! - 金鑰包裝 (AES-KW)
+//! - 標準 AAD 建構
+//! - 金鑰衍生 (HKDF)
+//! - 訊息鑑別碼 (HMAC-SHA256 / BLAKE3 keyed)
+//! - 雜湊 (SHA-256 / SHA-512 / BLAKE3)
+//! - 密碼金鑰衍生 (Argon2id / scrypt / PBKDF2)
+//! - 常數時間比對
+//! - 秘密位元組 (清零、不可序列化)
+//! - 加密前壓縮 (DEFLATE，需明確啟用)
+//! - age 檔案加密 (X25519 收件者)
+//! - WebCrypto AES-GCM 後端 (feature = "webcrypto")
 
 pub mod keys;
 pub mod x3dh;
 pub mod ratchet;
+pub mod libsignal_import;
+pub mod olm;
 pub mod aes;
+pub mod prekeys;
+pub mod fingerprint;
+pub mod sas;
+pub mod xeddsa;
+pub mod prehash;
+pub mod pkcs8;
+pub mod export;
+pub mod mnemonic;
+pub mod derivation;
+pub mod device;
+pub mod provisioning;
+pub mod sync;
+pub mod cross_signing;
+pub mod transparency;
+pub mod discovery;
+pub mod username;
+pub mod opaque;
+pub mod svr;
+pub mod shamir;
+pub mod ristretto;
+#[cfg(feature = "p256")]
+pub mod p256;
+pub mod webauthn;
+pub mod lifecycle;
+pub mod convert;
+pub mod revocation;
+pub mod key_update;
+pub mod openpgp;
+pub mod did;
+pub mod jws;
+pub mod hpke;
+pub mod identity_encryption;
+pub mod context_signature;
+pub mod attachment;
+pub mod legacy_attachment;
+pub mod sticker;
+pub mod avatar;
+pub mod padding;
+pub mod aead;
+pub mod xchacha;
+pub mod key_wrap;
+pub mod aad;
+pub mod kdf;
+pub mod mac;
+pub mod hash;
+pub mod password;
+pub mod constant_time;
+pub(crate) mod protobuf;
+pub mod secret;
+pub mod compression;
+pub mod age;
+#[cfg(feature = "webcrypto")]
+pub mod webcrypto;
 
 pub use keys::*;
 pub use x3dh::*;
 pub use ratchet::*;
+pub use libsignal_import::*;
+pub use olm::*;
 pub use aes::*;
+pub use prekeys::*;
+pub use fingerprint::*;
+pub use sas::*;
+pub use xeddsa::*;
+pub use prehash::*;
+pub use pkcs8::*;
+pub use export::*;
+pub use mnemonic::*;
+pub use derivation::*;
+pub use device::*;
+pub use provisioning::*;
+pub use sync::*;
+pub use cross_signing::*;
+pub use transparency::*;
+pub use discovery::*;
+pub use username::*;
+pub use opaque::*;
+pub use svr::*;
+pub use shamir::*;
+pub use ristretto::*;
+#[cfg(feature = "p256")]
+pub use self::p256::*;
+pub use webauthn::*;
+pub use lifecycle::*;
+pub use convert::*;
+pub use revocation::*;
+pub use key_update::*;
+pub use openpgp::*;
+pub use did::*;
+pub use jws::*;
+pub use hpke::*;
+pub use identity_encryption::*;
+pub use context_signature::*;
+pub use attachment::*;
+pub use legacy_attachment::*;
+pub use sticker::*;
+pub use avatar::*;
+pub use padding::*;
+pub use aead::*;
+pub use xchacha::*;
+pub use key_wrap::*;
+pub use aad::*;
+pub use kdf::*;
+pub use mac::*;
+pub use hash::*;
+pub use password::*;
+pub use constant_time::*;
+pub use secret::*;
+pub use compression::*;
+pub use age::*;
+#[cfg(feature = "webcrypto")]
+pub use webcrypto::*;
@@ -0,0 +1,650 @@
+//! Olm 互通模組
+//!
+//! 實作 Matrix 一對一加密使用的 Olm 會話 (與 libolm / vodozemac 相同的金鑰衍生與訊息格式)，
+//! mist 用戶端可直接與 Matrix 裝置交換 `m.olm.v1.curve25519-aes-sha2` 訊息，不需要明文閘道：
+//! - 3DH 建立會話：`S = DH(I_A, E_B) || DH(E_A, I_B) || DH(E_A, E_B)`，
+//!   `R0 || C0 = HKDF(S, "OLM_ROOT")`，E_B 為對方的一次性金鑰、E_A 為發起方的 base key
+//! - Double Ratchet：`HKDF(salt = 根金鑰, DH, "OLM_RATCHET")`；
+//!   鏈金鑰 `HMAC(C, 0x02)`、訊息金鑰 `HMAC(C, 0x01)`
+//! - 訊息加密：`HKDF(M, "OLM_KEYS")` 衍生 AES-256-CBC 金鑰、HMAC 金鑰與 IV，
+//!   MAC 為 HMAC-SHA256 的前 8 bytes
+//!
+//! 訊息格式 (version 3，欄位以 protobuf 編碼，見 `protobuf` 模組)：
+//!
+//! ```text
+//! 一般訊息 (type 1)：0x03 || ratchet_key (1) || chain_index (2) || 密文 (4) || MAC (8)
+//! pre-key 訊息 (type 0)：0x03 || one_time_key (1) || base_key (2) || identity_key (3) || 一般訊息 (4)
+//! ```
+//!
+//! 發起方在收到對方任何訊息前持續送出 pre-key 訊息。
+//! Olm 身份金鑰為 X25519 (`X25519KeyPair`)；一次性金鑰的簽章與上傳、Megolm 群組會話不在此模組範圍
+
+use aes::Aes256;
+use base64::{engine::general_purpose::STANDARD_NO_PAD as BASE64, Engine as _};
+use cbc::cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroize;
+
+use super::constant_time::constant_time_eq;
+use super::kdf::hkdf_sha256;
+use super::keys::X25519KeyPair;
+use super::mac::hmac_sha256_bytes;
+use super::protobuf::{bytes_field, fields, varint_field, write_bytes_field, write_varint_field, Field};
+
+/// Olm 訊息版本
+pub const OLM_MESSAGE_VERSION: u8 = 3;
+/// pre-key 訊息類型
+pub const OLM_MESSAGE_TYPE_PRE_KEY: u32 = 0;
+/// 一般訊息類型
+pub const OLM_MESSAGE_TYPE_NORMAL: u32 = 1;
+
+const MAC_SIZE: usize = 8;
+/// 單一鏈可跳過的最大訊息數 (與 libolm 相同)
+const MAX_MESSAGE_GAP: u32 = 2000;
+/// 保留的跳過訊息金鑰上限 (與 libolm 相同)
+const MAX_SKIPPED_KEYS: usize = 40;
+/// 保留的接收鏈上限 (與 libolm 相同)
+const MAX_RECEIVER_CHAINS: usize = 5;
+
+const ROOT_INFO: &[u8] = b"OLM_ROOT";
+const RATCHET_INFO: &[u8] = b"OLM_RATCHET";
+const KEYS_INFO: &[u8] = b"OLM_KEYS";
+
+const FIELD_RATCHET_KEY: u32 = 1;
+const FIELD_CHAIN_INDEX: u32 = 2;
+const FIELD_CIPHERTEXT: u32 = 4;
+const FIELD_ONE_TIME_KEY: u32 = 1;
+const FIELD_BASE_KEY: u32 = 2;
+const FIELD_IDENTITY_KEY: u32 = 3;
+const FIELD_MESSAGE: u32 = 4;
+
+fn public_key(bytes: &[u8], name: &str) -> Result<[u8; 32], String> {
+    bytes.try_into().map_err(|_| format!("Olm {} must be 32 bytes", name))
+}
+
+/// 解析版本位元組之後的欄位 (未知欄位略過)
+fn read_fields(bytes: &[u8]) -> Result<Vec<(u32, Field<'_>)>, String> {
+    match bytes.first() {
+        Some(&OLM_MESSAGE_VERSION) => {}
+        Some(version) => return Err(format!("Unsupported Olm message version {}", version)),
+        None => return Err("Empty Olm message".to_string()),
+    }
+    fields(&bytes[1..]).map_err(|e| format!("Invalid Olm message: {}", e))
+}
+
+/// 訊息金鑰衍生的 AES 金鑰、HMAC 金鑰與 IV
+struct CipherKeys {
+    aes_key: [u8; 32],
+    mac_key: [u8; 32],
+    iv: [u8; 16],
+}
+
+impl CipherKeys {
+    fn derive(message_key: &[u8; 32]) -> Result<Self, String> {
+        let mut okm = hkdf_sha256(&[], message_key, KEYS_INFO, 80)?;
+        let mut keys = Self { aes_key: [0; 32], mac_key: [0; 32], iv: [0; 16] };
+        keys.aes_key.copy_from_slice(&okm[..32]);
+        keys.mac_key.copy_from_slice(&okm[32..64]);
+        keys.iv.copy_from_slice(&okm[64..]);
+        okm.zeroize();
+        Ok(keys)
+    }
+}
+
+impl Drop for CipherKeys {
+    fn drop(&mut self) {
+        self.aes_key.zeroize();
+        self.mac_key.zeroize();
+    }
+}
+
+/// 一般訊息的欄位
+struct NormalMessage<'a> {
+    ratchet_key: [u8; 32],
+    chain_index: u32,
+    ciphertext: &'a [u8],
+}
+
+impl<'a> NormalMessage<'a> {
+    fn parse(body: &'a [u8]) -> Result<Self, String> {
+        if body.len() < 1 + MAC_SIZE {
+            return Err("Truncated Olm message".to_string());
+        }
+        let fields = read_fields(&body[..body.len() - MAC_SIZE])?;
+        let ratchet_key = public_key(bytes_field(&fields, FIELD_RATCHET_KEY).ok_or("Missing ratchet key in Olm message")?, "ratchet key")?;
+        let chain_index = varint_field(&fields, FIELD_CHAIN_INDEX).ok_or("Missing chain index in Olm message")?;
+        let chain_index = u32::try_from(chain_index).map_err(|_| "Invalid chain index in Olm message")?;
+        let ciphertext = bytes_field(&fields, FIELD_CIPHERTEXT).ok_or("Missing ciphertext in Olm message")?;
+        Ok(Self { ratchet_key, chain_index, ciphertext })
+    }
+
+    fn decrypt(&self, body: &[u8], message_key: &[u8; 32]) -> Result<Vec<u8>, String> {
+        let keys = CipherKeys::derive(message_key)?;
+        let (authenticated, mac) = body.split_at(body.len() - MAC_SIZE);
+        if !constant_time_eq(&hmac_sha256_bytes(&keys.mac_key, authenticated)[..MAC_SIZE], mac) {
+            return Err("Olm message authentication failed".to_string());
+        }
+        cbc::Decryptor::<Aes256>::new((&keys.aes_key).into(), (&keys.iv).into())
+            .decrypt_padded_vec_mut::<Pkcs7>(self.ciphertext)
+            .map_err(|_| "Olm message authentication failed".to_string())
+    }
+}
+
+fn encode_normal_message(ratchet_key: &[u8; 32], chain_index: u32, message_key: &[u8; 32], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let keys = CipherKeys::derive(message_key)?;
+    let ciphertext = cbc::Encryptor::<Aes256>::new((&keys.aes_key).into(), (&keys.iv).into()).encrypt_padded_vec_mut::<Pkcs7>(plaintext);
+    let mut body = vec![OLM_MESSAGE_VERSION];
+    write_bytes_field(&mut body, FIELD_RATCHET_KEY, ratchet_key);
+    write_varint_field(&mut body, FIELD_CHAIN_INDEX, u64::from(chain_index));
+    write_bytes_field(&mut body, FIELD_CIPHERTEXT, &ciphertext);
+    let mac = hmac_sha256_bytes(&keys.mac_key, &body);
+    body.extend_from_slice(&mac[..MAC_SIZE]);
+    Ok(body)
+}
+
+/// pre-key 訊息
+///
+/// 接收方先以 `oneTimeKey` 找出對應的一次性私鑰，再以 `OlmSession.newInbound` 建立會話
+#[wasm_bindgen]
+pub struct OlmPreKeyMessage {
+    one_time_key: [u8; 32],
+    base_key: [u8; 32],
+    identity_key: [u8; 32],
+    message: Vec<u8>,
+}
+
+impl OlmPreKeyMessage {
+    /// 解析 pre-key 訊息 (Rust 端使用)
+    pub fn parse(body: &[u8]) -> Result<Self, String> {
+        let fields = read_fields(body)?;
+        let field = |number, name: &str| -> Result<&[u8], String> {
+            bytes_field(&fields, number).ok_or_else(|| format!("Missing {} in Olm pre-key message", name))
+        };
+        Ok(Self {
+            one_time_key: public_key(field(FIELD_ONE_TIME_KEY, "one-time key")?, "one-time key")?,
+            base_key: public_key(field(FIELD_BASE_KEY, "base key")?, "base key")?,
+            identity_key: public_key(field(FIELD_IDENTITY_KEY, "identity key")?, "identity key")?,
+            message: field(FIELD_MESSAGE, "message")?.to_vec(),
+        })
+    }
+
+    fn encode(keys: &SessionKeys, message: &[u8]) -> Vec<u8> {
+        let mut body = vec![OLM_MESSAGE_VERSION];
+        write_bytes_field(&mut body, FIELD_ONE_TIME_KEY, &keys.one_time_key);
+        write_bytes_field(&mut body, FIELD_BASE_KEY, &keys.base_key);
+        write_bytes_field(&mut body, FIELD_IDENTITY_KEY, &keys.identity_key);
+        write_bytes_field(&mut body, FIELD_MESSAGE, message);
+        body
+    }
+}
+
+#[wasm_bindgen]
+impl OlmPreKeyMessage {
+    /// 解析 pre-key 訊息 (`type` 為 0 的訊息本體)
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(body: &[u8]) -> Result<OlmPreKeyMessage, JsError> {
+        Self::parse(body).map_err(|e| JsError::new(&e))
+    }
+
+    /// 接收方被使用的一次性公鑰
+    #[wasm_bindgen(getter, js_name = oneTimeKey)]
+    pub fn one_time_key(&self) -> Vec<u8> {
+        self.one_time_key.to_vec()
+    }
+
+    /// 發起方的 base key
+    #[wasm_bindgen(getter, js_name = baseKey)]
+    pub fn base_key(&self) -> Vec<u8> {
+        self.base_key.to_vec()
+    }
+
+    /// 發起方的身份公鑰 (Curve25519)
+    #[wasm_bindgen(getter, js_name = identityKey)]
+    pub fn identity_key(&self) -> Vec<u8> {
+        self.identity_key.to_vec()
+    }
+}
+
+/// 加密後的 Olm 訊息 (Matrix 事件中的 `type` 與 `body`)
+#[wasm_bindgen]
+pub struct OlmMessage {
+    message_type: u32,
+    body: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl OlmMessage {
+    /// 0 為 pre-key 訊息、1 為一般訊息
+    #[wasm_bindgen(getter, js_name = messageType)]
+    pub fn message_type(&self) -> u32 {
+        self.message_type
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn body(&self) -> Vec<u8> {
+        self.body.clone()
+    }
+
+    /// Matrix 事件使用的 `body` (無填充 base64)
+    #[wasm_bindgen(getter, js_name = bodyBase64)]
+    pub fn body_base64(&self) -> String {
+        BASE64.encode(&self.body)
+    }
+}
+
+/// 建立會話時使用的公鑰 (發起方身份金鑰、base key、接收方一次性金鑰)
+#[derive(Clone, Serialize, Deserialize)]
+struct SessionKeys {
+    identity_key: [u8; 32],
+    base_key: [u8; 32],
+    one_time_key: [u8; 32],
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ChainKey {
+    key: [u8; 32],
+    index: u32,
+}
+
+impl ChainKey {
+    fn message_key(&self) -> [u8; 32] {
+        hmac_sha256_bytes(&self.key, &[0x01])
+    }
+
+    fn advance(&mut self) {
+        self.key = hmac_sha256_bytes(&self.key, &[0x02]);
+        self.index += 1;
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SenderChain {
+    ratchet_private: [u8; 32],
+    ratchet_public: [u8; 32],
+    chain: ChainKey,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct ReceiverChain {
+    ratchet_key: [u8; 32],
+    chain: ChainKey,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+struct SkippedKey {
+    ratchet_key: [u8; 32],
+    index: u32,
+    message_key: [u8; 32],
+}
+
+/// 推進根金鑰：回傳新的根金鑰與鏈金鑰
+fn advance_root(root_key: &[u8; 32], shared: &[u8; 32]) -> Result<([u8; 32], ChainKey), String> {
+    split_root(&hkdf_sha256(root_key, shared, RATCHET_INFO, 64)?)
+}
+
+fn split_root(okm: &[u8]) -> Result<([u8; 32], ChainKey), String> {
+    let mut root = [0u8; 32];
+    let mut chain = [0u8; 32];
+    root.copy_from_slice(&okm[..32]);
+    chain.copy_from_slice(&okm[32..64]);
+    Ok((root, ChainKey { key: chain, index: 0 }))
+}
+
+/// Olm 會話
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OlmSession {
+    keys: SessionKeys,
+    root_key: [u8; 32],
+    sender: Option<SenderChain>,
+    /// 最新的接收鏈在前
+    receivers: Vec<ReceiverChain>,
+    skipped: Vec<SkippedKey>,
+    /// 發起方建立的會話 (收到回覆前送出 pre-key 訊息)
+    outbound: bool,
+    received_message: bool,
+}
+
+impl OlmSession {
+    /// 發起方建立會話 (Rust 端使用)
+    pub fn outbound(identity: &X25519KeyPair, their_identity_key: &[u8], their_one_time_key: &[u8]) -> Result<Self, String> {
+        let their_identity_key = public_key(their_identity_key, "identity key")?;
+        let their_one_time_key = public_key(their_one_time_key, "one-time key")?;
+        let base_key = X25519KeyPair::new();
+        let ratchet_key = X25519KeyPair::new();
+
+        let mut secret = [
+            identity.shared_secret(&their_one_time_key)?,
+            base_key.shared_secret(&their_identity_key)?,
+            base_key.shared_secret(&their_one_time_key)?,
+        ]
+        .concat();
+        let derived = hkdf_sha256(&[], &secret, ROOT_INFO, 64);
+        secret.zeroize();
+        let (root_key, chain) = split_root(&derived?)?;
+
+        Ok(Self {
+            keys: SessionKeys {
+                identity_key: public_key(&identity.public_key_bytes(), "identity key")?,
+                base_key: public_key(&base_key.public_key_bytes(), "base key")?,
+                one_time_key: their_one_time_key,
+            },
+            root_key,
+            sender: Some(SenderChain {
+                ratchet_private: public_key(ratchet_key.private_key_bytes().expose(), "ratchet key")?,
+                ratchet_public: public_key(&ratchet_key.public_key_bytes(), "ratchet key")?,
+                chain,
+            }),
+            receivers: Vec::new(),
+            skipped: Vec::new(),
+            outbound: true,
+            received_message: false,
+        })
+    }
+
+    /// 接收方依 pre-key 訊息建立會話 (Rust 端使用)；之後以 `decrypt_message` 解密該訊息
+    pub fn inbound(identity: &X25519KeyPair, one_time_key: &X25519KeyPair, pre_key_message: &[u8]) -> Result<Self, String> {
+        let message = OlmPreKeyMessage::parse(pre_key_message)?;
+        if one_time_key.public_key_bytes() != message.one_time_key {
+            return Err("Olm pre-key message uses a different one-time key".to_string());
+        }
+        let inner = NormalMessage::parse(&message.message)?;
+
+        let mut secret = [
+            one_time_key.shared_secret(&message.identity_key)?,
+            identity.shared_secret(&message.base_key)?,
+            one_time_key.shared_secret(&message.base_key)?,
+        ]
+        .concat();
+        let derived = hkdf_sha256(&[], &secret, ROOT_INFO, 64);
+        secret.zeroize();
+        let (root_key, chain) = split_root(&derived?)?;
+
+        Ok(Self {
+            keys: SessionKeys {
+                identity_key: message.identity_key,
+                base_key: message.base_key,
+                one_time_key: message.one_time_key,
+            },
+            root_key,
+            sender: None,
+            receivers: vec![ReceiverChain { ratchet_key: inner.ratchet_key, chain }],
+            skipped: Vec::new(),
+            outbound: false,
+            received_message: false,
+        })
+    }
+
+    /// 會話 ID (與 libolm 相同：建立會話公鑰的 SHA-256，無填充 base64)
+    pub fn id(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.keys.identity_key);
+        hasher.update(self.keys.base_key);
+        hasher.update(self.keys.one_time_key);
+        BASE64.encode(hasher.finalize())
+    }
+
+    /// pre-key 訊息是否屬於此會話
+    pub fn matches_inbound(&self, pre_key_message: &[u8]) -> bool {
+        OlmPreKeyMessage::parse(pre_key_message).is_ok_and(|message| {
+            message.identity_key == self.keys.identity_key
+                && message.base_key == self.keys.base_key
+                && message.one_time_key == self.keys.one_time_key
+        })
+    }
+
+    /// 加密 (Rust 端使用)
+    pub fn encrypt_message(&mut self, plaintext: &[u8]) -> Result<OlmMessage, String> {
+        if self.sender.is_none() {
+            let receiver = self.receivers.first().ok_or("Olm session has no receiver chain")?;
+            let ratchet_key = X25519KeyPair::new();
+            let shared = ratchet_key.shared_secret(&receiver.ratchet_key)?;
+            let (root_key, chain) = advance_root(&self.root_key, &shared)?;
+            self.root_key = root_key;
+            self.sender = Some(SenderChain {
+                ratchet_private: public_key(ratchet_key.private_key_bytes().expose(), "ratchet key")?,
+                ratchet_public: public_key(&ratchet_key.public_key_bytes(), "ratchet key")?,
+                chain,
+            });
+        }
+        let sender = self.sender.as_mut().ok_or("Olm session has no sender chain")?;
+        let message_key = sender.chain.message_key();
+        let body = encode_normal_message(&sender.ratchet_public, sender.chain.index, &message_key, plaintext)?;
+        sender.chain.advance();
+
+        if self.outbound && !self.received_message {
+            return Ok(OlmMessage {
+                message_type: OLM_MESSAGE_TYPE_PRE_KEY,
+                body: OlmPreKeyMessage::encode(&self.keys, &body),
+            });
+        }
+        Ok(OlmMessage { message_type: OLM_MESSAGE_TYPE_NORMAL, body })
+    }
+
+    /// 解密 (Rust 端使用)
+    pub fn decrypt_message(&mut self, message_type: u32, body: &[u8]) -> Result<Vec<u8>, String> {
+        let pre_key_inner;
+        let body = match message_type {
+            OLM_MESSAGE_TYPE_NORMAL => body,
+            OLM_MESSAGE_TYPE_PRE_KEY => {
+                if !self.matches_inbound(body) {
+                    return Err("Olm pre-key message does not match this session".to_string());
+                }
+                pre_key_inner = OlmPreKeyMessage::parse(body)?.message;
+                &pre_key_inner[..]
+            }
+            other => return Err(format!("Unknown Olm message type {}", other)),
+        };
+        let message = NormalMessage::parse(body)?;
+
+        let plaintext = match self.receivers.iter().position(|receiver| receiver.ratchet_key == message.ratchet_key) {
+            Some(position) => self.decrypt_with_chain(position, &message, body)?,
+            None => self.decrypt_with_new_chain(&message, body)?,
+        };
+        self.received_message = true;
+        Ok(plaintext)
+    }
+
+    /// 以既有的接收鏈解密 (含先前跳過的訊息)
+    fn decrypt_with_chain(&mut self, position: usize, message: &NormalMessage, body: &[u8]) -> Result<Vec<u8>, String> {
+        let chain = &self.receivers[position].chain;
+        if message.chain_index < chain.index {
+            let skipped = self
+                .skipped
+                .iter()
+                .position(|key| key.ratchet_key == message.ratchet_key && key.index == message.chain_index)
+                .ok_or("Olm message key already used")?;
+            let plaintext = message.decrypt(body, &self.skipped[skipped].message_key)?;
+            self.skipped.remove(skipped);
+            return Ok(plaintext);
+        }
+
+        let mut chain = chain.clone();
+        let skipped = Self::advance_to(&mut chain, message)?;
+        let plaintext = message.decrypt(body, &chain.message_key())?;
+        chain.advance();
+        self.receivers[position].chain = chain;
+        self.store_skipped(skipped);
+        Ok(plaintext)
+    }
+
+    /// 對方換了 ratchet 金鑰：推進根金鑰建立新的接收鏈
+    fn decrypt_with_new_chain(&mut self, message: &NormalMessage, body: &[u8]) -> Result<Vec<u8>, String> {
+        let sender = self.sender.as_ref().ok_or("Olm message uses an unknown ratchet key")?;
+        let ratchet = X25519KeyPair::from_secret_bytes(sender.ratchet_private);
+        let (root_key, mut chain) = advance_root(&self.root_key, &ratchet.shared_secret(&message.ratchet_key)?)?;
+        let skipped = Self::advance_to(&mut chain, message)?;
+        let plaintext = message.decrypt(body, &chain.message_key())?;
+        chain.advance();
+
+        self.root_key = root_key;
+        self.sender = None;
+        self.receivers.insert(0, ReceiverChain { ratchet_key: message.ratchet_key, chain });
+        self.receivers.truncate(MAX_RECEIVER_CHAINS);
+        self.store_skipped(skipped);
+        Ok(plaintext)
+    }
+
+    /// 推進鏈到訊息的編號，回傳途中跳過的訊息金鑰
+    fn advance_to(chain: &mut ChainKey, message: &NormalMessage) -> Result<Vec<SkippedKey>, String> {
+        if message.chain_index - chain.index > MAX_MESSAGE_GAP {
+            return Err("Olm message gap too large".to_string());
+        }
+        let mut skipped = Vec::new();
+        while chain.index < message.chain_index {
+            skipped.push(SkippedKey {
+                ratchet_key: message.ratchet_key,
+                index: chain.index,
+                message_key: chain.message_key(),
+            });
+            chain.advance();
+        }
+        Ok(skipped)
+    }
+
+    fn store_skipped(&mut self, skipped: Vec<SkippedKey>) {
+        self.skipped.extend(skipped);
+        let excess = self.skipped.len().saturating_sub(MAX_SKIPPED_KEYS);
+        self.skipped.drain(..excess);
+    }
+}
+
+#[wasm_bindgen]
+impl OlmSession {
+    /// 以對方的身份金鑰與一次性金鑰 (皆為 Curve25519) 建立會話
+    #[wasm_bindgen(js_name = newOutbound)]
+    pub fn new_outbound(identity: &X25519KeyPair, their_identity_key: &[u8], their_one_time_key: &[u8]) -> Result<OlmSession, JsError> {
+        Self::outbound(identity, their_identity_key, their_one_time_key).map_err(|e| JsError::new(&e))
+    }
+
+    /// 依收到的 pre-key 訊息建立會話；之後呼叫 `decrypt(0, body)` 取得內容
+    ///
+    /// 一次性私鑰應在解密成功後刪除
+    #[wasm_bindgen(js_name = newInbound)]
+    pub fn new_inbound(identity: &X25519KeyPair, one_time_key: &X25519KeyPair, pre_key_message: &[u8]) -> Result<OlmSession, JsError> {
+        Self::inbound(identity, one_time_key, pre_key_message).map_err(|e| JsError::new(&e))
+    }
+
+    /// 會話 ID
+    #[wasm_bindgen(getter, js_name = sessionId)]
+    pub fn session_id(&self) -> String {
+        self.id()
+    }
+
+    /// pre-key 訊息是否屬於此會話 (重送的 pre-key 訊息應交給既有會話)
+    #[wasm_bindgen(js_name = matchesInbound)]
+    pub fn matches_inbound_js(&self, pre_key_message: &[u8]) -> bool {
+        self.matches_inbound(pre_key_message)
+    }
+
+    /// 是否已收到對方的訊息 (之後送出一般訊息)
+    #[wasm_bindgen(getter, js_name = hasReceivedMessage)]
+    pub fn has_received_message(&self) -> bool {
+        self.received_message
+    }
+
+    #[wasm_bindgen(js_name = encrypt)]
+    pub fn encrypt_js(&mut self, plaintext: &[u8]) -> Result<OlmMessage, JsError> {
+        self.encrypt_message(plaintext).map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密 Matrix 事件的 `type` 與 `body` (base64 解碼後)
+    #[wasm_bindgen(js_name = decrypt)]
+    pub fn decrypt_js(&mut self, message_type: u32, body: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.decrypt_message(message_type, body).map_err(|e| JsError::new(&e))
+    }
+
+    /// 序列化會話狀態
+    #[wasm_bindgen(js_name = serialize)]
+    pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 還原會話狀態
+    #[wasm_bindgen(js_name = deserialize)]
+    pub fn deserialize(bytes: &[u8]) -> Result<OlmSession, JsError> {
+        bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+
+    /// 以獨立實作依 Olm 規格產生的 pre-key 訊息：Alice 的身份金鑰、base key、ratchet 金鑰與
+    /// Bob 的身份金鑰、一次性金鑰的私鑰分別為 32 bytes 的 0x01、0x02、0x03、0x04、0x05，
+    /// 兩則訊息為 chain index 0 與 1
+    const OLM_PRE_KEY_VECTORS: [&str; 2] = [
+        concat!(
+            "030a2050a61409b1ddd0325e9b16b700e719e9772c07000b1bd7786e907c653d20495d1220ce8d3ad1ccb633ec7b70c17814a5c76ecd0296",
+            "85050d344745ba05870e587d591a20a4e09292b651c278b9772c569f5fa9bb13d906b46ab68c9df9dc2b4409f8a209223f030a205dfedd3b",
+            "6bd47f6fa28ee15d969d5bb0ea53774d488bdaf9df1c6e0124b3ef22100022105c6ef9b6394d32d3f762b71691028beb4ae9ef561a0e5eb8",
+        ),
+        concat!(
+            "030a2050a61409b1ddd0325e9b16b700e719e9772c07000b1bd7786e907c653d20495d1220ce8d3ad1ccb633ec7b70c17814a5c76ecd0296",
+            "85050d344745ba05870e587d591a20a4e09292b651c278b9772c569f5fa9bb13d906b46ab68c9df9dc2b4409f8a209223f030a205dfedd3b",
+            "6bd47f6fa28ee15d969d5bb0ea53774d488bdaf9df1c6e0124b3ef22100122108b3a0ef62c3a6cc37a76926a51338e942f6299477b47ca47",
+        ),
+    ];
+    const OLM_SESSION_ID_VECTOR: &str = "8i02nn1Wkd1p+ExLP+LYyS4acPLBipSl5DkmqoL/NR8";
+
+    #[test]
+    fn test_olm_known_answer() {
+        let bob_identity = X25519KeyPair::from_secret_bytes([4u8; 32]);
+        let bob_one_time = X25519KeyPair::from_secret_bytes([5u8; 32]);
+        let first = hex(OLM_PRE_KEY_VECTORS[0]);
+        let second = hex(OLM_PRE_KEY_VECTORS[1]);
+        let prekey = OlmPreKeyMessage::parse(&first).unwrap();
+        assert_eq!(prekey.identity_key(), X25519KeyPair::from_secret_bytes([1u8; 32]).public_key_bytes());
+        assert_eq!(prekey.base_key(), X25519KeyPair::from_secret_bytes([2u8; 32]).public_key_bytes());
+
+        let mut bob = OlmSession::inbound(&bob_identity, &bob_one_time, &first).unwrap();
+        assert_eq!(bob.id(), OLM_SESSION_ID_VECTOR);
+        assert_eq!(bob.decrypt_message(OLM_MESSAGE_TYPE_PRE_KEY, &first).unwrap(), b"Hello, Matrix!");
+        assert_eq!(bob.decrypt_message(OLM_MESSAGE_TYPE_PRE_KEY, &second).unwrap(), b"second message");
+
+        // 重新編碼的欄位與原訊息逐 byte 相同
+        let inner = NormalMessage::parse(&prekey.message).unwrap();
+        assert_eq!(inner.chain_index, 0);
+        assert_eq!(inner.ratchet_key.to_vec(), X25519KeyPair::from_secret_bytes([3u8; 32]).public_key_bytes());
+        let keys = SessionKeys { identity_key: prekey.identity_key, base_key: prekey.base_key, one_time_key: prekey.one_time_key };
+        assert_eq!(OlmPreKeyMessage::encode(&keys, &prekey.message), first);
+    }
+
+    #[test]
+    fn test_olm_session_round_trip() {
+        let (alice_identity, bob_identity) = (X25519KeyPair::new(), X25519KeyPair::new());
+        let bob_one_time = X25519KeyPair::new();
+        let mut alice = OlmSession::outbound(&alice_identity, &bob_identity.public_key_bytes(), &bob_one_time.public_key_bytes()).unwrap();
+
+        let first = alice.encrypt_message(b"hello matrix").unwrap();
+        let second = alice.encrypt_message(b"second").unwrap();
+        assert_eq!(first.message_type(), OLM_MESSAGE_TYPE_PRE_KEY);
+        let prekey = OlmPreKeyMessage::parse(&first.body()).unwrap();
+        assert_eq!(prekey.identity_key(), alice_identity.public_key_bytes());
+
+        let mut bob = OlmSession::inbound(&bob_identity, &bob_one_time, &first.body()).unwrap();
+        assert_eq!(bob.id(), alice.id());
+        assert!(bob.matches_inbound(&second.body()));
+        // 亂序：先收第二則
+        assert_eq!(bob.decrypt_message(second.message_type(), &second.body()).unwrap(), b"second");
+        assert_eq!(bob.decrypt_message(first.message_type(), &first.body()).unwrap(), b"hello matrix");
+        assert!(bob.decrypt_message(first.message_type(), &first.body()).is_err());
+
+        let reply = bob.encrypt_message(b"reply").unwrap();
+        assert_eq!(reply.message_type(), OLM_MESSAGE_TYPE_NORMAL);
+        assert_eq!(alice.decrypt_message(reply.message_type(), &reply.body()).unwrap(), b"reply");
+
+        // 收到回覆後改送一般訊息，並換新的 ratchet 金鑰
+        let next = alice.encrypt_message(b"ratcheted").unwrap();
+        assert_eq!(next.message_type(), OLM_MESSAGE_TYPE_NORMAL);
+        let mut tampered = next.body();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        assert!(bob.decrypt_message(OLM_MESSAGE_TYPE_NORMAL, &tampered).is_err());
+        assert_eq!(bob.decrypt_message(next.message_type(), &next.body()).unwrap(), b"ratcheted");
+    }
+}
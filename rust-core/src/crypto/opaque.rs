@@ -0,0 +1,256 @@
+//! OPAQUE 密碼驗證模組
+//!
+//! 依 OPAQUE (RFC 9807) 實作客戶端的註冊與登入流程：
+//! 伺服器永遠看不到密碼，也無法離線暴力破解。
+//! 登入成功後得到的 export key 只有客戶端知道，可用來保護遠端備份金鑰
+//!
+//! 套件：ristretto255 OPRF + 3DH 金鑰交換 + Argon2id 金鑰延展
+
+use wasm_bindgen::prelude::*;
+use opaque_ke::{
+    ClientLogin, ClientLoginFinishParameters, ClientRegistration, ClientRegistrationFinishParameters,
+    CipherSuite, CredentialResponse, RegistrationResponse,
+};
+use rand::rngs::OsRng;
+
+/// SafeTalk 使用的 OPAQUE 套件
+pub struct SafeTalkOpaque;
+
+impl CipherSuite for SafeTalkOpaque {
+    type OprfCs = opaque_ke::Ristretto255;
+    type KeGroup = opaque_ke::Ristretto255;
+    type KeyExchange = opaque_ke::key_exchange::tripledh::TripleDh;
+    type Ksf = argon2::Argon2<'static>;
+}
+
+/// OPAQUE 註冊 (客戶端)
+///
+/// 1. `start` 產生註冊請求送給伺服器
+/// 2. 以伺服器回應呼叫 `finish`，把 `upload` 送回伺服器並保存 `exportKey`
+#[wasm_bindgen]
+pub struct OpaqueRegistration {
+    state: Option<ClientRegistration<SafeTalkOpaque>>,
+    request: Vec<u8>,
+}
+
+/// OPAQUE 註冊結果
+#[wasm_bindgen]
+pub struct OpaqueRegistrationResult {
+    upload: Vec<u8>,
+    export_key: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl OpaqueRegistrationResult {
+    /// 要上傳給伺服器的註冊資料
+    #[wasm_bindgen(getter)]
+    pub fn upload(&self) -> Vec<u8> {
+        self.upload.clone()
+    }
+
+    /// export key (64 bytes，只有客戶端知道)
+    #[wasm_bindgen(getter, js_name = exportKey)]
+    pub fn export_key(&self) -> Vec<u8> {
+        self.export_key.clone()
+    }
+}
+
+impl OpaqueRegistration {
+    /// 開始註冊
+    pub fn begin(password: &[u8]) -> Result<Self, String> {
+        let result = ClientRegistration::<SafeTalkOpaque>::start(&mut OsRng, password)
+            .map_err(|e| format!("OPAQUE registration failed: {}", e))?;
+        Ok(Self {
+            request: result.message.serialize().to_vec(),
+            state: Some(result.state),
+        })
+    }
+
+    /// 完成註冊
+    pub fn complete(&mut self, password: &[u8], response: &[u8]) -> Result<OpaqueRegistrationResult, String> {
+        let state = self
+            .state
+            .take()
+            .ok_or_else(|| "Registration already finished".to_string())?;
+        let response = RegistrationResponse::<SafeTalkOpaque>::deserialize(response)
+            .map_err(|e| format!("Invalid registration response: {}", e))?;
+        let result = state
+            .finish(&mut OsRng, password, response, ClientRegistrationFinishParameters::default())
+            .map_err(|e| format!("OPAQUE registration failed: {}", e))?;
+        Ok(OpaqueRegistrationResult {
+            upload: result.message.serialize().to_vec(),
+            export_key: result.export_key.to_vec(),
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl OpaqueRegistration {
+    /// 開始註冊
+    #[wasm_bindgen(js_name = start)]
+    pub fn start(password: &[u8]) -> Result<OpaqueRegistration, JsError> {
+        Self::begin(password).map_err(|e| JsError::new(&e))
+    }
+
+    /// 要送給伺服器的註冊請求
+    #[wasm_bindgen(getter)]
+    pub fn request(&self) -> Vec<u8> {
+        self.request.clone()
+    }
+
+    /// 以伺服器回應完成註冊
+    #[wasm_bindgen(js_name = finish)]
+    pub fn finish(&mut self, password: &[u8], response: &[u8]) -> Result<OpaqueRegistrationResult, JsError> {
+        self.complete(password, response).map_err(|e| JsError::new(&e))
+    }
+}
+
+/// OPAQUE 登入 (客戶端)
+///
+/// 1. `start` 產生憑證請求送給伺服器
+/// 2. 以伺服器回應呼叫 `finish`，把 `finalization` 送回伺服器
+#[wasm_bindgen]
+pub struct OpaqueLogin {
+    state: Option<ClientLogin<SafeTalkOpaque>>,
+    request: Vec<u8>,
+}
+
+/// OPAQUE 登入結果
+#[wasm_bindgen]
+pub struct OpaqueLoginResult {
+    finalization: Vec<u8>,
+    session_key: Vec<u8>,
+    export_key: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl OpaqueLoginResult {
+    /// 要送給伺服器的最終訊息
+    #[wasm_bindgen(getter)]
+    pub fn finalization(&self) -> Vec<u8> {
+        self.finalization.clone()
+    }
+
+    /// 與伺服器共享的會話金鑰
+    #[wasm_bindgen(getter, js_name = sessionKey)]
+    pub fn session_key(&self) -> Vec<u8> {
+        self.session_key.clone()
+    }
+
+    /// export key (與註冊時相同)
+    #[wasm_bindgen(getter, js_name = exportKey)]
+    pub fn export_key(&self) -> Vec<u8> {
+        self.export_key.clone()
+    }
+}
+
+impl OpaqueLogin {
+    /// 開始登入
+    pub fn begin(password: &[u8]) -> Result<Self, String> {
+        let result = ClientLogin::<SafeTalkOpaque>::start(&mut OsRng, password)
+            .map_err(|e| format!("OPAQUE login failed: {}", e))?;
+        Ok(Self {
+            request: result.message.serialize().to_vec(),
+            state: Some(result.state),
+        })
+    }
+
+    /// 完成登入 (密碼錯誤時回傳錯誤)
+    pub fn complete(&mut self, password: &[u8], response: &[u8]) -> Result<OpaqueLoginResult, String> {
+        let state = self
+            .state
+            .take()
+            .ok_or_else(|| "Login already finished".to_string())?;
+        let response = CredentialResponse::<SafeTalkOpaque>::deserialize(response)
+            .map_err(|e| format!("Invalid credential response: {}", e))?;
+        let result = state
+            .finish(password, response, ClientLoginFinishParameters::default())
+            .map_err(|e| format!("OPAQUE login failed: {}", e))?;
+        Ok(OpaqueLoginResult {
+            finalization: result.message.serialize().to_vec(),
+            session_key: result.session_key.to_vec(),
+            export_key: result.export_key.to_vec(),
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl OpaqueLogin {
+    /// 開始登入
+    #[wasm_bindgen(js_name = start)]
+    pub fn start(password: &[u8]) -> Result<OpaqueLogin, JsError> {
+        Self::begin(password).map_err(|e| JsError::new(&e))
+    }
+
+    /// 要送給伺服器的憑證請求
+    #[wasm_bindgen(getter)]
+    pub fn request(&self) -> Vec<u8> {
+        self.request.clone()
+    }
+
+    /// 以伺服器回應完成登入
+    #[wasm_bindgen(js_name = finish)]
+    pub fn finish(&mut self, password: &[u8], response: &[u8]) -> Result<OpaqueLoginResult, JsError> {
+        self.complete(password, response).map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opaque_ke::{
+        CredentialFinalization, CredentialRequest, RegistrationRequest, RegistrationUpload, ServerLogin,
+        ServerLoginStartParameters, ServerRegistration, ServerSetup,
+    };
+
+    #[test]
+    fn test_opaque_registration_and_login() {
+        let server_setup = ServerSetup::<SafeTalkOpaque>::new(&mut OsRng);
+        let user = b"alice@example.com";
+
+        // 註冊
+        let mut registration = OpaqueRegistration::begin(b"hunter2").unwrap();
+        let request = RegistrationRequest::deserialize(&registration.request()).unwrap();
+        let response = ServerRegistration::start(&server_setup, request, user).unwrap();
+        let registered = registration
+            .complete(b"hunter2", &response.message.serialize())
+            .unwrap();
+        let password_file =
+            ServerRegistration::finish(RegistrationUpload::deserialize(&registered.upload()).unwrap());
+        assert!(registration.complete(b"hunter2", &response.message.serialize()).is_err());
+
+        // 登入 (正確密碼)
+        let mut login = OpaqueLogin::begin(b"hunter2").unwrap();
+        let server_login = ServerLogin::start(
+            &mut OsRng,
+            &server_setup,
+            Some(password_file.clone()),
+            CredentialRequest::deserialize(&login.request()).unwrap(),
+            user,
+            ServerLoginStartParameters::default(),
+        )
+        .unwrap();
+        let logged_in = login
+            .complete(b"hunter2", &server_login.message.serialize())
+            .unwrap();
+        let server_finish = server_login
+            .state
+            .finish(CredentialFinalization::deserialize(&logged_in.finalization()).unwrap())
+            .unwrap();
+        assert_eq!(logged_in.session_key(), server_finish.session_key.to_vec());
+        assert_eq!(logged_in.export_key(), registered.export_key());
+
+        // 登入 (錯誤密碼)
+        let mut wrong = OpaqueLogin::begin(b"hunter3").unwrap();
+        let server_login = ServerLogin::start(
+            &mut OsRng,
+            &server_setup,
+            Some(password_file),
+            CredentialRequest::deserialize(&wrong.request()).unwrap(),
+            user,
+            ServerLoginStartParameters::default(),
+        )
+        .unwrap();
+        assert!(wrong.complete(b"hunter3", &server_login.message.serialize()).is_err());
+    }
+}
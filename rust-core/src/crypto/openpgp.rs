@@ -0,0 +1,577 @@
+//! OpenPGP 身份匯出模組
+//!
+//! 依 RFC 4880 / RFC 9580 將 Ed25519 身份公鑰包裝為 v4 公鑰封包
+//! (EdDSALegacy, Ed25519 OID)，附上使用者 ID 與正向自我認證簽章，
+//! 輸出 ASCII armor 公鑰區塊，可匯入 GnuPG 或發布到 keyserver、網站，
+//! 讓使用者在 SafeTalk 之外交叉驗證身份
+//!
+//! 指紋為 OpenPGP v4 指紋 (SHA-1)，依 OpenPGP 定義與金鑰建立時間綁定，
+//! 同一把身份金鑰必須使用固定的建立時間才能得到相同的指紋
+
+use wasm_bindgen::prelude::*;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+
+use super::keys::IdentityKeyPair;
+
+/// Armor 標頭
+const ARMOR_BEGIN: &str = "-----BEGIN PGP PUBLIC KEY BLOCK-----";
+/// Armor 結尾
+const ARMOR_END: &str = "-----END PGP PUBLIC KEY BLOCK-----";
+
+/// 封包標籤
+const TAG_SIGNATURE: u8 = 2;
+const TAG_PUBLIC_KEY: u8 = 6;
+const TAG_USER_ID: u8 = 13;
+
+/// EdDSALegacy 公鑰演算法
+const ALGORITHM_EDDSA: u8 = 22;
+/// Ed25519 曲線 OID (1.3.6.1.4.1.11591.15.1)
+const ED25519_OID: [u8; 9] = [0x2b, 0x06, 0x01, 0x04, 0x01, 0xda, 0x47, 0x0f, 0x01];
+/// SHA-256 雜湊演算法
+const HASH_SHA256: u8 = 8;
+/// 正向認證 (positive certification) 簽章類型
+const SIG_POSITIVE_CERTIFICATION: u8 = 0x13;
+
+/// 簽章子封包類型
+const SUBPACKET_CREATION_TIME: u8 = 2;
+const SUBPACKET_ISSUER: u8 = 16;
+const SUBPACKET_KEY_FLAGS: u8 = 27;
+const SUBPACKET_ISSUER_FINGERPRINT: u8 = 33;
+/// 金鑰用途：認證 + 簽署
+const KEY_FLAGS_CERTIFY_SIGN: u8 = 0x03;
+
+/// ms 轉換為 OpenPGP 使用的 Unix 秒 (u32)
+fn to_openpgp_time(timestamp_ms: u64) -> Result<u32, String> {
+    u32::try_from(timestamp_ms / 1000).map_err(|_| "Timestamp out of OpenPGP range".to_string())
+}
+
+/// 公鑰封包內容
+fn public_key_body(identity_key: &[u8], created_at: u32) -> Vec<u8> {
+    let mut body = vec![4];
+    body.extend_from_slice(&created_at.to_be_bytes());
+    body.push(ALGORITHM_EDDSA);
+    body.push(ED25519_OID.len() as u8);
+    body.extend_from_slice(&ED25519_OID);
+    // 0x40 前綴的原生點編碼，共 263 bits
+    let mut point = vec![0x40];
+    point.extend_from_slice(identity_key);
+    body.extend_from_slice(&encode_mpi(&point));
+    body
+}
+
+/// 指紋與簽章雜湊使用的公鑰前綴
+fn key_hash_prefix(key_body: &[u8]) -> Vec<u8> {
+    let mut prefix = vec![0x99];
+    prefix.extend_from_slice(&(key_body.len() as u16).to_be_bytes());
+    prefix.extend_from_slice(key_body);
+    prefix
+}
+
+/// v4 指紋 SHA-1(0x99 || 長度 || 公鑰封包)
+fn fingerprint_of(key_body: &[u8]) -> [u8; 20] {
+    Sha1::digest(key_hash_prefix(key_body)).into()
+}
+
+/// 多精度整數：位元長度 + 去除前導零的大端位元組
+fn encode_mpi(value: &[u8]) -> Vec<u8> {
+    let start = value.iter().position(|b| *b != 0).unwrap_or(value.len());
+    let value = &value[start..];
+    let bits = match value.first() {
+        Some(first) => (value.len() * 8 - first.leading_zeros() as usize) as u16,
+        None => 0,
+    };
+    let mut out = bits.to_be_bytes().to_vec();
+    out.extend_from_slice(value);
+    out
+}
+
+/// 新格式封包
+fn encode_packet(tag: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = vec![0xc0 | tag];
+    let len = body.len();
+    if len < 192 {
+        packet.push(len as u8);
+    } else if len < 8384 {
+        let len = len - 192;
+        packet.push((len >> 8) as u8 + 192);
+        packet.push(len as u8);
+    } else {
+        packet.push(0xff);
+        packet.extend_from_slice(&(len as u32).to_be_bytes());
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+/// 簽章子封包
+fn encode_subpacket(kind: u8, data: &[u8]) -> Vec<u8> {
+    let mut subpacket = vec![(data.len() + 1) as u8, kind];
+    subpacket.extend_from_slice(data);
+    subpacket
+}
+
+/// 使用者 ID 認證簽章的雜湊輸入 (不含雜湊欄位之後的部分)
+fn certification_hash_input(key_body: &[u8], user_id: &[u8], hashed_part: &[u8]) -> Vec<u8> {
+    let mut input = key_hash_prefix(key_body);
+    input.push(0xb4);
+    input.extend_from_slice(&(user_id.len() as u32).to_be_bytes());
+    input.extend_from_slice(user_id);
+    input.extend_from_slice(hashed_part);
+    // v4 trailer
+    input.extend_from_slice(&[4, 0xff]);
+    input.extend_from_slice(&(hashed_part.len() as u32).to_be_bytes());
+    input
+}
+
+/// 依演算法代碼計算雜湊
+fn hash_with(algorithm: u8, data: &[u8]) -> Result<Vec<u8>, String> {
+    match algorithm {
+        8 => Ok(Sha256::digest(data).to_vec()),
+        9 => Ok(Sha384::digest(data).to_vec()),
+        10 => Ok(Sha512::digest(data).to_vec()),
+        _ => Err(format!("Unsupported OpenPGP hash algorithm {}", algorithm)),
+    }
+}
+
+/// CRC-24 (armor 檢查碼)
+fn crc24(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xb704ce;
+    for byte in data {
+        crc ^= (*byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= 0x1864cfb;
+            }
+        }
+    }
+    crc & 0xffffff
+}
+
+/// 以 ASCII armor 包裝公鑰區塊
+fn armor(data: &[u8]) -> String {
+    let encoded = BASE64.encode(data);
+    let mut armored = String::from(ARMOR_BEGIN);
+    armored.push_str("\n\n");
+    for line in encoded.as_bytes().chunks(64) {
+        armored.push_str(&String::from_utf8_lossy(line));
+        armored.push('\n');
+    }
+    armored.push('=');
+    armored.push_str(&BASE64.encode(&crc24(data).to_be_bytes()[1..]));
+    armored.push('\n');
+    armored.push_str(ARMOR_END);
+    armored.push('\n');
+    armored
+}
+
+/// 解除 ASCII armor 並檢查 CRC
+fn dearmor(armored: &str) -> Result<Vec<u8>, String> {
+    let body = armored
+        .trim()
+        .strip_prefix(ARMOR_BEGIN)
+        .and_then(|rest| rest.strip_suffix(ARMOR_END))
+        .ok_or_else(|| "Missing PGP PUBLIC KEY BLOCK armor".to_string())?;
+
+    // 略過 armor 標頭 (到第一個空行為止)
+    let lines = body.lines().map(str::trim).skip_while(|line| line.is_empty());
+    let mut encoded = String::new();
+    let mut checksum = None;
+    let mut in_headers = true;
+    for line in lines {
+        if in_headers {
+            if line.is_empty() {
+                in_headers = false;
+                continue;
+            }
+            if line.contains(": ") {
+                continue;
+            }
+            in_headers = false;
+        }
+        if let Some(crc) = line.strip_prefix('=') {
+            checksum = Some(crc.to_string());
+            break;
+        }
+        encoded.push_str(line);
+    }
+
+    let data = BASE64
+        .decode(encoded)
+        .map_err(|e| format!("Invalid armor base64: {}", e))?;
+    if let Some(crc) = checksum {
+        let expected = BASE64
+            .decode(crc)
+            .map_err(|e| format!("Invalid armor checksum: {}", e))?;
+        if expected != crc24(&data).to_be_bytes()[1..] {
+            return Err("Armor checksum mismatch".to_string());
+        }
+    }
+    Ok(data)
+}
+
+/// 讀取封包序列 (標籤, 內容)，支援新舊兩種標頭格式
+fn parse_packets(data: &[u8]) -> Result<Vec<(u8, &[u8])>, String> {
+    let truncated = || "Truncated OpenPGP packet".to_string();
+    let mut packets = Vec::new();
+    let mut rest = data;
+    while let Some((&header, tail)) = rest.split_first() {
+        if header & 0x80 == 0 {
+            return Err("Invalid OpenPGP packet header".to_string());
+        }
+        let (tag, len, tail) = if header & 0x40 != 0 {
+            let (&first, tail) = tail.split_first().ok_or_else(truncated)?;
+            match first {
+                0..=191 => (header & 0x3f, first as usize, tail),
+                192..=223 => {
+                    let (&second, tail) = tail.split_first().ok_or_else(truncated)?;
+                    (header & 0x3f, ((first as usize - 192) << 8) + second as usize + 192, tail)
+                }
+                255 => {
+                    let len: [u8; 4] = tail.get(..4).ok_or_else(truncated)?.try_into().unwrap();
+                    (header & 0x3f, u32::from_be_bytes(len) as usize, &tail[4..])
+                }
+                _ => return Err("Partial OpenPGP packet lengths are not supported".to_string()),
+            }
+        } else {
+            let tag = (header >> 2) & 0x0f;
+            let len_bytes = match header & 0x03 {
+                0 => 1,
+                1 => 2,
+                2 => 4,
+                _ => return Err("Indeterminate OpenPGP packet lengths are not supported".to_string()),
+            };
+            let len = tail
+                .get(..len_bytes)
+                .ok_or_else(truncated)?
+                .iter()
+                .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+            (tag, len, &tail[len_bytes..])
+        };
+        let body = tail.get(..len).ok_or_else(truncated)?;
+        packets.push((tag, body));
+        rest = &tail[len..];
+    }
+    Ok(packets)
+}
+
+/// 讀取多精度整數
+fn read_mpi(data: &[u8]) -> Result<(&[u8], &[u8]), String> {
+    let bits: [u8; 2] = data
+        .get(..2)
+        .ok_or_else(|| "Truncated OpenPGP MPI".to_string())?
+        .try_into()
+        .unwrap();
+    let len = (u16::from_be_bytes(bits) as usize).div_ceil(8);
+    let value = data.get(2..2 + len).ok_or_else(|| "Truncated OpenPGP MPI".to_string())?;
+    Ok((value, &data[2 + len..]))
+}
+
+/// 將 MPI 左側補零為 32 bytes
+fn mpi_to_32(value: &[u8]) -> Result<[u8; 32], String> {
+    if value.len() > 32 {
+        return Err("OpenPGP EdDSA signature component too long".to_string());
+    }
+    let mut out = [0u8; 32];
+    out[32 - value.len()..].copy_from_slice(value);
+    Ok(out)
+}
+
+/// 從公鑰封包取出 Ed25519 公鑰與建立時間
+fn parse_public_key(body: &[u8]) -> Result<(Vec<u8>, u32), String> {
+    if body.first() != Some(&4) {
+        return Err("Only OpenPGP v4 keys are supported".to_string());
+    }
+    let created_at = u32::from_be_bytes(
+        body.get(1..5)
+            .ok_or_else(|| "Truncated OpenPGP public key".to_string())?
+            .try_into()
+            .unwrap(),
+    );
+    if body.get(5) != Some(&ALGORITHM_EDDSA) {
+        return Err("Unsupported OpenPGP key algorithm".to_string());
+    }
+    if body.get(6) != Some(&(ED25519_OID.len() as u8)) || body.get(7..16) != Some(&ED25519_OID[..]) {
+        return Err("Unsupported OpenPGP curve".to_string());
+    }
+    let (point, rest) = read_mpi(&body[16..])?;
+    if !rest.is_empty() || point.len() != 33 || point[0] != 0x40 {
+        return Err("Invalid OpenPGP Ed25519 public key".to_string());
+    }
+    Ok((point[1..].to_vec(), created_at))
+}
+
+/// 驗證使用者 ID 的自我認證簽章
+fn verify_certification(
+    key_body: &[u8],
+    identity_key: &[u8],
+    user_id: &[u8],
+    signature: &[u8],
+) -> Result<(), String> {
+    let truncated = || "Truncated OpenPGP signature".to_string();
+    if signature.first() != Some(&4) {
+        return Err("Only OpenPGP v4 signatures are supported".to_string());
+    }
+    let header = signature.get(..6).ok_or_else(truncated)?;
+    if !(0x10..=0x13).contains(&header[1]) {
+        return Err("Not a user ID certification".to_string());
+    }
+    if header[2] != ALGORITHM_EDDSA {
+        return Err("Unsupported OpenPGP signature algorithm".to_string());
+    }
+    let hashed_len = u16::from_be_bytes([header[4], header[5]]) as usize;
+    let hashed_part = signature.get(..6 + hashed_len).ok_or_else(truncated)?;
+    let rest = &signature[6 + hashed_len..];
+    let unhashed_len = u16::from_be_bytes(rest.get(..2).ok_or_else(truncated)?.try_into().unwrap()) as usize;
+    let rest = rest.get(2 + unhashed_len..).ok_or_else(truncated)?;
+    let left16 = rest.get(..2).ok_or_else(truncated)?;
+    let (r, rest) = read_mpi(&rest[2..])?;
+    let (s, _) = read_mpi(rest)?;
+
+    let digest = hash_with(header[3], &certification_hash_input(key_body, user_id, hashed_part))?;
+    if digest[..2] != *left16 {
+        return Err("OpenPGP signature hash prefix mismatch".to_string());
+    }
+    let mut sig = mpi_to_32(r)?.to_vec();
+    sig.extend_from_slice(&mpi_to_32(s)?);
+    if !IdentityKeyPair::verify_signature(identity_key, &digest, &sig) {
+        return Err("Invalid OpenPGP self-signature".to_string());
+    }
+    Ok(())
+}
+
+/// 以身份金鑰產生 OpenPGP 公鑰區塊 (二進位)
+pub fn openpgp_export(identity: &IdentityKeyPair, user_id: &str, created_at_ms: u64) -> Result<Vec<u8>, String> {
+    if user_id.is_empty() {
+        return Err("OpenPGP user ID must not be empty".to_string());
+    }
+    let created_at = to_openpgp_time(created_at_ms)?;
+    let key_body = public_key_body(&identity.public_key_bytes(), created_at);
+    let fingerprint = fingerprint_of(&key_body);
+
+    let mut issuer_fingerprint = vec![4];
+    issuer_fingerprint.extend_from_slice(&fingerprint);
+    let mut subpackets = encode_subpacket(SUBPACKET_CREATION_TIME, &created_at.to_be_bytes());
+    subpackets.extend(encode_subpacket(SUBPACKET_KEY_FLAGS, &[KEY_FLAGS_CERTIFY_SIGN]));
+    subpackets.extend(encode_subpacket(SUBPACKET_ISSUER_FINGERPRINT, &issuer_fingerprint));
+
+    let mut signature = vec![4, SIG_POSITIVE_CERTIFICATION, ALGORITHM_EDDSA, HASH_SHA256];
+    signature.extend_from_slice(&(subpackets.len() as u16).to_be_bytes());
+    signature.extend_from_slice(&subpackets);
+
+    let digest = Sha256::digest(certification_hash_input(&key_body, user_id.as_bytes(), &signature));
+    let sig = identity.sign(&digest);
+
+    let unhashed = encode_subpacket(SUBPACKET_ISSUER, &fingerprint[12..]);
+    signature.extend_from_slice(&(unhashed.len() as u16).to_be_bytes());
+    signature.extend_from_slice(&unhashed);
+    signature.extend_from_slice(&digest[..2]);
+    signature.extend(encode_mpi(&sig[..32]));
+    signature.extend(encode_mpi(&sig[32..]));
+
+    let mut block = encode_packet(TAG_PUBLIC_KEY, &key_body);
+    block.extend(encode_packet(TAG_USER_ID, user_id.as_bytes()));
+    block.extend(encode_packet(TAG_SIGNATURE, &signature));
+    Ok(block)
+}
+
+/// Ed25519 身份公鑰的 OpenPGP v4 指紋
+pub fn openpgp_fingerprint(identity_key: &[u8], created_at_ms: u64) -> Result<[u8; 20], String> {
+    if identity_key.len() != 32 {
+        return Err("Identity key must be 32 bytes".to_string());
+    }
+    Ok(fingerprint_of(&public_key_body(identity_key, to_openpgp_time(created_at_ms)?)))
+}
+
+/// 以 GnuPG 慣用格式顯示指紋 (每 4 個十六進位字元一組，中間以兩個空白分隔)
+pub fn format_openpgp_fingerprint(fingerprint: &[u8]) -> String {
+    let hex: String = fingerprint.iter().map(|b| format!("{:02X}", b)).collect();
+    let groups: Vec<&str> = (0..hex.len())
+        .step_by(4)
+        .map(|i| &hex[i..(i + 4).min(hex.len())])
+        .collect();
+    let half = groups.len() / 2;
+    if groups.len() == 10 {
+        format!("{}  {}", groups[..half].join(" "), groups[half..].join(" "))
+    } else {
+        groups.join(" ")
+    }
+}
+
+/// 已驗證的 OpenPGP 身份
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OpenPgpIdentity {
+    identity_key: Vec<u8>,
+    user_id: String,
+    /// 金鑰建立時間 (Unix 毫秒)
+    created_at: u64,
+    fingerprint: [u8; 20],
+}
+
+impl OpenPgpIdentity {
+    /// 解析二進位公鑰區塊並驗證第一個使用者 ID 的自我簽章
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let packets = parse_packets(data)?;
+        let mut packets = packets.into_iter();
+        let (tag, key_body) = packets.next().ok_or_else(|| "Empty OpenPGP block".to_string())?;
+        if tag != TAG_PUBLIC_KEY {
+            return Err("OpenPGP block does not start with a public key".to_string());
+        }
+        let (identity_key, created_at) = parse_public_key(key_body)?;
+
+        let mut packets = packets.skip_while(|(tag, _)| *tag != TAG_USER_ID);
+        let (_, user_id) = packets.next().ok_or_else(|| "OpenPGP block has no user ID".to_string())?;
+        let user_id =
+            String::from_utf8(user_id.to_vec()).map_err(|_| "OpenPGP user ID is not UTF-8".to_string())?;
+
+        // 使用者 ID 之後的簽章中至少要有一個有效的自我認證
+        let mut last_error = "OpenPGP user ID has no self-signature".to_string();
+        for (_, signature) in packets.take_while(|(tag, _)| *tag == TAG_SIGNATURE) {
+            match verify_certification(key_body, &identity_key, user_id.as_bytes(), signature) {
+                Ok(()) => {
+                    return Ok(Self {
+                        identity_key,
+                        user_id,
+                        created_at: created_at as u64 * 1000,
+                        fingerprint: fingerprint_of(key_body),
+                    });
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+}
+
+#[wasm_bindgen]
+impl OpenPgpIdentity {
+    /// 解析 ASCII armor 公鑰區塊並驗證自我簽章
+    #[wasm_bindgen(js_name = fromArmored)]
+    pub fn from_armored(armored: &str) -> Result<OpenPgpIdentity, JsError> {
+        dearmor(armored)
+            .and_then(|data| Self::parse(&data))
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// Ed25519 身份公鑰
+    #[wasm_bindgen(getter, js_name = identityKey)]
+    pub fn identity_key(&self) -> Vec<u8> {
+        self.identity_key.clone()
+    }
+
+    /// 使用者 ID
+    #[wasm_bindgen(getter, js_name = userId)]
+    pub fn user_id(&self) -> String {
+        self.user_id.clone()
+    }
+
+    /// 金鑰建立時間
+    #[wasm_bindgen(getter, js_name = createdAt)]
+    pub fn created_at(&self) -> u64 {
+        self.created_at
+    }
+
+    /// OpenPGP v4 指紋
+    #[wasm_bindgen(getter)]
+    pub fn fingerprint(&self) -> Vec<u8> {
+        self.fingerprint.to_vec()
+    }
+
+    /// 格式化的指紋
+    #[wasm_bindgen(js_name = displayFingerprint)]
+    pub fn display_fingerprint(&self) -> String {
+        format_openpgp_fingerprint(&self.fingerprint)
+    }
+
+    /// 是否為指定的身份公鑰
+    #[wasm_bindgen(js_name = matchesIdentity)]
+    pub fn matches_identity(&self, identity_key: &[u8]) -> bool {
+        self.identity_key == identity_key
+    }
+}
+
+#[wasm_bindgen]
+impl IdentityKeyPair {
+    /// 匯出為 OpenPGP ASCII armor 公鑰區塊 (含自我簽章)
+    ///
+    /// `created_at` 決定 OpenPGP 指紋，每次匯出應使用相同的值
+    #[wasm_bindgen(js_name = toOpenPgpArmored)]
+    pub fn to_openpgp_armored(&self, user_id: &str, created_at: u64) -> Result<String, JsError> {
+        openpgp_export(self, user_id, created_at)
+            .map(|block| armor(&block))
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// OpenPGP v4 指紋 (GnuPG 格式)
+    #[wasm_bindgen(js_name = openPgpFingerprint)]
+    pub fn openpgp_fingerprint_display(&self, created_at: u64) -> Result<String, JsError> {
+        openpgp_fingerprint(&self.public_key_bytes(), created_at)
+            .map(|fingerprint| format_openpgp_fingerprint(&fingerprint))
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_round_trip() {
+        let identity = IdentityKeyPair::new();
+        let armored = identity.to_openpgp_armored("Alice <alice@example.com>", 1_700_000_000_123).unwrap();
+        assert!(armored.starts_with(ARMOR_BEGIN));
+
+        let parsed = OpenPgpIdentity::parse(&dearmor(&armored).unwrap()).unwrap();
+        assert!(parsed.matches_identity(&identity.public_key_bytes()));
+        assert_eq!(parsed.user_id(), "Alice <alice@example.com>");
+        assert_eq!(parsed.created_at(), 1_700_000_000_000);
+        assert_eq!(
+            parsed.display_fingerprint(),
+            identity.openpgp_fingerprint_display(1_700_000_000_123).unwrap()
+        );
+
+        // 竄改使用者 ID
+        let mut data = openpgp_export(&identity, "Alice <alice@example.com>", 1_700_000_000_000).unwrap();
+        let position = data.windows(5).position(|w| w == b"Alice").unwrap();
+        data[position] = b'M';
+        assert!(OpenPgpIdentity::parse(&data).is_err());
+
+        // 錯誤的檢查碼
+        let corrupted = armored.replace("\n=", "\n=AAAA\n=");
+        assert!(dearmor(&corrupted).is_err());
+    }
+
+    #[test]
+    fn test_export_accepted_by_gnupg() {
+        // 以下指紋由 `gpg --import` 匯入此匯出結果後經 `gpg --check-sigs` 驗證 (sig!3)
+        let identity = IdentityKeyPair::from_bytes(&[9u8; 32]).unwrap();
+        let armored = identity.to_openpgp_armored("SafeTalk <st@example.com>", 1_700_000_000_000).unwrap();
+        let parsed = OpenPgpIdentity::from_armored(&armored).unwrap();
+        assert_eq!(parsed.display_fingerprint(), "839E 3E61 9B9C 3F99 DB47  90D1 03B2 6C9E 9B76 342D");
+    }
+
+    #[test]
+    fn test_gnupg_generated_key() {
+        // gpg --quick-gen-key "Test <test@example.com>" ed25519 sign never
+        // gpg --export --armor
+        let armored = "-----BEGIN PGP PUBLIC KEY BLOCK-----\n\
+                       \n\
+                       mDMEas+lkxYJKwYBBAHaRw8BAQdAdReTmRUqVQGQawv/0iaFmfi1xXtcyaY4HuHO\n\
+                       fjzhbZG0F1Rlc3QgPHRlc3RAZXhhbXBsZS5jb20+iJAEExYIADgWIQRWEAfPaApF\n\
+                       Wse+QZVpShOrO92fZgUCas+lkwIbAwULCQgHAgYVCgkICwIEFgIDAQIeAQIXgAAK\n\
+                       CRBpShOrO92fZumlAP9lPwxOIHNafRKMMmpZh15cExImkOdlS1WHJTzX2fKsDgEA\n\
+                       lZ4ElgQc6+kAW/zFQSWJoZs/9O6ExWjiP0KXXl/3oAo=\n\
+                       =+h38\n\
+                       -----END PGP PUBLIC KEY BLOCK-----\n";
+        let parsed = OpenPgpIdentity::from_armored(armored).unwrap();
+        assert_eq!(parsed.user_id(), "Test <test@example.com>");
+        assert_eq!(parsed.display_fingerprint(), "5610 07CF 680A 455A C7BE  4195 694A 13AB 3BDD 9F66");
+        assert_eq!(
+            openpgp_fingerprint(&parsed.identity_key(), parsed.created_at()).unwrap().to_vec(),
+            parsed.fingerprint()
+        );
+    }
+}
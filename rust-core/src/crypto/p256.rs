@@ -0,0 +1,208 @@
+//! P-256 金鑰模組 (需啟用 `p256` feature)
+//!
+//! 提供與 [`X25519KeyPair`](super::X25519KeyPair) / [`IdentityKeyPair`](super::IdentityKeyPair)
+//! 相同形狀的 P-256 ECDH 與 ECDSA 金鑰對，
+//! 讓必須使用 WebCrypto 不可匯出金鑰或企業 PKI 的部署也能驅動會話
+//!
+//! 編碼與 WebCrypto 一致：
+//! - 公鑰：SEC1 未壓縮格式 (65 bytes，`raw` 匯出格式)
+//! - 簽章：ECDSA-SHA256，IEEE P1363 `r || s` (64 bytes)
+//! - ECDH：共享秘密為 x 座標 (32 bytes，等同 `deriveBits(256)`)
+
+use wasm_bindgen::prelude::*;
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use p256::{
+    ecdh,
+    ecdsa::{signature::Signer, signature::Verifier, Signature, SigningKey, VerifyingKey},
+    PublicKey, SecretKey,
+};
+use rand::rngs::OsRng;
+
+/// 解析 SEC1 公鑰 (接受壓縮或未壓縮格式)
+fn parse_public_key(bytes: &[u8]) -> Result<PublicKey, String> {
+    PublicKey::from_sec1_bytes(bytes).map_err(|_| "Invalid P-256 public key".to_string())
+}
+
+fn encode_public_key(public: &PublicKey) -> Vec<u8> {
+    use p256::elliptic_curve::sec1::ToEncodedPoint;
+    public.to_encoded_point(false).as_bytes().to_vec()
+}
+
+/// P-256 ECDH 金鑰對
+#[wasm_bindgen]
+pub struct P256KeyPair {
+    secret: SecretKey,
+}
+
+#[wasm_bindgen]
+impl P256KeyPair {
+    /// 生成新的 P-256 ECDH 金鑰對
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self { secret: SecretKey::random(&mut OsRng) }
+    }
+
+    /// 從私鑰位元組還原
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<P256KeyPair, JsError> {
+        if bytes.len() != 32 {
+            return Err(JsError::new("Private key must be 32 bytes"));
+        }
+        let secret = SecretKey::from_slice(bytes).map_err(|_| JsError::new("Invalid P-256 private key"))?;
+        Ok(Self { secret })
+    }
+
+    /// 取得公鑰 (Base64)
+    #[wasm_bindgen(js_name = publicKeyBase64)]
+    pub fn public_key_base64(&self) -> String {
+        BASE64.encode(self.public_key_bytes())
+    }
+
+    /// 取得公鑰位元組 (SEC1 未壓縮)
+    #[wasm_bindgen(js_name = publicKeyBytes)]
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        encode_public_key(&self.secret.public_key())
+    }
+
+    /// 取得私鑰位元組 (敏感！)
+    #[wasm_bindgen(js_name = privateKeyBytes)]
+    pub fn private_key_bytes(&self) -> Vec<u8> {
+        self.secret.to_bytes().to_vec()
+    }
+
+    /// 執行 Diffie-Hellman 金鑰交換
+    #[wasm_bindgen(js_name = diffieHellman)]
+    pub fn diffie_hellman(&self, their_public: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.shared_secret(their_public)
+            .map(|shared| shared.to_vec())
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+impl P256KeyPair {
+    /// 執行 Diffie-Hellman 金鑰交換 (Rust 端使用)
+    pub fn shared_secret(&self, their_public: &[u8]) -> Result<[u8; 32], String> {
+        let their_public = parse_public_key(their_public)?;
+        let shared = ecdh::diffie_hellman(self.secret.to_nonzero_scalar(), their_public.as_affine());
+        let mut out = [0u8; 32];
+        out.copy_from_slice(shared.raw_secret_bytes());
+        Ok(out)
+    }
+}
+
+impl Default for P256KeyPair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// P-256 ECDSA 簽章金鑰對
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct P256SigningKeyPair {
+    signing_key: SigningKey,
+}
+
+#[wasm_bindgen]
+impl P256SigningKeyPair {
+    /// 生成新的 P-256 簽章金鑰對
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self { signing_key: SigningKey::random(&mut OsRng) }
+    }
+
+    /// 從私鑰位元組還原
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<P256SigningKeyPair, JsError> {
+        if bytes.len() != 32 {
+            return Err(JsError::new("Private key must be 32 bytes"));
+        }
+        let signing_key =
+            SigningKey::from_slice(bytes).map_err(|_| JsError::new("Invalid P-256 private key"))?;
+        Ok(Self { signing_key })
+    }
+
+    /// 取得公鑰 (Base64)
+    #[wasm_bindgen(js_name = publicKeyBase64)]
+    pub fn public_key_base64(&self) -> String {
+        BASE64.encode(self.public_key_bytes())
+    }
+
+    /// 取得公鑰位元組 (SEC1 未壓縮)
+    #[wasm_bindgen(js_name = publicKeyBytes)]
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        encode_public_key(&PublicKey::from(self.signing_key.verifying_key()))
+    }
+
+    /// 取得私鑰位元組 (敏感！僅用於備份)
+    #[wasm_bindgen(js_name = privateKeyBytes)]
+    pub fn private_key_bytes(&self) -> Vec<u8> {
+        self.signing_key.to_bytes().to_vec()
+    }
+
+    /// 簽署訊息 (ECDSA-SHA256，r || s)
+    pub fn sign(&self, message: &[u8]) -> Vec<u8> {
+        let signature: Signature = self.signing_key.sign(message);
+        signature.to_bytes().to_vec()
+    }
+
+    /// 驗證簽章
+    #[wasm_bindgen(js_name = verifySignature)]
+    pub fn verify_signature(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        let verifying_key = match VerifyingKey::from_sec1_bytes(public_key) {
+            Ok(k) => k,
+            Err(_) => return false,
+        };
+        let signature = match Signature::from_slice(signature) {
+            Ok(sig) => sig,
+            Err(_) => return false,
+        };
+        verifying_key.verify(message, &signature).is_ok()
+    }
+}
+
+impl Default for P256SigningKeyPair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_p256_ecdh_vector() {
+        // NIST CAVS ECC CDH Primitive, P-256 COUNT = 0
+        let private = hex("7d7dc5f71eb29ddaf80d6214632eeae03d9058af1fb6d22ed80badb62bc1a534");
+        let their_public = hex(
+            "04700c48f77f56584c5cc632ca65640db91b6bacce3a4df6b42ce7cc838833d287\
+             db71e509e3fd9b060ddb20ba5c51dcc5948d46fbf640dfe0441782cab85fa4ac",
+        );
+        let expected = hex("46fc62106420ff012e54a434fbdd2d25ccc5852060561e68040dd7778997bd7b");
+
+        let keypair = P256KeyPair::from_bytes(&private).unwrap();
+        assert_eq!(keypair.shared_secret(&their_public).unwrap().to_vec(), expected);
+
+        let alice = P256KeyPair::new();
+        let bob = P256KeyPair::new();
+        assert_eq!(alice.public_key_bytes().len(), 65);
+        assert_eq!(
+            alice.shared_secret(&bob.public_key_bytes()).unwrap(),
+            bob.shared_secret(&alice.public_key_bytes()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_p256_ecdsa_sign_verify() {
+        let keypair = P256SigningKeyPair::new();
+        let signature = keypair.sign(b"bundle");
+        assert_eq!(signature.len(), 64);
+
+        let public = keypair.public_key_bytes();
+        assert!(P256SigningKeyPair::verify_signature(&public, b"bundle", &signature));
+        assert!(!P256SigningKeyPair::verify_signature(&public, b"other", &signature));
+        assert!(!P256SigningKeyPair::verify_signature(&public[..33], b"bundle", &signature));
+    }
+}
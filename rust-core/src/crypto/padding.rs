@@ -0,0 +1,96 @@
+//! Padmé 長度填充模組
+//!
+//! 加密前把長度填充到 Padmé 長度 (Nikitin et al., PETS 2019)：
+//! 長度 L 只保留最高的 ⌊log₂ ⌊log₂ L⌋⌋ + 1 個位元，其餘進位，
+//! 密文長度最多洩漏 O(log log L) 位元的資訊，額外開銷不超過 12%
+//!
+//! 填充格式與 ISO/IEC 7816-4 相同：data || 0x80 || 0x00...，可自行界定結尾
+
+use wasm_bindgen::prelude::*;
+
+pub(crate) const PADDING_MARKER: u8 = 0x80;
+
+/// 長度 `len` 的 Padmé 長度
+pub fn padme_length(len: u64) -> u64 {
+    if len < 2 {
+        return len;
+    }
+    let exponent = 63 - len.leading_zeros() as u64;
+    let significant_bits = (63 - exponent.leading_zeros() as u64) + 1;
+    let mask = (1u64 << (exponent - significant_bits)) - 1;
+    (len + mask) & !mask
+}
+
+/// 需附加的填充位元組數 (包含結尾標記)
+pub fn padme_padding_len(len: usize) -> usize {
+    (padme_length(len as u64 + 1) - len as u64) as usize
+}
+
+/// 填充位元組：0x80 || 0x00...
+pub fn padme_padding(len: usize) -> Vec<u8> {
+    let mut padding = vec![0u8; padme_padding_len(len)];
+    padding[0] = PADDING_MARKER;
+    padding
+}
+
+/// 填充到 Padmé 長度
+#[wasm_bindgen(js_name = padmePad)]
+pub fn padme_pad(data: &[u8]) -> Vec<u8> {
+    let mut padded = Vec::with_capacity(data.len() + padme_padding_len(data.len()));
+    padded.extend_from_slice(data);
+    padded.extend(padme_padding(data.len()));
+    padded
+}
+
+/// 移除填充 (Rust 端使用)
+pub fn padme_unpad_bytes(padded: &[u8]) -> Result<&[u8], String> {
+    let end = padded
+        .iter()
+        .rposition(|&byte| byte != 0)
+        .filter(|&index| padded[index] == PADDING_MARKER)
+        .ok_or_else(|| "Invalid padding".to_string())?;
+    Ok(&padded[..end])
+}
+
+/// 移除填充
+#[wasm_bindgen(js_name = padmeUnpad)]
+pub fn padme_unpad(padded: &[u8]) -> Result<Vec<u8>, JsError> {
+    padme_unpad_bytes(padded).map(<[u8]>::to_vec).map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_padme_lengths() {
+        assert_eq!(padme_length(0), 0);
+        assert_eq!(padme_length(3), 3);
+        assert_eq!(padme_length(9), 10);
+        assert_eq!(padme_length(1000), 1024);
+        assert_eq!(padme_length(1_000_000), 1_015_808);
+
+        // 結果單調且開銷不超過 12%
+        let mut previous = 0;
+        for len in 1..20_000u64 {
+            let padded = padme_length(len);
+            assert!(padded >= len && padded >= previous);
+            assert!((padded - len) * 100 <= len * 12);
+            previous = padded;
+        }
+    }
+
+    #[test]
+    fn test_pad_unpad_round_trip() {
+        for len in [0usize, 1, 15, 100, 4097] {
+            let data = vec![0u8; len];
+            let padded = padme_pad(&data);
+            assert_eq!(padded.len() as u64, padme_length(len as u64 + 1));
+            assert_eq!(padme_unpad_bytes(&padded).unwrap(), data.as_slice());
+        }
+
+        assert!(padme_unpad_bytes(&[1, 2, 0, 0]).is_err());
+        assert!(padme_unpad_bytes(&[0, 0]).is_err());
+        assert!(padme_unpad_bytes(&[]).is_err());
+    }
+}
@@ -0,0 +1,390 @@
+//! 密碼金鑰衍生模組
+//!
+//! 以 Argon2id (預設) 或 scrypt 從密碼衍生 32 bytes 金鑰，
+//! 作為密碼保護匯出、本機儲存解鎖與 PIN 強化的共同基礎；
+//! PBKDF2-HMAC-SHA256 僅為了解開舊版或其他應用程式產生的備份與匯出檔
+//!
+//! 衍生時一併輸出 PHC 風格的參數字串 (不含雜湊值)，
+//! 存下字串即可在之後以相同參數與 salt 重新衍生：
+//!
+//! ```text
+//! $argon2id$v=19$m=19456,t=2,p=1$<salt>
+//! $scrypt$ln=17,r=8,p=1$<salt>
+//! $pbkdf2-sha256$i=600000$<salt>
+//! ```
+//!
+//! salt 以不含填充的標準 Base64 編碼
+
+use wasm_bindgen::prelude::*;
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine as _};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::export::Argon2idParams;
+use super::secret::SecretBytes;
+
+/// 衍生出的金鑰長度
+pub const PASSWORD_KEY_SIZE: usize = 32;
+/// 最短 salt 長度
+pub const PASSWORD_MIN_SALT_SIZE: usize = 8;
+
+/// 解析外部參數時接受的最大 scrypt 記憶體用量 (與 Argon2id 上限相同，512 MiB)
+const MAX_SCRYPT_MEMORY: u64 = 512 * 1024 * 1024;
+/// 解析外部參數時接受的最大 scrypt 平行度
+const MAX_SCRYPT_PARALLELISM: u32 = 16;
+/// 解析外部參數時接受的最大 PBKDF2 迭代次數
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+/// scrypt 參數
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScryptParams {
+    /// CPU/記憶體成本 N 的 log₂
+    pub log_n: u8,
+    /// 區塊大小
+    pub r: u32,
+    /// 平行度
+    pub p: u32,
+}
+
+impl Default for ScryptParams {
+    /// OWASP 建議值：N = 2^17、r = 8、p = 1
+    fn default() -> Self {
+        Self { log_n: 17, r: 8, p: 1 }
+    }
+}
+
+impl ScryptParams {
+    /// 以 scrypt 從密碼衍生 32 bytes 金鑰
+    pub fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<[u8; 32], String> {
+        let params = scrypt::Params::new(self.log_n, self.r, self.p, PASSWORD_KEY_SIZE)
+            .map_err(|e| format!("Invalid scrypt parameters: {}", e))?;
+        let mut key = [0u8; 32];
+        scrypt::scrypt(password, salt, &params, &mut key).map_err(|e| format!("scrypt failed: {}", e))?;
+        Ok(key)
+    }
+
+    /// 檢查外部提供的參數是否在允許範圍內
+    pub(crate) fn check_limits(&self) -> Result<(), String> {
+        let memory = 128u128 * self.r as u128 * 1u128.checked_shl(self.log_n as u32).unwrap_or(u128::MAX);
+        if memory > MAX_SCRYPT_MEMORY as u128 || self.p > MAX_SCRYPT_PARALLELISM {
+            return Err("scrypt parameters exceed allowed limits".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// PBKDF2-HMAC-SHA256 參數
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Pbkdf2Params {
+    /// 迭代次數
+    pub iterations: u32,
+}
+
+impl Default for Pbkdf2Params {
+    /// OWASP 建議值：600,000 次迭代
+    fn default() -> Self {
+        Self { iterations: 600_000 }
+    }
+}
+
+impl Pbkdf2Params {
+    /// 以 PBKDF2-HMAC-SHA256 從密碼衍生 32 bytes 金鑰
+    pub fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<[u8; 32], String> {
+        if self.iterations == 0 {
+            return Err("PBKDF2 iterations must be at least 1".to_string());
+        }
+        let mut key = [0u8; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(password, salt, self.iterations, &mut key);
+        Ok(key)
+    }
+
+    /// 檢查外部提供的參數是否在允許範圍內
+    pub(crate) fn check_limits(&self) -> Result<(), String> {
+        if self.iterations > MAX_PBKDF2_ITERATIONS {
+            return Err("PBKDF2 parameters exceed allowed limits".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 密碼 KDF 與其參數
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "algorithm", rename_all = "lowercase")]
+pub enum PasswordKdfParams {
+    Argon2id(Argon2idParams),
+    Scrypt(ScryptParams),
+    #[serde(rename = "pbkdf2-sha256")]
+    Pbkdf2Sha256(Pbkdf2Params),
+}
+
+impl Default for PasswordKdfParams {
+    fn default() -> Self {
+        PasswordKdfParams::Argon2id(Argon2idParams::default())
+    }
+}
+
+impl PasswordKdfParams {
+    /// 從密碼衍生 32 bytes 金鑰
+    pub fn derive_key(&self, password: &[u8], salt: &[u8]) -> Result<[u8; 32], String> {
+        if salt.len() < PASSWORD_MIN_SALT_SIZE {
+            return Err(format!("Salt must be at least {} bytes", PASSWORD_MIN_SALT_SIZE));
+        }
+        match self {
+            PasswordKdfParams::Argon2id(params) => params.derive_key(password, salt),
+            PasswordKdfParams::Scrypt(params) => params.derive_key(password, salt),
+            PasswordKdfParams::Pbkdf2Sha256(params) => params.derive_key(password, salt),
+        }
+    }
+
+    /// 檢查外部提供的參數是否在允許範圍內
+    pub(crate) fn check_limits(&self) -> Result<(), String> {
+        match self {
+            PasswordKdfParams::Argon2id(params) => params.check_limits(),
+            PasswordKdfParams::Scrypt(params) => params.check_limits(),
+            PasswordKdfParams::Pbkdf2Sha256(params) => params.check_limits(),
+        }
+    }
+
+    /// 編碼為參數字串
+    pub fn encode(&self, salt: &[u8]) -> String {
+        let salt = STANDARD_NO_PAD.encode(salt);
+        match self {
+            PasswordKdfParams::Argon2id(params) => format!(
+                "$argon2id$v=19$m={},t={},p={}${}",
+                params.memory_kib, params.iterations, params.parallelism, salt
+            ),
+            PasswordKdfParams::Scrypt(params) => {
+                format!("$scrypt$ln={},r={},p={}${}", params.log_n, params.r, params.p, salt)
+            }
+            PasswordKdfParams::Pbkdf2Sha256(params) => format!("$pbkdf2-sha256$i={}${}", params.iterations, salt),
+        }
+    }
+
+    /// 解析參數字串，回傳參數與 salt
+    ///
+    /// 參數超出允許範圍時拒絕，避免外部字串耗盡記憶體
+    pub fn decode(encoded: &str) -> Result<(Self, Vec<u8>), String> {
+        let fields: Vec<&str> = encoded.split('$').collect();
+        let (params, salt) = match fields.as_slice() {
+            ["", "argon2id", "v=19", values, salt] => {
+                let [memory_kib, iterations, parallelism] = parse_values(values, ["m", "t", "p"])?;
+                let params = Argon2idParams { memory_kib, iterations, parallelism };
+                (PasswordKdfParams::Argon2id(params), salt)
+            }
+            ["", "scrypt", values, salt] => {
+                let [log_n, r, p] = parse_values(values, ["ln", "r", "p"])?;
+                let log_n = u8::try_from(log_n).map_err(|_| "scrypt parameters exceed allowed limits".to_string())?;
+                (PasswordKdfParams::Scrypt(ScryptParams { log_n, r, p }), salt)
+            }
+            ["", "pbkdf2-sha256", values, salt] => {
+                let [iterations] = parse_values(values, ["i"])?;
+                (PasswordKdfParams::Pbkdf2Sha256(Pbkdf2Params { iterations }), salt)
+            }
+            _ => return Err("Unsupported password KDF encoding".to_string()),
+        };
+        params.check_limits()?;
+        let salt = STANDARD_NO_PAD.decode(salt).map_err(|_| "Invalid salt encoding".to_string())?;
+        Ok((params, salt))
+    }
+}
+
+/// 依序解析 `name=value,...`，名稱與順序必須完全相符
+fn parse_values<const N: usize>(values: &str, names: [&str; N]) -> Result<[u32; N], String> {
+    let invalid = || format!("Invalid password KDF parameters: {}", values);
+    let mut parsed = [0u32; N];
+    let mut pairs = values.split(',');
+    for (slot, name) in parsed.iter_mut().zip(names) {
+        let (key, value) = pairs.next().and_then(|pair| pair.split_once('=')).ok_or_else(invalid)?;
+        if key != name {
+            return Err(invalid());
+        }
+        *slot = value.parse().map_err(|_| invalid())?;
+    }
+    if pairs.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(parsed)
+}
+
+/// 以參數字串重新衍生金鑰 (Rust 端使用)
+pub fn derive_key_from_encoded_params(password: &[u8], encoded: &str) -> Result<[u8; 32], String> {
+    let (params, salt) = PasswordKdfParams::decode(encoded)?;
+    params.derive_key(password, &salt)
+}
+
+/// 密碼 KDF 設定
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug)]
+pub struct PasswordKdf {
+    params: PasswordKdfParams,
+}
+
+impl PasswordKdf {
+    /// 取得參數 (Rust 端使用)
+    pub fn params(&self) -> PasswordKdfParams {
+        self.params
+    }
+}
+
+impl From<PasswordKdfParams> for PasswordKdf {
+    fn from(params: PasswordKdfParams) -> Self {
+        Self { params }
+    }
+}
+
+#[wasm_bindgen]
+impl PasswordKdf {
+    /// Argon2id，未指定的參數使用預設值 (19 MiB、2 次迭代、平行度 1)
+    pub fn argon2id(
+        memory_kib: Option<u32>,
+        iterations: Option<u32>,
+        parallelism: Option<u32>,
+    ) -> Result<PasswordKdf, JsError> {
+        let defaults = Argon2idParams::default();
+        let params = PasswordKdfParams::Argon2id(Argon2idParams {
+            memory_kib: memory_kib.unwrap_or(defaults.memory_kib),
+            iterations: iterations.unwrap_or(defaults.iterations),
+            parallelism: parallelism.unwrap_or(defaults.parallelism),
+        });
+        params.check_limits().map_err(|e| JsError::new(&e))?;
+        Ok(params.into())
+    }
+
+    /// scrypt，未指定的參數使用預設值 (N = 2^17、r = 8、p = 1)
+    pub fn scrypt(log_n: Option<u8>, r: Option<u32>, p: Option<u32>) -> Result<PasswordKdf, JsError> {
+        let defaults = ScryptParams::default();
+        let params = PasswordKdfParams::Scrypt(ScryptParams {
+            log_n: log_n.unwrap_or(defaults.log_n),
+            r: r.unwrap_or(defaults.r),
+            p: p.unwrap_or(defaults.p),
+        });
+        params.check_limits().map_err(|e| JsError::new(&e))?;
+        Ok(params.into())
+    }
+
+    /// PBKDF2-HMAC-SHA256，僅供與舊版或其他應用程式相容 (預設 600,000 次迭代)
+    pub fn pbkdf2(iterations: Option<u32>) -> Result<PasswordKdf, JsError> {
+        let params = PasswordKdfParams::Pbkdf2Sha256(Pbkdf2Params {
+            iterations: iterations.unwrap_or(Pbkdf2Params::default().iterations),
+        });
+        params.check_limits().map_err(|e| JsError::new(&e))?;
+        Ok(params.into())
+    }
+
+    /// 演算法名稱 ("argon2id" / "scrypt" / "pbkdf2-sha256")
+    #[wasm_bindgen(getter)]
+    pub fn algorithm(&self) -> String {
+        match self.params {
+            PasswordKdfParams::Argon2id(_) => "argon2id",
+            PasswordKdfParams::Scrypt(_) => "scrypt",
+            PasswordKdfParams::Pbkdf2Sha256(_) => "pbkdf2-sha256",
+        }
+        .to_string()
+    }
+}
+
+/// 密碼衍生出的金鑰與參數字串
+#[wasm_bindgen]
+pub struct DerivedPasswordKey {
+    key: SecretBytes,
+    encoded: String,
+}
+
+#[wasm_bindgen]
+impl DerivedPasswordKey {
+    /// 32 bytes 金鑰
+    #[wasm_bindgen(getter)]
+    pub fn key(&self) -> SecretBytes {
+        self.key.clone()
+    }
+
+    /// 參數字串 (含 salt)，保存後可用 `deriveKeyFromEncoded` 重新衍生
+    #[wasm_bindgen(getter)]
+    pub fn encoded(&self) -> String {
+        self.encoded.clone()
+    }
+}
+
+/// 從密碼衍生金鑰 (Rust 端使用)
+pub fn derive_password_key(password: &[u8], salt: &[u8], params: &PasswordKdfParams) -> Result<DerivedPasswordKey, String> {
+    let key = params.derive_key(password, salt)?;
+    Ok(DerivedPasswordKey { key: key.into(), encoded: params.encode(salt) })
+}
+
+/// 從密碼衍生 32 bytes 金鑰，未指定參數時使用 Argon2id 預設值
+#[wasm_bindgen(js_name = deriveKeyFromPassword)]
+pub fn derive_key_from_password(
+    password: &str,
+    salt: &[u8],
+    params: Option<PasswordKdf>,
+) -> Result<DerivedPasswordKey, JsError> {
+    let params = params.map(|kdf| kdf.params).unwrap_or_default();
+    derive_password_key(password.as_bytes(), salt, &params).map_err(|e| JsError::new(&e))
+}
+
+/// 以 `deriveKeyFromPassword` 輸出的參數字串重新衍生金鑰
+#[wasm_bindgen(js_name = deriveKeyFromEncoded)]
+pub fn derive_key_from_encoded(password: &str, encoded: &str) -> Result<SecretBytes, JsError> {
+    derive_key_from_encoded_params(password.as_bytes(), encoded)
+        .map(SecretBytes::from)
+        .map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::to_hex;
+
+    const SALT: &[u8] = b"saltsaltsaltsalt";
+
+    #[test]
+    fn test_known_answers() {
+        let argon2 = PasswordKdfParams::Argon2id(Argon2idParams { memory_kib: 64, iterations: 1, parallelism: 1 });
+        let derived = derive_password_key(b"password", SALT, &argon2).unwrap();
+        assert_eq!(to_hex(derived.key().expose()), "59bf4338b29483094be5f8da77db5f08f534481028d0f118fdebc9461c2d511d");
+        assert_eq!(derived.encoded(), "$argon2id$v=19$m=64,t=1,p=1$c2FsdHNhbHRzYWx0c2FsdA");
+
+        let scrypt = PasswordKdfParams::Scrypt(ScryptParams { log_n: 4, r: 1, p: 1 });
+        let derived = derive_password_key(b"password", SALT, &scrypt).unwrap();
+        assert_eq!(to_hex(derived.key().expose()), "396f980b3e6192884d4025bb5a5781ab38f6a88c90af75ceafcb80ab7a22b5ed");
+        assert_eq!(derived.encoded(), "$scrypt$ln=4,r=1,p=1$c2FsdHNhbHRzYWx0c2FsdA");
+
+        assert_eq!(derive_key_from_encoded("password", &derived.encoded()).unwrap(), derived.key());
+        assert_ne!(derive_key_from_encoded_params(b"wrong", &derived.encoded()).unwrap(), derived.key().expose());
+    }
+
+    #[test]
+    fn test_pbkdf2_compatibility() {
+        // RFC 7914 §11 的 PBKDF2-HMAC-SHA256 向量 (取前 32 bytes)
+        let key = Pbkdf2Params { iterations: 1 }.derive_key(b"passwd", b"salt").unwrap();
+        assert_eq!(to_hex(&key), "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc");
+        assert!(Pbkdf2Params { iterations: 0 }.derive_key(b"password", b"salt").is_err());
+
+        let params = PasswordKdfParams::Pbkdf2Sha256(Pbkdf2Params { iterations: 4096 });
+        let derived = derive_password_key(b"password", SALT, &params).unwrap();
+        assert_eq!(derived.encoded(), "$pbkdf2-sha256$i=4096$c2FsdHNhbHRzYWx0c2FsdA");
+        assert_eq!(PasswordKdfParams::decode(&derived.encoded()).unwrap(), (params, SALT.to_vec()));
+        assert!(PasswordKdfParams::decode("$pbkdf2-sha256$i=4294967295$c2FsdA").is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_invalid_params() {
+        assert!(PasswordKdfParams::decode("$argon2id$v=19$m=64,t=1$c2FsdA").is_err());
+        assert!(PasswordKdfParams::decode("$argon2id$v=16$m=64,t=1,p=1$c2FsdA").is_err());
+        assert!(PasswordKdfParams::decode("$argon2id$v=19$t=1,m=64,p=1$c2FsdA").is_err());
+        assert!(PasswordKdfParams::decode("$argon2i$v=19$m=64,t=1,p=1$c2FsdA").is_err());
+        assert!(PasswordKdfParams::decode("$scrypt$ln=4,r=1,p=1$!!").is_err());
+
+        // 超出上限的參數
+        assert!(PasswordKdfParams::decode("$argon2id$v=19$m=4194304,t=1,p=1$c2FsdA").is_err());
+        assert!(PasswordKdfParams::decode("$scrypt$ln=30,r=8,p=1$c2FsdA").is_err());
+        assert!(PasswordKdfParams::decode("$scrypt$ln=300,r=8,p=1$c2FsdA").is_err());
+
+        let (params, salt) = PasswordKdfParams::decode("$scrypt$ln=17,r=8,p=1$c2FsdA").unwrap();
+        assert_eq!(params, PasswordKdfParams::Scrypt(ScryptParams::default()));
+        assert_eq!(salt, b"salt");
+
+        // salt 太短
+        assert!(params.derive_key(b"password", b"salt").is_err());
+    }
+}
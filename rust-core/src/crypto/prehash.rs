@@ -0,0 +1,121 @@
+//! Ed25519ph 預雜湊簽章模組
+//!
+//! 大型附件或備份可分段餵入 SHA-512，最後再以 Ed25519ph (RFC 8032) 簽署摘要，
+//! 不需要一次把整份資料傳進 WASM
+
+use wasm_bindgen::prelude::*;
+use ed25519_dalek::{Signature, VerifyingKey};
+use sha2::{Digest, Sha512};
+
+use super::keys::IdentityKeyPair;
+
+/// 串流式預雜湊訊息
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct PrehashedMessage {
+    hasher: Sha512,
+}
+
+#[wasm_bindgen]
+impl PrehashedMessage {
+    /// 建立空的預雜湊訊息
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 加入一段資料
+    pub fn update(&mut self, chunk: &[u8]) {
+        self.hasher.update(chunk);
+    }
+
+    /// 目前的 SHA-512 摘要 (不影響後續 update)
+    pub fn digest(&self) -> Vec<u8> {
+        self.hasher.clone().finalize().to_vec()
+    }
+}
+
+#[wasm_bindgen]
+impl IdentityKeyPair {
+    /// 以 Ed25519ph 簽署預雜湊訊息
+    #[wasm_bindgen(js_name = signPrehashed)]
+    pub fn sign_prehashed(&self, message: &PrehashedMessage) -> Vec<u8> {
+        self.signing_key()
+            .sign_prehashed(message.hasher.clone(), None)
+            .expect("Ed25519ph without context cannot fail")
+            .to_bytes()
+            .to_vec()
+    }
+
+    /// 驗證 Ed25519ph 簽章
+    #[wasm_bindgen(js_name = verifyPrehashed)]
+    pub fn verify_prehashed(public_key: &[u8], message: &PrehashedMessage, signature: &[u8]) -> bool {
+        let pk_bytes: [u8; 32] = match public_key.try_into() {
+            Ok(bytes) => bytes,
+            Err(_) => return false,
+        };
+        let verifying_key = match VerifyingKey::from_bytes(&pk_bytes) {
+            Ok(k) => k,
+            Err(_) => return false,
+        };
+        let signature = match Signature::from_slice(signature) {
+            Ok(sig) => sig,
+            Err(_) => return false,
+        };
+        verifying_key
+            .verify_prehashed(message.hasher.clone(), None, &signature)
+            .is_ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_rfc8032_ed25519ph_vector() {
+        // RFC 8032 7.3 TEST abc
+        let keypair = IdentityKeyPair::from_bytes(&hex(
+            "833fe62409237b9d62ec77587520911e9a759cec1d19755b7da901b96dca3d42",
+        ))
+        .unwrap();
+        assert_eq!(
+            keypair.public_key_bytes(),
+            hex("ec172b93ad5e563bf4932c70e1245034c35467ef2efd4d64ebf819683467e2bf")
+        );
+
+        let mut message = PrehashedMessage::new();
+        message.update(b"abc");
+        let signature = keypair.sign_prehashed(&message);
+        assert_eq!(
+            signature,
+            hex("98a70222f0b8121aa9d30f813d683f809e462b469c7ff87639499bb94e6dae41\
+                 31f85042463c2a355a2003d062adf5aaa10b8c61e636062aaad11c2a26083406")
+        );
+        assert!(IdentityKeyPair::verify_prehashed(&keypair.public_key_bytes(), &message, &signature));
+    }
+
+    #[test]
+    fn test_streaming_matches_single_update() {
+        let keypair = IdentityKeyPair::new();
+        let data = vec![0x42u8; 3 * 1024 * 1024];
+
+        let mut streamed = PrehashedMessage::new();
+        for chunk in data.chunks(64 * 1024) {
+            streamed.update(chunk);
+        }
+        let mut whole = PrehashedMessage::new();
+        whole.update(&data);
+        assert_eq!(streamed.digest(), whole.digest());
+
+        let signature = keypair.sign_prehashed(&streamed);
+        assert!(IdentityKeyPair::verify_prehashed(&keypair.public_key_bytes(), &whole, &signature));
+
+        // Ed25519ph 簽章不能當作一般 Ed25519 簽章使用
+        assert!(!IdentityKeyPair::verify_signature(&keypair.public_key_bytes(), &data, &signature));
+
+        whole.update(b"tampered");
+        assert!(!IdentityKeyPair::verify_prehashed(&keypair.public_key_bytes(), &whole, &signature));
+    }
+}
@@ -0,0 +1,262 @@
+//! 一次性預金鑰池模組
+//!
+//! 管理 One-Time PreKey 的批次生成、消耗記錄與補充
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::keys::{OneTimePreKey, X25519KeyPair};
+use super::x3dh::{X3DHInitialMessage, X3DH};
+
+/// 預設低水位：剩餘數量低於此值時需要補充
+pub const DEFAULT_REPLENISH_THRESHOLD: u32 = 20;
+
+/// 預設目標數量：補充時補到此數量
+pub const DEFAULT_TARGET_COUNT: u32 = 100;
+
+/// 單次最多生成的金鑰數量
+pub const MAX_BATCH_SIZE: u32 = 1000;
+
+/// 一次性預金鑰池
+///
+/// 私鑰只保存在本地，公鑰以批次方式發布到伺服器。
+/// 每把金鑰只能被消耗一次，消耗後私鑰立即刪除。
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct OneTimePreKeyPool {
+    /// 下一個要分配的金鑰 ID
+    next_id: u32,
+    /// 尚未消耗的金鑰對 (key_id -> key pair)
+    keys: BTreeMap<u32, X25519KeyPair>,
+    /// 已消耗的金鑰數量
+    consumed_count: u64,
+    /// 補充門檻
+    threshold: u32,
+    /// 補充目標數量
+    target_count: u32,
+}
+
+impl OneTimePreKeyPool {
+    /// 生成 `count` 把新的一次性預金鑰，回傳公開部分 (最多 `MAX_BATCH_SIZE` 把)
+    pub fn generate_keys(&mut self, count: u32) -> Result<Vec<OneTimePreKey>, String> {
+        if count > MAX_BATCH_SIZE {
+            return Err(format!("Cannot generate more than {} prekeys at once", MAX_BATCH_SIZE));
+        }
+        let mut batch = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let key_id = self.next_id;
+            self.next_id = self
+                .next_id
+                .checked_add(1)
+                .ok_or_else(|| "One-time prekey ID space exhausted".to_string())?;
+
+            let keypair = X25519KeyPair::new();
+            batch.push(OneTimePreKey {
+                key_id,
+                public_key: keypair.public_key_bytes(),
+            });
+            self.keys.insert(key_id, keypair);
+        }
+        Ok(batch)
+    }
+
+    /// 消耗指定的一次性預金鑰，回傳私鑰
+    ///
+    /// 金鑰不存在或已被消耗時回傳錯誤 (防止重放)
+    pub fn consume(&mut self, key_id: u32) -> Result<Vec<u8>, String> {
+        let keypair = self
+            .keys
+            .remove(&key_id)
+            .ok_or_else(|| format!("Unknown or already consumed one-time prekey: {}", key_id))?;
+        self.consumed_count += 1;
+        Ok(keypair.private_key_bytes().expose().to_vec())
+    }
+
+    /// 讀取尚未消耗的金鑰私鑰 (不消耗)
+    pub fn private_key(&self, key_id: u32) -> Result<Vec<u8>, String> {
+        self.keys
+            .get(&key_id)
+            .map(|keypair| keypair.private_key_bytes().expose().to_vec())
+            .ok_or_else(|| format!("Unknown or already consumed one-time prekey: {}", key_id))
+    }
+
+    /// 若剩餘數量低於門檻，生成補充批次 (補到目標數量)
+    ///
+    /// 不需要補充時回傳空陣列
+    pub fn replenish_keys(&mut self) -> Result<Vec<OneTimePreKey>, String> {
+        if !self.needs_replenishment() {
+            return Ok(Vec::new());
+        }
+        let missing = self.target_count.saturating_sub(self.remaining_count());
+        self.generate_keys(missing)
+    }
+}
+
+#[wasm_bindgen]
+impl OneTimePreKeyPool {
+    /// 建立新的金鑰池
+    ///
+    /// # 參數
+    /// - `start_id`: 第一把金鑰的 ID
+    /// - `threshold`: 補充門檻 (未提供時使用預設值)
+    /// - `target_count`: 補充目標數量 (未提供時使用預設值)
+    #[wasm_bindgen(constructor)]
+    pub fn new(start_id: u32, threshold: Option<u32>, target_count: Option<u32>) -> Self {
+        Self {
+            next_id: start_id,
+            keys: BTreeMap::new(),
+            consumed_count: 0,
+            threshold: threshold.unwrap_or(DEFAULT_REPLENISH_THRESHOLD),
+            target_count: target_count.unwrap_or(DEFAULT_TARGET_COUNT),
+        }
+    }
+
+    /// 生成一批一次性預金鑰，回傳公鑰列表 JSON
+    #[wasm_bindgen(js_name = generateBatch)]
+    pub fn generate_batch(&mut self, count: u32) -> Result<String, JsError> {
+        let batch = self.generate_keys(count).map_err(|e| JsError::new(&e))?;
+        serde_json::to_string(&batch).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 生成補充批次，回傳公鑰列表 JSON (不需要補充時為空陣列)
+    #[wasm_bindgen(js_name = replenish)]
+    pub fn replenish(&mut self) -> Result<String, JsError> {
+        let batch = self.replenish_keys().map_err(|e| JsError::new(&e))?;
+        serde_json::to_string(&batch).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 消耗指定金鑰並回傳私鑰
+    #[wasm_bindgen(js_name = consumeKey)]
+    pub fn consume_key(&mut self, key_id: u32) -> Result<Vec<u8>, JsError> {
+        self.consume(key_id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 是否持有指定金鑰 (尚未消耗)
+    #[wasm_bindgen(js_name = containsKey)]
+    pub fn contains_key(&self, key_id: u32) -> bool {
+        self.keys.contains_key(&key_id)
+    }
+
+    /// 剩餘可用的金鑰數量
+    #[wasm_bindgen(getter, js_name = remainingCount)]
+    pub fn remaining_count(&self) -> u32 {
+        self.keys.len() as u32
+    }
+
+    /// 累計已消耗的金鑰數量
+    #[wasm_bindgen(getter, js_name = consumedCount)]
+    pub fn consumed_count(&self) -> u64 {
+        self.consumed_count
+    }
+
+    /// 下一個要分配的金鑰 ID
+    #[wasm_bindgen(getter, js_name = nextId)]
+    pub fn next_id(&self) -> u32 {
+        self.next_id
+    }
+
+    /// 剩餘數量是否低於補充門檻
+    #[wasm_bindgen(js_name = needsReplenishment)]
+    pub fn needs_replenishment(&self) -> bool {
+        self.remaining_count() < self.threshold
+    }
+
+    /// 序列化金鑰池 (包含私鑰，敏感！)
+    #[wasm_bindgen(js_name = serialize)]
+    pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 還原金鑰池
+    #[wasm_bindgen(js_name = deserialize)]
+    pub fn deserialize(bytes: &[u8]) -> Result<OneTimePreKeyPool, JsError> {
+        bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[wasm_bindgen]
+impl X3DH {
+    /// 接收者：使用金鑰池計算共享密鑰
+    ///
+    /// 依初始訊息中的 `one_time_prekey_id` 從金鑰池取出對應私鑰，計算成功後才消耗，
+    /// 同一把一次性預金鑰無法被使用第二次，偽造的初始訊息也無法耗盡金鑰池
+    #[wasm_bindgen(js_name = responderCalculateWithPool)]
+    pub fn responder_calculate_with_pool(
+        recipient_identity_private: &[u8],
+        recipient_signed_prekey_private: &[u8],
+        pool: &mut OneTimePreKeyPool,
+        initial_message: &X3DHInitialMessage,
+    ) -> Result<Vec<u8>, JsError> {
+        let one_time_prekey_id = initial_message.one_time_prekey_id();
+        let one_time_prekey_private = match one_time_prekey_id {
+            Some(id) => Some(pool.private_key(id).map_err(|e| JsError::new(&e))?),
+            None => None,
+        };
+
+        let shared_secret = X3DH::responder_calculate(
+            recipient_identity_private,
+            recipient_signed_prekey_private,
+            one_time_prekey_private,
+            &initial_message.sender_identity_key(),
+            &initial_message.ephemeral_key(),
+        )?;
+        if let Some(id) = one_time_prekey_id {
+            pool.consume(id).map_err(|e| JsError::new(&e))?;
+        }
+        Ok(shared_secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_and_consume() {
+        let mut pool = OneTimePreKeyPool::new(1, Some(3), Some(5));
+        let batch = pool.generate_keys(5).unwrap();
+
+        let ids: Vec<u32> = batch.iter().map(|k| k.key_id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4, 5]);
+        assert_eq!(pool.remaining_count(), 5);
+
+        // 私鑰與公鑰相符
+        let private = pool.consume(3).unwrap();
+        let restored = X25519KeyPair::from_bytes(&private).unwrap();
+        assert_eq!(restored.public_key_bytes(), batch[2].public_key);
+
+        // 不能重複消耗
+        assert!(pool.consume(3).is_err());
+        assert_eq!(pool.remaining_count(), 4);
+        assert_eq!(pool.consumed_count(), 1);
+
+        // 單次生成數量有上限
+        assert!(pool.generate_keys(MAX_BATCH_SIZE + 1).is_err());
+        assert!(pool.generate_keys(u32::MAX).is_err());
+        assert_eq!(pool.remaining_count(), 4);
+    }
+
+    #[test]
+    fn test_replenishment() {
+        let mut pool = OneTimePreKeyPool::new(10, Some(3), Some(5));
+        assert!(pool.needs_replenishment());
+
+        let first = pool.replenish_keys().unwrap();
+        assert_eq!(first.len(), 5);
+        assert!(!pool.needs_replenishment());
+        assert!(pool.replenish_keys().unwrap().is_empty());
+
+        for id in 10..13 {
+            pool.consume(id).unwrap();
+        }
+        assert!(pool.needs_replenishment());
+
+        // 補到目標數量，ID 接續
+        let refill = pool.replenish_keys().unwrap();
+        assert_eq!(refill.len(), 3);
+        assert_eq!(refill[0].key_id, 15);
+        assert_eq!(pool.remaining_count(), 5);
+    }
+}
@@ -0,0 +1,124 @@
+//! protobuf 線路格式模組
+//!
+//! Olm 訊息 (見 `olm` 模組) 與 Signal 備份 frame (見 `storage::signal_import`) 共用的
+//! 最小 protobuf 讀寫，不需要 .proto 定義：
+//! - 讀取時依序回傳 (欄位編號, 值)，未知欄位由呼叫端略過
+//! - 支援 varint、64 位元、長度前綴與 32 位元四種 wire type；group (3、4) 視為錯誤
+
+/// 欄位值
+pub(crate) enum Field<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    /// 內容目前沒有使用者，只略過
+    Fixed32,
+}
+
+pub(crate) fn read_varint(bytes: &[u8], position: &mut usize) -> Result<u64, String> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *bytes.get(*position).ok_or("Truncated protobuf varint")?;
+        *position += 1;
+        value |= u64::from(byte & 0x7f) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("Invalid protobuf varint".to_string())
+}
+
+fn take<'a>(bytes: &'a [u8], position: &mut usize, length: usize) -> Result<&'a [u8], String> {
+    let end = position
+        .checked_add(length)
+        .filter(|end| *end <= bytes.len())
+        .ok_or("Truncated protobuf field")?;
+    let value = &bytes[*position..end];
+    *position = end;
+    Ok(value)
+}
+
+/// 解析 protobuf 訊息的欄位 (欄位編號, 值)
+pub(crate) fn fields(bytes: &[u8]) -> Result<Vec<(u32, Field<'_>)>, String> {
+    let mut position = 0;
+    let mut result = Vec::new();
+    while position < bytes.len() {
+        let tag = read_varint(bytes, &mut position)?;
+        let number = u32::try_from(tag >> 3).map_err(|_| "Invalid protobuf field number")?;
+        let field = match tag & 7 {
+            0 => Field::Varint(read_varint(bytes, &mut position)?),
+            1 => Field::Fixed64(u64::from_le_bytes(take(bytes, &mut position, 8)?.try_into().expect("8 bytes"))),
+            2 => {
+                let length = usize::try_from(read_varint(bytes, &mut position)?).map_err(|_| "Truncated protobuf field")?;
+                Field::Bytes(take(bytes, &mut position, length)?)
+            }
+            5 => {
+                take(bytes, &mut position, 4)?;
+                Field::Fixed32
+            }
+            wire_type => return Err(format!("Unsupported protobuf wire type: {}", wire_type)),
+        };
+        result.push((number, field));
+    }
+    Ok(result)
+}
+
+/// 第一個編號為 `number` 的 varint 欄位
+pub(crate) fn varint_field(fields: &[(u32, Field<'_>)], number: u32) -> Option<u64> {
+    fields.iter().find_map(|(n, field)| match field {
+        Field::Varint(value) if *n == number => Some(*value),
+        _ => None,
+    })
+}
+
+/// 第一個編號為 `number` 的長度前綴欄位
+pub(crate) fn bytes_field<'a>(fields: &[(u32, Field<'a>)], number: u32) -> Option<&'a [u8]> {
+    fields.iter().find_map(|(n, field)| match field {
+        Field::Bytes(value) if *n == number => Some(*value),
+        _ => None,
+    })
+}
+
+pub(crate) fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+pub(crate) fn write_varint_field(out: &mut Vec<u8>, number: u32, value: u64) {
+    write_varint(out, u64::from(number) << 3);
+    write_varint(out, value);
+}
+
+pub(crate) fn write_bytes_field(out: &mut Vec<u8>, number: u32, bytes: &[u8]) {
+    write_varint(out, u64::from(number) << 3 | 2);
+    write_varint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_protobuf_round_trip() {
+        let mut message = Vec::new();
+        write_bytes_field(&mut message, 1, b"abc");
+        write_varint_field(&mut message, 2, 300);
+        write_bytes_field(&mut message, 16, &[]);
+        // 欄位 1 (bytes "abc")、欄位 2 (varint 300)、欄位 16 (空 bytes)
+        assert_eq!(message, [0x0a, 3, b'a', b'b', b'c', 0x10, 0xac, 0x02, 0x82, 0x01, 0]);
+
+        let parsed = fields(&message).unwrap();
+        assert_eq!(parsed.len(), 3);
+        assert_eq!(bytes_field(&parsed, 1), Some(&b"abc"[..]));
+        assert_eq!(varint_field(&parsed, 2), Some(300));
+        assert_eq!(bytes_field(&parsed, 16), Some(&[][..]));
+        assert_eq!(varint_field(&parsed, 1), None);
+
+        assert!(fields(&message[..message.len() - 2]).is_err());
+        assert!(fields(&[0x0b]).is_err());
+        assert!(fields(&[0x08; 11]).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! 裝置連結 (Provisioning) 模組
+//!
+//! 新裝置產生臨時 X25519 金鑰並以 provisioning URI (QR 碼) 顯示，
+//! 主裝置掃描後把身份金鑰與個人資料加密給該臨時公鑰：
+//!
+//! 1. 新裝置：`ProvisioningSession` → `provisioningUri`
+//! 2. 主裝置：`parseProvisioningUri` → `ProvisionMessage.encryptForDevice`
+//! 3. 新裝置：`decryptEnvelope` 取得身份金鑰
+//!
+//! 信封格式：version (1) || HPKE enc (32) || AES-256-GCM 密文
+//! (HPKE base 模式，info 為 `SafeTalk_Provisioning`，AAD 為版本位元組)
+
+use wasm_bindgen::prelude::*;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64_URL};
+use serde::{Deserialize, Serialize};
+
+use super::hpke::{hpke_open, hpke_seal, HpkeAead, HPKE_ENC_SIZE};
+use super::keys::{IdentityKeyPair, X25519KeyPair};
+
+const INFO_PROVISIONING: &[u8] = b"SafeTalk_Provisioning";
+
+/// provisioning URI 前綴
+pub const PROVISIONING_URI_PREFIX: &str = "safetalk://link";
+
+/// 目前的信封版本
+pub const PROVISIONING_VERSION: u8 = 2;
+
+const ENVELOPE_HEADER_SIZE: usize = 1 + HPKE_ENC_SIZE;
+
+/// 解析 provisioning URI，回傳 (裝置 UUID, 臨時公鑰)
+pub fn parse_provisioning_uri(uri: &str) -> Result<(String, Vec<u8>), String> {
+    let query = uri
+        .strip_prefix(PROVISIONING_URI_PREFIX)
+        .and_then(|rest| rest.strip_prefix('?'))
+        .ok_or_else(|| "Not a provisioning URI".to_string())?;
+
+    let mut uuid = None;
+    let mut public_key = None;
+    for pair in query.split('&') {
+        match pair.split_once('=') {
+            Some(("uuid", value)) => uuid = Some(value.to_string()),
+            Some(("pub_key", value)) => {
+                let bytes = BASE64_URL
+                    .decode(value)
+                    .map_err(|e| format!("Invalid provisioning public key: {}", e))?;
+                if bytes.len() != 32 {
+                    return Err("Provisioning public key must be 32 bytes".to_string());
+                }
+                public_key = Some(bytes);
+            }
+            _ => {}
+        }
+    }
+
+    match (uuid, public_key) {
+        (Some(uuid), Some(public_key)) => Ok((uuid, public_key)),
+        _ => Err("Provisioning URI is missing uuid or pub_key".to_string()),
+    }
+}
+
+/// 主裝置傳給新裝置的連結資料
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProvisionMessage {
+    /// 身份私鑰 (Ed25519)
+    identity_private_key: Vec<u8>,
+    /// 帳號 ID
+    account_id: String,
+    /// 分配給新裝置的裝置 ID
+    device_id: u32,
+    /// 個人資料金鑰 (如有)
+    profile_key: Option<Vec<u8>>,
+}
+
+impl ProvisionMessage {
+    /// 加密給新裝置的臨時公鑰
+    pub fn encrypt_for(&self, device_public_key: &[u8]) -> Result<Vec<u8>, String> {
+        let body = bincode::serialize(self).map_err(|e| e.to_string())?;
+        let version = [PROVISIONING_VERSION];
+        let mut envelope = version.to_vec();
+        envelope.extend(hpke_seal(HpkeAead::Aes256Gcm, device_public_key, INFO_PROVISIONING, &version, &body)?);
+        Ok(envelope)
+    }
+}
+
+#[wasm_bindgen]
+impl ProvisionMessage {
+    /// 建立連結資料
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        identity: &IdentityKeyPair,
+        account_id: &str,
+        device_id: u32,
+        profile_key: Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            identity_private_key: identity.private_key_bytes().expose().to_vec(),
+            account_id: account_id.to_string(),
+            device_id,
+            profile_key,
+        }
+    }
+
+    /// 加密給新裝置的臨時公鑰，回傳信封
+    #[wasm_bindgen(js_name = encryptForDevice)]
+    pub fn encrypt_for_device(&self, device_public_key: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.encrypt_for(device_public_key).map_err(|e| JsError::new(&e))
+    }
+
+    /// 身份金鑰對
+    #[wasm_bindgen(js_name = identityKeyPair)]
+    pub fn identity_key_pair(&self) -> Result<IdentityKeyPair, JsError> {
+        IdentityKeyPair::from_bytes(&self.identity_private_key)
+    }
+
+    #[wasm_bindgen(getter, js_name = accountId)]
+    pub fn account_id(&self) -> String {
+        self.account_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = deviceId)]
+    pub fn device_id(&self) -> u32 {
+        self.device_id
+    }
+
+    #[wasm_bindgen(getter, js_name = profileKey)]
+    pub fn profile_key(&self) -> Option<Vec<u8>> {
+        self.profile_key.clone()
+    }
+}
+
+/// 新裝置的連結流程
+#[wasm_bindgen]
+pub struct ProvisioningSession {
+    uuid: String,
+    ephemeral: X25519KeyPair,
+}
+
+impl ProvisioningSession {
+    /// 解密主裝置送來的信封
+    pub fn decrypt(&self, envelope: &[u8]) -> Result<ProvisionMessage, String> {
+        if envelope.len() < ENVELOPE_HEADER_SIZE {
+            return Err("Provisioning envelope too short".to_string());
+        }
+        if envelope[0] != PROVISIONING_VERSION {
+            return Err(format!("Unsupported provisioning version: {}", envelope[0]));
+        }
+        let (version, sealed) = envelope.split_at(1);
+        let body = hpke_open(HpkeAead::Aes256Gcm, &self.ephemeral, INFO_PROVISIONING, version, sealed)
+            .map_err(|_| "Failed to decrypt provisioning envelope".to_string())?;
+        bincode::deserialize(&body).map_err(|e| format!("Invalid provisioning message: {}", e))
+    }
+}
+
+#[wasm_bindgen]
+impl ProvisioningSession {
+    /// 新裝置：開始連結流程
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self {
+            uuid: uuid::Uuid::new_v4().to_string(),
+            ephemeral: X25519KeyPair::new(),
+        }
+    }
+
+    /// 裝置 UUID (伺服器轉送信封用)
+    #[wasm_bindgen(getter)]
+    pub fn uuid(&self) -> String {
+        self.uuid.clone()
+    }
+
+    /// 臨時公鑰
+    #[wasm_bindgen(getter, js_name = publicKey)]
+    pub fn public_key(&self) -> Vec<u8> {
+        self.ephemeral.public_key_bytes()
+    }
+
+    /// provisioning URI (顯示為 QR 碼)
+    #[wasm_bindgen(getter, js_name = provisioningUri)]
+    pub fn provisioning_uri(&self) -> String {
+        format!(
+            "{}?uuid={}&pub_key={}",
+            PROVISIONING_URI_PREFIX,
+            self.uuid,
+            BASE64_URL.encode(self.ephemeral.public_key_bytes())
+        )
+    }
+
+    /// 解密主裝置送來的信封
+    #[wasm_bindgen(js_name = decryptEnvelope)]
+    pub fn decrypt_envelope(&self, envelope: &[u8]) -> Result<ProvisionMessage, JsError> {
+        self.decrypt(envelope).map_err(|e| JsError::new(&e))
+    }
+}
+
+impl Default for ProvisioningSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 主裝置：解析 provisioning URI，回傳臨時公鑰
+#[wasm_bindgen(js_name = parseProvisioningUri)]
+pub fn parse_provisioning_uri_js(uri: &str) -> Result<Vec<u8>, JsError> {
+    parse_provisioning_uri(uri)
+        .map(|(_, public_key)| public_key)
+        .map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provisioning_flow() {
+        let identity = IdentityKeyPair::new();
+
+        // 新裝置顯示 QR 碼
+        let new_device = ProvisioningSession::new();
+        let (uuid, public_key) = parse_provisioning_uri(&new_device.provisioning_uri()).unwrap();
+        assert_eq!(uuid, new_device.uuid());
+
+        // 主裝置加密身份金鑰
+        let message = ProvisionMessage::new(&identity, "alice", 2, Some(vec![9u8; 32]));
+        let envelope = message.encrypt_for(&public_key).unwrap();
+
+        let received = new_device.decrypt(&envelope).unwrap();
+        assert_eq!(received.identity_key_pair().unwrap().public_key_bytes(), identity.public_key_bytes());
+        assert_eq!(received.account_id(), "alice");
+        assert_eq!(received.device_id(), 2);
+        assert_eq!(received.profile_key(), Some(vec![9u8; 32]));
+
+        // 其他裝置無法解密
+        assert!(ProvisioningSession::new().decrypt(&envelope).is_err());
+        let mut tampered = envelope;
+        tampered[5] ^= 0x01;
+        assert!(new_device.decrypt(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_parse_provisioning_uri_rejects_invalid() {
+        assert!(parse_provisioning_uri("https://example.com?uuid=a&pub_key=b").is_err());
+        assert!(parse_provisioning_uri("safetalk://link?uuid=a").is_err());
+        assert!(parse_provisioning_uri("safetalk://link?uuid=a&pub_key=AAAA").is_err());
+    }
+}
@@ -10,10 +10,15 @@ use sha2::Sha256;
 use hmac::{Hmac, Mac};
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
-use super::aes::{AesGcmCipher, EncryptedMessage};
+use rand::{rngs::OsRng, RngCore};
+
+use super::aead::{CipherSuite, AEAD_NONCE_SIZE};
+use super::compression::Compression;
 use super::keys::X25519KeyPair;
 
 const MAX_SKIP: u32 = 1000;
+/// 壓縮訊息解壓縮後的最大長度
+const MAX_DECOMPRESSED_SIZE: usize = 16 * 1024 * 1024;
 const INFO_RATCHET: &[u8] = b"SafeTalk_Ratchet";
 const INFO_MESSAGE_KEYS: &[u8] = b"SafeTalk_MessageKeys";
 
@@ -54,6 +59,9 @@ pub struct RatchetSession {
     prev_send_count: u32,
     /// 跳過的訊息金鑰
     skipped_keys: SkippedKeys,
+    /// 發送訊息使用的 cipher suite (接收時依訊息標頭)
+    #[serde(default)]
+    cipher_suite: CipherSuite,
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -67,7 +75,7 @@ impl DhKeyPair {
         let keypair = X25519KeyPair::new();
         Self {
             public: keypair.public_key_bytes(),
-            private: keypair.private_key_bytes(),
+            private: keypair.private_key_bytes().expose().to_vec(),
         }
     }
 
@@ -99,6 +107,12 @@ pub struct RatchetMessage {
     ciphertext: Vec<u8>,
     /// Nonce
     nonce: Vec<u8>,
+    /// cipher suite (舊版訊息沒有此欄位，視為 AES-256-GCM)
+    #[serde(default)]
+    cipher_suite: CipherSuite,
+    /// 加密前的壓縮 (舊版訊息沒有此欄位，視為未壓縮)
+    #[serde(default)]
+    compression: Compression,
 }
 
 #[wasm_bindgen]
@@ -128,6 +142,16 @@ impl RatchetMessage {
         self.nonce.clone()
     }
 
+    #[wasm_bindgen(getter, js_name = cipherSuite)]
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
     #[wasm_bindgen(js_name = toJson)]
     pub fn to_json(&self) -> Result<String, JsError> {
         serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
@@ -225,6 +249,7 @@ impl RatchetSession {
             recv_count: 0,
             prev_send_count: 0,
             skipped_keys: SkippedKeys::default(),
+            cipher_suite: CipherSuite::default(),
         })
     }
 
@@ -294,11 +319,29 @@ impl RatchetSession {
             recv_count: 0,
             prev_send_count: 0,
             skipped_keys: SkippedKeys::default(),
+            cipher_suite: CipherSuite::default(),
         })
     }
 
     /// 加密訊息
     pub fn encrypt(&mut self, plaintext: &[u8]) -> Result<RatchetMessage, JsError> {
+        self.encrypt_with_compression(plaintext, Compression::None)
+    }
+
+    /// 壓縮後加密訊息 (`compression` 未提供時為 DEFLATE)
+    ///
+    /// 壓縮後的長度會洩漏內容，只能用於不受攻擊者影響的內容 (例如使用者自己傳送的檔案)，
+    /// 不可用於包含引用或轉貼他人內容的訊息；壓縮後沒有變短時不壓縮
+    #[wasm_bindgen(js_name = encryptCompressed)]
+    pub fn encrypt_compressed(
+        &mut self,
+        plaintext: &[u8],
+        compression: Option<Compression>,
+    ) -> Result<RatchetMessage, JsError> {
+        self.encrypt_with_compression(plaintext, compression.unwrap_or(Compression::Deflate))
+    }
+
+    fn encrypt_with_compression(&mut self, plaintext: &[u8], compression: Compression) -> Result<RatchetMessage, JsError> {
         // 如果沒有發送鏈金鑰（例如 Bob 第一次發送），需要先進行 DH ratchet
         if self.chain_key_send.is_none() {
             let dh_remote = self.dh_remote.as_ref()
@@ -329,15 +372,22 @@ impl RatchetSession {
         self.chain_key_send = Some(Self::chain_key_step(&chain_key)?);
 
         // 加密
-        let cipher = AesGcmCipher::new(&message_keys.cipher_key)?;
-        let encrypted = cipher.encrypt(plaintext)?;
+        let cipher = self.cipher_suite.cipher(&message_keys.cipher_key).map_err(|e| JsError::new(&e))?;
+        let mut nonce = [0u8; AEAD_NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let (compression, plaintext) = compression.compress_if_smaller(plaintext);
+        let ciphertext = cipher
+            .seal(&nonce, &Self::message_aad(self.cipher_suite, compression), &plaintext)
+            .map_err(|e| JsError::new(&e))?;
 
         let message = RatchetMessage {
             dh_public: self.dh_self.public.clone(),
             prev_chain_count: self.prev_send_count,
             message_number: self.send_count,
-            ciphertext: encrypted.ciphertext(),
-            nonce: encrypted.nonce(),
+            ciphertext,
+            nonce: nonce.to_vec(),
+            cipher_suite: self.cipher_suite,
+            compression,
         };
 
         self.send_count += 1;
@@ -439,12 +489,33 @@ impl RatchetSession {
     }
 
     /// 使用訊息金鑰解密
+    ///
+    /// 舊版客戶端的訊息以舊版 AAD 加密；以不同的 cipher suite 或壓縮方式加密的密文
+    /// 在兩種 AAD 下都無法通過驗證，退回舊版 AAD 不會讓竄改的標頭被接受
     fn decrypt_with_keys(keys: &MessageKeys, message: &RatchetMessage) -> Result<Vec<u8>, JsError> {
-        let cipher = AesGcmCipher::new(&keys.cipher_key)?;
-        let encrypted = EncryptedMessage::from_bytes(
-            &[message.nonce.clone(), message.ciphertext.clone()].concat()
-        )?;
-        cipher.decrypt(&encrypted)
+        let cipher = message.cipher_suite.cipher(&keys.cipher_key).map_err(|e| JsError::new(&e))?;
+        let plaintext = cipher
+            .open(&message.nonce, &Self::message_aad(message.cipher_suite, message.compression), &message.ciphertext)
+            .or_else(|_| cipher.open(&message.nonce, &Self::legacy_message_aad(message.compression), &message.ciphertext))
+            .map_err(|e| JsError::new(&e))?;
+        message
+            .compression
+            .decompress(&plaintext, MAX_DECOMPRESSED_SIZE)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 訊息的 AAD：cipher suite 與壓縮方式，竄改標頭中的任一欄位都會解密失敗
+    fn message_aad(cipher_suite: CipherSuite, compression: Compression) -> [u8; 2] {
+        [cipher_suite.into(), compression.into()]
+    }
+
+    /// 舊版訊息的 AAD：只有壓縮的訊息以壓縮位元組作為 AAD
+    fn legacy_message_aad(compression: Compression) -> Vec<u8> {
+        if compression.is_none() {
+            Vec::new()
+        } else {
+            vec![compression.into()]
+        }
     }
 
     /// KDF for root key (HKDF)
@@ -520,6 +591,32 @@ impl RatchetSession {
         bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
     }
 
+    /// 發送訊息使用的 cipher suite
+    #[wasm_bindgen(getter, js_name = cipherSuite)]
+    pub fn cipher_suite(&self) -> CipherSuite {
+        self.cipher_suite
+    }
+
+    /// 設定發送訊息使用的 cipher suite (應為與對方協商的結果)
+    #[wasm_bindgen(js_name = setCipherSuite)]
+    pub fn set_cipher_suite(&mut self, suite: CipherSuite) {
+        self.cipher_suite = suite;
+    }
+
+    /// 保留中、等待亂序訊息的跳過訊息金鑰數量
+    #[wasm_bindgen(getter, js_name = skippedKeyCount)]
+    pub fn skipped_key_count(&self) -> u32 {
+        self.skipped_keys.keys.len() as u32
+    }
+
+    /// 丟棄所有跳過的訊息金鑰，回傳丟棄的數量；之後才到達的亂序訊息將無法解密
+    #[wasm_bindgen(js_name = clearSkippedKeys)]
+    pub fn clear_skipped_keys(&mut self) -> u32 {
+        let count = self.skipped_key_count();
+        self.skipped_keys.keys.clear();
+        count
+    }
+
     /// 取得我方當前 DH 公鑰
     #[wasm_bindgen(getter, js_name = myPublicKey)]
     pub fn my_public_key(&self) -> Vec<u8> {
@@ -533,6 +630,66 @@ impl RatchetSession {
     }
 }
 
+/// 從其他實作轉換會話時使用的 ratchet 狀態 (見 `libsignal_import` 模組)
+pub(crate) struct RatchetState {
+    pub dh_public: Vec<u8>,
+    pub dh_private: Vec<u8>,
+    pub dh_remote: Option<Vec<u8>>,
+    pub root_key: [u8; 32],
+    pub chain_key_send: Option<[u8; 32]>,
+    /// 發送鏈的下一個訊息編號
+    pub send_count: u32,
+    pub chain_key_recv: Option<[u8; 32]>,
+    /// 接收鏈的下一個訊息編號
+    pub recv_count: u32,
+    pub prev_send_count: u32,
+}
+
+impl RatchetSession {
+    /// 以轉換後的 ratchet 狀態建立會話 (不含跳過的訊息金鑰)
+    pub(crate) fn from_state(state: RatchetState) -> Self {
+        Self {
+            dh_self: DhKeyPair { public: state.dh_public, private: state.dh_private },
+            dh_remote: state.dh_remote,
+            root_key: state.root_key,
+            chain_key_send: state.chain_key_send,
+            chain_key_recv: state.chain_key_recv,
+            send_count: state.send_count,
+            recv_count: state.recv_count,
+            prev_send_count: state.prev_send_count,
+            skipped_keys: SkippedKeys::default(),
+            cipher_suite: CipherSuite::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+impl RatchetSession {
+    /// 測試用會話 (不經過會呼叫 console 的初始化流程)
+    pub(crate) fn for_test(root_key: [u8; 32]) -> Self {
+        Self {
+            dh_self: DhKeyPair::new(),
+            dh_remote: None,
+            root_key,
+            chain_key_send: None,
+            chain_key_recv: None,
+            send_count: 0,
+            recv_count: 0,
+            prev_send_count: 0,
+            skipped_keys: SkippedKeys::default(),
+            cipher_suite: CipherSuite::default(),
+        }
+    }
+
+    /// 測試用的一對已連線會話 (Alice 先發送)
+    pub(crate) fn test_pair(root_key: [u8; 32]) -> (Self, Self) {
+        let bob = Self::for_test(root_key);
+        let mut alice = Self::for_test(root_key);
+        alice.dh_remote = Some(bob.dh_self.public.clone());
+        (alice, bob)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -547,14 +704,14 @@ mod tests {
         let mut alice = RatchetSession::init_as_alice(
             &shared_secret,
             &bob_spk.public_key_bytes(),
-            &alice_ephemeral.private_key_bytes(),
+            alice_ephemeral.private_key_bytes().expose(),
             &alice_ephemeral.public_key_bytes(),
         ).unwrap();
 
         // Bob 初始化（需要 Alice 的臨時公鑰）
         let mut bob = RatchetSession::init_as_bob(
             &shared_secret,
-            &bob_spk.private_key_bytes(),
+            bob_spk.private_key_bytes().expose(),
             &bob_spk.public_key_bytes(),
             &alice_ephemeral.public_key_bytes(),
         ).unwrap();
@@ -579,4 +736,65 @@ mod tests {
         assert_eq!(d3, b"Message 3");
         assert_eq!(d4, b"Message 4");
     }
+
+    #[test]
+    fn test_cipher_suite_per_message() {
+        let (mut alice, mut bob) = RatchetSession::test_pair([1u8; 32]);
+        alice.set_cipher_suite(CipherSuite::ChaCha20Poly1305);
+
+        let msg1 = alice.encrypt(b"over chacha").unwrap();
+        assert_eq!(msg1.cipher_suite(), CipherSuite::ChaCha20Poly1305);
+        assert_eq!(bob.decrypt(&msg1).unwrap(), b"over chacha");
+
+        // 接收端依訊息標頭解密，自己仍以設定的 suite 發送
+        let msg2 = bob.encrypt(b"over aes").unwrap();
+        assert_eq!(msg2.cipher_suite(), CipherSuite::Aes256Gcm);
+        assert_eq!(alice.decrypt(&msg2).unwrap(), b"over aes");
+
+        // 沒有 cipher suite 欄位的舊訊息視為 AES-256-GCM
+        let msg3 = bob.encrypt(b"legacy").unwrap();
+        let mut legacy: serde_json::Value = serde_json::to_value(&msg3).unwrap();
+        legacy.as_object_mut().unwrap().remove("cipher_suite");
+        let legacy: RatchetMessage = serde_json::from_value(legacy).unwrap();
+        assert_eq!(alice.decrypt(&legacy).unwrap(), b"legacy");
+    }
+
+    #[test]
+    fn test_compressed_messages() {
+        let (mut alice, mut bob) = RatchetSession::test_pair([2u8; 32]);
+        let text = "file transfer chunk ".repeat(200);
+
+        let compressed = alice.encrypt_compressed(text.as_bytes(), None).unwrap();
+        assert_eq!(compressed.compression(), Compression::Deflate);
+        assert!(compressed.ciphertext().len() < text.len() / 4);
+        assert_eq!(bob.decrypt(&compressed).unwrap(), text.as_bytes());
+
+        // 壓縮後沒有變短時不壓縮；一般的 encrypt 從不壓縮
+        let short = alice.encrypt_compressed(b"hi", None).unwrap();
+        assert_eq!(short.compression(), Compression::None);
+        assert_eq!(bob.decrypt(&short).unwrap(), b"hi");
+        let plain = alice.encrypt(text.as_bytes()).unwrap();
+        assert_eq!(plain.compression(), Compression::None);
+        assert_eq!(bob.decrypt(&plain).unwrap(), text.as_bytes());
+    }
+
+    #[test]
+    fn test_message_aad_binds_header() {
+        let suites = [CipherSuite::Aes256Gcm, CipherSuite::ChaCha20Poly1305];
+        let compressions = [Compression::None, Compression::Deflate];
+        let aads: std::collections::BTreeSet<[u8; 2]> = suites
+            .iter()
+            .flat_map(|suite| compressions.iter().map(|compression| RatchetSession::message_aad(*suite, *compression)))
+            .collect();
+        assert_eq!(aads.len(), 4);
+
+        // 舊版客戶端以空的 AAD 加密的訊息仍可解密
+        let (mut alice, mut bob) = RatchetSession::test_pair([3u8; 32]);
+        bob.decrypt(&alice.encrypt(b"first").unwrap()).unwrap();
+        let keys = RatchetSession::kdf_ck(&alice.chain_key_send.unwrap()).unwrap();
+        let mut legacy = alice.encrypt(b"legacy").unwrap();
+        legacy.ciphertext =
+            CipherSuite::Aes256Gcm.cipher(&keys.cipher_key).unwrap().seal(&legacy.nonce, &[], b"legacy").unwrap();
+        assert_eq!(bob.decrypt(&legacy).unwrap(), b"legacy");
+    }
 }
@@ -0,0 +1,253 @@
+//! 身份金鑰撤銷模組
+//!
+//! 使用者以舊身份金鑰簽署撤銷聲明 (原因、時間、後繼金鑰)，
+//! 聯絡人驗證後交給信任儲存，該身份公鑰之後一律視為已撤銷，
+//! 不再用於建立會話或信任裝置
+//!
+//! 後繼金鑰只是提示：金鑰外洩時攻擊者同樣能簽署撤銷聲明，
+//! 新身份仍需經由安全碼等方式驗證
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::keys::IdentityKeyPair;
+use crate::storage::{IdentityStatus, IdentityTrustStore};
+
+const REVOCATION_CONTEXT: &[u8] = b"SafeTalk_IdentityRevocation_v1";
+
+/// 撤銷原因
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RevocationReason {
+    /// 未指定
+    Unspecified = 0,
+    /// 私鑰外洩
+    KeyCompromised = 1,
+    /// 由新金鑰取代
+    Superseded = 2,
+    /// 不再使用
+    Retired = 3,
+}
+
+/// 身份撤銷憑證
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RevocationCertificate {
+    /// 被撤銷的身份公鑰
+    identity_key: Vec<u8>,
+    /// 撤銷原因
+    reason: RevocationReason,
+    /// 撤銷時間 (Unix 毫秒)
+    revoked_at: u64,
+    /// 後繼身份公鑰
+    successor_key: Option<Vec<u8>>,
+    /// 被撤銷的身份金鑰對以上欄位的簽章
+    signature: Vec<u8>,
+}
+
+impl RevocationCertificate {
+    fn signing_payload(
+        identity_key: &[u8],
+        reason: RevocationReason,
+        revoked_at: u64,
+        successor_key: Option<&[u8]>,
+    ) -> Vec<u8> {
+        let successor = successor_key.unwrap_or_default();
+        let mut payload = REVOCATION_CONTEXT.to_vec();
+        payload.extend_from_slice(&(identity_key.len() as u32).to_be_bytes());
+        payload.extend_from_slice(identity_key);
+        payload.push(reason as u8);
+        payload.extend_from_slice(&revoked_at.to_be_bytes());
+        payload.extend_from_slice(&(successor.len() as u32).to_be_bytes());
+        payload.extend_from_slice(successor);
+        payload
+    }
+
+    /// 以要撤銷的身份金鑰簽署撤銷聲明
+    pub fn create(
+        identity: &IdentityKeyPair,
+        reason: RevocationReason,
+        revoked_at: u64,
+        successor_key: Option<&[u8]>,
+    ) -> Self {
+        let identity_key = identity.public_key_bytes();
+        let signature = identity.sign(&Self::signing_payload(&identity_key, reason, revoked_at, successor_key));
+        Self {
+            identity_key,
+            reason,
+            revoked_at,
+            successor_key: successor_key.map(<[u8]>::to_vec),
+            signature,
+        }
+    }
+
+    /// 驗證簽章
+    pub fn verify_signature(&self) -> Result<(), String> {
+        if self.successor_key.as_deref() == Some(self.identity_key.as_slice()) {
+            return Err("Successor key must differ from the revoked key".to_string());
+        }
+        let payload = Self::signing_payload(
+            &self.identity_key,
+            self.reason,
+            self.revoked_at,
+            self.successor_key.as_deref(),
+        );
+        if !IdentityKeyPair::verify_signature(&self.identity_key, &payload, &self.signature) {
+            return Err("Invalid revocation signature".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl RevocationCertificate {
+    /// 以要撤銷的身份金鑰簽署撤銷聲明
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        identity: &IdentityKeyPair,
+        reason: RevocationReason,
+        revoked_at: u64,
+        successor_key: Option<Vec<u8>>,
+    ) -> RevocationCertificate {
+        Self::create(identity, reason, revoked_at, successor_key.as_deref())
+    }
+
+    /// 簽章是否有效
+    #[wasm_bindgen(js_name = verify)]
+    pub fn verify(&self) -> bool {
+        self.verify_signature().is_ok()
+    }
+
+    /// 被撤銷的身份公鑰
+    #[wasm_bindgen(getter, js_name = identityKey)]
+    pub fn identity_key(&self) -> Vec<u8> {
+        self.identity_key.clone()
+    }
+
+    /// 撤銷原因
+    #[wasm_bindgen(getter)]
+    pub fn reason(&self) -> RevocationReason {
+        self.reason
+    }
+
+    /// 撤銷時間
+    #[wasm_bindgen(getter, js_name = revokedAt)]
+    pub fn revoked_at(&self) -> u64 {
+        self.revoked_at
+    }
+
+    /// 後繼身份公鑰
+    #[wasm_bindgen(getter, js_name = successorKey)]
+    pub fn successor_key(&self) -> Option<Vec<u8>> {
+        self.successor_key.clone()
+    }
+
+    /// 序列化為 JSON
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 從 JSON 反序列化
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<RevocationCertificate, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+impl IdentityTrustStore {
+    /// 驗證並記錄撤銷憑證
+    ///
+    /// 回傳記錄前該身份公鑰對此聯絡人的狀態
+    pub fn apply_revocation(
+        &mut self,
+        contact_id: &str,
+        certificate: &RevocationCertificate,
+    ) -> Result<IdentityStatus, String> {
+        certificate.verify_signature()?;
+        let status = self.check_identity(contact_id, &certificate.identity_key);
+        self.record_revocation(certificate.clone());
+        Ok(status)
+    }
+}
+
+#[wasm_bindgen]
+impl IdentityTrustStore {
+    /// 驗證並記錄撤銷憑證
+    #[wasm_bindgen(js_name = applyRevocation)]
+    pub fn apply_revocation_certificate(
+        &mut self,
+        contact_id: &str,
+        certificate: &RevocationCertificate,
+    ) -> Result<IdentityStatus, JsError> {
+        self.apply_revocation(contact_id, certificate).map_err(|e| JsError::new(&e))
+    }
+
+    /// 身份公鑰是否已被撤銷
+    #[wasm_bindgen(js_name = isRevoked)]
+    pub fn is_revoked(&self, identity_key: &[u8]) -> bool {
+        self.revocation(identity_key).is_some()
+    }
+
+    /// 取得撤銷憑證
+    #[wasm_bindgen(js_name = getRevocation)]
+    pub fn get_revocation(&self, identity_key: &[u8]) -> Option<RevocationCertificate> {
+        self.revocation(identity_key).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_revocation_certificate() {
+        let old = IdentityKeyPair::new();
+        let new = IdentityKeyPair::new();
+        let cert = RevocationCertificate::create(
+            &old,
+            RevocationReason::Superseded,
+            1000,
+            Some(&new.public_key_bytes()),
+        );
+        assert!(cert.verify());
+
+        let json = cert.to_json().unwrap();
+        assert_eq!(RevocationCertificate::from_json(&json).unwrap(), cert);
+
+        // 竄改後繼金鑰或原因
+        let mut forged = cert.clone();
+        forged.successor_key = Some(IdentityKeyPair::new().public_key_bytes());
+        assert!(!forged.verify());
+        let mut forged = cert;
+        forged.reason = RevocationReason::Retired;
+        assert!(!forged.verify());
+    }
+
+    #[test]
+    fn test_trust_store_consumes_revocation() {
+        let old = IdentityKeyPair::new();
+        let new = IdentityKeyPair::new();
+        let mut store = IdentityTrustStore::new();
+        store.save_identity("bob", &old.public_key_bytes(), 1);
+
+        let cert = RevocationCertificate::create(&old, RevocationReason::KeyCompromised, 2, None);
+        assert_eq!(store.apply_revocation("bob", &cert), Ok(IdentityStatus::Trusted));
+
+        assert!(store.is_revoked(&old.public_key_bytes()));
+        assert_eq!(store.check_identity("bob", &old.public_key_bytes()), IdentityStatus::Revoked);
+        assert!(!store.is_trusted("bob", &old.public_key_bytes()));
+        // 其他聯絡人使用同一把金鑰也視為撤銷
+        assert_eq!(store.check_identity("carol", &old.public_key_bytes()), IdentityStatus::Revoked);
+        assert_eq!(store.save_identity("bob", &old.public_key_bytes(), 3), IdentityStatus::Revoked);
+
+        // 新身份仍需走一般的變更流程
+        assert_eq!(store.check_identity("bob", &new.public_key_bytes()), IdentityStatus::Changed);
+
+        // 無效簽章不會被記錄
+        let mut forged = RevocationCertificate::create(&new, RevocationReason::Retired, 4, None);
+        forged.revoked_at = 5;
+        assert!(store.apply_revocation("bob", &forged).is_err());
+        assert!(!store.is_revoked(&new.public_key_bytes()));
+    }
+}
@@ -0,0 +1,344 @@
+//! Ristretto255 模組
+//!
+//! 提供質數階群 Ristretto255 的金鑰對與純量/點運算，
+//! 供群組憑證、匿名代幣等零知識功能使用，不必在 JS 端實作曲線運算
+//!
+//! 與 X25519 不同，Ristretto255 沒有 cofactor，點編碼唯一，
+//! 適合直接用在證明系統中
+
+use wasm_bindgen::prelude::*;
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_POINT,
+    ristretto::{CompressedRistretto, RistrettoPoint as DalekPoint},
+    scalar::Scalar,
+    traits::Identity,
+};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha512};
+
+use super::secret::SecretBytes;
+
+/// 解析 32 bytes 標準純量編碼
+pub fn parse_scalar(bytes: &[u8]) -> Result<Scalar, String> {
+    let bytes: [u8; 32] = bytes
+        .try_into()
+        .map_err(|_| "Scalar must be 32 bytes".to_string())?;
+    Option::from(Scalar::from_canonical_bytes(bytes))
+        .ok_or_else(|| "Non-canonical scalar encoding".to_string())
+}
+
+/// 解析 32 bytes 壓縮點編碼
+pub fn parse_point(bytes: &[u8]) -> Result<DalekPoint, String> {
+    CompressedRistretto::from_slice(bytes)
+        .map_err(|_| "Point must be 32 bytes".to_string())?
+        .decompress()
+        .ok_or_else(|| "Invalid Ristretto255 point".to_string())
+}
+
+fn random_scalar() -> Scalar {
+    let mut wide = [0u8; 64];
+    OsRng.fill_bytes(&mut wide);
+    Scalar::from_bytes_mod_order_wide(&wide)
+}
+
+/// Ristretto255 純量 (mod ℓ)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RistrettoScalar {
+    inner: Scalar,
+}
+
+impl RistrettoScalar {
+    /// 內部純量
+    pub fn scalar(&self) -> Scalar {
+        self.inner
+    }
+}
+
+impl From<Scalar> for RistrettoScalar {
+    fn from(inner: Scalar) -> Self {
+        Self { inner }
+    }
+}
+
+#[wasm_bindgen]
+impl RistrettoScalar {
+    /// 隨機純量
+    #[wasm_bindgen(js_name = random)]
+    pub fn random() -> RistrettoScalar {
+        random_scalar().into()
+    }
+
+    /// 從 32 bytes 標準編碼還原
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<RistrettoScalar, JsError> {
+        parse_scalar(bytes).map(Into::into).map_err(|e| JsError::new(&e))
+    }
+
+    /// SHA-512(data) mod ℓ
+    #[wasm_bindgen(js_name = fromHash)]
+    pub fn from_hash(data: &[u8]) -> RistrettoScalar {
+        Scalar::from_bytes_mod_order_wide(&Sha512::digest(data).into()).into()
+    }
+
+    /// 從整數建立
+    #[wasm_bindgen(js_name = fromU64)]
+    pub fn from_u64(value: u64) -> RistrettoScalar {
+        Scalar::from(value).into()
+    }
+
+    /// 32 bytes 編碼
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.to_bytes().to_vec()
+    }
+
+    /// 相加
+    pub fn add(&self, other: &RistrettoScalar) -> RistrettoScalar {
+        (self.inner + other.inner).into()
+    }
+
+    /// 相減
+    pub fn sub(&self, other: &RistrettoScalar) -> RistrettoScalar {
+        (self.inner - other.inner).into()
+    }
+
+    /// 相乘
+    pub fn mul(&self, other: &RistrettoScalar) -> RistrettoScalar {
+        (self.inner * other.inner).into()
+    }
+
+    /// 取負值
+    pub fn negate(&self) -> RistrettoScalar {
+        (-self.inner).into()
+    }
+
+    /// 乘法反元素 (零沒有反元素)
+    pub fn invert(&self) -> Result<RistrettoScalar, JsError> {
+        if self.inner == Scalar::ZERO {
+            return Err(JsError::new("Cannot invert zero scalar"));
+        }
+        Ok(self.inner.invert().into())
+    }
+
+    /// 是否為零
+    #[wasm_bindgen(js_name = isZero)]
+    pub fn is_zero(&self) -> bool {
+        self.inner == Scalar::ZERO
+    }
+
+    /// 是否相等
+    pub fn equals(&self, other: &RistrettoScalar) -> bool {
+        self == other
+    }
+}
+
+/// Ristretto255 群元素
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RistrettoPoint {
+    inner: DalekPoint,
+}
+
+impl RistrettoPoint {
+    /// 內部點
+    pub fn point(&self) -> DalekPoint {
+        self.inner
+    }
+}
+
+impl From<DalekPoint> for RistrettoPoint {
+    fn from(inner: DalekPoint) -> Self {
+        Self { inner }
+    }
+}
+
+#[wasm_bindgen]
+impl RistrettoPoint {
+    /// 基點 B
+    #[wasm_bindgen(js_name = basepoint)]
+    pub fn basepoint() -> RistrettoPoint {
+        RISTRETTO_BASEPOINT_POINT.into()
+    }
+
+    /// 單位元素
+    #[wasm_bindgen(js_name = identity)]
+    pub fn identity() -> RistrettoPoint {
+        DalekPoint::identity().into()
+    }
+
+    /// scalar · B
+    #[wasm_bindgen(js_name = mulBase)]
+    pub fn mul_base(scalar: &RistrettoScalar) -> RistrettoPoint {
+        DalekPoint::mul_base(&scalar.inner).into()
+    }
+
+    /// 從 32 bytes 壓縮編碼還原
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<RistrettoPoint, JsError> {
+        parse_point(bytes).map(Into::into).map_err(|e| JsError::new(&e))
+    }
+
+    /// 雜湊到群元素 (SHA-512 + Elligator)，離散對數未知
+    #[wasm_bindgen(js_name = fromHash)]
+    pub fn from_hash(data: &[u8]) -> RistrettoPoint {
+        DalekPoint::from_uniform_bytes(&Sha512::digest(data).into()).into()
+    }
+
+    /// 32 bytes 壓縮編碼
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.inner.compress().to_bytes().to_vec()
+    }
+
+    /// 相加
+    pub fn add(&self, other: &RistrettoPoint) -> RistrettoPoint {
+        (self.inner + other.inner).into()
+    }
+
+    /// 相減
+    pub fn sub(&self, other: &RistrettoPoint) -> RistrettoPoint {
+        (self.inner - other.inner).into()
+    }
+
+    /// 純量乘法
+    pub fn mul(&self, scalar: &RistrettoScalar) -> RistrettoPoint {
+        (self.inner * scalar.inner).into()
+    }
+
+    /// 取負值
+    pub fn negate(&self) -> RistrettoPoint {
+        (-self.inner).into()
+    }
+
+    /// 是否為單位元素
+    #[wasm_bindgen(js_name = isIdentity)]
+    pub fn is_identity(&self) -> bool {
+        self.inner == DalekPoint::identity()
+    }
+
+    /// 是否相等
+    pub fn equals(&self, other: &RistrettoPoint) -> bool {
+        self == other
+    }
+}
+
+/// Ristretto255 金鑰對
+#[wasm_bindgen]
+pub struct RistrettoKeyPair {
+    secret: Scalar,
+    public: DalekPoint,
+}
+
+impl RistrettoKeyPair {
+    /// 執行 Diffie-Hellman (Rust 端使用)，拒絕單位元素
+    pub fn shared_secret(&self, their_public: &[u8]) -> Result<[u8; 32], String> {
+        let shared = parse_point(their_public)? * self.secret;
+        if shared == DalekPoint::identity() {
+            return Err("Shared secret is the identity element".to_string());
+        }
+        Ok(shared.compress().to_bytes())
+    }
+}
+
+#[wasm_bindgen]
+impl RistrettoKeyPair {
+    /// 生成新的 Ristretto255 金鑰對
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        let secret = random_scalar();
+        Self { secret, public: DalekPoint::mul_base(&secret) }
+    }
+
+    /// 從私鑰純量還原
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<RistrettoKeyPair, JsError> {
+        let secret = parse_scalar(bytes).map_err(|e| JsError::new(&e))?;
+        if secret == Scalar::ZERO {
+            return Err(JsError::new("Private key must not be zero"));
+        }
+        Ok(Self { secret, public: DalekPoint::mul_base(&secret) })
+    }
+
+    /// 取得公鑰位元組
+    #[wasm_bindgen(js_name = publicKeyBytes)]
+    pub fn public_key_bytes(&self) -> Vec<u8> {
+        self.public.compress().to_bytes().to_vec()
+    }
+
+    /// 取得私鑰位元組 (敏感！)
+    ///
+    /// 以 `SecretBytes` 回傳，需明確呼叫 `expose()` 才能取得內容
+    #[wasm_bindgen(js_name = privateKeyBytes)]
+    pub fn private_key_bytes(&self) -> SecretBytes {
+        self.secret.to_bytes().into()
+    }
+
+    /// 公鑰點
+    #[wasm_bindgen(js_name = publicPoint)]
+    pub fn public_point(&self) -> RistrettoPoint {
+        self.public.into()
+    }
+
+    /// 私鑰純量 (敏感！)
+    #[wasm_bindgen(js_name = secretScalar)]
+    pub fn secret_scalar(&self) -> RistrettoScalar {
+        self.secret.into()
+    }
+
+    /// 執行 Diffie-Hellman 金鑰交換
+    #[wasm_bindgen(js_name = diffieHellman)]
+    pub fn diffie_hellman(&self, their_public: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.shared_secret(their_public)
+            .map(|shared| shared.to_vec())
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+impl Default for RistrettoKeyPair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+
+    #[test]
+    fn test_ristretto_basepoint_multiples() {
+        // RFC 9496 附錄 A.1
+        let two_b = hex("6a493210f7499cd17fecb510ae0cea23a110e8d5b901f8acadd3095c73a3b919");
+        let three_b = hex("94741f5d5d52755ece4f23f044ee27d5d1ea1e2bd196b462166b16152a9d0259");
+
+        let b = RistrettoPoint::basepoint();
+        assert_eq!(b.add(&b).to_bytes(), two_b);
+        assert_eq!(RistrettoPoint::mul_base(&RistrettoScalar::from_u64(3)).to_bytes(), three_b);
+        assert!(RistrettoPoint::mul_base(&RistrettoScalar::from_u64(0)).is_identity());
+
+        // 非標準編碼被拒絕
+        assert!(parse_point(&[0xffu8; 32]).is_err());
+        assert!(parse_scalar(&[0xffu8; 32]).is_err());
+    }
+
+    #[test]
+    fn test_ristretto_scalar_and_dh() {
+        let a = RistrettoScalar::random();
+        let b = RistrettoScalar::from_hash(b"token");
+        let p = RistrettoPoint::from_hash(b"generator");
+
+        // (a + b)P = aP + bP
+        assert_eq!(p.mul(&a.add(&b)), p.mul(&a).add(&p.mul(&b)));
+        // a · a⁻¹ = 1
+        assert_eq!(a.mul(&a.invert().unwrap()), RistrettoScalar::from_u64(1));
+
+        let alice = RistrettoKeyPair::new();
+        let bob = RistrettoKeyPair::new();
+        assert_eq!(
+            alice.shared_secret(&bob.public_key_bytes()).unwrap(),
+            bob.shared_secret(&alice.public_key_bytes()).unwrap()
+        );
+        assert!(alice.shared_secret(&RistrettoPoint::identity().to_bytes()).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+//! 短驗證字串 (SAS) 模組
+//!
+//! 雙方透過新的臨時 X25519 金鑰交換，衍生出可以口頭比對的 emoji 或數字，
+//! 流程與 Matrix/Element 的互動式驗證相同：
+//!
+//! 1. 發起者送出臨時公鑰的承諾值 (commitment)
+//! 2. 回應者送出臨時公鑰
+//! 3. 發起者公開臨時公鑰，回應者檢查是否與承諾值相符
+//! 4. 雙方計算 SAS 並比對
+//!
+//! 發起者在看到對方公鑰前就已承諾，回應者在看到發起者公鑰前就已公開，
+//! 因此任何一方都無法反覆嘗試金鑰來操控結果
+
+use wasm_bindgen::prelude::*;
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+
+use super::constant_time::constant_time_eq;
+use super::keys::X25519KeyPair;
+
+const INFO_SAS: &[u8] = b"SafeTalk_SAS";
+const COMMITMENT_CONTEXT: &[u8] = b"SafeTalk_SAS_Commitment";
+
+/// emoji 數量 (每個 6 bits)
+pub const SAS_EMOJI_COUNT: usize = 7;
+
+/// 數字組數 (每組 13 bits，範圍 1000-9191)
+pub const SAS_DECIMAL_COUNT: usize = 3;
+
+/// SAS 驗證角色
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SasRole {
+    Initiator = 0,
+    Responder = 1,
+}
+
+/// 互動式 SAS 驗證流程
+#[wasm_bindgen]
+pub struct SasVerification {
+    role: SasRole,
+    transaction_id: String,
+    our_identity_key: Vec<u8>,
+    their_identity_key: Vec<u8>,
+    ephemeral: X25519KeyPair,
+    /// 回應者收到的發起者承諾值
+    their_commitment: Option<[u8; 32]>,
+    /// 對方的臨時公鑰
+    their_ephemeral: Option<Vec<u8>>,
+}
+
+/// 承諾值 = SHA-256(context || transaction_id || identity_key || ephemeral_public)
+fn commitment(transaction_id: &str, identity_key: &[u8], ephemeral_public: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(COMMITMENT_CONTEXT);
+    for field in [transaction_id.as_bytes(), identity_key, ephemeral_public] {
+        hasher.update((field.len() as u32).to_be_bytes());
+        hasher.update(field);
+    }
+    hasher.finalize().into()
+}
+
+impl SasVerification {
+    fn create(
+        role: SasRole,
+        transaction_id: &str,
+        our_identity_key: &[u8],
+        their_identity_key: &[u8],
+        their_commitment: Option<[u8; 32]>,
+    ) -> Self {
+        Self {
+            role,
+            transaction_id: transaction_id.to_string(),
+            our_identity_key: our_identity_key.to_vec(),
+            their_identity_key: their_identity_key.to_vec(),
+            ephemeral: X25519KeyPair::new(),
+            their_commitment,
+            their_ephemeral: None,
+        }
+    }
+
+    /// 回應者：以發起者的承諾值建立驗證流程
+    pub fn new_responder(
+        transaction_id: &str,
+        our_identity_key: &[u8],
+        their_identity_key: &[u8],
+        their_commitment: &[u8],
+    ) -> Result<Self, String> {
+        if their_commitment.len() != 32 {
+            return Err("Commitment must be 32 bytes".to_string());
+        }
+        let mut commitment = [0u8; 32];
+        commitment.copy_from_slice(their_commitment);
+        Ok(Self::create(
+            SasRole::Responder,
+            transaction_id,
+            our_identity_key,
+            their_identity_key,
+            Some(commitment),
+        ))
+    }
+
+    /// 收到對方的臨時公鑰
+    ///
+    /// 回應者會檢查公鑰是否與承諾值相符
+    pub fn receive_their_key(&mut self, their_ephemeral: &[u8]) -> Result<(), String> {
+        if self.their_ephemeral.is_some() {
+            return Err("Ephemeral key already received".to_string());
+        }
+        if their_ephemeral.len() != 32 {
+            return Err("Ephemeral key must be 32 bytes".to_string());
+        }
+        if let Some(expected) = self.their_commitment {
+            let actual = commitment(&self.transaction_id, &self.their_identity_key, their_ephemeral);
+            if !constant_time_eq(&actual, &expected) {
+                return Err("Ephemeral key does not match commitment".to_string());
+            }
+        }
+        self.their_ephemeral = Some(their_ephemeral.to_vec());
+        Ok(())
+    }
+
+    /// 發起者：取得要公開的臨時公鑰
+    ///
+    /// 必須在收到回應者公鑰之後才能公開
+    pub fn reveal_key(&self) -> Result<Vec<u8>, String> {
+        if self.role == SasRole::Initiator && self.their_ephemeral.is_none() {
+            return Err("Initiator must receive responder key before revealing".to_string());
+        }
+        Ok(self.ephemeral.public_key_bytes())
+    }
+
+    /// 衍生 SAS 原始位元組 (6 bytes)
+    ///
+    /// HKDF 的 info 綁定雙方身份公鑰、臨時公鑰與交易 ID
+    pub fn sas_bytes(&self) -> Result<[u8; 6], String> {
+        let their_ephemeral = self
+            .their_ephemeral
+            .as_ref()
+            .ok_or_else(|| "Ephemeral key not yet received".to_string())?;
+        let our_ephemeral = self.ephemeral.public_key_bytes();
+
+        let shared = self.ephemeral.shared_secret(their_ephemeral)?;
+
+        // 依角色排列，雙方得到相同的 info
+        let (initiator, responder) = match self.role {
+            SasRole::Initiator => (
+                (&self.our_identity_key, &our_ephemeral),
+                (&self.their_identity_key, their_ephemeral),
+            ),
+            SasRole::Responder => (
+                (&self.their_identity_key, their_ephemeral),
+                (&self.our_identity_key, &our_ephemeral),
+            ),
+        };
+
+        let mut info = INFO_SAS.to_vec();
+        for field in [
+            self.transaction_id.as_bytes(),
+            initiator.0,
+            initiator.1,
+            responder.0,
+            responder.1,
+        ] {
+            info.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            info.extend_from_slice(field);
+        }
+
+        let hkdf = Hkdf::<Sha256>::new(None, &shared);
+        let mut output = [0u8; 6];
+        hkdf.expand(&info, &mut output)
+            .map_err(|e| format!("HKDF failed: {}", e))?;
+        Ok(output)
+    }
+
+    /// 7 個 emoji 索引 (0-63，對應 Matrix SAS emoji 表)
+    pub fn emoji_indices(&self) -> Result<Vec<u8>, String> {
+        let bytes = self.sas_bytes()?;
+        let bits = bytes.iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+        // 使用前 42 bits
+        Ok((0..SAS_EMOJI_COUNT)
+            .map(|i| ((bits >> (48 - 6 * (i + 1))) & 0x3f) as u8)
+            .collect())
+    }
+
+    /// 3 組 4 位數字 (1000-9191)
+    pub fn decimals(&self) -> Result<Vec<u16>, String> {
+        let bytes = self.sas_bytes()?;
+        let bits = bytes[..5].iter().fold(0u64, |acc, b| (acc << 8) | *b as u64);
+        // 使用前 39 bits
+        Ok((0..SAS_DECIMAL_COUNT)
+            .map(|i| (((bits >> (40 - 13 * (i + 1))) & 0x1fff) + 1000) as u16)
+            .collect())
+    }
+}
+
+#[wasm_bindgen]
+impl SasVerification {
+    /// 發起者：建立驗證流程
+    #[wasm_bindgen(js_name = initiate)]
+    pub fn initiate(
+        transaction_id: &str,
+        our_identity_key: &[u8],
+        their_identity_key: &[u8],
+    ) -> SasVerification {
+        Self::create(SasRole::Initiator, transaction_id, our_identity_key, their_identity_key, None)
+    }
+
+    /// 回應者：以發起者的承諾值建立驗證流程
+    #[wasm_bindgen(js_name = respond)]
+    pub fn respond(
+        transaction_id: &str,
+        our_identity_key: &[u8],
+        their_identity_key: &[u8],
+        their_commitment: &[u8],
+    ) -> Result<SasVerification, JsError> {
+        Self::new_responder(transaction_id, our_identity_key, their_identity_key, their_commitment)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 角色
+    #[wasm_bindgen(getter)]
+    pub fn role(&self) -> SasRole {
+        self.role
+    }
+
+    /// 發起者的承諾值 (發送給回應者)
+    #[wasm_bindgen(getter)]
+    pub fn commitment(&self) -> Vec<u8> {
+        commitment(
+            &self.transaction_id,
+            &self.our_identity_key,
+            &self.ephemeral.public_key_bytes(),
+        )
+        .to_vec()
+    }
+
+    /// 取得要發送給對方的臨時公鑰
+    #[wasm_bindgen(js_name = ephemeralPublicKey)]
+    pub fn ephemeral_public_key(&self) -> Result<Vec<u8>, JsError> {
+        self.reveal_key().map_err(|e| JsError::new(&e))
+    }
+
+    /// 收到對方的臨時公鑰
+    #[wasm_bindgen(js_name = receiveKey)]
+    pub fn receive_key(&mut self, their_ephemeral: &[u8]) -> Result<(), JsError> {
+        self.receive_their_key(their_ephemeral).map_err(|e| JsError::new(&e))
+    }
+
+    /// 取得 emoji 索引
+    #[wasm_bindgen(js_name = emoji)]
+    pub fn emoji(&self) -> Result<Vec<u8>, JsError> {
+        self.emoji_indices().map_err(|e| JsError::new(&e))
+    }
+
+    /// 取得數字
+    #[wasm_bindgen(js_name = decimal)]
+    pub fn decimal(&self) -> Result<Vec<u16>, JsError> {
+        self.decimals().map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sas_flow() {
+        let alice_id = [1u8; 32];
+        let bob_id = [2u8; 32];
+
+        let mut alice = SasVerification::initiate("tx1", &alice_id, &bob_id);
+        // 發起者不能在收到對方公鑰前公開
+        assert!(alice.reveal_key().is_err());
+
+        let mut bob = SasVerification::new_responder("tx1", &bob_id, &alice_id, &alice.commitment()).unwrap();
+        alice.receive_their_key(&bob.reveal_key().unwrap()).unwrap();
+        bob.receive_their_key(&alice.reveal_key().unwrap()).unwrap();
+
+        assert_eq!(alice.emoji_indices().unwrap(), bob.emoji_indices().unwrap());
+        assert_eq!(alice.decimals().unwrap(), bob.decimals().unwrap());
+        assert_eq!(alice.emoji_indices().unwrap().len(), SAS_EMOJI_COUNT);
+        assert!(alice.emoji_indices().unwrap().iter().all(|i| *i < 64));
+        assert!(alice.decimals().unwrap().iter().all(|d| (1000..=9191).contains(d)));
+    }
+
+    #[test]
+    fn test_sas_rejects_key_not_matching_commitment() {
+        let alice_id = [1u8; 32];
+        let bob_id = [2u8; 32];
+
+        let alice = SasVerification::initiate("tx1", &alice_id, &bob_id);
+        let mut bob = SasVerification::new_responder("tx1", &bob_id, &alice_id, &alice.commitment()).unwrap();
+
+        // 中間人替換發起者的公鑰
+        let mallory = X25519KeyPair::new();
+        assert!(bob.receive_their_key(&mallory.public_key_bytes()).is_err());
+        assert!(bob.sas_bytes().is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! 秘密位元組模組
+//!
+//! 私鑰與衍生出的秘密值以 `SecretBytes` 回傳，而不是直接交出 `Vec<u8>`：
+//! - 沒有 Display / Serialize，Debug 只顯示長度，不會意外寫進 log 或 JSON
+//! - 釋放 (Rust 端 drop 或 JS 端 `free()`) 時清零記憶體
+//! - 必須明確呼叫 `expose()` 才能取得內容
+
+use std::fmt;
+
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+use super::constant_time::constant_time_eq;
+
+/// 釋放時清零的秘密位元組
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct SecretBytes {
+    bytes: Zeroizing<Vec<u8>>,
+}
+
+impl SecretBytes {
+    /// 包裝秘密值 (Rust 端使用)
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes: Zeroizing::new(bytes) }
+    }
+
+    /// 取得內容 (Rust 端使用)
+    pub fn expose(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl From<[u8; 32]> for SecretBytes {
+    fn from(bytes: [u8; 32]) -> Self {
+        // 暫存的陣列同樣清零
+        let bytes = Zeroizing::new(bytes);
+        Self::new(bytes.to_vec())
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretBytes([REDACTED; {}])", self.bytes.len())
+    }
+}
+
+impl PartialEq for SecretBytes {
+    /// 常數時間比對
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq(&self.bytes, &other.bytes)
+    }
+}
+
+impl Eq for SecretBytes {}
+
+#[wasm_bindgen]
+impl SecretBytes {
+    /// 取得內容的複本 (JS 端取得後應盡快使用並丟棄)
+    #[wasm_bindgen(js_name = expose)]
+    pub fn expose_copy(&self) -> Vec<u8> {
+        self.bytes.to_vec()
+    }
+
+    /// 長度 (bytes)
+    #[wasm_bindgen(getter)]
+    pub fn length(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secret_bytes_is_redacted() {
+        let secret = SecretBytes::from([0x42u8; 32]);
+        assert_eq!(format!("{:?}", secret), "SecretBytes([REDACTED; 32])");
+        assert_eq!(secret.expose(), &[0x42u8; 32]);
+        assert_eq!(secret.expose_copy(), vec![0x42u8; 32]);
+        assert_eq!(secret.length(), 32);
+
+        assert_eq!(secret, SecretBytes::new(vec![0x42u8; 32]));
+        assert_ne!(secret, SecretBytes::new(vec![0x42u8; 31]));
+    }
+}
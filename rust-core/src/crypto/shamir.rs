@@ -0,0 +1,322 @@
+//! Shamir 秘密分享模組
+//!
+//! 把身份私鑰或備份主金鑰拆成 n 份，任意 k 份即可還原 (例如 3-of-5)，
+//! 讓使用者把復原分享交給信任的聯絡人，而不是只依賴一組密碼
+//!
+//! 運算在 GF(256) (AES 多項式 x^8 + x^4 + x^3 + x + 1) 上逐 byte 進行
+//!
+//! 分享格式：
+//!
+//! ```text
+//! version (1) || set id (4) || threshold (1) || index (1) || data (n)
+//! || checksum (4) = SHA-256(前面所有欄位) 前 4 bytes
+//! ```
+//!
+//! set id 在同一次拆分中相同，可偵測混用不同批次的分享
+
+use wasm_bindgen::prelude::*;
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha256};
+
+use super::constant_time::constant_time_eq;
+use super::keys::IdentityKeyPair;
+
+/// 目前的分享格式版本
+pub const SHARE_VERSION: u8 = 1;
+
+const SET_ID_SIZE: usize = 4;
+const CHECKSUM_SIZE: usize = 4;
+const HEADER_SIZE: usize = 1 + SET_ID_SIZE + 1 + 1;
+
+/// GF(256) 乘法 (固定 8 次迴圈，不依資料分支)
+fn gf_mul(mut a: u8, mut b: u8) -> u8 {
+    let mut product = 0u8;
+    for _ in 0..8 {
+        product ^= a & (b & 1).wrapping_neg();
+        let carry = (a >> 7).wrapping_neg();
+        a = (a << 1) ^ (0x1b & carry);
+        b >>= 1;
+    }
+    product
+}
+
+/// GF(256) 乘法反元素 (a^254)
+fn gf_inv(a: u8) -> u8 {
+    let mut result = 1u8;
+    let mut base = a;
+    let mut exp = 254u8;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+        base = gf_mul(base, base);
+        exp >>= 1;
+    }
+    result
+}
+
+fn checksum(data: &[u8]) -> [u8; CHECKSUM_SIZE] {
+    let digest = Sha256::digest(data);
+    let mut out = [0u8; CHECKSUM_SIZE];
+    out.copy_from_slice(&digest[..CHECKSUM_SIZE]);
+    out
+}
+
+/// 單一秘密分享
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SecretShare {
+    set_id: [u8; SET_ID_SIZE],
+    threshold: u8,
+    index: u8,
+    data: Vec<u8>,
+}
+
+impl SecretShare {
+    /// 分享內容 (與秘密等長)
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 批次 ID
+    pub fn set_id(&self) -> [u8; SET_ID_SIZE] {
+        self.set_id
+    }
+}
+
+#[wasm_bindgen]
+impl SecretShare {
+    /// 分享編號 (1-255)
+    #[wasm_bindgen(getter)]
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+
+    /// 還原所需的分享數量
+    #[wasm_bindgen(getter)]
+    pub fn threshold(&self) -> u8 {
+        self.threshold
+    }
+
+    /// 序列化 (含 checksum)
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + self.data.len() + CHECKSUM_SIZE);
+        bytes.push(SHARE_VERSION);
+        bytes.extend_from_slice(&self.set_id);
+        bytes.push(self.threshold);
+        bytes.push(self.index);
+        bytes.extend_from_slice(&self.data);
+        let sum = checksum(&bytes);
+        bytes.extend_from_slice(&sum);
+        bytes
+    }
+
+    /// 反序列化並檢查 checksum
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<SecretShare, JsError> {
+        Self::parse(bytes).map_err(|e| JsError::new(&e))
+    }
+}
+
+impl SecretShare {
+    /// 反序列化並檢查 checksum
+    pub fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() <= HEADER_SIZE + CHECKSUM_SIZE {
+            return Err("Share too short".to_string());
+        }
+        let (body, sum) = bytes.split_at(bytes.len() - CHECKSUM_SIZE);
+        if checksum(body) != sum {
+            return Err("Share checksum mismatch".to_string());
+        }
+        if body[0] != SHARE_VERSION {
+            return Err(format!("Unsupported share version: {}", body[0]));
+        }
+
+        let mut set_id = [0u8; SET_ID_SIZE];
+        set_id.copy_from_slice(&body[1..1 + SET_ID_SIZE]);
+        let threshold = body[1 + SET_ID_SIZE];
+        let index = body[2 + SET_ID_SIZE];
+        if threshold < 2 || index == 0 {
+            return Err("Invalid share header".to_string());
+        }
+
+        Ok(Self {
+            set_id,
+            threshold,
+            index,
+            data: body[HEADER_SIZE..].to_vec(),
+        })
+    }
+}
+
+/// 把秘密拆成 `count` 份，任意 `threshold` 份可還原
+pub fn shamir_split(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<SecretShare>, String> {
+    if secret.is_empty() {
+        return Err("Secret must not be empty".to_string());
+    }
+    if threshold < 2 {
+        return Err("Threshold must be at least 2".to_string());
+    }
+    if count < threshold {
+        return Err("Share count must be at least the threshold".to_string());
+    }
+
+    let mut set_id = [0u8; SET_ID_SIZE];
+    OsRng.fill_bytes(&mut set_id);
+
+    // 每個 byte 一個 threshold - 1 次多項式，常數項為秘密
+    let mut coefficients = vec![0u8; secret.len() * (threshold as usize - 1)];
+    OsRng.fill_bytes(&mut coefficients);
+
+    let shares = (1..=count)
+        .map(|x| {
+            let data = secret
+                .iter()
+                .enumerate()
+                .map(|(i, s)| {
+                    let coeffs = &coefficients[i * (threshold as usize - 1)..(i + 1) * (threshold as usize - 1)];
+                    // Horner 法，最後一項為常數項
+                    coeffs
+                        .iter()
+                        .rev()
+                        .chain(std::iter::once(s))
+                        .fold(0u8, |acc, c| gf_mul(acc, x) ^ c)
+                })
+                .collect();
+            SecretShare { set_id, threshold, index: x, data }
+        })
+        .collect();
+
+    coefficients.iter_mut().for_each(|c| *c = 0);
+    Ok(shares)
+}
+
+/// 以至少 threshold 份分享還原秘密
+pub fn shamir_combine(shares: &[SecretShare]) -> Result<Vec<u8>, String> {
+    let first = shares.first().ok_or_else(|| "No shares provided".to_string())?;
+    let threshold = first.threshold as usize;
+    if shares.len() < threshold {
+        return Err(format!("Need {} shares, got {}", threshold, shares.len()));
+    }
+    for share in shares {
+        if share.set_id != first.set_id
+            || share.threshold != first.threshold
+            || share.data.len() != first.data.len()
+        {
+            return Err("Shares belong to different splits".to_string());
+        }
+    }
+
+    // 重複提供的同一份分享只取一次，再從不重複的分享中取 threshold 份
+    let mut distinct: Vec<&SecretShare> = Vec::with_capacity(shares.len());
+    for share in shares {
+        match distinct.iter().find(|b| b.index == share.index) {
+            Some(b) if !constant_time_eq(&b.data, &share.data) => return Err("Conflicting shares for the same index".to_string()),
+            Some(_) => {}
+            None => distinct.push(share),
+        }
+    }
+    if distinct.len() < threshold {
+        return Err(format!("Need {} distinct shares, got {}", threshold, distinct.len()));
+    }
+    let shares = &distinct[..threshold];
+
+    // Lagrange 插值求 f(0)：GF(2^8) 中減法即 XOR
+    let basis: Vec<u8> = shares
+        .iter()
+        .map(|a| {
+            shares
+                .iter()
+                .filter(|b| b.index != a.index)
+                .fold(1u8, |acc, b| gf_mul(acc, gf_mul(b.index, gf_inv(b.index ^ a.index))))
+        })
+        .collect();
+
+    Ok((0..first.data.len())
+        .map(|i| {
+            shares
+                .iter()
+                .zip(&basis)
+                .fold(0u8, |acc, (share, l)| acc ^ gf_mul(share.data[i], *l))
+        })
+        .collect())
+}
+
+/// 把秘密拆成分享
+#[wasm_bindgen(js_name = splitSecret)]
+pub fn split_secret(secret: &[u8], threshold: u8, count: u8) -> Result<Vec<SecretShare>, JsError> {
+    shamir_split(secret, threshold, count).map_err(|e| JsError::new(&e))
+}
+
+/// 以分享還原秘密
+#[wasm_bindgen(js_name = combineShares)]
+pub fn combine_shares(shares: Vec<SecretShare>) -> Result<Vec<u8>, JsError> {
+    shamir_combine(&shares).map_err(|e| JsError::new(&e))
+}
+
+#[wasm_bindgen]
+impl IdentityKeyPair {
+    /// 把身份私鑰拆成復原分享
+    #[wasm_bindgen(js_name = splitRecoveryShares)]
+    pub fn split_recovery_shares(&self, threshold: u8, count: u8) -> Result<Vec<SecretShare>, JsError> {
+        split_secret(self.private_key_bytes().expose(), threshold, count)
+    }
+
+    /// 以復原分享還原身份金鑰
+    #[wasm_bindgen(js_name = fromRecoveryShares)]
+    pub fn from_recovery_shares(shares: Vec<SecretShare>) -> Result<IdentityKeyPair, JsError> {
+        let secret = combine_shares(shares)?;
+        IdentityKeyPair::from_bytes(&secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gf256_arithmetic() {
+        // FIPS-197 範例：{57} • {83} = {c1}
+        assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+        for a in 1..=255u8 {
+            assert_eq!(gf_mul(a, gf_inv(a)), 1);
+        }
+    }
+
+    #[test]
+    fn test_split_and_combine_any_three_of_five() {
+        let secret = [0x42u8; 32];
+        let shares = shamir_split(&secret, 3, 5).unwrap();
+        assert_eq!(shares.len(), 5);
+
+        for picks in [[0, 1, 2], [0, 2, 4], [4, 3, 1], [1, 2, 3]] {
+            let subset: Vec<_> = picks.iter().map(|i| shares[*i].clone()).collect();
+            assert_eq!(shamir_combine(&subset).unwrap(), secret);
+        }
+
+        // 不足門檻
+        assert!(shamir_combine(&shares[..2]).is_err());
+
+        // 重複的分享不計入門檻，但多餘的重複不影響還原
+        let duplicated = vec![shares[0].clone(), shares[0].clone(), shares[1].clone()];
+        assert!(shamir_combine(&duplicated).unwrap_err().contains("distinct"));
+        let duplicated = vec![shares[0].clone(), shares[0].clone(), shares[1].clone(), shares[3].clone()];
+        assert_eq!(shamir_combine(&duplicated).unwrap(), secret);
+        let mut conflicting = shares[0].clone();
+        conflicting.data[0] ^= 1;
+        assert!(shamir_combine(&[shares[0].clone(), conflicting, shares[1].clone(), shares[2].clone()]).is_err());
+
+        // 序列化並檢查 checksum
+        let bytes = shares[0].to_bytes();
+        assert_eq!(SecretShare::parse(&bytes).unwrap(), shares[0]);
+        let mut corrupted = bytes;
+        corrupted[HEADER_SIZE] ^= 0x01;
+        assert_eq!(SecretShare::parse(&corrupted), Err("Share checksum mismatch".to_string()));
+
+        // 不同批次的分享不能混用
+        let other = shamir_split(&secret, 3, 5).unwrap();
+        let mixed = vec![shares[0].clone(), shares[1].clone(), other[2].clone()];
+        assert!(shamir_combine(&mixed).is_err());
+    }
+}
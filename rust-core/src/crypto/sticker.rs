@@ -0,0 +1,461 @@
+//! 貼圖包加密模組 (與 Signal 相同的設計)
+//!
+//! 建立者產生隨機的 pack ID (16 bytes，公開，作為伺服器上的位置) 與 pack key (32 bytes)。
+//! manifest (標題、作者、封面與每張貼圖對應的 emoji) 與每張貼圖都以 pack key 經 HKDF
+//! 衍生的 64 bytes 金鑰，用舊式附件格式 (AES-256-CBC + HMAC-SHA256，見 `legacy_attachment`
+//! 模組) 分別加密，伺服器只看到 pack ID 與密文
+//!
+//! 分享貼圖包時傳送 `StickerPackShare` (pack ID 與 pack key)，可轉成
+//! `pack_id=<hex>&pack_key=<hex>` 形式的 URL fragment；訊息中引用單張貼圖時
+//! 傳送 `StickerReference` (另含貼圖 ID 與 emoji)
+//!
+//! 貼圖包發佈後內容不可變，要修改須以新的 pack ID 與 pack key 重新建立
+
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+use super::constant_time::constant_time_eq;
+use super::kdf::hkdf_sha256;
+use super::legacy_attachment::{legacy_attachment_decrypt, legacy_attachment_encrypt, LEGACY_ATTACHMENT_KEY_SIZE};
+
+/// pack ID 長度
+pub const STICKER_PACK_ID_SIZE: usize = 16;
+/// pack key 長度
+pub const STICKER_PACK_KEY_SIZE: usize = 32;
+/// 單一貼圖包的貼圖數量上限
+pub const MAX_STICKERS_PER_PACK: usize = 200;
+const INFO_STICKER_PACK: &[u8] = b"Sticker Pack";
+
+fn hex_encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn hex_decode(text: &str, expected: usize, name: &str) -> Result<Vec<u8>, String> {
+    let invalid = || format!("Invalid sticker {}", name);
+    if text.len() != expected * 2 || !text.is_ascii() {
+        return Err(invalid());
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+fn check_length(bytes: &[u8], expected: usize, name: &str) -> Result<(), String> {
+    if bytes.len() != expected {
+        return Err(format!("Sticker {} must be {} bytes", name, expected));
+    }
+    Ok(())
+}
+
+/// 以 pack key 衍生 manifest 與貼圖共用的加密金鑰 (AES-256 金鑰 || HMAC-SHA256 金鑰)
+fn pack_cipher_key(pack_key: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+    check_length(pack_key, STICKER_PACK_KEY_SIZE, "pack key")?;
+    Ok(Zeroizing::new(hkdf_sha256(&[], pack_key, INFO_STICKER_PACK, LEGACY_ATTACHMENT_KEY_SIZE)?))
+}
+
+fn encrypt_with_pack_key(pack_key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+    let mut iv = [0u8; 16];
+    OsRng.fill_bytes(&mut iv);
+    legacy_attachment_encrypt(&pack_cipher_key(pack_key)?, &iv, plaintext)
+}
+
+/// manifest 中的單張貼圖
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StickerInfo {
+    id: u32,
+    emoji: String,
+    content_type: String,
+}
+
+#[wasm_bindgen]
+impl StickerInfo {
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    /// 對應的 emoji (輸入該 emoji 時建議此貼圖)
+    #[wasm_bindgen(getter)]
+    pub fn emoji(&self) -> String {
+        self.emoji.clone()
+    }
+
+    /// MIME 類型 (例如 `image/webp`)
+    #[wasm_bindgen(getter, js_name = contentType)]
+    pub fn content_type(&self) -> String {
+        self.content_type.clone()
+    }
+}
+
+/// 貼圖包 manifest
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StickerManifest {
+    title: String,
+    author: String,
+    /// 封面貼圖 ID
+    cover: Option<u32>,
+    stickers: Vec<StickerInfo>,
+}
+
+#[wasm_bindgen]
+impl StickerManifest {
+    #[wasm_bindgen(getter)]
+    pub fn title(&self) -> String {
+        self.title.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn author(&self) -> String {
+        self.author.clone()
+    }
+
+    /// 封面貼圖 ID
+    #[wasm_bindgen(getter)]
+    pub fn cover(&self) -> Option<u32> {
+        self.cover
+    }
+
+    /// 所有貼圖 (依 ID 排序)
+    #[wasm_bindgen(getter)]
+    pub fn stickers(&self) -> Vec<StickerInfo> {
+        self.stickers.clone()
+    }
+
+    /// 取得貼圖
+    pub fn sticker(&self, id: u32) -> Option<StickerInfo> {
+        self.stickers.iter().find(|sticker| sticker.id == id).cloned()
+    }
+
+    /// 對應 `emoji` 的貼圖 ID
+    #[wasm_bindgen(js_name = stickersForEmoji)]
+    pub fn stickers_for_emoji(&self, emoji: &str) -> Vec<u32> {
+        self.stickers.iter().filter(|sticker| sticker.emoji == emoji).map(|sticker| sticker.id).collect()
+    }
+}
+
+/// 已加密的貼圖 (上傳到 `<pack ID>/<貼圖 ID>`)
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EncryptedSticker {
+    id: u32,
+    ciphertext: Vec<u8>,
+}
+
+#[wasm_bindgen]
+impl EncryptedSticker {
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn ciphertext(&self) -> Vec<u8> {
+        self.ciphertext.clone()
+    }
+}
+
+/// 貼圖包分享內容 (pack ID 與 pack key)
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StickerPackShare {
+    pack_id: Vec<u8>,
+    pack_key: Vec<u8>,
+}
+
+impl StickerPackShare {
+    /// 從 pack ID 與 pack key 建立 (Rust 端使用)
+    pub fn from_parts(pack_id: &[u8], pack_key: &[u8]) -> Result<Self, String> {
+        check_length(pack_id, STICKER_PACK_ID_SIZE, "pack ID")?;
+        check_length(pack_key, STICKER_PACK_KEY_SIZE, "pack key")?;
+        Ok(Self { pack_id: pack_id.to_vec(), pack_key: pack_key.to_vec() })
+    }
+
+    /// 解析 `pack_id=<hex>&pack_key=<hex>` (Rust 端使用)
+    pub fn parse_fragment(fragment: &str) -> Result<Self, String> {
+        let (mut pack_id, mut pack_key) = (None, None);
+        for pair in fragment.trim_start_matches('#').split('&') {
+            match pair.split_once('=') {
+                Some(("pack_id", value)) => pack_id = Some(hex_decode(value, STICKER_PACK_ID_SIZE, "pack ID")?),
+                Some(("pack_key", value)) => pack_key = Some(hex_decode(value, STICKER_PACK_KEY_SIZE, "pack key")?),
+                _ => {}
+            }
+        }
+        match (pack_id, pack_key) {
+            (Some(pack_id), Some(pack_key)) => Ok(Self { pack_id, pack_key }),
+            _ => Err("Sticker pack link is missing pack_id or pack_key".to_string()),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl StickerPackShare {
+    /// 從 pack ID 與 pack key 建立
+    #[wasm_bindgen(constructor)]
+    pub fn new(pack_id: &[u8], pack_key: &[u8]) -> Result<StickerPackShare, JsError> {
+        Self::from_parts(pack_id, pack_key).map_err(|e| JsError::new(&e))
+    }
+
+    #[wasm_bindgen(getter, js_name = packId)]
+    pub fn pack_id(&self) -> Vec<u8> {
+        self.pack_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = packKey)]
+    pub fn pack_key(&self) -> Vec<u8> {
+        self.pack_key.clone()
+    }
+
+    /// URL fragment：`pack_id=<hex>&pack_key=<hex>`
+    #[wasm_bindgen(js_name = toFragment)]
+    pub fn to_fragment(&self) -> String {
+        format!("pack_id={}&pack_key={}", hex_encode(&self.pack_id), hex_encode(&self.pack_key))
+    }
+
+    /// 解析 URL fragment (可含開頭的 `#`)
+    #[wasm_bindgen(js_name = fromFragment)]
+    pub fn from_fragment(fragment: &str) -> Result<StickerPackShare, JsError> {
+        Self::parse_fragment(fragment).map_err(|e| JsError::new(&e))
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<StickerPackShare, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// 訊息中引用的貼圖
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct StickerReference {
+    pack: StickerPackShare,
+    sticker_id: u32,
+    emoji: String,
+}
+
+#[wasm_bindgen]
+impl StickerReference {
+    /// 所屬貼圖包 (接收端尚未安裝時用來下載)
+    #[wasm_bindgen(getter)]
+    pub fn pack(&self) -> StickerPackShare {
+        self.pack.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = stickerId)]
+    pub fn sticker_id(&self) -> u32 {
+        self.sticker_id
+    }
+
+    /// 貼圖的 emoji (下載完成前的替代顯示)
+    #[wasm_bindgen(getter)]
+    pub fn emoji(&self) -> String {
+        self.emoji.clone()
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<StickerReference, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// 貼圖包 (建立或已解開 manifest)
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct StickerPack {
+    pack_id: [u8; STICKER_PACK_ID_SIZE],
+    pack_key: Zeroizing<[u8; STICKER_PACK_KEY_SIZE]>,
+    manifest: StickerManifest,
+}
+
+impl StickerPack {
+    /// 加密貼圖並加入 manifest，貼圖 ID 依加入順序遞增 (Rust 端使用)
+    pub fn add_sticker(&mut self, data: &[u8], emoji: &str, content_type: &str) -> Result<EncryptedSticker, String> {
+        if self.manifest.stickers.len() >= MAX_STICKERS_PER_PACK {
+            return Err(format!("Sticker pack cannot hold more than {} stickers", MAX_STICKERS_PER_PACK));
+        }
+        let id = self.manifest.stickers.len() as u32;
+        let ciphertext = encrypt_with_pack_key(self.pack_key.as_slice(), data)?;
+        self.manifest.stickers.push(StickerInfo { id, emoji: emoji.to_string(), content_type: content_type.to_string() });
+        Ok(EncryptedSticker { id, ciphertext })
+    }
+
+    /// 設定封面貼圖 (Rust 端使用)
+    pub fn set_cover(&mut self, sticker_id: u32) -> Result<(), String> {
+        if self.manifest.sticker(sticker_id).is_none() {
+            return Err(format!("Unknown sticker: {}", sticker_id));
+        }
+        self.manifest.cover = Some(sticker_id);
+        Ok(())
+    }
+
+    /// 加密 manifest (上傳到 `<pack ID>/manifest`) (Rust 端使用)
+    pub fn encrypt_manifest(&self) -> Result<Vec<u8>, String> {
+        let manifest = Zeroizing::new(serde_json::to_vec(&self.manifest).map_err(|e| e.to_string())?);
+        encrypt_with_pack_key(self.pack_key.as_slice(), &manifest)
+    }
+
+    /// 以分享內容解開下載的 manifest (Rust 端使用)
+    pub fn open_manifest(share: &StickerPackShare, encrypted_manifest: &[u8]) -> Result<Self, String> {
+        let pack_key = pack_cipher_key(&share.pack_key)?;
+        let manifest = Zeroizing::new(legacy_attachment_decrypt(&pack_key, encrypted_manifest, None, None)?);
+        let manifest = serde_json::from_slice(&manifest).map_err(|e| format!("Corrupted sticker manifest: {}", e))?;
+        let mut pack = Self {
+            pack_id: [0u8; STICKER_PACK_ID_SIZE],
+            pack_key: Zeroizing::new([0u8; STICKER_PACK_KEY_SIZE]),
+            manifest,
+        };
+        pack.pack_id.copy_from_slice(&share.pack_id);
+        pack.pack_key.copy_from_slice(&share.pack_key);
+        Ok(pack)
+    }
+
+    /// 解密下載的貼圖 (Rust 端使用)
+    pub fn open_sticker(&self, sticker_id: u32, ciphertext: &[u8]) -> Result<Vec<u8>, String> {
+        if self.manifest.sticker(sticker_id).is_none() {
+            return Err(format!("Unknown sticker: {}", sticker_id));
+        }
+        legacy_attachment_decrypt(&pack_cipher_key(self.pack_key.as_slice())?, ciphertext, None, None)
+    }
+
+    /// 訊息中引用貼圖的內容 (Rust 端使用)
+    pub fn sticker_reference(&self, sticker_id: u32) -> Result<StickerReference, String> {
+        let sticker = self
+            .manifest
+            .sticker(sticker_id)
+            .ok_or_else(|| format!("Unknown sticker: {}", sticker_id))?;
+        Ok(StickerReference { pack: self.share(), sticker_id, emoji: sticker.emoji })
+    }
+
+    /// 是否為 `reference` 所屬的貼圖包
+    pub fn contains(&self, reference: &StickerReference) -> bool {
+        constant_time_eq(&reference.pack.pack_id, &self.pack_id)
+            && constant_time_eq(&reference.pack.pack_key, self.pack_key.as_slice())
+    }
+}
+
+#[wasm_bindgen]
+impl StickerPack {
+    /// 以新的隨機 pack ID 與 pack key 建立空的貼圖包
+    #[wasm_bindgen(constructor)]
+    pub fn new(title: &str, author: &str) -> Self {
+        let mut pack = Self {
+            pack_id: [0u8; STICKER_PACK_ID_SIZE],
+            pack_key: Zeroizing::new([0u8; STICKER_PACK_KEY_SIZE]),
+            manifest: StickerManifest {
+                title: title.to_string(),
+                author: author.to_string(),
+                cover: None,
+                stickers: Vec::new(),
+            },
+        };
+        OsRng.fill_bytes(&mut pack.pack_id);
+        OsRng.fill_bytes(pack.pack_key.as_mut_slice());
+        pack
+    }
+
+    #[wasm_bindgen(getter, js_name = packId)]
+    pub fn pack_id(&self) -> Vec<u8> {
+        self.pack_id.to_vec()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn manifest(&self) -> StickerManifest {
+        self.manifest.clone()
+    }
+
+    /// 分享內容 (含 pack key，只應傳給要安裝此貼圖包的對象)
+    pub fn share(&self) -> StickerPackShare {
+        StickerPackShare { pack_id: self.pack_id.to_vec(), pack_key: self.pack_key.to_vec() }
+    }
+
+    /// 加密貼圖並加入 manifest，貼圖 ID 依加入順序遞增
+    #[wasm_bindgen(js_name = addSticker)]
+    pub fn add_sticker_js(&mut self, data: &[u8], emoji: &str, content_type: &str) -> Result<EncryptedSticker, JsError> {
+        self.add_sticker(data, emoji, content_type).map_err(|e| JsError::new(&e))
+    }
+
+    /// 設定封面貼圖
+    #[wasm_bindgen(js_name = setCover)]
+    pub fn set_cover_js(&mut self, sticker_id: u32) -> Result<(), JsError> {
+        self.set_cover(sticker_id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 加密 manifest (加入所有貼圖後上傳)
+    #[wasm_bindgen(js_name = encryptManifest)]
+    pub fn encrypt_manifest_js(&self) -> Result<Vec<u8>, JsError> {
+        self.encrypt_manifest().map_err(|e| JsError::new(&e))
+    }
+
+    /// 以分享內容解開下載的 manifest
+    pub fn open(share: &StickerPackShare, encrypted_manifest: &[u8]) -> Result<StickerPack, JsError> {
+        Self::open_manifest(share, encrypted_manifest).map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密下載的貼圖
+    #[wasm_bindgen(js_name = decryptSticker)]
+    pub fn decrypt_sticker(&self, sticker_id: u32, ciphertext: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.open_sticker(sticker_id, ciphertext).map_err(|e| JsError::new(&e))
+    }
+
+    /// 訊息中引用貼圖的內容
+    #[wasm_bindgen(js_name = stickerReference)]
+    pub fn sticker_reference_js(&self, sticker_id: u32) -> Result<StickerReference, JsError> {
+        self.sticker_reference(sticker_id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 是否為 `reference` 所屬的貼圖包
+    #[wasm_bindgen(js_name = contains)]
+    pub fn contains_js(&self, reference: &StickerReference) -> bool {
+        self.contains(reference)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sticker_pack_round_trip() {
+        let mut pack = StickerPack::new("Cats", "alice");
+        let grin = pack.add_sticker(b"webp-grin", "😀", "image/webp").unwrap();
+        let heart = pack.add_sticker(b"webp-heart", "❤️", "image/webp").unwrap();
+        pack.add_sticker(b"webp-grin-2", "😀", "image/webp").unwrap();
+        pack.set_cover(heart.id()).unwrap();
+        assert!(pack.set_cover(9).is_err());
+        assert!(!grin.ciphertext().windows(9).any(|window| window == b"webp-grin"));
+
+        // 接收端只拿到分享內容與密文
+        let share = StickerPackShare::parse_fragment(&format!("#{}", pack.share().to_fragment())).unwrap();
+        assert_eq!(share, pack.share());
+        let opened = StickerPack::open_manifest(&share, &pack.encrypt_manifest().unwrap()).unwrap();
+        assert_eq!(opened.manifest(), pack.manifest());
+        assert_eq!(opened.manifest().cover(), Some(1));
+        assert_eq!(opened.manifest().stickers_for_emoji("😀"), vec![0, 2]);
+        assert_eq!(opened.open_sticker(grin.id(), &grin.ciphertext()).unwrap(), b"webp-grin");
+        assert!(opened.open_sticker(7, &grin.ciphertext()).is_err());
+
+        let reference = opened.sticker_reference(heart.id()).unwrap();
+        assert_eq!(reference.emoji(), "❤️");
+        assert!(pack.contains(&reference));
+        assert!(!StickerPack::new("Dogs", "bob").contains(&reference));
+
+        // 錯誤的 pack key 無法解開 manifest
+        let wrong = StickerPackShare::from_parts(&share.pack_id(), &[7u8; STICKER_PACK_KEY_SIZE]).unwrap();
+        assert!(StickerPack::open_manifest(&wrong, &pack.encrypt_manifest().unwrap()).is_err());
+        assert!(StickerPackShare::parse_fragment("pack_id=00").is_err());
+    }
+}
@@ -0,0 +1,348 @@
+//! PIN 安全值復原 (SVR) 模組
+//!
+//! 使用者遺失裝置時，以短 PIN 取回主儲存金鑰。
+//! PIN 經 Argon2id 延展後再以 HKDF 分成兩把金鑰：
+//!
+//! - access key：交給伺服器比對，伺服器依此限制猜測次數
+//! - encryption key：只留在客戶端，用來包裝主儲存金鑰
+//!
+//! 伺服器保存 [`SvrRecord`] (salt、剩餘次數、access key 與包裝後的金鑰)，
+//! 比對失敗就遞減次數，用盡後刪除紀錄，短 PIN 也只能被猜測有限次
+
+use wasm_bindgen::prelude::*;
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use hkdf::Hkdf;
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+use super::constant_time::constant_time_eq;
+use super::export::Argon2idParams;
+
+const INFO_ACCESS_KEY: &[u8] = b"SafeTalk_SVR_AccessKey";
+const INFO_ENCRYPTION_KEY: &[u8] = b"SafeTalk_SVR_EncryptionKey";
+const WRAP_AAD: &[u8] = b"SafeTalk_SVR_MasterKey";
+
+const SALT_SIZE: usize = 16;
+const NONCE_SIZE: usize = 12;
+
+/// PIN 最短長度
+pub const MIN_PIN_LENGTH: usize = 4;
+
+/// 預設允許的猜測次數
+pub const DEFAULT_MAX_TRIES: u32 = 10;
+
+/// 正規化 PIN (去除前後空白並檢查長度)
+pub fn normalize_pin(pin: &str) -> Result<String, String> {
+    let pin = pin.trim();
+    if pin.chars().count() < MIN_PIN_LENGTH {
+        return Err(format!("PIN must be at least {} characters", MIN_PIN_LENGTH));
+    }
+    Ok(pin.to_string())
+}
+
+/// 伺服器公開給客戶端的復原參數
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SvrChallenge {
+    salt: Vec<u8>,
+    params: Argon2idParams,
+    tries_remaining: u32,
+}
+
+#[wasm_bindgen]
+impl SvrChallenge {
+    /// 伺服器保存的 salt
+    #[wasm_bindgen(getter)]
+    pub fn salt(&self) -> Vec<u8> {
+        self.salt.clone()
+    }
+
+    /// 剩餘猜測次數
+    #[wasm_bindgen(getter, js_name = triesRemaining)]
+    pub fn tries_remaining(&self) -> u32 {
+        self.tries_remaining
+    }
+
+    /// 序列化為 JSON
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 從 JSON 反序列化
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<SvrChallenge, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// 由 PIN 衍生的兩把金鑰
+#[wasm_bindgen]
+pub struct PinHash {
+    access_key: [u8; 32],
+    encryption_key: [u8; 32],
+}
+
+impl PinHash {
+    /// 以 PIN 與伺服器 salt 衍生金鑰
+    pub fn derive(pin: &str, salt: &[u8], params: &Argon2idParams) -> Result<Self, String> {
+        params.check_limits()?;
+        let pin = normalize_pin(pin)?;
+        let stretched = params.derive_key(pin.as_bytes(), salt)?;
+
+        let hkdf = Hkdf::<Sha256>::new(Some(salt), &stretched);
+        let mut access_key = [0u8; 32];
+        hkdf.expand(INFO_ACCESS_KEY, &mut access_key)
+            .map_err(|e| format!("HKDF failed: {}", e))?;
+        let mut encryption_key = [0u8; 32];
+        hkdf.expand(INFO_ENCRYPTION_KEY, &mut encryption_key)
+            .map_err(|e| format!("HKDF failed: {}", e))?;
+
+        Ok(Self { access_key, encryption_key })
+    }
+
+    /// 以挑戰內容衍生金鑰
+    pub fn for_challenge(pin: &str, challenge: &SvrChallenge) -> Result<Self, String> {
+        Self::derive(pin, &challenge.salt, &challenge.params)
+    }
+
+    /// 包裝主儲存金鑰：nonce (12) || ciphertext + tag (48)
+    pub fn wrap_key(&self, master_key: &[u8; 32]) -> Result<Vec<u8>, String> {
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+
+        let cipher = Aes256Gcm::new_from_slice(&self.encryption_key).map_err(|e| e.to_string())?;
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: master_key, aad: WRAP_AAD })
+            .map_err(|e| format!("Encryption failed: {}", e))?;
+
+        let mut wrapped = nonce.to_vec();
+        wrapped.extend_from_slice(&ciphertext);
+        Ok(wrapped)
+    }
+
+    /// 解開主儲存金鑰
+    pub fn unwrap_key(&self, wrapped: &[u8]) -> Result<[u8; 32], String> {
+        if wrapped.len() < NONCE_SIZE {
+            return Err("Wrapped key too short".to_string());
+        }
+        let (nonce, ciphertext) = wrapped.split_at(NONCE_SIZE);
+
+        let cipher = Aes256Gcm::new_from_slice(&self.encryption_key).map_err(|e| e.to_string())?;
+        let plaintext = cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: WRAP_AAD })
+            .map_err(|_| "Wrong PIN or corrupted wrapped key".to_string())?;
+
+        plaintext
+            .as_slice()
+            .try_into()
+            .map_err(|_| "Master key must be 32 bytes".to_string())
+    }
+}
+
+#[wasm_bindgen]
+impl PinHash {
+    /// 以 PIN 與伺服器挑戰衍生金鑰
+    #[wasm_bindgen(js_name = fromChallenge)]
+    pub fn from_challenge(pin: &str, challenge: &SvrChallenge) -> Result<PinHash, JsError> {
+        Self::for_challenge(pin, challenge).map_err(|e| JsError::new(&e))
+    }
+
+    /// 要送給伺服器的 access key
+    #[wasm_bindgen(getter, js_name = accessKey)]
+    pub fn access_key(&self) -> Vec<u8> {
+        self.access_key.to_vec()
+    }
+
+    /// 解開伺服器回傳的主儲存金鑰
+    #[wasm_bindgen(js_name = unwrapMasterKey)]
+    pub fn unwrap_master_key(&self, wrapped: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.unwrap_key(wrapped)
+            .map(|key| key.to_vec())
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+/// 伺服器保存的復原紀錄
+#[wasm_bindgen]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SvrRecord {
+    salt: Vec<u8>,
+    params: Argon2idParams,
+    max_tries: u32,
+    tries_remaining: u32,
+    access_key: Vec<u8>,
+    wrapped_key: Vec<u8>,
+}
+
+impl SvrRecord {
+    /// 以 PIN 包裝主儲存金鑰，產生要上傳的紀錄
+    pub fn create(
+        pin: &str,
+        master_key: &[u8; 32],
+        params: &Argon2idParams,
+        max_tries: u32,
+    ) -> Result<Self, String> {
+        if max_tries == 0 {
+            return Err("max_tries must be at least 1".to_string());
+        }
+        let mut salt = vec![0u8; SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+
+        let hash = PinHash::derive(pin, &salt, params)?;
+        Ok(Self {
+            wrapped_key: hash.wrap_key(master_key)?,
+            access_key: hash.access_key.to_vec(),
+            salt,
+            params: *params,
+            max_tries,
+            tries_remaining: max_tries,
+        })
+    }
+
+    /// 取得公開給客戶端的挑戰
+    pub fn challenge(&self) -> SvrChallenge {
+        SvrChallenge {
+            salt: self.salt.clone(),
+            params: self.params,
+            tries_remaining: self.tries_remaining,
+        }
+    }
+
+    /// 伺服器端：比對 access key
+    ///
+    /// 成功時重置次數並回傳包裝後的金鑰；失敗時遞減次數，用盡後清除紀錄
+    pub fn attempt(&mut self, access_key: &[u8]) -> Result<Vec<u8>, String> {
+        if self.tries_remaining == 0 {
+            return Err("No tries remaining".to_string());
+        }
+        if constant_time_eq(&self.access_key, access_key) {
+            self.tries_remaining = self.max_tries;
+            return Ok(self.wrapped_key.clone());
+        }
+
+        self.tries_remaining -= 1;
+        if self.tries_remaining == 0 {
+            self.access_key.clear();
+            self.wrapped_key.clear();
+            return Err("No tries remaining".to_string());
+        }
+        Err(format!("Wrong PIN, {} tries remaining", self.tries_remaining))
+    }
+}
+
+#[wasm_bindgen]
+impl SvrRecord {
+    /// 以 PIN 包裝主儲存金鑰 (預設 Argon2id 參數與次數)
+    #[wasm_bindgen(constructor)]
+    pub fn new(pin: &str, master_key: &[u8]) -> Result<SvrRecord, JsError> {
+        let master_key: [u8; 32] = master_key
+            .try_into()
+            .map_err(|_| JsError::new("Master key must be 32 bytes"))?;
+        Self::create(pin, &master_key, &Argon2idParams::default(), DEFAULT_MAX_TRIES)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 以自訂 Argon2id 記憶體 (KiB)、迭代次數與猜測次數建立紀錄
+    #[wasm_bindgen(js_name = withParams)]
+    pub fn with_params(
+        pin: &str,
+        master_key: &[u8],
+        memory_kib: u32,
+        iterations: u32,
+        max_tries: u32,
+    ) -> Result<SvrRecord, JsError> {
+        let master_key: [u8; 32] = master_key
+            .try_into()
+            .map_err(|_| JsError::new("Master key must be 32 bytes"))?;
+        let params = Argon2idParams {
+            memory_kib,
+            iterations,
+            ..Argon2idParams::default()
+        };
+        params.check_limits().map_err(|e| JsError::new(&e))?;
+        Self::create(pin, &master_key, &params, max_tries).map_err(|e| JsError::new(&e))
+    }
+
+    /// 公開給客戶端的挑戰
+    #[wasm_bindgen(js_name = getChallenge)]
+    pub fn get_challenge(&self) -> SvrChallenge {
+        self.challenge()
+    }
+
+    /// 剩餘猜測次數
+    #[wasm_bindgen(getter, js_name = triesRemaining)]
+    pub fn tries_remaining(&self) -> u32 {
+        self.tries_remaining
+    }
+
+    /// 比對 access key
+    #[wasm_bindgen(js_name = tryAccessKey)]
+    pub fn try_access_key(&mut self, access_key: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.attempt(access_key).map_err(|e| JsError::new(&e))
+    }
+
+    /// 序列化為 JSON
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 從 JSON 反序列化
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<SvrRecord, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_PARAMS: Argon2idParams = Argon2idParams {
+        memory_kib: 64,
+        iterations: 1,
+        parallelism: 1,
+    };
+
+    #[test]
+    fn test_pin_recovery_roundtrip() {
+        let master_key = [7u8; 32];
+        let mut record = SvrRecord::create(" 1234 ", &master_key, &TEST_PARAMS, 3).unwrap();
+
+        let challenge = record.challenge();
+        assert_eq!(challenge.tries_remaining, 3);
+
+        let hash = PinHash::for_challenge("1234", &challenge).unwrap();
+        let wrapped = record.attempt(&hash.access_key()).unwrap();
+        assert_eq!(hash.unwrap_key(&wrapped), Ok(master_key));
+
+        // 不同 PIN 無法解開
+        let other = PinHash::for_challenge("4321", &challenge).unwrap();
+        assert!(other.unwrap_key(&wrapped).is_err());
+        assert!(PinHash::for_challenge("12", &challenge).is_err());
+    }
+
+    #[test]
+    fn test_pin_guess_limit() {
+        let mut record = SvrRecord::create("1234", &[1u8; 32], &TEST_PARAMS, 3).unwrap();
+        let wrong = PinHash::for_challenge("0000", &record.challenge()).unwrap();
+        let right = PinHash::for_challenge("1234", &record.challenge()).unwrap();
+
+        assert!(record.attempt(&wrong.access_key()).is_err());
+        assert_eq!(record.challenge().tries_remaining, 2);
+
+        // 成功後次數重置
+        assert!(record.attempt(&right.access_key()).is_ok());
+        assert_eq!(record.challenge().tries_remaining, 3);
+
+        for _ in 0..3 {
+            assert!(record.attempt(&wrong.access_key()).is_err());
+        }
+        assert_eq!(record.attempt(&right.access_key()), Err("No tries remaining".to_string()));
+    }
+}
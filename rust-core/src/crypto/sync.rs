@@ -0,0 +1,221 @@
+//! 跨裝置同步訊息模組
+//!
+//! 把已送出訊息的副本、已讀狀態與聯絡人更新加密給自己的其他裝置，
+//! 每台裝置之間使用各自的 Double Ratchet 會話 (聯絡人 ID 為自己)。
+//! 信封外層只帶有路由所需的來源與目的裝置 ID
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::ratchet::{RatchetMessage, RatchetSession};
+
+/// 已讀記錄
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ReadEntry {
+    /// 訊息發送者
+    pub sender: String,
+    /// 訊息時間戳記 (Unix 毫秒)
+    pub timestamp: u64,
+}
+
+/// 聯絡人更新
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ContactUpdate {
+    /// 聯絡人 ID
+    pub contact_id: String,
+    /// 顯示名稱
+    #[serde(default)]
+    pub display_name: Option<String>,
+    /// 已確認的身份公鑰
+    #[serde(default)]
+    pub identity_key: Option<Vec<u8>>,
+    /// 是否封鎖
+    #[serde(default)]
+    pub blocked: bool,
+}
+
+/// 同步內容
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SyncContent {
+    /// 在其他裝置送出的訊息副本
+    SentTranscript {
+        /// 收件者 (聯絡人或群組 ID)
+        destination: String,
+        /// 訊息時間戳記 (Unix 毫秒)
+        timestamp: u64,
+        /// 訊息明文
+        body: Vec<u8>,
+    },
+    /// 已讀狀態
+    Read { entries: Vec<ReadEntry> },
+    /// 聯絡人更新
+    Contacts { updates: Vec<ContactUpdate> },
+}
+
+/// 同步訊息
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct SyncMessage {
+    content: SyncContent,
+}
+
+impl SyncMessage {
+    /// 建立同步訊息
+    pub fn new(content: SyncContent) -> Self {
+        Self { content }
+    }
+
+    /// 同步內容
+    pub fn content(&self) -> &SyncContent {
+        &self.content
+    }
+}
+
+#[wasm_bindgen]
+impl SyncMessage {
+    /// 已送出訊息的副本
+    #[wasm_bindgen(js_name = sentTranscript)]
+    pub fn sent_transcript(destination: &str, timestamp: u64, body: &[u8]) -> SyncMessage {
+        Self::new(SyncContent::SentTranscript {
+            destination: destination.to_string(),
+            timestamp,
+            body: body.to_vec(),
+        })
+    }
+
+    /// 內容類型 (`sent_transcript`、`read`、`contacts`)
+    #[wasm_bindgen(getter, js_name = contentType)]
+    pub fn content_type(&self) -> String {
+        match self.content {
+            SyncContent::SentTranscript { .. } => "sent_transcript",
+            SyncContent::Read { .. } => "read",
+            SyncContent::Contacts { .. } => "contacts",
+        }
+        .to_string()
+    }
+
+    /// 以 JSON 表示的內容
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(&self.content).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 從 JSON 建立 (例如 `{"type":"read","entries":[...]}`)
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<SyncMessage, JsError> {
+        serde_json::from_str(json)
+            .map(Self::new)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 以與目的裝置之間的會話加密
+    #[wasm_bindgen(js_name = encryptForDevice)]
+    pub fn encrypt_for_device(
+        &self,
+        session: &mut RatchetSession,
+        source_device_id: u32,
+        destination_device_id: u32,
+    ) -> Result<SyncEnvelope, JsError> {
+        let plaintext = serde_json::to_vec(&self.content).map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(SyncEnvelope {
+            source_device_id,
+            destination_device_id,
+            message: session.encrypt(&plaintext)?,
+        })
+    }
+}
+
+/// 加密後的同步訊息信封
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SyncEnvelope {
+    /// 來源裝置 ID
+    source_device_id: u32,
+    /// 目的裝置 ID
+    destination_device_id: u32,
+    /// 加密內容
+    message: RatchetMessage,
+}
+
+#[wasm_bindgen]
+impl SyncEnvelope {
+    #[wasm_bindgen(getter, js_name = sourceDeviceId)]
+    pub fn source_device_id(&self) -> u32 {
+        self.source_device_id
+    }
+
+    #[wasm_bindgen(getter, js_name = destinationDeviceId)]
+    pub fn destination_device_id(&self) -> u32 {
+        self.destination_device_id
+    }
+
+    /// 以與來源裝置之間的會話解密
+    pub fn decrypt(&self, session: &mut RatchetSession) -> Result<SyncMessage, JsError> {
+        let plaintext = session.decrypt(&self.message)?;
+        serde_json::from_slice(&plaintext)
+            .map(SyncMessage::new)
+            .map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<SyncEnvelope, JsError> {
+        bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_roundtrip() {
+        let (mut phone, mut desktop) = RatchetSession::test_pair([3u8; 32]);
+
+        let messages = [
+            SyncMessage::sent_transcript("bob", 1000, b"hi bob"),
+            SyncMessage::new(SyncContent::Read {
+                entries: vec![ReadEntry { sender: "carol".to_string(), timestamp: 900 }],
+            }),
+            SyncMessage::new(SyncContent::Contacts {
+                updates: vec![ContactUpdate {
+                    contact_id: "dave".to_string(),
+                    display_name: Some("Dave".to_string()),
+                    identity_key: None,
+                    blocked: true,
+                }],
+            }),
+        ];
+
+        for message in &messages {
+            let envelope = message.encrypt_for_device(&mut phone, 1, 2).unwrap();
+            let envelope = SyncEnvelope::from_bytes(&envelope.to_bytes().unwrap()).unwrap();
+            assert_eq!(envelope.source_device_id(), 1);
+            assert_eq!(envelope.destination_device_id(), 2);
+            assert_eq!(&envelope.decrypt(&mut desktop).unwrap(), message);
+        }
+        assert_eq!(messages[1].content_type(), "read");
+    }
+
+    #[test]
+    fn test_sync_content_json() {
+        let json = r#"{"type":"contacts","updates":[{"contact_id":"eve"}]}"#;
+        let content: SyncContent = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            content,
+            SyncContent::Contacts {
+                updates: vec![ContactUpdate {
+                    contact_id: "eve".to_string(),
+                    display_name: None,
+                    identity_key: None,
+                    blocked: false,
+                }],
+            }
+        );
+    }
+}
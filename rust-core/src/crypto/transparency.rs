@@ -0,0 +1,472 @@
+//! 金鑰透明化 (Key Transparency) 模組
+//!
+//! 驗證金鑰透明化日誌 (RFC 9162 Merkle tree) 提供的包含證明與一致性證明，
+//! 確認伺服器發布的身份公鑰與 PreKeyBundle 確實記錄在所有人看到的同一份日誌中，
+//! 讓惡意伺服器無法只對特定使用者替換金鑰
+
+use wasm_bindgen::prelude::*;
+use sha2::{Digest, Sha256};
+
+use super::keys::{IdentityKeyPair, PreKeyBundle};
+
+const TREE_HEAD_CONTEXT: &[u8] = b"SafeTalk_KT_TreeHead_v1";
+const IDENTITY_LEAF_CONTEXT: &[u8] = b"SafeTalk_KT_Identity_v1";
+const BUNDLE_LEAF_CONTEXT: &[u8] = b"SafeTalk_KT_Bundle_v1";
+
+/// 32 bytes 雜湊值
+pub type Hash = [u8; 32];
+
+/// 葉節點雜湊：SHA-256(0x00 || data)
+pub fn leaf_hash(data: &[u8]) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x00]);
+    hasher.update(data);
+    hasher.finalize().into()
+}
+
+/// 內部節點雜湊：SHA-256(0x01 || left || right)
+pub fn node_hash(left: &Hash, right: &Hash) -> Hash {
+    let mut hasher = Sha256::new();
+    hasher.update([0x01]);
+    hasher.update(left);
+    hasher.update(right);
+    hasher.finalize().into()
+}
+
+fn length_prefixed(context: &[u8], fields: &[&[u8]]) -> Vec<u8> {
+    let mut out = context.to_vec();
+    for field in fields {
+        out.extend_from_slice(&(field.len() as u32).to_be_bytes());
+        out.extend_from_slice(field);
+    }
+    out
+}
+
+/// 身份公鑰的日誌葉節點內容
+pub fn identity_leaf(user_id: &str, identity_key: &[u8]) -> Vec<u8> {
+    length_prefixed(IDENTITY_LEAF_CONTEXT, &[user_id.as_bytes(), identity_key])
+}
+
+/// PreKeyBundle 的日誌葉節點內容
+pub fn bundle_leaf(user_id: &str, bundle: &PreKeyBundle) -> Vec<u8> {
+    length_prefixed(BUNDLE_LEAF_CONTEXT, &[user_id.as_bytes(), &bundle.signing_payload()])
+}
+
+/// 將串接的證明拆成 32 bytes 雜湊列表
+fn parse_proof(proof: &[u8]) -> Result<Vec<Hash>, String> {
+    if !proof.len().is_multiple_of(32) {
+        return Err("Proof length must be a multiple of 32".to_string());
+    }
+    Ok(proof
+        .chunks(32)
+        .map(|chunk| {
+            let mut hash = [0u8; 32];
+            hash.copy_from_slice(chunk);
+            hash
+        })
+        .collect())
+}
+
+fn parse_hash(bytes: &[u8]) -> Result<Hash, String> {
+    bytes
+        .try_into()
+        .map_err(|_| "Hash must be 32 bytes".to_string())
+}
+
+/// 驗證包含證明 (RFC 9162 2.1.3.2)
+pub fn verify_inclusion(leaf: &Hash, leaf_index: u64, tree_size: u64, proof: &[Hash], root: &Hash) -> bool {
+    if leaf_index >= tree_size {
+        return false;
+    }
+    let mut fn_ = leaf_index;
+    let mut sn = tree_size - 1;
+    let mut r = *leaf;
+    for p in proof {
+        if sn == 0 {
+            return false;
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            r = node_hash(p, &r);
+            if fn_ & 1 == 0 {
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            }
+        } else {
+            r = node_hash(&r, p);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    sn == 0 && &r == root
+}
+
+/// 驗證一致性證明 (RFC 9162 2.1.4.2)
+pub fn verify_consistency(
+    first_size: u64,
+    second_size: u64,
+    first_root: &Hash,
+    second_root: &Hash,
+    proof: &[Hash],
+) -> bool {
+    if first_size == 0 || first_size > second_size {
+        return false;
+    }
+    if first_size == second_size {
+        return proof.is_empty() && first_root == second_root;
+    }
+    if proof.is_empty() {
+        return false;
+    }
+
+    let mut path = proof.to_vec();
+    if first_size.is_power_of_two() {
+        path.insert(0, *first_root);
+    }
+
+    let mut fn_ = first_size - 1;
+    let mut sn = second_size - 1;
+    while fn_ & 1 == 1 {
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+
+    let mut fr = path[0];
+    let mut sr = path[0];
+    for c in &path[1..] {
+        if sn == 0 {
+            return false;
+        }
+        if fn_ & 1 == 1 || fn_ == sn {
+            fr = node_hash(c, &fr);
+            sr = node_hash(c, &sr);
+            if fn_ & 1 == 0 {
+                while fn_ & 1 == 0 && fn_ != 0 {
+                    fn_ >>= 1;
+                    sn >>= 1;
+                }
+            }
+        } else {
+            sr = node_hash(&sr, c);
+        }
+        fn_ >>= 1;
+        sn >>= 1;
+    }
+    sn == 0 && &fr == first_root && &sr == second_root
+}
+
+/// 日誌簽署的樹頭
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TreeHead {
+    tree_size: u64,
+    timestamp: u64,
+    root_hash: Hash,
+}
+
+impl TreeHead {
+    /// 樹頭簽章內容
+    fn signing_payload(&self) -> Vec<u8> {
+        length_prefixed(
+            TREE_HEAD_CONTEXT,
+            &[&self.tree_size.to_be_bytes(), &self.timestamp.to_be_bytes(), &self.root_hash],
+        )
+    }
+}
+
+#[wasm_bindgen]
+impl TreeHead {
+    #[wasm_bindgen(getter, js_name = treeSize)]
+    pub fn tree_size(&self) -> u64 {
+        self.tree_size
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    #[wasm_bindgen(getter, js_name = rootHash)]
+    pub fn root_hash(&self) -> Vec<u8> {
+        self.root_hash.to_vec()
+    }
+}
+
+/// 金鑰透明化日誌客戶端
+///
+/// 保存最後一次驗證過的樹頭，新的樹頭必須有日誌簽章且與舊樹頭一致
+#[wasm_bindgen]
+pub struct KeyTransparencyClient {
+    log_public_key: Vec<u8>,
+    trusted_head: Option<TreeHead>,
+}
+
+impl KeyTransparencyClient {
+    /// 驗證並更新樹頭
+    pub fn update_tree_head(
+        &mut self,
+        tree_size: u64,
+        timestamp: u64,
+        root_hash: &[u8],
+        signature: &[u8],
+        consistency_proof: &[u8],
+    ) -> Result<(), String> {
+        let head = TreeHead {
+            tree_size,
+            timestamp,
+            root_hash: parse_hash(root_hash)?,
+        };
+        if !IdentityKeyPair::verify_signature(&self.log_public_key, &head.signing_payload(), signature) {
+            return Err("Invalid tree head signature".to_string());
+        }
+
+        if let Some(trusted) = &self.trusted_head {
+            if head.tree_size < trusted.tree_size {
+                return Err("Tree head is older than the trusted tree head".to_string());
+            }
+            let proof = parse_proof(consistency_proof)?;
+            if !verify_consistency(
+                trusted.tree_size,
+                head.tree_size,
+                &trusted.root_hash,
+                &head.root_hash,
+                &proof,
+            ) {
+                return Err("Tree head is not consistent with the trusted tree head".to_string());
+            }
+        }
+        self.trusted_head = Some(head);
+        Ok(())
+    }
+
+    /// 驗證葉節點內容包含於目前信任的樹頭
+    pub fn verify_leaf(&self, leaf_data: &[u8], leaf_index: u64, proof: &[u8]) -> Result<(), String> {
+        let head = self
+            .trusted_head
+            .as_ref()
+            .ok_or_else(|| "No trusted tree head".to_string())?;
+        let proof = parse_proof(proof)?;
+        if verify_inclusion(&leaf_hash(leaf_data), leaf_index, head.tree_size, &proof, &head.root_hash) {
+            Ok(())
+        } else {
+            Err("Inclusion proof does not match the trusted tree head".to_string())
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl KeyTransparencyClient {
+    /// 以日誌的 Ed25519 公鑰建立客戶端
+    #[wasm_bindgen(constructor)]
+    pub fn new(log_public_key: &[u8]) -> Self {
+        Self {
+            log_public_key: log_public_key.to_vec(),
+            trusted_head: None,
+        }
+    }
+
+    /// 驗證並更新樹頭
+    ///
+    /// 第一次呼叫時 `consistency_proof` 可為空；之後必須提供與上一個樹頭的一致性證明
+    /// (串接的 32 bytes 雜湊)
+    #[wasm_bindgen(js_name = updateTreeHead)]
+    pub fn update_tree_head_js(
+        &mut self,
+        tree_size: u64,
+        timestamp: u64,
+        root_hash: &[u8],
+        signature: &[u8],
+        consistency_proof: &[u8],
+    ) -> Result<(), JsError> {
+        self.update_tree_head(tree_size, timestamp, root_hash, signature, consistency_proof)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 目前信任的樹頭
+    #[wasm_bindgen(getter, js_name = trustedTreeHead)]
+    pub fn trusted_tree_head(&self) -> Option<TreeHead> {
+        self.trusted_head.clone()
+    }
+
+    /// 驗證身份公鑰包含於日誌
+    #[wasm_bindgen(js_name = verifyIdentityKey)]
+    pub fn verify_identity_key(
+        &self,
+        user_id: &str,
+        identity_key: &[u8],
+        leaf_index: u64,
+        proof: &[u8],
+    ) -> bool {
+        self.verify_leaf(&identity_leaf(user_id, identity_key), leaf_index, proof).is_ok()
+    }
+
+    /// 驗證 PreKeyBundle 包含於日誌
+    #[wasm_bindgen(js_name = verifyPreKeyBundle)]
+    pub fn verify_pre_key_bundle(
+        &self,
+        user_id: &str,
+        bundle: &PreKeyBundle,
+        leaf_index: u64,
+        proof: &[u8],
+    ) -> bool {
+        self.verify_leaf(&bundle_leaf(user_id, bundle), leaf_index, proof).is_ok()
+    }
+}
+
+/// WASM 輔助函式：驗證包含證明
+#[wasm_bindgen(js_name = verifyMerkleInclusion)]
+pub fn verify_merkle_inclusion(
+    leaf_data: &[u8],
+    leaf_index: u64,
+    tree_size: u64,
+    proof: &[u8],
+    root_hash: &[u8],
+) -> bool {
+    match (parse_proof(proof), parse_hash(root_hash)) {
+        (Ok(proof), Ok(root)) => verify_inclusion(&leaf_hash(leaf_data), leaf_index, tree_size, &proof, &root),
+        _ => false,
+    }
+}
+
+/// WASM 輔助函式：驗證一致性證明
+#[wasm_bindgen(js_name = verifyMerkleConsistency)]
+pub fn verify_merkle_consistency(
+    first_size: u64,
+    second_size: u64,
+    first_root: &[u8],
+    second_root: &[u8],
+    proof: &[u8],
+) -> bool {
+    match (parse_proof(proof), parse_hash(first_root), parse_hash(second_root)) {
+        (Ok(proof), Ok(first), Ok(second)) => verify_consistency(first_size, second_size, &first, &second, &proof),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 6962 2.1 的參考定義，用來產生測試證明
+
+    fn largest_power_of_two_below(n: usize) -> usize {
+        let mut k = 1;
+        while k * 2 < n {
+            k *= 2;
+        }
+        k
+    }
+
+    fn mth(leaves: &[Hash]) -> Hash {
+        match leaves.len() {
+            1 => leaves[0],
+            n => {
+                let k = largest_power_of_two_below(n);
+                node_hash(&mth(&leaves[..k]), &mth(&leaves[k..]))
+            }
+        }
+    }
+
+    fn audit_path(m: usize, leaves: &[Hash]) -> Vec<Hash> {
+        let n = leaves.len();
+        if n == 1 {
+            return vec![];
+        }
+        let k = largest_power_of_two_below(n);
+        if m < k {
+            let mut path = audit_path(m, &leaves[..k]);
+            path.push(mth(&leaves[k..]));
+            path
+        } else {
+            let mut path = audit_path(m - k, &leaves[k..]);
+            path.push(mth(&leaves[..k]));
+            path
+        }
+    }
+
+    fn subproof(m: usize, leaves: &[Hash], complete: bool) -> Vec<Hash> {
+        let n = leaves.len();
+        if m == n {
+            return if complete { vec![] } else { vec![mth(leaves)] };
+        }
+        let k = largest_power_of_two_below(n);
+        if m <= k {
+            let mut proof = subproof(m, &leaves[..k], complete);
+            proof.push(mth(&leaves[k..]));
+            proof
+        } else {
+            let mut proof = subproof(m - k, &leaves[k..], false);
+            proof.push(mth(&leaves[..k]));
+            proof
+        }
+    }
+
+    fn leaves(n: usize) -> Vec<Hash> {
+        (0..n).map(|i| leaf_hash(&identity_leaf(&format!("user{}", i), &[i as u8; 32]))).collect()
+    }
+
+    #[test]
+    fn test_inclusion_proofs() {
+        for n in 1..=9 {
+            let tree = leaves(n);
+            let root = mth(&tree);
+            for m in 0..n {
+                let path = audit_path(m, &tree);
+                assert!(verify_inclusion(&tree[m], m as u64, n as u64, &path, &root), "n={} m={}", n, m);
+                // 錯誤的索引或葉節點
+                if m + 1 < n {
+                    assert!(!verify_inclusion(&tree[m], m as u64 + 1, n as u64, &path, &root));
+                }
+                assert!(!verify_inclusion(&leaf_hash(b"other"), m as u64, n as u64, &path, &root));
+            }
+        }
+    }
+
+    #[test]
+    fn test_consistency_proofs() {
+        let tree = leaves(9);
+        for n in 1..=9 {
+            for m in 1..=n {
+                let proof = subproof(m, &tree[..n], true);
+                let first = mth(&tree[..m]);
+                let second = mth(&tree[..n]);
+                assert!(verify_consistency(m as u64, n as u64, &first, &second, &proof), "m={} n={}", m, n);
+                if m < n {
+                    assert!(!verify_consistency(m as u64, n as u64, &leaf_hash(b"forked"), &second, &proof));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_client_tracks_tree_head() {
+        let log = IdentityKeyPair::new();
+        let tree = leaves(7);
+        let sign_head = |size: usize| {
+            let head = TreeHead {
+                tree_size: size as u64,
+                timestamp: size as u64 * 1000,
+                root_hash: mth(&tree[..size]),
+            };
+            (head.root_hash, log.sign(&head.signing_payload()))
+        };
+
+        let mut client = KeyTransparencyClient::new(&log.public_key_bytes());
+        let (root4, sig4) = sign_head(4);
+        client.update_tree_head(4, 4000, &root4, &sig4, &[]).unwrap();
+
+        let (root7, sig7) = sign_head(7);
+        assert!(client.update_tree_head(7, 7000, &root7, &sig7, &[]).is_err());
+        let proof = subproof(4, &tree[..7], true).concat();
+        client.update_tree_head(7, 7000, &root7, &sig7, &proof).unwrap();
+        assert_eq!(client.trusted_tree_head().unwrap().tree_size(), 7);
+
+        let path = audit_path(5, &tree[..7]).concat();
+        assert!(client.verify_identity_key("user5", &[5u8; 32], 5, &path));
+        assert!(!client.verify_identity_key("user5", &[6u8; 32], 5, &path));
+
+        // 未經日誌簽署的樹頭
+        assert!(client.update_tree_head(7, 7000, &root7, &sig4, &[]).is_err());
+    }
+}
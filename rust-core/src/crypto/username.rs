@@ -0,0 +1,187 @@
+//! 使用者名稱證明模組
+//!
+//! 以身份金鑰簽署「使用者名稱 ↔ 身份公鑰」的綁定並附上有效期限，
+//! 伺服器只負責發布證明，無法偽造使用者名稱的擁有權
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::keys::IdentityKeyPair;
+
+const USERNAME_PROOF_CONTEXT: &[u8] = b"SafeTalk_UsernameProof_v1";
+
+/// 使用者名稱最短長度
+pub const MIN_USERNAME_LEN: usize = 3;
+/// 使用者名稱最長長度
+pub const MAX_USERNAME_LEN: usize = 32;
+
+/// 正規化並檢查使用者名稱
+///
+/// 轉為小寫，只允許 `a-z`、`0-9`、`_` 與 `.`，且不可以數字或 `.` 開頭
+pub fn normalize_username(username: &str) -> Result<String, String> {
+    let normalized = username.trim().to_lowercase();
+    if normalized.len() < MIN_USERNAME_LEN || normalized.len() > MAX_USERNAME_LEN {
+        return Err(format!(
+            "Username must be {}-{} characters",
+            MIN_USERNAME_LEN, MAX_USERNAME_LEN
+        ));
+    }
+    if !normalized
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '.')
+    {
+        return Err("Username may only contain a-z, 0-9, '_' and '.'".to_string());
+    }
+    if normalized.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+        return Err("Username must not start with a digit or '.'".to_string());
+    }
+    Ok(normalized)
+}
+
+/// 使用者名稱證明
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct UsernameProof {
+    /// 正規化後的使用者名稱
+    username: String,
+    /// 擁有者身份公鑰
+    identity_key: Vec<u8>,
+    /// 簽發時間 (Unix 毫秒)
+    issued_at: u64,
+    /// 到期時間 (Unix 毫秒)
+    expires_at: u64,
+    /// 身份金鑰對以上欄位的簽章
+    signature: Vec<u8>,
+}
+
+impl UsernameProof {
+    fn signing_payload(username: &str, identity_key: &[u8], issued_at: u64, expires_at: u64) -> Vec<u8> {
+        let mut payload = USERNAME_PROOF_CONTEXT.to_vec();
+        for field in [
+            username.as_bytes(),
+            identity_key,
+            &issued_at.to_be_bytes(),
+            &expires_at.to_be_bytes(),
+        ] {
+            payload.extend_from_slice(&(field.len() as u32).to_be_bytes());
+            payload.extend_from_slice(field);
+        }
+        payload
+    }
+
+    /// 建立證明
+    pub fn create(identity: &IdentityKeyPair, username: &str, now: u64, ttl_ms: u64) -> Result<Self, String> {
+        let username = normalize_username(username)?;
+        let identity_key = identity.public_key_bytes();
+        let expires_at = now
+            .checked_add(ttl_ms)
+            .ok_or_else(|| "Expiry overflows".to_string())?;
+        let payload = Self::signing_payload(&username, &identity_key, now, expires_at);
+        Ok(Self {
+            signature: identity.sign(&payload),
+            username,
+            identity_key,
+            issued_at: now,
+            expires_at,
+        })
+    }
+
+    /// 驗證證明在 `now` 時有效
+    pub fn check(&self, now: u64) -> Result<(), String> {
+        if normalize_username(&self.username)? != self.username {
+            return Err("Username is not normalized".to_string());
+        }
+        let payload = Self::signing_payload(&self.username, &self.identity_key, self.issued_at, self.expires_at);
+        if !IdentityKeyPair::verify_signature(&self.identity_key, &payload, &self.signature) {
+            return Err("Invalid username proof signature".to_string());
+        }
+        if now >= self.expires_at {
+            return Err("Username proof expired".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl UsernameProof {
+    /// 建立證明
+    #[wasm_bindgen(js_name = create)]
+    pub fn create_js(identity: &IdentityKeyPair, username: &str, now: u64, ttl_ms: u64) -> Result<UsernameProof, JsError> {
+        Self::create(identity, username, now, ttl_ms).map_err(|e| JsError::new(&e))
+    }
+
+    /// 驗證證明在 `now` 時有效
+    #[wasm_bindgen(js_name = verify)]
+    pub fn verify(&self, now: u64) -> bool {
+        self.check(now).is_ok()
+    }
+
+    /// 驗證證明有效且屬於指定的使用者名稱
+    #[wasm_bindgen(js_name = verifyFor)]
+    pub fn verify_for(&self, username: &str, now: u64) -> bool {
+        normalize_username(username).is_ok_and(|u| u == self.username) && self.verify(now)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn username(&self) -> String {
+        self.username.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = identityKey)]
+    pub fn identity_key(&self) -> Vec<u8> {
+        self.identity_key.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = issuedAt)]
+    pub fn issued_at(&self) -> u64 {
+        self.issued_at
+    }
+
+    #[wasm_bindgen(getter, js_name = expiresAt)]
+    pub fn expires_at(&self) -> u64 {
+        self.expires_at
+    }
+
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<UsernameProof, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_username_proof() {
+        let identity = IdentityKeyPair::new();
+        let proof = UsernameProof::create(&identity, " Alice.Chen ", 1000, 500).unwrap();
+        assert_eq!(proof.username(), "alice.chen");
+        assert!(proof.verify(1000));
+        assert!(proof.verify_for("ALICE.chen", 1200));
+        assert!(!proof.verify_for("bob", 1200));
+        assert_eq!(proof.check(1500), Err("Username proof expired".to_string()));
+
+        // 伺服器嘗試把名稱轉給其他身份
+        let mut forged = proof.clone();
+        forged.identity_key = IdentityKeyPair::new().public_key_bytes();
+        assert!(!forged.verify(1000));
+
+        let mut renamed = proof;
+        renamed.username = "mallory".to_string();
+        assert!(!renamed.verify(1000));
+    }
+
+    #[test]
+    fn test_normalize_username() {
+        assert!(normalize_username("ab").is_err());
+        assert!(normalize_username("1alice").is_err());
+        assert!(normalize_username("alice!").is_err());
+        assert_eq!(normalize_username("Bob_99"), Ok("bob_99".to_string()));
+    }
+}
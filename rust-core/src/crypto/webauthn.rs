@@ -0,0 +1,468 @@
+//! WebAuthn / Passkey 身份綁定模組
+//!
+//! 產生綁定身份公鑰的 WebAuthn 挑戰，交給平台 passkey 以
+//! `navigator.credentials.get()` 簽署，再驗證回傳的 assertion。
+//! 身份金鑰因此與硬體保護的憑證綁定，接管帳號必須同時持有驗證器
+//!
+//! 支援的 COSE 演算法：
+//! - EdDSA (-8)：Ed25519
+//! - ES256 (-7)：P-256 + SHA-256 (需啟用 `p256` feature)
+//!
+//! 驗證項目：clientDataJSON 的 type / challenge / origin，
+//! authenticatorData 的 RP ID 雜湊、UP/UV 旗標與簽章計數器
+
+use wasm_bindgen::prelude::*;
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::keys::IdentityKeyPair;
+
+const CHALLENGE_CONTEXT: &[u8] = b"SafeTalk_PasskeyAttestation_v1";
+
+/// 挑戰有效期限 (5 分鐘)
+pub const PASSKEY_CHALLENGE_TTL_MS: u64 = 5 * 60 * 1000;
+
+/// COSE 演算法 EdDSA
+pub const COSE_ALG_EDDSA: i32 = -8;
+/// COSE 演算法 ES256
+pub const COSE_ALG_ES256: i32 = -7;
+
+/// authenticatorData 旗標：使用者在場
+const FLAG_USER_PRESENT: u8 = 0x01;
+/// authenticatorData 旗標：使用者已驗證
+const FLAG_USER_VERIFIED: u8 = 0x04;
+
+/// SubjectPublicKeyInfo 中公鑰之前的固定 DER 前綴
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+const P256_SPKI_PREFIX: [u8; 26] = [
+    0x30, 0x59, 0x30, 0x13, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01, 0x06, 0x08, 0x2a,
+    0x86, 0x48, 0xce, 0x3d, 0x03, 0x01, 0x07, 0x03, 0x42, 0x00,
+];
+
+/// 綁定身份公鑰的 passkey 挑戰
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct IdentityAttestationChallenge {
+    /// 要綁定的身份公鑰
+    identity_key: Vec<u8>,
+    /// passkey 憑證 ID
+    credential_id: Vec<u8>,
+    /// 隨機值
+    nonce: Vec<u8>,
+    /// 建立時間 (Unix 毫秒)
+    issued_at: u64,
+}
+
+impl IdentityAttestationChallenge {
+    /// 建立挑戰
+    pub fn create(identity_key: &[u8], credential_id: &[u8], now: u64) -> Self {
+        let mut nonce = vec![0u8; 32];
+        OsRng.fill_bytes(&mut nonce);
+        Self {
+            identity_key: identity_key.to_vec(),
+            credential_id: credential_id.to_vec(),
+            nonce,
+            issued_at: now,
+        }
+    }
+
+    /// 挑戰值 = SHA-256(context || 長度前綴欄位 || issued_at)
+    pub fn challenge_bytes(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(CHALLENGE_CONTEXT);
+        for field in [&self.identity_key, &self.credential_id, &self.nonce] {
+            hasher.update((field.len() as u32).to_be_bytes());
+            hasher.update(field);
+        }
+        hasher.update(self.issued_at.to_be_bytes());
+        hasher.finalize().into()
+    }
+}
+
+#[wasm_bindgen]
+impl IdentityAttestationChallenge {
+    /// 建立挑戰
+    #[wasm_bindgen(constructor)]
+    pub fn new(identity_key: &[u8], credential_id: &[u8], now: u64) -> IdentityAttestationChallenge {
+        Self::create(identity_key, credential_id, now)
+    }
+
+    /// 為身份金鑰對建立挑戰
+    #[wasm_bindgen(js_name = forIdentity)]
+    pub fn for_identity(identity: &IdentityKeyPair, credential_id: &[u8], now: u64) -> IdentityAttestationChallenge {
+        Self::create(&identity.public_key_bytes(), credential_id, now)
+    }
+
+    /// 傳給 `navigator.credentials.get()` 的 challenge
+    #[wasm_bindgen(getter)]
+    pub fn challenge(&self) -> Vec<u8> {
+        self.challenge_bytes().to_vec()
+    }
+
+    /// 身份公鑰
+    #[wasm_bindgen(getter, js_name = identityKey)]
+    pub fn identity_key(&self) -> Vec<u8> {
+        self.identity_key.clone()
+    }
+
+    /// 建立時間
+    #[wasm_bindgen(getter, js_name = issuedAt)]
+    pub fn issued_at(&self) -> u64 {
+        self.issued_at
+    }
+
+    /// 序列化為 JSON
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 從 JSON 反序列化
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<IdentityAttestationChallenge, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// 已註冊的 passkey 憑證
+#[wasm_bindgen]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PasskeyCredential {
+    credential_id: Vec<u8>,
+    /// COSE 演算法
+    algorithm: i32,
+    /// 原始公鑰 (Ed25519 32 bytes / P-256 SEC1)
+    public_key: Vec<u8>,
+    rp_id: String,
+    origin: String,
+    /// 最後看到的簽章計數器
+    sign_count: u32,
+}
+
+/// 去除 SPKI 前綴，接受原始公鑰或 `getPublicKey()` 回傳的 DER
+fn raw_public_key(algorithm: i32, public_key: &[u8]) -> Result<Vec<u8>, String> {
+    match algorithm {
+        COSE_ALG_EDDSA => match public_key.len() {
+            32 => Ok(public_key.to_vec()),
+            44 if public_key.starts_with(&ED25519_SPKI_PREFIX) => Ok(public_key[12..].to_vec()),
+            _ => Err("Invalid Ed25519 credential public key".to_string()),
+        },
+        COSE_ALG_ES256 => match public_key.len() {
+            65 if public_key[0] == 0x04 => Ok(public_key.to_vec()),
+            91 if public_key.starts_with(&P256_SPKI_PREFIX) => Ok(public_key[26..].to_vec()),
+            _ => Err("Invalid P-256 credential public key".to_string()),
+        },
+        other => Err(format!("Unsupported COSE algorithm: {}", other)),
+    }
+}
+
+/// 驗證 authenticatorData || SHA-256(clientDataJSON) 的簽章
+fn verify_signature(algorithm: i32, public_key: &[u8], signed: &[u8], signature: &[u8]) -> bool {
+    match algorithm {
+        COSE_ALG_EDDSA => IdentityKeyPair::verify_signature(public_key, signed, signature),
+        #[cfg(feature = "p256")]
+        COSE_ALG_ES256 => {
+            use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+            let verifying_key = match VerifyingKey::from_sec1_bytes(public_key) {
+                Ok(k) => k,
+                Err(_) => return false,
+            };
+            // WebAuthn 的 ES256 簽章為 DER 編碼
+            match Signature::from_der(signature) {
+                Ok(sig) => verifying_key.verify(signed, &sig).is_ok(),
+                Err(_) => false,
+            }
+        }
+        _ => false,
+    }
+}
+
+impl PasskeyCredential {
+    /// 登記 passkey 憑證
+    pub fn create(
+        credential_id: &[u8],
+        algorithm: i32,
+        public_key: &[u8],
+        rp_id: &str,
+        origin: &str,
+    ) -> Result<Self, String> {
+        if credential_id.is_empty() {
+            return Err("Credential ID must not be empty".to_string());
+        }
+        #[cfg(not(feature = "p256"))]
+        if algorithm == COSE_ALG_ES256 {
+            return Err("ES256 credentials require the p256 feature".to_string());
+        }
+        Ok(Self {
+            credential_id: credential_id.to_vec(),
+            algorithm,
+            public_key: raw_public_key(algorithm, public_key)?,
+            rp_id: rp_id.to_string(),
+            origin: origin.to_string(),
+            sign_count: 0,
+        })
+    }
+
+    /// 驗證 assertion 並更新簽章計數器
+    ///
+    /// 計數器非零且沒有遞增時視為憑證遭複製
+    pub fn verify_assertion(
+        &mut self,
+        challenge: &IdentityAttestationChallenge,
+        authenticator_data: &[u8],
+        client_data_json: &[u8],
+        signature: &[u8],
+        require_user_verification: bool,
+        now: u64,
+    ) -> Result<(), String> {
+        if challenge.credential_id != self.credential_id {
+            return Err("Challenge was issued for a different credential".to_string());
+        }
+        if now < challenge.issued_at || now - challenge.issued_at > PASSKEY_CHALLENGE_TTL_MS {
+            return Err("Challenge expired".to_string());
+        }
+
+        // clientDataJSON
+        let client_data: serde_json::Value = serde_json::from_slice(client_data_json)
+            .map_err(|e| format!("Invalid clientDataJSON: {}", e))?;
+        if client_data["type"] != "webauthn.get" {
+            return Err("clientDataJSON type must be webauthn.get".to_string());
+        }
+        if client_data["challenge"] != BASE64URL.encode(challenge.challenge_bytes()) {
+            return Err("Challenge mismatch".to_string());
+        }
+        if client_data["origin"] != self.origin.as_str() {
+            return Err("Origin mismatch".to_string());
+        }
+        if client_data["crossOrigin"] == true {
+            return Err("Cross-origin assertions are not allowed".to_string());
+        }
+
+        // authenticatorData：rpIdHash (32) || flags (1) || signCount (4, BE) || ...
+        if authenticator_data.len() < 37 {
+            return Err("authenticatorData too short".to_string());
+        }
+        if authenticator_data[..32] != Sha256::digest(self.rp_id.as_bytes())[..] {
+            return Err("RP ID mismatch".to_string());
+        }
+        let flags = authenticator_data[32];
+        if flags & FLAG_USER_PRESENT == 0 {
+            return Err("User presence flag not set".to_string());
+        }
+        if require_user_verification && flags & FLAG_USER_VERIFIED == 0 {
+            return Err("User verification flag not set".to_string());
+        }
+
+        let mut signed = authenticator_data.to_vec();
+        signed.extend_from_slice(&Sha256::digest(client_data_json));
+        if !verify_signature(self.algorithm, &self.public_key, &signed, signature) {
+            return Err("Invalid assertion signature".to_string());
+        }
+
+        let mut count = [0u8; 4];
+        count.copy_from_slice(&authenticator_data[33..37]);
+        let sign_count = u32::from_be_bytes(count);
+        if (sign_count != 0 || self.sign_count != 0) && sign_count <= self.sign_count {
+            return Err("Signature counter did not increase; credential may be cloned".to_string());
+        }
+        self.sign_count = sign_count;
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl PasskeyCredential {
+    /// 登記 passkey 憑證
+    ///
+    /// `public_key` 可為原始公鑰或 `getPublicKey()` 回傳的 SPKI DER
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        credential_id: &[u8],
+        algorithm: i32,
+        public_key: &[u8],
+        rp_id: &str,
+        origin: &str,
+    ) -> Result<PasskeyCredential, JsError> {
+        Self::create(credential_id, algorithm, public_key, rp_id, origin).map_err(|e| JsError::new(&e))
+    }
+
+    /// 驗證綁定身份的 assertion
+    #[wasm_bindgen(js_name = verifyIdentityAssertion)]
+    pub fn verify_identity_assertion(
+        &mut self,
+        challenge: &IdentityAttestationChallenge,
+        authenticator_data: &[u8],
+        client_data_json: &[u8],
+        signature: &[u8],
+        require_user_verification: bool,
+        now: u64,
+    ) -> Result<(), JsError> {
+        self.verify_assertion(
+            challenge,
+            authenticator_data,
+            client_data_json,
+            signature,
+            require_user_verification,
+            now,
+        )
+        .map_err(|e| JsError::new(&e))
+    }
+
+    /// 憑證 ID
+    #[wasm_bindgen(getter, js_name = credentialId)]
+    pub fn credential_id(&self) -> Vec<u8> {
+        self.credential_id.clone()
+    }
+
+    /// COSE 演算法
+    #[wasm_bindgen(getter)]
+    pub fn algorithm(&self) -> i32 {
+        self.algorithm
+    }
+
+    /// 最後看到的簽章計數器
+    #[wasm_bindgen(getter, js_name = signCount)]
+    pub fn sign_count(&self) -> u32 {
+        self.sign_count
+    }
+
+    /// 序列化為 JSON
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 從 JSON 反序列化
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<PasskeyCredential, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RP_ID: &str = "mist.example";
+    const ORIGIN: &str = "https://mist.example";
+
+    fn authenticator_data(flags: u8, sign_count: u32) -> Vec<u8> {
+        let mut data = Sha256::digest(RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&sign_count.to_be_bytes());
+        data
+    }
+
+    fn client_data(challenge: &IdentityAttestationChallenge, origin: &str) -> Vec<u8> {
+        format!(
+            r#"{{"type":"webauthn.get","challenge":"{}","origin":"{}","crossOrigin":false}}"#,
+            BASE64URL.encode(challenge.challenge_bytes()),
+            origin
+        )
+        .into_bytes()
+    }
+
+    fn sign(authenticator: &IdentityKeyPair, auth_data: &[u8], client_data: &[u8]) -> Vec<u8> {
+        let mut signed = auth_data.to_vec();
+        signed.extend_from_slice(&Sha256::digest(client_data));
+        authenticator.sign(&signed)
+    }
+
+    #[test]
+    fn test_passkey_identity_assertion() {
+        let identity = IdentityKeyPair::new();
+        // 以 Ed25519 金鑰模擬驗證器
+        let authenticator = IdentityKeyPair::new();
+        let credential_id = b"credential-1";
+        let mut credential =
+            PasskeyCredential::create(credential_id, COSE_ALG_EDDSA, &authenticator.public_key_bytes(), RP_ID, ORIGIN)
+                .unwrap();
+
+        let now = 1_700_000_000_000;
+        let challenge = IdentityAttestationChallenge::for_identity(&identity, credential_id, now);
+        let auth_data = authenticator_data(FLAG_USER_PRESENT | FLAG_USER_VERIFIED, 1);
+        let client = client_data(&challenge, ORIGIN);
+        let signature = sign(&authenticator, &auth_data, &client);
+
+        credential
+            .verify_assertion(&challenge, &auth_data, &client, &signature, true, now + 1000)
+            .unwrap();
+        assert_eq!(credential.sign_count(), 1);
+
+        // 計數器未遞增 (重放或複製)
+        assert!(credential
+            .verify_assertion(&challenge, &auth_data, &client, &signature, true, now + 1000)
+            .is_err());
+    }
+
+    #[test]
+    fn test_passkey_assertion_rejections() {
+        let identity = IdentityKeyPair::new();
+        let authenticator = IdentityKeyPair::new();
+        let credential_id = b"credential-1";
+        let mut credential =
+            PasskeyCredential::create(credential_id, COSE_ALG_EDDSA, &authenticator.public_key_bytes(), RP_ID, ORIGIN)
+                .unwrap();
+        let now = 1_700_000_000_000;
+        let challenge = IdentityAttestationChallenge::for_identity(&identity, credential_id, now);
+
+        // 錯誤 origin
+        let auth_data = authenticator_data(FLAG_USER_PRESENT, 5);
+        let phishing = client_data(&challenge, "https://evil.example");
+        let signature = sign(&authenticator, &auth_data, &phishing);
+        assert_eq!(
+            credential.verify_assertion(&challenge, &auth_data, &phishing, &signature, false, now),
+            Err("Origin mismatch".to_string())
+        );
+
+        // 需要 UV 但未驗證
+        let client = client_data(&challenge, ORIGIN);
+        let signature = sign(&authenticator, &auth_data, &client);
+        assert!(credential
+            .verify_assertion(&challenge, &auth_data, &client, &signature, true, now)
+            .is_err());
+
+        // 挑戰過期
+        assert_eq!(
+            credential.verify_assertion(&challenge, &auth_data, &client, &signature, false, now + PASSKEY_CHALLENGE_TTL_MS + 1),
+            Err("Challenge expired".to_string())
+        );
+
+        // 不是驗證器的簽章
+        let forged = sign(&identity, &auth_data, &client);
+        assert!(credential
+            .verify_assertion(&challenge, &auth_data, &client, &forged, false, now)
+            .is_err());
+
+        assert!(credential
+            .verify_assertion(&challenge, &auth_data, &client, &signature, false, now)
+            .is_ok());
+    }
+
+    #[cfg(feature = "p256")]
+    #[test]
+    fn test_passkey_es256_assertion() {
+        use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+
+        let authenticator = SigningKey::random(&mut OsRng);
+        let public = authenticator.verifying_key().to_encoded_point(false);
+        let mut spki = P256_SPKI_PREFIX.to_vec();
+        spki.extend_from_slice(public.as_bytes());
+
+        let mut credential = PasskeyCredential::create(b"cred", COSE_ALG_ES256, &spki, RP_ID, ORIGIN).unwrap();
+        let challenge = IdentityAttestationChallenge::create(&[7u8; 32], b"cred", 0);
+        let auth_data = authenticator_data(FLAG_USER_PRESENT, 0);
+        let client = client_data(&challenge, ORIGIN);
+        let mut signed = auth_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&client));
+        let signature: Signature = authenticator.sign(&signed);
+
+        credential
+            .verify_assertion(&challenge, &auth_data, &client, signature.to_der().as_bytes(), false, 0)
+            .unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+//! WebCrypto AES-GCM 後端 (feature = "webcrypto")
+//!
+//! 大量資料的 AES-256-GCM 運算交給瀏覽器的 `crypto.subtle` (有硬體加速)，
+//! 金鑰衍生與 ratchet 邏輯仍留在 Rust。純 WASM 的 AES 對大型附件慢上數倍
+//!
+//! 密文與 `AesGcmCipher` 的 `EncryptedMessage` 完全相容 (12 bytes 隨機 nonce，
+//! 16 bytes 驗證標籤附在密文後)，兩種後端可以互相解密
+//!
+//! 只能在有 `crypto.subtle` 的 JS 環境 (瀏覽器、Worker、Node 18+) 中使用
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use js_sys::{Array, Promise, Uint8Array};
+use rand::{rngs::OsRng, RngCore};
+use web_sys::{AesGcmParams, Crypto, CryptoKey, SubtleCrypto};
+
+use super::aes::EncryptedMessage;
+
+const NONCE_SIZE: usize = 12;
+const KEY_SIZE: usize = 32;
+const ALGORITHM: &str = "AES-GCM";
+
+/// 取得全域的 `crypto.subtle` (window 與 worker 皆可)
+fn subtle() -> Result<SubtleCrypto, JsError> {
+    let crypto = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("crypto"))
+        .ok()
+        .filter(|crypto| !crypto.is_undefined())
+        .ok_or_else(|| JsError::new("WebCrypto is not available"))?;
+    Ok(crypto.unchecked_into::<Crypto>().subtle())
+}
+
+fn gcm_params(nonce: &[u8], aad: Option<&[u8]>) -> AesGcmParams {
+    let params = AesGcmParams::new(ALGORITHM, &Uint8Array::from(nonce));
+    if let Some(aad) = aad {
+        params.set_additional_data(&Uint8Array::from(aad));
+    }
+    params
+}
+
+fn js_error(context: &str, error: JsValue) -> JsError {
+    JsError::new(&format!("{}: {:?}", context, error))
+}
+
+/// 以 WebCrypto 執行的 AES-256-GCM 加密器
+///
+/// 金鑰匯入為不可匯出的 `CryptoKey`
+#[wasm_bindgen]
+pub struct WebCryptoAesGcm {
+    key: CryptoKey,
+}
+
+#[wasm_bindgen]
+impl WebCryptoAesGcm {
+    /// 匯入 32 bytes 金鑰 (回傳 Promise)
+    #[wasm_bindgen(js_name = importKey)]
+    pub async fn import_key(key: Vec<u8>) -> Result<WebCryptoAesGcm, JsError> {
+        if key.len() != KEY_SIZE {
+            return Err(JsError::new(&format!("Key must be {} bytes, got {}", KEY_SIZE, key.len())));
+        }
+        let usages = Array::of2(&JsValue::from_str("encrypt"), &JsValue::from_str("decrypt"));
+        let promise = subtle()?
+            .import_key_with_str("raw", &Uint8Array::from(key.as_slice()), ALGORITHM, false, &usages)
+            .map_err(|e| js_error("Failed to import key", e))?;
+        let key = JsFuture::from(promise)
+            .await
+            .map_err(|e| js_error("Failed to import key", e))?;
+        Ok(Self { key: key.unchecked_into() })
+    }
+
+    /// 加密訊息，Promise 的結果為 `EncryptedMessage`
+    pub fn encrypt(&self, plaintext: Vec<u8>, aad: Option<Vec<u8>>) -> Promise {
+        let key = self.key.clone();
+        future_to_promise(async move {
+            let mut nonce = [0u8; NONCE_SIZE];
+            OsRng.fill_bytes(&mut nonce);
+
+            let promise = subtle()?
+                .encrypt_with_object_and_u8_array(&gcm_params(&nonce, aad.as_deref()), &key, &plaintext)
+                .map_err(|e| js_error("Encryption failed", e))?;
+            let ciphertext = JsFuture::from(promise)
+                .await
+                .map_err(|e| js_error("Encryption failed", e))?;
+            Ok(EncryptedMessage::from_parts(nonce.to_vec(), Uint8Array::new(&ciphertext).to_vec()).into())
+        })
+    }
+
+    /// 解密訊息，Promise 的結果為明文 `Uint8Array`；驗證失敗時 reject
+    pub fn decrypt(&self, encrypted: &EncryptedMessage, aad: Option<Vec<u8>>) -> Promise {
+        let key = self.key.clone();
+        let nonce = encrypted.nonce();
+        let ciphertext = encrypted.ciphertext();
+        future_to_promise(async move {
+            if nonce.len() != NONCE_SIZE {
+                return Err(JsError::new("Invalid nonce size").into());
+            }
+            let promise = subtle()?
+                .decrypt_with_object_and_u8_array(&gcm_params(&nonce, aad.as_deref()), &key, &ciphertext)
+                .map_err(|e| js_error("Decryption failed", e))?;
+            let plaintext = JsFuture::from(promise)
+                .await
+                .map_err(|_| JsError::new("Decryption failed"))?;
+            Ok(Uint8Array::new(&plaintext).into())
+        })
+    }
+}
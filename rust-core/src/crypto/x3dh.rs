@@ -10,13 +10,20 @@ use hkdf::Hkdf;
 use sha2::Sha256;
 use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 
-use super::keys::X25519KeyPair;
+use super::convert::{ed25519_private_to_x25519_bytes, ed25519_public_to_x25519_bytes};
+use super::device::PRIMARY_DEVICE_ID;
+use super::keys::{IdentityKeyPair, PreKeyBundle, SignedPreKeyRecord, X25519KeyPair};
+use super::ratchet::RatchetSession;
+use crate::storage::{
+    AsyncIdentityKeyStore, AsyncPreKeyStore, AsyncSessionStore, AsyncSignedPreKeyStore, IdentityKeyStore,
+    IdentityStatus, IdentityTrustStore, PreKeyStore, SessionStore, SignedPreKeyStore, Transactional,
+};
 
 const INFO: &[u8] = b"SafeTalk_X3DH";
 
 /// X3DH 發起者輸出
 #[wasm_bindgen]
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct X3DHSenderOutput {
     /// 共享密鑰 (32 bytes)
     shared_secret: Vec<u8>,
@@ -66,6 +73,12 @@ pub struct X3DHInitialMessage {
     ephemeral_key: Vec<u8>,
     /// 使用的一次性預金鑰 ID (如有)
     one_time_prekey_id: Option<u32>,
+    /// 發送者註冊 ID (如有)
+    #[serde(default)]
+    registration_id: Option<u32>,
+    /// 發送者裝置 ID (如有)
+    #[serde(default)]
+    device_id: Option<u32>,
 }
 
 #[wasm_bindgen]
@@ -85,6 +98,16 @@ impl X3DHInitialMessage {
         self.one_time_prekey_id
     }
 
+    #[wasm_bindgen(getter, js_name = registrationId)]
+    pub fn registration_id(&self) -> Option<u32> {
+        self.registration_id
+    }
+
+    #[wasm_bindgen(getter, js_name = deviceId)]
+    pub fn device_id(&self) -> Option<u32> {
+        self.device_id
+    }
+
     #[wasm_bindgen(js_name = toJson)]
     pub fn to_json(&self) -> Result<String, JsError> {
         serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
@@ -164,7 +187,7 @@ impl X3DH {
         let dh1 = sender_x25519.diffie_hellman(&recipient_spk);
 
         // DH2 = DH(EKa, IKb)
-        let ephemeral_secret = X25519SecretKey::from(Self::vec_to_32(&ephemeral.private_key_bytes())?);
+        let ephemeral_secret = X25519SecretKey::from(Self::vec_to_32(ephemeral.private_key_bytes().expose())?);
         let dh2 = ephemeral_secret.diffie_hellman(&recipient_identity_x25519);
 
         // DH3 = DH(EKa, SPKb)
@@ -202,7 +225,7 @@ impl X3DH {
         Ok(X3DHSenderOutput {
             shared_secret,
             ephemeral_public_key: ephemeral.public_key_bytes(),
-            ephemeral_private_key: ephemeral.private_key_bytes(),
+            ephemeral_private_key: ephemeral.private_key_bytes().expose().to_vec(),
             used_one_time_prekey_id: used_otpk_id,
         })
     }
@@ -286,6 +309,24 @@ impl X3DH {
             sender_identity_key: sender_identity_public.to_vec(),
             ephemeral_key: ephemeral_public.to_vec(),
             one_time_prekey_id,
+            registration_id: None,
+            device_id: None,
+        }
+    }
+
+    /// 建立帶有發送者註冊 ID 與裝置 ID 的初始訊息
+    #[wasm_bindgen(js_name = createInitialMessageForDevice)]
+    pub fn create_initial_message_for_device(
+        sender_identity_public: &[u8],
+        ephemeral_public: &[u8],
+        one_time_prekey_id: Option<u32>,
+        registration_id: u32,
+        device_id: u32,
+    ) -> X3DHInitialMessage {
+        X3DHInitialMessage {
+            registration_id: Some(registration_id),
+            device_id: Some(device_id),
+            ..Self::create_initial_message(sender_identity_public, ephemeral_public, one_time_prekey_id)
         }
     }
 
@@ -324,46 +365,14 @@ impl X3DH {
 
     /// Ed25519 私鑰轉 X25519 私鑰
     fn ed25519_to_x25519_private(ed_private: &[u8]) -> Result<X25519SecretKey, JsError> {
-        use sha2::{Sha512, Digest};
-
-        if ed_private.len() != 32 {
-            return Err(JsError::new("Invalid Ed25519 private key length"));
-        }
-
-        // 根據 RFC 8032，Ed25519 私鑰經過 SHA-512 雜湊後的前 32 位元組可用於 X25519
-        let mut hasher = Sha512::new();
-        hasher.update(ed_private);
-        let hash = hasher.finalize();
-
-        let mut x25519_bytes = [0u8; 32];
-        x25519_bytes.copy_from_slice(&hash[..32]);
-
-        // 清除位元 (根據 Curve25519 要求)
-        x25519_bytes[0] &= 248;
-        x25519_bytes[31] &= 127;
-        x25519_bytes[31] |= 64;
-
+        let x25519_bytes = ed25519_private_to_x25519_bytes(ed_private).map_err(|e| JsError::new(&e))?;
         Ok(X25519SecretKey::from(x25519_bytes))
     }
 
     /// Ed25519 公鑰轉 X25519 公鑰
     fn ed25519_to_x25519_public(ed_public: &[u8]) -> Result<X25519PublicKey, JsError> {
-        use curve25519_dalek::edwards::CompressedEdwardsY;
-
-        if ed_public.len() != 32 {
-            return Err(JsError::new("Invalid Ed25519 public key length"));
-        }
-
-        let mut pk_bytes = [0u8; 32];
-        pk_bytes.copy_from_slice(ed_public);
-
-        let compressed = CompressedEdwardsY(pk_bytes);
-        let edwards_point = compressed
-            .decompress()
-            .ok_or_else(|| JsError::new("Invalid Ed25519 public key"))?;
-
-        let montgomery_point = edwards_point.to_montgomery();
-        Ok(X25519PublicKey::from(montgomery_point.to_bytes()))
+        let x25519_bytes = ed25519_public_to_x25519_bytes(ed_public).map_err(|e| JsError::new(&e))?;
+        Ok(X25519PublicKey::from(x25519_bytes))
     }
 
     fn bytes_to_x25519_public(bytes: &[u8]) -> Result<X25519PublicKey, JsError> {
@@ -385,6 +394,326 @@ impl X3DH {
     }
 }
 
+/// 帶有身份檢查的會話建立結果
+///
+/// 身份公鑰變更時 (`Changed`) 不會產生任何金鑰材料，
+/// 需由使用者確認後以信任儲存的 `saveIdentity` 接受新身份再重試；
+/// 已撤銷 (`Revoked`) 的身份公鑰無法再建立會話
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct SessionEstablishment {
+    status: IdentityStatus,
+    sender_output: Option<X3DHSenderOutput>,
+    shared_secret: Option<Vec<u8>>,
+}
+
+#[wasm_bindgen]
+impl SessionEstablishment {
+    /// 身份公鑰檢查結果
+    #[wasm_bindgen(getter, js_name = identityStatus)]
+    pub fn identity_status(&self) -> IdentityStatus {
+        self.status
+    }
+
+    /// 身份公鑰是否已變更
+    #[wasm_bindgen(getter, js_name = identityChanged)]
+    pub fn identity_changed(&self) -> bool {
+        self.status == IdentityStatus::Changed
+    }
+
+    /// 發起者輸出 (僅發起者且身份未變更時存在)
+    #[wasm_bindgen(getter, js_name = senderOutput)]
+    pub fn sender_output(&self) -> Option<X3DHSenderOutput> {
+        self.sender_output.clone()
+    }
+
+    /// 共享密鑰 (身份未變更時存在)
+    #[wasm_bindgen(getter, js_name = sharedSecret)]
+    pub fn shared_secret(&self) -> Option<Vec<u8>> {
+        self.shared_secret.clone()
+    }
+}
+
+impl SessionEstablishment {
+    fn rejected(status: IdentityStatus) -> Self {
+        Self {
+            status,
+            sender_output: None,
+            shared_secret: None,
+        }
+    }
+
+    fn initiated(status: IdentityStatus, output: X3DHSenderOutput) -> Self {
+        Self {
+            status,
+            shared_secret: Some(output.shared_secret.clone()),
+            sender_output: Some(output),
+        }
+    }
+
+    fn responded(status: IdentityStatus, shared_secret: Vec<u8>) -> Self {
+        Self {
+            status,
+            sender_output: None,
+            shared_secret: Some(shared_secret),
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl X3DH {
+    /// 發起者：檢查對方身份後計算共享密鑰
+    ///
+    /// 第一次看到的身份公鑰會被記錄 (TOFU)；
+    /// 身份公鑰與記錄不同時回傳 `Changed`、已撤銷時回傳 `Revoked`，並中止金鑰交換
+    #[wasm_bindgen(js_name = initiatorCalculateTrusted)]
+    pub fn initiator_calculate_trusted(
+        trust_store: &mut IdentityTrustStore,
+        contact_id: &str,
+        sender_identity_private: &[u8],
+        recipient_bundle: &PreKeyBundle,
+        now: u64,
+    ) -> Result<SessionEstablishment, JsError> {
+        let status = trust_store.check_identity(contact_id, &recipient_bundle.identity_key);
+        if matches!(status, IdentityStatus::Changed | IdentityStatus::Revoked) {
+            return Ok(SessionEstablishment::rejected(status));
+        }
+
+        let output = Self::initiator_calculate(
+            sender_identity_private,
+            &recipient_bundle.identity_key,
+            &recipient_bundle.signed_pre_key.public_key,
+            &recipient_bundle.signed_pre_key.signature,
+            recipient_bundle.one_time_pre_key_public(),
+            recipient_bundle.one_time_pre_key_id(),
+        )?;
+        trust_store.save_identity(contact_id, &recipient_bundle.identity_key, now);
+        Ok(SessionEstablishment::initiated(status, output))
+    }
+
+    /// 接收者：檢查發送者身份後計算共享密鑰
+    #[wasm_bindgen(js_name = responderCalculateTrusted)]
+    pub fn responder_calculate_trusted(
+        trust_store: &mut IdentityTrustStore,
+        contact_id: &str,
+        recipient_identity_private: &[u8],
+        recipient_signed_prekey_private: &[u8],
+        recipient_one_time_prekey_private: Option<Vec<u8>>,
+        initial_message: &X3DHInitialMessage,
+        now: u64,
+    ) -> Result<SessionEstablishment, JsError> {
+        let status = trust_store.check_identity(contact_id, &initial_message.sender_identity_key);
+        if matches!(status, IdentityStatus::Changed | IdentityStatus::Revoked) {
+            return Ok(SessionEstablishment::rejected(status));
+        }
+
+        let shared_secret = Self::responder_calculate(
+            recipient_identity_private,
+            recipient_signed_prekey_private,
+            recipient_one_time_prekey_private,
+            &initial_message.sender_identity_key,
+            &initial_message.ephemeral_key,
+        )?;
+        trust_store.save_identity(contact_id, &initial_message.sender_identity_key, now);
+        Ok(SessionEstablishment::responded(status, shared_secret))
+    }
+}
+
+impl X3DH {
+    /// 發起者：完成 X3DH 並建立 Double Ratchet 會話 (不涉及儲存)
+    fn establish_as_initiator(
+        identity: &IdentityKeyPair,
+        recipient_bundle: &PreKeyBundle,
+    ) -> Result<(X3DHSenderOutput, RatchetSession), JsError> {
+        let output = Self::initiator_calculate(
+            identity.private_key_bytes().expose(),
+            &recipient_bundle.identity_key,
+            &recipient_bundle.signed_pre_key.public_key,
+            &recipient_bundle.signed_pre_key.signature,
+            recipient_bundle.one_time_pre_key_public(),
+            recipient_bundle.one_time_pre_key_id(),
+        )?;
+        let session = RatchetSession::init_as_alice(
+            &output.shared_secret,
+            &recipient_bundle.signed_pre_key.public_key,
+            &output.ephemeral_private_key,
+            &output.ephemeral_public_key,
+        )?;
+        Ok((output, session))
+    }
+
+    /// 接收者：完成 X3DH 並建立 Double Ratchet 會話 (不涉及儲存)
+    fn establish_as_responder(
+        identity: &IdentityKeyPair,
+        signed_pre_key: &SignedPreKeyRecord,
+        one_time_prekey_private: Option<Vec<u8>>,
+        initial_message: &X3DHInitialMessage,
+    ) -> Result<(Vec<u8>, RatchetSession), JsError> {
+        let signed_pre_key = signed_pre_key.key_pair();
+        let shared_secret = Self::responder_calculate(
+            identity.private_key_bytes().expose(),
+            signed_pre_key.private_key_bytes().expose(),
+            one_time_prekey_private,
+            &initial_message.sender_identity_key,
+            &initial_message.ephemeral_key,
+        )?;
+        let session = RatchetSession::init_as_bob(
+            &shared_secret,
+            signed_pre_key.private_key_bytes().expose(),
+            &signed_pre_key.public_key_bytes(),
+            &initial_message.ephemeral_key,
+        )?;
+        Ok((shared_secret, session))
+    }
+
+    /// 發起者：透過儲存介面完成 X3DH，建立並儲存 Double Ratchet 會話 (Rust 端使用)
+    ///
+    /// 身份檢查規則與 `initiatorCalculateTrusted` 相同；被拒絕時不會寫入任何資料
+    pub fn initiate_session<S: IdentityKeyStore + SessionStore>(
+        store: &mut S,
+        contact_id: &str,
+        device_id: u32,
+        recipient_bundle: &PreKeyBundle,
+        now: u64,
+    ) -> Result<SessionEstablishment, JsError> {
+        let status = store
+            .check_identity(contact_id, &recipient_bundle.identity_key)
+            .map_err(|e| JsError::new(&e))?;
+        if matches!(status, IdentityStatus::Changed | IdentityStatus::Revoked) {
+            return Ok(SessionEstablishment::rejected(status));
+        }
+
+        let identity = store.identity_key_pair().map_err(|e| JsError::new(&e))?;
+        let (output, session) = Self::establish_as_initiator(&identity, recipient_bundle)?;
+        store
+            .save_identity(contact_id, &recipient_bundle.identity_key, now)
+            .map_err(|e| JsError::new(&e))?;
+        store
+            .store_session(contact_id, device_id, &session)
+            .map_err(|e| JsError::new(&e))?;
+        Ok(SessionEstablishment::initiated(status, output))
+    }
+
+    /// 接收者：透過儲存介面處理初始訊息，建立並儲存 Double Ratchet 會話 (Rust 端使用)
+    ///
+    /// 一次性預金鑰在身份檢查通過、金鑰交換成功後才會被消耗，
+    /// 消耗預金鑰、記錄身份與儲存會話在同一個交易中提交；
+    /// 會話以初始訊息中的裝置 ID 儲存，未提供時視為主裝置
+    pub fn respond_session<S: IdentityKeyStore + SessionStore + PreKeyStore + SignedPreKeyStore + Transactional>(
+        store: &mut S,
+        contact_id: &str,
+        signed_pre_key_id: u32,
+        initial_message: &X3DHInitialMessage,
+        now: u64,
+    ) -> Result<SessionEstablishment, JsError> {
+        let status = store
+            .check_identity(contact_id, &initial_message.sender_identity_key)
+            .map_err(|e| JsError::new(&e))?;
+        if matches!(status, IdentityStatus::Changed | IdentityStatus::Revoked) {
+            return Ok(SessionEstablishment::rejected(status));
+        }
+
+        let identity = store.identity_key_pair().map_err(|e| JsError::new(&e))?;
+        let signed_pre_key = store
+            .load_signed_pre_key(signed_pre_key_id)
+            .map_err(|e| JsError::new(&e))?;
+        let one_time_prekey_private = match initial_message.one_time_prekey_id {
+            Some(id) => Some(store.load_pre_key(id).map_err(|e| JsError::new(&e))?),
+            None => None,
+        };
+
+        let (shared_secret, session) =
+            Self::establish_as_responder(&identity, &signed_pre_key, one_time_prekey_private, initial_message)?;
+        let device_id = initial_message.device_id.unwrap_or(PRIMARY_DEVICE_ID);
+        store
+            .atomic(|store| {
+                if let Some(id) = initial_message.one_time_prekey_id {
+                    store.consume_pre_key(id)?;
+                }
+                store.save_identity(contact_id, &initial_message.sender_identity_key, now)?;
+                store.store_session(contact_id, device_id, &session)
+            })
+            .map_err(|e| JsError::new(&e))?;
+        Ok(SessionEstablishment::responded(status, shared_secret))
+    }
+
+    /// `initiate_session` 的非同步儲存版本 (Rust 端使用)
+    pub async fn initiate_session_async<S: AsyncIdentityKeyStore + AsyncSessionStore>(
+        store: &mut S,
+        contact_id: &str,
+        device_id: u32,
+        recipient_bundle: &PreKeyBundle,
+        now: u64,
+    ) -> Result<SessionEstablishment, JsError> {
+        let status = store
+            .check_identity(contact_id, &recipient_bundle.identity_key)
+            .await
+            .map_err(|e| JsError::new(&e))?;
+        if matches!(status, IdentityStatus::Changed | IdentityStatus::Revoked) {
+            return Ok(SessionEstablishment::rejected(status));
+        }
+
+        let identity = store.identity_key_pair().await.map_err(|e| JsError::new(&e))?;
+        let (output, session) = Self::establish_as_initiator(&identity, recipient_bundle)?;
+        store
+            .save_identity(contact_id, &recipient_bundle.identity_key, now)
+            .await
+            .map_err(|e| JsError::new(&e))?;
+        store
+            .store_session(contact_id, device_id, &session)
+            .await
+            .map_err(|e| JsError::new(&e))?;
+        Ok(SessionEstablishment::initiated(status, output))
+    }
+
+    /// `respond_session` 的非同步儲存版本 (Rust 端使用)
+    ///
+    /// 非同步儲存無法跨呼叫交易，一次性預金鑰在金鑰交換成功後、寫入會話前才消耗
+    pub async fn respond_session_async<
+        S: AsyncIdentityKeyStore + AsyncSessionStore + AsyncPreKeyStore + AsyncSignedPreKeyStore,
+    >(
+        store: &mut S,
+        contact_id: &str,
+        signed_pre_key_id: u32,
+        initial_message: &X3DHInitialMessage,
+        now: u64,
+    ) -> Result<SessionEstablishment, JsError> {
+        let status = store
+            .check_identity(contact_id, &initial_message.sender_identity_key)
+            .await
+            .map_err(|e| JsError::new(&e))?;
+        if matches!(status, IdentityStatus::Changed | IdentityStatus::Revoked) {
+            return Ok(SessionEstablishment::rejected(status));
+        }
+
+        let identity = store.identity_key_pair().await.map_err(|e| JsError::new(&e))?;
+        let signed_pre_key = store
+            .load_signed_pre_key(signed_pre_key_id)
+            .await
+            .map_err(|e| JsError::new(&e))?;
+        let one_time_prekey_private = match initial_message.one_time_prekey_id {
+            Some(id) => Some(store.load_pre_key(id).await.map_err(|e| JsError::new(&e))?),
+            None => None,
+        };
+
+        let (shared_secret, session) =
+            Self::establish_as_responder(&identity, &signed_pre_key, one_time_prekey_private, initial_message)?;
+        if let Some(id) = initial_message.one_time_prekey_id {
+            store.consume_pre_key(id).await.map_err(|e| JsError::new(&e))?;
+        }
+        store
+            .save_identity(contact_id, &initial_message.sender_identity_key, now)
+            .await
+            .map_err(|e| JsError::new(&e))?;
+        let device_id = initial_message.device_id.unwrap_or(PRIMARY_DEVICE_ID);
+        store
+            .store_session(contact_id, device_id, &session)
+            .await
+            .map_err(|e| JsError::new(&e))?;
+        Ok(SessionEstablishment::responded(status, shared_secret))
+    }
+}
+
 /// 簽署預金鑰
 #[wasm_bindgen(js_name = signPreKey)]
 pub fn sign_pre_key(identity_private: &[u8], prekey_public: &[u8]) -> Result<Vec<u8>, JsError> {
@@ -404,7 +733,6 @@ pub fn sign_pre_key(identity_private: &[u8], prekey_public: &[u8]) -> Result<Vec
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::super::keys::IdentityKeyPair;
 
     #[test]
     fn test_x3dh_key_exchange() {
@@ -418,13 +746,13 @@ mod tests {
 
         // Bob 簽署預金鑰
         let bob_spk_signature = sign_pre_key(
-            &bob_identity.private_key_bytes(),
+            bob_identity.private_key_bytes().expose(),
             &bob_signed_prekey.public_key_bytes(),
         ).unwrap();
 
         // Alice 計算共享密鑰
         let alice_output = X3DH::initiator_calculate(
-            &alice_identity.private_key_bytes(),
+            alice_identity.private_key_bytes().expose(),
             &bob_identity.public_key_bytes(),
             &bob_signed_prekey.public_key_bytes(),
             &bob_spk_signature,
@@ -434,9 +762,9 @@ mod tests {
 
         // Bob 計算共享密鑰
         let bob_shared = X3DH::responder_calculate(
-            &bob_identity.private_key_bytes(),
-            &bob_signed_prekey.private_key_bytes(),
-            Some(bob_one_time_prekey.private_key_bytes()),
+            bob_identity.private_key_bytes().expose(),
+            bob_signed_prekey.private_key_bytes().expose(),
+            Some(bob_one_time_prekey.private_key_bytes().expose().to_vec()),
             &alice_identity.public_key_bytes(),
             &alice_output.ephemeral_public_key,
         ).unwrap();
@@ -454,13 +782,13 @@ mod tests {
 
         // Bob 簽署預金鑰
         let bob_spk_signature = sign_pre_key(
-            &bob_identity.private_key_bytes(),
+            bob_identity.private_key_bytes().expose(),
             &bob_signed_prekey.public_key_bytes(),
         ).unwrap();
 
         // Alice 計算（沒有 OTP）
         let alice_output = X3DH::initiator_calculate(
-            &alice_identity.private_key_bytes(),
+            alice_identity.private_key_bytes().expose(),
             &bob_identity.public_key_bytes(),
             &bob_signed_prekey.public_key_bytes(),
             &bob_spk_signature,
@@ -470,8 +798,8 @@ mod tests {
 
         // Bob 計算（沒有 OTP）
         let bob_shared = X3DH::responder_calculate(
-            &bob_identity.private_key_bytes(),
-            &bob_signed_prekey.private_key_bytes(),
+            bob_identity.private_key_bytes().expose(),
+            bob_signed_prekey.private_key_bytes().expose(),
             None,  // 沒有 OTP
             &alice_identity.public_key_bytes(),
             &alice_output.ephemeral_public_key,
@@ -490,7 +818,7 @@ mod tests {
         let identity = IdentityKeyPair::new();
 
         // 方法 1: Ed25519 私鑰 -> X25519 私鑰 -> X25519 公鑰
-        let x25519_private = X3DH::ed25519_to_x25519_private(&identity.private_key_bytes()).unwrap();
+        let x25519_private = X3DH::ed25519_to_x25519_private(identity.private_key_bytes().expose()).unwrap();
         let x25519_public_from_private = X25519PublicKey::from(&x25519_private);
 
         // 方法 2: Ed25519 公鑰 -> X25519 公鑰
@@ -507,6 +835,55 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_initiator_aborts_on_identity_change() {
+        use crate::storage::IdentityTrustStore;
+
+        let alice_identity = IdentityKeyPair::new();
+        let bob_identity = IdentityKeyPair::new();
+        let bundle = PreKeyBundle::from_components(
+            &bob_identity.public_key_bytes(),
+            1,
+            &X25519KeyPair::new().public_key_bytes(),
+            &[0u8; 64],
+            0,
+            None,
+            None,
+        );
+
+        let mut store = IdentityTrustStore::new();
+        store.save_identity("bob", &IdentityKeyPair::new().public_key_bytes(), 0);
+
+        let result = X3DH::initiator_calculate_trusted(
+            &mut store,
+            "bob",
+            alice_identity.private_key_bytes().expose(),
+            &bundle,
+            1,
+        ).unwrap();
+
+        assert!(result.identity_changed());
+        assert!(result.sender_output().is_none());
+        assert!(result.shared_secret().is_none());
+        // 信任儲存不會被自動覆寫
+        assert!(!store.is_trusted("bob", &bob_identity.public_key_bytes()));
+    }
+
+    #[test]
+    fn test_initial_message_device_fields() {
+        let message = X3DH::create_initial_message_for_device(&[1u8; 32], &[2u8; 32], Some(5), 1234, 2);
+        let json = message.to_json().unwrap();
+        let restored = X3DHInitialMessage::from_json(&json).unwrap();
+        assert_eq!(restored.registration_id(), Some(1234));
+        assert_eq!(restored.device_id(), Some(2));
+
+        // 舊版訊息沒有裝置欄位
+        let legacy = r#"{"sender_identity_key":[1],"ephemeral_key":[2],"one_time_prekey_id":null}"#;
+        let restored = X3DHInitialMessage::from_json(legacy).unwrap();
+        assert_eq!(restored.registration_id(), None);
+        assert_eq!(restored.device_id(), None);
+    }
+
     #[test]
     fn test_full_encryption_flow() {
         use super::super::ratchet::RatchetSession;
@@ -517,13 +894,13 @@ mod tests {
         let bob_signed_prekey = X25519KeyPair::new();
 
         let bob_spk_signature = sign_pre_key(
-            &bob_identity.private_key_bytes(),
+            bob_identity.private_key_bytes().expose(),
             &bob_signed_prekey.public_key_bytes(),
         ).unwrap();
 
         // X3DH
         let alice_x3dh = X3DH::initiator_calculate(
-            &alice_identity.private_key_bytes(),
+            alice_identity.private_key_bytes().expose(),
             &bob_identity.public_key_bytes(),
             &bob_signed_prekey.public_key_bytes(),
             &bob_spk_signature,
@@ -532,8 +909,8 @@ mod tests {
         ).unwrap();
 
         let bob_shared = X3DH::responder_calculate(
-            &bob_identity.private_key_bytes(),
-            &bob_signed_prekey.private_key_bytes(),
+            bob_identity.private_key_bytes().expose(),
+            bob_signed_prekey.private_key_bytes().expose(),
             None,
             &alice_identity.public_key_bytes(),
             &alice_x3dh.ephemeral_public_key,
@@ -553,7 +930,7 @@ mod tests {
 
         let mut bob_session = RatchetSession::init_as_bob(
             &bob_shared,
-            &bob_signed_prekey.private_key_bytes(),
+            bob_signed_prekey.private_key_bytes().expose(),
             &bob_signed_prekey.public_key_bytes(),
             &alice_x3dh.ephemeral_public_key,
         ).unwrap();
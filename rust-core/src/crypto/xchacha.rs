@@ -0,0 +1,143 @@
+//! XChaCha20-Poly1305 模組
+//!
+//! 24 bytes 的 nonce 可以安全地隨機產生：同一把長期金鑰加密大量密文時
+//! (備份、附件池等)，96-bit 隨機 nonce 的 AES-GCM 會逐漸有碰撞風險，
+//! 192-bit 隨機 nonce 則可以忽略
+//!
+//! 密文格式：nonce (24) || 密文 || 驗證標籤 (16)
+
+use wasm_bindgen::prelude::*;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use rand::{rngs::OsRng, RngCore};
+
+/// XChaCha20-Poly1305 nonce 長度
+pub const XCHACHA_NONCE_SIZE: usize = 24;
+const KEY_SIZE: usize = 32;
+const TAG_SIZE: usize = 16;
+
+/// XChaCha20-Poly1305 加密器
+#[wasm_bindgen]
+pub struct XChaChaCipher {
+    cipher: XChaCha20Poly1305,
+}
+
+impl XChaChaCipher {
+    /// 從金鑰建立加密器 (Rust 端使用)
+    pub fn from_key(key: &[u8]) -> Result<Self, String> {
+        if key.len() != KEY_SIZE {
+            return Err(format!("Key must be {} bytes, got {}", KEY_SIZE, key.len()));
+        }
+        let cipher = XChaCha20Poly1305::new_from_slice(key).map_err(|e| format!("Failed to create cipher: {}", e))?;
+        Ok(Self { cipher })
+    }
+
+    /// 以指定的 nonce 加密，回傳密文 || 驗證標籤 (Rust 端使用)
+    pub fn seal(&self, nonce: &[u8; XCHACHA_NONCE_SIZE], aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        self.cipher
+            .encrypt(XNonce::from_slice(nonce), Payload { msg: plaintext, aad })
+            .map_err(|e| format!("Encryption failed: {}", e))
+    }
+
+    /// 以隨機 nonce 加密，回傳 nonce || 密文 (Rust 端使用)
+    pub fn seal_random(&self, aad: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let mut nonce = [0u8; XCHACHA_NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let mut output = nonce.to_vec();
+        output.extend(self.seal(&nonce, aad, plaintext)?);
+        Ok(output)
+    }
+
+    /// 解密 nonce || 密文 (Rust 端使用)
+    pub fn open(&self, aad: &[u8], encrypted: &[u8]) -> Result<Vec<u8>, String> {
+        if encrypted.len() < XCHACHA_NONCE_SIZE + TAG_SIZE {
+            return Err("Invalid encrypted message: too short".to_string());
+        }
+        let (nonce, ciphertext) = encrypted.split_at(XCHACHA_NONCE_SIZE);
+        self.cipher
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|e| format!("Decryption failed: {}", e))
+    }
+}
+
+#[wasm_bindgen]
+impl XChaChaCipher {
+    /// 從金鑰建立加密器
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &[u8]) -> Result<XChaChaCipher, JsError> {
+        Self::from_key(key).map_err(|e| JsError::new(&e))
+    }
+
+    /// 加密訊息，回傳 nonce || 密文
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.seal_random(&[], plaintext).map_err(|e| JsError::new(&e))
+    }
+
+    /// 加密訊息 (附帶關聯資料)
+    #[wasm_bindgen(js_name = encryptWithAad)]
+    pub fn encrypt_with_aad(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.seal_random(aad, plaintext).map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密 nonce || 密文
+    pub fn decrypt(&self, encrypted: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.open(&[], encrypted).map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密訊息 (附帶關聯資料)
+    #[wasm_bindgen(js_name = decryptWithAad)]
+    pub fn decrypt_with_aad(&self, encrypted: &[u8], aad: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.open(aad, encrypted).map_err(|e| JsError::new(&e))
+    }
+}
+
+/// 快速加密函式 (回傳 nonce || 密文)
+#[wasm_bindgen(js_name = xchachaEncrypt)]
+pub fn xchacha_encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, JsError> {
+    XChaChaCipher::new(key)?.encrypt(plaintext)
+}
+
+/// 快速解密函式
+#[wasm_bindgen(js_name = xchachaDecrypt)]
+pub fn xchacha_decrypt(key: &[u8], encrypted: &[u8]) -> Result<Vec<u8>, JsError> {
+    XChaChaCipher::new(key)?.decrypt(encrypted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::to_hex;
+
+    #[test]
+    fn test_xchacha_draft_vector() {
+        // draft-irtf-cfrg-xchacha-03 §A.3.1
+        let key: Vec<u8> = (0x80..=0x9f).collect();
+        let nonce: [u8; XCHACHA_NONCE_SIZE] = core::array::from_fn(|i| 0x40 + i as u8);
+        let aad = [0x50, 0x51, 0x52, 0x53, 0xc0, 0xc1, 0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7];
+        let plaintext = b"Ladies and Gentlemen of the class of '99: If I could offer you only one tip for the future, sunscreen would be it.";
+
+        let cipher = XChaChaCipher::from_key(&key).unwrap();
+        let sealed = cipher.seal(&nonce, &aad, plaintext).unwrap();
+        assert_eq!(to_hex(&sealed[..16]), "bd6d179d3e83d43b9576579493c0e939");
+        assert_eq!(to_hex(&sealed[sealed.len() - TAG_SIZE..]), "c0875924c1c7987947deafd8780acf49");
+
+        let encrypted = [&nonce[..], &sealed].concat();
+        assert_eq!(cipher.open(&aad, &encrypted).unwrap(), plaintext);
+        assert!(cipher.open(b"other", &encrypted).is_err());
+    }
+
+    #[test]
+    fn test_xchacha_random_nonces() {
+        let cipher = XChaChaCipher::from_key(&[9u8; 32]).unwrap();
+        let first = cipher.seal_random(b"backup", b"same").unwrap();
+        let second = cipher.seal_random(b"backup", b"same").unwrap();
+        assert_eq!(first.len(), XCHACHA_NONCE_SIZE + 4 + TAG_SIZE);
+        assert_ne!(first[..XCHACHA_NONCE_SIZE], second[..XCHACHA_NONCE_SIZE]);
+        assert_eq!(cipher.open(b"backup", &second).unwrap(), b"same");
+
+        assert!(cipher.open(b"backup", &first[..XCHACHA_NONCE_SIZE + TAG_SIZE - 1]).is_err());
+        assert!(XChaChaCipher::from_key(&[0u8; 16]).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! XEdDSA 簽章模組
+//!
+//! 依照 Signal 的 XEdDSA 規格，讓 X25519 金鑰直接產生可用 Ed25519 驗證的簽章，
+//! 不需要另外維護一把 Ed25519 金鑰或做私鑰轉換
+
+use wasm_bindgen::prelude::*;
+use curve25519_dalek::{edwards::EdwardsPoint, montgomery::MontgomeryPoint, scalar::Scalar};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use rand::{rngs::OsRng, RngCore};
+use sha2::{Digest, Sha512};
+
+use super::keys::X25519KeyPair;
+
+/// hash1 前綴 = 2^256 - 2 (little-endian)，用於導出 nonce
+const HASH1_PREFIX: [u8; 32] = {
+    let mut prefix = [0xffu8; 32];
+    prefix[0] = 0xfe;
+    prefix
+};
+
+/// 2^255 - 19 (little-endian)
+const FIELD_PRIME: [u8; 32] = {
+    let mut p = [0xffu8; 32];
+    p[0] = 0xed;
+    p[31] = 0x7f;
+    p
+};
+
+/// X25519 私鑰 clamping
+fn clamp(mut k: [u8; 32]) -> [u8; 32] {
+    k[0] &= 248;
+    k[31] &= 127;
+    k[31] |= 64;
+    k
+}
+
+/// u 座標是否小於 p (little-endian 比較)
+fn is_reduced(u: &[u8; 32]) -> bool {
+    for i in (0..32).rev() {
+        if u[i] != FIELD_PRIME[i] {
+            return u[i] < FIELD_PRIME[i];
+        }
+    }
+    false
+}
+
+/// 由 X25519 私鑰計算 Edwards 金鑰對 (A 的符號位元固定為 0)
+fn calculate_key_pair(x25519_private: &[u8; 32]) -> (Scalar, [u8; 32]) {
+    let k = Scalar::from_bytes_mod_order(clamp(*x25519_private));
+    let mut public = EdwardsPoint::mul_base(&k).compress().to_bytes();
+    let a = if public[31] & 0x80 != 0 { -k } else { k };
+    public[31] &= 0x7f;
+    (a, public)
+}
+
+/// 使用 X25519 私鑰產生 XEdDSA 簽章
+///
+/// `random` 為 64 bytes 隨機值 Z
+pub fn xeddsa_sign(x25519_private: &[u8; 32], message: &[u8], random: &[u8; 64]) -> [u8; 64] {
+    let (a, public) = calculate_key_pair(x25519_private);
+
+    let mut hasher = Sha512::new();
+    hasher.update(HASH1_PREFIX);
+    hasher.update(a.as_bytes());
+    hasher.update(message);
+    hasher.update(random);
+    let r = Scalar::from_bytes_mod_order_wide(&hasher.finalize().into());
+    let big_r = EdwardsPoint::mul_base(&r).compress().to_bytes();
+
+    let mut hasher = Sha512::new();
+    hasher.update(big_r);
+    hasher.update(public);
+    hasher.update(message);
+    let h = Scalar::from_bytes_mod_order_wide(&hasher.finalize().into());
+
+    let s = r + h * a;
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&big_r);
+    signature[32..].copy_from_slice(s.as_bytes());
+    signature
+}
+
+/// 使用 X25519 公鑰驗證 XEdDSA 簽章
+///
+/// 相容 libsignal 的舊格式：S 最高位元攜帶 Edwards 公鑰符號位元，
+/// 本模組產生的簽章該位元恆為 0
+pub fn xeddsa_verify(x25519_public: &[u8; 32], message: &[u8], signature: &[u8; 64]) -> bool {
+    if !is_reduced(x25519_public) {
+        return false;
+    }
+
+    let sign_bit = signature[63] >> 7;
+    let mut signature = *signature;
+    signature[63] &= 0x7f;
+    if signature[63] & 0xe0 != 0 {
+        return false;
+    }
+
+    // 轉換為 Edwards 公鑰後，以一般 Ed25519 驗證
+    let edwards = match MontgomeryPoint(*x25519_public).to_edwards(sign_bit) {
+        Some(point) => point,
+        None => return false,
+    };
+    let verifying_key = match VerifyingKey::from_bytes(&edwards.compress().to_bytes()) {
+        Ok(key) => key,
+        Err(_) => return false,
+    };
+    verifying_key
+        .verify(message, &Signature::from_bytes(&signature))
+        .is_ok()
+}
+
+#[wasm_bindgen]
+impl X25519KeyPair {
+    /// 使用此 X25519 金鑰產生 XEdDSA 簽章
+    #[wasm_bindgen(js_name = xeddsaSign)]
+    pub fn xeddsa_sign(&self, message: &[u8]) -> Vec<u8> {
+        let mut private = [0u8; 32];
+        private.copy_from_slice(self.private_key_bytes().expose());
+        let mut random = [0u8; 64];
+        OsRng.fill_bytes(&mut random);
+        xeddsa_sign(&private, message, &random).to_vec()
+    }
+
+    /// 使用 X25519 公鑰驗證 XEdDSA 簽章
+    #[wasm_bindgen(js_name = xeddsaVerify)]
+    pub fn xeddsa_verify(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+        if public_key.len() != 32 || signature.len() != 64 {
+            return false;
+        }
+        let mut pk_bytes = [0u8; 32];
+        pk_bytes.copy_from_slice(public_key);
+        let mut sig_bytes = [0u8; 64];
+        sig_bytes.copy_from_slice(signature);
+        xeddsa_verify(&pk_bytes, message, &sig_bytes)
+    }
+
+    /// 取得 XEdDSA 對應的 Ed25519 公鑰 (符號位元為 0)
+    ///
+    /// 可用一般 Ed25519 實作驗證 XEdDSA 簽章
+    #[wasm_bindgen(js_name = xeddsaPublicKey)]
+    pub fn xeddsa_public_key(&self) -> Vec<u8> {
+        let mut private = [0u8; 32];
+        private.copy_from_slice(self.private_key_bytes().expose());
+        calculate_key_pair(&private).1.to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::hex;
+    use super::super::keys::IdentityKeyPair;
+
+    #[test]
+    fn test_libsignal_signature_vector() {
+        // libsignal-protocol-c test_curve25519_signature
+        let private = hex("c097248412e58bf05df487968205132794178e367637f5818f81e0e6ce73e865");
+        let public = hex("ab7e717d4a163b7d9a1d8071dfe9dcf8cdcd1cea3339b6356be84d887e322c64");
+        let message = hex("05edce9d9c415ca78cb7252e72c2c4a554d3eb29485a0e1d503118d1a82d99fb4a");
+        let signature = hex(
+            "5de88ca9a89b4a115da79109c67c9c7464a3e4180274f1cb8c63c2984e286dfb\
+             ede82deb9dcd9fae0bfbb821569b3d9001bd8130cd11d486cef047bd60b86e88",
+        );
+
+        let keypair = X25519KeyPair::from_bytes(&private).unwrap();
+        assert_eq!(keypair.public_key_bytes(), public);
+        assert!(X25519KeyPair::xeddsa_verify(&public, &message, &signature));
+
+        let mut modified = signature.clone();
+        for i in 0..modified.len() {
+            modified[i] ^= 0x01;
+            assert!(!X25519KeyPair::xeddsa_verify(&public, &message, &modified));
+            modified[i] ^= 0x01;
+        }
+    }
+
+    #[test]
+    fn test_xeddsa_sign_verify() {
+        let keypair = X25519KeyPair::new();
+        let message = b"signed prekey";
+        let signature = keypair.xeddsa_sign(message);
+
+        assert!(X25519KeyPair::xeddsa_verify(&keypair.public_key_bytes(), message, &signature));
+        assert!(!X25519KeyPair::xeddsa_verify(&keypair.public_key_bytes(), b"other", &signature));
+
+        // 可用一般 Ed25519 驗證
+        assert!(IdentityKeyPair::verify_signature(&keypair.xeddsa_public_key(), message, &signature));
+    }
+}
@@ -3,25 +3,235 @@ use wasm_bindgen::prelude::*;
 pub mod crypto;
 pub mod storage;
 pub mod network;
+#[cfg(test)]
+mod test_util;
 
 // 重新導出常用類型供 WASM 使用
 pub use crypto::{
     IdentityKeyPair,
     X25519KeyPair,
+    PreKeyBundle,
     X3DH,
     X3DHSenderOutput,
     X3DHInitialMessage,
+    SessionEstablishment,
     RatchetSession,
     RatchetMessage,
+    ImportedLibsignalSession,
+    OlmSession,
+    OlmMessage,
+    OlmPreKeyMessage,
     AesGcmCipher,
+    StreamCipher,
+    Attachment,
+    EncryptedAttachment,
+    AttachmentPointer,
+    AttachmentPart,
+    StickerPack,
+    StickerManifest,
+    StickerInfo,
+    EncryptedSticker,
+    StickerPackShare,
+    StickerReference,
+    CipherSuite,
+    XChaChaCipher,
+    WrappedKey,
+    WrappedKeyKind,
+    AadBuilder,
+    Hasher,
+    HashAlgorithm,
+    MacAlgorithm,
+    PasswordKdf,
+    DerivedPasswordKey,
+    SecretBytes,
+    Compression,
     EncryptedMessage,
+    OneTimePreKeyPool,
+    Fingerprint,
+    ScanResult,
+    SasVerification,
+    PrehashedMessage,
+    MnemonicBackup,
+    KeyDerivation,
+    ProvisioningSession,
+    ProvisionMessage,
+    SyncMessage,
+    SyncEnvelope,
+    DeviceCertificate,
+    KeyTransparencyClient,
+    DiscoveryQuery,
+    UsernameProof,
+    OpaqueRegistration,
+    OpaqueLogin,
+    PinHash,
+    SvrChallenge,
+    SvrRecord,
+    SecretShare,
+    RistrettoKeyPair,
+    RistrettoScalar,
+    RistrettoPoint,
+    IdentityAttestationChallenge,
+    PasskeyCredential,
+    KeyLifecycle,
+    ManagedKeyKind,
+    RevocationCertificate,
+    RevocationReason,
+    KeyUpdate,
+    OpenPgpIdentity,
+    HpkeAead,
+    SignedPreKeyRecord,
     aes_encrypt,
     aes_decrypt,
     aes_decrypt_bytes,
     sign_pre_key,
     create_pre_key_bundle_json,
+    validate_pre_key_bundle_json,
+    verify_signatures_batch,
+    split_secret,
+    combine_shares,
+    ed25519_public_to_x25519,
+    ed25519_private_to_x25519,
+    did_key_from_public_key,
+    resolve_did_key,
+    did_document_json,
+    verify_jws,
+    verify_jwt,
+    hpke_seal_js,
+    hpke_open_js,
+    encrypt_to_identity,
+    decrypt_from_identity,
+    supported_cipher_suites,
+    negotiate_cipher_suite,
+    xchacha_encrypt,
+    xchacha_decrypt,
+    padme_pad,
+    padme_unpad,
+    derive_attachment_subkey,
+    encrypt_legacy_attachment,
+    decrypt_legacy_attachment,
+    hkdf_derive,
+    hkdf_extract,
+    hkdf_expand,
+    hmac_sha256,
+    hmac_verify,
+    sha256,
+    sha512,
+    blake3,
+    compute_mac,
+    verify_mac,
+    derive_key_from_password,
+    derive_key_from_encoded,
+    constant_time_equals,
+    age_encrypt,
+    age_decrypt,
 };
 
+#[cfg(feature = "p256")]
+pub use crypto::{P256KeyPair, P256SigningKeyPair};
+
+#[cfg(feature = "webcrypto")]
+pub use crypto::WebCryptoAesGcm;
+
+pub use storage::{
+    IdentityStatus,
+    IdentityTrustStore,
+    KeyStore,
+    SessionManager,
+    KeyStoreSnapshot,
+    InMemoryProtocolStore,
+    IdentityKeyStore,
+    SessionStore,
+    PreKeyStore,
+    SignedPreKeyStore,
+    StoredMessage,
+    MessageStore,
+    InMemoryMessageStore,
+    RetentionPolicy,
+    StorageKey,
+    StorageLock,
+    KeyRotationProgress,
+    StorageVerification,
+    CorruptRecord,
+    RepairAction,
+    InMemorySearchIndex,
+    BlobStore,
+    BlobWriter,
+    BlobReader,
+    InMemoryBlobStore,
+    KeyValueStore,
+    InMemoryKeyValueStore,
+    AvatarCache,
+    SettingsStore,
+    InMemorySettingsStore,
+    ScheduledMessage,
+    RetryPolicy,
+    OutboxStore,
+    InMemoryOutbox,
+    SelfSession,
+    Metric,
+    MetricsStore,
+    InMemoryMetrics,
+    Contact,
+    ContactStore,
+    VerificationState,
+    InMemoryContactStore,
+    Group,
+    GroupMember,
+    GroupRole,
+    GroupStore,
+    InMemoryGroupStore,
+    Transactional,
+    StoreEvent,
+    StoreEventKind,
+    StoreObservers,
+    QuotaTracker,
+    QuotaPolicy,
+    PruneCandidate,
+    PruneCategory,
+    PruneTarget,
+    BackupWriter,
+    BackupReader,
+    BackupSecret,
+    BackupRecord,
+    RestoredBackup,
+    BackupManifest,
+    BackupCheckpoint,
+    BackupExport,
+    ChatExportWriter,
+    ChatExportReader,
+    ChatExportRecord,
+    ChatAttachment,
+    ImportedChat,
+    SignalBackupReader,
+    ImportedSignalBackup,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use storage::FileBlobStore;
+
+#[cfg(feature = "indexeddb")]
+pub use storage::IndexedDbStore;
+
+#[cfg(feature = "opfs")]
+pub use storage::OpfsBlobStore;
+
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+pub use storage::SqliteStore;
+
+pub use network::{
+    Envelope,
+    MessageType,
+    EnvelopeCompatibility,
+    ConnectionState,
+    ReconnectPolicy,
+    supported_protocol_versions,
+    negotiate_protocol_version,
+    check_envelope_compatibility,
+};
+
+#[cfg(feature = "websocket")]
+pub use network::WebSocketTransport;
+
 #[wasm_bindgen(start)]
 pub fn init() {
     // 設定 panic hook 以便在瀏覽器 console 顯示錯誤
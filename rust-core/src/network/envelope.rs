@@ -0,0 +1,349 @@
+//! 訊息信封模組
+//!
+//! 所有送上線路的訊息都包在版本化的 `Envelope` 中，格式固定、不依賴 bincode 的結構佈局，
+//! 不同版本的客戶端可以互通，無法互通時明確回報而不是解析出錯誤的資料：
+//!
+//! `protocol version (1) || message type (1) || header length (2) || header || payload`
+//!
+//! header (版本 1)：`device id (4) || timestamp (8, Unix 毫秒) || sender length (2) || sender (UTF-8)`
+//!
+//! 整數皆為 big-endian。同一個協定版本內只能在 header 尾端新增欄位，舊版本依 header length
+//! 略過無法辨識的欄位；不相容的變更須提高協定版本。雙方以 `negotiateProtocolVersion`
+//! 選出共同支援的最高版本，收到的信封先以 `checkEnvelopeCompatibility` 判斷能否處理
+//!
+//! 無法辨識的訊息類型照常解析，由呼叫端決定忽略或回報
+
+use wasm_bindgen::prelude::*;
+
+/// 目前的協定版本
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// 仍能解析的最舊協定版本
+pub const MIN_PROTOCOL_VERSION: u8 = 1;
+
+/// 協定版本、訊息類型與 header length
+const ENVELOPE_PREFIX_SIZE: usize = 4;
+/// 版本 1 header 的固定欄位 (不含 sender)
+const HEADER_FIXED_SIZE: usize = 4 + 8 + 2;
+
+/// 訊息類型 (以單一位元組記錄在信封中)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MessageType {
+    /// 既有會話的 ratchet 訊息
+    Ratchet = 1,
+    /// X3DH 初始訊息 (含第一則 ratchet 訊息)
+    PreKey = 2,
+    /// 自己裝置之間的同步訊息
+    Sync = 3,
+    /// Olm 預金鑰訊息
+    OlmPreKey = 4,
+    /// Olm 一般訊息
+    Olm = 5,
+    /// 送達 / 已讀回條
+    Receipt = 6,
+}
+
+impl From<MessageType> for u8 {
+    fn from(message_type: MessageType) -> u8 {
+        message_type as u8
+    }
+}
+
+impl TryFrom<u8> for MessageType {
+    type Error = String;
+
+    fn try_from(byte: u8) -> Result<Self, String> {
+        match byte {
+            1 => Ok(MessageType::Ratchet),
+            2 => Ok(MessageType::PreKey),
+            3 => Ok(MessageType::Sync),
+            4 => Ok(MessageType::OlmPreKey),
+            5 => Ok(MessageType::Olm),
+            6 => Ok(MessageType::Receipt),
+            _ => Err(format!("Unknown message type: {}", byte)),
+        }
+    }
+}
+
+/// 信封能否由本端處理
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnvelopeCompatibility {
+    Compatible = 0,
+    /// 對方的協定版本過舊，對方需要更新
+    PeerOutdated = 1,
+    /// 對方的協定版本較新，本端需要更新
+    UpgradeRequired = 2,
+    /// 不是信封 (長度不足)
+    Malformed = 3,
+}
+
+/// 協定版本的相容性
+fn version_compatibility(version: u8) -> EnvelopeCompatibility {
+    if version < MIN_PROTOCOL_VERSION {
+        EnvelopeCompatibility::PeerOutdated
+    } else if version > PROTOCOL_VERSION {
+        EnvelopeCompatibility::UpgradeRequired
+    } else {
+        EnvelopeCompatibility::Compatible
+    }
+}
+
+/// 本端支援的協定版本 (由新到舊，用於公告給對方)
+pub fn supported_versions() -> Vec<u8> {
+    (MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).rev().collect()
+}
+
+/// 選出雙方都支援的最高協定版本 (Rust 端使用)
+pub fn negotiate_version(remote: &[u8]) -> Option<u8> {
+    supported_versions().into_iter().find(|version| remote.contains(version))
+}
+
+/// 信封的相容性 (只讀取協定版本，不解析內容)
+pub fn envelope_compatibility(bytes: &[u8]) -> EnvelopeCompatibility {
+    match bytes.first() {
+        Some(_) if bytes.len() < ENVELOPE_PREFIX_SIZE => EnvelopeCompatibility::Malformed,
+        Some(&version) => version_compatibility(version),
+        None => EnvelopeCompatibility::Malformed,
+    }
+}
+
+/// 版本化的訊息信封
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Envelope {
+    version: u8,
+    /// 訊息類型位元組 (可能是本端無法辨識的類型)
+    message_type: u8,
+    sender: String,
+    device_id: u32,
+    timestamp: u64,
+    payload: Vec<u8>,
+}
+
+impl Envelope {
+    /// 序列化為線路格式
+    pub fn encode(&self) -> Result<Vec<u8>, String> {
+        let sender = self.sender.as_bytes();
+        let sender_len = u16::try_from(sender.len()).map_err(|_| "Sender address is too long".to_string())?;
+        let header_len = u16::try_from(HEADER_FIXED_SIZE + sender.len()).map_err(|_| "Envelope header is too long".to_string())?;
+
+        let mut bytes = Vec::with_capacity(ENVELOPE_PREFIX_SIZE + header_len as usize + self.payload.len());
+        bytes.push(self.version);
+        bytes.push(self.message_type);
+        bytes.extend_from_slice(&header_len.to_be_bytes());
+        bytes.extend_from_slice(&self.device_id.to_be_bytes());
+        bytes.extend_from_slice(&self.timestamp.to_be_bytes());
+        bytes.extend_from_slice(&sender_len.to_be_bytes());
+        bytes.extend_from_slice(sender);
+        bytes.extend_from_slice(&self.payload);
+        Ok(bytes)
+    }
+
+    /// 解析線路格式，協定版本不相容時回傳說明原因的錯誤
+    pub fn decode(bytes: &[u8]) -> Result<Self, String> {
+        match envelope_compatibility(bytes) {
+            EnvelopeCompatibility::Compatible => {}
+            EnvelopeCompatibility::Malformed => return Err("Envelope too short".to_string()),
+            EnvelopeCompatibility::PeerOutdated => {
+                return Err(format!("Peer protocol version {} is no longer supported (minimum {})", bytes[0], MIN_PROTOCOL_VERSION))
+            }
+            EnvelopeCompatibility::UpgradeRequired => {
+                return Err(format!("Peer protocol version {} is newer than supported version {}", bytes[0], PROTOCOL_VERSION))
+            }
+        }
+        let header_len = u16::from_be_bytes([bytes[2], bytes[3]]) as usize;
+        let header = bytes
+            .get(ENVELOPE_PREFIX_SIZE..ENVELOPE_PREFIX_SIZE + header_len)
+            .ok_or_else(|| "Envelope header truncated".to_string())?;
+        if header.len() < HEADER_FIXED_SIZE {
+            return Err("Envelope header too short".to_string());
+        }
+        let (device_id, rest) = header.split_at(4);
+        let (timestamp, rest) = rest.split_at(8);
+        let (sender_len, rest) = rest.split_at(2);
+        let sender_len = u16::from_be_bytes([sender_len[0], sender_len[1]]) as usize;
+        // sender 之後的欄位由較新的同版本客戶端加入，略過
+        let sender = rest.get(..sender_len).ok_or_else(|| "Envelope sender truncated".to_string())?;
+
+        Ok(Self {
+            version: bytes[0],
+            message_type: bytes[1],
+            sender: String::from_utf8(sender.to_vec()).map_err(|_| "Envelope sender is not valid UTF-8".to_string())?,
+            device_id: u32::from_be_bytes(device_id.try_into().expect("4-byte slice")),
+            timestamp: u64::from_be_bytes(timestamp.try_into().expect("8-byte slice")),
+            payload: bytes[ENVELOPE_PREFIX_SIZE + header_len..].to_vec(),
+        })
+    }
+
+    /// 以協商出的協定版本送出 (Rust 端使用)
+    pub fn set_version(&mut self, version: u8) -> Result<(), String> {
+        if version_compatibility(version) != EnvelopeCompatibility::Compatible {
+            return Err(format!("Unsupported protocol version: {}", version));
+        }
+        self.version = version;
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl Envelope {
+    /// 以目前的協定版本建立信封，`timestamp` 為 Unix 毫秒
+    #[wasm_bindgen(constructor)]
+    pub fn new(message_type: MessageType, sender: &str, device_id: u32, timestamp: u64, payload: &[u8]) -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            message_type: message_type.into(),
+            sender: sender.to_string(),
+            device_id,
+            timestamp,
+            payload: payload.to_vec(),
+        }
+    }
+
+    /// 改以協商出的協定版本 (`negotiateProtocolVersion`) 送出
+    #[wasm_bindgen(js_name = setVersion)]
+    pub fn set_version_js(&mut self, version: u8) -> Result<(), JsError> {
+        self.set_version(version).map_err(|e| JsError::new(&e))
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// 訊息類型，本端無法辨識時為 undefined (見 `rawMessageType`)
+    #[wasm_bindgen(getter, js_name = messageType)]
+    pub fn message_type(&self) -> Option<MessageType> {
+        MessageType::try_from(self.message_type).ok()
+    }
+
+    /// 訊息類型位元組
+    #[wasm_bindgen(getter, js_name = rawMessageType)]
+    pub fn raw_message_type(&self) -> u8 {
+        self.message_type
+    }
+
+    /// 發送者位址
+    #[wasm_bindgen(getter)]
+    pub fn sender(&self) -> String {
+        self.sender.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = deviceId)]
+    pub fn device_id(&self) -> u32 {
+        self.device_id
+    }
+
+    /// 發送時間 (Unix 毫秒)
+    #[wasm_bindgen(getter)]
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        self.encode().map_err(|e| JsError::new(&e))
+    }
+
+    /// 解析信封，協定版本不相容時拋出說明原因的錯誤 (可先以 `checkEnvelopeCompatibility` 判斷)
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<Envelope, JsError> {
+        Self::decode(bytes).map_err(|e| JsError::new(&e))
+    }
+}
+
+/// 本端支援的協定版本 (由新到舊，用於公告給對方)
+#[wasm_bindgen(js_name = supportedProtocolVersions)]
+pub fn supported_protocol_versions() -> Vec<u8> {
+    supported_versions()
+}
+
+/// 協商協定版本：雙方都支援的最高版本，沒有時回傳 undefined
+#[wasm_bindgen(js_name = negotiateProtocolVersion)]
+pub fn negotiate_protocol_version(remote: &[u8]) -> Option<u8> {
+    negotiate_version(remote)
+}
+
+/// 信封能否由本端處理 (只讀取協定版本)
+#[wasm_bindgen(js_name = checkEnvelopeCompatibility)]
+pub fn check_envelope_compatibility(bytes: &[u8]) -> EnvelopeCompatibility {
+    envelope_compatibility(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_roundtrip() {
+        let envelope = Envelope::new(MessageType::PreKey, "alice@example.com", 3, 1_700_000_000_000, b"ciphertext");
+        let bytes = envelope.encode().unwrap();
+        assert_eq!(&bytes[..4], &[PROTOCOL_VERSION, 2, 0, (HEADER_FIXED_SIZE + 17) as u8]);
+        assert_eq!(envelope_compatibility(&bytes), EnvelopeCompatibility::Compatible);
+
+        let decoded = Envelope::decode(&bytes).unwrap();
+        assert_eq!(decoded, envelope);
+        assert_eq!(decoded.message_type(), Some(MessageType::PreKey));
+        assert_eq!(decoded.sender(), "alice@example.com");
+        assert_eq!(decoded.device_id(), 3);
+        assert_eq!(decoded.timestamp(), 1_700_000_000_000);
+        assert_eq!(decoded.payload(), b"ciphertext");
+
+        for byte in 1..=6 {
+            assert_eq!(u8::from(MessageType::try_from(byte).unwrap()), byte);
+        }
+    }
+
+    #[test]
+    fn test_envelope_forward_compatibility() {
+        let envelope = Envelope::new(MessageType::Ratchet, "bob", 1, 42, b"body");
+        let mut bytes = envelope.encode().unwrap();
+
+        // 同版本的較新客戶端在 header 尾端加入欄位、使用新的訊息類型
+        let header_len = u16::from_be_bytes([bytes[2], bytes[3]]) + 3;
+        bytes[1] = 200;
+        bytes[2..4].copy_from_slice(&header_len.to_be_bytes());
+        let payload_start = ENVELOPE_PREFIX_SIZE + header_len as usize - 3;
+        bytes.splice(payload_start..payload_start, [9, 9, 9]);
+        let decoded = Envelope::decode(&bytes).unwrap();
+        assert_eq!(decoded.message_type(), None);
+        assert_eq!(decoded.raw_message_type(), 200);
+        assert_eq!(decoded.sender(), "bob");
+        assert_eq!(decoded.payload(), b"body");
+
+        // 不相容的版本明確回報
+        bytes[0] = PROTOCOL_VERSION + 1;
+        assert_eq!(envelope_compatibility(&bytes), EnvelopeCompatibility::UpgradeRequired);
+        assert!(Envelope::decode(&bytes).unwrap_err().contains("newer"));
+        bytes[0] = 0;
+        assert_eq!(envelope_compatibility(&bytes), EnvelopeCompatibility::PeerOutdated);
+        assert!(Envelope::decode(&bytes).unwrap_err().contains("no longer supported"));
+        assert_eq!(envelope_compatibility(&[PROTOCOL_VERSION, 1]), EnvelopeCompatibility::Malformed);
+        assert_eq!(envelope_compatibility(&[]), EnvelopeCompatibility::Malformed);
+
+        // 截斷的信封
+        let bytes = envelope.encode().unwrap();
+        assert!(Envelope::decode(&bytes[..10]).is_err());
+        assert!(Envelope::decode(&[PROTOCOL_VERSION, 1, 0, 2, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn test_protocol_negotiation() {
+        assert_eq!(supported_versions(), vec![PROTOCOL_VERSION]);
+        assert_eq!(negotiate_version(&[PROTOCOL_VERSION + 1, PROTOCOL_VERSION]), Some(PROTOCOL_VERSION));
+        assert_eq!(negotiate_version(&[PROTOCOL_VERSION + 1]), None);
+        assert_eq!(negotiate_version(&[]), None);
+
+        let mut envelope = Envelope::new(MessageType::Sync, "carol", 2, 0, &[]);
+        envelope.set_version(PROTOCOL_VERSION).unwrap();
+        assert!(envelope.set_version(PROTOCOL_VERSION + 1).is_err());
+        assert_eq!(Envelope::decode(&envelope.encode().unwrap()).unwrap().payload(), Vec::<u8>::new());
+    }
+}
@@ -1,11 +1,26 @@
 //! 網路模組
 //!
 //! 包含：
-//! - 訊息格式定義
+//! - 版本化訊息信封與協定版本協商
+//! - 傳輸層共用型別 (重新連線政策、連線狀態)
+//! - WebSocket 傳輸 (自動重新連線、指數退避、keepalive，feature = "websocket")
 //! - 控制指令定義
 //!
 //! TODO: Phase 2 實作
 
+pub mod envelope;
+pub mod transport;
+#[cfg(all(feature = "websocket", target_arch = "wasm32"))]
+pub mod websocket;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+pub mod websocket_native;
+
+pub use envelope::*;
+pub use transport::*;
+#[cfg(all(feature = "websocket", target_arch = "wasm32"))]
+pub use websocket::*;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+pub use websocket_native::*;
+
 // 暫時註解掉未實作的模組
-// pub mod message;
 // pub mod command;
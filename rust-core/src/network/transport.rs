@@ -0,0 +1,309 @@
+//! 傳輸層共用模組
+//!
+//! WebSocket 傳輸 (見 `websocket` 模組) 的重新連線政策與連線狀態：
+//! - 斷線後以指數退避重新連線，延遲再減去隨機抖動，大量客戶端不會在伺服器恢復時同時重連
+//! - 連線成功後重設退避
+//! - 連線閒置 `keepalive_interval_ms` 後送出 keepalive，`keepalive_timeout_ms` 內未收到任何資料時視為斷線；
+//!   瀏覽器改為 keepalive 在逾時內仍未離開傳送緩衝時才視為斷線
+//! - 未連線時送出的訊息先排入佇列，連線後依序送出
+
+#[cfg(any(feature = "websocket", test))]
+use std::collections::VecDeque;
+
+#[cfg(any(feature = "websocket", test))]
+use rand::Rng;
+use wasm_bindgen::prelude::*;
+
+/// 未連線時最多排入佇列的訊息數
+#[cfg(any(feature = "websocket", test))]
+pub(crate) const MAX_QUEUED_MESSAGES: usize = 1024;
+
+/// 連線狀態
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// 第一次連線中
+    Connecting = 0,
+    /// 已連線
+    Open = 1,
+    /// 斷線後等待重新連線 (或重新連線中)
+    Reconnecting = 2,
+    /// 已關閉 (呼叫 `close` 或超過重新連線次數上限)，不再重新連線
+    Closed = 3,
+}
+
+/// 重新連線與 keepalive 政策
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReconnectPolicy {
+    initial_backoff_ms: u64,
+    max_backoff_ms: u64,
+    /// 抖動比例 (0 ~ 1)
+    jitter: f64,
+    /// 連續失敗的次數上限，0 為不限
+    max_attempts: u32,
+    /// 0 為停用 keepalive
+    keepalive_interval_ms: u64,
+    keepalive_timeout_ms: u64,
+}
+
+impl Default for ReconnectPolicy {
+    /// 0.5 秒起、最長 30 秒，抖動 50%，不限次數；閒置 25 秒送出 keepalive、10 秒逾時
+    fn default() -> Self {
+        Self {
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            jitter: 0.5,
+            max_attempts: 0,
+            keepalive_interval_ms: 25_000,
+            keepalive_timeout_ms: 10_000,
+        }
+    }
+}
+
+#[cfg(any(feature = "websocket", test))]
+impl ReconnectPolicy {
+    /// 連續第 `attempt` 次失敗後的等待時間，`random` 為 [0, 1) 的亂數
+    ///
+    /// 在 `[base * (1 - jitter), base]` 之間，base 每次加倍直到 `max_backoff_ms`
+    fn backoff_with(&self, attempt: u32, random: f64) -> u64 {
+        let factor = 1u64.checked_shl(attempt.saturating_sub(1)).unwrap_or(u64::MAX);
+        let base = self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms);
+        base - (base as f64 * self.jitter * random) as u64
+    }
+
+    /// 連續第 `attempt` 次失敗後的等待時間 (含隨機抖動)
+    pub(crate) fn backoff(&self, attempt: u32) -> u64 {
+        self.backoff_with(attempt, rand::rngs::OsRng.gen::<f64>())
+    }
+
+    /// 連續失敗 `attempt` 次後是否放棄
+    pub(crate) fn gives_up(&self, attempt: u32) -> bool {
+        self.max_attempts != 0 && attempt >= self.max_attempts
+    }
+}
+
+#[wasm_bindgen]
+impl ReconnectPolicy {
+    /// 建立重新連線政策，未指定的欄位使用預設值 (`jitter` 限制在 0 ~ 1)
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        initial_backoff_ms: Option<u64>,
+        max_backoff_ms: Option<u64>,
+        jitter: Option<f64>,
+        max_attempts: Option<u32>,
+        keepalive_interval_ms: Option<u64>,
+        keepalive_timeout_ms: Option<u64>,
+    ) -> Self {
+        let defaults = Self::default();
+        let initial_backoff_ms = initial_backoff_ms.unwrap_or(defaults.initial_backoff_ms);
+        Self {
+            initial_backoff_ms,
+            max_backoff_ms: max_backoff_ms.unwrap_or(defaults.max_backoff_ms).max(initial_backoff_ms),
+            jitter: jitter.filter(|jitter| !jitter.is_nan()).unwrap_or(defaults.jitter).clamp(0.0, 1.0),
+            max_attempts: max_attempts.unwrap_or(defaults.max_attempts),
+            keepalive_interval_ms: keepalive_interval_ms.unwrap_or(defaults.keepalive_interval_ms),
+            keepalive_timeout_ms: keepalive_timeout_ms.unwrap_or(defaults.keepalive_timeout_ms),
+        }
+    }
+
+    #[wasm_bindgen(getter, js_name = initialBackoffMs)]
+    pub fn initial_backoff_ms(&self) -> u64 {
+        self.initial_backoff_ms
+    }
+
+    #[wasm_bindgen(getter, js_name = maxBackoffMs)]
+    pub fn max_backoff_ms(&self) -> u64 {
+        self.max_backoff_ms
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn jitter(&self) -> f64 {
+        self.jitter
+    }
+
+    /// 連續失敗的次數上限 (0 為不限)
+    #[wasm_bindgen(getter, js_name = maxAttempts)]
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// 閒置多久後送出 keepalive (0 為停用)
+    #[wasm_bindgen(getter, js_name = keepaliveIntervalMs)]
+    pub fn keepalive_interval_ms(&self) -> u64 {
+        self.keepalive_interval_ms
+    }
+
+    #[wasm_bindgen(getter, js_name = keepaliveTimeoutMs)]
+    pub fn keepalive_timeout_ms(&self) -> u64 {
+        self.keepalive_timeout_ms
+    }
+}
+
+/// `Keepalive::poll` 的結果
+#[cfg(any(feature = "websocket", test))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum KeepaliveAction {
+    Idle,
+    /// 送出 keepalive
+    Ping,
+    /// 逾時未收到資料，視為斷線
+    Timeout,
+}
+
+/// 單一連線的 keepalive 計時 (時間單位為毫秒)
+#[cfg(any(feature = "websocket", test))]
+pub(crate) struct Keepalive {
+    interval: u64,
+    timeout: u64,
+    last_received: u64,
+    ping_sent: Option<u64>,
+}
+
+#[cfg(any(feature = "websocket", test))]
+impl Keepalive {
+    pub(crate) fn new(policy: &ReconnectPolicy, now: u64) -> Self {
+        Self {
+            interval: policy.keepalive_interval_ms,
+            timeout: policy.keepalive_timeout_ms,
+            last_received: now,
+            ping_sent: None,
+        }
+    }
+
+    /// 收到任何資料 (包括 pong)
+    pub(crate) fn received(&mut self, now: u64) {
+        self.last_received = now;
+        self.ping_sent = None;
+    }
+
+    /// keepalive 已離開傳送緩衝 (瀏覽器的 `bufferedAmount` 歸零)
+    ///
+    /// 瀏覽器無法送出 ping，空訊息不保證有回覆；送出即視為連線仍可用，重新開始閒置計時
+    #[cfg(any(all(feature = "websocket", target_arch = "wasm32"), test))]
+    pub(crate) fn flushed(&mut self, now: u64) {
+        if self.ping_sent.take().is_some() {
+            self.last_received = now;
+        }
+    }
+
+    pub(crate) fn poll(&mut self, now: u64) -> KeepaliveAction {
+        if self.interval == 0 {
+            return KeepaliveAction::Idle;
+        }
+        match self.ping_sent {
+            Some(sent) if now.saturating_sub(sent) >= self.timeout => KeepaliveAction::Timeout,
+            Some(_) => KeepaliveAction::Idle,
+            None if now.saturating_sub(self.last_received) >= self.interval => {
+                self.ping_sent = Some(now);
+                KeepaliveAction::Ping
+            }
+            None => KeepaliveAction::Idle,
+        }
+    }
+}
+
+/// 未連線時排入的訊息
+#[cfg(any(feature = "websocket", test))]
+#[derive(Default)]
+pub(crate) struct OutgoingQueue {
+    messages: VecDeque<Vec<u8>>,
+}
+
+#[cfg(any(feature = "websocket", test))]
+impl OutgoingQueue {
+    pub(crate) fn push(&mut self, message: Vec<u8>) -> Result<(), String> {
+        if self.messages.len() >= MAX_QUEUED_MESSAGES {
+            return Err("Too many messages queued while disconnected".to_string());
+        }
+        self.messages.push_back(message);
+        Ok(())
+    }
+
+    /// 送出失敗的訊息放回佇列最前面
+    pub(crate) fn requeue(&mut self, message: Vec<u8>) {
+        self.messages.push_front(message);
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Vec<u8>> {
+        self.messages.pop_front()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.messages.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconnect_backoff() {
+        let policy = ReconnectPolicy::new(Some(100), Some(1_000), Some(0.5), Some(3), None, None);
+        assert_eq!(policy.backoff_with(1, 0.0), 100);
+        assert_eq!(policy.backoff_with(2, 0.0), 200);
+        assert_eq!(policy.backoff_with(4, 0.0), 800);
+        assert_eq!(policy.backoff_with(5, 0.0), 1_000);
+        assert_eq!(policy.backoff_with(100, 0.0), 1_000);
+        // 抖動只縮短延遲，最多 jitter 比例
+        assert_eq!(policy.backoff_with(2, 0.999), 101);
+        for attempt in 1..10 {
+            let delay = policy.backoff(attempt);
+            let base = policy.backoff_with(attempt, 0.0);
+            assert!(delay <= base && delay >= base / 2);
+        }
+        assert!(!policy.gives_up(2));
+        assert!(policy.gives_up(3));
+        assert!(!ReconnectPolicy::default().gives_up(u32::MAX));
+
+        // 參數限制
+        let policy = ReconnectPolicy::new(Some(500), Some(10), Some(7.0), None, None, None);
+        assert_eq!(policy.max_backoff_ms(), 500);
+        assert_eq!(policy.jitter(), 1.0);
+    }
+
+    #[test]
+    fn test_keepalive() {
+        let policy = ReconnectPolicy::new(None, None, None, None, Some(1_000), Some(500));
+        let mut keepalive = Keepalive::new(&policy, 0);
+        assert_eq!(keepalive.poll(999), KeepaliveAction::Idle);
+        assert_eq!(keepalive.poll(1_000), KeepaliveAction::Ping);
+        assert_eq!(keepalive.poll(1_200), KeepaliveAction::Idle);
+        keepalive.received(1_300);
+        assert_eq!(keepalive.poll(2_000), KeepaliveAction::Idle);
+        assert_eq!(keepalive.poll(2_300), KeepaliveAction::Ping);
+        assert_eq!(keepalive.poll(2_800), KeepaliveAction::Timeout);
+
+        // 伺服器忽略空訊息：送出後傳送緩衝清空即不逾時，緩衝一直未清空才逾時
+        let mut keepalive = Keepalive::new(&policy, 0);
+        assert_eq!(keepalive.poll(1_000), KeepaliveAction::Ping);
+        keepalive.flushed(1_001);
+        assert_eq!(keepalive.poll(1_600), KeepaliveAction::Idle);
+        assert_eq!(keepalive.poll(2_001), KeepaliveAction::Ping);
+        assert_eq!(keepalive.poll(2_501), KeepaliveAction::Timeout);
+        // 沒有送出中的 keepalive 時不影響閒置計時
+        let mut keepalive = Keepalive::new(&policy, 0);
+        keepalive.flushed(900);
+        assert_eq!(keepalive.poll(1_000), KeepaliveAction::Ping);
+
+        let disabled = ReconnectPolicy::new(None, None, None, None, Some(0), None);
+        assert_eq!(Keepalive::new(&disabled, 0).poll(u64::MAX), KeepaliveAction::Idle);
+    }
+
+    #[test]
+    fn test_outgoing_queue() {
+        let mut queue = OutgoingQueue::default();
+        queue.push(vec![1]).unwrap();
+        queue.push(vec![2]).unwrap();
+        let first = queue.pop().unwrap();
+        queue.requeue(first);
+        assert_eq!(queue.pop(), Some(vec![1]));
+        for _ in 1..MAX_QUEUED_MESSAGES {
+            queue.push(vec![0]).unwrap();
+        }
+        assert!(queue.push(vec![3]).is_err());
+        queue.clear();
+        assert_eq!(queue.pop(), None);
+    }
+}
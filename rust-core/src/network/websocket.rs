@@ -0,0 +1,318 @@
+//! 瀏覽器 WebSocket 傳輸模組 (feature = "websocket"，僅 WASM 建置)
+//!
+//! 以 web-sys 的 `WebSocket` 維持連線，重新連線、keepalive 與未連線時的佇列見 `transport` 模組。
+//! 瀏覽器無法送出 WebSocket ping，keepalive 以空的二進位訊息送出，伺服器應忽略 (或回覆) 空訊息。
+//! 空訊息不一定有回覆，因此只有 keepalive 在 `keepalive_timeout_ms` 內仍留在傳送緩衝
+//! (`bufferedAmount` 未歸零) 時才視為斷線；其餘斷線由 `close` / `error` 事件處理
+//!
+//! window 與 worker 皆可使用 (計時器取自全域的 `setTimeout` / `setInterval`)
+
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+
+use js_sys::{ArrayBuffer, Function, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{BinaryType, CloseEvent, MessageEvent, WebSocket};
+
+use super::transport::{ConnectionState, Keepalive, KeepaliveAction, OutgoingQueue, ReconnectPolicy};
+
+/// keepalive 檢查的間隔
+const KEEPALIVE_TICK_MS: f64 = 1_000.0;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_name = setTimeout)]
+    fn set_timeout(handler: &Function, timeout: f64) -> JsValue;
+
+    #[wasm_bindgen(js_name = clearTimeout)]
+    fn clear_timeout(id: &JsValue);
+
+    #[wasm_bindgen(js_name = setInterval)]
+    fn set_interval(handler: &Function, timeout: f64) -> JsValue;
+
+    #[wasm_bindgen(js_name = clearInterval)]
+    fn clear_interval(id: &JsValue);
+}
+
+type EventCallback = Closure<dyn FnMut(JsValue)>;
+type MessageCallback = Closure<dyn FnMut(MessageEvent)>;
+type CloseCallback = Closure<dyn FnMut(CloseEvent)>;
+type TimerCallback = Closure<dyn FnMut()>;
+
+/// 目前的連線與其事件處理器
+struct Connection {
+    socket: WebSocket,
+    _on_open: EventCallback,
+    _on_message: MessageCallback,
+    _on_close: CloseCallback,
+}
+
+impl Connection {
+    /// 卸下處理器並關閉連線
+    fn detach(&self) {
+        self.socket.set_onopen(None);
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.detach();
+    }
+}
+
+struct TransportState {
+    url: String,
+    policy: ReconnectPolicy,
+    on_message: Function,
+    on_state: Option<Function>,
+    state: Cell<ConnectionState>,
+    /// 連續失敗的次數
+    attempt: Cell<u32>,
+    connection: RefCell<Option<Connection>>,
+    /// 已中斷的連線：處理器可能正在執行 (例如在 close 事件中換新連線)，保留到下一次中斷才釋放
+    retired: RefCell<Option<Connection>>,
+    queue: RefCell<OutgoingQueue>,
+    keepalive: RefCell<Option<Keepalive>>,
+    /// 等待重新連線的計時器
+    reconnect_timer: RefCell<Option<JsValue>>,
+    keepalive_timer: RefCell<Option<JsValue>>,
+    on_reconnect: TimerCallback,
+    on_keepalive: TimerCallback,
+}
+
+impl TransportState {
+    fn set_state(&self, state: ConnectionState) {
+        self.state.set(state);
+        if let Some(callback) = &self.on_state {
+            let _ = callback.call2(&JsValue::NULL, &JsValue::from(state), &JsValue::from(self.attempt.get()));
+        }
+    }
+
+    /// 卸下並關閉目前的連線 (處理器保留到下一次中斷)
+    fn retire(&self) {
+        self.stop_timers();
+        self.keepalive.borrow_mut().take();
+        let connection = self.connection.borrow_mut().take();
+        if let Some(connection) = connection {
+            connection.detach();
+            *self.retired.borrow_mut() = Some(connection);
+        }
+    }
+
+    fn stop_timers(&self) {
+        if let Some(timer) = self.reconnect_timer.borrow_mut().take() {
+            clear_timeout(&timer);
+        }
+        if let Some(timer) = self.keepalive_timer.borrow_mut().take() {
+            clear_interval(&timer);
+        }
+    }
+}
+
+/// 開啟新的連線，失敗時排定重新連線
+fn open(state: &Rc<TransportState>) {
+    let socket = match WebSocket::new(&state.url) {
+        Ok(socket) => socket,
+        Err(_) => return reconnect(state),
+    };
+    socket.set_binary_type(BinaryType::Arraybuffer);
+
+    let opened = Rc::downgrade(state);
+    let on_open = EventCallback::new(move |_| {
+        if let Some(state) = opened.upgrade() {
+            on_open(&state);
+        }
+    });
+    let received = Rc::downgrade(state);
+    let on_message = MessageCallback::new(move |event: MessageEvent| {
+        if let Some(state) = received.upgrade() {
+            on_message(&state, event.data());
+        }
+    });
+    let closed = Rc::downgrade(state);
+    let on_close = CloseCallback::new(move |_| {
+        if let Some(state) = closed.upgrade() {
+            disconnect(&state);
+        }
+    });
+    socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+    socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+    socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+    *state.connection.borrow_mut() = Some(Connection {
+        socket,
+        _on_open: on_open,
+        _on_message: on_message,
+        _on_close: on_close,
+    });
+}
+
+fn on_open(state: &Rc<TransportState>) {
+    state.attempt.set(0);
+    *state.keepalive.borrow_mut() = Some(Keepalive::new(&state.policy, js_sys::Date::now() as u64));
+    if state.policy.keepalive_interval_ms() > 0 {
+        *state.keepalive_timer.borrow_mut() = Some(set_interval(state.on_keepalive.as_ref().unchecked_ref(), KEEPALIVE_TICK_MS));
+    }
+    state.set_state(ConnectionState::Open);
+    flush(state);
+}
+
+fn on_message(state: &TransportState, data: JsValue) {
+    if let Some(keepalive) = state.keepalive.borrow_mut().as_mut() {
+        keepalive.received(js_sys::Date::now() as u64);
+    }
+    let message = match data.dyn_into::<ArrayBuffer>() {
+        Ok(buffer) => Uint8Array::new(&buffer),
+        Err(data) => match data.as_string() {
+            Some(text) => Uint8Array::from(text.as_bytes()),
+            None => return,
+        },
+    };
+    let _ = state.on_message.call1(&JsValue::NULL, &message);
+}
+
+/// 送出佇列中的訊息，送出失敗的訊息留在佇列中
+fn flush(state: &TransportState) {
+    let connection = state.connection.borrow();
+    let Some(connection) = connection.as_ref() else {
+        return;
+    };
+    let mut queue = state.queue.borrow_mut();
+    while let Some(message) = queue.pop() {
+        if connection.socket.send_with_u8_array(&message).is_err() {
+            queue.requeue(message);
+            break;
+        }
+    }
+}
+
+fn keepalive(state: &Rc<TransportState>) {
+    let now = js_sys::Date::now() as u64;
+    let buffered = state.connection.borrow().as_ref().map_or(0, |connection| connection.socket.buffered_amount());
+    let action = match state.keepalive.borrow_mut().as_mut() {
+        Some(keepalive) => {
+            if buffered == 0 {
+                keepalive.flushed(now);
+            }
+            keepalive.poll(now)
+        }
+        None => return,
+    };
+    match action {
+        KeepaliveAction::Idle => {}
+        KeepaliveAction::Ping => {
+            if let Some(connection) = state.connection.borrow().as_ref() {
+                let _ = connection.socket.send_with_u8_array(&[]);
+            }
+        }
+        // keepalive 一直送不出去：斷線的連線可能很久才觸發 close，直接換新連線
+        KeepaliveAction::Timeout => disconnect(state),
+    }
+}
+
+/// 連線中斷：卸下連線並排定重新連線
+fn disconnect(state: &Rc<TransportState>) {
+    state.retire();
+    if state.state.get() != ConnectionState::Closed {
+        reconnect(state);
+    }
+}
+
+fn reconnect(state: &Rc<TransportState>) {
+    let attempt = state.attempt.get() + 1;
+    state.attempt.set(attempt);
+    if state.policy.gives_up(attempt) {
+        state.queue.borrow_mut().clear();
+        state.set_state(ConnectionState::Closed);
+        return;
+    }
+    state.set_state(ConnectionState::Reconnecting);
+    let delay = state.policy.backoff(attempt) as f64;
+    *state.reconnect_timer.borrow_mut() = Some(set_timeout(state.on_reconnect.as_ref().unchecked_ref(), delay));
+}
+
+/// 自動重新連線的 WebSocket 傳輸
+///
+/// 複製出的實例共用同一個連線
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct WebSocketTransport {
+    state: Rc<TransportState>,
+}
+
+#[wasm_bindgen]
+impl WebSocketTransport {
+    /// 開始連線 `url` (`ws://` 或 `wss://`)，連線失敗或斷線時依 `policy` (未指定時為預設值) 重新連線
+    ///
+    /// `on_message(data: Uint8Array)` 收到每則訊息 (文字訊息以 UTF-8 位元組傳入)；
+    /// `on_state(state: ConnectionState, attempt: number)` 收到新的連線狀態與連續失敗的次數。
+    /// callback 拋出的例外會被忽略
+    #[wasm_bindgen(constructor)]
+    pub fn connect(url: String, on_message: Function, on_state: Option<Function>, policy: Option<ReconnectPolicy>) -> Self {
+        let state = Rc::new_cyclic(|weak: &Weak<TransportState>| TransportState {
+            url,
+            policy: policy.unwrap_or_default(),
+            on_message,
+            on_state,
+            state: Cell::new(ConnectionState::Connecting),
+            attempt: Cell::new(0),
+            connection: RefCell::new(None),
+            retired: RefCell::new(None),
+            queue: RefCell::default(),
+            keepalive: RefCell::new(None),
+            reconnect_timer: RefCell::new(None),
+            keepalive_timer: RefCell::new(None),
+            on_reconnect: TimerCallback::new({
+                let weak = weak.clone();
+                move || {
+                    if let Some(state) = weak.upgrade() {
+                        state.reconnect_timer.borrow_mut().take();
+                        open(&state);
+                    }
+                }
+            }),
+            on_keepalive: TimerCallback::new({
+                let weak = weak.clone();
+                move || {
+                    if let Some(state) = weak.upgrade() {
+                        keepalive(&state);
+                    }
+                }
+            }),
+        });
+        state.set_state(ConnectionState::Connecting);
+        open(&state);
+        Self { state }
+    }
+
+    /// 送出二進位訊息；未連線時排入佇列，連線後依序送出
+    pub fn send(&self, data: &[u8]) -> Result<(), JsError> {
+        if self.state.state.get() == ConnectionState::Closed {
+            return Err(JsError::new("WebSocket transport is closed"));
+        }
+        self.state.queue.borrow_mut().push(data.to_vec()).map_err(|e| JsError::new(&e))?;
+        if self.state.state.get() == ConnectionState::Open {
+            flush(&self.state);
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn state(&self) -> ConnectionState {
+        self.state.state.get()
+    }
+
+    /// 關閉連線 (不再重新連線)；佇列中的訊息被丟棄
+    pub fn close(&self) {
+        if self.state.state.get() == ConnectionState::Closed {
+            return;
+        }
+        self.state.state.set(ConnectionState::Closed);
+        self.state.retire();
+        self.state.queue.borrow_mut().clear();
+        self.state.set_state(ConnectionState::Closed);
+    }
+}
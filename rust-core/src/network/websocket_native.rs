@@ -0,0 +1,330 @@
+//! 原生 WebSocket 傳輸模組 (feature = "websocket"，僅原生建置)
+//!
+//! 以 tungstenite 在背景執行緒中維持連線 (`wss://` 以 rustls 與 webpki 根憑證驗證)，
+//! 重新連線、keepalive 與未連線時的佇列見 `transport` 模組。keepalive 以 WebSocket ping 送出
+//!
+//! 收到的訊息與狀態變更在背景執行緒中呼叫 callback；callback 不應長時間阻塞，否則會延遲收送
+
+use std::io::ErrorKind;
+use std::net::TcpStream;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{Message, WebSocket};
+
+use super::transport::{ConnectionState, Keepalive, KeepaliveAction, OutgoingQueue, ReconnectPolicy};
+
+/// 讀取逾時，背景執行緒以此間隔檢查待送訊息與 keepalive
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+
+enum Command {
+    Send(Vec<u8>),
+    Close,
+}
+
+/// 連線結束的原因
+enum Disconnect {
+    /// 呼叫 `close` (或 transport 已 drop)
+    Closed,
+    Failed,
+}
+
+/// 背景執行緒的狀態
+struct Worker<M, S> {
+    url: String,
+    policy: ReconnectPolicy,
+    commands: Receiver<Command>,
+    state: Arc<Mutex<ConnectionState>>,
+    queue: OutgoingQueue,
+    started: Instant,
+    on_message: M,
+    on_state: S,
+}
+
+impl<M: FnMut(Vec<u8>), S: FnMut(ConnectionState, u32)> Worker<M, S> {
+    fn now(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn set_state(&mut self, state: ConnectionState, attempt: u32) {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner()) = state;
+        (self.on_state)(state, attempt);
+    }
+
+    /// 重新連線直到關閉或超過次數上限
+    fn run(mut self) {
+        let mut attempt = 0;
+        self.set_state(ConnectionState::Connecting, attempt);
+        loop {
+            if let Ok((mut socket, _)) = tungstenite::connect(self.url.as_str()) {
+                if set_read_timeout(&socket, POLL_INTERVAL).is_ok() {
+                    attempt = 0;
+                    self.set_state(ConnectionState::Open, 0);
+                    if let Disconnect::Closed = self.serve(&mut socket) {
+                        let _ = socket.close(None);
+                        let _ = socket.flush();
+                        break;
+                    }
+                }
+            }
+
+            attempt += 1;
+            if self.policy.gives_up(attempt) {
+                break;
+            }
+            self.set_state(ConnectionState::Reconnecting, attempt);
+            if let Disconnect::Closed = self.wait(Duration::from_millis(self.policy.backoff(attempt))) {
+                break;
+            }
+        }
+        self.queue.clear();
+        self.set_state(ConnectionState::Closed, attempt);
+    }
+
+    /// 處理指令；回傳 `Closed` 時停止
+    fn handle(&mut self, command: Command) -> Option<Disconnect> {
+        match command {
+            Command::Send(message) => {
+                // 佇列已滿時丟棄
+                let _ = self.queue.push(message);
+                None
+            }
+            Command::Close => Some(Disconnect::Closed),
+        }
+    }
+
+    /// 退避等待期間繼續收下待送訊息
+    fn wait(&mut self, delay: Duration) -> Disconnect {
+        let deadline = Instant::now() + delay;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            match self.commands.recv_timeout(remaining) {
+                Ok(command) => {
+                    if let Some(disconnect) = self.handle(command) {
+                        return disconnect;
+                    }
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Disconnect::Closed,
+            }
+        }
+        Disconnect::Failed
+    }
+
+    /// 已連線時收送訊息，直到關閉或斷線
+    fn serve(&mut self, socket: &mut Socket) -> Disconnect {
+        let mut keepalive = Keepalive::new(&self.policy, self.now());
+        loop {
+            loop {
+                match self.commands.try_recv() {
+                    Ok(command) => {
+                        if let Some(disconnect) = self.handle(command) {
+                            return disconnect;
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return Disconnect::Closed,
+                }
+            }
+            while let Some(message) = self.queue.pop() {
+                if socket.send(Message::Binary(message.clone().into())).is_err() {
+                    self.queue.requeue(message);
+                    return Disconnect::Failed;
+                }
+            }
+
+            match socket.read() {
+                Ok(message) => {
+                    keepalive.received(self.now());
+                    match message {
+                        Message::Binary(data) => (self.on_message)(data.to_vec()),
+                        Message::Text(text) => (self.on_message)(text.as_bytes().to_vec()),
+                        Message::Close(_) => return Disconnect::Failed,
+                        // ping 由 tungstenite 自動回覆
+                        Message::Ping(_) | Message::Pong(_) | Message::Frame(_) => {}
+                    }
+                }
+                Err(tungstenite::Error::Io(e)) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(_) => return Disconnect::Failed,
+            }
+
+            match keepalive.poll(self.now()) {
+                KeepaliveAction::Idle => {}
+                KeepaliveAction::Ping => {
+                    if socket.send(Message::Ping(Default::default())).is_err() {
+                        return Disconnect::Failed;
+                    }
+                }
+                KeepaliveAction::Timeout => return Disconnect::Failed,
+            }
+        }
+    }
+}
+
+fn set_read_timeout(socket: &Socket, timeout: Duration) -> std::io::Result<()> {
+    match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream.set_read_timeout(Some(timeout)),
+        MaybeTlsStream::Rustls(stream) => stream.get_ref().set_read_timeout(Some(timeout)),
+        _ => Err(ErrorKind::Unsupported.into()),
+    }
+}
+
+/// 自動重新連線的 WebSocket 傳輸
+///
+/// drop 時關閉連線並等待背景執行緒結束
+pub struct WebSocketTransport {
+    commands: Sender<Command>,
+    state: Arc<Mutex<ConnectionState>>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl WebSocketTransport {
+    /// 開始連線 `url` (`ws://` 或 `wss://`)，連線失敗或斷線時依 `policy` 重新連線
+    ///
+    /// `on_message` 收到每則訊息 (文字訊息以 UTF-8 位元組傳入)；`on_state` 收到新的連線狀態與
+    /// 連續失敗的次數
+    pub fn connect(
+        url: &str,
+        policy: ReconnectPolicy,
+        on_message: impl FnMut(Vec<u8>) + Send + 'static,
+        on_state: impl FnMut(ConnectionState, u32) + Send + 'static,
+    ) -> Result<Self, String> {
+        let (sender, commands) = mpsc::channel();
+        let state = Arc::new(Mutex::new(ConnectionState::Connecting));
+        let worker = Worker {
+            url: url.to_string(),
+            policy,
+            commands,
+            state: state.clone(),
+            queue: OutgoingQueue::default(),
+            started: Instant::now(),
+            on_message,
+            on_state,
+        };
+        let worker = std::thread::Builder::new()
+            .name("safetalk-websocket".to_string())
+            .spawn(move || worker.run())
+            .map_err(|e| format!("Failed to start WebSocket thread: {}", e))?;
+        Ok(Self { commands: sender, state, worker: Some(worker) })
+    }
+
+    /// 送出二進位訊息；未連線時排入佇列，連線後依序送出 (佇列已滿時丟棄)
+    pub fn send(&self, data: &[u8]) -> Result<(), String> {
+        if self.state() == ConnectionState::Closed {
+            return Err("WebSocket transport is closed".to_string());
+        }
+        self.commands
+            .send(Command::Send(data.to_vec()))
+            .map_err(|_| "WebSocket transport is closed".to_string())
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        *self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 關閉連線 (不再重新連線)，等待背景執行緒結束；佇列中的訊息被丟棄
+    pub fn close(&mut self) {
+        let _ = self.commands.send(Command::Close);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Drop for WebSocketTransport {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_websocket_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        // 第一個連線回送一則訊息後斷線，第二個連線回送收到的訊息
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            let message = socket.read().unwrap();
+            socket.send(message).unwrap();
+            drop(socket);
+
+            let (stream, _) = listener.accept().unwrap();
+            let mut socket = tungstenite::accept(stream).unwrap();
+            loop {
+                match socket.read() {
+                    Ok(message @ Message::Binary(_)) => socket.send(message).unwrap(),
+                    Ok(Message::Close(_)) | Err(_) => break,
+                    Ok(_) => {}
+                }
+            }
+        });
+
+        let (messages, received) = mpsc::channel();
+        let (states, changes) = mpsc::channel();
+        let policy = ReconnectPolicy::new(Some(10), Some(20), None, None, None, None);
+        let mut transport = WebSocketTransport::connect(
+            &url,
+            policy,
+            move |message| messages.send(message).unwrap(),
+            move |state, attempt| states.send((state, attempt)).unwrap(),
+        )
+        .unwrap();
+        transport.send(b"first").unwrap();
+        let timeout = Duration::from_secs(5);
+        assert_eq!(received.recv_timeout(timeout).unwrap(), b"first");
+
+        // 斷線後重新連線，佇列中的訊息在連線後送出
+        let mut reconnected = false;
+        while let Ok((state, attempt)) = changes.recv_timeout(timeout) {
+            if state == ConnectionState::Reconnecting {
+                assert!(attempt >= 1);
+                reconnected = true;
+            }
+            if reconnected && state == ConnectionState::Open {
+                break;
+            }
+        }
+        assert!(reconnected);
+        transport.send(b"second").unwrap();
+        assert_eq!(received.recv_timeout(timeout).unwrap(), b"second");
+
+        transport.close();
+        assert_eq!(transport.state(), ConnectionState::Closed);
+        assert!(transport.send(b"third").is_err());
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_websocket_gives_up() {
+        // 沒有伺服器監聽的位址
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        drop(listener);
+
+        let (states, changes) = mpsc::channel();
+        let policy = ReconnectPolicy::new(Some(1), Some(2), None, Some(2), None, None);
+        let transport = WebSocketTransport::connect(&url, policy, |_| {}, move |state, attempt| {
+            let _ = states.send((state, attempt));
+        })
+        .unwrap();
+        let mut last = None;
+        while let Ok(change) = changes.recv_timeout(Duration::from_secs(5)) {
+            last = Some(change);
+            if change.0 == ConnectionState::Closed {
+                break;
+            }
+        }
+        assert_eq!(last, Some((ConnectionState::Closed, 2)));
+        assert_eq!(transport.state(), ConnectionState::Closed);
+    }
+}
@@ -0,0 +1,309 @@
+//! 加密封存格式 (備份與對話匯出共用)
+//!
+//! `magic (4) || version (1) || kdf 長度 (u16 BE) || kdf 參數字串 || STREAM 標頭 || 密文`
+//!
+//! - 封存金鑰由密碼衍生 (kdf 參數字串含 salt)，或直接提供 (此時 kdf 長度為 0)
+//! - 串流金鑰以 HKDF 從封存金鑰與標頭前段衍生，標頭被竄改時解密失敗
+//! - 明文為一連串長度前綴 (u32 BE) 的記錄；記錄內容與結尾記錄由各格式定義，
+//!   STREAM 的結束旗標確保封存沒有被截斷
+//! - 以 `finish_padded` 結束時，明文在記錄之後以 Padmé 填充 (`0x80 || 0x00...`)，
+//!   密文長度只洩漏內容大小的概略範圍
+
+use std::io::{ErrorKind, Read, Write};
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use zeroize::Zeroizing;
+
+use crate::crypto::kdf::hkdf_sha256;
+use crate::crypto::padding::{padme_length, PADDING_MARKER};
+use crate::crypto::{
+    derive_key_from_encoded_params, CipherSuite, PasswordKdfParams, StreamCipher, DEFAULT_STREAM_CHUNK_SIZE,
+    STREAM_HEADER_SIZE,
+};
+
+const ARCHIVE_KEY_SIZE: usize = 32;
+const ARCHIVE_SALT_SIZE: usize = 16;
+/// 單筆記錄的上限，避免損毀的長度欄位耗盡記憶體
+pub(crate) const MAX_ARCHIVE_RECORD_SIZE: usize = 64 * 1024 * 1024;
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// 封存格式的識別資訊
+pub(crate) struct ArchiveFormat {
+    pub magic: &'static [u8; 4],
+    pub version: u8,
+    /// 串流金鑰的 HKDF info
+    pub key_info: &'static [u8],
+    /// 錯誤訊息中的名稱 (例如 "Backup")
+    pub name: &'static str,
+}
+
+/// 封存金鑰來源
+#[derive(Clone, Copy)]
+pub enum BackupSecret<'a> {
+    /// 32 bytes 封存金鑰 (例如 `KeyDerivation::backup_key`)
+    Key(&'a [u8]),
+    /// 密碼，KDF 參數與 salt 記錄在封存標頭中
+    Password(&'a [u8]),
+}
+
+impl ArchiveFormat {
+    pub(crate) fn write_error(&self, error: std::io::Error) -> String {
+        format!("Failed to write {}: {}", self.name.to_lowercase(), error)
+    }
+
+    pub(crate) fn incomplete(&self, error: impl std::fmt::Display) -> String {
+        format!("{} is incomplete or corrupted: {}", self.name, error)
+    }
+
+    /// 標頭前段 (magic、版本、kdf 參數字串)
+    fn prefix(&self, kdf: &str) -> Result<Vec<u8>, String> {
+        let length = u16::try_from(kdf.len()).map_err(|_| format!("{} KDF parameters are too long", self.name))?;
+        Ok([self.magic.as_slice(), &[self.version], &length.to_be_bytes(), kdf.as_bytes()].concat())
+    }
+
+    /// 串流金鑰：以標頭前段為 HKDF salt，綁定 kdf 參數
+    fn stream_key(&self, key: &[u8], prefix: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        if key.len() != ARCHIVE_KEY_SIZE {
+            return Err(format!("{} key must be {} bytes", self.name, ARCHIVE_KEY_SIZE));
+        }
+        Ok(Zeroizing::new(hkdf_sha256(prefix, key, self.key_info, ARCHIVE_KEY_SIZE)?))
+    }
+
+    /// manifest 的 MAC 金鑰 (與串流金鑰分開衍生)
+    fn manifest_key(&self, key: &[u8], prefix: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        let info = [self.key_info, b"-Manifest"].concat();
+        Ok(Zeroizing::new(hkdf_sha256(prefix, key, &info, ARCHIVE_KEY_SIZE)?))
+    }
+
+    fn read_exact(&self, input: &mut impl Read, buffer: &mut [u8]) -> Result<(), String> {
+        input.read_exact(buffer).map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => format!("{} archive is truncated", self.name),
+            _ => format!("Failed to read {}: {}", self.name.to_lowercase(), e),
+        })
+    }
+}
+
+/// 串流寫入封存記錄
+pub(crate) struct ArchiveWriter<W: Write> {
+    format: &'static ArchiveFormat,
+    output: W,
+    cipher: StreamCipher,
+    manifest_key: Zeroizing<Vec<u8>>,
+    /// 已寫入的明文長度
+    written: u64,
+}
+
+impl<W: Write> ArchiveWriter<W> {
+    /// 以封存金鑰建立寫入器並寫出標頭
+    pub(crate) fn with_key(format: &'static ArchiveFormat, output: W, key: &[u8]) -> Result<Self, String> {
+        Self::start(format, output, key, "")
+    }
+
+    /// 以密碼建立寫入器 (以 `kdf` 參數與隨機 salt 衍生封存金鑰) 並寫出標頭
+    pub(crate) fn with_password(
+        format: &'static ArchiveFormat,
+        output: W,
+        password: &[u8],
+        kdf: &PasswordKdfParams,
+    ) -> Result<Self, String> {
+        let mut salt = [0u8; ARCHIVE_SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+        let key = Zeroizing::new(kdf.derive_key(password, &salt)?);
+        Self::start(format, output, key.as_slice(), &kdf.encode(&salt))
+    }
+
+    fn start(format: &'static ArchiveFormat, mut output: W, key: &[u8], kdf: &str) -> Result<Self, String> {
+        let prefix = format.prefix(kdf)?;
+        let cipher = StreamCipher::new_encryptor(
+            &format.stream_key(key, &prefix)?,
+            CipherSuite::default(),
+            DEFAULT_STREAM_CHUNK_SIZE,
+        )?;
+        let manifest_key = format.manifest_key(key, &prefix)?;
+        output.write_all(&prefix).map_err(|e| format.write_error(e))?;
+        output.write_all(&cipher.header()).map_err(|e| format.write_error(e))?;
+        Ok(Self { format, output, cipher, manifest_key, written: 0 })
+    }
+
+    /// 簽署 manifest 用的 MAC 金鑰
+    pub(crate) fn manifest_key(&self) -> &[u8] {
+        &self.manifest_key
+    }
+
+    /// 寫入一筆記錄
+    pub(crate) fn write_record(&mut self, bytes: &[u8]) -> Result<(), String> {
+        if bytes.len() > MAX_ARCHIVE_RECORD_SIZE {
+            return Err(format!("{} record is too large", self.format.name));
+        }
+        for part in [&(bytes.len() as u32).to_be_bytes()[..], bytes] {
+            self.push(part)?;
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, plaintext: &[u8]) -> Result<(), String> {
+        let ciphertext = self.cipher.push_bytes(plaintext)?;
+        self.output.write_all(&ciphertext).map_err(|e| self.format.write_error(e))?;
+        self.written += plaintext.len() as u64;
+        Ok(())
+    }
+
+    /// 以 Padmé 填充明文後結束串流，回傳輸出端
+    pub(crate) fn finish_padded(mut self) -> Result<W, String> {
+        let mut remaining = padme_length(self.written + 1) - self.written;
+        let mut padding = vec![0u8; READ_BUFFER_SIZE];
+        padding[0] = PADDING_MARKER;
+        while remaining > 0 {
+            let length = remaining.min(READ_BUFFER_SIZE as u64) as usize;
+            self.push(&padding[..length])?;
+            padding[0] = 0;
+            remaining -= length as u64;
+        }
+        self.finish()
+    }
+
+    /// 結束串流，回傳輸出端
+    pub(crate) fn finish(mut self) -> Result<W, String> {
+        let ciphertext = self.cipher.finish_bytes()?;
+        self.output.write_all(&ciphertext).map_err(|e| self.format.write_error(e))?;
+        self.output.flush().map_err(|e| self.format.write_error(e))?;
+        Ok(self.output)
+    }
+}
+
+/// 串流讀取封存記錄
+pub(crate) struct ArchiveReader<R: Read> {
+    format: &'static ArchiveFormat,
+    input: R,
+    cipher: StreamCipher,
+    /// 已解密、尚未解析的明文
+    buffer: Zeroizing<Vec<u8>>,
+    /// 輸入已讀完，串流已以結束旗標驗證
+    finished: bool,
+    manifest_key: Zeroizing<Vec<u8>>,
+    /// 已取出的記錄明文長度 (含長度前綴)
+    consumed: u64,
+}
+
+impl<R: Read> ArchiveReader<R> {
+    /// 讀取標頭並以封存金鑰或密碼開啟
+    pub(crate) fn open(format: &'static ArchiveFormat, mut input: R, secret: BackupSecret) -> Result<Self, String> {
+        let mut fixed = [0u8; 7];
+        format.read_exact(&mut input, &mut fixed)?;
+        if &fixed[..4] != format.magic {
+            return Err(format!("Not a {} archive", format.name.to_lowercase()));
+        }
+        if fixed[4] != format.version {
+            return Err(format!("Unsupported {} version: {}", format.name.to_lowercase(), fixed[4]));
+        }
+        let mut kdf = vec![0u8; u16::from_be_bytes([fixed[5], fixed[6]]) as usize];
+        format.read_exact(&mut input, &mut kdf)?;
+        let kdf = String::from_utf8(kdf).map_err(|_| format!("Corrupted {} header", format.name.to_lowercase()))?;
+        let key = match (secret, kdf.is_empty()) {
+            (BackupSecret::Key(key), true) => Zeroizing::new(key.to_vec()),
+            (BackupSecret::Password(password), false) => {
+                Zeroizing::new(derive_key_from_encoded_params(password, &kdf)?.to_vec())
+            }
+            (BackupSecret::Key(_), false) => return Err(format!("{} is protected by a password", format.name)),
+            (BackupSecret::Password(_), true) => return Err(format!("{} is protected by a key", format.name)),
+        };
+        let mut header = [0u8; STREAM_HEADER_SIZE];
+        format.read_exact(&mut input, &mut header)?;
+        let prefix = format.prefix(&kdf)?;
+        let cipher = StreamCipher::new_decryptor(&format.stream_key(&key, &prefix)?, &header)?;
+        let manifest_key = format.manifest_key(&key, &prefix)?;
+        Ok(Self {
+            format,
+            input,
+            cipher,
+            buffer: Zeroizing::new(Vec::new()),
+            finished: false,
+            manifest_key,
+            consumed: 0,
+        })
+    }
+
+    /// 驗證 manifest 用的 MAC 金鑰
+    pub(crate) fn manifest_key(&self) -> &[u8] {
+        &self.manifest_key
+    }
+
+    /// 從緩衝區取出一筆完整的記錄
+    fn take_record(&mut self) -> Result<Option<Zeroizing<Vec<u8>>>, String> {
+        let Some(length) = self.buffer.get(..4) else {
+            return Ok(None);
+        };
+        let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+        if length > MAX_ARCHIVE_RECORD_SIZE {
+            return Err(self.format.incomplete("record length out of range"));
+        }
+        let Some(bytes) = self.buffer.get(4..4 + length) else {
+            return Ok(None);
+        };
+        let record = Zeroizing::new(bytes.to_vec());
+        self.buffer.drain(..4 + length);
+        self.consumed += 4 + length as u64;
+        Ok(Some(record))
+    }
+
+    /// 讀取並解密下一段輸入；輸入結束時驗證結束旗標
+    fn fill(&mut self) -> Result<(), String> {
+        let mut chunk = vec![0u8; READ_BUFFER_SIZE];
+        let read = loop {
+            match self.input.read(&mut chunk) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(format!("Failed to read {}: {}", self.format.name.to_lowercase(), e)),
+            }
+        };
+        let plaintext = Zeroizing::new(if read == 0 {
+            self.finished = true;
+            self.cipher.finish_bytes().map_err(|e| self.format.incomplete(e))?
+        } else {
+            self.cipher.push_bytes(&chunk[..read]).map_err(|e| self.format.incomplete(e))?
+        });
+        self.buffer.extend_from_slice(&plaintext);
+        Ok(())
+    }
+
+    /// 讀取下一筆記錄；串流正確結束且沒有剩餘資料時回傳 None
+    pub(crate) fn next_record(&mut self) -> Result<Option<Zeroizing<Vec<u8>>>, String> {
+        loop {
+            if let Some(record) = self.take_record()? {
+                return Ok(Some(record));
+            }
+            if self.finished {
+                if !self.buffer.is_empty() {
+                    return Err(self.format.incomplete("truncated record"));
+                }
+                return Ok(None);
+            }
+            self.fill()?;
+        }
+    }
+
+    /// 讀完其餘的明文，確認只有 `finish_padded` 寫入的 Padmé 填充且串流正確結束
+    pub(crate) fn finish_padded(&mut self) -> Result<(), String> {
+        let expected = padme_length(self.consumed + 1) - self.consumed;
+        let mut seen = 0u64;
+        loop {
+            for &byte in self.buffer.iter() {
+                let valid = if seen == 0 { byte == PADDING_MARKER } else { byte == 0 };
+                if !valid || seen == expected {
+                    return Err(self.format.incomplete("trailing data after last record"));
+                }
+                seen += 1;
+            }
+            self.buffer.clear();
+            if self.finished {
+                break;
+            }
+            self.fill()?;
+        }
+        if seen != expected {
+            return Err(self.format.incomplete("invalid padding"));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+//! 頭像快取模組
+//!
+//! 從伺服器下載的頭像密文 (見 `crypto::avatar`) 以密文的 SHA-256 摘要為鍵存入鍵值儲存，
+//! 相同的頭像 (例如多個群組共用、重新下載未變更的頭像) 只保存一份：
+//! - `avatars` namespace：摘要 (base64url) -> 頭像密文
+//! - `avatar_owners` namespace：擁有者 (聯絡人或群組 ID) -> 摘要
+//!
+//! 快取內容維持頭像密文，顯示時再以 profile key 解密；持久化後端另外以主儲存金鑰加密。
+//! 擁有者更換或移除頭像時，不再被任何擁有者參照的密文會一併刪除
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL, Engine as _};
+use wasm_bindgen::prelude::*;
+
+use super::kv::{AsyncKeyValueStore, InMemoryKeyValueStore, KeyValueStore};
+use crate::crypto::hash::sha256;
+
+/// 頭像密文的 namespace
+pub const AVATAR_NAMESPACE: &str = "avatars";
+/// 擁有者 -> 摘要的 namespace
+pub const AVATAR_OWNER_NAMESPACE: &str = "avatar_owners";
+
+/// 頭像密文的快取鍵 (SHA-256 摘要，base64url)
+#[wasm_bindgen(js_name = avatarDigest)]
+pub fn avatar_digest(ciphertext: &[u8]) -> String {
+    BASE64URL.encode(sha256(ciphertext))
+}
+
+fn decode_owner_digest(value: Vec<u8>) -> Result<String, String> {
+    String::from_utf8(value).map_err(|_| "Corrupted avatar owner record".to_string())
+}
+
+/// 頭像快取 (所有 `KeyValueStore` 皆可使用)
+pub trait AvatarCache {
+    /// 存入擁有者的頭像密文，回傳摘要
+    fn cache_avatar(&mut self, owner: &str, ciphertext: &[u8]) -> Result<String, String>;
+
+    /// 依摘要讀取頭像密文
+    fn cached_avatar(&self, digest: &str) -> Result<Option<Vec<u8>>, String>;
+
+    /// 擁有者目前頭像的摘要
+    fn avatar_digest_of(&self, owner: &str) -> Result<Option<String>, String>;
+
+    /// 移除擁有者的頭像，回傳是否存在
+    fn remove_avatar(&mut self, owner: &str) -> Result<bool, String>;
+
+    /// 擁有者目前的頭像密文
+    fn avatar_of(&self, owner: &str) -> Result<Option<Vec<u8>>, String> {
+        match self.avatar_digest_of(owner)? {
+            Some(digest) => self.cached_avatar(&digest),
+            None => Ok(None),
+        }
+    }
+}
+
+/// 摘要若已無擁有者參照則刪除密文
+fn release_digest<S: KeyValueStore + ?Sized>(store: &mut S, digest: &str) -> Result<(), String> {
+    for owner in store.list(AVATAR_OWNER_NAMESPACE)? {
+        if let Some(value) = store.get(AVATAR_OWNER_NAMESPACE, &owner)? {
+            if decode_owner_digest(value)? == digest {
+                return Ok(());
+            }
+        }
+    }
+    store.remove(AVATAR_NAMESPACE, digest)?;
+    Ok(())
+}
+
+impl<S: KeyValueStore> AvatarCache for S {
+    fn cache_avatar(&mut self, owner: &str, ciphertext: &[u8]) -> Result<String, String> {
+        let digest = avatar_digest(ciphertext);
+        let previous = self.avatar_digest_of(owner)?;
+        if self.get(AVATAR_NAMESPACE, &digest)?.is_none() {
+            self.put(AVATAR_NAMESPACE, &digest, ciphertext)?;
+        }
+        self.put(AVATAR_OWNER_NAMESPACE, owner, digest.as_bytes())?;
+        if let Some(previous) = previous.filter(|previous| *previous != digest) {
+            release_digest(self, &previous)?;
+        }
+        Ok(digest)
+    }
+
+    fn cached_avatar(&self, digest: &str) -> Result<Option<Vec<u8>>, String> {
+        self.get(AVATAR_NAMESPACE, digest)
+    }
+
+    fn avatar_digest_of(&self, owner: &str) -> Result<Option<String>, String> {
+        self.get(AVATAR_OWNER_NAMESPACE, owner)?.map(decode_owner_digest).transpose()
+    }
+
+    fn remove_avatar(&mut self, owner: &str) -> Result<bool, String> {
+        let Some(digest) = self.avatar_digest_of(owner)? else {
+            return Ok(false);
+        };
+        self.remove(AVATAR_OWNER_NAMESPACE, owner)?;
+        release_digest(self, &digest)?;
+        Ok(true)
+    }
+}
+
+/// 非同步頭像快取 (所有 `AsyncKeyValueStore` 皆可使用)
+#[allow(async_fn_in_trait)]
+pub trait AsyncAvatarCache {
+    async fn cache_avatar(&mut self, owner: &str, ciphertext: &[u8]) -> Result<String, String>;
+    async fn cached_avatar(&self, digest: &str) -> Result<Option<Vec<u8>>, String>;
+    async fn avatar_digest_of(&self, owner: &str) -> Result<Option<String>, String>;
+    async fn remove_avatar(&mut self, owner: &str) -> Result<bool, String>;
+
+    async fn avatar_of(&self, owner: &str) -> Result<Option<Vec<u8>>, String> {
+        match self.avatar_digest_of(owner).await? {
+            Some(digest) => self.cached_avatar(&digest).await,
+            None => Ok(None),
+        }
+    }
+}
+
+async fn release_digest_async<S: AsyncKeyValueStore + ?Sized>(store: &mut S, digest: &str) -> Result<(), String> {
+    for owner in store.list(AVATAR_OWNER_NAMESPACE).await? {
+        if let Some(value) = store.get(AVATAR_OWNER_NAMESPACE, &owner).await? {
+            if decode_owner_digest(value)? == digest {
+                return Ok(());
+            }
+        }
+    }
+    store.remove(AVATAR_NAMESPACE, digest).await?;
+    Ok(())
+}
+
+impl<S: AsyncKeyValueStore> AsyncAvatarCache for S {
+    async fn cache_avatar(&mut self, owner: &str, ciphertext: &[u8]) -> Result<String, String> {
+        let digest = avatar_digest(ciphertext);
+        let previous = AsyncAvatarCache::avatar_digest_of(self, owner).await?;
+        if self.get(AVATAR_NAMESPACE, &digest).await?.is_none() {
+            self.put(AVATAR_NAMESPACE, &digest, ciphertext).await?;
+        }
+        self.put(AVATAR_OWNER_NAMESPACE, owner, digest.as_bytes()).await?;
+        if let Some(previous) = previous.filter(|previous| *previous != digest) {
+            release_digest_async(self, &previous).await?;
+        }
+        Ok(digest)
+    }
+
+    async fn cached_avatar(&self, digest: &str) -> Result<Option<Vec<u8>>, String> {
+        self.get(AVATAR_NAMESPACE, digest).await
+    }
+
+    async fn avatar_digest_of(&self, owner: &str) -> Result<Option<String>, String> {
+        self.get(AVATAR_OWNER_NAMESPACE, owner).await?.map(decode_owner_digest).transpose()
+    }
+
+    async fn remove_avatar(&mut self, owner: &str) -> Result<bool, String> {
+        let Some(digest) = AsyncAvatarCache::avatar_digest_of(self, owner).await? else {
+            return Ok(false);
+        };
+        self.remove(AVATAR_OWNER_NAMESPACE, owner).await?;
+        release_digest_async(self, &digest).await?;
+        Ok(true)
+    }
+}
+
+#[wasm_bindgen]
+impl InMemoryKeyValueStore {
+    /// 存入擁有者 (聯絡人或群組 ID) 的頭像密文，回傳摘要
+    #[wasm_bindgen(js_name = cacheAvatar)]
+    pub fn cache_avatar_js(&mut self, owner: &str, ciphertext: &[u8]) -> Result<String, JsError> {
+        self.cache_avatar(owner, ciphertext).map_err(|e| JsError::new(&e))
+    }
+
+    /// 依摘要讀取頭像密文
+    #[wasm_bindgen(js_name = cachedAvatar)]
+    pub fn cached_avatar_js(&self, digest: &str) -> Result<Option<Vec<u8>>, JsError> {
+        self.cached_avatar(digest).map_err(|e| JsError::new(&e))
+    }
+
+    /// 擁有者目前頭像的摘要 (可與伺服器提供的摘要比對，決定是否需要重新下載)
+    #[wasm_bindgen(js_name = avatarDigestOf)]
+    pub fn avatar_digest_of_js(&self, owner: &str) -> Result<Option<String>, JsError> {
+        self.avatar_digest_of(owner).map_err(|e| JsError::new(&e))
+    }
+
+    /// 擁有者目前的頭像密文
+    #[wasm_bindgen(js_name = avatarOf)]
+    pub fn avatar_of_js(&self, owner: &str) -> Result<Option<Vec<u8>>, JsError> {
+        self.avatar_of(owner).map_err(|e| JsError::new(&e))
+    }
+
+    /// 移除擁有者的頭像，回傳是否存在
+    #[wasm_bindgen(js_name = removeAvatar)]
+    pub fn remove_avatar_js(&mut self, owner: &str) -> Result<bool, JsError> {
+        self.remove_avatar(owner).map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::avatar::{open_avatar, seal_avatar};
+
+    #[test]
+    fn test_avatar_cache_dedup_and_release() {
+        let profile_key = [9u8; 32];
+        let ciphertext = seal_avatar(&profile_key, b"avatar").unwrap();
+        let mut store = InMemoryKeyValueStore::new();
+
+        let digest = store.cache_avatar("group-a", &ciphertext).unwrap();
+        assert_eq!(store.cache_avatar("group-b", &ciphertext).unwrap(), digest);
+        assert_eq!(store.list(AVATAR_NAMESPACE).unwrap(), vec![digest.clone()]);
+        assert_eq!(open_avatar(&profile_key, &store.avatar_of("group-b").unwrap().unwrap()).unwrap(), b"avatar");
+
+        // 仍有其他擁有者參照時保留密文
+        let replacement = seal_avatar(&profile_key, b"new avatar").unwrap();
+        store.cache_avatar("group-a", &replacement).unwrap();
+        assert!(store.cached_avatar(&digest).unwrap().is_some());
+
+        assert!(store.remove_avatar("group-b").unwrap());
+        assert!(!store.remove_avatar("group-b").unwrap());
+        assert!(store.cached_avatar(&digest).unwrap().is_none());
+        assert_eq!(store.list(AVATAR_NAMESPACE).unwrap(), vec![avatar_digest(&replacement)]);
+    }
+}
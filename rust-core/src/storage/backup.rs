@@ -0,0 +1,1147 @@
+//! 完整加密備份模組
+//!
+//! 把協定儲存 (身份金鑰、聯絡人身份與撤銷記錄、會話、預金鑰)、應用層儲存
+//! (聯絡人、群組、設定、鍵值儲存) 與訊息記錄匯出為單一有版本的封存檔，以備份金鑰
+//! 串流加密 (`StreamCipher`)。寫入與讀取都逐筆記錄進行，不必先把整份封存組成一個緩衝區
+//!
+//! 封存格式 (見 `archive` 模組)：
+//! `magic "STBK" || version (1) || kdf 長度 (u16 BE) || kdf 參數字串 || STREAM 標頭 || 密文`
+//!
+//! - 備份金鑰由密碼衍生 (kdf 參數字串含 salt)，或來自金鑰階層
+//!   (`KeyDerivation::backup_key`，此時 kdf 長度為 0)
+//! - 串流金鑰以 HKDF 從備份金鑰與封存標頭衍生，標頭被竄改時解密失敗
+//! - 明文為一連串長度前綴 (u32 BE) 的 bincode 記錄，最後一筆為 manifest：
+//!   schema 版本、各檔案 (協定儲存、保留政策、訊息、應用層儲存) 的記錄數與 SHA-256 摘要，
+//!   以備份中的身份金鑰簽署 (網域分隔簽章)，並以備份金鑰衍生的 MAC 金鑰鑑別
+//! - manifest 之後以 Padmé 填充明文 (見 `padding` 模組)，封存大小不洩漏確切的訊息量
+//! - STREAM 的結束旗標與 manifest 一起確保封存完整；`restore` 在 manifest 驗證通過前
+//!   不回傳任何內容。匯入持久化儲存時應先以 `verify` 完整驗證，再重新開啟逐筆匯入，
+//!   被截斷或竄改的備份不會留下匯入一半的資料庫
+//!
+//! 增量備份：
+//! - 每份備份完成時取得檢查點 (`BackupCheckpoint`)，記錄備份 ID 與訊息儲存的變更序號
+//! - `write_delta` 只寫入檢查點之後寫入或刪除的訊息；協定儲存、應用層儲存與保留政策較小，每次完整寫入
+//! - manifest 記錄在鏈中的序號與上一份備份的 ID (簽章涵蓋)，`restore_backup_chain`
+//!   從最後一份完整備份依序驗證每個連結後才套用增量
+
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL, Engine as _};
+use sha2::{Digest, Sha256};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+use crate::crypto::mac::{hmac_sha256_bytes, hmac_sha256_verify};
+use crate::crypto::{IdentityKeyPair, PasswordKdf, PasswordKdfParams};
+use super::archive::{ArchiveFormat, ArchiveReader, ArchiveWriter};
+use super::contacts::InMemoryContactStore;
+use super::groups::InMemoryGroupStore;
+use super::kv::InMemoryKeyValueStore;
+use super::messages::{InMemoryMessageStore, MessageStore, RetentionPolicy, StoredMessage};
+use super::settings::InMemorySettingsStore;
+use super::store::{IdentityKeyStore, InMemoryProtocolStore};
+
+pub use super::archive::BackupSecret;
+
+/// 備份封存格式版本
+pub const BACKUP_VERSION: u8 = 4;
+const BACKUP_FORMAT: ArchiveFormat = ArchiveFormat {
+    magic: b"STBK",
+    version: BACKUP_VERSION,
+    key_info: b"SafeTalk-Backup-Stream-v1",
+    name: "Backup",
+};
+
+/// manifest 簽章的用途字串
+const MANIFEST_SIGNATURE_CONTEXT: &str = "backup-manifest";
+
+/// manifest 列出的檔案 (記錄類別)，順序與 `file_index` 相同
+const MANIFEST_FILES: [&str; 8] = [
+    "protocol_store",
+    "retention_policies",
+    "messages",
+    "removed_messages",
+    "contacts",
+    "groups",
+    "settings",
+    "kv",
+];
+
+/// manifest 中一個檔案的記錄數與摘要
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackupManifestFile {
+    pub name: String,
+    pub records: u64,
+    /// 依寫入順序對每筆記錄的 `長度 (u32 BE) || bincode` 計算的 SHA-256
+    pub digest: [u8; 32],
+}
+
+/// 備份 manifest
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BackupManifest {
+    schema_version: u8,
+    /// 在備份鏈中的序號，0 為完整備份
+    sequence: u32,
+    /// 上一份備份的 ID (增量備份)
+    parent: Option<[u8; 32]>,
+    files: Vec<BackupManifestFile>,
+    /// 簽署者 (備份中的身份金鑰) 的 Ed25519 公鑰
+    identity_key: Vec<u8>,
+    signature: Vec<u8>,
+    mac: Vec<u8>,
+}
+
+impl BackupManifest {
+    /// 簽章與 MAC 涵蓋的內容
+    fn payload(&self) -> Result<Vec<u8>, String> {
+        bincode::serialize(&(self.schema_version, self.sequence, self.parent, &self.files, &self.identity_key))
+            .map_err(|e| e.to_string())
+    }
+
+    fn seal(
+        files: Vec<BackupManifestFile>,
+        chain: Option<&BackupCheckpoint>,
+        identity: &IdentityKeyPair,
+        mac_key: &[u8],
+    ) -> Result<Self, String> {
+        let mut manifest = Self {
+            schema_version: BACKUP_VERSION,
+            sequence: chain.map_or(0, |checkpoint| checkpoint.sequence + 1),
+            parent: chain.map(|checkpoint| checkpoint.backup_id),
+            files,
+            identity_key: identity.public_key_bytes(),
+            signature: Vec::new(),
+            mac: Vec::new(),
+        };
+        let payload = manifest.payload()?;
+        manifest.signature = identity.sign_for_context(MANIFEST_SIGNATURE_CONTEXT, &payload)?;
+        manifest.mac = hmac_sha256_bytes(mac_key, &[payload, manifest.signature.clone()].concat()).to_vec();
+        Ok(manifest)
+    }
+
+    /// 驗證 MAC、簽章，並與實際讀到的記錄比對
+    fn verify(&self, files: &[BackupManifestFile], identity_key: Option<&[u8]>, mac_key: &[u8]) -> Result<(), String> {
+        let payload = self.payload()?;
+        if !hmac_sha256_verify(mac_key, &[payload.as_slice(), &self.signature].concat(), &self.mac) {
+            return Err("Backup manifest authentication failed".to_string());
+        }
+        if !IdentityKeyPair::verify_for_context(&self.identity_key, MANIFEST_SIGNATURE_CONTEXT, &payload, &self.signature) {
+            return Err("Backup manifest signature is invalid".to_string());
+        }
+        if self.schema_version != BACKUP_VERSION {
+            return Err(format!("Unsupported backup schema version: {}", self.schema_version));
+        }
+        if identity_key != Some(self.identity_key.as_slice()) {
+            return Err("Backup manifest is not signed by the backed up identity".to_string());
+        }
+        if self.files != files {
+            return Err(BACKUP_FORMAT.incomplete("records do not match manifest"));
+        }
+        Ok(())
+    }
+
+    /// 確認此備份直接接在 `previous` 之後
+    fn verify_follows(&self, previous: &BackupManifest) -> Result<(), String> {
+        if self.parent.is_none() {
+            return Err("Backup chain contains more than one full backup".to_string());
+        }
+        if self.parent != Some(previous.backup_id()?) || self.sequence != previous.sequence + 1 {
+            return Err(format!("Backup chain is broken at sequence {}", self.sequence));
+        }
+        if self.identity_key != previous.identity_key {
+            return Err("Backup chain is signed by different identities".to_string());
+        }
+        Ok(())
+    }
+
+    /// 檔案清單 (Rust 端使用)
+    pub fn files(&self) -> &[BackupManifestFile] {
+        &self.files
+    }
+
+    /// 備份 ID (manifest 簽署內容的 SHA-256，Rust 端使用)
+    pub fn backup_id(&self) -> Result<[u8; 32], String> {
+        Ok(Sha256::digest(self.payload()?).into())
+    }
+
+    fn file_records(&self, name: &str) -> u64 {
+        self.files.iter().filter(|file| file.name == name).map(|file| file.records).sum()
+    }
+}
+
+#[wasm_bindgen]
+impl BackupManifest {
+    #[wasm_bindgen(getter, js_name = schemaVersion)]
+    pub fn schema_version(&self) -> u8 {
+        self.schema_version
+    }
+
+    /// 在備份鏈中的序號 (0 為完整備份)
+    #[wasm_bindgen(getter)]
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+
+    #[wasm_bindgen(getter, js_name = isIncremental)]
+    pub fn is_incremental(&self) -> bool {
+        self.parent.is_some()
+    }
+
+    #[wasm_bindgen(getter, js_name = backupId)]
+    pub fn backup_id_js(&self) -> Result<Vec<u8>, JsError> {
+        self.backup_id().map(|id| id.to_vec()).map_err(|e| JsError::new(&e))
+    }
+
+    /// 簽署者的身份公鑰，還原到既有裝置時可與目前的身份比對
+    #[wasm_bindgen(getter, js_name = identityKey)]
+    pub fn identity_key(&self) -> Vec<u8> {
+        self.identity_key.clone()
+    }
+
+    /// 記錄總數
+    #[wasm_bindgen(getter, js_name = recordCount)]
+    pub fn record_count(&self) -> u64 {
+        self.files.iter().map(|file| file.records).sum()
+    }
+
+    #[wasm_bindgen(getter, js_name = messageCount)]
+    pub fn message_count(&self) -> u64 {
+        self.file_records("messages")
+    }
+
+    #[wasm_bindgen(getter, js_name = retentionPolicyCount)]
+    pub fn retention_policy_count(&self) -> u64 {
+        self.file_records("retention_policies")
+    }
+
+    /// 增量備份中刪除的訊息數
+    #[wasm_bindgen(getter, js_name = removedMessageCount)]
+    pub fn removed_message_count(&self) -> u64 {
+        self.file_records("removed_messages")
+    }
+}
+
+/// 增量備份的檢查點
+///
+/// 完成一份備份後取得，下一次增量備份時提供；只能用於產生它的訊息儲存
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct BackupCheckpoint {
+    backup_id: [u8; 32],
+    sequence: u32,
+    /// 訊息儲存的變更序號來源與備份當時的序號
+    epoch: u64,
+    revision: u64,
+}
+
+#[wasm_bindgen]
+impl BackupCheckpoint {
+    /// 編碼為可保存的字串
+    #[wasm_bindgen(js_name = toToken)]
+    pub fn to_token(&self) -> Result<String, JsError> {
+        let bytes = bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))?;
+        Ok(BASE64URL.encode(bytes))
+    }
+
+    #[wasm_bindgen(js_name = fromToken)]
+    pub fn from_token(token: &str) -> Result<BackupCheckpoint, JsError> {
+        BASE64URL
+            .decode(token)
+            .ok()
+            .and_then(|bytes| bincode::deserialize(&bytes).ok())
+            .ok_or_else(|| JsError::new("Invalid backup checkpoint"))
+    }
+
+    /// 檢查點所在備份的 ID
+    #[wasm_bindgen(getter, js_name = backupId)]
+    pub fn backup_id(&self) -> Vec<u8> {
+        self.backup_id.to_vec()
+    }
+
+    /// 檢查點所在備份在鏈中的序號
+    #[wasm_bindgen(getter)]
+    pub fn sequence(&self) -> u32 {
+        self.sequence
+    }
+}
+
+impl BackupCheckpoint {
+    fn for_store(manifest: &BackupManifest, messages: &InMemoryMessageStore) -> Result<Self, String> {
+        let (epoch, revision) = messages.revision();
+        Ok(Self { backup_id: manifest.backup_id()?, sequence: manifest.sequence, epoch, revision })
+    }
+
+    fn check_store(&self, messages: &InMemoryMessageStore) -> Result<(), String> {
+        let (epoch, revision) = messages.revision();
+        if epoch != self.epoch || revision < self.revision {
+            return Err("Backup checkpoint belongs to a different message store".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// 備份中的應用層儲存：聯絡人、群組、設定與鍵值儲存
+#[wasm_bindgen]
+#[derive(Clone, Default)]
+pub struct BackupAppStores {
+    contacts: InMemoryContactStore,
+    groups: InMemoryGroupStore,
+    settings: InMemorySettingsStore,
+    kv: InMemoryKeyValueStore,
+}
+
+impl BackupAppStores {
+    /// 取出聯絡人、群組、設定與鍵值儲存
+    pub fn into_parts(self) -> (InMemoryContactStore, InMemoryGroupStore, InMemorySettingsStore, InMemoryKeyValueStore) {
+        (self.contacts, self.groups, self.settings, self.kv)
+    }
+
+    /// 套用應用層儲存的記錄，其他記錄原樣回傳
+    fn apply(&mut self, record: BackupRecord) -> Option<BackupRecord> {
+        match record {
+            BackupRecord::Contacts(contacts) => self.contacts = *contacts,
+            BackupRecord::Groups(groups) => self.groups = *groups,
+            BackupRecord::Settings(settings) => self.settings = *settings,
+            BackupRecord::KeyValues(kv) => self.kv = *kv,
+            record => return Some(record),
+        }
+        None
+    }
+}
+
+#[wasm_bindgen]
+impl BackupAppStores {
+    /// 複製要備份的應用層儲存
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        contacts: &InMemoryContactStore,
+        groups: &InMemoryGroupStore,
+        settings: &InMemorySettingsStore,
+        kv: &InMemoryKeyValueStore,
+    ) -> Self {
+        Self { contacts: contacts.clone(), groups: groups.clone(), settings: settings.clone(), kv: kv.clone() }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn contacts(&self) -> InMemoryContactStore {
+        self.contacts.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn groups(&self) -> InMemoryGroupStore {
+        self.groups.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn settings(&self) -> InMemorySettingsStore {
+        self.settings.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = keyValueStore)]
+    pub fn kv(&self) -> InMemoryKeyValueStore {
+        self.kv.clone()
+    }
+}
+
+/// 累計各檔案的記錄數與摘要 (寫入與讀取共用)
+#[derive(Default)]
+struct ManifestDigests {
+    hashers: [Sha256; MANIFEST_FILES.len()],
+    records: [u64; MANIFEST_FILES.len()],
+}
+
+impl ManifestDigests {
+    fn add(&mut self, file: usize, bytes: &[u8]) {
+        self.hashers[file].update((bytes.len() as u32).to_be_bytes());
+        self.hashers[file].update(bytes);
+        self.records[file] += 1;
+    }
+
+    fn files(&self) -> Vec<BackupManifestFile> {
+        MANIFEST_FILES
+            .iter()
+            .zip(&self.hashers)
+            .zip(self.records)
+            .map(|((name, hasher), records)| BackupManifestFile {
+                name: name.to_string(),
+                records,
+                digest: hasher.clone().finalize().into(),
+            })
+            .collect()
+    }
+}
+
+/// 備份封存中的一筆記錄
+#[derive(Clone, Serialize, Deserialize)]
+pub enum BackupRecord {
+    /// 身份金鑰、聯絡人身份 (TOFU 與撤銷)、會話與預金鑰
+    ProtocolStore(Box<InMemoryProtocolStore>),
+    Message(StoredMessage),
+    RetentionPolicy { conversation_id: String, policy: RetentionPolicy },
+    /// 增量備份：檢查點之後刪除的訊息
+    MessageRemoved { id: String },
+    Contacts(Box<InMemoryContactStore>),
+    Groups(Box<InMemoryGroupStore>),
+    Settings(Box<InMemorySettingsStore>),
+    KeyValues(Box<InMemoryKeyValueStore>),
+}
+
+/// 寫入用的記錄 (借用)，編碼與 `Entry` 相同
+#[derive(Serialize)]
+enum EntryRef<'a> {
+    ProtocolStore(&'a InMemoryProtocolStore),
+    Message(&'a StoredMessage),
+    RetentionPolicy { conversation_id: &'a str, policy: RetentionPolicy },
+    Manifest(&'a BackupManifest),
+    MessageRemoved { id: &'a str },
+    Contacts(&'a InMemoryContactStore),
+    Groups(&'a InMemoryGroupStore),
+    Settings(&'a InMemorySettingsStore),
+    KeyValues(&'a InMemoryKeyValueStore),
+}
+
+impl EntryRef<'_> {
+    /// 記錄所屬的 manifest 檔案
+    fn file_index(&self) -> Option<usize> {
+        match self {
+            EntryRef::ProtocolStore(_) => Some(0),
+            EntryRef::RetentionPolicy { .. } => Some(1),
+            EntryRef::Message(_) => Some(2),
+            EntryRef::MessageRemoved { .. } => Some(3),
+            EntryRef::Contacts(_) => Some(4),
+            EntryRef::Groups(_) => Some(5),
+            EntryRef::Settings(_) => Some(6),
+            EntryRef::KeyValues(_) => Some(7),
+            EntryRef::Manifest(_) => None,
+        }
+    }
+}
+
+/// 讀取用的記錄，`Manifest` 只在封存內部使用
+#[derive(Deserialize)]
+enum Entry {
+    ProtocolStore(Box<InMemoryProtocolStore>),
+    Message(StoredMessage),
+    RetentionPolicy { conversation_id: String, policy: RetentionPolicy },
+    Manifest(BackupManifest),
+    MessageRemoved { id: String },
+    Contacts(Box<InMemoryContactStore>),
+    Groups(Box<InMemoryGroupStore>),
+    Settings(Box<InMemorySettingsStore>),
+    KeyValues(Box<InMemoryKeyValueStore>),
+}
+
+/// 串流寫入備份封存
+///
+/// `finish` 以寫入的協定儲存中的身份金鑰簽署 manifest，因此必須寫入協定儲存
+pub struct BackupWriter<W: Write> {
+    archive: ArchiveWriter<W>,
+    digests: ManifestDigests,
+    identity: Option<IdentityKeyPair>,
+    /// 增量備份接續的檢查點
+    parent: Option<BackupCheckpoint>,
+    /// 寫入的訊息儲存 (取得檢查點用)
+    revision: Option<(u64, u64)>,
+}
+
+impl<W: Write> BackupWriter<W> {
+    /// 以備份金鑰建立寫入器並寫出標頭
+    pub fn with_key(output: W, key: &[u8]) -> Result<Self, String> {
+        Ok(Self {
+            archive: ArchiveWriter::with_key(&BACKUP_FORMAT, output, key)?,
+            digests: ManifestDigests::default(),
+            identity: None,
+            parent: None,
+            revision: None,
+        })
+    }
+
+    /// 以密碼建立寫入器 (以 `kdf` 參數與隨機 salt 衍生備份金鑰) 並寫出標頭
+    pub fn with_password(output: W, password: &[u8], kdf: &PasswordKdfParams) -> Result<Self, String> {
+        Ok(Self {
+            archive: ArchiveWriter::with_password(&BACKUP_FORMAT, output, password, kdf)?,
+            digests: ManifestDigests::default(),
+            identity: None,
+            parent: None,
+            revision: None,
+        })
+    }
+
+    fn write_record(&mut self, entry: &EntryRef) -> Result<(), String> {
+        let bytes = Zeroizing::new(bincode::serialize(entry).map_err(|e| e.to_string())?);
+        self.archive.write_record(&bytes)?;
+        if let Some(file) = entry.file_index() {
+            self.digests.add(file, &bytes);
+        }
+        Ok(())
+    }
+
+    /// 寫入協定儲存 (身份金鑰、聯絡人、會話、預金鑰)
+    pub fn write_protocol_store(&mut self, store: &InMemoryProtocolStore) -> Result<(), String> {
+        self.write_record(&EntryRef::ProtocolStore(store))?;
+        self.identity = Some(store.identity_key_pair()?);
+        Ok(())
+    }
+
+    /// 寫入應用層儲存 (聯絡人、群組、設定、鍵值)
+    pub fn write_app_stores(&mut self, stores: &BackupAppStores) -> Result<(), String> {
+        self.write_record(&EntryRef::Contacts(&stores.contacts))?;
+        self.write_record(&EntryRef::Groups(&stores.groups))?;
+        self.write_record(&EntryRef::Settings(&stores.settings))?;
+        self.write_record(&EntryRef::KeyValues(&stores.kv))
+    }
+
+    /// 寫入一則訊息
+    pub fn write_message(&mut self, message: &StoredMessage) -> Result<(), String> {
+        self.write_record(&EntryRef::Message(message))
+    }
+
+    /// 寫入對話的保留政策
+    pub fn write_retention_policy(&mut self, conversation_id: &str, policy: RetentionPolicy) -> Result<(), String> {
+        self.write_record(&EntryRef::RetentionPolicy { conversation_id, policy })
+    }
+
+    fn write_store_state(
+        &mut self,
+        store: &InMemoryProtocolStore,
+        app: &BackupAppStores,
+        messages: &InMemoryMessageStore,
+    ) -> Result<(), String> {
+        self.write_protocol_store(store)?;
+        self.write_app_stores(app)?;
+        for (conversation_id, policy) in messages.retention_policies()? {
+            self.write_retention_policy(&conversation_id, policy)?;
+        }
+        self.revision = Some(messages.revision());
+        Ok(())
+    }
+
+    /// 寫入協定儲存、應用層儲存與訊息儲存中的所有內容
+    pub fn write_stores(
+        &mut self,
+        store: &InMemoryProtocolStore,
+        app: &BackupAppStores,
+        messages: &InMemoryMessageStore,
+    ) -> Result<(), String> {
+        self.write_store_state(store, app, messages)?;
+        for message in messages.messages() {
+            self.write_message(message)?;
+        }
+        Ok(())
+    }
+
+    /// 寫入增量備份：協定儲存、應用層儲存、保留政策，以及檢查點之後寫入與刪除的訊息
+    ///
+    /// 必須是寫入器的唯一內容
+    pub fn write_delta(
+        &mut self,
+        store: &InMemoryProtocolStore,
+        app: &BackupAppStores,
+        messages: &InMemoryMessageStore,
+        since: &BackupCheckpoint,
+    ) -> Result<(), String> {
+        since.check_store(messages)?;
+        if self.digests.records.iter().any(|records| *records > 0) {
+            return Err("Incremental backup must be the only content of the archive".to_string());
+        }
+        self.parent = Some(since.clone());
+        self.write_store_state(store, app, messages)?;
+        for message in messages.messages_since(since.revision) {
+            self.write_message(message)?;
+        }
+        for id in messages.removed_since(since.revision) {
+            self.write_record(&EntryRef::MessageRemoved { id })?;
+        }
+        Ok(())
+    }
+
+    fn seal(&mut self) -> Result<BackupManifest, String> {
+        let identity = self.identity.take().ok_or("Backup does not contain a protocol store")?;
+        let manifest =
+            BackupManifest::seal(self.digests.files(), self.parent.as_ref(), &identity, self.archive.manifest_key())?;
+        self.write_record(&EntryRef::Manifest(&manifest))?;
+        Ok(manifest)
+    }
+
+    /// 寫入簽署的 manifest、填充並結束串流，回傳輸出端
+    pub fn finish(mut self) -> Result<W, String> {
+        self.seal()?;
+        self.archive.finish_padded()
+    }
+
+    /// 寫入簽署的 manifest、填充並結束串流，回傳輸出端與下一次增量備份的檢查點
+    ///
+    /// 需以 `write_stores` 或 `write_delta` 寫入
+    pub fn finish_with_checkpoint(mut self) -> Result<(W, BackupCheckpoint), String> {
+        let (epoch, revision) = self.revision.ok_or("Backup checkpoint requires write_stores or write_delta")?;
+        let manifest = self.seal()?;
+        let checkpoint = BackupCheckpoint { backup_id: manifest.backup_id()?, sequence: manifest.sequence, epoch, revision };
+        Ok((self.archive.finish_padded()?, checkpoint))
+    }
+}
+
+/// 串流讀取備份封存
+///
+/// `next_record` 逐筆解密回傳記錄；在回傳 None 之前 manifest 尚未驗證，
+/// 被截斷的封存會在讀到最後時才失敗，匯入持久化儲存前應先呼叫 `verify`
+pub struct BackupReader<R: Read> {
+    archive: ArchiveReader<R>,
+    digests: ManifestDigests,
+    /// 讀到的協定儲存中的身份公鑰
+    identity_key: Option<Vec<u8>>,
+    /// 已驗證的 manifest (讀到結尾後)
+    manifest: Option<BackupManifest>,
+}
+
+impl<R: Read> BackupReader<R> {
+    /// 讀取封存標頭並以備份金鑰或密碼開啟
+    pub fn open(input: R, secret: BackupSecret) -> Result<Self, String> {
+        Ok(Self {
+            archive: ArchiveReader::open(&BACKUP_FORMAT, input, secret)?,
+            digests: ManifestDigests::default(),
+            identity_key: None,
+            manifest: None,
+        })
+    }
+
+    /// 讀取下一筆記錄；讀到結尾且 manifest 驗證通過時回傳 None
+    pub fn next_record(&mut self) -> Result<Option<BackupRecord>, String> {
+        if self.manifest.is_some() {
+            return Ok(None);
+        }
+        let bytes = self
+            .archive
+            .next_record()?
+            .ok_or_else(|| BACKUP_FORMAT.incomplete("missing manifest"))?;
+        let (file, record) = match bincode::deserialize(&bytes).map_err(|e| BACKUP_FORMAT.incomplete(e))? {
+            Entry::ProtocolStore(store) => {
+                self.identity_key = Some(store.identity_key_pair()?.public_key_bytes());
+                (0, BackupRecord::ProtocolStore(store))
+            }
+            Entry::RetentionPolicy { conversation_id, policy } => {
+                (1, BackupRecord::RetentionPolicy { conversation_id, policy })
+            }
+            Entry::Message(message) => (2, BackupRecord::Message(message)),
+            Entry::MessageRemoved { id } => (3, BackupRecord::MessageRemoved { id }),
+            Entry::Contacts(contacts) => (4, BackupRecord::Contacts(contacts)),
+            Entry::Groups(groups) => (5, BackupRecord::Groups(groups)),
+            Entry::Settings(settings) => (6, BackupRecord::Settings(settings)),
+            Entry::KeyValues(kv) => (7, BackupRecord::KeyValues(kv)),
+            Entry::Manifest(manifest) => {
+                // manifest 之後只能有填充，且串流必須正確結束
+                self.archive.finish_padded()?;
+                manifest.verify(&self.digests.files(), self.identity_key.as_deref(), self.archive.manifest_key())?;
+                self.manifest = Some(manifest);
+                return Ok(None);
+            }
+        };
+        self.digests.add(file, &bytes);
+        Ok(Some(record))
+    }
+
+    /// 已驗證的 manifest (`next_record` 回傳 None 之後)
+    pub fn manifest(&self) -> Option<&BackupManifest> {
+        self.manifest.as_ref()
+    }
+
+    /// 讀完整份封存並驗證 manifest，不保留任何記錄
+    pub fn verify(mut self) -> Result<BackupManifest, String> {
+        while self.next_record()?.is_some() {}
+        self.manifest.ok_or_else(|| BACKUP_FORMAT.incomplete("missing manifest"))
+    }
+
+    /// 讀取整份完整備份，驗證完整後還原成記憶體儲存
+    ///
+    /// 增量備份需以 `restore_backup_chain` 與其完整備份一起還原
+    pub fn restore(mut self) -> Result<RestoredBackup, String> {
+        let mut protocol_store = None;
+        let mut app_stores = BackupAppStores::default();
+        let mut message_store = InMemoryMessageStore::new();
+        while let Some(record) = self.next_record()? {
+            match app_stores.apply(record) {
+                Some(BackupRecord::ProtocolStore(_)) if protocol_store.is_some() => {
+                    return Err("Backup contains more than one protocol store".to_string());
+                }
+                Some(BackupRecord::ProtocolStore(store)) => protocol_store = Some(*store),
+                Some(record) => apply_message_record(&mut message_store, record)?,
+                None => {}
+            }
+        }
+        let manifest = self.manifest.ok_or_else(|| BACKUP_FORMAT.incomplete("missing manifest"))?;
+        if manifest.is_incremental() {
+            return Err("Backup is incremental; restore it together with its chain".to_string());
+        }
+        let protocol_store = protocol_store.ok_or_else(|| "Backup does not contain a protocol store".to_string())?;
+        Ok(RestoredBackup { protocol_store, app_stores, message_store, manifest })
+    }
+
+    /// 讀完整份增量備份並驗證 manifest，回傳所有記錄
+    fn read_delta(mut self) -> Result<(Vec<BackupRecord>, BackupManifest), String> {
+        let mut records = Vec::new();
+        while let Some(record) = self.next_record()? {
+            records.push(record);
+        }
+        let manifest = self.manifest.ok_or_else(|| BACKUP_FORMAT.incomplete("missing manifest"))?;
+        Ok((records, manifest))
+    }
+}
+
+/// 套用訊息與保留政策記錄
+fn apply_message_record(messages: &mut InMemoryMessageStore, record: BackupRecord) -> Result<(), String> {
+    match record {
+        BackupRecord::Message(message) => messages.store_message(&message),
+        BackupRecord::RetentionPolicy { conversation_id, policy } => {
+            messages.set_retention_policy(&conversation_id, Some(policy))
+        }
+        BackupRecord::MessageRemoved { id } => messages.remove_message(&id).map(|_| ()),
+        _ => Err("Unexpected store record".to_string()),
+    }
+}
+
+/// 依序還原完整備份與其後的增量備份
+///
+/// 每份增量備份在完整驗證 manifest 並確認接在前一份之後才套用
+pub fn restore_backup_chain<R: Read>(chain: impl IntoIterator<Item = BackupReader<R>>) -> Result<RestoredBackup, String> {
+    let mut chain = chain.into_iter();
+    let mut restored = chain.next().ok_or("Backup chain is empty")?.restore()?;
+    for reader in chain {
+        let (records, manifest) = reader.read_delta()?;
+        manifest.verify_follows(&restored.manifest)?;
+        // 增量備份包含完整的保留政策
+        for (conversation_id, _) in restored.message_store.retention_policies()? {
+            restored.message_store.set_retention_policy(&conversation_id, None)?;
+        }
+        for record in records {
+            match restored.app_stores.apply(record) {
+                Some(BackupRecord::ProtocolStore(store)) => restored.protocol_store = *store,
+                Some(record) => apply_message_record(&mut restored.message_store, record)?,
+                None => {}
+            }
+        }
+        restored.manifest = manifest;
+    }
+    Ok(restored)
+}
+
+/// 驗證備份鏈的每份備份與連結，不匯入任何記錄，回傳最後一份的 manifest
+pub fn verify_backup_chain<R: Read>(chain: impl IntoIterator<Item = BackupReader<R>>) -> Result<BackupManifest, String> {
+    let mut latest: Option<BackupManifest> = None;
+    for reader in chain {
+        let manifest = reader.verify()?;
+        match &latest {
+            Some(previous) => manifest.verify_follows(previous)?,
+            None if manifest.is_incremental() => return Err("Backup chain must start with a full backup".to_string()),
+            None => {}
+        }
+        latest = Some(manifest);
+    }
+    latest.ok_or_else(|| "Backup chain is empty".to_string())
+}
+
+/// 從備份還原的內容
+#[wasm_bindgen]
+pub struct RestoredBackup {
+    protocol_store: InMemoryProtocolStore,
+    app_stores: BackupAppStores,
+    message_store: InMemoryMessageStore,
+    manifest: BackupManifest,
+}
+
+impl RestoredBackup {
+    /// 取出協定儲存、應用層儲存與訊息儲存
+    pub fn into_parts(self) -> (InMemoryProtocolStore, BackupAppStores, InMemoryMessageStore) {
+        (self.protocol_store, self.app_stores, self.message_store)
+    }
+}
+
+#[wasm_bindgen]
+impl RestoredBackup {
+    #[wasm_bindgen(getter, js_name = protocolStore)]
+    pub fn protocol_store(&self) -> InMemoryProtocolStore {
+        self.protocol_store.clone()
+    }
+
+    /// 聯絡人、群組、設定與鍵值儲存
+    #[wasm_bindgen(getter, js_name = appStores)]
+    pub fn app_stores(&self) -> BackupAppStores {
+        self.app_stores.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = messageStore)]
+    pub fn message_store(&self) -> InMemoryMessageStore {
+        self.message_store.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn manifest(&self) -> BackupManifest {
+        self.manifest.clone()
+    }
+
+    /// 以還原的訊息儲存繼續備份鏈的檢查點
+    #[wasm_bindgen(getter)]
+    pub fn checkpoint(&self) -> Result<BackupCheckpoint, JsError> {
+        BackupCheckpoint::for_store(&self.manifest, &self.message_store).map_err(|e| JsError::new(&e))
+    }
+}
+
+/// 匯出的備份與下一次增量備份的檢查點
+#[wasm_bindgen]
+pub struct BackupExport {
+    archive: Vec<u8>,
+    checkpoint: BackupCheckpoint,
+}
+
+#[wasm_bindgen]
+impl BackupExport {
+    #[wasm_bindgen(getter)]
+    pub fn archive(&self) -> Vec<u8> {
+        self.archive.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn checkpoint(&self) -> BackupCheckpoint {
+        self.checkpoint.clone()
+    }
+}
+
+#[wasm_bindgen]
+impl InMemoryMessageStore {
+    /// 丟棄檢查點之前的刪除記錄 (確認該備份已上傳後呼叫)，回傳丟棄的數量
+    ///
+    /// 之後無法再以更早的檢查點產生增量備份
+    #[wasm_bindgen(js_name = pruneBackupTombstones)]
+    pub fn prune_backup_tombstones(&mut self, checkpoint: &BackupCheckpoint) -> Result<u32, JsError> {
+        checkpoint.check_store(self).map_err(|e| JsError::new(&e))?;
+        Ok(self.prune_tombstones(checkpoint.revision) as u32)
+    }
+}
+
+/// 以備份金鑰匯出完整備份
+#[wasm_bindgen(js_name = exportBackup)]
+pub fn export_backup(
+    store: &InMemoryProtocolStore,
+    app: &BackupAppStores,
+    messages: &InMemoryMessageStore,
+    key: &[u8],
+) -> Result<Vec<u8>, JsError> {
+    let export = || {
+        let mut writer = BackupWriter::with_key(Vec::new(), key)?;
+        writer.write_stores(store, app, messages)?;
+        writer.finish()
+    };
+    export().map_err(|e| JsError::new(&e))
+}
+
+/// 以密碼匯出完整備份 (`params` 未提供時使用預設的 Argon2id 參數)
+#[wasm_bindgen(js_name = exportBackupWithPassword)]
+pub fn export_backup_with_password(
+    store: &InMemoryProtocolStore,
+    app: &BackupAppStores,
+    messages: &InMemoryMessageStore,
+    password: &str,
+    params: Option<PasswordKdf>,
+) -> Result<Vec<u8>, JsError> {
+    let params = params.map(|kdf| kdf.params()).unwrap_or_default();
+    let export = || {
+        let mut writer = BackupWriter::with_password(Vec::new(), password.as_bytes(), &params)?;
+        writer.write_stores(store, app, messages)?;
+        writer.finish()
+    };
+    export().map_err(|e| JsError::new(&e))
+}
+
+/// 以備份金鑰匯入完整備份
+#[wasm_bindgen(js_name = importBackup)]
+pub fn import_backup(bytes: &[u8], key: &[u8]) -> Result<RestoredBackup, JsError> {
+    BackupReader::open(bytes, BackupSecret::Key(key))
+        .and_then(BackupReader::restore)
+        .map_err(|e| JsError::new(&e))
+}
+
+/// 以密碼匯入完整備份
+#[wasm_bindgen(js_name = importBackupWithPassword)]
+pub fn import_backup_with_password(bytes: &[u8], password: &str) -> Result<RestoredBackup, JsError> {
+    BackupReader::open(bytes, BackupSecret::Password(password.as_bytes()))
+        .and_then(BackupReader::restore)
+        .map_err(|e| JsError::new(&e))
+}
+
+/// 以備份金鑰匯出完整備份，並取得增量備份的檢查點
+#[wasm_bindgen(js_name = exportBackupWithCheckpoint)]
+pub fn export_backup_with_checkpoint(
+    store: &InMemoryProtocolStore,
+    app: &BackupAppStores,
+    messages: &InMemoryMessageStore,
+    key: &[u8],
+) -> Result<BackupExport, JsError> {
+    let export = || {
+        let mut writer = BackupWriter::with_key(Vec::new(), key)?;
+        writer.write_stores(store, app, messages)?;
+        writer.finish_with_checkpoint()
+    };
+    let (archive, checkpoint) = export().map_err(|e| JsError::new(&e))?;
+    Ok(BackupExport { archive, checkpoint })
+}
+
+/// 以備份金鑰匯出檢查點之後的增量備份
+#[wasm_bindgen(js_name = exportIncrementalBackup)]
+pub fn export_incremental_backup(
+    store: &InMemoryProtocolStore,
+    app: &BackupAppStores,
+    messages: &InMemoryMessageStore,
+    key: &[u8],
+    since: &BackupCheckpoint,
+) -> Result<BackupExport, JsError> {
+    let export = || {
+        let mut writer = BackupWriter::with_key(Vec::new(), key)?;
+        writer.write_delta(store, app, messages, since)?;
+        writer.finish_with_checkpoint()
+    };
+    let (archive, checkpoint) = export().map_err(|e| JsError::new(&e))?;
+    Ok(BackupExport { archive, checkpoint })
+}
+
+/// 以備份金鑰匯入備份鏈 (完整備份在前，之後依序為增量備份)
+#[wasm_bindgen(js_name = importBackupChain)]
+pub fn import_backup_chain(archives: Vec<js_sys::Uint8Array>, key: &[u8]) -> Result<RestoredBackup, JsError> {
+    let archives: Vec<Vec<u8>> = archives.iter().map(js_sys::Uint8Array::to_vec).collect();
+    let readers = archives
+        .iter()
+        .map(|archive| BackupReader::open(archive.as_slice(), BackupSecret::Key(key)))
+        .collect::<Result<Vec<_>, _>>();
+    readers.and_then(restore_backup_chain).map_err(|e| JsError::new(&e))
+}
+
+/// 以備份金鑰驗證備份鏈，不匯入任何記錄，回傳最後一份的 manifest
+#[wasm_bindgen(js_name = verifyBackupChain)]
+pub fn verify_backup_chain_js(archives: Vec<js_sys::Uint8Array>, key: &[u8]) -> Result<BackupManifest, JsError> {
+    let archives: Vec<Vec<u8>> = archives.iter().map(js_sys::Uint8Array::to_vec).collect();
+    let readers = archives
+        .iter()
+        .map(|archive| BackupReader::open(archive.as_slice(), BackupSecret::Key(key)))
+        .collect::<Result<Vec<_>, _>>();
+    readers.and_then(verify_backup_chain).map_err(|e| JsError::new(&e))
+}
+
+/// 以備份金鑰驗證備份的完整性與 manifest，不匯入任何記錄
+#[wasm_bindgen(js_name = verifyBackup)]
+pub fn verify_backup(bytes: &[u8], key: &[u8]) -> Result<BackupManifest, JsError> {
+    BackupReader::open(bytes, BackupSecret::Key(key))
+        .and_then(BackupReader::verify)
+        .map_err(|e| JsError::new(&e))
+}
+
+/// 以密碼驗證備份的完整性與 manifest，不匯入任何記錄
+#[wasm_bindgen(js_name = verifyBackupWithPassword)]
+pub fn verify_backup_with_password(bytes: &[u8], password: &str) -> Result<BackupManifest, JsError> {
+    BackupReader::open(bytes, BackupSecret::Password(password.as_bytes()))
+        .and_then(BackupReader::verify)
+        .map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{
+        Argon2idParams, IdentityKeyPair, KeyDerivation, RatchetSession, DEFAULT_STREAM_CHUNK_SIZE, STREAM_HEADER_SIZE,
+    };
+    use crate::storage::{
+        Contact, ContactStore, Group, GroupStore, IdentityKeyStore, KeyValueStore, SessionStore, SettingValue,
+        SettingsStore, SETTING_READ_RECEIPTS,
+    };
+
+    fn stores() -> (InMemoryProtocolStore, BackupAppStores, InMemoryMessageStore) {
+        let mut store = InMemoryProtocolStore::new(&IdentityKeyPair::new(), 77);
+        store.save_identity("bob", &[1u8; 32], 1).unwrap();
+        store.store_session("bob", 2, &RatchetSession::for_test([3u8; 32])).unwrap();
+        let mut app = BackupAppStores::default();
+        let mut contact = Contact::new("bob");
+        contact.set_nickname(Some("Bobby".to_string()));
+        app.contacts.store_contact(&contact).unwrap();
+        app.groups.store_group(&Group::new("family")).unwrap();
+        app.settings.set_setting(SETTING_READ_RECEIPTS, SettingValue::Boolean(false)).unwrap();
+        app.kv.put("push", "token", b"secret").unwrap();
+        let mut messages = InMemoryMessageStore::new();
+        for i in 0..50u64 {
+            messages.store_message(&StoredMessage::new(&format!("m{}", i), "chat", "bob", i, &[i as u8; 4096])).unwrap();
+        }
+        messages.set_retention_policy("chat", Some(RetentionPolicy::new(Some(1000), None))).unwrap();
+        (store, app, messages)
+    }
+
+    #[test]
+    fn test_backup_round_trip() {
+        let (store, app, messages) = stores();
+        let key = KeyDerivation::from_seed(&[5u8; 32]).unwrap().backup_key();
+        let archive = export_backup(&store, &app, &messages, &key).unwrap();
+        assert!(!archive.windows(4).any(|window| window == b"chat"));
+
+        let (restored, restored_app, restored_messages) = BackupReader::open(archive.as_slice(), BackupSecret::Key(&key))
+            .unwrap()
+            .restore()
+            .unwrap()
+            .into_parts();
+        assert_eq!(
+            restored.identity_key_pair().unwrap().public_key_bytes(),
+            store.identity_key_pair().unwrap().public_key_bytes()
+        );
+        assert_eq!(restored.local_registration_id().unwrap(), 77);
+        assert_eq!(restored.check_identity("bob", &[1u8; 32]).unwrap(), crate::storage::IdentityStatus::Trusted);
+        assert_eq!(restored.session_devices("bob").unwrap(), vec![2]);
+        assert_eq!(restored_messages.size(), 50);
+        assert_eq!(restored_messages.load_message("m7").unwrap().unwrap().body(), vec![7u8; 4096]);
+        assert_eq!(restored_messages.retention_policies().unwrap(), messages.retention_policies().unwrap());
+        let (contacts, groups, settings, kv) = restored_app.into_parts();
+        assert_eq!(contacts.load_contact("bob").unwrap().unwrap().nickname().as_deref(), Some("Bobby"));
+        assert!(groups.load_group("family").unwrap().is_some());
+        assert_eq!(settings.setting(SETTING_READ_RECEIPTS).unwrap(), SettingValue::Boolean(false));
+        assert_eq!(kv.get("push", "token").unwrap().as_deref(), Some(&b"secret"[..]));
+
+        // 以密碼保護的封存
+        let kdf = PasswordKdfParams::Argon2id(Argon2idParams { memory_kib: 64, iterations: 1, parallelism: 1 });
+        let mut writer = BackupWriter::with_password(Vec::new(), b"correct horse", &kdf).unwrap();
+        writer.write_stores(&store, &app, &messages).unwrap();
+        let archive = writer.finish().unwrap();
+        let restored = import_backup_with_password(&archive, "correct horse").unwrap();
+        assert_eq!(restored.message_store().size(), 50);
+        assert!(BackupReader::open(archive.as_slice(), BackupSecret::Password(b"wrong")).unwrap().restore().is_err());
+        assert!(BackupReader::open(archive.as_slice(), BackupSecret::Key(&key)).is_err());
+    }
+
+    #[test]
+    fn test_backup_integrity() {
+        let (store, app, messages) = stores();
+        let key = [9u8; 32];
+        let archive = export_backup(&store, &app, &messages, &key).unwrap();
+        let restore = |bytes: &[u8]| BackupReader::open(bytes, BackupSecret::Key(&key)).and_then(BackupReader::restore);
+        assert!(restore(&archive).is_ok());
+
+        // 截斷 (包含剛好在分段邊界)、竄改密文或標頭、錯誤的金鑰都會失敗
+        let header = BACKUP_FORMAT.magic.len() + 3 + STREAM_HEADER_SIZE;
+        let segment = DEFAULT_STREAM_CHUNK_SIZE as usize + 16;
+        for length in [archive.len() - 1, header + segment, header + 10] {
+            assert!(restore(&archive[..length]).is_err());
+        }
+        let mut tampered = archive.clone();
+        tampered[header + 100] ^= 1;
+        assert!(restore(&tampered).is_err());
+        let mut tampered = archive.clone();
+        tampered[4] = BACKUP_VERSION + 1;
+        assert!(restore(&tampered).is_err());
+        assert!(BackupReader::open(archive.as_slice(), BackupSecret::Key(&[8u8; 32])).unwrap().restore().is_err());
+        assert!(restore(b"not a backup").is_err());
+
+        // 缺少填充或填充後還有資料
+        let mut writer = BackupWriter::with_key(Vec::new(), &key).unwrap();
+        writer.write_stores(&store, &app, &messages).unwrap();
+        writer.seal().unwrap();
+        assert!(restore(&writer.archive.finish().unwrap()).is_err());
+        let mut writer = BackupWriter::with_key(Vec::new(), &key).unwrap();
+        writer.write_stores(&store, &app, &messages).unwrap();
+        writer.seal().unwrap();
+        writer.archive.write_record(&[]).unwrap();
+        assert!(restore(&writer.archive.finish_padded().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_backup_manifest() {
+        let (store, app, messages) = stores();
+        let key = [9u8; 32];
+        let archive = export_backup(&store, &app, &messages, &key).unwrap();
+        let verify = |bytes: &[u8]| BackupReader::open(bytes, BackupSecret::Key(&key)).and_then(BackupReader::verify);
+        let manifest = verify(&archive).unwrap();
+        assert_eq!(manifest.schema_version(), BACKUP_VERSION);
+        assert_eq!(manifest.message_count(), 50);
+        assert_eq!(manifest.record_count(), 56);
+        assert_eq!(manifest.identity_key(), store.identity_key_pair().unwrap().public_key_bytes());
+        assert_eq!(manifest.files().iter().map(|file| file.name.as_str()).collect::<Vec<_>>(), MANIFEST_FILES);
+
+        // 沒有協定儲存時無法簽署 manifest
+        let mut writer = BackupWriter::with_key(Vec::new(), &key).unwrap();
+        writer.write_message(messages.messages().next().unwrap()).unwrap();
+        assert!(writer.finish().is_err());
+
+        // 以正確的串流金鑰寫入、但 manifest 少列一則訊息 / 以其他身份簽署 / MAC 金鑰錯誤
+        let forge = |drop_message: bool, signer: &IdentityKeyPair, mac_key: Option<&[u8]>| {
+            let mut writer = BackupWriter::with_key(Vec::new(), &key).unwrap();
+            writer.write_stores(&store, &app, &messages).unwrap();
+            let mut digests = ManifestDigests::default();
+            for message in messages.messages().skip(usize::from(drop_message)) {
+                digests.add(2, &bincode::serialize(&EntryRef::Message(message)).unwrap());
+            }
+            let mut files = writer.digests.files();
+            if drop_message {
+                files[2] = digests.files().remove(2);
+            }
+            let mac_key = mac_key.unwrap_or(writer.archive.manifest_key()).to_vec();
+            let manifest = BackupManifest::seal(files, None, signer, &mac_key).unwrap();
+            writer.write_record(&EntryRef::Manifest(&manifest)).unwrap();
+            writer.archive.finish_padded().unwrap()
+        };
+        let identity = store.identity_key_pair().unwrap();
+        assert!(verify(&forge(false, &identity, None)).is_ok());
+        assert!(verify(&forge(true, &identity, None)).is_err());
+        assert!(verify(&forge(false, &IdentityKeyPair::new(), None)).is_err());
+        assert!(verify(&forge(false, &identity, Some(&[1u8; 32]))).is_err());
+    }
+
+    #[test]
+    fn test_incremental_backup_chain() {
+        let (mut store, mut app, mut messages) = stores();
+        let key = [9u8; 32];
+        fn open(bytes: &[u8]) -> BackupReader<&[u8]> {
+            BackupReader::open(bytes, BackupSecret::Key(&[9u8; 32])).unwrap()
+        }
+        let export = |store: &InMemoryProtocolStore,
+                      app: &BackupAppStores,
+                      messages: &InMemoryMessageStore,
+                      since: Option<&BackupCheckpoint>| {
+            let mut writer = BackupWriter::with_key(Vec::new(), &key).unwrap();
+            match since {
+                Some(since) => writer.write_delta(store, app, messages, since).unwrap(),
+                None => writer.write_stores(store, app, messages).unwrap(),
+            }
+            writer.finish_with_checkpoint().unwrap()
+        };
+        let (full, checkpoint) = export(&store, &app, &messages, None);
+
+        // 增量只包含變更：新增、修改與刪除的訊息
+        messages.store_message(&StoredMessage::new("m50", "chat", "bob", 50, b"new")).unwrap();
+        messages.store_message(&StoredMessage::new("m3", "chat", "bob", 3, b"edited")).unwrap();
+        messages.remove_message("m4").unwrap();
+        messages.set_retention_policy("chat", None).unwrap();
+        store.save_identity("carol", &[2u8; 32], 1).unwrap();
+        app.kv.remove("push", "token").unwrap();
+        let (first, checkpoint) = export(&store, &app, &messages, Some(&checkpoint));
+        assert!(first.len() < full.len() / 10);
+        messages.remove_message("m50").unwrap();
+        let (second, _) = export(&store, &app, &messages, Some(&checkpoint));
+
+        let manifest = verify_backup_chain([open(&full), open(&first), open(&second)]).unwrap();
+        assert_eq!((manifest.sequence(), manifest.removed_message_count()), (2, 1));
+        let first_manifest = open(&first).verify().unwrap();
+        assert_eq!((first_manifest.message_count(), first_manifest.removed_message_count()), (2, 1));
+
+        let restored = restore_backup_chain([open(&full), open(&first), open(&second)]).unwrap();
+        let (restored_store, restored_app, restored_messages) = restored.into_parts();
+        assert_eq!(restored_messages.size(), 49);
+        assert!(restored_messages.load_message("m4").unwrap().is_none());
+        assert!(restored_messages.load_message("m50").unwrap().is_none());
+        assert_eq!(restored_messages.load_message("m3").unwrap().unwrap().body(), b"edited");
+        assert!(restored_messages.retention_policies().unwrap().is_empty());
+        assert_eq!(restored_store.check_identity("carol", &[2u8; 32]).unwrap(), crate::storage::IdentityStatus::Trusted);
+        assert!(restored_app.kv.list("push").unwrap().is_empty());
+
+        // 缺少中間的增量、順序錯誤、只有增量都會在匯入前失敗
+        assert!(restore_backup_chain([open(&full), open(&second)]).is_err());
+        assert!(verify_backup_chain([open(&full), open(&second), open(&first)]).is_err());
+        assert!(open(&first).restore().is_err());
+        assert!(verify_backup_chain([open(&first)]).is_err());
+
+        // 檢查點不能用於其他訊息儲存
+        let mut writer = BackupWriter::with_key(Vec::new(), &key).unwrap();
+        assert!(writer.write_delta(&store, &app, &InMemoryMessageStore::new(), &checkpoint).is_err());
+    }
+}
@@ -0,0 +1,312 @@
+//! 附件 blob 儲存模組
+//!
+//! 大型附件以 STREAM 分段 AEAD (`StreamCipher`) 加密後分成多個 chunk 存放，
+//! 寫入與讀取都是串流進行，整份附件不需同時存在 WASM 記憶體中：
+//! - chunk 0：STREAM 標頭
+//! - chunk 1..n：依序的密文 (chunk 邊界不必與加密分段對齊)
+//!
+//! 最後一段帶有結束旗標，chunk 遺失、截斷或順序錯誤時讀取失敗
+//!
+//! 後端：`InMemoryBlobStore`、`FileBlobStore` (原生建置，檔案系統)、
+//! `IndexedDbStore` (feature = "indexeddb")、`OpfsBlobStore` (feature = "opfs"，瀏覽器端大型附件)
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use crate::crypto::{CipherSuite, StreamCipher, DEFAULT_STREAM_CHUNK_SIZE};
+
+/// 一個已加密的 chunk
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlobChunk {
+    pub index: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// 串流加密寫入：依序把輸出的 chunk 存入 blob 儲存
+///
+/// 覆寫既有的 blob 前需先 `remove_blob`，否則舊的多餘 chunk 會讓讀取失敗
+pub struct BlobWriter {
+    cipher: StreamCipher,
+    next_index: u32,
+}
+
+impl BlobWriter {
+    /// 建立寫入器，同時回傳要最先存入的標頭 chunk
+    pub fn new(key: &[u8], chunk_size: Option<u32>, suite: Option<CipherSuite>) -> Result<(Self, BlobChunk), String> {
+        let cipher = StreamCipher::new_encryptor(
+            key,
+            suite.unwrap_or_default(),
+            chunk_size.unwrap_or(DEFAULT_STREAM_CHUNK_SIZE),
+        )?;
+        let header = BlobChunk { index: 0, bytes: cipher.header() };
+        Ok((Self { cipher, next_index: 1 }, header))
+    }
+
+    fn chunk(&mut self, bytes: Vec<u8>) -> Result<BlobChunk, String> {
+        let index = self.next_index;
+        self.next_index = index.checked_add(1).ok_or_else(|| "Blob chunk index exhausted".to_string())?;
+        Ok(BlobChunk { index, bytes })
+    }
+
+    /// 加密一段明文；累積不滿一個加密分段時不輸出 chunk
+    pub fn write(&mut self, data: &[u8]) -> Result<Option<BlobChunk>, String> {
+        let bytes = self.cipher.push_bytes(data)?;
+        if bytes.is_empty() {
+            return Ok(None);
+        }
+        self.chunk(bytes).map(Some)
+    }
+
+    /// 加密最後一段並結束
+    pub fn finish(&mut self) -> Result<BlobChunk, String> {
+        let bytes = self.cipher.finish_bytes()?;
+        self.chunk(bytes)
+    }
+}
+
+/// 串流解密讀取：依序傳入 chunk 1..n，全部讀完後呼叫 `finish`
+pub struct BlobReader {
+    cipher: StreamCipher,
+    next_index: u32,
+}
+
+impl BlobReader {
+    /// 以標頭 chunk 建立讀取器
+    pub fn new(key: &[u8], header: &[u8]) -> Result<Self, String> {
+        Ok(Self { cipher: StreamCipher::new_decryptor(key, header)?, next_index: 1 })
+    }
+
+    /// 下一個要讀取的 chunk 編號
+    pub fn next_index(&self) -> u32 {
+        self.next_index
+    }
+
+    /// 解密下一個 chunk，回傳目前可輸出的明文 (可能為空)
+    pub fn read(&mut self, chunk: &[u8]) -> Result<Vec<u8>, String> {
+        let plaintext = self.cipher.push_bytes(chunk)?;
+        self.next_index = self.next_index.saturating_add(1);
+        Ok(plaintext)
+    }
+
+    /// 沒有下一個 chunk 時呼叫，驗證結束旗標並回傳最後的明文
+    pub fn finish(&mut self) -> Result<Vec<u8>, String> {
+        self.cipher
+            .finish_bytes()
+            .map_err(|e| format!("Blob is incomplete or corrupted: {}", e))
+    }
+}
+
+/// blob chunk 儲存
+pub trait BlobStore {
+    /// 存入 (覆寫) chunk
+    fn put_chunk(&mut self, blob_id: &str, index: u32, bytes: &[u8]) -> Result<(), String>;
+
+    /// 讀取 chunk
+    fn get_chunk(&self, blob_id: &str, index: u32) -> Result<Option<Vec<u8>>, String>;
+
+    /// 刪除 blob 的所有 chunk，回傳是否存在
+    fn remove_blob(&mut self, blob_id: &str) -> Result<bool, String>;
+
+    /// 從 `reader` 串流加密寫入 blob (覆寫既有的 blob)，回傳明文長度
+    fn write_blob(
+        &mut self,
+        blob_id: &str,
+        key: &[u8],
+        reader: &mut impl Read,
+        chunk_size: Option<u32>,
+        suite: Option<CipherSuite>,
+    ) -> Result<u64, String> {
+        self.remove_blob(blob_id)?;
+        let (mut writer, header) = BlobWriter::new(key, chunk_size, suite)?;
+        self.put_chunk(blob_id, header.index, &header.bytes)?;
+        let mut buffer = vec![0u8; chunk_size.unwrap_or(DEFAULT_STREAM_CHUNK_SIZE) as usize];
+        let mut total = 0u64;
+        loop {
+            let read = reader.read(&mut buffer).map_err(|e| format!("Failed to read blob input: {}", e))?;
+            if read == 0 {
+                break;
+            }
+            total += read as u64;
+            if let Some(chunk) = writer.write(&buffer[..read])? {
+                self.put_chunk(blob_id, chunk.index, &chunk.bytes)?;
+            }
+        }
+        let chunk = writer.finish()?;
+        self.put_chunk(blob_id, chunk.index, &chunk.bytes)?;
+        Ok(total)
+    }
+
+    /// 串流解密 blob 寫到 `writer`，回傳明文長度
+    fn read_blob(&self, blob_id: &str, key: &[u8], writer: &mut impl Write) -> Result<u64, String> {
+        let header = self
+            .get_chunk(blob_id, 0)?
+            .ok_or_else(|| format!("Unknown blob: {}", blob_id))?;
+        let mut reader = BlobReader::new(key, &header)?;
+        let mut total = 0u64;
+        let mut emit = |plaintext: Vec<u8>| {
+            total += plaintext.len() as u64;
+            writer.write_all(&plaintext).map_err(|e| format!("Failed to write blob output: {}", e))
+        };
+        while let Some(chunk) = self.get_chunk(blob_id, reader.next_index())? {
+            emit(reader.read(&chunk)?)?;
+        }
+        emit(reader.finish()?)?;
+        Ok(total)
+    }
+}
+
+/// 非同步 blob chunk 儲存 (IndexedDB 等只能非同步存取的後端)
+#[allow(async_fn_in_trait)]
+pub trait AsyncBlobStore {
+    async fn put_chunk(&mut self, blob_id: &str, index: u32, bytes: &[u8]) -> Result<(), String>;
+    async fn get_chunk(&self, blob_id: &str, index: u32) -> Result<Option<Vec<u8>>, String>;
+    async fn remove_blob(&mut self, blob_id: &str) -> Result<bool, String>;
+}
+
+/// 記憶體 blob 儲存
+#[derive(Clone, Default)]
+pub struct InMemoryBlobStore {
+    /// (blob_id, index) -> chunk
+    chunks: BTreeMap<(String, u32), Vec<u8>>,
+}
+
+impl InMemoryBlobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BlobStore for InMemoryBlobStore {
+    fn put_chunk(&mut self, blob_id: &str, index: u32, bytes: &[u8]) -> Result<(), String> {
+        self.chunks.insert((blob_id.to_string(), index), bytes.to_vec());
+        Ok(())
+    }
+
+    fn get_chunk(&self, blob_id: &str, index: u32) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.chunks.get(&(blob_id.to_string(), index)).cloned())
+    }
+
+    fn remove_blob(&mut self, blob_id: &str) -> Result<bool, String> {
+        let before = self.chunks.len();
+        self.chunks.retain(|(id, _), _| id != blob_id);
+        Ok(self.chunks.len() != before)
+    }
+}
+
+/// 檔案系統 blob 儲存 (原生建置)：`root/<blob_id>/<index>.chunk`
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileBlobStore {
+    root: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileBlobStore {
+    /// blob ID 的最大長度
+    pub const MAX_BLOB_ID_LENGTH: usize = 128;
+
+    /// 以 `root` 目錄存放 blob (不存在時建立)
+    pub fn new(root: impl Into<std::path::PathBuf>) -> Result<Self, String> {
+        let root = root.into();
+        std::fs::create_dir_all(&root).map_err(|e| format!("Failed to create blob directory: {}", e))?;
+        Ok(Self { root })
+    }
+
+    /// blob ID 直接作為目錄名稱，只接受英數字、`-` 與 `_`
+    fn blob_dir(&self, blob_id: &str) -> Result<std::path::PathBuf, String> {
+        let valid = !blob_id.is_empty()
+            && blob_id.len() <= Self::MAX_BLOB_ID_LENGTH
+            && blob_id.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !valid {
+            return Err(format!("Invalid blob ID: {}", blob_id));
+        }
+        Ok(self.root.join(blob_id))
+    }
+
+    fn chunk_path(&self, blob_id: &str, index: u32) -> Result<std::path::PathBuf, String> {
+        Ok(self.blob_dir(blob_id)?.join(format!("{:08}.chunk", index)))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl BlobStore for FileBlobStore {
+    fn put_chunk(&mut self, blob_id: &str, index: u32, bytes: &[u8]) -> Result<(), String> {
+        let path = self.chunk_path(blob_id, index)?;
+        let dir = self.blob_dir(blob_id)?;
+        std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create blob directory: {}", e))?;
+        // 先寫暫存檔再改名，中斷時不會留下半個 chunk
+        let temporary = path.with_extension("tmp");
+        std::fs::write(&temporary, bytes).map_err(|e| format!("Failed to write blob chunk: {}", e))?;
+        std::fs::rename(&temporary, &path).map_err(|e| format!("Failed to write blob chunk: {}", e))
+    }
+
+    fn get_chunk(&self, blob_id: &str, index: u32) -> Result<Option<Vec<u8>>, String> {
+        match std::fs::read(self.chunk_path(blob_id, index)?) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(format!("Failed to read blob chunk: {}", e)),
+        }
+    }
+
+    fn remove_blob(&mut self, blob_id: &str) -> Result<bool, String> {
+        match std::fs::remove_dir_all(self.blob_dir(blob_id)?) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(format!("Failed to remove blob: {}", e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_streaming_round_trip() {
+        let key = [7u8; 32];
+        let data: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        let mut store = InMemoryBlobStore::new();
+        assert_eq!(store.write_blob("photo", &key, &mut data.as_slice(), Some(1024), None).unwrap(), 10_000);
+        // 標頭 + 至少 10 個分段
+        assert!(store.get_chunk("photo", 10).unwrap().is_some());
+
+        let mut output = Vec::new();
+        assert_eq!(store.read_blob("photo", &key, &mut output).unwrap(), 10_000);
+        assert_eq!(output, data);
+        assert!(store.read_blob("photo", &[8u8; 32], &mut Vec::new()).is_err());
+
+        // 截斷 (移除最後一個 chunk) 時讀取失敗
+        let mut truncated = store.clone();
+        let last = (1..).find(|index| store.get_chunk("photo", *index).unwrap().is_none()).unwrap() - 1;
+        truncated.chunks.remove(&("photo".to_string(), last));
+        assert!(truncated.read_blob("photo", &key, &mut Vec::new()).is_err());
+
+        // 覆寫為較短的內容後不殘留舊的 chunk
+        store.write_blob("photo", &key, &mut &b"short"[..], Some(1024), None).unwrap();
+        let mut output = Vec::new();
+        store.read_blob("photo", &key, &mut output).unwrap();
+        assert_eq!(output, b"short");
+        assert!(store.remove_blob("photo").unwrap());
+        assert!(store.read_blob("photo", &key, &mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn test_file_blob_store() {
+        let root = std::env::temp_dir().join(format!("safetalk-blobs-{}", uuid::Uuid::new_v4()));
+        let mut store = FileBlobStore::new(&root).unwrap();
+        let key = [1u8; 32];
+        let data = vec![42u8; 5000];
+        store.write_blob("video-1", &key, &mut data.as_slice(), Some(512), None).unwrap();
+
+        let mut output = Vec::new();
+        store.read_blob("video-1", &key, &mut output).unwrap();
+        assert_eq!(output, data);
+        // 檔案內容為密文
+        let chunk = std::fs::read(root.join("video-1").join("00000001.chunk")).unwrap();
+        assert!(!chunk.windows(16).any(|window| window == [42u8; 16]));
+
+        assert!(store.put_chunk("../escape", 0, b"x").is_err());
+        assert!(store.remove_blob("video-1").unwrap());
+        assert!(!store.remove_blob("video-1").unwrap());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
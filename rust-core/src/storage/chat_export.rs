@@ -0,0 +1,402 @@
+//! 對話匯出 / 匯入模組
+//!
+//! 把單一對話的訊息與附件清單 (attachment manifest) 匯出為可攜的加密封存，
+//! 用於在裝置間搬移對話或在應用程式外封存。附件內容不在封存中，清單記錄
+//! 每個附件的 `AttachmentPointer` (位置、金鑰、密文長度與摘要)，匯入端可
+//! 據此重新下載並驗證
+//!
+//! 封存格式 (版本 1)：
+//!
+//! ```text
+//! magic "STCX" (4) || version 1 || kdf 長度 (u16 BE) || kdf 參數字串
+//! || STREAM 標頭 || STREAM 密文
+//! ```
+//!
+//! - kdf 參數字串為 PHC 格式 (見 `password` 模組，含 salt)；以金鑰匯出時長度為 0
+//! - 串流金鑰 = HKDF-SHA256(salt = 標頭前段, 封存金鑰, "SafeTalk-Chat-Export-v1")
+//! - 明文為一連串 `長度 (u32 BE) || 記錄`，記錄以 bincode 1 預設編碼
+//!   (列舉以 u32 LE 變體索引開頭，字串與位元組以 u64 LE 長度前綴)：
+//!   - 0 `Header { conversation_id, exported_at }`：第一筆
+//!   - 1 `Message(StoredMessage)`：對話中的訊息，依時間排序
+//!   - 2 `Attachment { message_id, pointer }`：附件清單
+//!   - 3 `End { messages, attachments }`：最後一筆，記錄數與 STREAM 結束旗標確保封存完整
+//!
+//! 匯入時整份封存驗證完整後才寫入訊息儲存
+
+use std::io::{Read, Write};
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+use crate::crypto::{AttachmentPointer, PasswordKdf, PasswordKdfParams};
+use super::archive::{ArchiveFormat, ArchiveReader, ArchiveWriter, BackupSecret};
+use super::messages::{InMemoryMessageStore, MessageStore, StoredMessage};
+
+/// 對話匯出格式版本
+pub const CHAT_EXPORT_VERSION: u8 = 1;
+const CHAT_EXPORT_FORMAT: ArchiveFormat = ArchiveFormat {
+    magic: b"STCX",
+    version: CHAT_EXPORT_VERSION,
+    key_info: b"SafeTalk-Chat-Export-v1",
+    name: "Chat export",
+};
+
+/// 附件清單中的一項
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatAttachment {
+    message_id: String,
+    pointer: AttachmentPointer,
+}
+
+#[wasm_bindgen]
+impl ChatAttachment {
+    /// 訊息 `message_id` 附帶的附件
+    #[wasm_bindgen(constructor)]
+    pub fn new(message_id: &str, pointer: &AttachmentPointer) -> Self {
+        Self { message_id: message_id.to_string(), pointer: pointer.clone() }
+    }
+
+    #[wasm_bindgen(getter, js_name = messageId)]
+    pub fn message_id(&self) -> String {
+        self.message_id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn pointer(&self) -> AttachmentPointer {
+        self.pointer.clone()
+    }
+}
+
+/// 對話匯出中的一筆記錄
+#[derive(Clone, Debug)]
+pub enum ChatExportRecord {
+    Message(StoredMessage),
+    Attachment(ChatAttachment),
+}
+
+/// 寫入用的記錄 (借用)，編碼與 `Entry` 相同
+#[derive(Serialize)]
+enum EntryRef<'a> {
+    Header { conversation_id: &'a str, exported_at: u64 },
+    Message(&'a StoredMessage),
+    Attachment { message_id: &'a str, pointer: &'a AttachmentPointer },
+    End { messages: u64, attachments: u64 },
+}
+
+#[derive(Deserialize)]
+enum Entry {
+    Header { conversation_id: String, exported_at: u64 },
+    Message(StoredMessage),
+    Attachment { message_id: String, pointer: AttachmentPointer },
+    End { messages: u64, attachments: u64 },
+}
+
+fn write_entry(archive: &mut ArchiveWriter<impl Write>, entry: &EntryRef) -> Result<(), String> {
+    let bytes = Zeroizing::new(bincode::serialize(entry).map_err(|e| e.to_string())?);
+    archive.write_record(&bytes)
+}
+
+fn read_entry(archive: &mut ArchiveReader<impl Read>) -> Result<Entry, String> {
+    let bytes = archive
+        .next_record()?
+        .ok_or_else(|| CHAT_EXPORT_FORMAT.incomplete("missing end record"))?;
+    bincode::deserialize(&bytes).map_err(|e| CHAT_EXPORT_FORMAT.incomplete(e))
+}
+
+/// 串流寫入對話匯出
+pub struct ChatExportWriter<W: Write> {
+    archive: ArchiveWriter<W>,
+    conversation_id: String,
+    messages: u64,
+    attachments: u64,
+}
+
+impl<W: Write> ChatExportWriter<W> {
+    /// 以 32 bytes 金鑰建立寫入器並寫出標頭
+    pub fn with_key(output: W, key: &[u8], conversation_id: &str, exported_at: u64) -> Result<Self, String> {
+        Self::start(ArchiveWriter::with_key(&CHAT_EXPORT_FORMAT, output, key)?, conversation_id, exported_at)
+    }
+
+    /// 以密碼建立寫入器 (以 `kdf` 參數與隨機 salt 衍生金鑰) 並寫出標頭
+    pub fn with_password(
+        output: W,
+        password: &[u8],
+        kdf: &PasswordKdfParams,
+        conversation_id: &str,
+        exported_at: u64,
+    ) -> Result<Self, String> {
+        Self::start(
+            ArchiveWriter::with_password(&CHAT_EXPORT_FORMAT, output, password, kdf)?,
+            conversation_id,
+            exported_at,
+        )
+    }
+
+    fn start(mut archive: ArchiveWriter<W>, conversation_id: &str, exported_at: u64) -> Result<Self, String> {
+        write_entry(&mut archive, &EntryRef::Header { conversation_id, exported_at })?;
+        Ok(Self { archive, conversation_id: conversation_id.to_string(), messages: 0, attachments: 0 })
+    }
+
+    /// 寫入一則訊息 (必須屬於匯出的對話)
+    pub fn write_message(&mut self, message: &StoredMessage) -> Result<(), String> {
+        if message.conversation_id() != self.conversation_id {
+            return Err("Message belongs to another conversation".to_string());
+        }
+        write_entry(&mut self.archive, &EntryRef::Message(message))?;
+        self.messages += 1;
+        Ok(())
+    }
+
+    /// 寫入附件清單中的一項
+    pub fn write_attachment(&mut self, attachment: &ChatAttachment) -> Result<(), String> {
+        write_entry(
+            &mut self.archive,
+            &EntryRef::Attachment { message_id: &attachment.message_id, pointer: &attachment.pointer },
+        )?;
+        self.attachments += 1;
+        Ok(())
+    }
+
+    /// 寫入訊息儲存中此對話的所有訊息
+    pub fn write_conversation(&mut self, messages: &impl MessageStore) -> Result<(), String> {
+        for message in messages.conversation_messages(&self.conversation_id, None, None)? {
+            self.write_message(&message)?;
+        }
+        Ok(())
+    }
+
+    /// 寫入結尾記錄並結束串流，回傳輸出端
+    pub fn finish(mut self) -> Result<W, String> {
+        write_entry(&mut self.archive, &EntryRef::End { messages: self.messages, attachments: self.attachments })?;
+        self.archive.finish()
+    }
+}
+
+/// 串流讀取對話匯出
+///
+/// `next_record` 在回傳 None 之前封存尚未驗證完整
+pub struct ChatExportReader<R: Read> {
+    archive: ArchiveReader<R>,
+    conversation_id: String,
+    exported_at: u64,
+    messages: u64,
+    attachments: u64,
+    done: bool,
+}
+
+impl<R: Read> ChatExportReader<R> {
+    /// 讀取標頭並以金鑰或密碼開啟
+    pub fn open(input: R, secret: BackupSecret) -> Result<Self, String> {
+        let mut archive = ArchiveReader::open(&CHAT_EXPORT_FORMAT, input, secret)?;
+        let Entry::Header { conversation_id, exported_at } = read_entry(&mut archive)? else {
+            return Err(CHAT_EXPORT_FORMAT.incomplete("missing header record"));
+        };
+        Ok(Self { archive, conversation_id, exported_at, messages: 0, attachments: 0, done: false })
+    }
+
+    /// 匯出的對話 ID
+    pub fn conversation_id(&self) -> &str {
+        &self.conversation_id
+    }
+
+    /// 匯出時間 (Unix 毫秒)
+    pub fn exported_at(&self) -> u64 {
+        self.exported_at
+    }
+
+    /// 讀取下一筆記錄；讀到結尾且封存驗證完整時回傳 None
+    pub fn next_record(&mut self) -> Result<Option<ChatExportRecord>, String> {
+        if self.done {
+            return Ok(None);
+        }
+        match read_entry(&mut self.archive)? {
+            Entry::Message(message) => {
+                if message.conversation_id() != self.conversation_id {
+                    return Err(CHAT_EXPORT_FORMAT.incomplete("message from another conversation"));
+                }
+                self.messages += 1;
+                Ok(Some(ChatExportRecord::Message(message)))
+            }
+            Entry::Attachment { message_id, pointer } => {
+                self.attachments += 1;
+                Ok(Some(ChatExportRecord::Attachment(ChatAttachment { message_id, pointer })))
+            }
+            Entry::End { messages, attachments } => {
+                if messages != self.messages || attachments != self.attachments {
+                    return Err(CHAT_EXPORT_FORMAT.incomplete("record count mismatch"));
+                }
+                if self.archive.next_record()?.is_some() {
+                    return Err(CHAT_EXPORT_FORMAT.incomplete("trailing data after end record"));
+                }
+                self.done = true;
+                Ok(None)
+            }
+            Entry::Header { .. } => Err(CHAT_EXPORT_FORMAT.incomplete("duplicate header record")),
+        }
+    }
+
+    /// 讀取整份封存，驗證完整後把訊息寫入 `store` (同 ID 的訊息被覆寫)
+    pub fn import_into(mut self, store: &mut impl MessageStore) -> Result<ImportedChat, String> {
+        let mut messages = Vec::new();
+        let mut attachments = Vec::new();
+        while let Some(record) = self.next_record()? {
+            match record {
+                ChatExportRecord::Message(message) => messages.push(message),
+                ChatExportRecord::Attachment(attachment) => attachments.push(attachment),
+            }
+        }
+        for message in &messages {
+            store.store_message(message)?;
+        }
+        Ok(ImportedChat {
+            conversation_id: self.conversation_id,
+            exported_at: self.exported_at,
+            message_count: messages.len() as u32,
+            attachments,
+        })
+    }
+}
+
+/// 匯入結果
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct ImportedChat {
+    conversation_id: String,
+    exported_at: u64,
+    message_count: u32,
+    attachments: Vec<ChatAttachment>,
+}
+
+#[wasm_bindgen]
+impl ImportedChat {
+    #[wasm_bindgen(getter, js_name = conversationId)]
+    pub fn conversation_id(&self) -> String {
+        self.conversation_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = exportedAt)]
+    pub fn exported_at(&self) -> u64 {
+        self.exported_at
+    }
+
+    /// 匯入的訊息數
+    #[wasm_bindgen(getter, js_name = messageCount)]
+    pub fn message_count(&self) -> u32 {
+        self.message_count
+    }
+
+    /// 附件清單 (需另外下載)
+    #[wasm_bindgen(getter)]
+    pub fn attachments(&self) -> Vec<ChatAttachment> {
+        self.attachments.clone()
+    }
+}
+
+fn export_with<W: Write>(
+    mut writer: ChatExportWriter<W>,
+    messages: &InMemoryMessageStore,
+    attachments: &[ChatAttachment],
+) -> Result<W, String> {
+    writer.write_conversation(messages)?;
+    for attachment in attachments {
+        writer.write_attachment(attachment)?;
+    }
+    writer.finish()
+}
+
+/// 以 32 bytes 金鑰匯出對話
+#[wasm_bindgen(js_name = exportConversation)]
+pub fn export_conversation(
+    messages: &InMemoryMessageStore,
+    conversation_id: &str,
+    attachments: Vec<ChatAttachment>,
+    key: &[u8],
+    now: u64,
+) -> Result<Vec<u8>, JsError> {
+    ChatExportWriter::with_key(Vec::new(), key, conversation_id, now)
+        .and_then(|writer| export_with(writer, messages, &attachments))
+        .map_err(|e| JsError::new(&e))
+}
+
+/// 以密碼匯出對話 (`params` 未提供時使用預設的 Argon2id 參數)
+#[wasm_bindgen(js_name = exportConversationWithPassword)]
+pub fn export_conversation_with_password(
+    messages: &InMemoryMessageStore,
+    conversation_id: &str,
+    attachments: Vec<ChatAttachment>,
+    password: &str,
+    params: Option<PasswordKdf>,
+    now: u64,
+) -> Result<Vec<u8>, JsError> {
+    let params = params.map(|kdf| kdf.params()).unwrap_or_default();
+    ChatExportWriter::with_password(Vec::new(), password.as_bytes(), &params, conversation_id, now)
+        .and_then(|writer| export_with(writer, messages, &attachments))
+        .map_err(|e| JsError::new(&e))
+}
+
+/// 以 32 bytes 金鑰匯入對話到 `store`
+#[wasm_bindgen(js_name = importConversation)]
+pub fn import_conversation(bytes: &[u8], key: &[u8], store: &mut InMemoryMessageStore) -> Result<ImportedChat, JsError> {
+    ChatExportReader::open(bytes, BackupSecret::Key(key))
+        .and_then(|reader| reader.import_into(store))
+        .map_err(|e| JsError::new(&e))
+}
+
+/// 以密碼匯入對話到 `store`
+#[wasm_bindgen(js_name = importConversationWithPassword)]
+pub fn import_conversation_with_password(
+    bytes: &[u8],
+    password: &str,
+    store: &mut InMemoryMessageStore,
+) -> Result<ImportedChat, JsError> {
+    ChatExportReader::open(bytes, BackupSecret::Password(password.as_bytes()))
+        .and_then(|reader| reader.import_into(store))
+        .map_err(|e| JsError::new(&e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{Argon2idParams, Attachment};
+
+    #[test]
+    fn test_chat_export_round_trip() {
+        let mut messages = InMemoryMessageStore::new();
+        for i in 0..10u64 {
+            messages.store_message(&StoredMessage::new(&format!("m{}", i), "trip", "bob", i, b"see you there")).unwrap();
+        }
+        messages.store_message(&StoredMessage::new("other", "work", "carol", 1, b"private")).unwrap();
+        let pointer = Attachment::new(b"photo", "photo.jpg", "image/jpeg")
+            .encrypt_with_key(&[4u8; 32], Default::default())
+            .unwrap()
+            .pointer("https://cdn.example/abc");
+        let attachments = vec![ChatAttachment::new("m3", &pointer)];
+
+        let kdf = PasswordKdfParams::Argon2id(Argon2idParams { memory_kib: 64, iterations: 1, parallelism: 1 });
+        let writer = ChatExportWriter::with_password(Vec::new(), b"hunter2", &kdf, "trip", 1000).unwrap();
+        let archive = export_with(writer, &messages, &attachments).unwrap();
+        assert!(!archive.windows(4).any(|window| window == b"trip"));
+
+        let mut imported = InMemoryMessageStore::new();
+        let reader = ChatExportReader::open(archive.as_slice(), BackupSecret::Password(b"hunter2")).unwrap();
+        assert_eq!(reader.conversation_id(), "trip");
+        let result = reader.import_into(&mut imported).unwrap();
+        assert_eq!(result.exported_at(), 1000);
+        assert_eq!(result.message_count(), 10);
+        assert_eq!(result.attachments(), attachments);
+        assert_eq!(imported.size(), 10);
+        assert!(imported.load_message("other").unwrap().is_none());
+
+        // 截斷或錯誤的密碼不寫入任何訊息
+        let mut untouched = InMemoryMessageStore::new();
+        let truncated = ChatExportReader::open(&archive[..archive.len() - 1], BackupSecret::Password(b"hunter2"));
+        assert!(truncated.and_then(|reader| reader.import_into(&mut untouched)).is_err());
+        assert!(ChatExportReader::open(archive.as_slice(), BackupSecret::Password(b"wrong")).is_err());
+        assert_eq!(untouched.size(), 0);
+
+        let mut writer = ChatExportWriter::with_key(Vec::new(), &[1u8; 32], "trip", 1000).unwrap();
+        assert!(writer.write_message(&StoredMessage::new("other", "work", "carol", 1, b"private")).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+//! 聯絡人儲存模組
+//!
+//! 以穩定的聯絡人 ID 存放身份公鑰、個人資料金鑰 (profile key)、驗證狀態與暱稱，
+//! 持久化後端與其他記錄一樣以主儲存金鑰加密、聯絡人 ID 盲化
+//!
+//! 身份公鑰由信任儲存決定：`save_contact_identity` 先經 `IdentityKeyStore`
+//! 記錄 (TOFU、撤銷檢查)，被接受後才寫入聯絡人。驗證記錄綁定驗證當時的
+//! 身份公鑰，身份變更後自動變成 `Changed`，應用程式不必自行追蹤
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use super::encryption::{RecordCodec, StorageKey, SNAPSHOT_STORE};
+use super::store::{IdentityKeyStore, InMemoryProtocolStore};
+use super::trust::IdentityStatus;
+
+const CONTACT_SNAPSHOT_KEY: &str = "contact_store";
+
+/// 聯絡人驗證狀態
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum VerificationState {
+    /// 尚未驗證 (僅 TOFU)
+    Unverified = 0,
+    /// 已比對安全碼
+    Verified = 1,
+    /// 驗證後身份公鑰已變更，需要重新驗證
+    Changed = 2,
+}
+
+/// 聯絡人
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Contact {
+    id: String,
+    /// 信任儲存接受的身份公鑰
+    identity_key: Option<Vec<u8>>,
+    profile_key: Option<Vec<u8>>,
+    nickname: Option<String>,
+    /// 驗證當時的身份公鑰
+    verified_key: Option<Vec<u8>>,
+    /// 驗證時間 (Unix 毫秒)
+    verified_at: Option<u64>,
+}
+
+impl Contact {
+    /// 清零個人資料金鑰 (刪除或覆寫時)
+    fn wipe(&mut self) {
+        if let Some(profile_key) = self.profile_key.as_mut() {
+            profile_key.zeroize();
+        }
+    }
+
+    /// 將已驗證的身份公鑰標記為已驗證
+    pub fn mark_verified(&mut self, now: u64) -> Result<(), String> {
+        let identity_key = self
+            .identity_key
+            .clone()
+            .ok_or_else(|| "Contact has no identity key to verify".to_string())?;
+        self.verified_key = Some(identity_key);
+        self.verified_at = Some(now);
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl Contact {
+    /// 建立尚無身份公鑰的聯絡人
+    #[wasm_bindgen(constructor)]
+    pub fn new(id: &str) -> Self {
+        Self {
+            id: id.to_string(),
+            identity_key: None,
+            profile_key: None,
+            nickname: None,
+            verified_key: None,
+            verified_at: None,
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// 身份公鑰 (只能經信任儲存更新)
+    #[wasm_bindgen(getter, js_name = identityKey)]
+    pub fn identity_key(&self) -> Option<Vec<u8>> {
+        self.identity_key.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = profileKey)]
+    pub fn profile_key(&self) -> Option<Vec<u8>> {
+        self.profile_key.clone()
+    }
+
+    /// 設定個人資料金鑰，未提供時移除
+    #[wasm_bindgen(js_name = setProfileKey)]
+    pub fn set_profile_key(&mut self, profile_key: Option<Vec<u8>>) {
+        self.wipe();
+        self.profile_key = profile_key;
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn nickname(&self) -> Option<String> {
+        self.nickname.clone()
+    }
+
+    /// 設定暱稱，未提供時移除
+    #[wasm_bindgen(js_name = setNickname)]
+    pub fn set_nickname(&mut self, nickname: Option<String>) {
+        self.nickname = nickname;
+    }
+
+    /// 依驗證當時與目前的身份公鑰計算驗證狀態
+    #[wasm_bindgen(getter, js_name = verificationState)]
+    pub fn verification_state(&self) -> VerificationState {
+        match &self.verified_key {
+            None => VerificationState::Unverified,
+            Some(key) if self.identity_key.as_ref() == Some(key) => VerificationState::Verified,
+            Some(_) => VerificationState::Changed,
+        }
+    }
+
+    /// 驗證時間 (Unix 毫秒)
+    #[wasm_bindgen(getter, js_name = verifiedAt)]
+    pub fn verified_at(&self) -> Option<u64> {
+        self.verified_at
+    }
+
+    /// 比對安全碼後，將目前的身份公鑰標記為已驗證
+    #[wasm_bindgen(js_name = markVerified)]
+    pub fn mark_verified_js(&mut self, now: u64) -> Result<(), JsError> {
+        self.mark_verified(now).map_err(|e| JsError::new(&e))
+    }
+
+    /// 清除驗證記錄
+    #[wasm_bindgen(js_name = clearVerification)]
+    pub fn clear_verification(&mut self) {
+        self.verified_key = None;
+        self.verified_at = None;
+    }
+}
+
+/// 聯絡人儲存
+pub trait ContactStore {
+    /// 載入聯絡人
+    fn load_contact(&self, contact_id: &str) -> Result<Option<Contact>, String>;
+
+    /// 儲存 (覆寫) 聯絡人
+    fn store_contact(&mut self, contact: &Contact) -> Result<(), String>;
+
+    /// 刪除聯絡人，回傳是否存在
+    fn remove_contact(&mut self, contact_id: &str) -> Result<bool, String>;
+
+    /// 所有聯絡人 (依 ID 排序)
+    fn contacts(&self) -> Result<Vec<Contact>, String>;
+
+    /// 依信任儲存記錄身份公鑰的結果更新聯絡人 (不存在時建立)
+    ///
+    /// 已撤銷的身份公鑰不會寫入
+    fn apply_identity(&mut self, contact_id: &str, identity_key: &[u8], status: IdentityStatus) -> Result<(), String> {
+        if status == IdentityStatus::Revoked {
+            return Ok(());
+        }
+        let mut contact = self.load_contact(contact_id)?.unwrap_or_else(|| Contact::new(contact_id));
+        if contact.identity_key.as_deref() == Some(identity_key) {
+            return Ok(());
+        }
+        contact.identity_key = Some(identity_key.to_vec());
+        self.store_contact(&contact)
+    }
+}
+
+/// 非同步聯絡人儲存，方法與 [`ContactStore`] 相同
+#[allow(async_fn_in_trait)]
+pub trait AsyncContactStore {
+    async fn load_contact(&self, contact_id: &str) -> Result<Option<Contact>, String>;
+    async fn store_contact(&mut self, contact: &Contact) -> Result<(), String>;
+    async fn remove_contact(&mut self, contact_id: &str) -> Result<bool, String>;
+    async fn contacts(&self) -> Result<Vec<Contact>, String>;
+
+    async fn apply_identity(&mut self, contact_id: &str, identity_key: &[u8], status: IdentityStatus) -> Result<(), String> {
+        if status == IdentityStatus::Revoked {
+            return Ok(());
+        }
+        let mut contact = self.load_contact(contact_id).await?.unwrap_or_else(|| Contact::new(contact_id));
+        if contact.identity_key.as_deref() == Some(identity_key) {
+            return Ok(());
+        }
+        contact.identity_key = Some(identity_key.to_vec());
+        self.store_contact(&contact).await
+    }
+}
+
+/// 經信任儲存記錄聯絡人的身份公鑰並更新聯絡人，回傳記錄前的狀態
+///
+/// 與 [`IdentityKeyStore::save_identity`] 相同，回傳 `Changed` 表示舊的身份公鑰
+/// 已被取代，呼叫端應在使用者確認後才呼叫；同一個後端同時實作兩者時
+/// (`SqliteStore`)，依序呼叫 `save_identity` 與 `apply_identity`
+pub fn save_contact_identity(
+    contacts: &mut impl ContactStore,
+    identities: &mut impl IdentityKeyStore,
+    contact_id: &str,
+    identity_key: &[u8],
+    now: u64,
+) -> Result<IdentityStatus, String> {
+    let status = identities.save_identity(contact_id, identity_key, now)?;
+    contacts.apply_identity(contact_id, identity_key, status)?;
+    Ok(status)
+}
+
+/// 記憶體聯絡人儲存，以 `serializeEncrypted` 持久化
+#[wasm_bindgen]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct InMemoryContactStore {
+    /// contact_id -> 聯絡人
+    contacts: BTreeMap<String, Contact>,
+}
+
+impl ContactStore for InMemoryContactStore {
+    fn load_contact(&self, contact_id: &str) -> Result<Option<Contact>, String> {
+        Ok(self.contacts.get(contact_id).cloned())
+    }
+
+    fn store_contact(&mut self, contact: &Contact) -> Result<(), String> {
+        if let Some(mut previous) = self.contacts.insert(contact.id.clone(), contact.clone()) {
+            previous.wipe();
+        }
+        Ok(())
+    }
+
+    fn remove_contact(&mut self, contact_id: &str) -> Result<bool, String> {
+        let Some(mut contact) = self.contacts.remove(contact_id) else {
+            return Ok(false);
+        };
+        contact.wipe();
+        Ok(true)
+    }
+
+    fn contacts(&self) -> Result<Vec<Contact>, String> {
+        Ok(self.contacts.values().cloned().collect())
+    }
+}
+
+#[wasm_bindgen]
+impl InMemoryContactStore {
+    /// 建立空的聯絡人儲存
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 載入聯絡人
+    #[wasm_bindgen(js_name = loadContact)]
+    pub fn load_contact_js(&self, contact_id: &str) -> Result<Option<Contact>, JsError> {
+        self.load_contact(contact_id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 儲存 (覆寫) 聯絡人
+    #[wasm_bindgen(js_name = storeContact)]
+    pub fn store_contact_js(&mut self, contact: &Contact) -> Result<(), JsError> {
+        self.store_contact(contact).map_err(|e| JsError::new(&e))
+    }
+
+    /// 刪除聯絡人，回傳是否存在
+    #[wasm_bindgen(js_name = removeContact)]
+    pub fn remove_contact_js(&mut self, contact_id: &str) -> Result<bool, JsError> {
+        self.remove_contact(contact_id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 所有聯絡人 (依 ID 排序)
+    #[wasm_bindgen(js_name = contacts)]
+    pub fn contacts_js(&self) -> Result<Vec<Contact>, JsError> {
+        self.contacts().map_err(|e| JsError::new(&e))
+    }
+
+    /// 經協定儲存的信任儲存記錄身份公鑰並更新聯絡人，回傳記錄前的狀態
+    #[wasm_bindgen(js_name = saveIdentity)]
+    pub fn save_identity_js(
+        &mut self,
+        protocol: &mut InMemoryProtocolStore,
+        contact_id: &str,
+        identity_key: &[u8],
+        now: u64,
+    ) -> Result<IdentityStatus, JsError> {
+        save_contact_identity(self, protocol, contact_id, identity_key, now).map_err(|e| JsError::new(&e))
+    }
+
+    /// 聯絡人總數
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.contacts.len()
+    }
+
+    /// 以主儲存金鑰加密序列化
+    #[wasm_bindgen(js_name = serializeEncrypted)]
+    pub fn serialize_encrypted(&self, key: &StorageKey) -> Result<Vec<u8>, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .encode(SNAPSHOT_STORE, CONTACT_SNAPSHOT_KEY, self)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密並還原
+    #[wasm_bindgen(js_name = deserializeEncrypted)]
+    pub fn deserialize_encrypted(bytes: &[u8], key: &StorageKey) -> Result<InMemoryContactStore, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .decode(SNAPSHOT_STORE, CONTACT_SNAPSHOT_KEY, bytes)
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::IdentityKeyPair;
+
+    #[test]
+    fn test_contact_verification_follows_trust_store() {
+        let mut protocol = InMemoryProtocolStore::new(&IdentityKeyPair::new(), 1);
+        let mut contacts = InMemoryContactStore::new();
+        let key_a = IdentityKeyPair::new().public_key_bytes();
+        let key_b = IdentityKeyPair::new().public_key_bytes();
+
+        let status = save_contact_identity(&mut contacts, &mut protocol, "bob", &key_a, 100).unwrap();
+        assert_eq!(status, IdentityStatus::NewIdentity);
+        let mut bob = contacts.load_contact("bob").unwrap().unwrap();
+        assert_eq!(bob.identity_key(), Some(key_a.clone()));
+        assert_eq!(bob.verification_state(), VerificationState::Unverified);
+        assert!(Contact::new("carol").mark_verified(100).is_err());
+
+        bob.set_nickname(Some("Bobby".to_string()));
+        bob.set_profile_key(Some(vec![7u8; 32]));
+        bob.mark_verified(200).unwrap();
+        contacts.store_contact(&bob).unwrap();
+        assert_eq!(contacts.load_contact("bob").unwrap().unwrap().verification_state(), VerificationState::Verified);
+
+        // 身份變更後驗證失效，暱稱與個人資料金鑰保留
+        let status = save_contact_identity(&mut contacts, &mut protocol, "bob", &key_b, 300).unwrap();
+        assert_eq!(status, IdentityStatus::Changed);
+        let bob = contacts.load_contact("bob").unwrap().unwrap();
+        assert_eq!(bob.verification_state(), VerificationState::Changed);
+        assert_eq!(bob.nickname().as_deref(), Some("Bobby"));
+        assert_eq!(bob.verified_at(), Some(200));
+
+        let key = StorageKey::generate();
+        let sealed = contacts.serialize_encrypted(&key).unwrap();
+        assert!(!sealed.windows(5).any(|window| window == b"Bobby"));
+        let mut restored = InMemoryContactStore::deserialize_encrypted(&sealed, &key).unwrap();
+        assert_eq!(restored.contacts().unwrap(), vec![bob]);
+        assert!(restored.remove_contact("bob").unwrap());
+        assert_eq!(restored.size(), 0);
+    }
+}
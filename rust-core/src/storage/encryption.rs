@@ -0,0 +1,699 @@
+//! 靜態加密模組
+//!
+//! 以主儲存金鑰 (`StorageKey`) 透明地加密每一筆持久化記錄，資料庫檔案
+//! (IndexedDB、SQLite) 被複製走也讀不出會話、預金鑰或訊息內容：
+//! - 記錄以 AES-256-GCM 加密，每筆使用隨機 nonce
+//! - AAD 綁定 (倉庫, 記錄鍵)，記錄被搬到其他列或其他倉庫時解密失敗
+//! - 聯絡人 ID、對話 ID、訊息 ID 等查詢用的鍵以 HMAC 盲化，仍可精確查詢
+//!
+//! 訊息記錄另以每則訊息各自的包裝金鑰加密，包裝金鑰再以所屬對話的對話金鑰
+//! 加密、分開存放。刪除訊息時刪除包裝金鑰並輪替對話金鑰 (其餘訊息的包裝金鑰改以新金鑰包裝)，
+//! 資料庫沒有實際覆寫頁面時，殘留的包裝金鑰無法以目前的對話金鑰解開。
+//! 舊對話金鑰的記錄本身以主儲存金鑰加密，若同樣殘留在檔案中，持有主儲存金鑰者仍可能
+//! 依序還原出已刪除的訊息：SQLite 以 `secure_delete` 覆寫刪除的頁面，
+//! IndexedDB 則取決於瀏覽器，無法保證
+//!
+//! 記錄數量與訊息時間戳 (排序所需) 仍以明文存放
+//!
+//! 主儲存金鑰可由密碼衍生 (`StorageLock`)：資料庫中只存 KDF 參數、salt
+//! 與驗證值，輸入正確密碼前無法讀取任何記錄
+//!
+//! 主儲存金鑰可以輪替 (變更密碼或懷疑本機遭入侵時)：各後端逐批以新金鑰重新加密記錄，
+//! 輪替期間兩把金鑰都能解密，中斷後以同一把新金鑰再次輪替即從進度記錄繼續。
+//! 盲化無法反推原值，查詢鍵不能重算，因此盲化索引金鑰沿用舊值，以新金鑰加密後存於資料庫中
+//!
+//! 記錄格式：`version (1) || nonce (12) || ciphertext || tag (16)`
+//!
+//! 每筆記錄都帶有檢查碼，單筆記錄毀損時只有該筆讀取失敗，可由 `verifyStorage` 找出：
+//! 加密記錄的驗證標籤即為檢查碼；未設定金鑰時記錄為
+//! `magic (2) || SHA-256(AAD || 內容) 前 8 bytes || 內容`，沒有 magic 的舊版記錄無法檢查
+
+use std::fmt;
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, OsRng, Payload},
+    Aes256Gcm, Nonce,
+};
+use rand::RngCore;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroizing;
+
+use crate::crypto::constant_time::constant_time_eq;
+use crate::crypto::hash::sha256;
+use crate::crypto::kdf::hkdf_sha256;
+use crate::crypto::mac::hmac_sha256_bytes;
+use crate::crypto::{PasswordKdf, PasswordKdfParams, SecretBytes};
+
+/// 主儲存金鑰長度
+pub const STORAGE_KEY_SIZE: usize = 32;
+/// 加密記錄格式版本
+const RECORD_VERSION: u8 = 1;
+const NONCE_SIZE: usize = 12;
+const RECORD_KEY_INFO: &[u8] = b"SafeTalk-Storage-Record-v1";
+const INDEX_KEY_INFO: &[u8] = b"SafeTalk-Storage-Index-v1";
+const VERIFIER_INFO: &[u8] = b"SafeTalk-Storage-Verifier-v1";
+const RECORD_AAD_PREFIX: &[u8] = b"SafeTalk-Storage-v1";
+/// 未加密記錄的開頭 (舊版記錄為 bincode 直接序列化，沒有此開頭)
+const PLAIN_RECORD_MAGIC: [u8; 2] = [0xfe, 0x5c];
+const PLAIN_CHECKSUM_SIZE: usize = 8;
+/// 密碼衍生主金鑰時使用的 salt 長度
+const STORAGE_LOCK_SALT_SIZE: usize = 16;
+/// 整個記憶體儲存加密序列化時使用的倉庫名稱
+pub(crate) const SNAPSHOT_STORE: &str = "snapshot";
+/// 包裝金鑰記錄的倉庫名稱 (AAD 用)
+#[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+const WRAPPING_KEY_STORE: &str = "message_keys";
+/// 對話金鑰記錄的倉庫名稱 (AAD 用)
+#[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+const CONVERSATION_KEY_STORE: &str = "conversation_keys";
+
+/// 主儲存金鑰
+///
+/// 記錄加密金鑰與盲化索引金鑰皆由主金鑰以 HKDF 衍生，互不相同
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct StorageKey {
+    master: Zeroizing<[u8; STORAGE_KEY_SIZE]>,
+    record_key: Zeroizing<[u8; STORAGE_KEY_SIZE]>,
+    index_key: Zeroizing<[u8; STORAGE_KEY_SIZE]>,
+}
+
+impl StorageKey {
+    fn derive(master: Zeroizing<[u8; STORAGE_KEY_SIZE]>) -> Self {
+        let subkey = |info: &[u8]| {
+            let mut key = Zeroizing::new([0u8; STORAGE_KEY_SIZE]);
+            let derived = Zeroizing::new(
+                hkdf_sha256(&[], master.as_slice(), info, STORAGE_KEY_SIZE).expect("32 bytes is a valid HKDF length"),
+            );
+            key.copy_from_slice(&derived);
+            key
+        };
+        Self { record_key: subkey(RECORD_KEY_INFO), index_key: subkey(INDEX_KEY_INFO), master }
+    }
+
+    /// 沿用其他金鑰的盲化索引金鑰 (主金鑰輪替後查詢鍵不變)
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    fn with_index_key(&self, index_key: &[u8; STORAGE_KEY_SIZE]) -> Self {
+        let mut key = self.clone();
+        key.index_key.copy_from_slice(index_key);
+        key
+    }
+
+    /// 驗證值：存放在資料庫中用來判斷密碼是否正確，無法反推出主金鑰
+    fn verifier(&self) -> Vec<u8> {
+        hkdf_sha256(&[], self.master.as_slice(), VERIFIER_INFO, STORAGE_KEY_SIZE).expect("32 bytes is a valid HKDF length")
+    }
+
+    /// 從主金鑰位元組建立 (Rust 端使用)
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() != STORAGE_KEY_SIZE {
+            return Err(format!("Storage key must be {} bytes", STORAGE_KEY_SIZE));
+        }
+        let mut master = Zeroizing::new([0u8; STORAGE_KEY_SIZE]);
+        master.copy_from_slice(bytes);
+        Ok(Self::derive(master))
+    }
+
+    /// 以盲化索引金鑰計算 `field` 欄位中 `value` 的確定性 HMAC (Rust 端使用)
+    ///
+    /// 相同的欄位與值永遠得到相同結果，可作為查詢鍵而不洩漏原值
+    pub fn blind_index(&self, field: &str, value: &[u8]) -> [u8; 32] {
+        let data = [field.as_bytes(), &[0], value].concat();
+        hmac_sha256_bytes(self.index_key.as_slice(), &data)
+    }
+}
+
+impl fmt::Debug for StorageKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "StorageKey([REDACTED])")
+    }
+}
+
+#[wasm_bindgen]
+impl StorageKey {
+    /// 產生隨機的主儲存金鑰
+    pub fn generate() -> StorageKey {
+        let mut master = Zeroizing::new([0u8; STORAGE_KEY_SIZE]);
+        OsRng.fill_bytes(master.as_mut_slice());
+        Self::derive(master)
+    }
+
+    /// 從主金鑰位元組建立
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<StorageKey, JsError> {
+        Self::from_slice(bytes).map_err(|e| JsError::new(&e))
+    }
+
+    /// 主金鑰位元組 (由呼叫端自行妥善保存，例如以密碼包裝)
+    ///
+    /// 以 `SecretBytes` 回傳，需明確呼叫 `expose()` 才能取得內容
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> SecretBytes {
+        (*self.master).into()
+    }
+}
+
+/// 密碼鎖：以密碼衍生主儲存金鑰所需的參數與驗證值 (本身不含秘密，可明文存放)
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageLock {
+    /// KDF 參數字串 (含 salt)，見 `password` 模組
+    params: String,
+    verifier: Vec<u8>,
+}
+
+impl StorageLock {
+    /// 以新的隨機 salt 建立密碼鎖，回傳密碼鎖與衍生出的主儲存金鑰 (Rust 端使用)
+    pub fn create_with_params(password: &[u8], params: &PasswordKdfParams) -> Result<(Self, StorageKey), String> {
+        params.check_limits()?;
+        let mut salt = [0u8; STORAGE_LOCK_SALT_SIZE];
+        OsRng.fill_bytes(&mut salt);
+        let key = StorageKey::derive(Zeroizing::new(params.derive_key(password, &salt)?));
+        let lock = Self { params: params.encode(&salt), verifier: key.verifier() };
+        Ok((lock, key))
+    }
+
+    /// 以密碼衍生主儲存金鑰，密碼錯誤時回傳錯誤 (Rust 端使用)
+    pub fn unlock_key(&self, password: &[u8]) -> Result<StorageKey, String> {
+        let (params, salt) = PasswordKdfParams::decode(&self.params)?;
+        let key = StorageKey::derive(Zeroizing::new(params.derive_key(password, &salt)?));
+        if !constant_time_eq(&key.verifier(), &self.verifier) {
+            return Err("Wrong storage password".to_string());
+        }
+        Ok(key)
+    }
+}
+
+#[wasm_bindgen]
+impl StorageLock {
+    /// 以新的隨機 salt 建立密碼鎖，未指定參數時使用 Argon2id 預設值
+    ///
+    /// 建立後以 `unlock` 取得主儲存金鑰
+    pub fn create(password: &str, params: Option<PasswordKdf>) -> Result<StorageLock, JsError> {
+        let params = params.map(|kdf| kdf.params()).unwrap_or_default();
+        Self::create_with_params(password.as_bytes(), &params)
+            .map(|(lock, _)| lock)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 以密碼衍生主儲存金鑰
+    pub fn unlock(&self, password: &str) -> Result<StorageKey, JsError> {
+        self.unlock_key(password.as_bytes()).map_err(|e| JsError::new(&e))
+    }
+
+    /// KDF 參數字串
+    #[wasm_bindgen(getter)]
+    pub fn params(&self) -> String {
+        self.params.clone()
+    }
+
+    /// 序列化為 JSON
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> Result<String, JsError> {
+        serde_json::to_string(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 從 JSON 還原
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str) -> Result<StorageLock, JsError> {
+        serde_json::from_str(json).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+/// 主金鑰輪替進度 (明文存放在資料庫中，不含秘密)
+///
+/// 記錄逐批重新加密，已以新金鑰加密的記錄在中斷後重新掃描時略過，
+/// 因此只需記錄已完成的倉庫數與新金鑰的驗證值
+#[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct KeyRotation {
+    verifier: Vec<u8>,
+    /// 變更密碼時的新密碼鎖，輪替完成時取代舊的密碼鎖
+    lock: Option<StorageLock>,
+    /// 已完成的倉庫數
+    pub(crate) completed: u32,
+}
+
+#[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+impl KeyRotation {
+    pub(crate) fn new(key: &StorageKey, lock: Option<StorageLock>) -> Self {
+        Self { verifier: key.verifier(), lock, completed: 0 }
+    }
+
+    /// 進行中的輪替是否以 `key` 為新金鑰
+    pub(crate) fn targets(&self, key: &StorageKey) -> bool {
+        constant_time_eq(&self.verifier, &key.verifier())
+    }
+
+    pub(crate) fn lock(&self) -> Option<&StorageLock> {
+        self.lock.as_ref()
+    }
+}
+
+/// 主金鑰輪替進度 (傳給進度 callback)
+#[wasm_bindgen]
+#[derive(Clone, Debug)]
+pub struct KeyRotationProgress {
+    store: String,
+    completed: u32,
+    total: u32,
+    records: u32,
+}
+
+#[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+impl KeyRotationProgress {
+    pub(crate) fn new(store: &str, completed: u32, total: u32, records: u32) -> Self {
+        Self { store: store.to_string(), completed, total, records }
+    }
+}
+
+#[wasm_bindgen]
+impl KeyRotationProgress {
+    /// 目前處理中的倉庫 (資料表)
+    #[wasm_bindgen(getter)]
+    pub fn store(&self) -> String {
+        self.store.clone()
+    }
+
+    /// 已完成的倉庫數
+    #[wasm_bindgen(getter)]
+    pub fn completed(&self) -> u32 {
+        self.completed
+    }
+
+    /// 需重新加密的倉庫總數
+    #[wasm_bindgen(getter)]
+    pub fn total(&self) -> u32 {
+        self.total
+    }
+
+    /// 目前的倉庫中本次已重新加密的記錄數
+    #[wasm_bindgen(getter)]
+    pub fn records(&self) -> u32 {
+        self.records
+    }
+}
+
+/// 儲存後端共用的記錄編解碼：未設定金鑰時只做 bincode 序列化
+#[derive(Clone, Default)]
+pub(crate) struct RecordCodec {
+    key: Option<StorageKey>,
+    /// 輪替期間的舊金鑰 (只用來解密)
+    previous: Option<StorageKey>,
+}
+
+impl RecordCodec {
+    pub(crate) fn new(key: Option<StorageKey>) -> Self {
+        Self { key, previous: None }
+    }
+
+    /// 是否設定了主儲存金鑰
+    #[cfg(feature = "indexeddb")]
+    pub(crate) fn is_encrypted(&self) -> bool {
+        self.key.is_some()
+    }
+
+    /// 輪替用的編碼器：以 `next` (沿用目前的盲化索引金鑰) 加密，解密時兩把金鑰都嘗試
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn rotating(&self, next: &StorageKey) -> Result<Self, String> {
+        let key = self.key.as_ref().ok_or("Storage is not encrypted")?;
+        Ok(Self { key: Some(next.with_index_key(&key.index_key)), previous: Some(key.clone()) })
+    }
+
+    /// 輪替完成後捨棄舊金鑰
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn finish_rotation(&self) -> Self {
+        Self::new(self.key.clone())
+    }
+
+    /// 保存盲化索引金鑰的記錄 (輪替完成時寫入，以目前的金鑰加密)
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn index_key_record(&self, store: &str, record_key: &str) -> Result<Vec<u8>, String> {
+        let key = self.key.as_ref().ok_or("Storage is not encrypted")?;
+        self.encode(store, record_key, &*key.index_key)
+    }
+
+    /// 套用 `index_key_record` 保存的盲化索引金鑰，主儲存金鑰錯誤時回傳錯誤
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn apply_index_key_record(&mut self, store: &str, record_key: &str, record: &[u8]) -> Result<(), String> {
+        let index_key = Zeroizing::new(
+            self.decode::<[u8; STORAGE_KEY_SIZE]>(store, record_key, record)
+                .map_err(|_| "Wrong storage key".to_string())?,
+        );
+        self.key = self.key.as_ref().map(|key| key.with_index_key(&index_key));
+        Ok(())
+    }
+
+    fn aad(store: &str, record_key: &[u8]) -> Vec<u8> {
+        [RECORD_AAD_PREFIX, &[0], store.as_bytes(), &[0], record_key].concat()
+    }
+
+    /// 序列化 (並加密) 記錄，`record_key` 為記錄在倉庫中的 (盲化後) 鍵
+    pub(crate) fn encode<T: Serialize>(
+        &self,
+        store: &str,
+        record_key: impl AsRef<[u8]>,
+        value: &T,
+    ) -> Result<Vec<u8>, String> {
+        let plaintext = Zeroizing::new(bincode::serialize(value).map_err(|e| e.to_string())?);
+        self.seal(store, record_key.as_ref(), &plaintext)
+    }
+
+    fn checksum(store: &str, record_key: &[u8], plaintext: &[u8]) -> [u8; PLAIN_CHECKSUM_SIZE] {
+        let mut checksum = [0u8; PLAIN_CHECKSUM_SIZE];
+        checksum.copy_from_slice(&sha256(&[Self::aad(store, record_key).as_slice(), plaintext].concat())[..PLAIN_CHECKSUM_SIZE]);
+        checksum
+    }
+
+    /// 檢查未加密記錄的檢查碼並取出內容，舊版記錄 (沒有 magic) 回傳 None
+    fn checked<'a>(store: &str, record_key: &[u8], bytes: &'a [u8]) -> Result<Option<&'a [u8]>, String> {
+        let Some(rest) = bytes.strip_prefix(&PLAIN_RECORD_MAGIC) else {
+            return Ok(None);
+        };
+        if rest.len() < PLAIN_CHECKSUM_SIZE {
+            return Err("Corrupted storage record: truncated".to_string());
+        }
+        let (checksum, plaintext) = rest.split_at(PLAIN_CHECKSUM_SIZE);
+        if !constant_time_eq(checksum, &Self::checksum(store, record_key, plaintext)) {
+            return Err("Corrupted storage record: checksum mismatch".to_string());
+        }
+        Ok(Some(plaintext))
+    }
+
+    fn seal(&self, store: &str, record_key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, String> {
+        let Some(key) = &self.key else {
+            return Ok([PLAIN_RECORD_MAGIC.as_slice(), &Self::checksum(store, record_key, plaintext), plaintext].concat());
+        };
+        let mut nonce = [0u8; NONCE_SIZE];
+        OsRng.fill_bytes(&mut nonce);
+        let aad = Self::aad(store, record_key);
+        let ciphertext = Aes256Gcm::new(key.record_key.as_slice().into())
+            .encrypt(Nonce::from_slice(&nonce), Payload { msg: plaintext, aad: &aad })
+            .map_err(|_| "Failed to encrypt storage record".to_string())?;
+        Ok([&[RECORD_VERSION], nonce.as_slice(), &ciphertext].concat())
+    }
+
+    /// (解密並) 反序列化記錄
+    pub(crate) fn decode<T: DeserializeOwned>(
+        &self,
+        store: &str,
+        record_key: impl AsRef<[u8]>,
+        bytes: &[u8],
+    ) -> Result<T, String> {
+        let plaintext = match (&self.key, &self.previous) {
+            (None, _) => match Self::checked(store, record_key.as_ref(), bytes) {
+                Ok(Some(plaintext)) => Zeroizing::new(plaintext.to_vec()),
+                Ok(None) => Zeroizing::new(bytes.to_vec()),
+                // 開頭恰好與 magic 相同的舊版記錄
+                Err(e) => return bincode::deserialize(bytes).map_err(|_| e),
+            },
+            (Some(key), None) => Self::open(key, store, record_key.as_ref(), bytes)?,
+            (Some(key), Some(previous)) => Self::open(key, store, record_key.as_ref(), bytes)
+                .or_else(|_| Self::open(previous, store, record_key.as_ref(), bytes))?,
+        };
+        bincode::deserialize(&plaintext).map_err(|e| format!("Corrupted storage record: {}", e))
+    }
+
+    fn open(key: &StorageKey, store: &str, record_key: &[u8], bytes: &[u8]) -> Result<Zeroizing<Vec<u8>>, String> {
+        let (version, rest) = bytes.split_first().ok_or("Corrupted storage record: empty")?;
+        if *version != RECORD_VERSION {
+            return Err(format!("Unsupported storage record version: {}", version));
+        }
+        if rest.len() < NONCE_SIZE {
+            return Err("Corrupted storage record: truncated".to_string());
+        }
+        let (nonce, ciphertext) = rest.split_at(NONCE_SIZE);
+        let aad = Self::aad(store, record_key);
+        Aes256Gcm::new(key.record_key.as_slice().into())
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: &aad })
+            .map(Zeroizing::new)
+            .map_err(|_| "Failed to decrypt storage record (wrong storage key or tampered data)".to_string())
+    }
+
+    /// 只檢查記錄的檢查碼 (驗證標籤)，不反序列化內容
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn verify(&self, store: &str, record_key: impl AsRef<[u8]>, bytes: &[u8]) -> Result<(), String> {
+        let record_key = record_key.as_ref();
+        match (&self.key, &self.previous) {
+            (None, _) => Self::checked(store, record_key, bytes).map(|_| ()),
+            (Some(key), None) => Self::open(key, store, record_key, bytes).map(|_| ()),
+            (Some(key), Some(previous)) => Self::open(key, store, record_key, bytes)
+                .or_else(|_| Self::open(previous, store, record_key, bytes))
+                .map(|_| ()),
+        }
+    }
+
+    /// 以輪替中的新金鑰重新加密記錄 (`rotating` 建立的編碼器)
+    ///
+    /// 已是新金鑰加密的記錄回傳 None；兩把金鑰都解不開時回傳錯誤
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn reseal(&self, store: &str, record_key: impl AsRef<[u8]>, bytes: &[u8]) -> Result<Option<Vec<u8>>, String> {
+        let (Some(key), Some(previous)) = (&self.key, &self.previous) else {
+            return Err("No storage key rotation in progress".to_string());
+        };
+        let record_key = record_key.as_ref();
+        match Self::open(previous, store, record_key, bytes) {
+            Ok(plaintext) => self.seal(store, record_key, &plaintext).map(Some),
+            Err(_) if Self::open(key, store, record_key, bytes).is_ok() => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 盲化查詢用的鍵 (位元組)；未設定金鑰時原樣回傳
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn blind_bytes(&self, field: &str, value: &[u8]) -> Vec<u8> {
+        match &self.key {
+            None => value.to_vec(),
+            Some(key) => key.blind_index(field, value).to_vec(),
+        }
+    }
+
+    /// 盲化查詢用的鍵 (字串)；未設定金鑰時原樣回傳
+    #[cfg(any(feature = "indexeddb", feature = "opfs", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn blind(&self, field: &str, value: &str) -> String {
+        use base64::{engine::general_purpose::URL_SAFE_NO_PAD as BASE64URL, Engine as _};
+
+        match &self.key {
+            None => value.to_string(),
+            Some(key) => BASE64URL.encode(key.blind_index(field, value.as_bytes())),
+        }
+    }
+
+    /// 產生新的對話金鑰，回傳 (金鑰, 以本編碼器編碼的金鑰記錄)
+    ///
+    /// `conversation_key` 為盲化後的對話 ID；對話金鑰只用來包裝該對話中各訊息的包裝金鑰
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn new_conversation_key(&self, conversation_key: impl AsRef<[u8]>) -> Result<(StorageKey, Vec<u8>), String> {
+        let key = StorageKey::generate();
+        let record = self.encode(CONVERSATION_KEY_STORE, conversation_key, &*key.master)?;
+        Ok((key, record))
+    }
+
+    /// 解開 `new_conversation_key` 的金鑰記錄
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn open_conversation_key(
+        &self,
+        conversation_key: impl AsRef<[u8]>,
+        record: &[u8],
+    ) -> Result<StorageKey, String> {
+        let master = Zeroizing::new(self.decode::<[u8; STORAGE_KEY_SIZE]>(CONVERSATION_KEY_STORE, conversation_key, record)?);
+        Ok(StorageKey::derive(master))
+    }
+
+    /// 以新產生的包裝金鑰加密記錄，回傳 (記錄, 以對話金鑰編碼的包裝金鑰)
+    ///
+    /// 兩者分開存放；刪除時銷毀包裝金鑰並輪替對話金鑰 (見 `rewrap`)
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn seal_wrapped<T: Serialize>(
+        conversation: &StorageKey,
+        store: &str,
+        record_key: impl AsRef<[u8]>,
+        value: &T,
+    ) -> Result<(Vec<u8>, Vec<u8>), String> {
+        let wrapping_key = StorageKey::generate();
+        let record = RecordCodec::new(Some(wrapping_key.clone())).encode(store, &record_key, value)?;
+        let key = RecordCodec::new(Some(conversation.clone())).encode(WRAPPING_KEY_STORE, &record_key, &*wrapping_key.master)?;
+        Ok((record, key))
+    }
+
+    /// 解開 `seal_wrapped` 的記錄；沒有包裝金鑰時視為舊版記錄直接解碼
+    ///
+    /// 對話金鑰出現之前 (SQLite schema 6、IndexedDB schema 7) 的包裝金鑰以本編碼器編碼，
+    /// 以對話金鑰解不開時改用本編碼器
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn open_wrapped<T: DeserializeOwned>(
+        &self,
+        conversation: Option<&StorageKey>,
+        store: &str,
+        record_key: impl AsRef<[u8]>,
+        record: &[u8],
+        wrapping_key: Option<&[u8]>,
+    ) -> Result<T, String> {
+        self.wrapped_codec(conversation, &record_key, wrapping_key)?
+            .decode(store, record_key, record)
+    }
+
+    /// 檢查 `seal_wrapped` 的記錄與其包裝金鑰的檢查碼
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn verify_wrapped(
+        &self,
+        conversation: Option<&StorageKey>,
+        store: &str,
+        record_key: impl AsRef<[u8]>,
+        record: &[u8],
+        wrapping_key: Option<&[u8]>,
+    ) -> Result<(), String> {
+        self.wrapped_codec(conversation, &record_key, wrapping_key)?
+            .verify(store, record_key, record)
+    }
+
+    /// `wrapping_key` 是否為以對話金鑰包裝的包裝金鑰 (不受主儲存金鑰輪替影響)
+    #[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+    pub(crate) fn is_wrapped_key(conversation: &StorageKey, record_key: impl AsRef<[u8]>, wrapping_key: &[u8]) -> bool {
+        Self::open(conversation, WRAPPING_KEY_STORE, record_key.as_ref(), wrapping_key).is_ok()
+    }
+
+    /// 把包裝金鑰改以新的對話金鑰包裝
+    ///
+    /// 刪除訊息時輪替對話金鑰：對話中其餘訊息的包裝金鑰改以新金鑰包裝，
+    /// 殘留在檔案中、以舊對話金鑰包裝的已刪除包裝金鑰隨舊金鑰一起失效
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn rewrap(
+        &self,
+        conversation: Option<&StorageKey>,
+        next: &StorageKey,
+        record_key: impl AsRef<[u8]>,
+        wrapping_key: &[u8],
+    ) -> Result<Vec<u8>, String> {
+        let master = self.open_wrapping_key(conversation, &record_key, wrapping_key)?;
+        RecordCodec::new(Some(next.clone())).encode(WRAPPING_KEY_STORE, record_key, &*master)
+    }
+
+    /// 解開包裝金鑰，回傳解開記錄用的編碼器 (沒有包裝金鑰時為本編碼器)
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    fn wrapped_codec(
+        &self,
+        conversation: Option<&StorageKey>,
+        record_key: impl AsRef<[u8]>,
+        wrapping_key: Option<&[u8]>,
+    ) -> Result<RecordCodec, String> {
+        let Some(wrapping_key) = wrapping_key else {
+            return Ok(self.clone());
+        };
+        let master = self.open_wrapping_key(conversation, record_key, wrapping_key)?;
+        Ok(RecordCodec::new(Some(StorageKey::derive(master))))
+    }
+
+    /// 以對話金鑰解開包裝金鑰，解不開時視為舊版記錄改用本編碼器
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    fn open_wrapping_key(
+        &self,
+        conversation: Option<&StorageKey>,
+        record_key: impl AsRef<[u8]>,
+        wrapping_key: &[u8],
+    ) -> Result<Zeroizing<[u8; STORAGE_KEY_SIZE]>, String> {
+        match conversation.and_then(|key| {
+            RecordCodec::new(Some(key.clone())).decode::<[u8; STORAGE_KEY_SIZE]>(WRAPPING_KEY_STORE, &record_key, wrapping_key).ok()
+        }) {
+            Some(master) => Ok(Zeroizing::new(master)),
+            None => Ok(Zeroizing::new(self.decode::<[u8; STORAGE_KEY_SIZE]>(WRAPPING_KEY_STORE, &record_key, wrapping_key)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_codec() {
+        let key = StorageKey::generate();
+        let codec = RecordCodec::new(Some(key.clone()));
+        let sealed = codec.encode("sessions", "1:bob", &"secret session").unwrap();
+        assert_eq!(sealed[0], RECORD_VERSION);
+        assert!(!sealed.windows(6).any(|window| window == b"secret"));
+        // 相同內容每次加密結果不同
+        assert_ne!(sealed, codec.encode("sessions", "1:bob", &"secret session").unwrap());
+        assert_eq!(codec.decode::<String>("sessions", "1:bob", &sealed).unwrap(), "secret session");
+
+        // 搬到其他記錄鍵、竄改或使用錯誤金鑰皆無法解密
+        assert!(codec.decode::<String>("sessions", "1:carol", &sealed).is_err());
+        let mut tampered = sealed.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(codec.decode::<String>("sessions", "1:bob", &tampered).is_err());
+        assert!(RecordCodec::new(Some(StorageKey::generate())).decode::<String>("sessions", "1:bob", &sealed).is_err());
+
+        // 以相同主金鑰重建後可解密，盲化結果相同
+        let restored = RecordCodec::new(Some(StorageKey::from_slice(key.to_bytes().expose()).unwrap()));
+        assert_eq!(restored.decode::<String>("sessions", "1:bob", &sealed).unwrap(), "secret session");
+        let restored_key = StorageKey::from_slice(key.to_bytes().expose()).unwrap();
+        assert_eq!(restored_key.blind_index("contact", b"bob"), key.blind_index("contact", b"bob"));
+        assert_ne!(key.blind_index("contact", b"bob"), key.blind_index("conversation", b"bob"));
+
+        // 未設定金鑰時不加密，但仍以檢查碼偵測毀損；沒有檢查碼的舊版記錄照常讀取
+        let plain = RecordCodec::default();
+        let mut record = plain.encode("x", "y", &"hi").unwrap();
+        assert_eq!(plain.decode::<String>("x", "y", &record).unwrap(), "hi");
+        assert_eq!(plain.decode::<String>("x", "y", &bincode::serialize(&"old").unwrap()).unwrap(), "old");
+        *record.last_mut().unwrap() ^= 1;
+        assert!(plain.decode::<String>("x", "y", &record).unwrap_err().contains("checksum"));
+        assert!(StorageKey::from_slice(&[0u8; 16]).is_err());
+    }
+
+    #[test]
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    fn test_wrapped_record_erasure() {
+        let codec = RecordCodec::new(Some(StorageKey::generate()));
+        let (_, conversation_record) = codec.new_conversation_key("chat").unwrap();
+        let conversation = codec.open_conversation_key("chat", &conversation_record).unwrap();
+        assert!(codec.open_conversation_key("other", &conversation_record).is_err());
+
+        let (record, wrapping_key) = RecordCodec::seal_wrapped(&conversation, "messages", "m1", &"hello").unwrap();
+        let opened: String = codec
+            .open_wrapped(Some(&conversation), "messages", "m1", &record, Some(&wrapping_key))
+            .unwrap();
+        assert_eq!(opened, "hello");
+        // 銷毀包裝金鑰後，主儲存金鑰與對話金鑰都解不開殘留的記錄
+        assert!(codec.open_wrapped::<String>(Some(&conversation), "messages", "m1", &record, None).is_err());
+        // 包裝金鑰不能脫離對話金鑰使用
+        let (other, _) = codec.new_conversation_key("other").unwrap();
+        assert!(codec.open_wrapped::<String>(Some(&other), "messages", "m1", &record, Some(&wrapping_key)).is_err());
+
+        // 刪除 m1 後輪替對話金鑰：m2 的包裝金鑰改以新金鑰包裝，
+        // 殘留的 m1 包裝金鑰以新的對話金鑰解不開
+        let (kept, kept_key) = RecordCodec::seal_wrapped(&conversation, "messages", "m2", &"kept").unwrap();
+        let (next, _) = codec.new_conversation_key("chat").unwrap();
+        let kept_key = codec.rewrap(Some(&conversation), &next, "m2", &kept_key).unwrap();
+        let opened: String = codec.open_wrapped(Some(&next), "messages", "m2", &kept, Some(&kept_key)).unwrap();
+        assert_eq!(opened, "kept");
+        assert!(codec.open_wrapped::<String>(Some(&next), "messages", "m1", &record, Some(&wrapping_key)).is_err());
+        assert!(codec.rewrap(Some(&next), &next, "m1", &wrapping_key).is_err());
+
+        // 對話金鑰出現之前以主儲存金鑰包裝的記錄仍可解開
+        let legacy_key = StorageKey::generate();
+        let legacy = RecordCodec::new(Some(legacy_key.clone())).encode("messages", "m0", &"old").unwrap();
+        let legacy_wrapping = codec.encode(WRAPPING_KEY_STORE, "m0", &*legacy_key.master).unwrap();
+        let opened: String = codec
+            .open_wrapped(Some(&conversation), "messages", "m0", &legacy, Some(&legacy_wrapping))
+            .unwrap();
+        assert_eq!(opened, "old");
+    }
+
+    #[test]
+    fn test_storage_lock() {
+        let params = PasswordKdf::argon2id(Some(64), Some(1), None).unwrap().params();
+        let (lock, key) = StorageLock::create_with_params(b"correct horse", &params).unwrap();
+        assert!(lock.params().starts_with("$argon2id$v=19$m=64,t=1,p=1$"));
+
+        let unlocked = lock.unlock_key(b"correct horse").unwrap();
+        assert_eq!(unlocked.to_bytes(), key.to_bytes());
+        assert_eq!(lock.unlock_key(b"wrong horse").unwrap_err(), "Wrong storage password");
+
+        // 相同密碼每次建立的 salt 不同
+        let (other, other_key) = StorageLock::create_with_params(b"correct horse", &params).unwrap();
+        assert_ne!(other.params(), lock.params());
+        assert_ne!(other_key.to_bytes(), key.to_bytes());
+        assert_eq!(StorageLock::from_json(&lock.to_json().unwrap()).unwrap(), lock);
+    }
+}
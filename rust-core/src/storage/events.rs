@@ -0,0 +1,288 @@
+//! 儲存變更通知模組
+//!
+//! JS 端使用的儲存 (`InMemoryProtocolStore`、`InMemoryMessageStore`、
+//! `InMemorySettingsStore`、`IndexedDbStore`) 在會話更新、訊息寫入或刪除、
+//! 聯絡人身份變更、設定變更時通知訂閱者，UI 不必輪詢或自行複製一份狀態
+//!
+//! `IndexedDbStore` 啟用多分頁協調時，通知也會轉發給其他分頁的訂閱者 (見 `tabs` 模組)
+//!
+//! 通知在變更完成後同步發出；在 `Transactional::atomic` 中的通知暫存到
+//! 交易提交後才發出，交易還原時一併捨棄。訂閱者不會被序列化
+
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::trust::IdentityStatus;
+
+/// 變更類型
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StoreEventKind {
+    /// 會話建立或更新
+    SessionUpdated = 0,
+    /// 會話刪除
+    SessionRemoved = 1,
+    /// 聯絡人身份首次記錄或變更
+    IdentityChanged = 2,
+    /// 訊息寫入 (含覆寫)
+    MessageStored = 3,
+    /// 訊息刪除
+    MessageRemoved = 4,
+    /// 設定變更 (含重設為預設值)
+    SettingChanged = 5,
+}
+
+/// 儲存變更
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoreEvent {
+    kind: StoreEventKind,
+    contact_id: Option<String>,
+    device_id: Option<u32>,
+    identity_status: Option<IdentityStatus>,
+    message_id: Option<String>,
+    conversation_id: Option<String>,
+    #[serde(default)]
+    setting_key: Option<String>,
+}
+
+impl StoreEvent {
+    fn new(kind: StoreEventKind) -> Self {
+        Self {
+            kind,
+            contact_id: None,
+            device_id: None,
+            identity_status: None,
+            message_id: None,
+            conversation_id: None,
+            setting_key: None,
+        }
+    }
+
+    pub(crate) fn session(kind: StoreEventKind, contact_id: &str, device_id: u32) -> Self {
+        Self { contact_id: Some(contact_id.to_string()), device_id: Some(device_id), ..Self::new(kind) }
+    }
+
+    pub(crate) fn identity_changed(contact_id: &str, status: IdentityStatus) -> Self {
+        Self {
+            contact_id: Some(contact_id.to_string()),
+            identity_status: Some(status),
+            ..Self::new(StoreEventKind::IdentityChanged)
+        }
+    }
+
+    pub(crate) fn message(kind: StoreEventKind, message_id: &str, conversation_id: &str) -> Self {
+        Self {
+            message_id: Some(message_id.to_string()),
+            conversation_id: Some(conversation_id.to_string()),
+            ..Self::new(kind)
+        }
+    }
+
+    pub(crate) fn setting(key: &str) -> Self {
+        Self { setting_key: Some(key.to_string()), ..Self::new(StoreEventKind::SettingChanged) }
+    }
+}
+
+#[wasm_bindgen]
+impl StoreEvent {
+    #[wasm_bindgen(getter)]
+    pub fn kind(&self) -> StoreEventKind {
+        self.kind
+    }
+
+    #[wasm_bindgen(getter, js_name = contactId)]
+    pub fn contact_id(&self) -> Option<String> {
+        self.contact_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = deviceId)]
+    pub fn device_id(&self) -> Option<u32> {
+        self.device_id
+    }
+
+    /// 身份變更時記錄前的狀態 (`NewIdentity` 或 `Changed`)
+    #[wasm_bindgen(getter, js_name = identityStatus)]
+    pub fn identity_status(&self) -> Option<IdentityStatus> {
+        self.identity_status
+    }
+
+    #[wasm_bindgen(getter, js_name = messageId)]
+    pub fn message_id(&self) -> Option<String> {
+        self.message_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = conversationId)]
+    pub fn conversation_id(&self) -> Option<String> {
+        self.conversation_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = settingKey)]
+    pub fn setting_key(&self) -> Option<String> {
+        self.setting_key.clone()
+    }
+}
+
+type Listener = Rc<dyn Fn(&StoreEvent)>;
+
+/// 變更訂閱者清單
+///
+/// 複製出的清單共用同一組訂閱者 (與共用連線的儲存實例一致)
+#[derive(Clone, Default)]
+pub struct StoreObservers {
+    listeners: Rc<RefCell<BTreeMap<u32, Listener>>>,
+    next_id: Rc<Cell<u32>>,
+    /// 進行中的交易暫存的通知，每層巢狀交易一組
+    pending: Rc<RefCell<Vec<Vec<StoreEvent>>>>,
+}
+
+impl StoreObservers {
+    /// 訂閱變更，回傳取消訂閱用的 ID
+    pub fn subscribe(&self, listener: impl Fn(&StoreEvent) + 'static) -> u32 {
+        let id = self.next_id.get();
+        self.next_id.set(id.wrapping_add(1));
+        self.listeners.borrow_mut().insert(id, Rc::new(listener));
+        id
+    }
+
+    /// 訂閱 JS callback：`(event: StoreEvent) => void`，callback 拋出的例外會被忽略
+    pub(crate) fn subscribe_js(&self, callback: js_sys::Function) -> u32 {
+        self.subscribe(move |event| {
+            let _ = callback.call1(&JsValue::NULL, &event.clone().into());
+        })
+    }
+
+    /// 取消訂閱，回傳是否存在
+    pub fn unsubscribe(&self, id: u32) -> bool {
+        self.listeners.borrow_mut().remove(&id).is_some()
+    }
+
+    /// 通知所有訂閱者，交易進行中時暫存到提交為止
+    pub(crate) fn emit(&self, event: StoreEvent) {
+        if let Some(events) = self.pending.borrow_mut().last_mut() {
+            events.push(event);
+            return;
+        }
+        self.deliver(&event);
+    }
+
+    /// 開始交易 (可巢狀)
+    pub(crate) fn begin(&self) {
+        self.pending.borrow_mut().push(Vec::new());
+    }
+
+    /// 提交交易：併入外層交易，或在最外層時發出暫存的通知
+    pub(crate) fn commit(&self) {
+        let mut pending = self.pending.borrow_mut();
+        let Some(events) = pending.pop() else {
+            return;
+        };
+        match pending.last_mut() {
+            Some(outer) => outer.extend(events),
+            None => {
+                drop(pending);
+                for event in events {
+                    self.deliver(&event);
+                }
+            }
+        }
+    }
+
+    /// 還原交易：捨棄暫存的通知
+    pub(crate) fn rollback(&self) {
+        self.pending.borrow_mut().pop();
+    }
+
+    /// 立即通知所有訂閱者 (訂閱者可在 callback 中訂閱或取消訂閱)
+    fn deliver(&self, event: &StoreEvent) {
+        let listeners: Vec<Listener> = self.listeners.borrow().values().cloned().collect();
+        for listener in listeners {
+            listener(event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{IdentityKeyPair, RatchetSession};
+    use crate::storage::{
+        IdentityKeyStore, InMemoryMessageStore, InMemoryProtocolStore, MessageStore, SessionStore, StoredMessage,
+        Transactional,
+    };
+
+    fn record(observers: &StoreObservers) -> (u32, Rc<RefCell<Vec<StoreEvent>>>) {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let sink = events.clone();
+        let id = observers.subscribe(move |event| sink.borrow_mut().push(event.clone()));
+        (id, events)
+    }
+
+    #[test]
+    fn test_store_change_notifications() {
+        let mut protocol = InMemoryProtocolStore::new(&IdentityKeyPair::new(), 1);
+        let (_, protocol_events) = record(protocol.observers());
+        let contact_key = IdentityKeyPair::new().public_key_bytes();
+        protocol.save_identity("bob", &contact_key, 1).unwrap();
+        protocol.save_identity("bob", &contact_key, 2).unwrap();
+        protocol.store_session("bob", 1, &RatchetSession::for_test([3u8; 32])).unwrap();
+        assert!(protocol.remove_session("bob", 1).unwrap());
+        assert!(!protocol.remove_session("bob", 1).unwrap());
+
+        let kinds: Vec<StoreEventKind> = protocol_events.borrow().iter().map(StoreEvent::kind).collect();
+        assert_eq!(
+            kinds,
+            vec![StoreEventKind::IdentityChanged, StoreEventKind::SessionUpdated, StoreEventKind::SessionRemoved]
+        );
+        assert_eq!(protocol_events.borrow()[0].identity_status(), Some(IdentityStatus::NewIdentity));
+        assert_eq!(protocol_events.borrow()[1].device_id(), Some(1));
+
+        let mut messages = InMemoryMessageStore::new();
+        let (id, message_events) = record(messages.observers());
+        messages.store_message(&StoredMessage::new("m1", "chat", "bob", 1, b"hi")).unwrap();
+        assert!(messages.remove_message("m1").unwrap());
+        assert_eq!(message_events.borrow().len(), 2);
+        assert_eq!(message_events.borrow()[1].kind(), StoreEventKind::MessageRemoved);
+        assert_eq!(message_events.borrow()[1].conversation_id().as_deref(), Some("chat"));
+
+        // 取消訂閱後不再通知
+        assert!(messages.observers().unsubscribe(id));
+        messages.store_message(&StoredMessage::new("m2", "chat", "bob", 2, b"hi")).unwrap();
+        assert_eq!(message_events.borrow().len(), 2);
+    }
+
+    #[test]
+    fn test_notifications_wait_for_commit() {
+        let mut messages = InMemoryMessageStore::new();
+        let (_, events) = record(messages.observers());
+
+        // 還原的交易不發出通知
+        let result: Result<(), String> = messages.atomic(|messages| {
+            messages.store_message(&StoredMessage::new("m1", "chat", "bob", 1, b"hi"))?;
+            Err("Failed to decrypt message".to_string())
+        });
+        assert!(result.is_err());
+        assert!(events.borrow().is_empty());
+
+        // 提交後才發出，巢狀交易中還原的部分被捨棄
+        messages
+            .atomic(|messages| {
+                messages.store_message(&StoredMessage::new("m1", "chat", "bob", 1, b"hi"))?;
+                let inner: Result<(), String> = messages.atomic(|messages| {
+                    messages.store_message(&StoredMessage::new("m2", "chat", "bob", 2, b"hi"))?;
+                    Err("rollback".to_string())
+                });
+                assert!(inner.is_err());
+                messages.atomic(|messages| messages.store_message(&StoredMessage::new("m3", "chat", "bob", 3, b"hi")))?;
+                assert!(events.borrow().is_empty());
+                Ok(())
+            })
+            .unwrap();
+        let ids: Vec<String> = events.borrow().iter().filter_map(StoreEvent::message_id).collect();
+        assert_eq!(ids, vec!["m1", "m3"]);
+    }
+}
@@ -0,0 +1,324 @@
+//! 群組成員儲存模組
+//!
+//! 群組記錄包含成員 (角色與裝置清單)、各成員裝置的 sender key 狀態參照
+//! 與成員變更的 epoch，作為群組訊息功能的持久化基礎
+//!
+//! 成員或裝置增減時 epoch 加一，並丟棄離開的裝置的 sender key 參照；
+//! 群組訊息層看到 epoch 改變時應輪替自己的 sender key。sender key 本身
+//! 不存在群組記錄中，參照指向實際保存狀態的位置 (例如鍵值儲存的 key)
+//!
+//! 持久化後端與其他記錄一樣以主儲存金鑰加密、群組 ID 盲化
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::encryption::{RecordCodec, StorageKey, SNAPSHOT_STORE};
+
+const GROUP_SNAPSHOT_KEY: &str = "group_store";
+
+/// 群組成員角色
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GroupRole {
+    /// 一般成員
+    Member = 0,
+    /// 管理員 (可變更成員與角色)
+    Admin = 1,
+}
+
+/// 群組成員
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GroupMember {
+    contact_id: String,
+    role: GroupRole,
+    /// 裝置 ID (排序、不重複)
+    devices: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl GroupMember {
+    #[wasm_bindgen(getter, js_name = contactId)]
+    pub fn contact_id(&self) -> String {
+        self.contact_id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn role(&self) -> GroupRole {
+        self.role
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn devices(&self) -> Vec<u32> {
+        self.devices.clone()
+    }
+}
+
+/// 排序並去除重複的裝置 ID
+fn normalize_devices(devices: &[u32]) -> Vec<u32> {
+    let mut devices = devices.to_vec();
+    devices.sort_unstable();
+    devices.dedup();
+    devices
+}
+
+/// 群組記錄
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Group {
+    id: String,
+    /// 成員變更次數
+    epoch: u64,
+    /// contact_id -> 成員
+    members: BTreeMap<String, GroupMember>,
+    /// (contact_id, device_id) -> sender key 狀態參照
+    sender_keys: BTreeMap<(String, u32), String>,
+}
+
+impl Group {
+    /// 丟棄成員已不存在的裝置的 sender key 參照
+    fn retain_sender_keys(&mut self) {
+        let members = &self.members;
+        self.sender_keys.retain(|(contact_id, device_id), _| {
+            members
+                .get(contact_id)
+                .is_some_and(|member| member.devices.binary_search(device_id).is_ok())
+        });
+    }
+
+    /// 設定成員裝置的 sender key 狀態參照 (裝置須在成員的裝置清單中)
+    pub fn set_sender_key(&mut self, contact_id: &str, device_id: u32, reference: &str) -> Result<(), String> {
+        let member = self
+            .members
+            .get(contact_id)
+            .ok_or_else(|| "Contact is not a group member".to_string())?;
+        if member.devices.binary_search(&device_id).is_err() {
+            return Err("Device is not registered for this group member".to_string());
+        }
+        self.sender_keys.insert((contact_id.to_string(), device_id), reference.to_string());
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl Group {
+    /// 建立沒有成員的群組 (epoch 0)
+    #[wasm_bindgen(constructor)]
+    pub fn new(id: &str) -> Self {
+        Self { id: id.to_string(), epoch: 0, members: BTreeMap::new(), sender_keys: BTreeMap::new() }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    /// 成員變更次數
+    #[wasm_bindgen(getter)]
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// 所有成員 (依聯絡人 ID 排序)
+    #[wasm_bindgen(getter)]
+    pub fn members(&self) -> Vec<GroupMember> {
+        self.members.values().cloned().collect()
+    }
+
+    /// 取得成員
+    pub fn member(&self, contact_id: &str) -> Option<GroupMember> {
+        self.members.get(contact_id).cloned()
+    }
+
+    /// 加入成員或更新其角色與裝置清單
+    ///
+    /// 新成員或裝置清單改變時 epoch 加一；只變更角色時不變
+    #[wasm_bindgen(js_name = setMember)]
+    pub fn set_member(&mut self, contact_id: &str, role: GroupRole, devices: &[u32]) {
+        let devices = normalize_devices(devices);
+        let changed = self.members.get(contact_id).is_none_or(|member| member.devices != devices);
+        self.members.insert(contact_id.to_string(), GroupMember { contact_id: contact_id.to_string(), role, devices });
+        if changed {
+            self.epoch += 1;
+            self.retain_sender_keys();
+        }
+    }
+
+    /// 移除成員，回傳是否存在 (存在時 epoch 加一)
+    #[wasm_bindgen(js_name = removeMember)]
+    pub fn remove_member(&mut self, contact_id: &str) -> bool {
+        if self.members.remove(contact_id).is_none() {
+            return false;
+        }
+        self.epoch += 1;
+        self.retain_sender_keys();
+        true
+    }
+
+    /// 設定成員裝置的 sender key 狀態參照
+    #[wasm_bindgen(js_name = setSenderKey)]
+    pub fn set_sender_key_js(&mut self, contact_id: &str, device_id: u32, reference: &str) -> Result<(), JsError> {
+        self.set_sender_key(contact_id, device_id, reference).map_err(|e| JsError::new(&e))
+    }
+
+    /// 成員裝置的 sender key 狀態參照
+    #[wasm_bindgen(js_name = senderKey)]
+    pub fn sender_key(&self, contact_id: &str, device_id: u32) -> Option<String> {
+        self.sender_keys.get(&(contact_id.to_string(), device_id)).cloned()
+    }
+
+    /// 清除所有 sender key 參照 (輪替 sender key 後)
+    #[wasm_bindgen(js_name = clearSenderKeys)]
+    pub fn clear_sender_keys(&mut self) {
+        self.sender_keys.clear();
+    }
+}
+
+/// 群組儲存
+pub trait GroupStore {
+    /// 載入群組
+    fn load_group(&self, group_id: &str) -> Result<Option<Group>, String>;
+
+    /// 儲存 (覆寫) 群組
+    fn store_group(&mut self, group: &Group) -> Result<(), String>;
+
+    /// 刪除群組，回傳是否存在
+    fn remove_group(&mut self, group_id: &str) -> Result<bool, String>;
+
+    /// 所有群組 (依 ID 排序)
+    fn groups(&self) -> Result<Vec<Group>, String>;
+}
+
+/// 非同步群組儲存，方法與 [`GroupStore`] 相同
+#[allow(async_fn_in_trait)]
+pub trait AsyncGroupStore {
+    async fn load_group(&self, group_id: &str) -> Result<Option<Group>, String>;
+    async fn store_group(&mut self, group: &Group) -> Result<(), String>;
+    async fn remove_group(&mut self, group_id: &str) -> Result<bool, String>;
+    async fn groups(&self) -> Result<Vec<Group>, String>;
+}
+
+/// 記憶體群組儲存，以 `serializeEncrypted` 持久化
+#[wasm_bindgen]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct InMemoryGroupStore {
+    /// group_id -> 群組
+    groups: BTreeMap<String, Group>,
+}
+
+impl GroupStore for InMemoryGroupStore {
+    fn load_group(&self, group_id: &str) -> Result<Option<Group>, String> {
+        Ok(self.groups.get(group_id).cloned())
+    }
+
+    fn store_group(&mut self, group: &Group) -> Result<(), String> {
+        self.groups.insert(group.id.clone(), group.clone());
+        Ok(())
+    }
+
+    fn remove_group(&mut self, group_id: &str) -> Result<bool, String> {
+        Ok(self.groups.remove(group_id).is_some())
+    }
+
+    fn groups(&self) -> Result<Vec<Group>, String> {
+        Ok(self.groups.values().cloned().collect())
+    }
+}
+
+#[wasm_bindgen]
+impl InMemoryGroupStore {
+    /// 建立空的群組儲存
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 載入群組
+    #[wasm_bindgen(js_name = loadGroup)]
+    pub fn load_group_js(&self, group_id: &str) -> Result<Option<Group>, JsError> {
+        self.load_group(group_id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 儲存 (覆寫) 群組
+    #[wasm_bindgen(js_name = storeGroup)]
+    pub fn store_group_js(&mut self, group: &Group) -> Result<(), JsError> {
+        self.store_group(group).map_err(|e| JsError::new(&e))
+    }
+
+    /// 刪除群組，回傳是否存在
+    #[wasm_bindgen(js_name = removeGroup)]
+    pub fn remove_group_js(&mut self, group_id: &str) -> Result<bool, JsError> {
+        self.remove_group(group_id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 所有群組 (依 ID 排序)
+    #[wasm_bindgen(js_name = groups)]
+    pub fn groups_js(&self) -> Result<Vec<Group>, JsError> {
+        self.groups().map_err(|e| JsError::new(&e))
+    }
+
+    /// 群組總數
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// 以主儲存金鑰加密序列化
+    #[wasm_bindgen(js_name = serializeEncrypted)]
+    pub fn serialize_encrypted(&self, key: &StorageKey) -> Result<Vec<u8>, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .encode(SNAPSHOT_STORE, GROUP_SNAPSHOT_KEY, self)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密並還原
+    #[wasm_bindgen(js_name = deserializeEncrypted)]
+    pub fn deserialize_encrypted(bytes: &[u8], key: &StorageKey) -> Result<InMemoryGroupStore, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .decode(SNAPSHOT_STORE, GROUP_SNAPSHOT_KEY, bytes)
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_membership_epochs() {
+        let mut group = Group::new("family");
+        group.set_member("alice", GroupRole::Admin, &[2, 1, 2]);
+        group.set_member("bob", GroupRole::Member, &[1]);
+        assert_eq!(group.epoch(), 2);
+        assert_eq!(group.member("alice").unwrap().devices(), vec![1, 2]);
+
+        group.set_sender_key("alice", 2, "sender_keys:family:alice:2").unwrap();
+        group.set_sender_key("bob", 1, "sender_keys:family:bob:1").unwrap();
+        assert!(group.set_sender_key("bob", 2, "sender_keys:family:bob:2").is_err());
+
+        // 只變更角色不影響 epoch 與 sender key
+        group.set_member("bob", GroupRole::Admin, &[1]);
+        assert_eq!(group.epoch(), 2);
+        assert_eq!(group.member("bob").unwrap().role(), GroupRole::Admin);
+
+        // 裝置離開時丟棄其 sender key 參照
+        group.set_member("alice", GroupRole::Admin, &[1]);
+        assert_eq!(group.epoch(), 3);
+        assert_eq!(group.sender_key("alice", 2), None);
+        assert!(group.remove_member("bob"));
+        assert!(!group.remove_member("bob"));
+        assert_eq!(group.epoch(), 4);
+        assert_eq!(group.sender_key("bob", 1), None);
+
+        let mut store = InMemoryGroupStore::new();
+        store.store_group(&group).unwrap();
+        store.store_group(&Group::new("book-club")).unwrap();
+        let key = StorageKey::generate();
+        let restored = InMemoryGroupStore::deserialize_encrypted(&store.serialize_encrypted(&key).unwrap(), &key).unwrap();
+        let ids: Vec<String> = restored.groups().unwrap().iter().map(Group::id).collect();
+        assert_eq!(ids, vec!["book-club", "family"]);
+        assert_eq!(restored.load_group("family").unwrap(), Some(group));
+    }
+}
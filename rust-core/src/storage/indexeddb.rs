@@ -0,0 +1,2335 @@
+//! IndexedDB 儲存模組 (feature = "indexeddb")
+//!
+//! 以 IndexedDB 實作非同步的協定儲存、訊息儲存、鍵值儲存、聯絡人與群組儲存介面，瀏覽器端不必再把整個
+//! 金鑰庫序列化到 localStorage。每次寫入都在交易完成 (`complete`) 後才回傳，
+//! 消耗預金鑰、更新身份等讀後寫的操作在同一個交易中完成
+//!
+//! 記錄以 bincode 序列化為 `Uint8Array` 存放，物件倉庫：
+//! - `local`：本機身份與下一個一次性預金鑰 ID
+//! - `identities` / `revocations`：聯絡人身份 (TOFU) 與撤銷憑證
+//! - `sessions`：[contact_id, device_id] -> 會話
+//! - `pre_keys` / `signed_pre_keys`：key_id -> 預金鑰
+//! - `messages`：[conversation_id, timestamp, id] -> 訊息；`message_ids`：id -> 排序鍵
+//! - `search_tokens`：[盲化詞元, 訊息 ID]；`search_messages`：訊息 ID -> 盲化詞元 (schema 2)
+//! - `blob_chunks`：[blob_id, index] -> 已加密的附件 chunk (schema 3，見 `blobs` 模組)
+//! - `message_keys`：訊息 ID -> 訊息的包裝金鑰；`retention_policies`：對話 ID -> 保留政策 (schema 4)
+//! - `kv`：[namespace, key] -> 應用層秘密 (schema 5，見 `kv` 模組)
+//! - `contacts`：contact_id -> 聯絡人 (schema 6，見 `contacts` 模組)
+//! - `groups`：group_id -> 群組 (schema 7，見 `groups` 模組)
+//! - `conversation_keys`：對話 ID -> 包裝該對話訊息金鑰的對話金鑰 (schema 8)
+//!
+//! 訊息內容以每則訊息各自的包裝金鑰加密，包裝金鑰再以對話金鑰加密，刪除訊息時一併刪除金鑰並輪替對話金鑰。
+//! 瀏覽器不保證覆寫被刪除的資料：殘留的包裝金鑰無法以新的對話金鑰解開，但舊對話金鑰的記錄
+//! 若也殘留在檔案中，持有主儲存金鑰者仍可能還原已刪除的訊息
+//!
+//! 以 `openEncrypted` 或 `unlockStorage` (密碼) 開啟時，所有記錄以主儲存金鑰
+//! 加密、鍵中的 ID 盲化 (見 `encryption` 模組)
+//!
+//! `rotateStorageKey` / `changeStoragePassword` 以新的主儲存金鑰逐批重新加密所有記錄，
+//! 中斷後再次呼叫即繼續 (見 `encryption` 模組)
+//!
+//! `verifyStorage` 檢查每筆記錄的檢查碼並修復毀損的預金鑰與會話 (見 `integrity` 模組)
+//!
+//! 多個分頁開啟同一個資料庫時，以 `coordinateTabs` 讓單一分頁負責會話寫入 (見 `tabs` 模組)
+//!
+//! 只能在有 `indexedDB` 的 JS 環境 (瀏覽器、Worker) 中使用
+
+use std::cell::RefCell;
+use std::collections::BTreeSet;
+use std::rc::Rc;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use js_sys::{Array, Function, Promise, Uint8Array};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use web_sys::{
+    IdbDatabase, IdbFactory, IdbKeyRange, IdbObjectStore, IdbRequest, IdbTransaction,
+    IdbTransactionMode, IdbVersionChangeEvent,
+};
+
+use crate::crypto::{
+    CipherSuite, IdentityKeyPair, OneTimePreKey, PasswordKdf, PasswordKdfParams, PreKeyBundle, RatchetSession, RevocationCertificate, SignedPreKeyRecord,
+    X25519KeyPair, X3DHInitialMessage, X3DH, MAX_BATCH_SIZE,
+};
+use super::avatars::AsyncAvatarCache;
+use super::blobs::{AsyncBlobStore, BlobReader, BlobWriter};
+use super::contacts::{AsyncContactStore, Contact};
+use super::encryption::{KeyRotation, KeyRotationProgress, RecordCodec, StorageKey, StorageLock};
+use super::groups::{AsyncGroupStore, Group};
+use super::integrity::{RepairAction, StorageVerification};
+use super::events::{StoreEvent, StoreEventKind, StoreObservers};
+use super::kv::{kv_record_key, AsyncKeyValueStore, KV_KEY_FIELD, KV_NAMESPACE_FIELD};
+use super::messages::{AsyncMessageStore, RetentionPolicy, StoredMessage};
+use super::quota::{AsyncPruneTarget, PruneCandidate};
+use super::search::{search_tokens, SEARCH_TOKEN_FIELD};
+use super::tabs::TabCoordinator;
+use super::store::{AsyncIdentityKeyStore, AsyncPreKeyStore, AsyncSessionStore, AsyncSignedPreKeyStore};
+use super::trust::{IdentityStatus, TrustedIdentity};
+
+/// 目前的資料庫 schema 版本
+pub const INDEXEDDB_SCHEMA_VERSION: u32 = 8;
+
+const LOCAL: &str = "local";
+const IDENTITIES: &str = "identities";
+const REVOCATIONS: &str = "revocations";
+const SESSIONS: &str = "sessions";
+const PRE_KEYS: &str = "pre_keys";
+const SIGNED_PRE_KEYS: &str = "signed_pre_keys";
+const MESSAGES: &str = "messages";
+const MESSAGE_IDS: &str = "message_ids";
+const SEARCH_TOKENS: &str = "search_tokens";
+const SEARCH_MESSAGES: &str = "search_messages";
+const BLOB_CHUNKS: &str = "blob_chunks";
+const MESSAGE_KEYS: &str = "message_keys";
+const RETENTION_POLICIES: &str = "retention_policies";
+const KV: &str = "kv";
+const CONTACTS: &str = "contacts";
+const GROUPS: &str = "groups";
+const CONVERSATION_KEYS: &str = "conversation_keys";
+const OBJECT_STORES: [&str; 17] = [
+    LOCAL,
+    IDENTITIES,
+    REVOCATIONS,
+    SESSIONS,
+    PRE_KEYS,
+    SIGNED_PRE_KEYS,
+    MESSAGES,
+    MESSAGE_IDS,
+    SEARCH_TOKENS,
+    SEARCH_MESSAGES,
+    BLOB_CHUNKS,
+    MESSAGE_KEYS,
+    RETENTION_POLICIES,
+    KV,
+    CONTACTS,
+    GROUPS,
+    CONVERSATION_KEYS,
+];
+/// 各物件倉庫加入時的 schema 版本
+const OBJECT_STORE_VERSIONS: [u32; 17] = [1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 3, 4, 4, 5, 6, 7, 8];
+
+const LOCAL_IDENTITY_KEY: &str = "identity";
+const NEXT_PRE_KEY_ID_KEY: &str = "next_pre_key_id";
+/// 密碼鎖以明文存放在 `local` 中
+const STORAGE_LOCK_KEY: &str = "storage_lock";
+/// 主金鑰輪替進度以明文存放在 `local` 中
+const KEY_ROTATION_KEY: &str = "key_rotation";
+/// 主金鑰輪替後沿用的盲化索引金鑰
+const INDEX_KEY_KEY: &str = "index_key";
+/// 因毀損被刪除、需要重新握手的會話 (會話記錄鍵)
+const SESSION_RESETS_KEY: &str = "session_resets";
+/// 主金鑰輪替時預設每批重新加密的記錄數
+const KEY_ROTATION_CHUNK_SIZE: u32 = 256;
+/// 主金鑰輪替時重新加密的物件倉庫
+///
+/// 搜尋索引與 `message_ids` 只存盲化的鍵，附件 chunk 以附件金鑰加密；以包裝金鑰加密的訊息與
+/// 以對話金鑰加密的包裝金鑰不受主金鑰影響，只重新加密其中的舊版記錄
+const ROTATED_STORES: [&str; 13] = [
+    LOCAL,
+    IDENTITIES,
+    REVOCATIONS,
+    SESSIONS,
+    PRE_KEYS,
+    SIGNED_PRE_KEYS,
+    MESSAGES,
+    MESSAGE_KEYS,
+    RETENTION_POLICIES,
+    KV,
+    CONTACTS,
+    GROUPS,
+    CONVERSATION_KEYS,
+];
+
+/// `local` 倉庫中的本機身份
+#[derive(Serialize, Deserialize)]
+struct LocalIdentity {
+    identity: IdentityKeyPair,
+    registration_id: u32,
+}
+
+fn js_error(context: &str, error: JsValue) -> String {
+    format!("{}: {:?}", context, error)
+}
+
+/// 取得全域的 `indexedDB` (window 與 worker 皆可)
+fn indexed_db() -> Result<IdbFactory, String> {
+    js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("indexedDB"))
+        .ok()
+        .filter(|factory| !factory.is_undefined() && !factory.is_null())
+        .map(JsCast::unchecked_into)
+        .ok_or_else(|| "IndexedDB is not available".to_string())
+}
+
+type AttachHandlers = Box<dyn Fn(Option<&Function>, Option<&Function>)>;
+
+/// 已掛上處理器、等待觸發的成功 / 失敗事件
+///
+/// 兩個事件只會觸發其中一個；drop 時卸下處理器，提早回傳錯誤後事件才觸發也不會呼叫到已釋放的 closure
+struct PendingEvent {
+    promise: Promise,
+    attach: AttachHandlers,
+    _on_done: Closure<dyn FnMut()>,
+    _on_fail: Closure<dyn FnMut()>,
+}
+
+impl PendingEvent {
+    fn new(attach: impl Fn(Option<&Function>, Option<&Function>) + 'static) -> Self {
+        let mut callbacks = None;
+        let promise = Promise::new(&mut |resolve, reject| callbacks = Some((resolve, reject)));
+        let (resolve, reject) = callbacks.expect("Promise executor runs synchronously");
+        let on_done = Closure::<dyn FnMut()>::new(move || {
+            let _ = resolve.call0(&JsValue::UNDEFINED);
+        });
+        let on_fail = Closure::<dyn FnMut()>::new(move || {
+            let _ = reject.call0(&JsValue::UNDEFINED);
+        });
+        attach(Some(on_done.as_ref().unchecked_ref()), Some(on_fail.as_ref().unchecked_ref()));
+        Self { promise, attach: Box::new(attach), _on_done: on_done, _on_fail: on_fail }
+    }
+
+    fn request(request: &IdbRequest) -> Self {
+        let request = request.clone();
+        Self::new(move |done, fail| {
+            request.set_onsuccess(done);
+            request.set_onerror(fail);
+        })
+    }
+
+    /// 交易以 `complete` 成功；失敗時一定會觸發 `abort`
+    fn transaction(transaction: &IdbTransaction) -> Self {
+        let transaction = transaction.clone();
+        Self::new(move |done, fail| {
+            transaction.set_oncomplete(done);
+            transaction.set_onabort(fail);
+        })
+    }
+
+    /// 成功事件觸發時回傳 true
+    async fn wait(&self) -> bool {
+        JsFuture::from(self.promise.clone()).await.is_ok()
+    }
+}
+
+impl Drop for PendingEvent {
+    fn drop(&mut self) {
+        (self.attach)(None, None);
+    }
+}
+
+/// 等待請求完成並取得結果
+async fn request_result(request: IdbRequest) -> Result<JsValue, String> {
+    if !PendingEvent::request(&request).wait().await {
+        let message = request
+            .error()
+            .ok()
+            .flatten()
+            .map(|error| error.message())
+            .unwrap_or_default();
+        return Err(format!("IndexedDB request failed: {}", message));
+    }
+    request.result().map_err(|e| js_error("IndexedDB request failed", e))
+}
+
+fn encode<T: Serialize>(codec: &RecordCodec, store: &str, record_key: impl AsRef<[u8]>, value: &T) -> Result<JsValue, String> {
+    let bytes = codec.encode(store, record_key, value)?;
+    Ok(Uint8Array::from(bytes.as_slice()).into())
+}
+
+/// 記錄的位元組內容，不存在時為 None
+fn record_bytes(value: JsValue) -> Result<Option<Vec<u8>>, String> {
+    if value.is_undefined() {
+        return Ok(None);
+    }
+    value
+        .dyn_into::<Uint8Array>()
+        .map(|bytes| Some(bytes.to_vec()))
+        .map_err(|_| "Corrupted IndexedDB record".to_string())
+}
+
+fn decode<T: DeserializeOwned>(
+    codec: &RecordCodec,
+    store: &str,
+    record_key: impl AsRef<[u8]>,
+    value: JsValue,
+) -> Result<Option<T>, String> {
+    record_bytes(value)?
+        .map(|bytes| codec.decode(store, record_key, &bytes))
+        .transpose()
+}
+
+/// 會話記錄鍵 (裝置 ID 在前，聯絡人 ID 中的字元不會造成歧義)
+fn session_record_key(contact_key: &str, device_id: u32) -> String {
+    format!("{}:{}", device_id, contact_key)
+}
+
+fn blob_chunk_key(codec: &RecordCodec, blob_id: &str, index: u32) -> JsValue {
+    Array::of2(&JsValue::from_str(&codec.blind("blob", blob_id)), &JsValue::from(index)).into()
+}
+
+fn session_key(contact_key: &str, device_id: u32) -> JsValue {
+    Array::of2(&JsValue::from_str(contact_key), &JsValue::from(device_id)).into()
+}
+
+/// `messages` 倉庫的排序鍵：[盲化對話 ID, 時間戳, 盲化訊息 ID]
+fn message_key(codec: &RecordCodec, message: &StoredMessage) -> JsValue {
+    Array::of3(
+        &JsValue::from_str(&codec.blind("conversation", &message.conversation_id())),
+        &JsValue::from(message.timestamp() as f64),
+        &JsValue::from_str(&codec.blind("message", &message.id())),
+    )
+    .into()
+}
+
+/// 呼叫輪替進度 callback，回傳是否繼續 (只有回傳 `false` 時暫停)
+fn report_rotation_progress(callback: Option<&Function>, progress: &KeyRotationProgress) -> bool {
+    callback.is_none_or(|callback| {
+        callback
+            .call1(&JsValue::NULL, &progress.clone().into())
+            .map_or(true, |result| result.as_bool() != Some(false))
+    })
+}
+
+/// 物件倉庫中的鍵對應的記錄鍵 (加密時綁定，見各倉庫的讀寫)
+fn bound_record_key(store: &str, key: &JsValue) -> Vec<u8> {
+    let element = |index| key.unchecked_ref::<Array>().get(index).as_string().unwrap_or_default();
+    match store {
+        SESSIONS => {
+            let device_id = key.unchecked_ref::<Array>().get(1).as_f64().unwrap_or_default() as u32;
+            session_record_key(&element(0), device_id).into_bytes()
+        }
+        PRE_KEYS | SIGNED_PRE_KEYS => (key.as_f64().unwrap_or_default() as u32).to_string().into_bytes(),
+        // 二進位鍵以 ArrayBuffer 取回
+        REVOCATIONS => Uint8Array::new(key).to_vec(),
+        MESSAGES => element(2).into_bytes(),
+        KV => kv_record_key(&element(0), &element(1)).into_bytes(),
+        _ => key.as_string().unwrap_or_default().into_bytes(),
+    }
+}
+
+/// 以 `prefix` 開頭的所有陣列鍵 (`upper` 為下一個元素的上界，不含)
+///
+/// 陣列鍵依元素逐一比較，較短的前綴排在前面，而陣列型別大於數字與字串
+fn prefix_range(prefix: &JsValue, upper: Option<&JsValue>) -> Result<IdbKeyRange, String> {
+    let lower = Array::of1(prefix);
+    let upper = Array::of2(prefix, upper.unwrap_or(&Array::new().into()));
+    IdbKeyRange::bound_with_lower_open_and_upper_open(&lower, &upper, false, true)
+        .map_err(|e| js_error("Invalid key range", e))
+}
+
+/// IndexedDB 協定 / 訊息儲存
+///
+/// 複製出的實例共用同一個資料庫連線
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct IndexedDbStore {
+    db: IdbDatabase,
+    /// 記錄編碼器 (複製出的實例共用，主金鑰輪替時一併更新)
+    codec: Rc<RefCell<RecordCodec>>,
+    /// 變更訂閱者 (複製出的實例共用)
+    observers: StoreObservers,
+    /// 多分頁協調 (複製出的實例共用，見 `tabs` 模組)
+    tabs: Rc<RefCell<Option<TabCoordinator>>>,
+}
+
+impl IndexedDbStore {
+    /// 開啟 (不存在時建立) 資料庫，設定 `key` 時以主儲存金鑰加密所有記錄 (Rust 端使用)
+    pub async fn open_database(name: &str, key: Option<StorageKey>) -> Result<Self, String> {
+        let store = Self {
+            db: Self::open_connection(name).await?,
+            codec: Rc::new(RefCell::new(RecordCodec::new(key.clone()))),
+            observers: StoreObservers::default(),
+            tabs: Rc::default(),
+        };
+        if key.is_none() && store.storage_lock().await?.is_some() {
+            store.db.close();
+            return Err("Storage is locked; unlock it with the storage password".to_string());
+        }
+        if let Err(e) = store.load_index_key().await {
+            store.db.close();
+            return Err(e);
+        }
+        Ok(store)
+    }
+
+    /// 以密碼解鎖資料庫；尚未設定密碼鎖的空資料庫會以 `kdf` 參數建立密碼鎖 (Rust 端使用)
+    ///
+    /// 之後沿用資料庫中記錄的參數與 salt，密碼錯誤時回傳錯誤
+    pub async fn unlock_database(name: &str, password: &[u8], kdf: &PasswordKdfParams) -> Result<Self, String> {
+        let store = Self {
+            db: Self::open_connection(name).await?,
+            codec: Rc::default(),
+            observers: StoreObservers::default(),
+            tabs: Rc::default(),
+        };
+        let unlocked = match store.storage_lock().await {
+            Ok(Some(lock)) => lock.unlock_key(password),
+            Ok(None) => store.create_storage_lock(password, kdf).await,
+            Err(e) => Err(e),
+        };
+        let loaded = match unlocked {
+            Ok(key) => {
+                *store.codec.borrow_mut() = RecordCodec::new(Some(key));
+                store.load_index_key().await
+            }
+            Err(e) => Err(e),
+        };
+        match loaded {
+            Ok(()) => Ok(store),
+            Err(e) => {
+                store.db.close();
+                Err(e)
+            }
+        }
+    }
+
+    fn codec(&self) -> RecordCodec {
+        self.codec.borrow().clone()
+    }
+
+    /// 套用主金鑰輪替後保存的盲化索引金鑰
+    async fn load_index_key(&self) -> Result<(), String> {
+        if self.codec().is_encrypted() {
+            if let Some(record) = self.raw_local(INDEX_KEY_KEY).await? {
+                self.codec.borrow_mut().apply_index_key_record(LOCAL, INDEX_KEY_KEY, &record)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 啟用多分頁協調且本分頁未持有寫入鎖時回傳錯誤
+    fn ensure_session_writer(&self) -> Result<(), String> {
+        match self.tabs.borrow().as_ref() {
+            Some(tabs) => tabs.ensure_writer(),
+            None => Ok(()),
+        }
+    }
+
+    async fn open_connection(name: &str) -> Result<IdbDatabase, String> {
+        let request = indexed_db()?
+            .open_with_u32(name, INDEXEDDB_SCHEMA_VERSION)
+            .map_err(|e| js_error("Failed to open IndexedDB", e))?;
+
+        let upgrade_request = request.clone();
+        let on_upgrade = Closure::<dyn FnMut(IdbVersionChangeEvent)>::new(move |event: IdbVersionChangeEvent| {
+            let upgraded = upgrade_request
+                .result()
+                .and_then(|db| Self::upgrade(&db.unchecked_into(), event.old_version()));
+            // 升級失敗時中止 versionchange 交易，開啟請求會以錯誤結束
+            if upgraded.is_err() {
+                if let Some(transaction) = upgrade_request.transaction() {
+                    let _ = transaction.abort();
+                }
+            }
+        });
+        request.set_onupgradeneeded(Some(on_upgrade.as_ref().unchecked_ref()));
+
+        let db = request_result(request.into()).await?;
+        Ok(db.unchecked_into())
+    }
+
+    /// `local` 中未經解碼的值
+    async fn raw_local(&self, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let (transaction, _) = self.transaction(&[LOCAL], IdbTransactionMode::Readonly)?;
+        let request = Self::object_store(&transaction, LOCAL)?
+            .get(&JsValue::from_str(key))
+            .map_err(|e| js_error("IndexedDB get failed", e))?;
+        record_bytes(request_result(request).await?)
+    }
+
+    async fn storage_lock(&self) -> Result<Option<StorageLock>, String> {
+        self.raw_local(STORAGE_LOCK_KEY)
+            .await?
+            .map(|bytes| RecordCodec::default().decode(LOCAL, STORAGE_LOCK_KEY, &bytes))
+            .transpose()
+    }
+
+    async fn key_rotation(&self) -> Result<Option<KeyRotation>, String> {
+        self.raw_local(KEY_ROTATION_KEY)
+            .await?
+            .map(|bytes| RecordCodec::default().decode(LOCAL, KEY_ROTATION_KEY, &bytes))
+            .transpose()
+    }
+
+    /// 在空資料庫中建立密碼鎖，回傳衍生出的主儲存金鑰
+    async fn create_storage_lock(&self, password: &[u8], kdf: &PasswordKdfParams) -> Result<StorageKey, String> {
+        let (transaction, complete) = self.transaction(&OBJECT_STORES, IdbTransactionMode::Readwrite)?;
+        let counts = OBJECT_STORES
+            .iter()
+            .map(|name| {
+                Self::object_store(&transaction, name)?
+                    .count()
+                    .map_err(|e| js_error("IndexedDB count failed", e))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        for count in counts {
+            if request_result(count).await?.as_f64().unwrap_or(0.0) > 0.0 {
+                let _ = transaction.abort();
+                return Err("Storage already contains data that is not password protected".to_string());
+            }
+        }
+        let (lock, key) = StorageLock::create_with_params(password, kdf)?;
+        Self::object_store(&transaction, LOCAL)?
+            .add_with_key(
+                &encode(&RecordCodec::default(), LOCAL, STORAGE_LOCK_KEY, &lock)?,
+                &JsValue::from_str(STORAGE_LOCK_KEY),
+            )
+            .map_err(|e| js_error("IndexedDB add failed", e))?;
+        Self::commit(complete).await?;
+        Ok(key)
+    }
+
+    /// 從舊版本升級 schema
+    fn upgrade(db: &IdbDatabase, old_version: f64) -> Result<(), JsValue> {
+        for (name, version) in OBJECT_STORES.iter().zip(OBJECT_STORE_VERSIONS) {
+            if old_version < version as f64 {
+                db.create_object_store(name)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 開始交易，並立即掛上完成事件 (讀後寫時中途 await 也不會錯過)
+    fn transaction(&self, stores: &[&str], mode: IdbTransactionMode) -> Result<(IdbTransaction, PendingEvent), String> {
+        let names: Array = stores.iter().map(|name| JsValue::from_str(name)).collect();
+        let transaction = self
+            .db
+            .transaction_with_str_sequence_and_mode(&names, mode)
+            .map_err(|e| js_error("Failed to start transaction", e))?;
+        let complete = PendingEvent::transaction(&transaction);
+        Ok((transaction, complete))
+    }
+
+    async fn commit(complete: PendingEvent) -> Result<(), String> {
+        if complete.wait().await {
+            Ok(())
+        } else {
+            Err("IndexedDB transaction aborted".to_string())
+        }
+    }
+
+    fn object_store(transaction: &IdbTransaction, name: &str) -> Result<IdbObjectStore, String> {
+        transaction
+            .object_store(name)
+            .map_err(|e| js_error("Unknown object store", e))
+    }
+
+    /// 讀取記錄，`record_key` 為加密時綁定的記錄鍵
+    async fn get_record<T: DeserializeOwned>(
+        &self,
+        store: &str,
+        key: &JsValue,
+        record_key: impl AsRef<[u8]>,
+    ) -> Result<Option<T>, String> {
+        let (transaction, _) = self.transaction(&[store], IdbTransactionMode::Readonly)?;
+        let request = Self::object_store(&transaction, store)?
+            .get(key)
+            .map_err(|e| js_error("IndexedDB get failed", e))?;
+        decode(&self.codec(), store, record_key, request_result(request).await?)
+    }
+
+    async fn put_record<T: Serialize>(
+        &self,
+        store: &str,
+        key: &JsValue,
+        record_key: impl AsRef<[u8]>,
+        value: &T,
+    ) -> Result<(), String> {
+        let (transaction, complete) = self.transaction(&[store], IdbTransactionMode::Readwrite)?;
+        Self::object_store(&transaction, store)?
+            .put_with_key(&encode(&self.codec(), store, record_key, value)?, key)
+            .map_err(|e| js_error("IndexedDB put failed", e))?;
+        Self::commit(complete).await
+    }
+
+    /// 刪除記錄，回傳是否存在
+    async fn delete_record(&self, store: &str, key: &JsValue) -> Result<bool, String> {
+        let (transaction, complete) = self.transaction(&[store], IdbTransactionMode::Readwrite)?;
+        let object_store = Self::object_store(&transaction, store)?;
+        let count = object_store
+            .count_with_key(key)
+            .map_err(|e| js_error("IndexedDB count failed", e))?;
+        object_store
+            .delete(key)
+            .map_err(|e| js_error("IndexedDB delete failed", e))?;
+        let existed = request_result(count).await?.as_f64().unwrap_or(0.0) > 0.0;
+        Self::commit(complete).await?;
+        Ok(existed)
+    }
+
+    /// 讀取對話金鑰 (`conversation_keys` 須在交易中)
+    async fn conversation_key(
+        &self,
+        conversation_keys: &IdbObjectStore,
+        conversation_id: &str,
+    ) -> Result<Option<StorageKey>, String> {
+        let request = conversation_keys
+            .get(&JsValue::from_str(conversation_id))
+            .map_err(|e| js_error("IndexedDB get failed", e))?;
+        record_bytes(request_result(request).await?)?
+            .map(|record| self.codec().open_conversation_key(conversation_id, &record))
+            .transpose()
+    }
+
+    /// 輪替對話金鑰 (`conversation_id` 為盲化後的值)：其餘訊息的包裝金鑰改以新金鑰包裝，
+    /// 對話中已沒有訊息時直接刪除對話金鑰 (`messages`、`message_keys`、`conversation_keys` 須在交易中)
+    async fn rotate_conversation_key(&self, transaction: &IdbTransaction, conversation_id: &str) -> Result<(), String> {
+        let conversation_keys = Self::object_store(transaction, CONVERSATION_KEYS)?;
+        let Some(current) = self.conversation_key(&conversation_keys, conversation_id).await? else {
+            return Ok(());
+        };
+        let conversation = JsValue::from_str(conversation_id);
+        let range = prefix_range(&conversation, None)?;
+        let request = Self::object_store(transaction, MESSAGES)?
+            .get_all_keys_with_key(&range)
+            .map_err(|e| js_error("IndexedDB getAllKeys failed", e))?;
+        let keys: Array = request_result(request).await?.unchecked_into();
+        if keys.length() == 0 {
+            conversation_keys
+                .delete(&conversation)
+                .map_err(|e| js_error("IndexedDB delete failed", e))?;
+            return Ok(());
+        }
+        let (next, record) = self.codec().new_conversation_key(conversation_id)?;
+        let message_keys = Self::object_store(transaction, MESSAGE_KEYS)?;
+        for key in keys.iter() {
+            let message_id = key.unchecked_ref::<Array>().get(2).as_string().unwrap_or_default();
+            let id = JsValue::from_str(&message_id);
+            let request = message_keys.get(&id).map_err(|e| js_error("IndexedDB get failed", e))?;
+            // 舊版記錄沒有包裝金鑰
+            let Some(wrapping_key) = record_bytes(request_result(request).await?)? else {
+                continue;
+            };
+            let wrapping_key = self.codec().rewrap(Some(&current), &next, &message_id, &wrapping_key)?;
+            message_keys
+                .put_with_key(&Uint8Array::from(wrapping_key.as_slice()), &id)
+                .map_err(|e| js_error("IndexedDB put failed", e))?;
+        }
+        conversation_keys
+            .put_with_key(&Uint8Array::from(record.as_slice()), &conversation)
+            .map_err(|e| js_error("IndexedDB put failed", e))?;
+        Ok(())
+    }
+
+    /// 以 `message_keys` 中的包裝金鑰與對話金鑰解開排序鍵為 `key` 的訊息記錄
+    /// (`message_keys`、`conversation_keys` 須在交易中)
+    async fn decode_message(
+        &self,
+        transaction: &IdbTransaction,
+        key: &JsValue,
+        value: JsValue,
+    ) -> Result<Option<StoredMessage>, String> {
+        let Some(bytes) = record_bytes(value)? else {
+            return Ok(None);
+        };
+        let key: &Array = key.unchecked_ref();
+        let conversation_id = key.get(0).as_string().unwrap_or_default();
+        let message_id = key.get(2).as_string().unwrap_or_default();
+        let request = Self::object_store(transaction, MESSAGE_KEYS)?
+            .get(&JsValue::from_str(&message_id))
+            .map_err(|e| js_error("IndexedDB get failed", e))?;
+        let wrapping_key = record_bytes(request_result(request).await?)?;
+        let conversation_key = self
+            .conversation_key(&Self::object_store(transaction, CONVERSATION_KEYS)?, &conversation_id)
+            .await?;
+        self.codec()
+            .open_wrapped(conversation_key.as_ref(), MESSAGES, &message_id, &bytes, wrapping_key.as_deref())
+            .map(Some)
+    }
+
+    async fn local_identity(&self) -> Result<LocalIdentity, String> {
+        self.get_record(LOCAL, &JsValue::from_str(LOCAL_IDENTITY_KEY), LOCAL_IDENTITY_KEY)
+            .await?
+            .ok_or_else(|| "Local identity has not been set".to_string())
+    }
+
+    /// 設定本機身份 (Rust 端使用)
+    pub async fn set_local_identity(&self, identity: &IdentityKeyPair, registration_id: u32) -> Result<(), String> {
+        let local = LocalIdentity { identity: identity.clone(), registration_id };
+        self.put_record(LOCAL, &JsValue::from_str(LOCAL_IDENTITY_KEY), LOCAL_IDENTITY_KEY, &local)
+            .await
+    }
+
+    /// 產生一次性預金鑰，回傳公開部分 (Rust 端使用，最多 `MAX_BATCH_SIZE` 把)
+    pub async fn generate_one_time_pre_keys(&self, count: u32) -> Result<Vec<OneTimePreKey>, String> {
+        if count > MAX_BATCH_SIZE {
+            return Err(format!("Cannot generate more than {} prekeys at once", MAX_BATCH_SIZE));
+        }
+        let (transaction, complete) = self.transaction(&[LOCAL, PRE_KEYS], IdbTransactionMode::Readwrite)?;
+        let local = Self::object_store(&transaction, LOCAL)?;
+        let next_id_key = JsValue::from_str(NEXT_PRE_KEY_ID_KEY);
+        let request = local.get(&next_id_key).map_err(|e| js_error("IndexedDB get failed", e))?;
+        let mut next_id: u32 =
+            decode(&self.codec(), LOCAL, NEXT_PRE_KEY_ID_KEY, request_result(request).await?)?.unwrap_or(1);
+
+        let pre_keys = Self::object_store(&transaction, PRE_KEYS)?;
+        let mut batch = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let key_id = next_id;
+            next_id = next_id
+                .checked_add(1)
+                .ok_or_else(|| "One-time prekey ID space exhausted".to_string())?;
+            let keypair = X25519KeyPair::new();
+            pre_keys
+                .put_with_key(&encode(&self.codec(), PRE_KEYS, key_id.to_string(), &keypair)?, &JsValue::from(key_id))
+                .map_err(|e| js_error("IndexedDB put failed", e))?;
+            batch.push(OneTimePreKey { key_id, public_key: keypair.public_key_bytes() });
+        }
+        local
+            .put_with_key(&encode(&self.codec(), LOCAL, NEXT_PRE_KEY_ID_KEY, &next_id)?, &next_id_key)
+            .map_err(|e| js_error("IndexedDB put failed", e))?;
+        Self::commit(complete).await?;
+        Ok(batch)
+    }
+
+    /// 索引 (或重新索引) 訊息文字，詞元以盲化後的形式存放 (Rust 端使用)
+    pub async fn index_message_text(&self, id: &str, text: &str) -> Result<(), String> {
+        let (transaction, complete) =
+            self.transaction(&[SEARCH_TOKENS, SEARCH_MESSAGES], IdbTransactionMode::Readwrite)?;
+        let tokens_store = Self::object_store(&transaction, SEARCH_TOKENS)?;
+        let messages_store = Self::object_store(&transaction, SEARCH_MESSAGES)?;
+        let message_id = JsValue::from_str(&self.codec().blind("message", id));
+
+        let previous = messages_store.get(&message_id).map_err(|e| js_error("IndexedDB get failed", e))?;
+        let previous = request_result(previous).await?;
+        if !previous.is_undefined() {
+            for token in previous.unchecked_into::<Array>().iter() {
+                tokens_store
+                    .delete(&Array::of2(&token, &message_id))
+                    .map_err(|e| js_error("IndexedDB delete failed", e))?;
+            }
+        }
+        let tokens: Array = search_tokens(text)
+            .iter()
+            .map(|token| JsValue::from_str(&self.codec().blind(SEARCH_TOKEN_FIELD, token)))
+            .collect();
+        for token in tokens.iter() {
+            tokens_store
+                .put_with_key(&JsValue::TRUE, &Array::of2(&token, &message_id))
+                .map_err(|e| js_error("IndexedDB put failed", e))?;
+        }
+        messages_store
+            .put_with_key(&tokens, &message_id)
+            .map_err(|e| js_error("IndexedDB put failed", e))?;
+        Self::commit(complete).await
+    }
+
+    /// 搜尋包含查詢中所有詞元的已儲存訊息，回傳依時間排序的訊息 ID (Rust 端使用)
+    pub async fn search_messages(&self, query: &str) -> Result<Vec<String>, String> {
+        let tokens = search_tokens(query);
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (transaction, _) =
+            self.transaction(
+                &[SEARCH_TOKENS, MESSAGES, MESSAGE_IDS, MESSAGE_KEYS, CONVERSATION_KEYS],
+                IdbTransactionMode::Readonly,
+            )?;
+        let tokens_store = Self::object_store(&transaction, SEARCH_TOKENS)?;
+        let mut matches: Option<Vec<String>> = None;
+        for token in tokens {
+            let range = prefix_range(&JsValue::from_str(&self.codec().blind(SEARCH_TOKEN_FIELD, &token)), None)?;
+            let request = tokens_store
+                .get_all_keys_with_key(&range)
+                .map_err(|e| js_error("IndexedDB getAllKeys failed", e))?;
+            let keys: Array = request_result(request).await?.unchecked_into();
+            let ids: Vec<String> = keys
+                .iter()
+                .filter_map(|key| key.unchecked_into::<Array>().get(1).as_string())
+                .collect();
+            matches = Some(match matches {
+                None => ids,
+                Some(previous) => previous.into_iter().filter(|id| ids.contains(id)).collect(),
+            });
+        }
+
+        // 只回傳仍存在的訊息，並以訊息內容中的原始 ID 回傳
+        let message_ids = Self::object_store(&transaction, MESSAGE_IDS)?;
+        let messages = Self::object_store(&transaction, MESSAGES)?;
+        let mut found = Vec::new();
+        for message_id in matches.unwrap_or_default() {
+            let request = message_ids
+                .get(&JsValue::from_str(&message_id))
+                .map_err(|e| js_error("IndexedDB get failed", e))?;
+            let key = request_result(request).await?;
+            if key.is_undefined() {
+                continue;
+            }
+            let request = messages.get(&key).map_err(|e| js_error("IndexedDB get failed", e))?;
+            let message = self.decode_message(&transaction, &key, request_result(request).await?).await?;
+            found.extend(message.map(|message| (message.timestamp(), message.id())));
+        }
+        found.sort();
+        Ok(found.into_iter().map(|(_, id)| id).collect())
+    }
+
+    /// 驗證並記錄撤銷憑證，回傳記錄前的狀態 (Rust 端使用)
+    pub async fn apply_revocation(
+        &self,
+        contact_id: &str,
+        certificate: &RevocationCertificate,
+    ) -> Result<IdentityStatus, String> {
+        certificate.verify_signature()?;
+        let status = self.check_identity(contact_id, &certificate.identity_key()).await?;
+        let revocation_key = self.codec().blind_bytes("identity_key", &certificate.identity_key());
+        let key: JsValue = Uint8Array::from(revocation_key.as_slice()).into();
+        self.put_record(REVOCATIONS, &key, &revocation_key, certificate).await?;
+        Ok(status)
+    }
+}
+
+impl IndexedDbStore {
+    /// 以新的主儲存金鑰重新加密所有記錄，回傳是否完成 (Rust 端使用，見 `rotateStorageKey`)
+    pub async fn rotate_key(
+        &self,
+        new_key: &StorageKey,
+        chunk_size: u32,
+        progress: impl FnMut(&KeyRotationProgress) -> bool,
+    ) -> Result<bool, String> {
+        if self.storage_lock().await?.is_some() {
+            return Err("Storage is password protected; change the storage password instead".to_string());
+        }
+        self.rotate(new_key, None, chunk_size, progress).await
+    }
+
+    /// 以新密碼衍生的主儲存金鑰重新加密所有記錄，回傳是否完成 (Rust 端使用，見 `changeStoragePassword`)
+    pub async fn change_password(
+        &self,
+        password: &[u8],
+        kdf: &PasswordKdfParams,
+        chunk_size: u32,
+        progress: impl FnMut(&KeyRotationProgress) -> bool,
+    ) -> Result<bool, String> {
+        if self.storage_lock().await?.is_none() {
+            return Err("Storage is not password protected".to_string());
+        }
+        let (lock, key) = match self.key_rotation().await?.as_ref().and_then(KeyRotation::lock) {
+            Some(lock) => (lock.clone(), lock.unlock_key(password)?),
+            None => StorageLock::create_with_params(password, kdf)?,
+        };
+        self.rotate(&key, Some(lock), chunk_size, progress).await
+    }
+
+    async fn put_local_raw(&self, key: &str, bytes: &[u8]) -> Result<(), String> {
+        let (transaction, complete) = self.transaction(&[LOCAL], IdbTransactionMode::Readwrite)?;
+        Self::object_store(&transaction, LOCAL)?
+            .put_with_key(&Uint8Array::from(bytes), &JsValue::from_str(key))
+            .map_err(|e| js_error("IndexedDB put failed", e))?;
+        Self::commit(complete).await
+    }
+
+    async fn rotate(
+        &self,
+        key: &StorageKey,
+        lock: Option<StorageLock>,
+        chunk_size: u32,
+        mut progress: impl FnMut(&KeyRotationProgress) -> bool,
+    ) -> Result<bool, String> {
+        // 其他分頁的連線仍以舊金鑰讀寫
+        self.ensure_session_writer()?;
+        if chunk_size == 0 {
+            return Err("Chunk size must be positive".to_string());
+        }
+        let mut rotation = match self.key_rotation().await? {
+            Some(rotation) if rotation.targets(key) => rotation,
+            Some(_) => return Err("A storage key rotation to a different key is in progress".to_string()),
+            None => KeyRotation::new(key, lock),
+        };
+        self.put_local_raw(KEY_ROTATION_KEY, &RecordCodec::default().encode(LOCAL, KEY_ROTATION_KEY, &rotation)?)
+            .await?;
+        let rotating = self.codec().rotating(key)?;
+        *self.codec.borrow_mut() = rotating;
+
+        let total = ROTATED_STORES.len() as u32;
+        for store in ROTATED_STORES.iter().skip(rotation.completed as usize) {
+            let completed = rotation.completed;
+            if !self
+                .reseal_store(store, chunk_size, |records| {
+                    progress(&KeyRotationProgress::new(store, completed, total, records))
+                })
+                .await?
+            {
+                return Ok(false);
+            }
+            rotation.completed += 1;
+            self.put_local_raw(KEY_ROTATION_KEY, &RecordCodec::default().encode(LOCAL, KEY_ROTATION_KEY, &rotation)?)
+                .await?;
+        }
+
+        // 保存沿用的盲化索引金鑰、換上新的密碼鎖並刪除進度記錄
+        let codec = self.codec().finish_rotation();
+        let index_key = codec.index_key_record(LOCAL, INDEX_KEY_KEY)?;
+        let (transaction, complete) = self.transaction(&[LOCAL], IdbTransactionMode::Readwrite)?;
+        let local = Self::object_store(&transaction, LOCAL)?;
+        local
+            .put_with_key(&Uint8Array::from(index_key.as_slice()), &JsValue::from_str(INDEX_KEY_KEY))
+            .map_err(|e| js_error("IndexedDB put failed", e))?;
+        if let Some(lock) = rotation.lock() {
+            local
+                .put_with_key(&encode(&RecordCodec::default(), LOCAL, STORAGE_LOCK_KEY, lock)?, &JsValue::from_str(STORAGE_LOCK_KEY))
+                .map_err(|e| js_error("IndexedDB put failed", e))?;
+        }
+        local
+            .delete(&JsValue::from_str(KEY_ROTATION_KEY))
+            .map_err(|e| js_error("IndexedDB delete failed", e))?;
+        Self::commit(complete).await?;
+        *self.codec.borrow_mut() = codec;
+        Ok(true)
+    }
+
+    /// 以鍵的順序逐批重新加密倉庫中的記錄，`progress` 收到本倉庫已重新加密的筆數，回傳 false 時暫停
+    ///
+    /// 每批的讀取與寫回在同一個交易中，輪替期間其他操作寫入的記錄已以新金鑰加密
+    async fn reseal_store(&self, store: &str, chunk_size: u32, mut progress: impl FnMut(u32) -> bool) -> Result<bool, String> {
+        let mut after: Option<JsValue> = None;
+        let mut records = 0;
+        loop {
+            let (transaction, complete) = self.transaction(&[store], IdbTransactionMode::Readwrite)?;
+            let object_store = Self::object_store(&transaction, store)?;
+            let range: JsValue = match &after {
+                Some(key) => IdbKeyRange::lower_bound_with_open(key, true)
+                    .map_err(|e| js_error("Invalid key range", e))?
+                    .into(),
+                None => JsValue::NULL,
+            };
+            let keys = object_store
+                .get_all_keys_with_key_and_limit(&range, chunk_size)
+                .map_err(|e| js_error("IndexedDB getAllKeys failed", e))?;
+            let values = object_store
+                .get_all_with_key_and_limit(&range, chunk_size)
+                .map_err(|e| js_error("IndexedDB getAll failed", e))?;
+            let keys: Array = request_result(keys).await?.unchecked_into();
+            let values: Array = request_result(values).await?.unchecked_into();
+            if keys.length() == 0 {
+                return Ok(true);
+            }
+            after = Some(keys.get(keys.length() - 1));
+
+            let codec = self.codec();
+            for (key, value) in keys.iter().zip(values.iter()) {
+                let record_key = bound_record_key(store, &key);
+                if store == LOCAL && [STORAGE_LOCK_KEY, KEY_ROTATION_KEY].iter().any(|plain| plain.as_bytes() == record_key) {
+                    continue;
+                }
+                let Some(bytes) = record_bytes(value)? else {
+                    continue;
+                };
+                let resealed = match codec.reseal(store, &record_key, &bytes) {
+                    Ok(resealed) => resealed,
+                    Err(_) if matches!(store, MESSAGES | MESSAGE_KEYS) => None,
+                    Err(e) => {
+                        let _ = transaction.abort();
+                        return Err(format!("Failed to re-encrypt {} record: {}", store, e));
+                    }
+                };
+                if let Some(resealed) = resealed {
+                    object_store
+                        .put_with_key(&Uint8Array::from(resealed.as_slice()), &key)
+                        .map_err(|e| js_error("IndexedDB put failed", e))?;
+                    records += 1;
+                }
+            }
+            Self::commit(complete).await?;
+            if !progress(records) {
+                return Ok(false);
+            }
+        }
+    }
+}
+
+impl IndexedDbStore {
+    /// 檢查所有記錄的檢查碼，回報毀損的記錄 (Rust 端使用，見 `verifyStorage`)
+    ///
+    /// `repair` 時在同一個交易中刪除毀損的預金鑰與會話，並把會話標記為需要重新握手
+    pub async fn verify(&self, repair: bool) -> Result<StorageVerification, String> {
+        let mut verification = StorageVerification::default();
+        // (毀損記錄的索引, 物件倉庫, 鍵)
+        let mut repairs = Vec::new();
+        let codec = self.codec();
+        for store in ROTATED_STORES {
+            // 包裝金鑰與訊息一起檢查
+            if matches!(store, MESSAGES | MESSAGE_KEYS) {
+                continue;
+            }
+            let (transaction, _) = self.transaction(&[store], IdbTransactionMode::Readonly)?;
+            let (keys, values) = Self::all_records(&transaction, store).await?;
+            for (key, value) in keys.iter().zip(values.iter()) {
+                let record_key = bound_record_key(store, &key);
+                let plain = store == LOCAL && [STORAGE_LOCK_KEY, KEY_ROTATION_KEY].iter().any(|plain| plain.as_bytes() == record_key);
+                let result = record_bytes(value).and_then(|bytes| match (plain, bytes) {
+                    (_, None) => Ok(()),
+                    (true, Some(bytes)) => RecordCodec::default().verify(store, &record_key, &bytes),
+                    (false, Some(bytes)) => codec.verify(store, &record_key, &bytes),
+                });
+                if verification.record(store, &record_key, result) && RepairAction::for_store(store) != RepairAction::ReportOnly {
+                    repairs.push((verification.corrupted_mut().len() - 1, store, key));
+                }
+            }
+        }
+
+        let (transaction, _) = self.transaction(&[MESSAGES, MESSAGE_KEYS, CONVERSATION_KEYS], IdbTransactionMode::Readonly)?;
+        let (keys, values) = Self::all_records(&transaction, MESSAGES).await?;
+        for (key, value) in keys.iter().zip(values.iter()) {
+            let result = self.verify_message(&transaction, &key, value).await;
+            verification.record(MESSAGES, &bound_record_key(MESSAGES, &key), result);
+        }
+
+        if repair && !repairs.is_empty() {
+            self.ensure_session_writer()?;
+            let mut resets = self.session_resets().await?;
+            let (transaction, complete) =
+                self.transaction(&[LOCAL, SESSIONS, PRE_KEYS, SIGNED_PRE_KEYS], IdbTransactionMode::Readwrite)?;
+            for (index, store, key) in repairs {
+                Self::object_store(&transaction, store)?
+                    .delete(&key)
+                    .map_err(|e| js_error("IndexedDB delete failed", e))?;
+                let record = &mut verification.corrupted_mut()[index];
+                if record.action() == RepairAction::Rehandshake {
+                    resets.insert(String::from_utf8_lossy(&record.record_key()).into_owned());
+                }
+                record.mark_repaired();
+            }
+            Self::object_store(&transaction, LOCAL)?
+                .put_with_key(&encode(&codec, LOCAL, SESSION_RESETS_KEY, &resets)?, &JsValue::from_str(SESSION_RESETS_KEY))
+                .map_err(|e| js_error("IndexedDB put failed", e))?;
+            Self::commit(complete).await?;
+        }
+        Ok(verification)
+    }
+
+    /// 物件倉庫中所有的鍵與值 (`store` 須在交易中)
+    async fn all_records(transaction: &IdbTransaction, store: &str) -> Result<(Array, Array), String> {
+        let object_store = Self::object_store(transaction, store)?;
+        let keys = object_store
+            .get_all_keys()
+            .map_err(|e| js_error("IndexedDB getAllKeys failed", e))?;
+        let values = object_store
+            .get_all()
+            .map_err(|e| js_error("IndexedDB getAll failed", e))?;
+        Ok((request_result(keys).await?.unchecked_into(), request_result(values).await?.unchecked_into()))
+    }
+
+    /// 檢查排序鍵為 `key` 的訊息記錄與其包裝金鑰 (`message_keys`、`conversation_keys` 須在交易中)
+    async fn verify_message(&self, transaction: &IdbTransaction, key: &JsValue, value: JsValue) -> Result<(), String> {
+        let Some(bytes) = record_bytes(value)? else {
+            return Ok(());
+        };
+        let key: &Array = key.unchecked_ref();
+        let conversation_id = key.get(0).as_string().unwrap_or_default();
+        let message_id = key.get(2).as_string().unwrap_or_default();
+        let request = Self::object_store(transaction, MESSAGE_KEYS)?
+            .get(&JsValue::from_str(&message_id))
+            .map_err(|e| js_error("IndexedDB get failed", e))?;
+        let wrapping_key = record_bytes(request_result(request).await?)?;
+        let conversation_key = self
+            .conversation_key(&Self::object_store(transaction, CONVERSATION_KEYS)?, &conversation_id)
+            .await?;
+        self.codec()
+            .verify_wrapped(conversation_key.as_ref(), MESSAGES, &message_id, &bytes, wrapping_key.as_deref())
+    }
+
+    async fn session_resets(&self) -> Result<BTreeSet<String>, String> {
+        Ok(self
+            .get_record(LOCAL, &JsValue::from_str(SESSION_RESETS_KEY), SESSION_RESETS_KEY)
+            .await?
+            .unwrap_or_default())
+    }
+
+    /// 會話是否因毀損被 `verifyStorage` 刪除，需要重新握手 (Rust 端使用)
+    pub async fn needs_rehandshake(&self, contact_id: &str, device_id: u32) -> Result<bool, String> {
+        let record_key = session_record_key(&self.codec().blind("contact", contact_id), device_id);
+        Ok(self.session_resets().await?.contains(&record_key))
+    }
+
+    /// 重新建立會話後清除標記，回傳是否有標記 (Rust 端使用)
+    pub async fn clear_rehandshake(&self, contact_id: &str, device_id: u32) -> Result<bool, String> {
+        let mut resets = self.session_resets().await?;
+        if !resets.remove(&session_record_key(&self.codec().blind("contact", contact_id), device_id)) {
+            return Ok(false);
+        }
+        self.put_record(LOCAL, &JsValue::from_str(SESSION_RESETS_KEY), SESSION_RESETS_KEY, &resets).await?;
+        Ok(true)
+    }
+}
+
+impl AsyncIdentityKeyStore for IndexedDbStore {
+    async fn identity_key_pair(&self) -> Result<IdentityKeyPair, String> {
+        Ok(self.local_identity().await?.identity)
+    }
+
+    async fn local_registration_id(&self) -> Result<u32, String> {
+        Ok(self.local_identity().await?.registration_id)
+    }
+
+    async fn check_identity(&self, contact_id: &str, identity_key: &[u8]) -> Result<IdentityStatus, String> {
+        let revocation_key = self.codec().blind_bytes("identity_key", identity_key);
+        let revoked = self
+            .get_record::<RevocationCertificate>(REVOCATIONS, &Uint8Array::from(revocation_key.as_slice()), &revocation_key)
+            .await?;
+        if revoked.is_some() {
+            return Ok(IdentityStatus::Revoked);
+        }
+        let contact_key = self.codec().blind("contact", contact_id);
+        let record: Option<TrustedIdentity> =
+            self.get_record(IDENTITIES, &JsValue::from_str(&contact_key), &contact_key).await?;
+        Ok(TrustedIdentity::compare(record.as_ref(), identity_key))
+    }
+
+    async fn save_identity(&mut self, contact_id: &str, identity_key: &[u8], now: u64) -> Result<IdentityStatus, String> {
+        let (transaction, complete) = self.transaction(&[IDENTITIES, REVOCATIONS], IdbTransactionMode::Readwrite)?;
+        let identities = Self::object_store(&transaction, IDENTITIES)?;
+        let contact_key = self.codec().blind("contact", contact_id);
+        let contact_js_key = JsValue::from_str(&contact_key);
+        let revoked = Self::object_store(&transaction, REVOCATIONS)?
+            .count_with_key(&Uint8Array::from(self.codec().blind_bytes("identity_key", identity_key).as_slice()))
+            .map_err(|e| js_error("IndexedDB count failed", e))?;
+        let existing = identities.get(&contact_js_key).map_err(|e| js_error("IndexedDB get failed", e))?;
+
+        let revoked = request_result(revoked).await?.as_f64().unwrap_or(0.0) > 0.0;
+        let record: Option<TrustedIdentity> =
+            decode(&self.codec(), IDENTITIES, &contact_key, request_result(existing).await?)?;
+        let status = if revoked {
+            IdentityStatus::Revoked
+        } else {
+            TrustedIdentity::compare(record.as_ref(), identity_key)
+        };
+        if matches!(status, IdentityStatus::NewIdentity | IdentityStatus::Changed) {
+            let record = TrustedIdentity::accept(record, identity_key, now);
+            identities
+                .put_with_key(&encode(&self.codec(), IDENTITIES, &contact_key, &record)?, &contact_js_key)
+                .map_err(|e| js_error("IndexedDB put failed", e))?;
+        }
+        Self::commit(complete).await?;
+        if matches!(status, IdentityStatus::NewIdentity | IdentityStatus::Changed) {
+            self.observers.emit(StoreEvent::identity_changed(contact_id, status));
+        }
+        Ok(status)
+    }
+}
+
+impl AsyncSessionStore for IndexedDbStore {
+    async fn load_session(&self, contact_id: &str, device_id: u32) -> Result<Option<RatchetSession>, String> {
+        let contact_key = self.codec().blind("contact", contact_id);
+        let record_key = session_record_key(&contact_key, device_id);
+        self.get_record(SESSIONS, &session_key(&contact_key, device_id), record_key).await
+    }
+
+    async fn store_session(&mut self, contact_id: &str, device_id: u32, session: &RatchetSession) -> Result<(), String> {
+        self.ensure_session_writer()?;
+        let contact_key = self.codec().blind("contact", contact_id);
+        let record_key = session_record_key(&contact_key, device_id);
+        self.put_record(SESSIONS, &session_key(&contact_key, device_id), record_key, session).await?;
+        self.observers
+            .emit(StoreEvent::session(StoreEventKind::SessionUpdated, contact_id, device_id));
+        Ok(())
+    }
+
+    async fn remove_session(&mut self, contact_id: &str, device_id: u32) -> Result<bool, String> {
+        self.ensure_session_writer()?;
+        let contact_key = self.codec().blind("contact", contact_id);
+        let existed = self.delete_record(SESSIONS, &session_key(&contact_key, device_id)).await?;
+        if existed {
+            self.observers
+                .emit(StoreEvent::session(StoreEventKind::SessionRemoved, contact_id, device_id));
+        }
+        Ok(existed)
+    }
+
+    async fn session_devices(&self, contact_id: &str) -> Result<Vec<u32>, String> {
+        let (transaction, _) = self.transaction(&[SESSIONS], IdbTransactionMode::Readonly)?;
+        let range = prefix_range(&JsValue::from_str(&self.codec().blind("contact", contact_id)), None)?;
+        let request = Self::object_store(&transaction, SESSIONS)?
+            .get_all_keys_with_key(&range)
+            .map_err(|e| js_error("IndexedDB getAllKeys failed", e))?;
+        let keys: Array = request_result(request).await?.unchecked_into();
+        Ok(keys
+            .iter()
+            .filter_map(|key| key.unchecked_into::<Array>().get(1).as_f64())
+            .map(|device_id| device_id as u32)
+            .collect())
+    }
+}
+
+impl AsyncPreKeyStore for IndexedDbStore {
+    async fn contains_pre_key(&self, key_id: u32) -> Result<bool, String> {
+        let (transaction, _) = self.transaction(&[PRE_KEYS], IdbTransactionMode::Readonly)?;
+        let request = Self::object_store(&transaction, PRE_KEYS)?
+            .count_with_key(&JsValue::from(key_id))
+            .map_err(|e| js_error("IndexedDB count failed", e))?;
+        Ok(request_result(request).await?.as_f64().unwrap_or(0.0) > 0.0)
+    }
+
+    async fn load_pre_key(&self, key_id: u32) -> Result<Vec<u8>, String> {
+        let (transaction, _) = self.transaction(&[PRE_KEYS], IdbTransactionMode::Readonly)?;
+        let request = Self::object_store(&transaction, PRE_KEYS)?
+            .get(&JsValue::from(key_id))
+            .map_err(|e| js_error("IndexedDB get failed", e))?;
+        let keypair: Option<X25519KeyPair> =
+            decode(&self.codec(), PRE_KEYS, key_id.to_string(), request_result(request).await?)?;
+        keypair
+            .map(|keypair| keypair.private_key_bytes().expose().to_vec())
+            .ok_or_else(|| format!("Unknown or already consumed one-time prekey: {}", key_id))
+    }
+
+    async fn consume_pre_key(&mut self, key_id: u32) -> Result<Vec<u8>, String> {
+        self.ensure_session_writer()?;
+        let (transaction, complete) = self.transaction(&[PRE_KEYS], IdbTransactionMode::Readwrite)?;
+        let pre_keys = Self::object_store(&transaction, PRE_KEYS)?;
+        let key = JsValue::from(key_id);
+        let request = pre_keys.get(&key).map_err(|e| js_error("IndexedDB get failed", e))?;
+        pre_keys.delete(&key).map_err(|e| js_error("IndexedDB delete failed", e))?;
+        let keypair: Option<X25519KeyPair> =
+            decode(&self.codec(), PRE_KEYS, key_id.to_string(), request_result(request).await?)?;
+        Self::commit(complete).await?;
+        keypair
+            .map(|keypair| keypair.private_key_bytes().expose().to_vec())
+            .ok_or_else(|| format!("Unknown or already consumed one-time prekey: {}", key_id))
+    }
+}
+
+impl AsyncSignedPreKeyStore for IndexedDbStore {
+    async fn load_signed_pre_key(&self, key_id: u32) -> Result<SignedPreKeyRecord, String> {
+        self.get_record(SIGNED_PRE_KEYS, &JsValue::from(key_id), key_id.to_string())
+            .await?
+            .ok_or_else(|| format!("Unknown signed prekey: {}", key_id))
+    }
+
+    async fn store_signed_pre_key(&mut self, record: &SignedPreKeyRecord) -> Result<(), String> {
+        let key_id = record.key_id();
+        self.put_record(SIGNED_PRE_KEYS, &JsValue::from(key_id), key_id.to_string(), record).await
+    }
+
+    async fn remove_signed_pre_key(&mut self, key_id: u32) -> Result<bool, String> {
+        self.delete_record(SIGNED_PRE_KEYS, &JsValue::from(key_id)).await
+    }
+}
+
+impl AsyncMessageStore for IndexedDbStore {
+    async fn store_message(&mut self, message: &StoredMessage) -> Result<(), String> {
+        let (transaction, complete) =
+            self.transaction(&[MESSAGES, MESSAGE_IDS, MESSAGE_KEYS, CONVERSATION_KEYS], IdbTransactionMode::Readwrite)?;
+        let messages = Self::object_store(&transaction, MESSAGES)?;
+        let message_ids = Self::object_store(&transaction, MESSAGE_IDS)?;
+        let message_id = self.codec().blind("message", &message.id());
+        let id = JsValue::from_str(&message_id);
+
+        // 對話的第一則訊息建立對話金鑰
+        let conversation_keys = Self::object_store(&transaction, CONVERSATION_KEYS)?;
+        let conversation_id = self.codec().blind("conversation", &message.conversation_id());
+        let conversation_key = match self.conversation_key(&conversation_keys, &conversation_id).await? {
+            Some(key) => key,
+            None => {
+                let (key, record) = self.codec().new_conversation_key(&conversation_id)?;
+                conversation_keys
+                    .put_with_key(&Uint8Array::from(record.as_slice()), &JsValue::from_str(&conversation_id))
+                    .map_err(|e| js_error("IndexedDB put failed", e))?;
+                key
+            }
+        };
+        let (record, wrapping_key) = RecordCodec::seal_wrapped(&conversation_key, MESSAGES, &message_id, message)?;
+
+        // 覆寫時先移除舊的排序鍵 (時間戳可能不同)
+        let previous = message_ids.get(&id).map_err(|e| js_error("IndexedDB get failed", e))?;
+        let previous = request_result(previous).await?;
+        if !previous.is_undefined() {
+            messages.delete(&previous).map_err(|e| js_error("IndexedDB delete failed", e))?;
+        }
+        let key = message_key(&self.codec(), message);
+        messages
+            .put_with_key(&Uint8Array::from(record.as_slice()), &key)
+            .map_err(|e| js_error("IndexedDB put failed", e))?;
+        message_ids
+            .put_with_key(&key, &id)
+            .map_err(|e| js_error("IndexedDB put failed", e))?;
+        Self::object_store(&transaction, MESSAGE_KEYS)?
+            .put_with_key(&Uint8Array::from(wrapping_key.as_slice()), &id)
+            .map_err(|e| js_error("IndexedDB put failed", e))?;
+        Self::commit(complete).await?;
+        self.observers
+            .emit(StoreEvent::message(StoreEventKind::MessageStored, &message.id(), &message.conversation_id()));
+        Ok(())
+    }
+
+    async fn load_message(&self, id: &str) -> Result<Option<StoredMessage>, String> {
+        let (transaction, _) = self.transaction(
+            &[MESSAGES, MESSAGE_IDS, MESSAGE_KEYS, CONVERSATION_KEYS],
+            IdbTransactionMode::Readonly,
+        )?;
+        let message_id = self.codec().blind("message", id);
+        let request = Self::object_store(&transaction, MESSAGE_IDS)?
+            .get(&JsValue::from_str(&message_id))
+            .map_err(|e| js_error("IndexedDB get failed", e))?;
+        let key = request_result(request).await?;
+        if key.is_undefined() {
+            return Ok(None);
+        }
+        let request = Self::object_store(&transaction, MESSAGES)?
+            .get(&key)
+            .map_err(|e| js_error("IndexedDB get failed", e))?;
+        self.decode_message(&transaction, &key, request_result(request).await?).await
+    }
+
+    async fn remove_message(&mut self, id: &str) -> Result<bool, String> {
+        let (transaction, complete) = self.transaction(
+            &[MESSAGES, MESSAGE_IDS, MESSAGE_KEYS, CONVERSATION_KEYS, SEARCH_TOKENS, SEARCH_MESSAGES],
+            IdbTransactionMode::Readwrite,
+        )?;
+        let message_ids = Self::object_store(&transaction, MESSAGE_IDS)?;
+        let message_keys = Self::object_store(&transaction, MESSAGE_KEYS)?;
+        let message_id = self.codec().blind("message", id);
+        let blinded_id = JsValue::from_str(&message_id);
+        let request = message_ids.get(&blinded_id).map_err(|e| js_error("IndexedDB get failed", e))?;
+        let key = request_result(request).await?;
+        let existed = !key.is_undefined();
+
+        // 通知需要原始的對話 ID，刪除前先解開記錄 (無法解開的記錄照常刪除)
+        let conversation_id = if existed {
+            let request = Self::object_store(&transaction, MESSAGES)?
+                .get(&key)
+                .map_err(|e| js_error("IndexedDB get failed", e))?;
+            let record = request_result(request).await?;
+            self.decode_message(&transaction, &key, record).await.ok().flatten().map(|m| m.conversation_id())
+        } else {
+            None
+        };
+
+        // 一併移除搜尋索引
+        let search_messages = Self::object_store(&transaction, SEARCH_MESSAGES)?;
+        let request = search_messages.get(&blinded_id).map_err(|e| js_error("IndexedDB get failed", e))?;
+        let tokens = request_result(request).await?;
+        if !tokens.is_undefined() {
+            let search_tokens = Self::object_store(&transaction, SEARCH_TOKENS)?;
+            for token in tokens.unchecked_into::<Array>().iter() {
+                search_tokens
+                    .delete(&Array::of2(&token, &blinded_id))
+                    .map_err(|e| js_error("IndexedDB delete failed", e))?;
+            }
+            search_messages.delete(&blinded_id).map_err(|e| js_error("IndexedDB delete failed", e))?;
+        }
+        if existed {
+            Self::object_store(&transaction, MESSAGES)?
+                .delete(&key)
+                .map_err(|e| js_error("IndexedDB delete failed", e))?;
+            message_ids.delete(&blinded_id).map_err(|e| js_error("IndexedDB delete failed", e))?;
+        }
+        // 銷毀包裝金鑰並輪替對話金鑰，殘留的包裝金鑰無法以新的對話金鑰解開
+        message_keys
+            .delete(&blinded_id)
+            .map_err(|e| js_error("IndexedDB delete failed", e))?;
+        if existed {
+            let conversation_id = key.unchecked_ref::<Array>().get(0).as_string().unwrap_or_default();
+            self.rotate_conversation_key(&transaction, &conversation_id).await?;
+        }
+        Self::commit(complete).await?;
+        if existed {
+            let conversation_id = conversation_id.unwrap_or_default();
+            self.observers
+                .emit(StoreEvent::message(StoreEventKind::MessageRemoved, id, &conversation_id));
+        }
+        Ok(existed)
+    }
+
+    async fn conversation_messages(
+        &self,
+        conversation_id: &str,
+        before: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<StoredMessage>, String> {
+        let (transaction, _) =
+            self.transaction(&[MESSAGES, MESSAGE_KEYS, CONVERSATION_KEYS], IdbTransactionMode::Readonly)?;
+        let messages = Self::object_store(&transaction, MESSAGES)?;
+        let before = before.map(|before| JsValue::from(before as f64));
+        let conversation_key = JsValue::from_str(&self.codec().blind("conversation", conversation_id));
+        let range = prefix_range(&conversation_key, before.as_ref())?;
+
+        // 先取得所有鍵 (體積小) 找出最新 `limit` 則的起點，再只讀取這一段的內容
+        let request = messages
+            .get_all_keys_with_key(&range)
+            .map_err(|e| js_error("IndexedDB getAllKeys failed", e))?;
+        let keys: Array = request_result(request).await?.unchecked_into();
+        let start = limit.map_or(0, |limit| keys.length().saturating_sub(limit));
+        if start == keys.length() {
+            return Ok(Vec::new());
+        }
+        let upper = range.upper().map_err(|e| js_error("Invalid key range", e))?;
+        let range = IdbKeyRange::bound_with_lower_open_and_upper_open(&keys.get(start), &upper, false, true)
+            .map_err(|e| js_error("Invalid key range", e))?;
+        let request = messages
+            .get_all_with_key(&range)
+            .map_err(|e| js_error("IndexedDB getAll failed", e))?;
+        let values: Array = request_result(request).await?.unchecked_into();
+
+        // 鍵與內容依相同順序回傳，鍵中的對話 ID 與訊息 ID 用於取得金鑰與解密
+        let mut result = Vec::with_capacity(values.length() as usize);
+        for (key, value) in keys.slice(start, keys.length()).iter().zip(values.iter()) {
+            let message = self.decode_message(&transaction, &key, value).await?;
+            result.push(message.ok_or_else(|| "Corrupted IndexedDB record".to_string())?);
+        }
+        Ok(result)
+    }
+
+    async fn set_retention_policy(&mut self, conversation_id: &str, policy: Option<RetentionPolicy>) -> Result<(), String> {
+        let conversation_key = self.codec().blind("conversation", conversation_id);
+        let key = JsValue::from_str(&conversation_key);
+        match policy {
+            Some(policy) => {
+                self.put_record(RETENTION_POLICIES, &key, &conversation_key, &(conversation_id, policy))
+                    .await
+            }
+            None => self.delete_record(RETENTION_POLICIES, &key).await.map(|_| ()),
+        }
+    }
+
+    async fn retention_policies(&self) -> Result<Vec<(String, RetentionPolicy)>, String> {
+        let (transaction, _) = self.transaction(&[RETENTION_POLICIES], IdbTransactionMode::Readonly)?;
+        let policies = Self::object_store(&transaction, RETENTION_POLICIES)?;
+        let keys = policies
+            .get_all_keys()
+            .map_err(|e| js_error("IndexedDB getAllKeys failed", e))?;
+        let values = policies.get_all().map_err(|e| js_error("IndexedDB getAll failed", e))?;
+        let keys: Array = request_result(keys).await?.unchecked_into();
+        let values: Array = request_result(values).await?.unchecked_into();
+        keys.iter()
+            .zip(values.iter())
+            .map(|(key, value)| {
+                let conversation_key = key.as_string().unwrap_or_default();
+                decode(&self.codec(), RETENTION_POLICIES, &conversation_key, value)?
+                    .ok_or_else(|| "Corrupted IndexedDB record".to_string())
+            })
+            .collect()
+    }
+}
+
+impl IndexedDbStore {
+    /// `kv` 倉庫的鍵與加密時綁定的記錄鍵
+    fn kv_key(&self, namespace: &str, key: &str) -> (JsValue, String) {
+        let namespace_key = self.codec().blind(KV_NAMESPACE_FIELD, namespace);
+        let entry_key = self.codec().blind(KV_KEY_FIELD, key);
+        let record_key = kv_record_key(&namespace_key, &entry_key);
+        (Array::of2(&JsValue::from_str(&namespace_key), &JsValue::from_str(&entry_key)).into(), record_key)
+    }
+}
+
+impl AsyncKeyValueStore for IndexedDbStore {
+    async fn put(&mut self, namespace: &str, key: &str, value: &[u8]) -> Result<(), String> {
+        let (store_key, record_key) = self.kv_key(namespace, key);
+        self.put_record(KV, &store_key, record_key, &(key, value)).await
+    }
+
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let (store_key, record_key) = self.kv_key(namespace, key);
+        let record: Option<(String, Vec<u8>)> = self.get_record(KV, &store_key, record_key).await?;
+        Ok(record.map(|(_, value)| value))
+    }
+
+    async fn list(&self, namespace: &str) -> Result<Vec<String>, String> {
+        let namespace_key = self.codec().blind(KV_NAMESPACE_FIELD, namespace);
+        let (transaction, _) = self.transaction(&[KV], IdbTransactionMode::Readonly)?;
+        let entries = Self::object_store(&transaction, KV)?;
+        let range = prefix_range(&JsValue::from_str(&namespace_key), None)?;
+        let keys = entries
+            .get_all_keys_with_key(&range)
+            .map_err(|e| js_error("IndexedDB getAllKeys failed", e))?;
+        let values = entries
+            .get_all_with_key(&range)
+            .map_err(|e| js_error("IndexedDB getAll failed", e))?;
+        let keys: Array = request_result(keys).await?.unchecked_into();
+        let values: Array = request_result(values).await?.unchecked_into();
+
+        // 盲化後的 key 沒有意義的順序，解密後重新排序
+        let mut result = keys
+            .iter()
+            .zip(values.iter())
+            .map(|(key, value)| {
+                let entry_key = key.unchecked_into::<Array>().get(1).as_string().unwrap_or_default();
+                let record: Option<(String, Vec<u8>)> =
+                    decode(&self.codec(), KV, kv_record_key(&namespace_key, &entry_key), value)?;
+                record
+                    .map(|(key, _)| key)
+                    .ok_or_else(|| "Corrupted IndexedDB record".to_string())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        result.sort();
+        Ok(result)
+    }
+
+    async fn remove(&mut self, namespace: &str, key: &str) -> Result<bool, String> {
+        let (store_key, _) = self.kv_key(namespace, key);
+        self.delete_record(KV, &store_key).await
+    }
+}
+
+impl AsyncContactStore for IndexedDbStore {
+    async fn load_contact(&self, contact_id: &str) -> Result<Option<Contact>, String> {
+        let contact_key = self.codec().blind("contact", contact_id);
+        self.get_record(CONTACTS, &JsValue::from_str(&contact_key), &contact_key).await
+    }
+
+    async fn store_contact(&mut self, contact: &Contact) -> Result<(), String> {
+        let contact_key = self.codec().blind("contact", &contact.id());
+        self.put_record(CONTACTS, &JsValue::from_str(&contact_key), &contact_key, contact).await
+    }
+
+    async fn remove_contact(&mut self, contact_id: &str) -> Result<bool, String> {
+        let contact_key = self.codec().blind("contact", contact_id);
+        self.delete_record(CONTACTS, &JsValue::from_str(&contact_key)).await
+    }
+
+    async fn contacts(&self) -> Result<Vec<Contact>, String> {
+        let (transaction, _) = self.transaction(&[CONTACTS], IdbTransactionMode::Readonly)?;
+        let contacts = Self::object_store(&transaction, CONTACTS)?;
+        let keys = contacts
+            .get_all_keys()
+            .map_err(|e| js_error("IndexedDB getAllKeys failed", e))?;
+        let values = contacts.get_all().map_err(|e| js_error("IndexedDB getAll failed", e))?;
+        let keys: Array = request_result(keys).await?.unchecked_into();
+        let values: Array = request_result(values).await?.unchecked_into();
+
+        // 盲化後的聯絡人 ID 沒有意義的順序，解密後重新排序
+        let mut result = keys
+            .iter()
+            .zip(values.iter())
+            .map(|(key, value)| {
+                let contact_key = key.as_string().unwrap_or_default();
+                decode::<Contact>(&self.codec(), CONTACTS, &contact_key, value)?
+                    .ok_or_else(|| "Corrupted IndexedDB record".to_string())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        result.sort_by_key(Contact::id);
+        Ok(result)
+    }
+}
+
+impl AsyncGroupStore for IndexedDbStore {
+    async fn load_group(&self, group_id: &str) -> Result<Option<Group>, String> {
+        let group_key = self.codec().blind("group", group_id);
+        self.get_record(GROUPS, &JsValue::from_str(&group_key), &group_key).await
+    }
+
+    async fn store_group(&mut self, group: &Group) -> Result<(), String> {
+        let group_key = self.codec().blind("group", &group.id());
+        self.put_record(GROUPS, &JsValue::from_str(&group_key), &group_key, group).await
+    }
+
+    async fn remove_group(&mut self, group_id: &str) -> Result<bool, String> {
+        let group_key = self.codec().blind("group", group_id);
+        self.delete_record(GROUPS, &JsValue::from_str(&group_key)).await
+    }
+
+    async fn groups(&self) -> Result<Vec<Group>, String> {
+        let (transaction, _) = self.transaction(&[GROUPS], IdbTransactionMode::Readonly)?;
+        let groups = Self::object_store(&transaction, GROUPS)?;
+        let keys = groups
+            .get_all_keys()
+            .map_err(|e| js_error("IndexedDB getAllKeys failed", e))?;
+        let values = groups.get_all().map_err(|e| js_error("IndexedDB getAll failed", e))?;
+        let keys: Array = request_result(keys).await?.unchecked_into();
+        let values: Array = request_result(values).await?.unchecked_into();
+
+        let mut result = keys
+            .iter()
+            .zip(values.iter())
+            .map(|(key, value)| {
+                let group_key = key.as_string().unwrap_or_default();
+                decode::<Group>(&self.codec(), GROUPS, &group_key, value)?
+                    .ok_or_else(|| "Corrupted IndexedDB record".to_string())
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        result.sort_by_key(Group::id);
+        Ok(result)
+    }
+}
+
+impl AsyncBlobStore for IndexedDbStore {
+    async fn put_chunk(&mut self, blob_id: &str, index: u32, bytes: &[u8]) -> Result<(), String> {
+        let (transaction, complete) = self.transaction(&[BLOB_CHUNKS], IdbTransactionMode::Readwrite)?;
+        Self::object_store(&transaction, BLOB_CHUNKS)?
+            .put_with_key(&Uint8Array::from(bytes), &blob_chunk_key(&self.codec(), blob_id, index))
+            .map_err(|e| js_error("IndexedDB put failed", e))?;
+        Self::commit(complete).await
+    }
+
+    async fn get_chunk(&self, blob_id: &str, index: u32) -> Result<Option<Vec<u8>>, String> {
+        let (transaction, _) = self.transaction(&[BLOB_CHUNKS], IdbTransactionMode::Readonly)?;
+        let request = Self::object_store(&transaction, BLOB_CHUNKS)?
+            .get(&blob_chunk_key(&self.codec(), blob_id, index))
+            .map_err(|e| js_error("IndexedDB get failed", e))?;
+        let chunk = request_result(request).await?;
+        if chunk.is_undefined() {
+            return Ok(None);
+        }
+        let chunk = chunk
+            .dyn_into::<Uint8Array>()
+            .map_err(|_| "Corrupted IndexedDB blob chunk".to_string())?;
+        Ok(Some(chunk.to_vec()))
+    }
+
+    async fn remove_blob(&mut self, blob_id: &str) -> Result<bool, String> {
+        let (transaction, complete) = self.transaction(&[BLOB_CHUNKS], IdbTransactionMode::Readwrite)?;
+        let chunks = Self::object_store(&transaction, BLOB_CHUNKS)?;
+        let range = prefix_range(&JsValue::from_str(&self.codec().blind("blob", blob_id)), None)?;
+        let count = chunks
+            .count_with_key(&range)
+            .map_err(|e| js_error("IndexedDB count failed", e))?;
+        chunks.delete(&range).map_err(|e| js_error("IndexedDB delete failed", e))?;
+        let existed = request_result(count).await?.as_f64().unwrap_or(0.0) > 0.0;
+        Self::commit(complete).await?;
+        Ok(existed)
+    }
+}
+
+/// IndexedDB 附件串流寫入器 (由 `IndexedDbStore.blobWriter` 建立)
+///
+/// chunk 編號在呼叫 `write` 時就決定，多個 `write` 不必等待前一個完成
+#[wasm_bindgen]
+pub struct IndexedDbBlobWriter {
+    store: IndexedDbStore,
+    blob_id: String,
+    writer: Rc<RefCell<BlobWriter>>,
+}
+
+#[wasm_bindgen]
+impl IndexedDbBlobWriter {
+    /// 加密並寫入一段明文
+    pub fn write(&self, data: &[u8]) -> Promise {
+        let chunk = self.writer.borrow_mut().write(data);
+        let mut store = self.store.clone();
+        let blob_id = self.blob_id.clone();
+        future_to_promise(async move {
+            if let Some(chunk) = chunk.map_err(|e| JsError::new(&e))? {
+                store
+                    .put_chunk(&blob_id, chunk.index, &chunk.bytes)
+                    .await
+                    .map_err(|e| JsError::new(&e))?;
+            }
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 寫入最後一段並結束
+    pub fn finish(&self) -> Promise {
+        let chunk = self.writer.borrow_mut().finish();
+        let mut store = self.store.clone();
+        let blob_id = self.blob_id.clone();
+        future_to_promise(async move {
+            let chunk = chunk.map_err(|e| JsError::new(&e))?;
+            store
+                .put_chunk(&blob_id, chunk.index, &chunk.bytes)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+}
+
+/// IndexedDB 附件串流讀取器 (由 `IndexedDbStore.blobReader` 建立)
+#[wasm_bindgen]
+pub struct IndexedDbBlobReader {
+    store: IndexedDbStore,
+    blob_id: String,
+    /// 讀完後為 None
+    reader: Rc<RefCell<Option<BlobReader>>>,
+}
+
+#[wasm_bindgen]
+impl IndexedDbBlobReader {
+    /// 讀取並解密下一個 chunk，Promise 的結果為明文 (可能為空) 或讀完後的 undefined
+    ///
+    /// 必須等前一個 `read` 完成後再呼叫
+    pub fn read(&self) -> Promise {
+        let store = self.store.clone();
+        let blob_id = self.blob_id.clone();
+        let reader = self.reader.clone();
+        future_to_promise(async move {
+            let Some(index) = reader.borrow().as_ref().map(BlobReader::next_index) else {
+                return Ok(JsValue::UNDEFINED);
+            };
+            let chunk = store.get_chunk(&blob_id, index).await.map_err(|e| JsError::new(&e))?;
+            let mut state = reader.borrow_mut();
+            let Some(current) = state.as_mut() else {
+                return Ok(JsValue::UNDEFINED);
+            };
+            let plaintext = match chunk {
+                Some(chunk) => current.read(&chunk),
+                None => {
+                    let last = current.finish();
+                    *state = None;
+                    last
+                }
+            };
+            let plaintext = plaintext.map_err(|e| JsError::new(&e))?;
+            Ok(Uint8Array::from(plaintext.as_slice()).into())
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl IndexedDbStore {
+    /// 開啟 (不存在時建立) 資料庫
+    pub async fn open(name: String) -> Result<IndexedDbStore, JsError> {
+        Self::open_database(&name, None).await.map_err(|e| JsError::new(&e))
+    }
+
+    /// 開啟 (不存在時建立) 以主儲存金鑰加密的資料庫，Promise 的結果為 `IndexedDbStore`
+    #[wasm_bindgen(js_name = openEncrypted)]
+    pub fn open_encrypted(name: String, key: &StorageKey) -> Promise {
+        let key = key.clone();
+        future_to_promise(async move {
+            let store = Self::open_database(&name, Some(key)).await.map_err(|e| JsError::new(&e))?;
+            Ok(store.into())
+        })
+    }
+
+    /// 以密碼解鎖資料庫 (首次使用時建立密碼鎖)，Promise 的結果為 `IndexedDbStore`
+    ///
+    /// `params` 只在建立密碼鎖時使用，未指定時為 Argon2id 預設值；
+    /// 衍生金鑰會阻塞目前的執行緒，建議在 Worker 中呼叫
+    #[wasm_bindgen(js_name = unlockStorage)]
+    pub fn unlock_storage(name: String, password: String, params: Option<PasswordKdf>) -> Promise {
+        let kdf = params.map(|kdf| kdf.params()).unwrap_or_default();
+        future_to_promise(async move {
+            let store = Self::unlock_database(&name, password.as_bytes(), &kdf)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(store.into())
+        })
+    }
+
+    /// 刪除整個資料庫 (回傳 Promise)
+    #[wasm_bindgen(js_name = deleteDatabase)]
+    pub async fn delete_database(name: String) -> Result<(), JsError> {
+        let factory = indexed_db().map_err(|e| JsError::new(&e))?;
+        let request = factory
+            .delete_database(&name)
+            .map_err(|e| JsError::new(&js_error("Failed to delete IndexedDB", e)))?;
+        request_result(request.into()).await.map_err(|e| JsError::new(&e))?;
+        Ok(())
+    }
+
+    /// 關閉資料庫連線 (並停止多分頁協調)
+    pub fn close(&self) {
+        if let Some(tabs) = self.tabs.borrow_mut().take() {
+            tabs.stop();
+        }
+        self.db.close();
+    }
+
+    /// 啟用多分頁協調：只有取得寫入鎖的分頁能寫入會話與消耗一次性預金鑰，
+    /// 其他分頁的變更通知轉發給 `onChange` 的訂閱者 (見 `tabs` 模組)
+    ///
+    /// 本分頁取得寫入鎖時呼叫 `on_writer`；已啟用時不做任何事
+    #[wasm_bindgen(js_name = coordinateTabs)]
+    pub fn coordinate_tabs(&self, on_writer: Option<Function>) -> Result<(), JsError> {
+        let mut tabs = self.tabs.borrow_mut();
+        if tabs.is_none() {
+            *tabs = Some(TabCoordinator::start(&self.db.name(), &self.observers, on_writer).map_err(|e| JsError::new(&e))?);
+        }
+        Ok(())
+    }
+
+    /// 本分頁是否能寫入會話 (未啟用多分頁協調時永遠為 true)
+    #[wasm_bindgen(getter, js_name = isSessionWriter)]
+    pub fn is_session_writer(&self) -> bool {
+        self.tabs.borrow().as_ref().is_none_or(TabCoordinator::is_writer)
+    }
+
+    /// 訂閱會話、身份與訊息變更：`(event: StoreEvent) => void`，回傳取消訂閱用的 ID
+    #[wasm_bindgen(js_name = onChange)]
+    pub fn on_change(&self, callback: Function) -> u32 {
+        self.observers.subscribe_js(callback)
+    }
+
+    /// 取消訂閱，回傳是否存在
+    #[wasm_bindgen(js_name = offChange)]
+    pub fn off_change(&self, id: u32) -> bool {
+        self.observers.unsubscribe(id)
+    }
+
+    /// 以新的主儲存金鑰重新加密所有記錄 (更換金鑰或懷疑本機遭入侵時)，Promise 的結果為是否完成
+    ///
+    /// 每批最多 `chunk_size` (預設 256) 筆記錄一個交易，每批完成後以 `KeyRotationProgress`
+    /// 呼叫 `on_progress`，回傳 `false` 時暫停 (callback 拋出的例外會被忽略)。輪替期間本實例與
+    /// 複製出的實例照常讀寫；暫停或中斷 (例如關閉分頁) 後以舊金鑰開啟，再以同一把新金鑰呼叫即繼續，
+    /// 完成後只能以新金鑰開啟。啟用多分頁協調時只有寫入分頁能輪替，其他分頁需在完成後以新金鑰重新開啟
+    ///
+    /// 以密碼解鎖的資料庫改用 `changeStoragePassword`；`OpfsBlobStore` 的檔名以開啟時的金鑰盲化，不在輪替範圍內
+    #[wasm_bindgen(js_name = rotateStorageKey)]
+    pub fn rotate_storage_key_js(&self, new_key: &StorageKey, chunk_size: Option<u32>, on_progress: Option<Function>) -> Promise {
+        let store = self.clone();
+        let new_key = new_key.clone();
+        future_to_promise(async move {
+            let completed = store
+                .rotate_key(&new_key, chunk_size.unwrap_or(KEY_ROTATION_CHUNK_SIZE), |progress| {
+                    report_rotation_progress(on_progress.as_ref(), progress)
+                })
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(completed.into())
+        })
+    }
+
+    /// 變更儲存密碼：以新密碼衍生的主儲存金鑰重新加密所有記錄，Promise 的結果為是否完成
+    ///
+    /// 新的密碼鎖以 `params` (未指定時為 Argon2id 預設值) 建立，完成時才取代舊的；
+    /// 暫停或中斷後以舊密碼解鎖，再以同一個新密碼呼叫即繼續 (其餘見 `rotateStorageKey`)
+    #[wasm_bindgen(js_name = changeStoragePassword)]
+    pub fn change_storage_password_js(
+        &self,
+        password: String,
+        params: Option<PasswordKdf>,
+        chunk_size: Option<u32>,
+        on_progress: Option<Function>,
+    ) -> Promise {
+        let store = self.clone();
+        let kdf = params.map(|kdf| kdf.params()).unwrap_or_default();
+        future_to_promise(async move {
+            let completed = store
+                .change_password(password.as_bytes(), &kdf, chunk_size.unwrap_or(KEY_ROTATION_CHUNK_SIZE), |progress| {
+                    report_rotation_progress(on_progress.as_ref(), progress)
+                })
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(completed.into())
+        })
+    }
+
+    /// 檢查所有記錄的檢查碼，Promise 的結果為 `StorageVerification`
+    ///
+    /// `repair` 時刪除毀損的預金鑰 (客戶端需重新產生並上傳) 與會話 (`needsRehandshake` 為 true，
+    /// 下次傳訊前重新建立)，其他毀損的記錄只回報。啟用多分頁協調時只有寫入分頁能修復
+    #[wasm_bindgen(js_name = verifyStorage)]
+    pub fn verify_storage_js(&self, repair: bool) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let verification = store.verify(repair).await.map_err(|e| JsError::new(&e))?;
+            Ok(verification.into())
+        })
+    }
+
+    /// 會話是否因毀損被 `verifyStorage` 刪除、需要重新握手 (回傳 Promise)
+    #[wasm_bindgen(js_name = needsRehandshake)]
+    pub fn needs_rehandshake_js(&self, contact_id: String, device_id: u32) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let needed = store
+                .needs_rehandshake(&contact_id, device_id)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(needed.into())
+        })
+    }
+
+    /// 重新建立會話後清除標記，Promise 的結果為是否有標記
+    #[wasm_bindgen(js_name = clearRehandshake)]
+    pub fn clear_rehandshake_js(&self, contact_id: String, device_id: u32) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let cleared = store
+                .clear_rehandshake(&contact_id, device_id)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(cleared.into())
+        })
+    }
+
+    /// 設定本機身份 (回傳 Promise)
+    #[wasm_bindgen(js_name = setLocalIdentity)]
+    pub fn set_local_identity_js(&self, identity: &IdentityKeyPair, registration_id: u32) -> Promise {
+        let store = self.clone();
+        let identity = identity.clone();
+        future_to_promise(async move {
+            store.set_local_identity(&identity, registration_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 本機身份金鑰對，Promise 的結果為 `IdentityKeyPair`
+    #[wasm_bindgen(js_name = identityKeyPair)]
+    pub fn identity_key_pair_js(&self) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let identity = store.identity_key_pair().await.map_err(|e| JsError::new(&e))?;
+            Ok(identity.into())
+        })
+    }
+
+    /// 檢查聯絡人身份公鑰，Promise 的結果為 `IdentityStatus`
+    #[wasm_bindgen(js_name = checkIdentity)]
+    pub fn check_identity_js(&self, contact_id: String, identity_key: Vec<u8>) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let status = store.check_identity(&contact_id, &identity_key).await.map_err(|e| JsError::new(&e))?;
+            Ok(status.into())
+        })
+    }
+
+    /// 記錄聯絡人身份公鑰，Promise 的結果為記錄前的 `IdentityStatus`
+    #[wasm_bindgen(js_name = saveIdentity)]
+    pub fn save_identity_js(&self, contact_id: String, identity_key: Vec<u8>, now: u64) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let status = store
+                .save_identity(&contact_id, &identity_key, now)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(status.into())
+        })
+    }
+
+    /// 驗證並記錄撤銷憑證，Promise 的結果為記錄前的 `IdentityStatus`
+    #[wasm_bindgen(js_name = applyRevocation)]
+    pub fn apply_revocation_js(&self, contact_id: String, certificate: &RevocationCertificate) -> Promise {
+        let store = self.clone();
+        let certificate = certificate.clone();
+        future_to_promise(async move {
+            let status = store
+                .apply_revocation(&contact_id, &certificate)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(status.into())
+        })
+    }
+
+    /// 載入會話，Promise 的結果為 `RatchetSession` 或 undefined
+    #[wasm_bindgen(js_name = loadSession)]
+    pub fn load_session_js(&self, contact_id: String, device_id: u32) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let session = store.load_session(&contact_id, device_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(session.map(JsValue::from).unwrap_or(JsValue::UNDEFINED))
+        })
+    }
+
+    /// 儲存會話
+    #[wasm_bindgen(js_name = storeSession)]
+    pub fn store_session_js(&self, contact_id: String, device_id: u32, session: &RatchetSession) -> Promise {
+        let mut store = self.clone();
+        let session = session.clone();
+        future_to_promise(async move {
+            store
+                .store_session(&contact_id, device_id, &session)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 刪除會話，Promise 的結果為是否存在
+    #[wasm_bindgen(js_name = removeSession)]
+    pub fn remove_session_js(&self, contact_id: String, device_id: u32) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let existed = store.remove_session(&contact_id, device_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(existed.into())
+        })
+    }
+
+    /// 聯絡人有會話的所有裝置 ID
+    #[wasm_bindgen(js_name = sessionDevices)]
+    pub fn session_devices_js(&self, contact_id: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let devices = store.session_devices(&contact_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(devices.into_iter().map(JsValue::from).collect::<Array>().into())
+        })
+    }
+
+    /// 產生一次性預金鑰，Promise 的結果為公鑰列表 JSON
+    #[wasm_bindgen(js_name = generatePreKeys)]
+    pub fn generate_pre_keys(&self, count: u32) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let batch = store.generate_one_time_pre_keys(count).await.map_err(|e| JsError::new(&e))?;
+            let json = serde_json::to_string(&batch).map_err(|e| JsError::new(&e.to_string()))?;
+            Ok(json.into())
+        })
+    }
+
+    /// 消耗一次性預金鑰，Promise 的結果為私鑰
+    #[wasm_bindgen(js_name = consumePreKey)]
+    pub fn consume_pre_key_js(&self, key_id: u32) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let private_key = store.consume_pre_key(key_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(Uint8Array::from(private_key.as_slice()).into())
+        })
+    }
+
+    /// 儲存簽署預金鑰
+    #[wasm_bindgen(js_name = storeSignedPreKey)]
+    pub fn store_signed_pre_key_js(&self, record: &SignedPreKeyRecord) -> Promise {
+        let mut store = self.clone();
+        let record = record.clone();
+        future_to_promise(async move {
+            store.store_signed_pre_key(&record).await.map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 載入簽署預金鑰，Promise 的結果為 `SignedPreKeyRecord`
+    #[wasm_bindgen(js_name = loadSignedPreKey)]
+    pub fn load_signed_pre_key_js(&self, key_id: u32) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let record = store.load_signed_pre_key(key_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(record.into())
+        })
+    }
+
+    /// 儲存訊息
+    #[wasm_bindgen(js_name = storeMessage)]
+    pub fn store_message_js(&self, message: &StoredMessage) -> Promise {
+        let mut store = self.clone();
+        let message = message.clone();
+        future_to_promise(async move {
+            store.store_message(&message).await.map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 載入訊息，Promise 的結果為 `StoredMessage` 或 undefined
+    #[wasm_bindgen(js_name = loadMessage)]
+    pub fn load_message_js(&self, id: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let message = store.load_message(&id).await.map_err(|e| JsError::new(&e))?;
+            Ok(message.map(JsValue::from).unwrap_or(JsValue::UNDEFINED))
+        })
+    }
+
+    /// 刪除訊息，Promise 的結果為是否存在
+    #[wasm_bindgen(js_name = removeMessage)]
+    pub fn remove_message_js(&self, id: String) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let existed = store.remove_message(&id).await.map_err(|e| JsError::new(&e))?;
+            Ok(existed.into())
+        })
+    }
+
+    /// 對話中的訊息 (分頁)，Promise 的結果為 `StoredMessage` 陣列
+    #[wasm_bindgen(js_name = conversationMessages)]
+    pub fn conversation_messages_js(&self, conversation_id: String, before: Option<u64>, limit: Option<u32>) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let messages = store
+                .conversation_messages(&conversation_id, before, limit)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(messages.into_iter().map(JsValue::from).collect::<Array>().into())
+        })
+    }
+
+    /// 設定對話的保留政策，未提供時移除
+    #[wasm_bindgen(js_name = setRetentionPolicy)]
+    pub fn set_retention_policy_js(&self, conversation_id: String, policy: Option<RetentionPolicy>) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            store
+                .set_retention_policy(&conversation_id, policy)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 刪除所有超出保留政策的訊息，Promise 的結果為被刪除的訊息 ID 陣列
+    #[wasm_bindgen(js_name = enforceRetention)]
+    pub fn enforce_retention_js(&self, now: u64) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let removed = store.enforce_retention(now).await.map_err(|e| JsError::new(&e))?;
+            Ok(removed.into_iter().map(JsValue::from).collect::<Array>().into())
+        })
+    }
+
+    /// 索引 (或重新索引) 訊息文字
+    #[wasm_bindgen(js_name = indexMessage)]
+    pub fn index_message(&self, id: String, text: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            store.index_message_text(&id, &text).await.map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 搜尋訊息，Promise 的結果為依時間排序的訊息 ID 陣列
+    pub fn search(&self, query: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let ids = store.search_messages(&query).await.map_err(|e| JsError::new(&e))?;
+            Ok(ids.into_iter().map(JsValue::from).collect::<Array>().into())
+        })
+    }
+
+    /// 存入 (覆寫) 應用層秘密
+    #[wasm_bindgen(js_name = kvPut)]
+    pub fn kv_put(&self, namespace: String, key: String, value: Vec<u8>) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            store.put(&namespace, &key, &value).await.map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 讀取應用層秘密，Promise 的結果為 `Uint8Array` 或 undefined
+    #[wasm_bindgen(js_name = kvGet)]
+    pub fn kv_get(&self, namespace: String, key: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let value = store.get(&namespace, &key).await.map_err(|e| JsError::new(&e))?;
+            Ok(value.map(|value| Uint8Array::from(value.as_slice()).into()).unwrap_or(JsValue::UNDEFINED))
+        })
+    }
+
+    /// namespace 中所有的 key，Promise 的結果為排序後的字串陣列
+    #[wasm_bindgen(js_name = kvList)]
+    pub fn kv_list(&self, namespace: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let keys = store.list(&namespace).await.map_err(|e| JsError::new(&e))?;
+            Ok(keys.into_iter().map(JsValue::from).collect::<Array>().into())
+        })
+    }
+
+    /// 刪除應用層秘密，Promise 的結果為是否存在
+    #[wasm_bindgen(js_name = kvRemove)]
+    pub fn kv_remove(&self, namespace: String, key: String) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let existed = store.remove(&namespace, &key).await.map_err(|e| JsError::new(&e))?;
+            Ok(existed.into())
+        })
+    }
+
+    /// 存入擁有者 (聯絡人或群組 ID) 的頭像密文，Promise 的結果為摘要
+    #[wasm_bindgen(js_name = cacheAvatar)]
+    pub fn cache_avatar_js(&self, owner: String, ciphertext: Vec<u8>) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let digest = store.cache_avatar(&owner, &ciphertext).await.map_err(|e| JsError::new(&e))?;
+            Ok(digest.into())
+        })
+    }
+
+    /// 依摘要讀取頭像密文，Promise 的結果為 `Uint8Array` 或 undefined
+    #[wasm_bindgen(js_name = cachedAvatar)]
+    pub fn cached_avatar_js(&self, digest: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let avatar = store.cached_avatar(&digest).await.map_err(|e| JsError::new(&e))?;
+            Ok(avatar.map(|avatar| Uint8Array::from(avatar.as_slice()).into()).unwrap_or(JsValue::UNDEFINED))
+        })
+    }
+
+    /// 擁有者目前頭像的摘要，Promise 的結果為字串或 undefined
+    #[wasm_bindgen(js_name = avatarDigestOf)]
+    pub fn avatar_digest_of_js(&self, owner: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let digest = store.avatar_digest_of(&owner).await.map_err(|e| JsError::new(&e))?;
+            Ok(digest.map(JsValue::from).unwrap_or(JsValue::UNDEFINED))
+        })
+    }
+
+    /// 擁有者目前的頭像密文，Promise 的結果為 `Uint8Array` 或 undefined
+    #[wasm_bindgen(js_name = avatarOf)]
+    pub fn avatar_of_js(&self, owner: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let avatar = store.avatar_of(&owner).await.map_err(|e| JsError::new(&e))?;
+            Ok(avatar.map(|avatar| Uint8Array::from(avatar.as_slice()).into()).unwrap_or(JsValue::UNDEFINED))
+        })
+    }
+
+    /// 移除擁有者的頭像，Promise 的結果為是否存在
+    #[wasm_bindgen(js_name = removeAvatar)]
+    pub fn remove_avatar_js(&self, owner: String) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let existed = store.remove_avatar(&owner).await.map_err(|e| JsError::new(&e))?;
+            Ok(existed.into())
+        })
+    }
+
+    /// 載入聯絡人，Promise 的結果為 `Contact` 或 undefined
+    #[wasm_bindgen(js_name = loadContact)]
+    pub fn load_contact_js(&self, contact_id: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let contact = store.load_contact(&contact_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(contact.map(JsValue::from).unwrap_or(JsValue::UNDEFINED))
+        })
+    }
+
+    /// 儲存 (覆寫) 聯絡人
+    #[wasm_bindgen(js_name = storeContact)]
+    pub fn store_contact_js(&self, contact: &Contact) -> Promise {
+        let mut store = self.clone();
+        let contact = contact.clone();
+        future_to_promise(async move {
+            store.store_contact(&contact).await.map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 刪除聯絡人，Promise 的結果為是否存在
+    #[wasm_bindgen(js_name = removeContact)]
+    pub fn remove_contact_js(&self, contact_id: String) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let existed = store.remove_contact(&contact_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(existed.into())
+        })
+    }
+
+    /// 所有聯絡人 (依 ID 排序)，Promise 的結果為 `Contact` 陣列
+    #[wasm_bindgen(js_name = contacts)]
+    pub fn contacts_js(&self) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let contacts = store.contacts().await.map_err(|e| JsError::new(&e))?;
+            Ok(contacts.into_iter().map(JsValue::from).collect::<Array>().into())
+        })
+    }
+
+    /// 載入群組，Promise 的結果為 `Group` 或 undefined
+    #[wasm_bindgen(js_name = loadGroup)]
+    pub fn load_group_js(&self, group_id: String) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let group = store.load_group(&group_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(group.map(JsValue::from).unwrap_or(JsValue::UNDEFINED))
+        })
+    }
+
+    /// 儲存 (覆寫) 群組
+    #[wasm_bindgen(js_name = storeGroup)]
+    pub fn store_group_js(&self, group: &Group) -> Promise {
+        let mut store = self.clone();
+        let group = group.clone();
+        future_to_promise(async move {
+            store.store_group(&group).await.map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 刪除群組，Promise 的結果為是否存在
+    #[wasm_bindgen(js_name = removeGroup)]
+    pub fn remove_group_js(&self, group_id: String) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let existed = store.remove_group(&group_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(existed.into())
+        })
+    }
+
+    /// 所有群組 (依 ID 排序)，Promise 的結果為 `Group` 陣列
+    #[wasm_bindgen(js_name = groups)]
+    pub fn groups_js(&self) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let groups = store.groups().await.map_err(|e| JsError::new(&e))?;
+            Ok(groups.into_iter().map(JsValue::from).collect::<Array>().into())
+        })
+    }
+
+    /// 記錄身份公鑰並更新聯絡人，Promise 的結果為記錄前的 `IdentityStatus`
+    #[wasm_bindgen(js_name = saveContactIdentity)]
+    pub fn save_contact_identity_js(&self, contact_id: String, identity_key: Vec<u8>, now: u64) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let status = store
+                .save_identity(&contact_id, &identity_key, now)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            store
+                .apply_identity(&contact_id, &identity_key, status)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(status.into())
+        })
+    }
+
+    /// 建立附件串流寫入器 (覆寫既有的 blob)，Promise 的結果為 `IndexedDbBlobWriter`
+    ///
+    /// `chunk_size` 未提供時為 64 KiB，`suite` 未提供時為 AES-256-GCM
+    #[wasm_bindgen(js_name = blobWriter)]
+    pub fn blob_writer(&self, blob_id: String, key: Vec<u8>, chunk_size: Option<u32>, suite: Option<CipherSuite>) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let (writer, header) = BlobWriter::new(&key, chunk_size, suite).map_err(|e| JsError::new(&e))?;
+            store.remove_blob(&blob_id).await.map_err(|e| JsError::new(&e))?;
+            store
+                .put_chunk(&blob_id, header.index, &header.bytes)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(IndexedDbBlobWriter { store, blob_id, writer: Rc::new(RefCell::new(writer)) }.into())
+        })
+    }
+
+    /// 建立附件串流讀取器，Promise 的結果為 `IndexedDbBlobReader`
+    #[wasm_bindgen(js_name = blobReader)]
+    pub fn blob_reader(&self, blob_id: String, key: Vec<u8>) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let header = store
+                .get_chunk(&blob_id, 0)
+                .await
+                .map_err(|e| JsError::new(&e))?
+                .ok_or_else(|| JsError::new(&format!("Unknown blob: {}", blob_id)))?;
+            let reader = BlobReader::new(&key, &header).map_err(|e| JsError::new(&e))?;
+            Ok(IndexedDbBlobReader { store, blob_id, reader: Rc::new(RefCell::new(Some(reader))) }.into())
+        })
+    }
+
+    /// 刪除附件的所有 chunk，Promise 的結果為是否存在
+    #[wasm_bindgen(js_name = removeBlob)]
+    pub fn remove_blob_js(&self, blob_id: String) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let existed = store.remove_blob(&blob_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(existed.into())
+        })
+    }
+
+    /// 刪除配額清理的候選項目 (見 `quota` 模組)，Promise 在刪除完成後 resolve
+    #[wasm_bindgen(js_name = pruneCandidate)]
+    pub fn prune_candidate_js(&self, candidate: &PruneCandidate) -> Promise {
+        let mut store = self.clone();
+        let candidate = candidate.clone();
+        future_to_promise(async move {
+            store.prune(&candidate).await.map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 發起者：對 bundle 完成 X3DH 並儲存新的會話，Promise 的結果為 `SessionEstablishment`
+    #[wasm_bindgen(js_name = initiateSession)]
+    pub fn initiate_session(&self, contact_id: String, device_id: u32, bundle: &PreKeyBundle, now: u64) -> Promise {
+        let mut store = self.clone();
+        let bundle = bundle.clone();
+        future_to_promise(async move {
+            let establishment = X3DH::initiate_session_async(&mut store, &contact_id, device_id, &bundle, now).await?;
+            Ok(establishment.into())
+        })
+    }
+
+    /// 接收者：處理初始訊息並儲存新的會話，Promise 的結果為 `SessionEstablishment`
+    #[wasm_bindgen(js_name = respondSession)]
+    pub fn respond_session(
+        &self,
+        contact_id: String,
+        signed_pre_key_id: u32,
+        initial_message: &X3DHInitialMessage,
+        now: u64,
+    ) -> Promise {
+        let mut store = self.clone();
+        let initial_message = initial_message.clone();
+        future_to_promise(async move {
+            let establishment =
+                X3DH::respond_session_async(&mut store, &contact_id, signed_pre_key_id, &initial_message, now).await?;
+            Ok(establishment.into())
+        })
+    }
+}
@@ -0,0 +1,139 @@
+//! 儲存完整性檢查模組
+//!
+//! 持久化後端的 `verifyStorage` 逐筆檢查記錄的檢查碼 (見 `encryption` 模組) 並回報毀損的記錄，
+//! 一次寫壞的記錄只影響該筆資料，不會讓整個客戶端無聲地失效。要求修復時依記錄類型處理：
+//! - 一次性 / 簽署預金鑰：刪除，客戶端重新產生並上傳
+//! - 會話：刪除並標記需要重新握手 (`needsRehandshake`)，下次傳訊前重新以 X3DH 建立會話
+//! - 其他記錄：只回報，由客戶端決定 (例如從備份還原)
+
+use wasm_bindgen::prelude::*;
+
+/// 毀損記錄的修復方式
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RepairAction {
+    /// 只回報
+    ReportOnly = 0,
+    /// 刪除預金鑰，重新產生並上傳
+    RegeneratePreKey = 1,
+    /// 刪除會話並標記需要重新握手
+    Rehandshake = 2,
+}
+
+impl RepairAction {
+    /// 倉庫 (資料表) 中毀損記錄的修復方式
+    #[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+    pub(crate) fn for_store(store: &str) -> Self {
+        match store {
+            "pre_keys" | "signed_pre_keys" => RepairAction::RegeneratePreKey,
+            "sessions" => RepairAction::Rehandshake,
+            _ => RepairAction::ReportOnly,
+        }
+    }
+}
+
+/// 毀損的記錄
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CorruptRecord {
+    store: String,
+    record_key: Vec<u8>,
+    error: String,
+    action: RepairAction,
+    repaired: bool,
+}
+
+#[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+impl CorruptRecord {
+    pub(crate) fn new(store: &str, record_key: &[u8], error: String) -> Self {
+        Self {
+            store: store.to_string(),
+            record_key: record_key.to_vec(),
+            error,
+            action: RepairAction::for_store(store),
+            repaired: false,
+        }
+    }
+
+    /// 已依修復方式處理 (`ReportOnly` 之外)
+    pub(crate) fn mark_repaired(&mut self) {
+        self.repaired = true;
+    }
+}
+
+#[wasm_bindgen]
+impl CorruptRecord {
+    /// 倉庫 (資料表) 名稱
+    #[wasm_bindgen(getter)]
+    pub fn store(&self) -> String {
+        self.store.clone()
+    }
+
+    /// 記錄鍵 (盲化後的值)
+    #[wasm_bindgen(getter, js_name = recordKey)]
+    pub fn record_key(&self) -> Vec<u8> {
+        self.record_key.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn error(&self) -> String {
+        self.error.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn action(&self) -> RepairAction {
+        self.action
+    }
+
+    /// 是否已修復 (只回報的記錄永遠為 false)
+    #[wasm_bindgen(getter)]
+    pub fn repaired(&self) -> bool {
+        self.repaired
+    }
+}
+
+/// `verifyStorage` 的結果
+#[wasm_bindgen]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StorageVerification {
+    checked: u32,
+    corrupted: Vec<CorruptRecord>,
+}
+
+#[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+impl StorageVerification {
+    /// 記錄一筆檢查結果，回傳是否毀損
+    pub(crate) fn record(&mut self, store: &str, record_key: &[u8], result: Result<(), String>) -> bool {
+        self.checked += 1;
+        let Err(error) = result else {
+            return false;
+        };
+        self.corrupted.push(CorruptRecord::new(store, record_key, error));
+        true
+    }
+
+    pub(crate) fn corrupted_mut(&mut self) -> &mut [CorruptRecord] {
+        &mut self.corrupted
+    }
+}
+
+#[wasm_bindgen]
+impl StorageVerification {
+    /// 檢查的記錄數
+    #[wasm_bindgen(getter)]
+    pub fn checked(&self) -> u32 {
+        self.checked
+    }
+
+    /// 毀損的記錄
+    #[wasm_bindgen(getter)]
+    pub fn corrupted(&self) -> Vec<CorruptRecord> {
+        self.corrupted.clone()
+    }
+
+    /// 沒有毀損的記錄
+    #[wasm_bindgen(getter)]
+    pub fn healthy(&self) -> bool {
+        self.corrupted.is_empty()
+    }
+}
@@ -0,0 +1,390 @@
+//! 多帳號金鑰庫模組
+//!
+//! 一個 WASM 實例可同時管理多個互相獨立的身份 (例如公司與個人帳號)，
+//! 每個帳號各自擁有身份金鑰、一次性預金鑰池、身份信任儲存與會話命名空間，
+//! 以 account ID 定址
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{
+    generate_registration_id, IdentityKeyPair, OneTimePreKeyPool, RatchetSession, SyncEnvelope,
+    SyncMessage, PRIMARY_DEVICE_ID,
+};
+use super::trust::{IdentityStatus, IdentityTrustStore};
+
+/// 會話記錄
+#[derive(Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    /// Double Ratchet 會話
+    pub session: RatchetSession,
+    /// 對方裝置的註冊 ID (如已知)
+    pub remote_registration_id: Option<u32>,
+}
+
+/// 單一帳號的金鑰與會話
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Account {
+    /// 身份私鑰 (Ed25519)
+    identity_private_key: Vec<u8>,
+    /// 本機註冊 ID
+    pub registration_id: u32,
+    /// 本機裝置 ID
+    pub device_id: u32,
+    /// 一次性預金鑰池
+    pub pre_key_pool: OneTimePreKeyPool,
+    /// 聯絡人身份信任儲存
+    pub trust_store: IdentityTrustStore,
+    /// (contact_id, device_id) -> 會話記錄
+    pub sessions: BTreeMap<(String, u32), SessionRecord>,
+}
+
+impl Account {
+    fn new(identity: &IdentityKeyPair, device_id: u32) -> Self {
+        Self {
+            identity_private_key: identity.private_key_bytes().expose().to_vec(),
+            registration_id: generate_registration_id(),
+            device_id,
+            pre_key_pool: OneTimePreKeyPool::new(1, None, None),
+            trust_store: IdentityTrustStore::new(),
+            sessions: BTreeMap::new(),
+        }
+    }
+
+    /// 身份私鑰
+    pub fn identity_private_key(&self) -> &[u8] {
+        &self.identity_private_key
+    }
+}
+
+/// 多帳號金鑰庫
+#[wasm_bindgen]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct KeyStore {
+    /// account_id -> 帳號
+    accounts: BTreeMap<String, Account>,
+}
+
+impl KeyStore {
+    /// 加入帳號 (Rust 端使用)
+    pub fn insert_account(
+        &mut self,
+        account_id: &str,
+        identity: &IdentityKeyPair,
+        device_id: u32,
+    ) -> Result<(), String> {
+        if self.accounts.contains_key(account_id) {
+            return Err(format!("Account already exists: {}", account_id));
+        }
+        self.accounts.insert(account_id.to_string(), Account::new(identity, device_id));
+        Ok(())
+    }
+
+    /// 取得帳號
+    pub fn account(&self, account_id: &str) -> Result<&Account, String> {
+        self.accounts
+            .get(account_id)
+            .ok_or_else(|| format!("Unknown account: {}", account_id))
+    }
+
+    /// 取得可修改的帳號
+    pub fn account_mut(&mut self, account_id: &str) -> Result<&mut Account, String> {
+        self.accounts
+            .get_mut(account_id)
+            .ok_or_else(|| format!("Unknown account: {}", account_id))
+    }
+}
+
+#[wasm_bindgen]
+impl KeyStore {
+    /// 建立空的金鑰庫
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 建立新帳號 (作為主裝置) 並產生身份金鑰，回傳身份公鑰
+    #[wasm_bindgen(js_name = createAccount)]
+    pub fn create_account(&mut self, account_id: &str) -> Result<Vec<u8>, JsError> {
+        let identity = IdentityKeyPair::new();
+        self.insert_account(account_id, &identity, PRIMARY_DEVICE_ID)
+            .map_err(|e| JsError::new(&e))?;
+        Ok(identity.public_key_bytes())
+    }
+
+    /// 以既有身份金鑰加入帳號
+    ///
+    /// `device_id` 未提供時視為主裝置
+    #[wasm_bindgen(js_name = addAccount)]
+    pub fn add_account(
+        &mut self,
+        account_id: &str,
+        identity: &IdentityKeyPair,
+        device_id: Option<u32>,
+    ) -> Result<(), JsError> {
+        self.insert_account(account_id, identity, device_id.unwrap_or(PRIMARY_DEVICE_ID))
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 帳號的本機註冊 ID
+    #[wasm_bindgen(js_name = registrationId)]
+    pub fn registration_id(&self, account_id: &str) -> Result<u32, JsError> {
+        let account = self.account(account_id).map_err(|e| JsError::new(&e))?;
+        Ok(account.registration_id)
+    }
+
+    /// 帳號的本機裝置 ID
+    #[wasm_bindgen(js_name = deviceId)]
+    pub fn device_id(&self, account_id: &str) -> Result<u32, JsError> {
+        let account = self.account(account_id).map_err(|e| JsError::new(&e))?;
+        Ok(account.device_id)
+    }
+
+    /// 移除帳號 (含所有預金鑰與會話)
+    #[wasm_bindgen(js_name = removeAccount)]
+    pub fn remove_account(&mut self, account_id: &str) -> bool {
+        self.accounts.remove(account_id).is_some()
+    }
+
+    /// 是否有此帳號
+    #[wasm_bindgen(js_name = hasAccount)]
+    pub fn has_account(&self, account_id: &str) -> bool {
+        self.accounts.contains_key(account_id)
+    }
+
+    /// 所有帳號 ID
+    #[wasm_bindgen(js_name = accountIds)]
+    pub fn account_ids(&self) -> Vec<String> {
+        self.accounts.keys().cloned().collect()
+    }
+
+    /// 取得帳號的身份金鑰對
+    #[wasm_bindgen(js_name = identityKeyPair)]
+    pub fn identity_key_pair(&self, account_id: &str) -> Result<IdentityKeyPair, JsError> {
+        let account = self.account(account_id).map_err(|e| JsError::new(&e))?;
+        IdentityKeyPair::from_bytes(account.identity_private_key())
+    }
+
+    /// 為帳號產生一次性預金鑰，回傳公鑰列表 JSON
+    #[wasm_bindgen(js_name = generatePreKeys)]
+    pub fn generate_pre_keys(&mut self, account_id: &str, count: u32) -> Result<String, JsError> {
+        let account = self.account_mut(account_id).map_err(|e| JsError::new(&e))?;
+        account.pre_key_pool.generate_batch(count)
+    }
+
+    /// 消耗帳號的一次性預金鑰並回傳私鑰
+    #[wasm_bindgen(js_name = consumePreKey)]
+    pub fn consume_pre_key(&mut self, account_id: &str, key_id: u32) -> Result<Vec<u8>, JsError> {
+        let account = self.account_mut(account_id).map_err(|e| JsError::new(&e))?;
+        account.pre_key_pool.consume_key(key_id)
+    }
+
+    /// 帳號剩餘的一次性預金鑰數量
+    #[wasm_bindgen(js_name = remainingPreKeys)]
+    pub fn remaining_pre_keys(&self, account_id: &str) -> Result<u32, JsError> {
+        let account = self.account(account_id).map_err(|e| JsError::new(&e))?;
+        Ok(account.pre_key_pool.remaining_count())
+    }
+
+    /// 記錄聯絡人身份公鑰，回傳記錄前的狀態
+    #[wasm_bindgen(js_name = saveIdentity)]
+    pub fn save_identity(
+        &mut self,
+        account_id: &str,
+        contact_id: &str,
+        identity_key: &[u8],
+        now: u64,
+    ) -> Result<IdentityStatus, JsError> {
+        let account = self.account_mut(account_id).map_err(|e| JsError::new(&e))?;
+        Ok(account.trust_store.save_identity(contact_id, identity_key, now))
+    }
+
+    /// 檢查聯絡人身份公鑰
+    #[wasm_bindgen(js_name = checkIdentity)]
+    pub fn check_identity(
+        &self,
+        account_id: &str,
+        contact_id: &str,
+        identity_key: &[u8],
+    ) -> Result<IdentityStatus, JsError> {
+        let account = self.account(account_id).map_err(|e| JsError::new(&e))?;
+        Ok(account.trust_store.check_identity(contact_id, identity_key))
+    }
+
+    /// 儲存會話
+    ///
+    /// `remote_registration_id` 通常取自 [`crate::crypto::X3DHInitialMessage`]，
+    /// 對方重新安裝後註冊 ID 會改變，可用來偵測過期的會話
+    #[wasm_bindgen(js_name = storeSession)]
+    pub fn store_session(
+        &mut self,
+        account_id: &str,
+        contact_id: &str,
+        device_id: u32,
+        session: &RatchetSession,
+        remote_registration_id: Option<u32>,
+    ) -> Result<(), JsError> {
+        let account = self.account_mut(account_id).map_err(|e| JsError::new(&e))?;
+        account.sessions.insert(
+            (contact_id.to_string(), device_id),
+            SessionRecord {
+                session: session.clone(),
+                remote_registration_id,
+            },
+        );
+        Ok(())
+    }
+
+    /// 載入會話
+    #[wasm_bindgen(js_name = loadSession)]
+    pub fn load_session(
+        &self,
+        account_id: &str,
+        contact_id: &str,
+        device_id: u32,
+    ) -> Result<Option<RatchetSession>, JsError> {
+        let account = self.account(account_id).map_err(|e| JsError::new(&e))?;
+        Ok(account
+            .sessions
+            .get(&(contact_id.to_string(), device_id))
+            .map(|record| record.session.clone()))
+    }
+
+    /// 會話記錄中對方的註冊 ID
+    #[wasm_bindgen(js_name = remoteRegistrationId)]
+    pub fn remote_registration_id(
+        &self,
+        account_id: &str,
+        contact_id: &str,
+        device_id: u32,
+    ) -> Result<Option<u32>, JsError> {
+        let account = self.account(account_id).map_err(|e| JsError::new(&e))?;
+        Ok(account
+            .sessions
+            .get(&(contact_id.to_string(), device_id))
+            .and_then(|record| record.remote_registration_id))
+    }
+
+    /// 刪除會話
+    #[wasm_bindgen(js_name = removeSession)]
+    pub fn remove_session(&mut self, account_id: &str, contact_id: &str, device_id: u32) -> Result<bool, JsError> {
+        let account = self.account_mut(account_id).map_err(|e| JsError::new(&e))?;
+        Ok(account.sessions.remove(&(contact_id.to_string(), device_id)).is_some())
+    }
+
+    /// 帳號下所有有會話的聯絡人
+    #[wasm_bindgen(js_name = sessionContacts)]
+    pub fn session_contacts(&self, account_id: &str) -> Result<Vec<String>, JsError> {
+        let account = self.account(account_id).map_err(|e| JsError::new(&e))?;
+        let mut contacts: Vec<String> = account.sessions.keys().map(|(contact, _)| contact.clone()).collect();
+        contacts.dedup();
+        Ok(contacts)
+    }
+
+    /// 聯絡人有會話的所有裝置 ID
+    #[wasm_bindgen(js_name = sessionDevices)]
+    pub fn session_devices(&self, account_id: &str, contact_id: &str) -> Result<Vec<u32>, JsError> {
+        let account = self.account(account_id).map_err(|e| JsError::new(&e))?;
+        Ok(account
+            .sessions
+            .keys()
+            .filter(|(contact, _)| contact == contact_id)
+            .map(|(_, device)| *device)
+            .collect())
+    }
+
+    /// 將同步訊息加密給帳號自己的其他裝置
+    ///
+    /// 自己其他裝置的會話以帳號 ID 作為聯絡人 ID 儲存
+    #[wasm_bindgen(js_name = encryptSyncMessage)]
+    pub fn encrypt_sync_message(
+        &mut self,
+        account_id: &str,
+        message: &SyncMessage,
+    ) -> Result<Vec<SyncEnvelope>, JsError> {
+        let account = self.account_mut(account_id).map_err(|e| JsError::new(&e))?;
+        let own_device = account.device_id;
+        let mut envelopes = Vec::new();
+        for ((contact, device_id), record) in account.sessions.iter_mut() {
+            if contact == account_id && *device_id != own_device {
+                envelopes.push(message.encrypt_for_device(&mut record.session, own_device, *device_id)?);
+            }
+        }
+        Ok(envelopes)
+    }
+
+    /// 序列化金鑰庫 (包含私鑰，敏感！)
+    #[wasm_bindgen(js_name = serialize)]
+    pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 還原金鑰庫
+    #[wasm_bindgen(js_name = deserialize)]
+    pub fn deserialize(bytes: &[u8]) -> Result<KeyStore, JsError> {
+        bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accounts_are_isolated() {
+        let mut store = KeyStore::new();
+        let work = store.create_account("work").unwrap();
+        let personal = store.create_account("personal").unwrap();
+        assert_ne!(work, personal);
+        assert!(store.insert_account("work", &IdentityKeyPair::new(), 2).is_err());
+        assert!(crate::crypto::is_valid_registration_id(store.account("work").unwrap().registration_id));
+        assert_eq!(store.account("work").unwrap().device_id, PRIMARY_DEVICE_ID);
+        assert_eq!(store.account_ids(), vec!["personal".to_string(), "work".to_string()]);
+
+        store.account_mut("work").unwrap().pre_key_pool.generate_keys(5).unwrap();
+        assert_eq!(store.account("work").unwrap().pre_key_pool.remaining_count(), 5);
+        assert_eq!(store.account("personal").unwrap().pre_key_pool.remaining_count(), 0);
+
+        // 同一聯絡人在不同帳號下各自記錄
+        store.account_mut("work").unwrap().trust_store.save_identity("bob", &[1u8; 32], 1);
+        assert_eq!(
+            store.account("personal").unwrap().trust_store.check_identity("bob", &[2u8; 32]),
+            IdentityStatus::NewIdentity
+        );
+
+        // 同一聯絡人的不同裝置各自一個會話
+        let session = RatchetSession::for_test([7u8; 32]);
+        store.store_session("work", "bob", 1, &session, Some(1234)).unwrap();
+        store.store_session("work", "bob", 2, &session, None).unwrap();
+        assert_eq!(store.session_devices("work", "bob").unwrap(), vec![1, 2]);
+        assert_eq!(store.session_contacts("work").unwrap(), vec!["bob".to_string()]);
+        assert_eq!(store.remote_registration_id("work", "bob", 1).unwrap(), Some(1234));
+        assert!(store.session_contacts("personal").unwrap().is_empty());
+
+        let bytes = bincode::serialize(&store).unwrap();
+        let restored: KeyStore = bincode::deserialize(&bytes).unwrap();
+        assert_eq!(restored.identity_key_pair("work").unwrap().public_key_bytes(), work);
+        assert!(restored.account("missing").is_err());
+        assert!(!store.has_account("missing"));
+    }
+
+    #[test]
+    fn test_encrypt_sync_message_to_own_devices() {
+        let mut store = KeyStore::new();
+        store.create_account("alice").unwrap();
+
+        let (to_desktop, mut desktop) = RatchetSession::test_pair([5u8; 32]);
+        let (to_tablet, _) = RatchetSession::test_pair([6u8; 32]);
+        store.store_session("alice", "alice", 2, &to_desktop, None).unwrap();
+        store.store_session("alice", "alice", 3, &to_tablet, None).unwrap();
+        store.store_session("alice", "bob", 1, &RatchetSession::test_pair([7u8; 32]).0, None).unwrap();
+
+        let message = SyncMessage::sent_transcript("bob", 42, b"hello");
+        let envelopes = store.encrypt_sync_message("alice", &message).unwrap();
+        let targets: Vec<u32> = envelopes.iter().map(|e| e.destination_device_id()).collect();
+        assert_eq!(targets, vec![2, 3]);
+        assert_eq!(envelopes[0].decrypt(&mut desktop).unwrap(), message);
+    }
+}
@@ -0,0 +1,174 @@
+//! 安全鍵值儲存模組
+//!
+//! 應用層的零散秘密 (推播 token、伺服器憑證、功能旗標等) 以
+//! (namespace, key) 存放，持久化後端與其他記錄一樣以主儲存金鑰加密、
+//! namespace 與 key 盲化，不必再放進明文的 localStorage
+//!
+//! 記錄內容同時保存原始的 key，`list` 解密後回傳
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use super::encryption::{RecordCodec, StorageKey, SNAPSHOT_STORE};
+
+const KV_SNAPSHOT_KEY: &str = "kv_store";
+
+/// 盲化 namespace / key 時使用的欄位名稱
+#[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+pub(crate) const KV_NAMESPACE_FIELD: &str = "kv_namespace";
+#[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+pub(crate) const KV_KEY_FIELD: &str = "kv_key";
+
+/// 加密時綁定的記錄鍵 (盲化後的 namespace 與 key，base64url 不含 `:`)
+#[cfg(any(feature = "indexeddb", all(feature = "sqlite", not(target_arch = "wasm32"))))]
+pub(crate) fn kv_record_key(namespace_key: &str, key: &str) -> String {
+    format!("{}:{}", namespace_key, key)
+}
+
+/// 鍵值儲存
+pub trait KeyValueStore {
+    /// 存入 (覆寫) 值
+    fn put(&mut self, namespace: &str, key: &str, value: &[u8]) -> Result<(), String>;
+
+    /// 讀取值
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, String>;
+
+    /// namespace 中所有的 key (排序)
+    fn list(&self, namespace: &str) -> Result<Vec<String>, String>;
+
+    /// 刪除值，回傳是否存在
+    fn remove(&mut self, namespace: &str, key: &str) -> Result<bool, String>;
+}
+
+/// 非同步鍵值儲存 (IndexedDB 等只能非同步存取的後端)
+#[allow(async_fn_in_trait)]
+pub trait AsyncKeyValueStore {
+    async fn put(&mut self, namespace: &str, key: &str, value: &[u8]) -> Result<(), String>;
+    async fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, String>;
+    async fn list(&self, namespace: &str) -> Result<Vec<String>, String>;
+    async fn remove(&mut self, namespace: &str, key: &str) -> Result<bool, String>;
+}
+
+/// 記憶體鍵值儲存，以 `serializeEncrypted` 持久化
+#[wasm_bindgen]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct InMemoryKeyValueStore {
+    /// (namespace, key) -> 值
+    entries: BTreeMap<(String, String), Vec<u8>>,
+}
+
+impl KeyValueStore for InMemoryKeyValueStore {
+    fn put(&mut self, namespace: &str, key: &str, value: &[u8]) -> Result<(), String> {
+        if let Some(mut previous) = self.entries.insert((namespace.to_string(), key.to_string()), value.to_vec()) {
+            previous.zeroize();
+        }
+        Ok(())
+    }
+
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, String> {
+        Ok(self.entries.get(&(namespace.to_string(), key.to_string())).cloned())
+    }
+
+    fn list(&self, namespace: &str) -> Result<Vec<String>, String> {
+        Ok(self
+            .entries
+            .range((namespace.to_string(), String::new())..)
+            .take_while(|((entry_namespace, _), _)| entry_namespace == namespace)
+            .map(|((_, key), _)| key.clone())
+            .collect())
+    }
+
+    fn remove(&mut self, namespace: &str, key: &str) -> Result<bool, String> {
+        let Some(mut value) = self.entries.remove(&(namespace.to_string(), key.to_string())) else {
+            return Ok(false);
+        };
+        value.zeroize();
+        Ok(true)
+    }
+}
+
+#[wasm_bindgen]
+impl InMemoryKeyValueStore {
+    /// 建立空的鍵值儲存
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 存入 (覆寫) 值
+    #[wasm_bindgen(js_name = put)]
+    pub fn put_js(&mut self, namespace: &str, key: &str, value: &[u8]) -> Result<(), JsError> {
+        self.put(namespace, key, value).map_err(|e| JsError::new(&e))
+    }
+
+    /// 讀取值
+    #[wasm_bindgen(js_name = get)]
+    pub fn get_js(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, JsError> {
+        self.get(namespace, key).map_err(|e| JsError::new(&e))
+    }
+
+    /// namespace 中所有的 key
+    #[wasm_bindgen(js_name = list)]
+    pub fn list_js(&self, namespace: &str) -> Result<Vec<String>, JsError> {
+        self.list(namespace).map_err(|e| JsError::new(&e))
+    }
+
+    /// 刪除值，回傳是否存在
+    #[wasm_bindgen(js_name = remove)]
+    pub fn remove_js(&mut self, namespace: &str, key: &str) -> Result<bool, JsError> {
+        self.remove(namespace, key).map_err(|e| JsError::new(&e))
+    }
+
+    /// 項目總數
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 以主儲存金鑰加密序列化
+    #[wasm_bindgen(js_name = serializeEncrypted)]
+    pub fn serialize_encrypted(&self, key: &StorageKey) -> Result<Vec<u8>, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .encode(SNAPSHOT_STORE, KV_SNAPSHOT_KEY, self)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密並還原
+    #[wasm_bindgen(js_name = deserializeEncrypted)]
+    pub fn deserialize_encrypted(bytes: &[u8], key: &StorageKey) -> Result<InMemoryKeyValueStore, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .decode(SNAPSHOT_STORE, KV_SNAPSHOT_KEY, bytes)
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_memory_key_value_store() {
+        let mut store = InMemoryKeyValueStore::new();
+        store.put("push", "fcm_token", b"token-1").unwrap();
+        store.put("push", "apns_token", b"token-2").unwrap();
+        store.put("server", "password", b"secret").unwrap();
+        store.put("push", "fcm_token", b"token-3").unwrap();
+
+        assert_eq!(store.get("push", "fcm_token").unwrap(), Some(b"token-3".to_vec()));
+        assert_eq!(store.get("server", "fcm_token").unwrap(), None);
+        assert_eq!(store.list("push").unwrap(), vec!["apns_token", "fcm_token"]);
+        assert!(store.list("pus").unwrap().is_empty());
+        assert!(store.remove("push", "apns_token").unwrap());
+        assert!(!store.remove("push", "apns_token").unwrap());
+
+        let key = StorageKey::generate();
+        let sealed = store.serialize_encrypted(&key).unwrap();
+        assert!(!sealed.windows(6).any(|window| window == b"secret"));
+        let restored = InMemoryKeyValueStore::deserialize_encrypted(&sealed, &key).unwrap();
+        assert_eq!(restored.get("server", "password").unwrap(), Some(b"secret".to_vec()));
+        assert_eq!(restored.size(), 2);
+    }
+}
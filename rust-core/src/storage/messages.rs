@@ -0,0 +1,468 @@
+//! 訊息儲存模組
+//!
+//! 以 `MessageStore` 抽象化訊息的持久化，訊息依 (對話 ID, 時間戳) 排序，
+//! 可分頁取得對話中較早的訊息。`body` 由呼叫端決定內容 (明文或已加密)
+//!
+//! 每個對話可設定保留政策 (`RetentionPolicy`)，由 `enforce_retention` 刪除
+//! 過期或超出數量的訊息。刪除時記憶體中的內容會清零；持久化後端以
+//! 每則訊息各自的包裝金鑰加密內容，刪除時銷毀金鑰，殘留的密文無法還原
+//!
+//! `InMemoryMessageStore` 在訊息寫入與刪除時通知訂閱者 (見 `events` 模組)，
+//! 並以遞增的變更序號記錄每則訊息最後寫入與刪除的時間點，供增量備份使用
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroize;
+
+use super::encryption::{RecordCodec, StorageKey, SNAPSHOT_STORE};
+use super::events::{StoreEvent, StoreEventKind, StoreObservers};
+
+const MESSAGE_SNAPSHOT_KEY: &str = "message_store";
+
+/// 儲存的訊息
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredMessage {
+    id: String,
+    conversation_id: String,
+    sender_id: String,
+    /// Unix 毫秒
+    timestamp: u64,
+    body: Vec<u8>,
+}
+
+impl StoredMessage {
+    /// 排序鍵：(對話 ID, 時間戳, 訊息 ID)
+    fn sort_key(&self) -> (String, u64, String) {
+        (self.conversation_id.clone(), self.timestamp, self.id.clone())
+    }
+}
+
+#[wasm_bindgen]
+impl StoredMessage {
+    #[wasm_bindgen(constructor)]
+    pub fn new(id: &str, conversation_id: &str, sender_id: &str, timestamp: u64, body: &[u8]) -> Self {
+        Self {
+            id: id.to_string(),
+            conversation_id: conversation_id.to_string(),
+            sender_id: sender_id.to_string(),
+            timestamp,
+            body: body.to_vec(),
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = conversationId)]
+    pub fn conversation_id(&self) -> String {
+        self.conversation_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = senderId)]
+    pub fn sender_id(&self) -> String {
+        self.sender_id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn body(&self) -> Vec<u8> {
+        self.body.clone()
+    }
+}
+
+/// 對話的訊息保留政策
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// 訊息保留時間 (毫秒)，以訊息時間戳計算
+    max_age_ms: Option<u64>,
+    /// 最多保留的訊息數量
+    max_messages: Option<u32>,
+}
+
+impl RetentionPolicy {
+    /// 依政策應刪除的訊息 (`messages` 依時間由舊到新排列)
+    pub(crate) fn expired<'a>(&self, messages: &'a [StoredMessage], now: u64) -> Vec<&'a StoredMessage> {
+        let excess = self
+            .max_messages
+            .map_or(0, |max| messages.len().saturating_sub(max as usize));
+        messages
+            .iter()
+            .enumerate()
+            .filter(|(i, message)| {
+                *i < excess || self.max_age_ms.is_some_and(|age| message.timestamp.saturating_add(age) <= now)
+            })
+            .map(|(_, message)| message)
+            .collect()
+    }
+}
+
+#[wasm_bindgen]
+impl RetentionPolicy {
+    /// 兩者皆未設定時不刪除任何訊息
+    #[wasm_bindgen(constructor)]
+    pub fn new(max_age_ms: Option<u64>, max_messages: Option<u32>) -> Self {
+        Self { max_age_ms, max_messages }
+    }
+
+    #[wasm_bindgen(getter, js_name = maxAgeMs)]
+    pub fn max_age_ms(&self) -> Option<u64> {
+        self.max_age_ms
+    }
+
+    #[wasm_bindgen(getter, js_name = maxMessages)]
+    pub fn max_messages(&self) -> Option<u32> {
+        self.max_messages
+    }
+}
+
+/// 訊息儲存
+pub trait MessageStore {
+    /// 儲存 (覆寫同 ID 的) 訊息
+    fn store_message(&mut self, message: &StoredMessage) -> Result<(), String>;
+
+    /// 載入訊息
+    fn load_message(&self, id: &str) -> Result<Option<StoredMessage>, String>;
+
+    /// 刪除訊息，回傳是否存在
+    fn remove_message(&mut self, id: &str) -> Result<bool, String>;
+
+    /// 對話中時間戳早於 `before` 的最新 `limit` 則訊息，依時間由舊到新排列
+    ///
+    /// `before` 為 None 時從最新的訊息開始，`limit` 為 None 時不限數量
+    fn conversation_messages(
+        &self,
+        conversation_id: &str,
+        before: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<StoredMessage>, String>;
+
+    /// 設定對話的保留政策，None 為移除
+    fn set_retention_policy(&mut self, conversation_id: &str, policy: Option<RetentionPolicy>) -> Result<(), String>;
+
+    /// 所有設定了保留政策的對話
+    fn retention_policies(&self) -> Result<Vec<(String, RetentionPolicy)>, String>;
+
+    /// 刪除所有超出保留政策的訊息，回傳被刪除的訊息 ID
+    fn enforce_retention(&mut self, now: u64) -> Result<Vec<String>, String> {
+        let mut removed = Vec::new();
+        for (conversation_id, policy) in self.retention_policies()? {
+            let messages = self.conversation_messages(&conversation_id, None, None)?;
+            for message in policy.expired(&messages, now) {
+                if self.remove_message(&message.id)? {
+                    removed.push(message.id.clone());
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// 非同步訊息儲存 (IndexedDB 等只能非同步存取的後端)
+#[allow(async_fn_in_trait)]
+pub trait AsyncMessageStore {
+    async fn store_message(&mut self, message: &StoredMessage) -> Result<(), String>;
+    async fn load_message(&self, id: &str) -> Result<Option<StoredMessage>, String>;
+    async fn remove_message(&mut self, id: &str) -> Result<bool, String>;
+    async fn conversation_messages(
+        &self,
+        conversation_id: &str,
+        before: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<StoredMessage>, String>;
+    async fn set_retention_policy(&mut self, conversation_id: &str, policy: Option<RetentionPolicy>) -> Result<(), String>;
+    async fn retention_policies(&self) -> Result<Vec<(String, RetentionPolicy)>, String>;
+
+    async fn enforce_retention(&mut self, now: u64) -> Result<Vec<String>, String> {
+        let mut removed = Vec::new();
+        for (conversation_id, policy) in self.retention_policies().await? {
+            let messages = self.conversation_messages(&conversation_id, None, None).await?;
+            for message in policy.expired(&messages, now) {
+                if self.remove_message(&message.id).await? {
+                    removed.push(message.id.clone());
+                }
+            }
+        }
+        Ok(removed)
+    }
+}
+
+/// 記憶體訊息儲存
+#[wasm_bindgen]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct InMemoryMessageStore {
+    /// (conversation_id, timestamp, id) -> 訊息
+    messages: BTreeMap<(String, u64, String), StoredMessage>,
+    /// id -> 排序鍵
+    index: BTreeMap<String, (String, u64, String)>,
+    /// conversation_id -> 保留政策
+    retention: BTreeMap<String, RetentionPolicy>,
+    /// 變更序號的來源識別，不同實例的序號不可混用
+    #[serde(default)]
+    epoch: u64,
+    /// 最後一次變更的序號
+    #[serde(default)]
+    revision: u64,
+    /// id -> 最後寫入時的序號
+    #[serde(default)]
+    revisions: BTreeMap<String, u64>,
+    /// 已刪除訊息的 id -> 刪除時的序號
+    #[serde(default)]
+    tombstones: BTreeMap<String, u64>,
+    /// 變更訂閱者，不序列化
+    #[serde(skip)]
+    observers: StoreObservers,
+}
+
+impl MessageStore for InMemoryMessageStore {
+    fn store_message(&mut self, message: &StoredMessage) -> Result<(), String> {
+        self.take_message(&message.id);
+        self.index.insert(message.id.clone(), message.sort_key());
+        self.messages.insert(message.sort_key(), message.clone());
+        self.revision += 1;
+        self.revisions.insert(message.id.clone(), self.revision);
+        self.tombstones.remove(&message.id);
+        self.observers
+            .emit(StoreEvent::message(StoreEventKind::MessageStored, &message.id, &message.conversation_id));
+        Ok(())
+    }
+
+    fn load_message(&self, id: &str) -> Result<Option<StoredMessage>, String> {
+        Ok(self.index.get(id).and_then(|key| self.messages.get(key)).cloned())
+    }
+
+    fn remove_message(&mut self, id: &str) -> Result<bool, String> {
+        let Some(conversation_id) = self.take_message(id) else {
+            return Ok(false);
+        };
+        self.revision += 1;
+        self.revisions.remove(id);
+        self.tombstones.insert(id.to_string(), self.revision);
+        self.observers
+            .emit(StoreEvent::message(StoreEventKind::MessageRemoved, id, &conversation_id));
+        Ok(true)
+    }
+
+    fn conversation_messages(
+        &self,
+        conversation_id: &str,
+        before: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<StoredMessage>, String> {
+        let lower = (conversation_id.to_string(), 0, String::new());
+        let mut messages: Vec<StoredMessage> = self
+            .messages
+            .range(lower..)
+            .take_while(|((conversation, timestamp, _), _)| {
+                conversation == conversation_id && before.is_none_or(|before| *timestamp < before)
+            })
+            .map(|(_, message)| message.clone())
+            .collect();
+        if let Some(limit) = limit {
+            messages.drain(..messages.len().saturating_sub(limit as usize));
+        }
+        Ok(messages)
+    }
+
+    fn set_retention_policy(&mut self, conversation_id: &str, policy: Option<RetentionPolicy>) -> Result<(), String> {
+        match policy {
+            Some(policy) => self.retention.insert(conversation_id.to_string(), policy),
+            None => self.retention.remove(conversation_id),
+        };
+        Ok(())
+    }
+
+    fn retention_policies(&self) -> Result<Vec<(String, RetentionPolicy)>, String> {
+        Ok(self.retention.iter().map(|(id, policy)| (id.clone(), *policy)).collect())
+    }
+}
+
+impl InMemoryMessageStore {
+    /// 變更訂閱者 (Rust 端訂閱用)
+    pub fn observers(&self) -> &StoreObservers {
+        &self.observers
+    }
+
+    /// 所有訊息，依 (對話, 時間戳, ID) 排序
+    pub(crate) fn messages(&self) -> impl Iterator<Item = &StoredMessage> {
+        self.messages.values()
+    }
+
+    /// 變更序號的來源識別與最後一次變更的序號
+    pub(crate) fn revision(&self) -> (u64, u64) {
+        (self.epoch, self.revision)
+    }
+
+    /// 序號 `since` 之後寫入的訊息
+    pub(crate) fn messages_since(&self, since: u64) -> impl Iterator<Item = &StoredMessage> {
+        self.messages
+            .values()
+            .filter(move |message| self.revisions.get(&message.id).is_some_and(|revision| *revision > since))
+    }
+
+    /// 序號 `since` 之後刪除的訊息 ID
+    pub(crate) fn removed_since(&self, since: u64) -> impl Iterator<Item = &str> {
+        self.tombstones
+            .iter()
+            .filter(move |(_, revision)| **revision > since)
+            .map(|(id, _)| id.as_str())
+    }
+
+    /// 丟棄序號 `through` (含) 之前的刪除記錄，回傳丟棄的數量
+    pub(crate) fn prune_tombstones(&mut self, through: u64) -> usize {
+        let before = self.tombstones.len();
+        self.tombstones.retain(|_, revision| *revision > through);
+        before - self.tombstones.len()
+    }
+
+    /// 移除訊息並清零內容，回傳其對話 ID (不發出通知)
+    fn take_message(&mut self, id: &str) -> Option<String> {
+        let mut message = self.index.remove(id).and_then(|key| self.messages.remove(&key))?;
+        // 刪除的內容不留在記憶體中
+        message.body.zeroize();
+        Some(message.conversation_id)
+    }
+}
+
+#[wasm_bindgen]
+impl InMemoryMessageStore {
+    /// 建立空的訊息儲存
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self { epoch: rand::random(), ..Self::default() }
+    }
+
+    /// 儲存訊息
+    #[wasm_bindgen(js_name = storeMessage)]
+    pub fn store_message_js(&mut self, message: &StoredMessage) -> Result<(), JsError> {
+        self.store_message(message).map_err(|e| JsError::new(&e))
+    }
+
+    /// 載入訊息
+    #[wasm_bindgen(js_name = loadMessage)]
+    pub fn load_message_js(&self, id: &str) -> Result<Option<StoredMessage>, JsError> {
+        self.load_message(id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 刪除訊息
+    #[wasm_bindgen(js_name = removeMessage)]
+    pub fn remove_message_js(&mut self, id: &str) -> Result<bool, JsError> {
+        self.remove_message(id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 對話中的訊息 (分頁)
+    #[wasm_bindgen(js_name = conversationMessages)]
+    pub fn conversation_messages_js(
+        &self,
+        conversation_id: &str,
+        before: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<StoredMessage>, JsError> {
+        self.conversation_messages(conversation_id, before, limit).map_err(|e| JsError::new(&e))
+    }
+
+    /// 設定對話的保留政策，未提供時移除
+    #[wasm_bindgen(js_name = setRetentionPolicy)]
+    pub fn set_retention_policy_js(&mut self, conversation_id: &str, policy: Option<RetentionPolicy>) -> Result<(), JsError> {
+        self.set_retention_policy(conversation_id, policy).map_err(|e| JsError::new(&e))
+    }
+
+    /// 刪除所有超出保留政策的訊息，回傳被刪除的訊息 ID
+    #[wasm_bindgen(js_name = enforceRetention)]
+    pub fn enforce_retention_js(&mut self, now: u64) -> Result<Vec<String>, JsError> {
+        self.enforce_retention(now).map_err(|e| JsError::new(&e))
+    }
+
+    /// 訂閱訊息寫入與刪除：`(event: StoreEvent) => void`，回傳取消訂閱用的 ID
+    #[wasm_bindgen(js_name = onChange)]
+    pub fn on_change(&self, callback: js_sys::Function) -> u32 {
+        self.observers.subscribe_js(callback)
+    }
+
+    /// 取消訂閱，回傳是否存在
+    #[wasm_bindgen(js_name = offChange)]
+    pub fn off_change(&self, id: u32) -> bool {
+        self.observers.unsubscribe(id)
+    }
+
+    /// 訊息總數
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.index.len()
+    }
+
+    /// 以主儲存金鑰加密序列化
+    #[wasm_bindgen(js_name = serializeEncrypted)]
+    pub fn serialize_encrypted(&self, key: &StorageKey) -> Result<Vec<u8>, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .encode(SNAPSHOT_STORE, MESSAGE_SNAPSHOT_KEY, self)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密並還原
+    #[wasm_bindgen(js_name = deserializeEncrypted)]
+    pub fn deserialize_encrypted(bytes: &[u8], key: &StorageKey) -> Result<InMemoryMessageStore, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .decode(SNAPSHOT_STORE, MESSAGE_SNAPSHOT_KEY, bytes)
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversation_paging() {
+        let mut store = InMemoryMessageStore::new();
+        for (i, timestamp) in [30u64, 10, 20, 40].iter().enumerate() {
+            let message = StoredMessage::new(&format!("m{}", i), "chat", "bob", *timestamp, b"hi");
+            store.store_message(&message).unwrap();
+        }
+        store.store_message(&StoredMessage::new("other", "other-chat", "bob", 15, b"x")).unwrap();
+
+        let timestamps = |messages: Vec<StoredMessage>| messages.iter().map(|m| m.timestamp()).collect::<Vec<_>>();
+        assert_eq!(timestamps(store.conversation_messages("chat", None, None).unwrap()), vec![10, 20, 30, 40]);
+        assert_eq!(timestamps(store.conversation_messages("chat", None, Some(2)).unwrap()), vec![30, 40]);
+        assert_eq!(timestamps(store.conversation_messages("chat", Some(30), Some(5)).unwrap()), vec![10, 20]);
+
+        // 覆寫時移到新的時間戳
+        store.store_message(&StoredMessage::new("m1", "chat", "bob", 50, b"edited")).unwrap();
+        assert_eq!(timestamps(store.conversation_messages("chat", None, None).unwrap()), vec![20, 30, 40, 50]);
+        assert_eq!(store.load_message("m1").unwrap().unwrap().body(), b"edited");
+        assert_eq!(store.size(), 5);
+
+        assert!(store.remove_message("m1").unwrap());
+        assert!(!store.remove_message("m1").unwrap());
+        assert!(store.load_message("m1").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_retention_policy() {
+        let mut store = InMemoryMessageStore::new();
+        for timestamp in 1..=5u64 {
+            store.store_message(&StoredMessage::new(&format!("a{}", timestamp), "a", "bob", timestamp * 1000, b"x")).unwrap();
+            store.store_message(&StoredMessage::new(&format!("b{}", timestamp), "b", "bob", timestamp * 1000, b"x")).unwrap();
+        }
+        store.set_retention_policy("a", Some(RetentionPolicy::new(None, Some(2)))).unwrap();
+        store.set_retention_policy("b", Some(RetentionPolicy::new(Some(2500), None))).unwrap();
+
+        // a：只留最新 2 則；b：時間戳 + 2.5 秒 <= 5 秒的訊息過期
+        assert_eq!(store.enforce_retention(5000).unwrap(), vec!["a1", "a2", "a3", "b1", "b2"]);
+        assert_eq!(store.size(), 5);
+        assert!(store.enforce_retention(5000).unwrap().is_empty());
+
+        store.set_retention_policy("a", None).unwrap();
+        assert_eq!(store.retention_policies().unwrap().len(), 1);
+        assert_eq!(store.enforce_retention(10_000).unwrap(), vec!["b3", "b4", "b5"]);
+    }
+}
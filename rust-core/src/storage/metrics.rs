@@ -0,0 +1,253 @@
+//! 本機統計計數模組
+//!
+//! 只記錄次數 (送出 / 收到的訊息、建立的會話、解密失敗)，供客戶端顯示使用統計與
+//! 健康診斷，不需要明文 log 或外部遙測：
+//! - 不記錄聯絡人、對話或訊息 ID，也不記錄個別事件的時間，只保留每日的合計
+//! - 每日合計只保留 `retention_days` 天，累計總數不受影響
+//! - 以 `serializeEncrypted` 與其他記憶體儲存一樣以主儲存金鑰加密持久化
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use super::encryption::{RecordCodec, StorageKey, SNAPSHOT_STORE};
+
+const METRICS_SNAPSHOT_KEY: &str = "metrics";
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// 預設保留每日合計的天數
+pub const DEFAULT_METRICS_RETENTION_DAYS: u32 = 90;
+
+/// 計數項目
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Metric {
+    MessagesSent = 0,
+    MessagesReceived = 1,
+    SessionsCreated = 2,
+    DecryptFailures = 3,
+}
+
+impl Metric {
+    /// 所有計數項目
+    pub const ALL: [Metric; 4] =
+        [Metric::MessagesSent, Metric::MessagesReceived, Metric::SessionsCreated, Metric::DecryptFailures];
+
+    /// JSON 摘要中的名稱
+    fn name(self) -> &'static str {
+        match self {
+            Metric::MessagesSent => "messages_sent",
+            Metric::MessagesReceived => "messages_received",
+            Metric::SessionsCreated => "sessions_created",
+            Metric::DecryptFailures => "decrypt_failures",
+        }
+    }
+}
+
+/// 本機統計計數儲存
+pub trait MetricsStore {
+    /// 在 `now` (Unix 毫秒) 所屬的日期累加次數
+    fn increment(&mut self, metric: Metric, now: u64, amount: u64) -> Result<(), String>;
+
+    /// 累計總數
+    fn total(&self, metric: Metric) -> Result<u64, String>;
+
+    /// `[since, until)` 期間的次數，以日為單位 (包含兩端所屬的整天)，只涵蓋仍保留的每日合計
+    fn count(&self, metric: Metric, since: u64, until: u64) -> Result<u64, String>;
+
+    /// 清除所有計數
+    fn reset(&mut self) -> Result<(), String>;
+}
+
+/// 記憶體統計計數，以 `serializeEncrypted` 持久化
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InMemoryMetrics {
+    totals: BTreeMap<Metric, u64>,
+    /// (項目, 日期序號) -> 當日次數
+    daily: BTreeMap<(Metric, u64), u64>,
+    retention_days: u32,
+}
+
+impl Default for InMemoryMetrics {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl InMemoryMetrics {
+    fn day(timestamp: u64) -> u64 {
+        timestamp / DAY_MS
+    }
+
+    /// 丟棄超出保留天數的每日合計
+    fn prune(&mut self, today: u64) {
+        let oldest = today.saturating_sub(self.retention_days.saturating_sub(1) as u64);
+        self.daily.retain(|(_, day), _| *day >= oldest);
+    }
+}
+
+impl MetricsStore for InMemoryMetrics {
+    fn increment(&mut self, metric: Metric, now: u64, amount: u64) -> Result<(), String> {
+        let today = Self::day(now);
+        let total = self.totals.entry(metric).or_default();
+        *total = total.saturating_add(amount);
+        let daily = self.daily.entry((metric, today)).or_default();
+        *daily = daily.saturating_add(amount);
+        self.prune(today);
+        Ok(())
+    }
+
+    fn total(&self, metric: Metric) -> Result<u64, String> {
+        Ok(self.totals.get(&metric).copied().unwrap_or(0))
+    }
+
+    fn count(&self, metric: Metric, since: u64, until: u64) -> Result<u64, String> {
+        if until <= since {
+            return Ok(0);
+        }
+        Ok(self
+            .daily
+            .range((metric, Self::day(since))..=(metric, Self::day(until - 1)))
+            .map(|(_, count)| *count)
+            .fold(0, u64::saturating_add))
+    }
+
+    fn reset(&mut self) -> Result<(), String> {
+        self.totals.clear();
+        self.daily.clear();
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl InMemoryMetrics {
+    /// 建立空的計數，`retention_days` 預設為 90 天 (至少 1 天)
+    #[wasm_bindgen(constructor)]
+    pub fn new(retention_days: Option<u32>) -> Self {
+        Self {
+            totals: BTreeMap::new(),
+            daily: BTreeMap::new(),
+            retention_days: retention_days.unwrap_or(DEFAULT_METRICS_RETENTION_DAYS).max(1),
+        }
+    }
+
+    /// 累加一次
+    #[wasm_bindgen(js_name = increment)]
+    pub fn increment_js(&mut self, metric: Metric, now: u64) -> Result<(), JsError> {
+        self.increment(metric, now, 1).map_err(|e| JsError::new(&e))
+    }
+
+    /// 累加 `amount` 次
+    #[wasm_bindgen(js_name = incrementBy)]
+    pub fn increment_by_js(&mut self, metric: Metric, now: u64, amount: u64) -> Result<(), JsError> {
+        self.increment(metric, now, amount).map_err(|e| JsError::new(&e))
+    }
+
+    /// 累計總數
+    #[wasm_bindgen(js_name = total)]
+    pub fn total_js(&self, metric: Metric) -> Result<u64, JsError> {
+        self.total(metric).map_err(|e| JsError::new(&e))
+    }
+
+    /// `[since, until)` 期間的次數 (以日為單位)
+    #[wasm_bindgen(js_name = count)]
+    pub fn count_js(&self, metric: Metric, since: u64, until: u64) -> Result<u64, JsError> {
+        self.count(metric, since, until).map_err(|e| JsError::new(&e))
+    }
+
+    /// 到 `now` 為止最近 `days` 天的每日次數，由舊到新排列 (圖表用)
+    pub fn daily(&self, metric: Metric, now: u64, days: u32) -> Vec<u64> {
+        let today = Self::day(now);
+        (0..days as u64)
+            .rev()
+            .map(|ago| {
+                today
+                    .checked_sub(ago)
+                    .and_then(|day| self.daily.get(&(metric, day)).copied())
+                    .unwrap_or(0)
+            })
+            .collect()
+    }
+
+    /// `[since, until)` 期間收到的訊息中解密失敗的比例，沒有收到任何訊息時為 0
+    #[wasm_bindgen(js_name = decryptFailureRate)]
+    pub fn decrypt_failure_rate(&self, since: u64, until: u64) -> Result<f64, JsError> {
+        let failures = self.count(Metric::DecryptFailures, since, until).map_err(|e| JsError::new(&e))?;
+        let received = self.count(Metric::MessagesReceived, since, until).map_err(|e| JsError::new(&e))?;
+        let attempts = failures.saturating_add(received);
+        Ok(if attempts == 0 { 0.0 } else { failures as f64 / attempts as f64 })
+    }
+
+    /// 以 JSON 表示的累計總數 (例如 `{"messages_sent":3,...}`)
+    pub fn summary(&self) -> Result<String, JsError> {
+        let totals: BTreeMap<&str, u64> = Metric::ALL
+            .iter()
+            .map(|metric| (metric.name(), self.totals.get(metric).copied().unwrap_or(0)))
+            .collect();
+        serde_json::to_string(&totals).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 清除所有計數
+    #[wasm_bindgen(js_name = reset)]
+    pub fn reset_js(&mut self) -> Result<(), JsError> {
+        self.reset().map_err(|e| JsError::new(&e))
+    }
+
+    #[wasm_bindgen(getter, js_name = retentionDays)]
+    pub fn retention_days(&self) -> u32 {
+        self.retention_days
+    }
+
+    /// 以主儲存金鑰加密序列化
+    #[wasm_bindgen(js_name = serializeEncrypted)]
+    pub fn serialize_encrypted(&self, key: &StorageKey) -> Result<Vec<u8>, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .encode(SNAPSHOT_STORE, METRICS_SNAPSHOT_KEY, self)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密並還原
+    #[wasm_bindgen(js_name = deserializeEncrypted)]
+    pub fn deserialize_encrypted(bytes: &[u8], key: &StorageKey) -> Result<InMemoryMetrics, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .decode(SNAPSHOT_STORE, METRICS_SNAPSHOT_KEY, bytes)
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metrics_counters() {
+        let mut metrics = InMemoryMetrics::new(Some(3));
+        metrics.increment(Metric::MessagesSent, 10, 1).unwrap();
+        metrics.increment(Metric::MessagesSent, DAY_MS + 5, 2).unwrap();
+        metrics.increment(Metric::MessagesReceived, DAY_MS, 3).unwrap();
+        metrics.increment(Metric::DecryptFailures, DAY_MS, 1).unwrap();
+
+        assert_eq!(metrics.total(Metric::MessagesSent).unwrap(), 3);
+        assert_eq!(metrics.count(Metric::MessagesSent, DAY_MS, 2 * DAY_MS).unwrap(), 2);
+        assert_eq!(metrics.count(Metric::MessagesSent, 0, 2 * DAY_MS).unwrap(), 3);
+        assert_eq!(metrics.daily(Metric::MessagesSent, DAY_MS, 3), vec![0, 1, 2]);
+        assert_eq!(metrics.decrypt_failure_rate(0, 2 * DAY_MS).unwrap(), 0.25);
+
+        // 超出保留天數的每日合計被丟棄，總數不變
+        metrics.increment(Metric::MessagesSent, 3 * DAY_MS, 1).unwrap();
+        assert_eq!(metrics.count(Metric::MessagesSent, 0, 4 * DAY_MS).unwrap(), 3);
+        assert_eq!(metrics.total(Metric::MessagesSent).unwrap(), 4);
+
+        let key = StorageKey::generate();
+        let mut restored =
+            InMemoryMetrics::deserialize_encrypted(&metrics.serialize_encrypted(&key).unwrap(), &key).unwrap();
+        assert_eq!(
+            restored.summary().unwrap(),
+            r#"{"decrypt_failures":1,"messages_received":3,"messages_sent":4,"sessions_created":0}"#
+        );
+        restored.reset().unwrap();
+        assert_eq!(restored.total(Metric::MessagesReceived).unwrap(), 0);
+    }
+}
@@ -1,11 +1,98 @@
 //! 儲存模組
 //!
 //! 包含：
-//! - sql.js 資料庫綁定
-//! - Schema 定義
-//! - 銷毀引擎
-//!
-//! TODO: Phase 2 實作
+//! - 身份信任儲存 (TOFU)
+//! - 多帳號金鑰庫
+//! - 會話管理 (bundle 快取)
+//! - 金鑰庫快照
+//! - 協定儲存介面 (會話、身份、預金鑰) 與記憶體實作
+//! - 訊息儲存與盲化搜尋索引
+//! - 附件 blob 儲存 (串流加密分段)
+//! - 安全鍵值儲存 (應用層秘密)
+//! - 頭像快取 (依密文摘要去重)
+//! - 加密設定儲存 (型別化偏好設定、變更通知)
+//! - 排程傳送佇列 (送出時間、重試政策)
+//! - 給自己的筆記 (自我會話、專用筆記金鑰)
+//! - 本機統計計數 (每日合計、無明文 log)
+//! - 加密聯絡人儲存 (身份、驗證狀態、暱稱)
+//! - 群組成員儲存 (成員、裝置、sender key 參照、epoch)
+//! - 跨儲存交易
+//! - 儲存變更通知
+//! - 儲存配額管理與自動清理
+//! - 靜態加密 (主儲存金鑰)
+//! - 儲存完整性檢查與修復
+//! - 完整加密備份 (匯出 / 匯入)
+//! - 單一對話的可攜加密匯出 / 匯入
+//! - Signal 備份匯入 (聯絡人、對話、文字訊息)
+//! - IndexedDB 儲存 (feature = "indexeddb")
+//! - 多分頁協調 (Web Locks、跨分頁變更通知，feature = "indexeddb")
+//! - OPFS 附件儲存 (feature = "opfs")
+//! - SQLite 儲存 (feature = "sqlite"，僅原生建置)
+
+pub mod trust;
+pub mod keystore;
+pub mod session_manager;
+pub mod snapshot;
+pub mod store;
+pub mod messages;
+pub mod encryption;
+pub mod integrity;
+pub mod search;
+pub mod blobs;
+pub mod kv;
+pub mod avatars;
+pub mod settings;
+pub mod outbox;
+pub mod notes;
+pub mod metrics;
+pub mod contacts;
+pub mod groups;
+pub mod transaction;
+pub mod events;
+pub mod quota;
+mod archive;
+pub mod backup;
+pub mod chat_export;
+pub mod signal_import;
+#[cfg(feature = "indexeddb")]
+pub mod indexeddb;
+#[cfg(feature = "indexeddb")]
+mod tabs;
+#[cfg(feature = "opfs")]
+pub mod opfs;
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+pub mod sqlite;
+
+pub use trust::*;
+pub use keystore::*;
+pub use session_manager::*;
+pub use snapshot::*;
+pub use store::*;
+pub use messages::*;
+pub use encryption::*;
+pub use integrity::*;
+pub use search::*;
+pub use blobs::*;
+pub use kv::*;
+pub use avatars::*;
+pub use settings::*;
+pub use outbox::*;
+pub use notes::*;
+pub use metrics::*;
+pub use contacts::*;
+pub use groups::*;
+pub use transaction::*;
+pub use events::*;
+pub use quota::*;
+pub use backup::*;
+pub use chat_export::*;
+pub use signal_import::*;
+#[cfg(feature = "indexeddb")]
+pub use indexeddb::*;
+#[cfg(feature = "opfs")]
+pub use opfs::*;
+#[cfg(all(feature = "sqlite", not(target_arch = "wasm32")))]
+pub use sqlite::*;
 
 // 暫時註解掉未實作的模組
 // pub mod db;
@@ -0,0 +1,222 @@
+//! 給自己的筆記 (Note to self) 模組
+//!
+//! 筆記是對話 ID 與發送者都是自己的一般訊息，不另外做明文特例：
+//! - `sendNote` 以與每台自己裝置之間的同步會話 (聯絡人 ID 為自己) 加密成
+//!   收件者為自己的 `SentTranscript`，並把筆記存入一般的訊息儲存
+//! - 其他裝置用一般的同步流程解密後交給 `receiveNote`，以相同的訊息 ID 存入
+//! - 只有一台裝置、或要讓之後連結的裝置取得舊筆記時，以 `sealNote` 用專用的
+//!   筆記金鑰加密後上傳。金鑰由身份私鑰衍生，共用同一身份的裝置都能以 `openNote` 解開
+//!
+//! 訊息 ID 由來源裝置 ID 與時間戳組成，同一則筆記從不同路徑收到時會覆寫而不會重複
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+use crate::crypto::{hkdf_sha256, IdentityKeyPair, SyncContent, SyncEnvelope, SyncMessage, XChaChaCipher};
+use super::messages::{InMemoryMessageStore, MessageStore, StoredMessage};
+use super::store::{InMemoryProtocolStore, SessionStore};
+
+const NOTE_KEY_INFO: &[u8] = b"SafeTalk_NoteToSelf_v1";
+
+/// `sealNote` 加密的內容
+#[derive(Serialize, Deserialize)]
+struct SealedNote {
+    id: String,
+    timestamp: u64,
+    body: Vec<u8>,
+}
+
+/// 自我會話：本機裝置寫給自己的筆記
+#[wasm_bindgen]
+pub struct SelfSession {
+    own_id: String,
+    device_id: u32,
+    note_key: Zeroizing<Vec<u8>>,
+}
+
+impl SelfSession {
+    /// 筆記的訊息 ID
+    fn note_id(device_id: u32, timestamp: u64) -> String {
+        format!("note:{}:{}", device_id, timestamp)
+    }
+
+    fn note(&self, id: &str, timestamp: u64, body: &[u8]) -> StoredMessage {
+        StoredMessage::new(id, &self.own_id, &self.own_id, timestamp, body)
+    }
+
+    /// 寫一則筆記：加密給自己的其他裝置並存入訊息儲存 (Rust 端使用)
+    ///
+    /// 回傳每台其他裝置各一個信封，沒有其他裝置時為空
+    pub fn send_note<S: SessionStore, M: MessageStore>(
+        &self,
+        sessions: &mut S,
+        messages: &mut M,
+        timestamp: u64,
+        body: &[u8],
+    ) -> Result<Vec<SyncEnvelope>, JsError> {
+        let transcript = SyncMessage::sent_transcript(&self.own_id, timestamp, body);
+        let mut envelopes = Vec::new();
+        for device_id in sessions.session_devices(&self.own_id).map_err(|e| JsError::new(&e))? {
+            if device_id == self.device_id {
+                continue;
+            }
+            let Some(mut session) = sessions
+                .load_session(&self.own_id, device_id)
+                .map_err(|e| JsError::new(&e))?
+            else {
+                continue;
+            };
+            envelopes.push(transcript.encrypt_for_device(&mut session, self.device_id, device_id)?);
+            sessions
+                .store_session(&self.own_id, device_id, &session)
+                .map_err(|e| JsError::new(&e))?;
+        }
+        messages
+            .store_message(&self.note(&Self::note_id(self.device_id, timestamp), timestamp, body))
+            .map_err(|e| JsError::new(&e))?;
+        Ok(envelopes)
+    }
+
+    /// 處理已解密的同步訊息，是筆記時存入訊息儲存並回傳 (Rust 端使用)
+    pub fn receive_note<M: MessageStore>(
+        &self,
+        messages: &mut M,
+        source_device_id: u32,
+        message: &SyncMessage,
+    ) -> Result<Option<StoredMessage>, String> {
+        let SyncContent::SentTranscript { destination, timestamp, body } = message.content() else {
+            return Ok(None);
+        };
+        if *destination != self.own_id {
+            return Ok(None);
+        }
+        let note = self.note(&Self::note_id(source_device_id, *timestamp), *timestamp, body);
+        messages.store_message(&note)?;
+        Ok(Some(note))
+    }
+
+    /// 以筆記金鑰加密筆記 (Rust 端使用)
+    pub fn seal_note(&self, note: &StoredMessage) -> Result<Vec<u8>, String> {
+        if note.conversation_id() != self.own_id {
+            return Err("Not a note to self".to_string());
+        }
+        let sealed = SealedNote { id: note.id(), timestamp: note.timestamp(), body: note.body() };
+        let plaintext = Zeroizing::new(serde_json::to_vec(&sealed).map_err(|e| e.to_string())?);
+        XChaChaCipher::from_key(&self.note_key)?.seal_random(self.own_id.as_bytes(), &plaintext)
+    }
+
+    /// 解密 `seal_note` 的密文 (Rust 端使用)
+    pub fn open_note(&self, sealed: &[u8]) -> Result<StoredMessage, String> {
+        let plaintext = Zeroizing::new(XChaChaCipher::from_key(&self.note_key)?.open(self.own_id.as_bytes(), sealed)?);
+        let note: SealedNote = serde_json::from_slice(&plaintext).map_err(|e| e.to_string())?;
+        Ok(self.note(&note.id, note.timestamp, &note.body))
+    }
+}
+
+#[wasm_bindgen]
+impl SelfSession {
+    /// 以身份金鑰建立本機裝置的自我會話
+    #[wasm_bindgen(constructor)]
+    pub fn new(identity: &IdentityKeyPair, own_id: &str, device_id: u32) -> Result<SelfSession, JsError> {
+        let note_key = hkdf_sha256(
+            &identity.public_key_bytes(),
+            identity.private_key_bytes().expose(),
+            NOTE_KEY_INFO,
+            32,
+        )
+        .map_err(|e| JsError::new(&e))?;
+        Ok(Self { own_id: own_id.to_string(), device_id, note_key: Zeroizing::new(note_key) })
+    }
+
+    #[wasm_bindgen(getter, js_name = ownId)]
+    pub fn own_id(&self) -> String {
+        self.own_id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = deviceId)]
+    pub fn device_id(&self) -> u32 {
+        self.device_id
+    }
+
+    /// 同步訊息是否為筆記
+    #[wasm_bindgen(js_name = isNote)]
+    pub fn is_note(&self, message: &SyncMessage) -> bool {
+        matches!(message.content(), SyncContent::SentTranscript { destination, .. } if *destination == self.own_id)
+    }
+
+    /// 寫一則筆記，回傳要送給其他裝置的信封
+    #[wasm_bindgen(js_name = sendNote)]
+    pub fn send_note_js(
+        &self,
+        sessions: &mut InMemoryProtocolStore,
+        messages: &mut InMemoryMessageStore,
+        timestamp: u64,
+        body: &[u8],
+    ) -> Result<Vec<SyncEnvelope>, JsError> {
+        self.send_note(sessions, messages, timestamp, body)
+    }
+
+    /// 處理已解密的同步訊息，不是筆記時回傳 undefined
+    #[wasm_bindgen(js_name = receiveNote)]
+    pub fn receive_note_js(
+        &self,
+        messages: &mut InMemoryMessageStore,
+        source_device_id: u32,
+        message: &SyncMessage,
+    ) -> Result<Option<StoredMessage>, JsError> {
+        self.receive_note(messages, source_device_id, message)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 以筆記金鑰加密筆記 (上傳給之後連結的裝置)
+    #[wasm_bindgen(js_name = sealNote)]
+    pub fn seal_note_js(&self, note: &StoredMessage) -> Result<Vec<u8>, JsError> {
+        self.seal_note(note).map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密 `sealNote` 的密文
+    #[wasm_bindgen(js_name = openNote)]
+    pub fn open_note_js(&self, sealed: &[u8]) -> Result<StoredMessage, JsError> {
+        self.open_note(sealed).map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::RatchetSession;
+
+    #[test]
+    fn test_note_to_self_roundtrip() {
+        let identity = IdentityKeyPair::new();
+        let phone = SelfSession::new(&identity, "alice", 1).unwrap();
+        let desktop = SelfSession::new(&identity, "alice", 2).unwrap();
+        let (phone_session, mut desktop_session) = RatchetSession::test_pair([9u8; 32]);
+
+        let mut phone_store = InMemoryProtocolStore::new(&identity, 1);
+        phone_store.store_session("alice", 2, &phone_session).unwrap();
+        let mut phone_messages = InMemoryMessageStore::new();
+        let mut desktop_messages = InMemoryMessageStore::new();
+
+        let envelopes = phone.send_note(&mut phone_store, &mut phone_messages, 1000, b"buy milk").unwrap();
+        assert_eq!(envelopes.len(), 1);
+        assert_eq!(envelopes[0].destination_device_id(), 2);
+
+        let sync = envelopes[0].decrypt(&mut desktop_session).unwrap();
+        assert!(desktop.is_note(&sync));
+        let note = desktop.receive_note(&mut desktop_messages, 1, &sync).unwrap().unwrap();
+        assert_eq!(Some(&note), phone_messages.load_message(&note.id()).unwrap().as_ref());
+        assert_eq!(desktop_messages.conversation_messages("alice", None, None).unwrap(), vec![note.clone()]);
+
+        // 其他收件者的副本不是筆記
+        let other = SyncMessage::sent_transcript("bob", 1000, b"hi");
+        assert!(desktop.receive_note(&mut desktop_messages, 1, &other).unwrap().is_none());
+
+        // 專用筆記金鑰：同一身份的裝置可解開，其他身份不行
+        let sealed = phone.seal_note(&note).unwrap();
+        assert_eq!(desktop.open_note(&sealed).unwrap(), note);
+        let stranger = SelfSession::new(&IdentityKeyPair::new(), "alice", 1).unwrap();
+        assert!(stranger.open_note(&sealed).is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! OPFS 附件儲存模組 (feature = "opfs")
+//!
+//! 以瀏覽器的 Origin Private File System 存放附件 blob，數百 MB 的媒體
+//! 不必經過 IndexedDB 的結構化複製與交易：
+//! - `<directory>/<blob>/<index>.chunk`，chunk 內容與其他後端相同 (見 `blobs` 模組)
+//! - 每個 chunk 以 `createWritable` 寫入，瀏覽器在 `close` 時才替換檔案，中斷時不會留下半個 chunk
+//! - 以主儲存金鑰開啟時 blob 目錄名稱盲化，檔案系統上看不到附件 ID
+//!
+//! 串流寫入 / 讀取使用 `OpfsBlobWriter`、`OpfsBlobReader`，用法與 IndexedDB 後端相同
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use js_sys::{ArrayBuffer, Promise, Uint8Array};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::{future_to_promise, JsFuture};
+use web_sys::{
+    Blob, DomException, FileSystemDirectoryHandle, FileSystemFileHandle, FileSystemGetDirectoryOptions,
+    FileSystemGetFileOptions, FileSystemRemoveOptions, FileSystemWritableFileStream, StorageManager,
+};
+
+use crate::crypto::CipherSuite;
+
+use super::blobs::{AsyncBlobStore, BlobReader, BlobWriter};
+use super::encryption::{RecordCodec, StorageKey};
+
+/// 未盲化時 blob ID 的最大長度
+const MAX_BLOB_ID_LENGTH: usize = 128;
+
+fn js_error(context: &str, error: JsValue) -> String {
+    format!("{}: {:?}", context, error)
+}
+
+/// 取得全域的 `navigator.storage` (window 與 worker 皆可)
+fn storage_manager() -> Option<StorageManager> {
+    let navigator = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("navigator")).ok()?;
+    js_sys::Reflect::get(&navigator, &JsValue::from_str("storage"))
+        .ok()
+        .filter(|storage| {
+            !storage.is_undefined()
+                && !storage.is_null()
+                && js_sys::Reflect::has(storage, &JsValue::from_str("getDirectory")).unwrap_or(false)
+        })
+        .map(JsCast::unchecked_into)
+}
+
+/// 等待 Promise；`NotFoundError` 回傳 None
+async fn resolve(promise: Promise, context: &str) -> Result<Option<JsValue>, String> {
+    match JsFuture::from(promise).await {
+        Ok(value) => Ok(Some(value)),
+        Err(error) if error.dyn_ref::<DomException>().is_some_and(|e| e.name() == "NotFoundError") => Ok(None),
+        Err(error) => Err(js_error(context, error)),
+    }
+}
+
+async fn directory_handle(parent: &FileSystemDirectoryHandle, name: &str, create: bool) -> Result<Option<FileSystemDirectoryHandle>, String> {
+    let options = FileSystemGetDirectoryOptions::new();
+    options.set_create(create);
+    let handle = resolve(parent.get_directory_handle_with_options(name, &options), "Failed to open OPFS directory").await?;
+    Ok(handle.map(JsCast::unchecked_into))
+}
+
+async fn file_handle(parent: &FileSystemDirectoryHandle, name: &str, create: bool) -> Result<Option<FileSystemFileHandle>, String> {
+    let options = FileSystemGetFileOptions::new();
+    options.set_create(create);
+    let handle = resolve(parent.get_file_handle_with_options(name, &options), "Failed to open OPFS file").await?;
+    Ok(handle.map(JsCast::unchecked_into))
+}
+
+async fn write_file(handle: &FileSystemFileHandle, bytes: &[u8]) -> Result<(), String> {
+    let writable: FileSystemWritableFileStream = JsFuture::from(handle.create_writable())
+        .await
+        .map_err(|e| js_error("Failed to open OPFS file for writing", e))?
+        .unchecked_into();
+    let written = match writable.write_with_u8_array(bytes) {
+        Ok(write) => JsFuture::from(write).await,
+        Err(error) => Err(error),
+    };
+    if let Err(error) = written {
+        // 放棄寫入，原本的檔案內容保持不變
+        let _ = JsFuture::from(writable.abort()).await;
+        return Err(js_error("Failed to write OPFS file", error));
+    }
+    JsFuture::from(writable.close())
+        .await
+        .map_err(|e| js_error("Failed to write OPFS file", e))?;
+    Ok(())
+}
+
+/// OPFS blob 儲存
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct OpfsBlobStore {
+    root: FileSystemDirectoryHandle,
+    codec: RecordCodec,
+}
+
+impl OpfsBlobStore {
+    /// 開啟 (不存在時建立) OPFS 根目錄下的 `directory` (Rust 端使用)
+    pub async fn open_directory(directory: &str, key: Option<StorageKey>) -> Result<Self, String> {
+        let storage = storage_manager().ok_or_else(|| "OPFS is not available".to_string())?;
+        let origin_root: FileSystemDirectoryHandle = JsFuture::from(storage.get_directory())
+            .await
+            .map_err(|e| js_error("Failed to open OPFS", e))?
+            .unchecked_into();
+        let root = directory_handle(&origin_root, directory, true)
+            .await?
+            .ok_or_else(|| "Failed to open OPFS directory".to_string())?;
+        Ok(Self { root, codec: RecordCodec::new(key) })
+    }
+
+    /// blob 的目錄名稱：有金鑰時盲化，否則只接受英數字、`-` 與 `_`
+    fn blob_name(&self, blob_id: &str) -> Result<String, String> {
+        let name = self.codec.blind("blob", blob_id);
+        let valid = !name.is_empty()
+            && name.len() <= MAX_BLOB_ID_LENGTH
+            && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_');
+        if !valid {
+            return Err(format!("Invalid blob ID: {}", blob_id));
+        }
+        Ok(name)
+    }
+
+    fn chunk_name(index: u32) -> String {
+        format!("{:08}.chunk", index)
+    }
+}
+
+impl AsyncBlobStore for OpfsBlobStore {
+    async fn put_chunk(&mut self, blob_id: &str, index: u32, bytes: &[u8]) -> Result<(), String> {
+        let dir = directory_handle(&self.root, &self.blob_name(blob_id)?, true)
+            .await?
+            .ok_or_else(|| "Failed to create OPFS blob directory".to_string())?;
+        let handle = file_handle(&dir, &Self::chunk_name(index), true)
+            .await?
+            .ok_or_else(|| "Failed to create OPFS blob chunk".to_string())?;
+        write_file(&handle, bytes).await
+    }
+
+    async fn get_chunk(&self, blob_id: &str, index: u32) -> Result<Option<Vec<u8>>, String> {
+        let Some(dir) = directory_handle(&self.root, &self.blob_name(blob_id)?, false).await? else {
+            return Ok(None);
+        };
+        let Some(handle) = file_handle(&dir, &Self::chunk_name(index), false).await? else {
+            return Ok(None);
+        };
+        let Some(blob) = resolve(handle.get_file(), "Failed to read OPFS blob chunk").await? else {
+            return Ok(None);
+        };
+        let buffer: ArrayBuffer = JsFuture::from(blob.unchecked_into::<Blob>().array_buffer())
+            .await
+            .map_err(|e| js_error("Failed to read OPFS blob chunk", e))?
+            .unchecked_into();
+        Ok(Some(Uint8Array::new(&buffer).to_vec()))
+    }
+
+    async fn remove_blob(&mut self, blob_id: &str) -> Result<bool, String> {
+        let options = FileSystemRemoveOptions::new();
+        options.set_recursive(true);
+        let removed = resolve(
+            self.root.remove_entry_with_options(&self.blob_name(blob_id)?, &options),
+            "Failed to remove OPFS blob",
+        )
+        .await?;
+        Ok(removed.is_some())
+    }
+}
+
+/// OPFS 附件串流寫入器 (由 `OpfsBlobStore.blobWriter` 建立)
+///
+/// chunk 編號在呼叫 `write` 時就決定，多個 `write` 不必等待前一個完成
+#[wasm_bindgen]
+pub struct OpfsBlobWriter {
+    store: OpfsBlobStore,
+    blob_id: String,
+    writer: Rc<RefCell<BlobWriter>>,
+}
+
+#[wasm_bindgen]
+impl OpfsBlobWriter {
+    /// 加密並寫入一段明文
+    pub fn write(&self, data: &[u8]) -> Promise {
+        let chunk = self.writer.borrow_mut().write(data);
+        let mut store = self.store.clone();
+        let blob_id = self.blob_id.clone();
+        future_to_promise(async move {
+            if let Some(chunk) = chunk.map_err(|e| JsError::new(&e))? {
+                store
+                    .put_chunk(&blob_id, chunk.index, &chunk.bytes)
+                    .await
+                    .map_err(|e| JsError::new(&e))?;
+            }
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+
+    /// 寫入最後一段並結束
+    pub fn finish(&self) -> Promise {
+        let chunk = self.writer.borrow_mut().finish();
+        let mut store = self.store.clone();
+        let blob_id = self.blob_id.clone();
+        future_to_promise(async move {
+            let chunk = chunk.map_err(|e| JsError::new(&e))?;
+            store
+                .put_chunk(&blob_id, chunk.index, &chunk.bytes)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(JsValue::UNDEFINED)
+        })
+    }
+}
+
+/// OPFS 附件串流讀取器 (由 `OpfsBlobStore.blobReader` 建立)
+#[wasm_bindgen]
+pub struct OpfsBlobReader {
+    store: OpfsBlobStore,
+    blob_id: String,
+    /// 讀完後為 None
+    reader: Rc<RefCell<Option<BlobReader>>>,
+}
+
+#[wasm_bindgen]
+impl OpfsBlobReader {
+    /// 讀取並解密下一個 chunk，Promise 的結果為明文 (可能為空) 或讀完後的 undefined
+    ///
+    /// 必須等前一個 `read` 完成後再呼叫
+    pub fn read(&self) -> Promise {
+        let store = self.store.clone();
+        let blob_id = self.blob_id.clone();
+        let reader = self.reader.clone();
+        future_to_promise(async move {
+            let Some(index) = reader.borrow().as_ref().map(BlobReader::next_index) else {
+                return Ok(JsValue::UNDEFINED);
+            };
+            let chunk = store.get_chunk(&blob_id, index).await.map_err(|e| JsError::new(&e))?;
+            let mut state = reader.borrow_mut();
+            let Some(current) = state.as_mut() else {
+                return Ok(JsValue::UNDEFINED);
+            };
+            let plaintext = match chunk {
+                Some(chunk) => current.read(&chunk),
+                None => {
+                    let last = current.finish();
+                    *state = None;
+                    last
+                }
+            };
+            let plaintext = plaintext.map_err(|e| JsError::new(&e))?;
+            Ok(Uint8Array::from(plaintext.as_slice()).into())
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl OpfsBlobStore {
+    /// 目前的環境是否支援 OPFS (不支援時改用 IndexedDB 後端)
+    #[wasm_bindgen(js_name = isAvailable)]
+    pub fn is_available() -> bool {
+        storage_manager().is_some()
+    }
+
+    /// 開啟 (不存在時建立) OPFS 根目錄下的 `directory`
+    pub async fn open(directory: String) -> Result<OpfsBlobStore, JsError> {
+        Self::open_directory(&directory, None).await.map_err(|e| JsError::new(&e))
+    }
+
+    /// 開啟並以主儲存金鑰盲化 blob 目錄名稱，Promise 的結果為 `OpfsBlobStore`
+    #[wasm_bindgen(js_name = openEncrypted)]
+    pub fn open_encrypted(directory: String, key: &StorageKey) -> Promise {
+        let key = key.clone();
+        future_to_promise(async move {
+            let store = Self::open_directory(&directory, Some(key)).await.map_err(|e| JsError::new(&e))?;
+            Ok(store.into())
+        })
+    }
+
+    /// 建立附件串流寫入器 (覆寫既有的 blob)，Promise 的結果為 `OpfsBlobWriter`
+    ///
+    /// `chunk_size` 未提供時為 64 KiB，`suite` 未提供時為 AES-256-GCM
+    #[wasm_bindgen(js_name = blobWriter)]
+    pub fn blob_writer(&self, blob_id: String, key: Vec<u8>, chunk_size: Option<u32>, suite: Option<CipherSuite>) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let (writer, header) = BlobWriter::new(&key, chunk_size, suite).map_err(|e| JsError::new(&e))?;
+            store.remove_blob(&blob_id).await.map_err(|e| JsError::new(&e))?;
+            store
+                .put_chunk(&blob_id, header.index, &header.bytes)
+                .await
+                .map_err(|e| JsError::new(&e))?;
+            Ok(OpfsBlobWriter { store, blob_id, writer: Rc::new(RefCell::new(writer)) }.into())
+        })
+    }
+
+    /// 建立附件串流讀取器，Promise 的結果為 `OpfsBlobReader`
+    #[wasm_bindgen(js_name = blobReader)]
+    pub fn blob_reader(&self, blob_id: String, key: Vec<u8>) -> Promise {
+        let store = self.clone();
+        future_to_promise(async move {
+            let header = store
+                .get_chunk(&blob_id, 0)
+                .await
+                .map_err(|e| JsError::new(&e))?
+                .ok_or_else(|| JsError::new(&format!("Unknown blob: {}", blob_id)))?;
+            let reader = BlobReader::new(&key, &header).map_err(|e| JsError::new(&e))?;
+            Ok(OpfsBlobReader { store, blob_id, reader: Rc::new(RefCell::new(Some(reader))) }.into())
+        })
+    }
+
+    /// 刪除附件的所有 chunk，Promise 的結果為是否存在
+    #[wasm_bindgen(js_name = removeBlob)]
+    pub fn remove_blob_js(&self, blob_id: String) -> Promise {
+        let mut store = self.clone();
+        future_to_promise(async move {
+            let existed = store.remove_blob(&blob_id).await.map_err(|e| JsError::new(&e))?;
+            Ok(existed.into())
+        })
+    }
+}
@@ -0,0 +1,403 @@
+//! 排程傳送佇列模組
+//!
+//! 預約傳送與離線時送不出的訊息放進佇列，重新啟動後仍會送出：
+//! - 內容在排入佇列時就已加密 (協定層產生的密文)，佇列不保存明文
+//! - 每則訊息有送出時間 (`send_at`) 與重試政策，失敗後以指數退避重試，
+//!   超過次數上限時標記為失敗，由使用者決定重送或取消
+//! - `claimDue` 取出到期訊息時設定租約，送出期間不會被再次取出；程式在送出途中結束時
+//!   租約到期後重新送出，因此同一則訊息可能送出不只一次，接收端需以訊息 ID 去重
+//!
+//! 佇列以 `serializeEncrypted` 與其他記憶體儲存一樣以主儲存金鑰加密持久化
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+use zeroize::Zeroize;
+
+use super::encryption::{RecordCodec, StorageKey, SNAPSHOT_STORE};
+
+const OUTBOX_SNAPSHOT_KEY: &str = "outbox";
+
+/// 重試政策：第 n 次失敗後等待 `initial_backoff_ms * 2^(n-1)` (不超過 `max_backoff_ms`)，
+/// 失敗 `max_attempts` 次後放棄
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff_ms: u64,
+    max_backoff_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    /// 最多 8 次，5 秒起、最長 1 小時
+    fn default() -> Self {
+        Self { max_attempts: 8, initial_backoff_ms: 5_000, max_backoff_ms: 3_600_000 }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `attempts` 次失敗後的等待時間
+    fn backoff(&self, attempts: u32) -> u64 {
+        let factor = 1u64.checked_shl(attempts.saturating_sub(1)).unwrap_or(u64::MAX);
+        self.initial_backoff_ms.saturating_mul(factor).min(self.max_backoff_ms)
+    }
+}
+
+#[wasm_bindgen]
+impl RetryPolicy {
+    /// 建立重試政策，`max_attempts` 至少為 1
+    #[wasm_bindgen(constructor)]
+    pub fn new(max_attempts: u32, initial_backoff_ms: u64, max_backoff_ms: u64) -> Self {
+        Self { max_attempts: max_attempts.max(1), initial_backoff_ms, max_backoff_ms: max_backoff_ms.max(initial_backoff_ms) }
+    }
+
+    #[wasm_bindgen(getter, js_name = maxAttempts)]
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    #[wasm_bindgen(getter, js_name = initialBackoffMs)]
+    pub fn initial_backoff_ms(&self) -> u64 {
+        self.initial_backoff_ms
+    }
+
+    #[wasm_bindgen(getter, js_name = maxBackoffMs)]
+    pub fn max_backoff_ms(&self) -> u64 {
+        self.max_backoff_ms
+    }
+}
+
+/// 佇列中的訊息
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScheduledMessage {
+    id: String,
+    conversation_id: String,
+    /// 已加密的內容
+    payload: Vec<u8>,
+    /// 預定送出時間 (Unix 毫秒)
+    send_at: u64,
+    policy: RetryPolicy,
+    /// 已失敗的次數
+    attempts: u32,
+    /// 下次可取出的時間 (退避或租約)
+    next_attempt_at: u64,
+    last_error: Option<String>,
+    /// 超過重試次數上限
+    failed: bool,
+}
+
+#[wasm_bindgen]
+impl ScheduledMessage {
+    /// 建立排程訊息，`payload` 為排入佇列時已加密的內容；未指定政策時使用預設重試政策
+    #[wasm_bindgen(constructor)]
+    pub fn new(id: &str, conversation_id: &str, payload: &[u8], send_at: u64, policy: Option<RetryPolicy>) -> Self {
+        Self {
+            id: id.to_string(),
+            conversation_id: conversation_id.to_string(),
+            payload: payload.to_vec(),
+            send_at,
+            policy: policy.unwrap_or_default(),
+            attempts: 0,
+            next_attempt_at: send_at,
+            last_error: None,
+            failed: false,
+        }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = conversationId)]
+    pub fn conversation_id(&self) -> String {
+        self.conversation_id.clone()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = sendAt)]
+    pub fn send_at(&self) -> u64 {
+        self.send_at
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn policy(&self) -> RetryPolicy {
+        self.policy
+    }
+
+    /// 已失敗的次數
+    #[wasm_bindgen(getter)]
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// 下次可取出的時間 (Unix 毫秒)
+    #[wasm_bindgen(getter, js_name = nextAttemptAt)]
+    pub fn next_attempt_at(&self) -> u64 {
+        self.next_attempt_at
+    }
+
+    /// 最後一次失敗的原因
+    #[wasm_bindgen(getter, js_name = lastError)]
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error.clone()
+    }
+
+    /// 是否已超過重試次數上限 (不再自動送出)
+    #[wasm_bindgen(getter)]
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+}
+
+/// 排程傳送佇列
+pub trait OutboxStore {
+    /// 排入 (或以相同 ID 取代) 訊息
+    fn schedule(&mut self, message: &ScheduledMessage) -> Result<(), String>;
+
+    /// 讀取訊息
+    fn scheduled(&self, id: &str) -> Result<Option<ScheduledMessage>, String>;
+
+    /// `now` 時已到期的訊息 (依可取出時間排序)，不改變佇列
+    fn due(&self, now: u64) -> Result<Vec<ScheduledMessage>, String>;
+
+    /// 取出最多 `limit` 則到期訊息，並在 `lease_ms` 內不再取出 (送出中)
+    fn claim_due(&mut self, now: u64, lease_ms: u64, limit: u32) -> Result<Vec<ScheduledMessage>, String>;
+
+    /// 送出成功，自佇列移除，回傳是否存在
+    fn mark_sent(&mut self, id: &str) -> Result<bool, String>;
+
+    /// 送出失敗，回傳之後是否會重試
+    fn mark_failed(&mut self, id: &str, now: u64, error: &str) -> Result<bool, String>;
+
+    /// 改為在 `send_at` 送出並重設失敗次數 (包括已放棄的訊息)
+    fn reschedule(&mut self, id: &str, send_at: u64) -> Result<(), String>;
+
+    /// 取消排程，回傳是否存在
+    fn cancel(&mut self, id: &str) -> Result<bool, String>;
+}
+
+/// 記憶體排程傳送佇列，以 `serializeEncrypted` 持久化
+#[wasm_bindgen]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct InMemoryOutbox {
+    /// id -> 訊息
+    messages: BTreeMap<String, ScheduledMessage>,
+}
+
+impl InMemoryOutbox {
+    fn message_mut(&mut self, id: &str) -> Result<&mut ScheduledMessage, String> {
+        self.messages.get_mut(id).ok_or_else(|| format!("Unknown scheduled message: {}", id))
+    }
+
+    /// 尚未放棄的訊息，依 (可取出時間, ID) 排序
+    fn pending(&self) -> Vec<&ScheduledMessage> {
+        let mut pending: Vec<_> = self.messages.values().filter(|message| !message.failed).collect();
+        pending.sort_by(|a, b| (a.next_attempt_at, &a.id).cmp(&(b.next_attempt_at, &b.id)));
+        pending
+    }
+}
+
+impl OutboxStore for InMemoryOutbox {
+    fn schedule(&mut self, message: &ScheduledMessage) -> Result<(), String> {
+        if let Some(mut previous) = self.messages.insert(message.id.clone(), message.clone()) {
+            previous.payload.zeroize();
+        }
+        Ok(())
+    }
+
+    fn scheduled(&self, id: &str) -> Result<Option<ScheduledMessage>, String> {
+        Ok(self.messages.get(id).cloned())
+    }
+
+    fn due(&self, now: u64) -> Result<Vec<ScheduledMessage>, String> {
+        Ok(self
+            .pending()
+            .into_iter()
+            .take_while(|message| message.next_attempt_at <= now)
+            .cloned()
+            .collect())
+    }
+
+    fn claim_due(&mut self, now: u64, lease_ms: u64, limit: u32) -> Result<Vec<ScheduledMessage>, String> {
+        let claimed: Vec<ScheduledMessage> = self.due(now)?.into_iter().take(limit as usize).collect();
+        for message in &claimed {
+            self.message_mut(&message.id)?.next_attempt_at = now.saturating_add(lease_ms);
+        }
+        Ok(claimed)
+    }
+
+    fn mark_sent(&mut self, id: &str) -> Result<bool, String> {
+        let Some(mut message) = self.messages.remove(id) else {
+            return Ok(false);
+        };
+        message.payload.zeroize();
+        Ok(true)
+    }
+
+    fn mark_failed(&mut self, id: &str, now: u64, error: &str) -> Result<bool, String> {
+        let message = self.message_mut(id)?;
+        message.attempts = message.attempts.saturating_add(1);
+        message.last_error = Some(error.to_string());
+        if message.attempts >= message.policy.max_attempts {
+            message.failed = true;
+            return Ok(false);
+        }
+        message.next_attempt_at = now.saturating_add(message.policy.backoff(message.attempts));
+        Ok(true)
+    }
+
+    fn reschedule(&mut self, id: &str, send_at: u64) -> Result<(), String> {
+        let message = self.message_mut(id)?;
+        message.send_at = send_at;
+        message.next_attempt_at = send_at;
+        message.attempts = 0;
+        message.last_error = None;
+        message.failed = false;
+        Ok(())
+    }
+
+    fn cancel(&mut self, id: &str) -> Result<bool, String> {
+        self.mark_sent(id)
+    }
+}
+
+#[wasm_bindgen]
+impl InMemoryOutbox {
+    /// 建立空的佇列
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 排入 (或以相同 ID 取代) 訊息
+    #[wasm_bindgen(js_name = schedule)]
+    pub fn schedule_js(&mut self, message: &ScheduledMessage) -> Result<(), JsError> {
+        self.schedule(message).map_err(|e| JsError::new(&e))
+    }
+
+    /// 讀取訊息
+    #[wasm_bindgen(js_name = get)]
+    pub fn get_js(&self, id: &str) -> Result<Option<ScheduledMessage>, JsError> {
+        self.scheduled(id).map_err(|e| JsError::new(&e))
+    }
+
+    /// `now` 時已到期的訊息，不改變佇列
+    #[wasm_bindgen(js_name = due)]
+    pub fn due_js(&self, now: u64) -> Result<Vec<ScheduledMessage>, JsError> {
+        self.due(now).map_err(|e| JsError::new(&e))
+    }
+
+    /// 取出最多 `limit` 則到期訊息，並在 `lease_ms` 內不再取出
+    #[wasm_bindgen(js_name = claimDue)]
+    pub fn claim_due_js(&mut self, now: u64, lease_ms: u64, limit: u32) -> Result<Vec<ScheduledMessage>, JsError> {
+        self.claim_due(now, lease_ms, limit).map_err(|e| JsError::new(&e))
+    }
+
+    /// 送出成功，自佇列移除，回傳是否存在
+    #[wasm_bindgen(js_name = markSent)]
+    pub fn mark_sent_js(&mut self, id: &str) -> Result<bool, JsError> {
+        self.mark_sent(id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 送出失敗，回傳之後是否會重試
+    #[wasm_bindgen(js_name = markFailed)]
+    pub fn mark_failed_js(&mut self, id: &str, now: u64, error: &str) -> Result<bool, JsError> {
+        self.mark_failed(id, now, error).map_err(|e| JsError::new(&e))
+    }
+
+    /// 改為在 `send_at` 送出並重設失敗次數
+    #[wasm_bindgen(js_name = reschedule)]
+    pub fn reschedule_js(&mut self, id: &str, send_at: u64) -> Result<(), JsError> {
+        self.reschedule(id, send_at).map_err(|e| JsError::new(&e))
+    }
+
+    /// 取消排程，回傳是否存在
+    #[wasm_bindgen(js_name = cancel)]
+    pub fn cancel_js(&mut self, id: &str) -> Result<bool, JsError> {
+        self.cancel(id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 最早的可取出時間 (設定計時器用)，沒有待送訊息時為 undefined
+    #[wasm_bindgen(getter, js_name = nextDueAt)]
+    pub fn next_due_at(&self) -> Option<u64> {
+        self.pending().first().map(|message| message.next_attempt_at)
+    }
+
+    /// 已超過重試次數上限的訊息 (依 ID 排序)
+    #[wasm_bindgen(getter)]
+    pub fn failed(&self) -> Vec<ScheduledMessage> {
+        self.messages.values().filter(|message| message.failed).cloned().collect()
+    }
+
+    /// 佇列中的訊息數
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.messages.len()
+    }
+
+    /// 以主儲存金鑰加密序列化
+    #[wasm_bindgen(js_name = serializeEncrypted)]
+    pub fn serialize_encrypted(&self, key: &StorageKey) -> Result<Vec<u8>, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .encode(SNAPSHOT_STORE, OUTBOX_SNAPSHOT_KEY, self)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密並還原
+    #[wasm_bindgen(js_name = deserializeEncrypted)]
+    pub fn deserialize_encrypted(bytes: &[u8], key: &StorageKey) -> Result<InMemoryOutbox, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .decode(SNAPSHOT_STORE, OUTBOX_SNAPSHOT_KEY, bytes)
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outbox_scheduling_and_retry() {
+        let mut outbox = InMemoryOutbox::new();
+        let policy = RetryPolicy::new(3, 1_000, 1_500);
+        outbox.schedule(&ScheduledMessage::new("later", "chat", b"ciphertext-2", 5_000, Some(policy))).unwrap();
+        outbox.schedule(&ScheduledMessage::new("soon", "chat", b"ciphertext-1", 1_000, Some(policy))).unwrap();
+        assert!(outbox.due(999).unwrap().is_empty());
+        assert_eq!(outbox.next_due_at(), Some(1_000));
+
+        // 取出後在租約內不再取出，租約到期 (送出途中結束) 後重新送出
+        let claimed = outbox.claim_due(1_000, 30_000, 10).unwrap();
+        assert_eq!(claimed.iter().map(ScheduledMessage::id).collect::<Vec<_>>(), vec!["soon"]);
+        assert_eq!(claimed[0].payload(), b"ciphertext-1");
+        assert!(outbox.claim_due(6_000, 30_000, 10).unwrap().iter().all(|message| message.id() == "later"));
+        assert_eq!(outbox.due(31_000).unwrap().len(), 1);
+
+        // 指數退避 (不超過上限)，超過次數上限後放棄
+        assert!(outbox.mark_failed("soon", 10_000, "offline").unwrap());
+        assert_eq!(outbox.scheduled("soon").unwrap().unwrap().next_attempt_at(), 11_000);
+        assert!(outbox.mark_failed("soon", 11_000, "offline").unwrap());
+        assert_eq!(outbox.scheduled("soon").unwrap().unwrap().next_attempt_at(), 12_500);
+        assert!(!outbox.mark_failed("soon", 12_500, "rejected").unwrap());
+        assert!(outbox.due(u64::MAX).unwrap().iter().all(|message| message.id() != "soon"));
+        assert_eq!(outbox.failed()[0].last_error().as_deref(), Some("rejected"));
+        assert!(outbox.mark_failed("missing", 0, "x").is_err());
+
+        // 加密持久化後仍在佇列中
+        let key = StorageKey::generate();
+        let mut restored = InMemoryOutbox::deserialize_encrypted(&outbox.serialize_encrypted(&key).unwrap(), &key).unwrap();
+        restored.reschedule("soon", 20_000).unwrap();
+        assert_eq!(restored.due(36_000).unwrap().len(), 2);
+        assert!(restored.mark_sent("later").unwrap());
+        assert!(restored.cancel("soon").unwrap());
+        assert!(!restored.cancel("soon").unwrap());
+        assert_eq!(restored.size(), 0);
+        assert_eq!(restored.next_due_at(), None);
+    }
+}
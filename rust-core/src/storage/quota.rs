@@ -0,0 +1,451 @@
+//! 儲存配額管理模組
+//!
+//! 瀏覽器的儲存配額有限，超出時寫入會直接失敗。`QuotaTracker` 記錄各項
+//! 可清理資料的大小與最後使用時間，用量超過 `QuotaPolicy` 的上限時依序
+//! 清理，直到用量降到目標以下：
+//! 1. 附件 blob (最舊的先清)
+//! 2. 久未使用的會話中保留的跳過訊息金鑰 (之後才到達的亂序訊息無法解密)
+//! 3. 訊息 (預設不清理，需由政策明確開啟)
+//!
+//! 每個項目刪除前呼叫確認 callback，回傳 false 時保留該項目 (例如使用者
+//! 釘選的附件) 並改清理下一個。實際刪除由 `PruneTarget` 執行，
+//! `(blobs, sessions, messages)` 三個儲存組成的 tuple 已實作；同時是非同步
+//! blob、會話與訊息儲存的後端 (`IndexedDbStore`) 實作 `AsyncPruneTarget`
+//!
+//! 大小由呼叫端在寫入時提供 (附件的密文大小、訊息內容長度)，追蹤器本身
+//! 不掃描儲存；以 `serializeEncrypted` 與其他記憶體儲存一起持久化
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::RatchetSession;
+use super::blobs::{AsyncBlobStore, BlobStore};
+use super::encryption::{RecordCodec, StorageKey, SNAPSHOT_STORE};
+use super::messages::{AsyncMessageStore, MessageStore, StoredMessage};
+use super::store::{AsyncSessionStore, SessionStore};
+
+const QUOTA_SNAPSHOT_KEY: &str = "quota_tracker";
+/// 每把跳過的訊息金鑰序列化後約佔的位元組數 (DH 公鑰 Base64、訊息編號與 80 bytes 金鑰)
+const SKIPPED_KEY_SIZE: u64 = 136;
+
+/// 可清理的資料類別 (依清理順序)
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum PruneCategory {
+    /// 附件 blob
+    Attachment = 0,
+    /// 會話中的跳過訊息金鑰
+    SkippedKeys = 1,
+    /// 訊息
+    Message = 2,
+}
+
+/// 清理候選項目
+#[wasm_bindgen]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PruneCandidate {
+    category: PruneCategory,
+    /// blob ID、聯絡人 ID 或訊息 ID
+    id: String,
+    /// 跳過訊息金鑰所屬會話的裝置 ID
+    device_id: Option<u32>,
+    size: u64,
+    last_used: u64,
+}
+
+impl PruneCandidate {
+    fn entry_key(&self) -> (PruneCategory, String, Option<u32>) {
+        (self.category, self.id.clone(), self.device_id)
+    }
+}
+
+#[wasm_bindgen]
+impl PruneCandidate {
+    #[wasm_bindgen(getter)]
+    pub fn category(&self) -> PruneCategory {
+        self.category
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = deviceId)]
+    pub fn device_id(&self) -> Option<u32> {
+        self.device_id
+    }
+
+    /// 清理後釋放的位元組數 (估計值)
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// 最後使用時間 (Unix 毫秒)
+    #[wasm_bindgen(getter, js_name = lastUsed)]
+    pub fn last_used(&self) -> u64 {
+        self.last_used
+    }
+}
+
+/// 配額政策
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuotaPolicy {
+    /// 用量上限，超過時才開始清理
+    limit: u64,
+    /// 清理到用量不超過此值 (不大於上限)
+    target: u64,
+    /// 是否清理訊息
+    prune_messages: bool,
+}
+
+#[wasm_bindgen]
+impl QuotaPolicy {
+    /// 建立政策，未指定目標時清理到剛好不超過上限
+    #[wasm_bindgen(constructor)]
+    pub fn new(limit: u64, target: Option<u64>) -> Self {
+        Self { limit, target: target.map_or(limit, |target| target.min(limit)), prune_messages: false }
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn target(&self) -> u64 {
+        self.target
+    }
+
+    #[wasm_bindgen(getter, js_name = pruneMessages)]
+    pub fn prune_messages(&self) -> bool {
+        self.prune_messages
+    }
+
+    /// 允許清理訊息 (在附件與跳過訊息金鑰之後)
+    #[wasm_bindgen(js_name = setPruneMessages)]
+    pub fn set_prune_messages(&mut self, prune_messages: bool) {
+        self.prune_messages = prune_messages;
+    }
+}
+
+/// 刪除清理候選項目的儲存
+pub trait PruneTarget {
+    /// 刪除項目 (已不存在時視為成功)
+    fn prune(&mut self, candidate: &PruneCandidate) -> Result<(), String>;
+}
+
+/// 非同步刪除清理候選項目的儲存，方法與 [`PruneTarget`] 相同
+#[allow(async_fn_in_trait)]
+pub trait AsyncPruneTarget {
+    async fn prune(&mut self, candidate: &PruneCandidate) -> Result<(), String>;
+}
+
+fn skipped_keys_device(candidate: &PruneCandidate) -> Result<u32, String> {
+    candidate
+        .device_id
+        .ok_or_else(|| "Skipped key candidate is missing a device ID".to_string())
+}
+
+impl<B: BlobStore, S: SessionStore, M: MessageStore> PruneTarget for (&mut B, &mut S, &mut M) {
+    fn prune(&mut self, candidate: &PruneCandidate) -> Result<(), String> {
+        match candidate.category {
+            PruneCategory::Attachment => self.0.remove_blob(&candidate.id).map(drop),
+            PruneCategory::SkippedKeys => {
+                let device_id = skipped_keys_device(candidate)?;
+                if let Some(mut session) = self.1.load_session(&candidate.id, device_id)? {
+                    session.clear_skipped_keys();
+                    self.1.store_session(&candidate.id, device_id, &session)?;
+                }
+                Ok(())
+            }
+            PruneCategory::Message => self.2.remove_message(&candidate.id).map(drop),
+        }
+    }
+}
+
+/// 同時實作非同步 blob、會話與訊息儲存的後端 (例如 `IndexedDbStore`)
+impl<T: AsyncBlobStore + AsyncSessionStore + AsyncMessageStore> AsyncPruneTarget for T {
+    async fn prune(&mut self, candidate: &PruneCandidate) -> Result<(), String> {
+        match candidate.category {
+            PruneCategory::Attachment => self.remove_blob(&candidate.id).await.map(drop),
+            PruneCategory::SkippedKeys => {
+                let device_id = skipped_keys_device(candidate)?;
+                if let Some(mut session) = self.load_session(&candidate.id, device_id).await? {
+                    session.clear_skipped_keys();
+                    self.store_session(&candidate.id, device_id, &session).await?;
+                }
+                Ok(())
+            }
+            PruneCategory::Message => self.remove_message(&candidate.id).await.map(drop),
+        }
+    }
+}
+
+/// 以 JS callback 刪除：`(candidate: PruneCandidate) => void`
+struct JsPruneTarget<'a>(&'a js_sys::Function);
+
+impl PruneTarget for JsPruneTarget<'_> {
+    fn prune(&mut self, candidate: &PruneCandidate) -> Result<(), String> {
+        self.0
+            .call1(&JsValue::NULL, &candidate.clone().into())
+            .map(drop)
+            .map_err(|e| format!("Prune callback failed: {:?}", e))
+    }
+}
+
+/// 單一項目的用量
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Usage {
+    size: u64,
+    last_used: u64,
+}
+
+/// 配額追蹤
+#[wasm_bindgen]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct QuotaTracker {
+    /// (類別, ID, 裝置 ID) -> 用量
+    entries: BTreeMap<(PruneCategory, String, Option<u32>), Usage>,
+}
+
+impl QuotaTracker {
+    /// 政策允許清理的項目，依類別再依最後使用時間排序
+    fn candidates(&self, policy: &QuotaPolicy) -> Vec<PruneCandidate> {
+        let mut candidates: Vec<PruneCandidate> = self
+            .entries
+            .iter()
+            .filter(|((category, _, _), _)| *category != PruneCategory::Message || policy.prune_messages)
+            .map(|((category, id, device_id), usage)| PruneCandidate {
+                category: *category,
+                id: id.clone(),
+                device_id: *device_id,
+                size: usage.size,
+                last_used: usage.last_used,
+            })
+            .collect();
+        candidates.sort_by_key(|candidate| (candidate.category, candidate.last_used));
+        candidates
+    }
+
+    /// 依政策清理，回傳已刪除的項目
+    ///
+    /// 用量未超過上限時不做任何事；每個項目刪除前呼叫 `confirm`，回傳 false 時保留
+    pub fn enforce(
+        &mut self,
+        policy: &QuotaPolicy,
+        mut confirm: impl FnMut(&PruneCandidate) -> bool,
+        target: &mut impl PruneTarget,
+    ) -> Result<Vec<PruneCandidate>, String> {
+        let mut usage = self.usage();
+        let mut pruned = Vec::new();
+        if usage <= policy.limit {
+            return Ok(pruned);
+        }
+        for candidate in self.candidates(policy) {
+            if usage <= policy.target {
+                break;
+            }
+            if !confirm(&candidate) {
+                continue;
+            }
+            target.prune(&candidate)?;
+            self.entries.remove(&candidate.entry_key());
+            usage = usage.saturating_sub(candidate.size);
+            pruned.push(candidate);
+        }
+        Ok(pruned)
+    }
+
+    /// 非同步版本的 [`QuotaTracker::enforce`]
+    pub async fn enforce_async(
+        &mut self,
+        policy: &QuotaPolicy,
+        mut confirm: impl FnMut(&PruneCandidate) -> bool,
+        target: &mut impl AsyncPruneTarget,
+    ) -> Result<Vec<PruneCandidate>, String> {
+        let mut usage = self.usage();
+        let mut pruned = Vec::new();
+        if usage <= policy.limit {
+            return Ok(pruned);
+        }
+        for candidate in self.candidates(policy) {
+            if usage <= policy.target {
+                break;
+            }
+            if !confirm(&candidate) {
+                continue;
+            }
+            target.prune(&candidate).await?;
+            self.entries.remove(&candidate.entry_key());
+            usage = usage.saturating_sub(candidate.size);
+            pruned.push(candidate);
+        }
+        Ok(pruned)
+    }
+}
+
+#[wasm_bindgen]
+impl QuotaTracker {
+    /// 建立空的追蹤器
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 記錄 (覆寫) 附件或訊息的大小與最後使用時間
+    pub fn track(&mut self, category: PruneCategory, id: &str, size: u64, last_used: u64) {
+        self.entries.insert((category, id.to_string(), None), Usage { size, last_used });
+    }
+
+    /// 記錄會話保留的跳過訊息金鑰；沒有跳過的金鑰時移除記錄
+    #[wasm_bindgen(js_name = trackSession)]
+    pub fn track_session(&mut self, contact_id: &str, device_id: u32, session: &RatchetSession, last_used: u64) {
+        let key = (PruneCategory::SkippedKeys, contact_id.to_string(), Some(device_id));
+        match session.skipped_key_count() {
+            0 => self.entries.remove(&key),
+            count => self.entries.insert(key, Usage { size: u64::from(count) * SKIPPED_KEY_SIZE, last_used }),
+        };
+    }
+
+    /// 記錄訊息 (大小為內容長度，最後使用時間為訊息時間戳)
+    #[wasm_bindgen(js_name = trackMessage)]
+    pub fn track_message(&mut self, message: &StoredMessage) {
+        self.track(PruneCategory::Message, &message.id(), message.body().len() as u64, message.timestamp());
+    }
+
+    /// 移除記錄 (資料已由其他途徑刪除時)，回傳是否存在
+    pub fn untrack(&mut self, category: PruneCategory, id: &str, device_id: Option<u32>) -> bool {
+        self.entries.remove(&(category, id.to_string(), device_id)).is_some()
+    }
+
+    /// 追蹤中的總用量
+    pub fn usage(&self) -> u64 {
+        self.entries.values().map(|usage| usage.size).sum()
+    }
+
+    /// 單一類別的用量
+    #[wasm_bindgen(js_name = categoryUsage)]
+    pub fn category_usage(&self, category: PruneCategory) -> u64 {
+        self.entries
+            .iter()
+            .filter(|((entry_category, _, _), _)| *entry_category == category)
+            .map(|(_, usage)| usage.size)
+            .sum()
+    }
+
+    /// 預計清理的項目 (假設全部確認)，用量未超過上限時為空
+    pub fn plan(&self, policy: &QuotaPolicy) -> Vec<PruneCandidate> {
+        let mut usage = self.usage();
+        if usage <= policy.limit {
+            return Vec::new();
+        }
+        self.candidates(policy)
+            .into_iter()
+            .take_while(|candidate| {
+                let needed = usage > policy.target;
+                usage = usage.saturating_sub(candidate.size);
+                needed
+            })
+            .collect()
+    }
+
+    /// 依政策清理，回傳已刪除的項目
+    ///
+    /// 刪除前呼叫 `confirm(candidate)`，回傳 falsy 時保留；`prune(candidate)` 執行實際的刪除
+    #[wasm_bindgen(js_name = enforce)]
+    pub fn enforce_js(
+        &mut self,
+        policy: &QuotaPolicy,
+        confirm: &js_sys::Function,
+        prune: &js_sys::Function,
+    ) -> Result<Vec<PruneCandidate>, JsError> {
+        let confirm = |candidate: &PruneCandidate| {
+            confirm
+                .call1(&JsValue::NULL, &candidate.clone().into())
+                .is_ok_and(|result| result.is_truthy())
+        };
+        self.enforce(policy, confirm, &mut JsPruneTarget(prune))
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 以主儲存金鑰加密序列化
+    #[wasm_bindgen(js_name = serializeEncrypted)]
+    pub fn serialize_encrypted(&self, key: &StorageKey) -> Result<Vec<u8>, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .encode(SNAPSHOT_STORE, QUOTA_SNAPSHOT_KEY, self)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密並還原
+    #[wasm_bindgen(js_name = deserializeEncrypted)]
+    pub fn deserialize_encrypted(bytes: &[u8], key: &StorageKey) -> Result<QuotaTracker, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .decode(SNAPSHOT_STORE, QUOTA_SNAPSHOT_KEY, bytes)
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::IdentityKeyPair;
+    use crate::storage::{InMemoryBlobStore, InMemoryMessageStore, InMemoryProtocolStore};
+
+    #[test]
+    fn test_quota_pruning_order() {
+        let mut blobs = InMemoryBlobStore::new();
+        let mut protocol = InMemoryProtocolStore::new(&IdentityKeyPair::new(), 1);
+        let mut messages = InMemoryMessageStore::new();
+        let mut tracker = QuotaTracker::new();
+        for (id, last_used) in [("photo", 30), ("video", 10), ("pinned", 5)] {
+            blobs.put_chunk(id, 0, &[0u8; 4]).unwrap();
+            tracker.track(PruneCategory::Attachment, id, 400, last_used);
+        }
+        protocol.store_session("bob", 1, &RatchetSession::for_test([1u8; 32])).unwrap();
+        tracker.entries.insert(
+            (PruneCategory::SkippedKeys, "bob".to_string(), Some(1)),
+            Usage { size: 100, last_used: 1 },
+        );
+        let message = StoredMessage::new("m1", "chat", "bob", 1, &[0u8; 300]);
+        messages.store_message(&message).unwrap();
+        tracker.track_message(&message);
+        assert_eq!(tracker.usage(), 1600);
+        assert_eq!(tracker.category_usage(PruneCategory::Attachment), 1200);
+
+        // 未超過上限時不清理
+        assert!(tracker.plan(&QuotaPolicy::new(2000, None)).is_empty());
+
+        // 附件 (最舊的先) 再跳過訊息金鑰；訊息預設不清理
+        let policy = QuotaPolicy::new(1000, Some(500));
+        let planned: Vec<String> = tracker.plan(&policy).iter().map(PruneCandidate::id).collect();
+        assert_eq!(planned, vec!["pinned", "video", "photo"]);
+
+        let mut stores = (&mut blobs, &mut protocol, &mut messages);
+        let pruned = tracker
+            .enforce(&policy, |candidate| candidate.id() != "pinned", &mut stores)
+            .unwrap();
+        let pruned: Vec<String> = pruned.iter().map(PruneCandidate::id).collect();
+        assert_eq!(pruned, vec!["video", "photo", "bob"]);
+        assert_eq!(tracker.usage(), 700);
+        assert!(blobs.get_chunk("pinned", 0).unwrap().is_some());
+        assert!(blobs.get_chunk("video", 0).unwrap().is_none());
+        assert!(protocol.load_session("bob", 1).unwrap().is_some());
+
+        // 開啟訊息清理後才刪除訊息
+        let mut policy = QuotaPolicy::new(500, None);
+        policy.set_prune_messages(true);
+        let mut stores = (&mut blobs, &mut protocol, &mut messages);
+        let pruned = tracker.enforce(&policy, |candidate| candidate.id() != "pinned", &mut stores).unwrap();
+        assert_eq!(pruned.iter().map(PruneCandidate::category).collect::<Vec<_>>(), vec![PruneCategory::Message]);
+        assert!(messages.load_message("m1").unwrap().is_none());
+
+        let key = StorageKey::generate();
+        let restored = QuotaTracker::deserialize_encrypted(&tracker.serialize_encrypted(&key).unwrap(), &key).unwrap();
+        assert_eq!(restored.usage(), 400);
+    }
+}
@@ -0,0 +1,193 @@
+//! 訊息搜尋索引模組
+//!
+//! 以盲化索引 (blind index) 提供本機全文搜尋：訊息文字正規化成詞元後，
+//! 每個詞元以主儲存金鑰的索引金鑰計算 HMAC，索引中只存 HMAC 與訊息 ID，
+//! 不存任何明文詞元
+//!
+//! 正規化：轉小寫、以非字母數字字元切詞；中日韓文字沒有分隔符號，
+//! 每個字各自成為一個詞元。搜尋時查詢字串的所有詞元都必須出現 (AND)
+//!
+//! 盲化索引會洩漏詞元的出現頻率 (相同詞元的 HMAC 相同)，但不洩漏內容
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::encryption::{RecordCodec, StorageKey, SNAPSHOT_STORE};
+
+/// 盲化詞元時使用的欄位名稱
+pub(crate) const SEARCH_TOKEN_FIELD: &str = "search_token";
+const SEARCH_SNAPSHOT_KEY: &str = "search_index";
+
+/// 中日韓文字 (統一表意文字、假名、諺文)
+fn is_cjk(c: char) -> bool {
+    matches!(
+        c as u32,
+        0x3040..=0x30FF | 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F
+    )
+}
+
+/// 將文字正規化為不重複的搜尋詞元
+pub(crate) fn search_tokens(text: &str) -> BTreeSet<String> {
+    let mut tokens = BTreeSet::new();
+    let mut word = String::new();
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if is_cjk(c) {
+            tokens.extend((!word.is_empty()).then(|| std::mem::take(&mut word)));
+            tokens.insert(c.to_string());
+        } else if c.is_alphanumeric() {
+            word.push(c);
+        } else if !word.is_empty() {
+            tokens.insert(std::mem::take(&mut word));
+        }
+    }
+    tokens.extend((!word.is_empty()).then_some(word));
+    tokens
+}
+
+/// 可序列化的索引內容 (金鑰不序列化)
+#[derive(Clone, Default, Serialize, Deserialize)]
+struct SearchPostings {
+    /// 盲化詞元 -> 訊息 ID
+    postings: BTreeMap<[u8; 32], BTreeSet<String>>,
+    /// 訊息 ID -> 盲化詞元 (重新索引與刪除用)
+    message_tokens: BTreeMap<String, BTreeSet<[u8; 32]>>,
+}
+
+/// 記憶體搜尋索引，與 `InMemoryMessageStore` 搭配使用
+#[wasm_bindgen]
+#[derive(Clone)]
+pub struct InMemorySearchIndex {
+    key: StorageKey,
+    index: SearchPostings,
+}
+
+impl InMemorySearchIndex {
+    fn blind_tokens(&self, text: &str) -> BTreeSet<[u8; 32]> {
+        search_tokens(text)
+            .iter()
+            .map(|token| self.key.blind_index(SEARCH_TOKEN_FIELD, token.as_bytes()))
+            .collect()
+    }
+}
+
+#[wasm_bindgen]
+impl InMemorySearchIndex {
+    /// 建立空的搜尋索引
+    #[wasm_bindgen(constructor)]
+    pub fn new(key: &StorageKey) -> Self {
+        Self { key: key.clone(), index: SearchPostings::default() }
+    }
+
+    /// 索引 (或重新索引) 訊息文字
+    #[wasm_bindgen(js_name = indexMessage)]
+    pub fn index_message(&mut self, id: &str, text: &str) {
+        self.remove_message(id);
+        let tokens = self.blind_tokens(text);
+        for token in &tokens {
+            self.index.postings.entry(*token).or_default().insert(id.to_string());
+        }
+        self.index.message_tokens.insert(id.to_string(), tokens);
+    }
+
+    /// 從索引中移除訊息，回傳是否存在
+    #[wasm_bindgen(js_name = removeMessage)]
+    pub fn remove_message(&mut self, id: &str) -> bool {
+        let Some(tokens) = self.index.message_tokens.remove(id) else {
+            return false;
+        };
+        for token in tokens {
+            if let Some(ids) = self.index.postings.get_mut(&token) {
+                ids.remove(id);
+                if ids.is_empty() {
+                    self.index.postings.remove(&token);
+                }
+            }
+        }
+        true
+    }
+
+    /// 搜尋包含查詢中所有詞元的訊息，回傳依 ID 排序的訊息 ID
+    pub fn search(&self, query: &str) -> Vec<String> {
+        let tokens = self.blind_tokens(query);
+        let mut postings = tokens.iter().map(|token| self.index.postings.get(token));
+        let Some(Some(first)) = postings.next() else {
+            return Vec::new();
+        };
+        let mut matches = first.clone();
+        for ids in postings {
+            match ids {
+                Some(ids) => matches.retain(|id| ids.contains(id)),
+                None => return Vec::new(),
+            }
+        }
+        matches.into_iter().collect()
+    }
+
+    /// 已索引的訊息數量
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.index.message_tokens.len()
+    }
+
+    /// 以主儲存金鑰加密序列化
+    #[wasm_bindgen(js_name = serializeEncrypted)]
+    pub fn serialize_encrypted(&self) -> Result<Vec<u8>, JsError> {
+        RecordCodec::new(Some(self.key.clone()))
+            .encode(SNAPSHOT_STORE, SEARCH_SNAPSHOT_KEY, &self.index)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密並還原
+    #[wasm_bindgen(js_name = deserializeEncrypted)]
+    pub fn deserialize_encrypted(bytes: &[u8], key: &StorageKey) -> Result<InMemorySearchIndex, JsError> {
+        let index = RecordCodec::new(Some(key.clone()))
+            .decode(SNAPSHOT_STORE, SEARCH_SNAPSHOT_KEY, bytes)
+            .map_err(|e| JsError::new(&e))?;
+        Ok(Self { key: key.clone(), index })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_tokens() {
+        let tokens: Vec<String> = search_tokens("Hello, WORLD! hello-again 明天見 ok").into_iter().collect();
+        assert_eq!(tokens, vec!["again", "hello", "ok", "world", "天", "明", "見"]);
+        assert!(search_tokens(" ,.!? ").is_empty());
+        assert_eq!(search_tokens("東京tokyo").len(), 3);
+    }
+
+    #[test]
+    fn test_in_memory_search_index() {
+        let key = StorageKey::generate();
+        let mut index = InMemorySearchIndex::new(&key);
+        index.index_message("m1", "Meet me at the café tomorrow");
+        index.index_message("m2", "明天在咖啡廳見");
+        index.index_message("m3", "See you TOMORROW");
+
+        assert_eq!(index.search("tomorrow"), vec!["m1", "m3"]);
+        assert_eq!(index.search("café TOMORROW"), vec!["m1"]);
+        assert_eq!(index.search("咖啡"), vec!["m2"]);
+        assert!(index.search("tomorrow 咖啡").is_empty());
+        assert!(index.search("").is_empty());
+
+        // 重新索引與刪除
+        index.index_message("m3", "see you later");
+        assert_eq!(index.search("tomorrow"), vec!["m1"]);
+        assert!(index.remove_message("m1"));
+        assert!(!index.remove_message("m1"));
+        assert!(index.search("tomorrow").is_empty());
+        assert_eq!(index.size(), 2);
+
+        // 索引中不含明文詞元
+        let sealed = index.serialize_encrypted().unwrap();
+        let restored = InMemorySearchIndex::deserialize_encrypted(&sealed, &key).unwrap();
+        assert_eq!(restored.search("later"), vec!["m3"]);
+        let postings = bincode::serialize(&restored.index).unwrap();
+        assert!(!postings.windows(5).any(|window| window == b"later"));
+    }
+}
@@ -0,0 +1,282 @@
+//! 會話管理模組
+//!
+//! 快取聯絡人各裝置的 PreKeyBundle，並套用聯絡人透過會話廣播的
+//! 金鑰更新公告，讓快取在兩次向伺服器取得之間也保持最新
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{KeyUpdate, KeyUpdateContent, PreKeyBundle, SignedPreKey};
+
+/// 快取的 bundle
+#[derive(Clone, Serialize, Deserialize)]
+struct CachedBundle {
+    bundle: PreKeyBundle,
+    /// 最後更新時間 (伺服器取得或金鑰更新公告，Unix 毫秒)
+    updated_at: u64,
+}
+
+/// 單一聯絡人的快取金鑰資料
+#[derive(Clone, Serialize, Deserialize)]
+struct ContactKeys {
+    identity_key: Vec<u8>,
+    bundles: BTreeMap<u32, CachedBundle>,
+    /// 已知裝置 (包含尚未取得 bundle 的新裝置)
+    devices: BTreeSet<u32>,
+    /// 最後套用的公告序號
+    last_sequence: Option<u64>,
+    /// 身份輪替後的後繼公鑰
+    successor_key: Option<Vec<u8>>,
+}
+
+impl ContactKeys {
+    fn new(identity_key: &[u8]) -> Self {
+        Self {
+            identity_key: identity_key.to_vec(),
+            bundles: BTreeMap::new(),
+            devices: BTreeSet::new(),
+            last_sequence: None,
+            successor_key: None,
+        }
+    }
+}
+
+/// 會話管理器
+#[wasm_bindgen]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct SessionManager {
+    contacts: BTreeMap<String, ContactKeys>,
+}
+
+impl SessionManager {
+    /// 套用金鑰更新公告
+    ///
+    /// 公告必須由快取中的身份公鑰簽署，且序號必須大於上次套用的序號
+    pub fn apply_key_update(&mut self, contact_id: &str, update: &KeyUpdate, now: u64) -> Result<(), String> {
+        update.verify_signature()?;
+        let contact = self
+            .contacts
+            .get_mut(contact_id)
+            .ok_or_else(|| format!("No cached keys for {}", contact_id))?;
+        if contact.identity_key != update.identity_key() {
+            return Err("Key update from a different identity".to_string());
+        }
+        if contact.last_sequence.is_some_and(|last| update.sequence() <= last) {
+            return Err("Stale or replayed key update".to_string());
+        }
+
+        match update.content() {
+            KeyUpdateContent::SignedPreKey { device_id, key_id, public_key, signature, timestamp } => {
+                if let Some(cached) = contact.bundles.get_mut(device_id) {
+                    cached.bundle.signed_pre_key = SignedPreKey {
+                        key_id: *key_id,
+                        public_key: public_key.clone(),
+                        signature: signature.clone(),
+                        timestamp: *timestamp,
+                    };
+                    // 整體簽章涵蓋舊的預金鑰，已不再適用
+                    cached.bundle.bundle_signature = None;
+                    cached.updated_at = now;
+                }
+                contact.devices.insert(*device_id);
+            }
+            KeyUpdateContent::NewDevice { certificate } => {
+                contact.devices.insert(certificate.device_id());
+            }
+            KeyUpdateContent::IdentityRotation { revocation } => {
+                // 舊身份的 bundle 一律作廢，需以新身份重新取得並驗證
+                contact.bundles.clear();
+                contact.successor_key = revocation.successor_key();
+            }
+        }
+        contact.last_sequence = Some(update.sequence());
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl SessionManager {
+    /// 建立空的會話管理器
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 快取從伺服器取得的 bundle
+    ///
+    /// 身份公鑰與快取不同時，舊的快取資料會被清除
+    #[wasm_bindgen(js_name = cacheBundle)]
+    pub fn cache_bundle(&mut self, contact_id: &str, device_id: u32, bundle: &PreKeyBundle, now: u64) {
+        let contact = self
+            .contacts
+            .entry(contact_id.to_string())
+            .or_insert_with(|| ContactKeys::new(&bundle.identity_key));
+        if contact.identity_key != bundle.identity_key {
+            *contact = ContactKeys::new(&bundle.identity_key);
+        }
+        contact.devices.insert(device_id);
+        contact.bundles.insert(
+            device_id,
+            CachedBundle { bundle: bundle.clone(), updated_at: now },
+        );
+    }
+
+    /// 取得快取的 bundle
+    #[wasm_bindgen(js_name = cachedBundle)]
+    pub fn cached_bundle(&self, contact_id: &str, device_id: u32) -> Option<PreKeyBundle> {
+        self.contacts
+            .get(contact_id)
+            .and_then(|contact| contact.bundles.get(&device_id))
+            .map(|cached| cached.bundle.clone())
+    }
+
+    /// bundle 是否不存在或超過 `max_age_ms` 未更新
+    #[wasm_bindgen(js_name = isStale)]
+    pub fn is_stale(&self, contact_id: &str, device_id: u32, now: u64, max_age_ms: u64) -> bool {
+        self.contacts
+            .get(contact_id)
+            .and_then(|contact| contact.bundles.get(&device_id))
+            .is_none_or(|cached| now.saturating_sub(cached.updated_at) > max_age_ms)
+    }
+
+    /// 已知裝置 ID
+    #[wasm_bindgen(js_name = knownDevices)]
+    pub fn known_devices(&self, contact_id: &str) -> Vec<u32> {
+        self.contacts
+            .get(contact_id)
+            .map(|contact| contact.devices.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// 已知但尚未快取 bundle 的裝置 (需要向伺服器取得)
+    #[wasm_bindgen(js_name = devicesNeedingFetch)]
+    pub fn devices_needing_fetch(&self, contact_id: &str) -> Vec<u32> {
+        self.contacts
+            .get(contact_id)
+            .map(|contact| {
+                contact
+                    .devices
+                    .iter()
+                    .filter(|id| !contact.bundles.contains_key(id))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// 身份輪替公告指向的後繼公鑰
+    #[wasm_bindgen(js_name = successorKey)]
+    pub fn successor_key(&self, contact_id: &str) -> Option<Vec<u8>> {
+        self.contacts.get(contact_id).and_then(|contact| contact.successor_key.clone())
+    }
+
+    /// 套用金鑰更新公告
+    #[wasm_bindgen(js_name = applyKeyUpdate)]
+    pub fn apply_key_update_message(&mut self, contact_id: &str, update: &KeyUpdate, now: u64) -> Result<(), JsError> {
+        self.apply_key_update(contact_id, update, now).map_err(|e| JsError::new(&e))
+    }
+
+    /// 移除聯絡人的快取
+    #[wasm_bindgen(js_name = removeContact)]
+    pub fn remove_contact(&mut self, contact_id: &str) -> bool {
+        self.contacts.remove(contact_id).is_some()
+    }
+
+    /// 序列化
+    #[wasm_bindgen(js_name = serialize)]
+    pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 還原
+    #[wasm_bindgen(js_name = deserialize)]
+    pub fn deserialize(bytes: &[u8]) -> Result<SessionManager, JsError> {
+        bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{DeviceCertificate, IdentityKeyPair, RevocationCertificate, RevocationReason, X25519KeyPair};
+
+    fn bundle_for(identity: &IdentityKeyPair) -> PreKeyBundle {
+        let prekey = X25519KeyPair::new();
+        PreKeyBundle::new(
+            identity.public_key_bytes(),
+            SignedPreKey {
+                key_id: 1,
+                public_key: prekey.public_key_bytes(),
+                signature: identity.sign(&prekey.public_key_bytes()),
+                timestamp: 0,
+            },
+            None,
+        )
+    }
+
+    #[test]
+    fn test_key_updates_refresh_cache() {
+        let bob = IdentityKeyPair::new();
+        let mut manager = SessionManager::new();
+        manager.cache_bundle("bob", 1, &bundle_for(&bob), 0);
+        assert!(manager.is_stale("bob", 1, 10_000, 5_000));
+
+        // 新的簽署預金鑰
+        let prekey = X25519KeyPair::new();
+        let update = KeyUpdate::create(
+            &bob,
+            1,
+            9_000,
+            KeyUpdateContent::SignedPreKey {
+                device_id: 1,
+                key_id: 2,
+                public_key: prekey.public_key_bytes(),
+                signature: bob.sign(&prekey.public_key_bytes()),
+                timestamp: 9_000,
+            },
+        )
+        .unwrap();
+        manager.apply_key_update("bob", &update, 9_000).unwrap();
+        let cached = manager.cached_bundle("bob", 1).unwrap();
+        assert_eq!(cached.signed_pre_key.key_id, 2);
+        assert!(!manager.is_stale("bob", 1, 10_000, 5_000));
+
+        // 重放被拒絕
+        assert!(manager.apply_key_update("bob", &update, 9_500).is_err());
+
+        // 新裝置
+        let device = DeviceCertificate::issue(&bob, 2, &IdentityKeyPair::new().public_key_bytes(), &[5u8; 32], 9_000);
+        let update = KeyUpdate::create(&bob, 2, 9_000, KeyUpdateContent::NewDevice { certificate: device }).unwrap();
+        manager.apply_key_update("bob", &update, 9_000).unwrap();
+        assert_eq!(manager.known_devices("bob"), vec![1, 2]);
+        assert_eq!(manager.devices_needing_fetch("bob"), vec![2]);
+    }
+
+    #[test]
+    fn test_identity_rotation_invalidates_cache() {
+        let bob = IdentityKeyPair::new();
+        let successor = IdentityKeyPair::new();
+        let mut manager = SessionManager::new();
+        manager.cache_bundle("bob", 1, &bundle_for(&bob), 0);
+
+        // 陌生身份的公告不被接受
+        let mallory = IdentityKeyPair::new();
+        let self_successor =
+            RevocationCertificate::create(&mallory, RevocationReason::Superseded, 1, Some(&mallory.public_key_bytes()));
+        let content = KeyUpdateContent::IdentityRotation { revocation: self_successor };
+        assert!(KeyUpdate::create(&mallory, 1, 1, content).is_err());
+        let other = KeyUpdate::identity_rotation(&mallory, 1, 1, &successor.public_key_bytes()).unwrap();
+        assert!(manager.apply_key_update("bob", &other, 1).is_err());
+
+        let rotation = KeyUpdate::identity_rotation(&bob, 1, 1, &successor.public_key_bytes()).unwrap();
+        manager.apply_key_update("bob", &rotation, 1).unwrap();
+        assert!(manager.cached_bundle("bob", 1).is_none());
+        assert_eq!(manager.successor_key("bob"), Some(successor.public_key_bytes()));
+        assert_eq!(manager.devices_needing_fetch("bob"), vec![1]);
+
+        let restored = SessionManager::deserialize(&manager.serialize().unwrap()).unwrap();
+        assert_eq!(restored.successor_key("bob"), Some(successor.public_key_bytes()));
+    }
+}
@@ -0,0 +1,355 @@
+//! 設定儲存模組
+//!
+//! 隱私相關的偏好設定 (已讀回條、輸入中提示等) 以型別化的值存放，
+//! 與其他記憶體儲存一樣以主儲存金鑰加密序列化，協定層與 UI 讀取同一份設定：
+//! - 每個設定有定義 (布林、字串或選項) 與預設值，未設定時回傳預設值
+//! - 寫入時檢查型別與選項，未定義的設定一律拒絕；應用程式可另外定義自己的設定
+//! - 值變更或重設時通知訂閱者 (`StoreEventKind::SettingChanged`)
+//!
+//! 只保存與預設值不同的設定；定義不序列化，還原後應用程式需重新定義自訂設定。
+//! 已保存的值與目前的定義不符 (例如選項被移除) 時視為預設值
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+use super::encryption::{RecordCodec, StorageKey, SNAPSHOT_STORE};
+use super::events::{StoreEvent, StoreObservers};
+
+const SETTINGS_SNAPSHOT_KEY: &str = "settings_store";
+
+/// 傳送已讀回條 (預設開啟)
+pub const SETTING_READ_RECEIPTS: &str = "read_receipts";
+/// 傳送輸入中提示 (預設開啟)
+pub const SETTING_TYPING_INDICATORS: &str = "typing_indicators";
+/// 產生連結預覽 (預設開啟)
+pub const SETTING_LINK_PREVIEWS: &str = "link_previews";
+/// 新對話的預設限時訊息時間 (預設 `off`)
+pub const SETTING_DEFAULT_DISAPPEARING_TIMER: &str = "default_disappearing_timer";
+
+/// 限時訊息時間的選項
+const DISAPPEARING_TIMER_OPTIONS: [&str; 5] = ["off", "1h", "1d", "1w", "4w"];
+
+/// 設定值
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SettingValue {
+    Boolean(bool),
+    Text(String),
+    /// 定義中列出的選項之一
+    Choice(String),
+}
+
+/// 設定定義
+#[derive(Clone, Debug)]
+pub struct SettingDefinition {
+    default: SettingValue,
+    /// 選項 (僅 `Choice`)
+    options: Vec<String>,
+}
+
+impl SettingDefinition {
+    pub fn boolean(default: bool) -> Self {
+        Self { default: SettingValue::Boolean(default), options: Vec::new() }
+    }
+
+    pub fn text(default: &str) -> Self {
+        Self { default: SettingValue::Text(default.to_string()), options: Vec::new() }
+    }
+
+    /// 選項設定，預設值必須是選項之一
+    pub fn choice(options: &[&str], default: &str) -> Result<Self, String> {
+        if !options.contains(&default) {
+            return Err(format!("Default option {} is not one of the options", default));
+        }
+        Ok(Self {
+            default: SettingValue::Choice(default.to_string()),
+            options: options.iter().map(|option| option.to_string()).collect(),
+        })
+    }
+
+    /// 值是否符合此定義
+    fn accepts(&self, value: &SettingValue) -> bool {
+        match (&self.default, value) {
+            (SettingValue::Boolean(_), SettingValue::Boolean(_)) => true,
+            (SettingValue::Text(_), SettingValue::Text(_)) => true,
+            (SettingValue::Choice(_), SettingValue::Choice(option)) => self.options.contains(option),
+            _ => false,
+        }
+    }
+}
+
+/// 內建的設定定義
+fn builtin_definitions() -> BTreeMap<String, SettingDefinition> {
+    let mut definitions = BTreeMap::new();
+    for key in [SETTING_READ_RECEIPTS, SETTING_TYPING_INDICATORS, SETTING_LINK_PREVIEWS] {
+        definitions.insert(key.to_string(), SettingDefinition::boolean(true));
+    }
+    definitions.insert(
+        SETTING_DEFAULT_DISAPPEARING_TIMER.to_string(),
+        SettingDefinition::choice(&DISAPPEARING_TIMER_OPTIONS, "off").expect("default is one of the options"),
+    );
+    definitions
+}
+
+/// 設定儲存
+pub trait SettingsStore {
+    /// 目前的值 (未設定時為預設值)
+    fn setting(&self, key: &str) -> Result<SettingValue, String>;
+
+    /// 寫入值 (型別與選項需符合定義)
+    fn set_setting(&mut self, key: &str, value: SettingValue) -> Result<(), String>;
+
+    /// 重設為預設值，回傳先前是否有設定
+    fn reset_setting(&mut self, key: &str) -> Result<bool, String>;
+
+    /// 布林設定的值
+    fn bool_setting(&self, key: &str) -> Result<bool, String> {
+        match self.setting(key)? {
+            SettingValue::Boolean(value) => Ok(value),
+            _ => Err(format!("Setting {} is not a boolean", key)),
+        }
+    }
+
+    /// 字串或選項設定的值
+    fn text_setting(&self, key: &str) -> Result<String, String> {
+        match self.setting(key)? {
+            SettingValue::Text(value) | SettingValue::Choice(value) => Ok(value),
+            SettingValue::Boolean(_) => Err(format!("Setting {} is not a string", key)),
+        }
+    }
+
+    /// 是否傳送已讀回條
+    fn read_receipts_enabled(&self) -> Result<bool, String> {
+        self.bool_setting(SETTING_READ_RECEIPTS)
+    }
+
+    /// 是否傳送輸入中提示
+    fn typing_indicators_enabled(&self) -> Result<bool, String> {
+        self.bool_setting(SETTING_TYPING_INDICATORS)
+    }
+}
+
+/// 記憶體設定儲存，以 `serializeEncrypted` 持久化
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InMemorySettingsStore {
+    /// key -> 與預設值不同的值
+    values: BTreeMap<String, SettingValue>,
+    /// 設定定義，不序列化
+    #[serde(skip, default = "builtin_definitions")]
+    definitions: BTreeMap<String, SettingDefinition>,
+    /// 變更訂閱者，不序列化
+    #[serde(skip)]
+    observers: StoreObservers,
+}
+
+impl Default for InMemorySettingsStore {
+    fn default() -> Self {
+        Self { values: BTreeMap::new(), definitions: builtin_definitions(), observers: StoreObservers::default() }
+    }
+}
+
+impl SettingsStore for InMemorySettingsStore {
+    fn setting(&self, key: &str) -> Result<SettingValue, String> {
+        let definition = self.definition(key)?;
+        Ok(self
+            .values
+            .get(key)
+            .filter(|value| definition.accepts(value))
+            .unwrap_or(&definition.default)
+            .clone())
+    }
+
+    fn set_setting(&mut self, key: &str, value: SettingValue) -> Result<(), String> {
+        let definition = self.definition(key)?;
+        if !definition.accepts(&value) {
+            return Err(format!("Invalid value for setting {}", key));
+        }
+        if self.setting(key)? == value {
+            return Ok(());
+        }
+        if value == definition.default {
+            self.values.remove(key);
+        } else {
+            self.values.insert(key.to_string(), value);
+        }
+        self.observers.emit(StoreEvent::setting(key));
+        Ok(())
+    }
+
+    fn reset_setting(&mut self, key: &str) -> Result<bool, String> {
+        let previous = self.setting(key)?;
+        let existed = self.values.remove(key).is_some();
+        if previous != self.definition(key)?.default {
+            self.observers.emit(StoreEvent::setting(key));
+        }
+        Ok(existed)
+    }
+}
+
+impl InMemorySettingsStore {
+    fn definition(&self, key: &str) -> Result<&SettingDefinition, String> {
+        self.definitions.get(key).ok_or_else(|| format!("Unknown setting: {}", key))
+    }
+
+    /// 定義應用程式的設定 (Rust 端使用)；內建設定不可重新定義
+    pub fn define(&mut self, key: &str, definition: SettingDefinition) -> Result<(), String> {
+        if builtin_definitions().contains_key(key) {
+            return Err(format!("Setting {} is built in", key));
+        }
+        self.definitions.insert(key.to_string(), definition);
+        Ok(())
+    }
+
+    /// 變更訂閱者 (Rust 端訂閱用)
+    pub fn observers(&self) -> &StoreObservers {
+        &self.observers
+    }
+}
+
+#[wasm_bindgen]
+impl InMemorySettingsStore {
+    /// 建立只含內建設定的儲存
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 定義布林設定
+    #[wasm_bindgen(js_name = defineBoolean)]
+    pub fn define_boolean(&mut self, key: &str, default: bool) -> Result<(), JsError> {
+        self.define(key, SettingDefinition::boolean(default)).map_err(|e| JsError::new(&e))
+    }
+
+    /// 定義字串設定
+    #[wasm_bindgen(js_name = defineText)]
+    pub fn define_text(&mut self, key: &str, default: &str) -> Result<(), JsError> {
+        self.define(key, SettingDefinition::text(default)).map_err(|e| JsError::new(&e))
+    }
+
+    /// 定義選項設定
+    #[wasm_bindgen(js_name = defineChoice)]
+    pub fn define_choice(&mut self, key: &str, options: Vec<String>, default: &str) -> Result<(), JsError> {
+        let options: Vec<&str> = options.iter().map(String::as_str).collect();
+        SettingDefinition::choice(&options, default)
+            .and_then(|definition| self.define(key, definition))
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 讀取布林設定
+    #[wasm_bindgen(js_name = getBoolean)]
+    pub fn get_boolean(&self, key: &str) -> Result<bool, JsError> {
+        self.bool_setting(key).map_err(|e| JsError::new(&e))
+    }
+
+    /// 寫入布林設定
+    #[wasm_bindgen(js_name = setBoolean)]
+    pub fn set_boolean(&mut self, key: &str, value: bool) -> Result<(), JsError> {
+        self.set_setting(key, SettingValue::Boolean(value)).map_err(|e| JsError::new(&e))
+    }
+
+    /// 讀取字串或選項設定
+    #[wasm_bindgen(js_name = getString)]
+    pub fn get_string(&self, key: &str) -> Result<String, JsError> {
+        self.text_setting(key).map_err(|e| JsError::new(&e))
+    }
+
+    /// 寫入字串或選項設定
+    #[wasm_bindgen(js_name = setString)]
+    pub fn set_string(&mut self, key: &str, value: &str) -> Result<(), JsError> {
+        let set = |store: &mut Self| {
+            let value = match store.definition(key)?.default {
+                SettingValue::Choice(_) => SettingValue::Choice(value.to_string()),
+                _ => SettingValue::Text(value.to_string()),
+            };
+            store.set_setting(key, value)
+        };
+        set(self).map_err(|e| JsError::new(&e))
+    }
+
+    /// 重設為預設值，回傳先前是否有設定
+    #[wasm_bindgen(js_name = reset)]
+    pub fn reset_js(&mut self, key: &str) -> Result<bool, JsError> {
+        self.reset_setting(key).map_err(|e| JsError::new(&e))
+    }
+
+    /// 所有已定義的設定 (排序)
+    #[wasm_bindgen(getter)]
+    pub fn keys(&self) -> Vec<String> {
+        self.definitions.keys().cloned().collect()
+    }
+
+    /// 訂閱設定變更：`(event: StoreEvent) => void`，回傳取消訂閱用的 ID
+    #[wasm_bindgen(js_name = onChange)]
+    pub fn on_change(&self, callback: js_sys::Function) -> u32 {
+        self.observers.subscribe_js(callback)
+    }
+
+    /// 取消訂閱，回傳是否存在
+    #[wasm_bindgen(js_name = offChange)]
+    pub fn off_change(&self, id: u32) -> bool {
+        self.observers.unsubscribe(id)
+    }
+
+    /// 以主儲存金鑰加密序列化
+    #[wasm_bindgen(js_name = serializeEncrypted)]
+    pub fn serialize_encrypted(&self, key: &StorageKey) -> Result<Vec<u8>, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .encode(SNAPSHOT_STORE, SETTINGS_SNAPSHOT_KEY, self)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密並還原
+    #[wasm_bindgen(js_name = deserializeEncrypted)]
+    pub fn deserialize_encrypted(bytes: &[u8], key: &StorageKey) -> Result<InMemorySettingsStore, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .decode(SNAPSHOT_STORE, SETTINGS_SNAPSHOT_KEY, bytes)
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_settings_store() {
+        let mut settings = InMemorySettingsStore::new();
+        let changes = Rc::new(RefCell::new(Vec::new()));
+        let recorded = changes.clone();
+        settings.observers().subscribe(move |event| recorded.borrow_mut().push(event.setting_key().unwrap()));
+
+        assert!(settings.read_receipts_enabled().unwrap());
+        settings.set_setting(SETTING_READ_RECEIPTS, SettingValue::Boolean(false)).unwrap();
+        settings.set_setting(SETTING_READ_RECEIPTS, SettingValue::Boolean(false)).unwrap();
+        assert!(!settings.read_receipts_enabled().unwrap());
+        assert_eq!(settings.text_setting(SETTING_DEFAULT_DISAPPEARING_TIMER).unwrap(), "off");
+        settings.set_setting(SETTING_DEFAULT_DISAPPEARING_TIMER, SettingValue::Choice("1d".to_string())).unwrap();
+
+        // 型別、選項與未定義的設定
+        assert!(settings.set_setting(SETTING_TYPING_INDICATORS, SettingValue::Text("no".to_string())).is_err());
+        assert!(settings.set_setting(SETTING_DEFAULT_DISAPPEARING_TIMER, SettingValue::Choice("2d".to_string())).is_err());
+        assert!(settings.setting("theme").is_err());
+        settings.define("theme", SettingDefinition::choice(&["light", "dark"], "light").unwrap()).unwrap();
+        settings.set_setting("theme", SettingValue::Choice("dark".to_string())).unwrap();
+        assert!(settings.define(SETTING_READ_RECEIPTS, SettingDefinition::boolean(false)).is_err());
+
+        let key = StorageKey::generate();
+        let restored = InMemorySettingsStore::deserialize_encrypted(&settings.serialize_encrypted(&key).unwrap(), &key).unwrap();
+        assert!(!restored.read_receipts_enabled().unwrap());
+        assert_eq!(restored.text_setting(SETTING_DEFAULT_DISAPPEARING_TIMER).unwrap(), "1d");
+        // 自訂設定需重新定義
+        assert!(restored.setting("theme").is_err());
+
+        assert!(settings.reset_setting(SETTING_READ_RECEIPTS).unwrap());
+        assert!(!settings.reset_setting(SETTING_READ_RECEIPTS).unwrap());
+        assert!(settings.read_receipts_enabled().unwrap());
+        assert_eq!(
+            *changes.borrow(),
+            vec![SETTING_READ_RECEIPTS, SETTING_DEFAULT_DISAPPEARING_TIMER, "theme", SETTING_READ_RECEIPTS]
+        );
+    }
+}
@@ -0,0 +1,223 @@
+//! 金鑰庫快照模組
+//!
+//! 將身份金鑰、簽署預金鑰與一次性預金鑰池收進單一、帶版本號的結構，
+//! 供持久化與備份使用，不必分別保存各種原始位元組陣列
+//!
+//! 快照包含所有私鑰 (敏感！)，落地前應先加密
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{IdentityKeyPair, OneTimePreKeyPool, SignedPreKeyRecord};
+use super::keystore::KeyStore;
+
+/// 目前的快照格式版本
+pub const KEYSTORE_SNAPSHOT_VERSION: u32 = 1;
+
+/// 金鑰庫快照
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct KeyStoreSnapshot {
+    version: u32,
+    identity: IdentityKeyPair,
+    registration_id: u32,
+    device_id: u32,
+    signed_pre_keys: Vec<SignedPreKeyRecord>,
+    one_time_pre_keys: OneTimePreKeyPool,
+}
+
+impl KeyStoreSnapshot {
+    /// 身份金鑰對
+    pub fn identity(&self) -> &IdentityKeyPair {
+        &self.identity
+    }
+
+    /// 簽署預金鑰記錄
+    pub fn signed_pre_key_records(&self) -> &[SignedPreKeyRecord] {
+        &self.signed_pre_keys
+    }
+
+    /// 從位元組還原 (Rust 端使用)
+    pub fn from_slice(bytes: &[u8]) -> Result<Self, String> {
+        let snapshot: Self = bincode::deserialize(bytes).map_err(|e| format!("Invalid keystore snapshot: {}", e))?;
+        if snapshot.version != KEYSTORE_SNAPSHOT_VERSION {
+            return Err(format!("Unsupported keystore snapshot version: {}", snapshot.version));
+        }
+        Ok(snapshot)
+    }
+}
+
+#[wasm_bindgen]
+impl KeyStoreSnapshot {
+    /// 建立快照
+    #[wasm_bindgen(constructor)]
+    pub fn new(
+        identity: &IdentityKeyPair,
+        registration_id: u32,
+        device_id: u32,
+        one_time_pre_keys: &OneTimePreKeyPool,
+    ) -> Self {
+        Self {
+            version: KEYSTORE_SNAPSHOT_VERSION,
+            identity: identity.clone(),
+            registration_id,
+            device_id,
+            signed_pre_keys: Vec::new(),
+            one_time_pre_keys: one_time_pre_keys.clone(),
+        }
+    }
+
+    /// 加入簽署預金鑰 (相同 key ID 會被取代)
+    #[wasm_bindgen(js_name = addSignedPreKey)]
+    pub fn add_signed_pre_key(&mut self, record: &SignedPreKeyRecord) {
+        self.signed_pre_keys.retain(|existing| existing.key_id() != record.key_id());
+        self.signed_pre_keys.push(record.clone());
+    }
+
+    /// 格式版本
+    #[wasm_bindgen(getter)]
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// 身份金鑰對
+    #[wasm_bindgen(js_name = identityKeyPair)]
+    pub fn identity_key_pair(&self) -> IdentityKeyPair {
+        self.identity.clone()
+    }
+
+    #[wasm_bindgen(getter, js_name = registrationId)]
+    pub fn registration_id(&self) -> u32 {
+        self.registration_id
+    }
+
+    #[wasm_bindgen(getter, js_name = deviceId)]
+    pub fn device_id(&self) -> u32 {
+        self.device_id
+    }
+
+    /// 取得指定的簽署預金鑰
+    #[wasm_bindgen(js_name = signedPreKey)]
+    pub fn signed_pre_key(&self, key_id: u32) -> Option<SignedPreKeyRecord> {
+        self.signed_pre_keys.iter().find(|record| record.key_id() == key_id).cloned()
+    }
+
+    /// 所有簽署預金鑰 ID
+    #[wasm_bindgen(js_name = signedPreKeyIds)]
+    pub fn signed_pre_key_ids(&self) -> Vec<u32> {
+        self.signed_pre_keys.iter().map(SignedPreKeyRecord::key_id).collect()
+    }
+
+    /// 一次性預金鑰池
+    #[wasm_bindgen(js_name = oneTimePreKeys)]
+    pub fn one_time_pre_keys(&self) -> OneTimePreKeyPool {
+        self.one_time_pre_keys.clone()
+    }
+
+    /// 序列化 (包含私鑰，敏感！)
+    #[wasm_bindgen(js_name = toBytes)]
+    pub fn to_bytes(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 還原，版本不符時回傳錯誤
+    #[wasm_bindgen(js_name = fromBytes)]
+    pub fn from_bytes(bytes: &[u8]) -> Result<KeyStoreSnapshot, JsError> {
+        Self::from_slice(bytes).map_err(|e| JsError::new(&e))
+    }
+}
+
+impl KeyStore {
+    /// 建立帳號的快照 (Rust 端使用)
+    pub fn account_snapshot(&self, account_id: &str) -> Result<KeyStoreSnapshot, String> {
+        let account = self.account(account_id)?;
+        let identity = IdentityKeyPair::from_bytes(account.identity_private_key())
+            .map_err(|_| "Invalid identity private key".to_string())?;
+        Ok(KeyStoreSnapshot::new(
+            &identity,
+            account.registration_id,
+            account.device_id,
+            &account.pre_key_pool,
+        ))
+    }
+
+    /// 從快照還原帳號 (Rust 端使用)
+    pub fn restore_account_snapshot(&mut self, account_id: &str, snapshot: &KeyStoreSnapshot) -> Result<(), String> {
+        self.insert_account(account_id, &snapshot.identity, snapshot.device_id)?;
+        let account = self.account_mut(account_id)?;
+        account.registration_id = snapshot.registration_id;
+        account.pre_key_pool = snapshot.one_time_pre_keys.clone();
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+impl KeyStore {
+    /// 建立帳號的金鑰快照 (不含會話與信任儲存)
+    #[wasm_bindgen(js_name = snapshotAccount)]
+    pub fn snapshot_account(&self, account_id: &str) -> Result<KeyStoreSnapshot, JsError> {
+        self.account_snapshot(account_id).map_err(|e| JsError::new(&e))
+    }
+
+    /// 從快照還原帳號
+    #[wasm_bindgen(js_name = restoreAccount)]
+    pub fn restore_account(&mut self, account_id: &str, snapshot: &KeyStoreSnapshot) -> Result<(), JsError> {
+        self.restore_account_snapshot(account_id, snapshot).map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::X25519KeyPair;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let identity = IdentityKeyPair::new();
+        let mut pool = OneTimePreKeyPool::new(1, None, None);
+        let batch = pool.generate_keys(3).unwrap();
+
+        let mut snapshot = KeyStoreSnapshot::new(&identity, 1234, 1, &pool);
+        let spk = SignedPreKeyRecord::generate(&identity, 7, 1000);
+        snapshot.add_signed_pre_key(&spk);
+
+        let restored = KeyStoreSnapshot::from_slice(&snapshot.to_bytes().unwrap()).unwrap();
+        assert_eq!(restored.identity().public_key_bytes(), identity.public_key_bytes());
+        assert_eq!(restored.registration_id(), 1234);
+        assert_eq!(restored.signed_pre_key_ids(), vec![7]);
+
+        let restored_spk = restored.signed_pre_key(7).unwrap();
+        assert_eq!(restored_spk.public_key(), spk.public_key());
+        assert!(IdentityKeyPair::verify_signature(
+            &identity.public_key_bytes(),
+            &restored_spk.public().public_key,
+            &restored_spk.signature()
+        ));
+
+        // 一次性預金鑰私鑰完整保留
+        let mut restored_pool = restored.one_time_pre_keys();
+        let private = restored_pool.consume(batch[1].key_id).unwrap();
+        assert_eq!(X25519KeyPair::from_bytes(&private).unwrap().public_key_bytes(), batch[1].public_key);
+    }
+
+    #[test]
+    fn test_snapshot_version_and_keystore_restore() {
+        let mut store = KeyStore::new();
+        store.create_account("work").unwrap();
+        store.generate_pre_keys("work", 5).unwrap();
+        let snapshot = store.account_snapshot("work").unwrap();
+
+        let mut other = KeyStore::new();
+        other.restore_account_snapshot("work", &snapshot).unwrap();
+        assert_eq!(other.registration_id("work").unwrap(), store.registration_id("work").unwrap());
+        assert_eq!(other.remaining_pre_keys("work").unwrap(), 5);
+        assert!(other.restore_account_snapshot("work", &snapshot).is_err());
+
+        let mut bytes = snapshot.to_bytes().unwrap();
+        bytes[0] = 2;
+        assert_eq!(
+            KeyStoreSnapshot::from_slice(&bytes).err(),
+            Some("Unsupported keystore snapshot version: 2".to_string())
+        );
+    }
+}
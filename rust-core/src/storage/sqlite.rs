@@ -0,0 +1,1707 @@
+//! SQLite 儲存模組 (feature = "sqlite"，僅原生建置)
+//!
+//! 以 SQLite 實作協定儲存、訊息儲存、鍵值儲存、聯絡人與群組儲存介面，伺服器端機器人與桌面客戶端
+//! 直接使用本 crate 時不必自行實作持久化。記錄以 bincode 序列化為 BLOB，
+//! 讀後寫的操作 (更新身份、消耗預金鑰) 在同一個交易中完成；內部交易使用
+//! SAVEPOINT，可包在 `Transactional::atomic` 中與其他操作一起提交或還原
+//!
+//! 以 `open_encrypted` 或 `unlock_storage` (密碼) 開啟時，所有記錄以
+//! 主儲存金鑰加密、查詢鍵盲化 (見 `encryption` 模組)
+//!
+//! 訊息內容以每則訊息各自的包裝金鑰加密，金鑰以對話金鑰 (`conversation_keys`)
+//! 加密後另存於 `message_keys`；刪除訊息時一併刪除金鑰並輪替對話金鑰，另啟用 `secure_delete`
+//! 讓 SQLite 以零覆寫被刪除的頁面。`secure_delete` 只涵蓋資料庫檔案：回復日誌刪除後，
+//! 其中舊頁面的內容 (包括舊對話金鑰的記錄) 可能仍殘留在磁碟上，持有主儲存金鑰者仍可能還原
+//!
+//! `rotate_storage_key` / `change_storage_password` 以新的主儲存金鑰逐批重新加密所有記錄，
+//! 每批一個交易，中斷後再次呼叫即繼續 (見 `encryption` 模組)
+//!
+//! `verify_storage` 檢查每筆記錄的檢查碼並修復毀損的預金鑰與會話 (見 `integrity` 模組)
+//!
+//! schema 版本記錄在 `PRAGMA user_version`；新版本只新增資料表，
+//! 舊資料庫開啟時補上缺少的資料表即完成升級
+
+use std::collections::BTreeSet;
+
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+
+use crate::crypto::{
+    IdentityKeyPair, OneTimePreKey, PasswordKdfParams, RatchetSession, RevocationCertificate, SignedPreKeyRecord,
+    X25519KeyPair, MAX_BATCH_SIZE,
+};
+use super::contacts::{Contact, ContactStore};
+use super::encryption::{KeyRotation, KeyRotationProgress, RecordCodec, StorageKey, StorageLock};
+use super::groups::{Group, GroupStore};
+use super::integrity::{RepairAction, StorageVerification};
+use super::kv::{kv_record_key, KeyValueStore, KV_KEY_FIELD, KV_NAMESPACE_FIELD};
+use super::messages::{MessageStore, RetentionPolicy, StoredMessage};
+use super::search::{search_tokens, SEARCH_TOKEN_FIELD};
+use super::store::{IdentityKeyStore, PreKeyStore, SessionStore, SignedPreKeyStore};
+use super::transaction::Transactional;
+use super::trust::{IdentityStatus, TrustedIdentity};
+
+/// 目前的資料庫 schema 版本
+pub const SQLITE_SCHEMA_VERSION: u32 = 7;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS local (
+        key TEXT PRIMARY KEY,
+        value BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS identities (
+        contact_id TEXT PRIMARY KEY,
+        record BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS revocations (
+        identity_key BLOB PRIMARY KEY,
+        certificate BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS sessions (
+        contact_id TEXT NOT NULL,
+        device_id INTEGER NOT NULL,
+        session BLOB NOT NULL,
+        PRIMARY KEY (contact_id, device_id)
+    );
+    CREATE TABLE IF NOT EXISTS pre_keys (
+        key_id INTEGER PRIMARY KEY,
+        keypair BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS signed_pre_keys (
+        key_id INTEGER PRIMARY KEY,
+        record BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS messages (
+        id TEXT PRIMARY KEY,
+        conversation_id TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        message BLOB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS messages_by_conversation ON messages (conversation_id, timestamp, id);
+    CREATE TABLE IF NOT EXISTS search_tokens (
+        token TEXT NOT NULL,
+        message_id TEXT NOT NULL,
+        PRIMARY KEY (token, message_id)
+    ) WITHOUT ROWID;
+    CREATE INDEX IF NOT EXISTS search_tokens_by_message ON search_tokens (message_id);
+    CREATE TABLE IF NOT EXISTS message_keys (
+        id TEXT PRIMARY KEY,
+        key BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS retention_policies (
+        conversation_id TEXT PRIMARY KEY,
+        policy BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS kv (
+        namespace TEXT NOT NULL,
+        key TEXT NOT NULL,
+        value BLOB NOT NULL,
+        PRIMARY KEY (namespace, key)
+    ) WITHOUT ROWID;
+    CREATE TABLE IF NOT EXISTS contacts (
+        contact_id TEXT PRIMARY KEY,
+        contact BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS chat_groups (
+        group_id TEXT PRIMARY KEY,
+        record BLOB NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS conversation_keys (
+        conversation_id TEXT PRIMARY KEY,
+        key BLOB NOT NULL
+    );
+";
+const TABLES: [&str; 14] = [
+    "local",
+    "identities",
+    "revocations",
+    "sessions",
+    "pre_keys",
+    "signed_pre_keys",
+    "messages",
+    "search_tokens",
+    "message_keys",
+    "retention_policies",
+    "kv",
+    "contacts",
+    "chat_groups",
+    "conversation_keys",
+];
+
+const LOCAL_IDENTITY_KEY: &str = "identity";
+const NEXT_PRE_KEY_ID_KEY: &str = "next_pre_key_id";
+/// 密碼鎖以明文存放在 `local` 中
+const STORAGE_LOCK_KEY: &str = "storage_lock";
+/// 主金鑰輪替進度以明文存放在 `local` 中
+const KEY_ROTATION_KEY: &str = "key_rotation";
+/// 主金鑰輪替後沿用的盲化索引金鑰
+const INDEX_KEY_KEY: &str = "index_key";
+/// 因毀損被刪除、需要重新握手的會話 (會話記錄鍵)
+const SESSION_RESETS_KEY: &str = "session_resets";
+/// 主金鑰輪替時重新加密的資料表：(資料表, 主鍵欄位, 記錄欄位, 加密時綁定的記錄鍵)
+///
+/// `search_tokens` 只存盲化的鍵；以包裝金鑰加密的訊息與以對話金鑰加密的包裝金鑰不受主金鑰影響，
+/// 只重新加密其中的舊版記錄
+const ROTATED_TABLES: [(&str, &str, &str, &str); 13] = [
+    ("local", "key", "value", "key"),
+    ("identities", "contact_id", "record", "contact_id"),
+    ("revocations", "identity_key", "certificate", "identity_key"),
+    ("sessions", "contact_id, device_id", "session", "device_id || ':' || contact_id"),
+    ("pre_keys", "key_id", "keypair", "key_id"),
+    ("signed_pre_keys", "key_id", "record", "key_id"),
+    ("messages", "id", "message", "id"),
+    ("message_keys", "id", "key", "id"),
+    ("retention_policies", "conversation_id", "policy", "conversation_id"),
+    ("kv", "namespace, key", "value", "namespace || ':' || key"),
+    ("contacts", "contact_id", "contact", "contact_id"),
+    ("chat_groups", "group_id", "record", "group_id"),
+    ("conversation_keys", "conversation_id", "key", "conversation_id"),
+];
+/// 訊息查詢的欄位與包裝金鑰、對話金鑰的 JOIN (舊版記錄沒有金鑰)
+const MESSAGE_COLUMNS: &str = "m.id, m.conversation_id, m.message, k.key, c.key";
+const MESSAGE_KEY_JOINS: &str = "LEFT JOIN message_keys k ON k.id = m.id
+    LEFT JOIN conversation_keys c ON c.conversation_id = m.conversation_id";
+
+/// (訊息 ID, 對話 ID, 記錄, 包裝金鑰, 對話金鑰)，ID 皆為盲化後的值
+type MessageRow = (String, String, Vec<u8>, Option<Vec<u8>>, Option<Vec<u8>>);
+
+fn message_row(row: &rusqlite::Row) -> rusqlite::Result<MessageRow> {
+    Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+}
+
+fn sql_error(error: rusqlite::Error) -> String {
+    format!("SQLite error: {}", error)
+}
+
+/// 會話記錄鍵 (裝置 ID 在前，聯絡人 ID 中的字元不會造成歧義)
+fn session_record_key(contact_key: &str, device_id: u32) -> String {
+    format!("{}:{}", device_id, contact_key)
+}
+
+/// 時間戳以 INTEGER (i64) 儲存
+fn timestamp_column(timestamp: u64) -> Result<i64, String> {
+    i64::try_from(timestamp).map_err(|_| format!("Timestamp out of range: {}", timestamp))
+}
+
+/// SQLite 協定 / 訊息儲存
+pub struct SqliteStore {
+    connection: Connection,
+    codec: RecordCodec,
+}
+
+impl SqliteStore {
+    /// 開啟 (不存在時建立) 資料庫檔案
+    pub fn open(path: &str) -> Result<Self, String> {
+        Self::from_connection(Connection::open(path).map_err(sql_error)?, None)
+    }
+
+    /// 開啟 (不存在時建立) 以主儲存金鑰加密的資料庫檔案
+    pub fn open_encrypted(path: &str, key: &StorageKey) -> Result<Self, String> {
+        Self::from_connection(Connection::open(path).map_err(sql_error)?, Some(key.clone()))
+    }
+
+    /// 建立只存在記憶體中的資料庫 (測試用)
+    pub fn open_in_memory() -> Result<Self, String> {
+        Self::from_connection(Connection::open_in_memory().map_err(sql_error)?, None)
+    }
+
+    /// 以密碼解鎖資料庫檔案；尚未設定密碼鎖的空資料庫會以 `kdf` 參數建立密碼鎖
+    ///
+    /// 之後沿用資料庫中記錄的參數與 salt，密碼錯誤時回傳錯誤
+    pub fn unlock_storage(path: &str, password: &[u8], kdf: &PasswordKdfParams) -> Result<Self, String> {
+        let connection = Connection::open(path).map_err(sql_error)?;
+        Self::migrate(&connection)?;
+        let key = match Self::storage_lock(&connection)? {
+            Some(lock) => lock.unlock_key(password)?,
+            None => {
+                if !Self::is_empty(&connection)? {
+                    return Err("Storage already contains data that is not password protected".to_string());
+                }
+                let (lock, key) = StorageLock::create_with_params(password, kdf)?;
+                let lock = RecordCodec::default().encode("local", STORAGE_LOCK_KEY, &lock)?;
+                connection
+                    .execute("INSERT INTO local (key, value) VALUES (?1, ?2)", params![STORAGE_LOCK_KEY, lock])
+                    .map_err(sql_error)?;
+                key
+            }
+        };
+        let codec = Self::codec(&connection, Some(key))?;
+        Ok(Self { connection, codec })
+    }
+
+    fn from_connection(connection: Connection, key: Option<StorageKey>) -> Result<Self, String> {
+        Self::migrate(&connection)?;
+        if key.is_none() && Self::storage_lock(&connection)?.is_some() {
+            return Err("Storage is locked; unlock it with the storage password".to_string());
+        }
+        let codec = Self::codec(&connection, key)?;
+        Ok(Self { connection, codec })
+    }
+
+    /// 記錄編碼器 (套用主金鑰輪替後保存的盲化索引金鑰)
+    fn codec(connection: &Connection, key: Option<StorageKey>) -> Result<RecordCodec, String> {
+        let mut codec = RecordCodec::new(key.clone());
+        if key.is_some() {
+            if let Some(record) = Self::raw_local(connection, INDEX_KEY_KEY)? {
+                codec.apply_index_key_record("local", INDEX_KEY_KEY, &record)?;
+            }
+        }
+        Ok(codec)
+    }
+
+    fn migrate(connection: &Connection) -> Result<(), String> {
+        let version: u32 = connection
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(sql_error)?;
+        if version > SQLITE_SCHEMA_VERSION {
+            return Err(format!("Unsupported SQLite schema version: {}", version));
+        }
+        // 刪除的內容以零覆寫，不殘留在空閒頁面中
+        connection.pragma_update(None, "secure_delete", true).map_err(sql_error)?;
+        connection.execute_batch(SCHEMA).map_err(sql_error)?;
+        connection
+            .pragma_update(None, "user_version", SQLITE_SCHEMA_VERSION)
+            .map_err(sql_error)
+    }
+
+    /// `local` 中未經解碼的值
+    fn raw_local(connection: &Connection, key: &str) -> Result<Option<Vec<u8>>, String> {
+        connection
+            .query_row("SELECT value FROM local WHERE key = ?1", [key], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)
+    }
+
+    fn storage_lock(connection: &Connection) -> Result<Option<StorageLock>, String> {
+        Self::raw_local(connection, STORAGE_LOCK_KEY)?
+            .map(|bytes| RecordCodec::default().decode("local", STORAGE_LOCK_KEY, &bytes))
+            .transpose()
+    }
+
+    fn key_rotation(&self) -> Result<Option<KeyRotation>, String> {
+        Self::raw_local(&self.connection, KEY_ROTATION_KEY)?
+            .map(|bytes| RecordCodec::default().decode("local", KEY_ROTATION_KEY, &bytes))
+            .transpose()
+    }
+
+    fn save_key_rotation(&self, rotation: &KeyRotation) -> Result<(), String> {
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO local (key, value) VALUES (?1, ?2)",
+                params![KEY_ROTATION_KEY, RecordCodec::default().encode("local", KEY_ROTATION_KEY, rotation)?],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn is_empty(connection: &Connection) -> Result<bool, String> {
+        for table in TABLES {
+            let exists: bool = connection
+                .query_row(&format!("SELECT EXISTS (SELECT 1 FROM {})", table), [], |row| row.get(0))
+                .map_err(sql_error)?;
+            if exists {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    fn local_value<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        let value: Option<Vec<u8>> = self
+            .connection
+            .query_row("SELECT value FROM local WHERE key = ?1", [key], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        value.map(|bytes| self.codec.decode("local", key, &bytes)).transpose()
+    }
+
+    /// 解碼訊息記錄
+    fn decode_message(&self, row: MessageRow) -> Result<StoredMessage, String> {
+        let (id, conversation_id, bytes, wrapping_key, conversation_key) = row;
+        let conversation_key = conversation_key
+            .map(|record| self.codec.open_conversation_key(&conversation_id, &record))
+            .transpose()?;
+        self.codec
+            .open_wrapped(conversation_key.as_ref(), "messages", &id, &bytes, wrapping_key.as_deref())
+    }
+
+    /// 記錄是否以包裝金鑰或對話金鑰加密 (主儲存金鑰輪替時略過)：
+    /// 有包裝金鑰的訊息，以及以所屬對話的對話金鑰包裝的包裝金鑰
+    fn is_wrapped_record(codec: &RecordCodec, connection: &Connection, table: &str, id: &[u8], bytes: &[u8]) -> Result<bool, String> {
+        let Ok(id) = std::str::from_utf8(id) else {
+            return Ok(false);
+        };
+        match table {
+            "messages" => connection
+                .query_row("SELECT EXISTS(SELECT 1 FROM message_keys WHERE id = ?1)", [id], |row| row.get(0))
+                .map_err(sql_error),
+            "message_keys" => {
+                let conversation: Option<(String, Vec<u8>)> = connection
+                    .query_row(
+                        "SELECT c.conversation_id, c.key FROM messages m
+                        JOIN conversation_keys c ON c.conversation_id = m.conversation_id WHERE m.id = ?1",
+                        [id],
+                        |row| Ok((row.get(0)?, row.get(1)?)),
+                    )
+                    .optional()
+                    .map_err(sql_error)?;
+                let Some((conversation_id, record)) = conversation else {
+                    return Ok(false);
+                };
+                let conversation = codec.open_conversation_key(&conversation_id, &record)?;
+                Ok(RecordCodec::is_wrapped_key(&conversation, id, bytes))
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// 輪替對話金鑰 (`conversation_id` 為盲化後的值)：其餘訊息的包裝金鑰改以新金鑰包裝，
+    /// 對話中已沒有訊息時直接刪除對話金鑰
+    fn rotate_conversation_key(codec: &RecordCodec, connection: &Connection, conversation_id: &str) -> Result<(), String> {
+        let record: Option<Vec<u8>> = connection
+            .query_row("SELECT key FROM conversation_keys WHERE conversation_id = ?1", [conversation_id], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(sql_error)?;
+        let Some(record) = record else {
+            return Ok(());
+        };
+        let current = codec.open_conversation_key(conversation_id, &record)?;
+        let keys = connection
+            .prepare("SELECT k.id, k.key FROM message_keys k JOIN messages m ON m.id = k.id WHERE m.conversation_id = ?1")
+            .and_then(|mut statement| {
+                statement
+                    .query_map([conversation_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))?
+                    .collect::<rusqlite::Result<Vec<_>>>()
+            })
+            .map_err(sql_error)?;
+        if keys.is_empty() {
+            connection
+                .execute("DELETE FROM conversation_keys WHERE conversation_id = ?1", [conversation_id])
+                .map_err(sql_error)?;
+            return Ok(());
+        }
+        let (next, record) = codec.new_conversation_key(conversation_id)?;
+        for (id, key) in keys {
+            connection
+                .execute(
+                    "UPDATE message_keys SET key = ?2 WHERE id = ?1",
+                    params![id, codec.rewrap(Some(&current), &next, &id, &key)?],
+                )
+                .map_err(sql_error)?;
+        }
+        connection
+            .execute(
+                "UPDATE conversation_keys SET key = ?2 WHERE conversation_id = ?1",
+                params![conversation_id, record],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn local_identity(&self) -> Result<(IdentityKeyPair, u32), String> {
+        self.local_value(LOCAL_IDENTITY_KEY)?
+            .ok_or_else(|| "Local identity has not been set".to_string())
+    }
+
+    /// 設定本機身份
+    pub fn set_local_identity(&mut self, identity: &IdentityKeyPair, registration_id: u32) -> Result<(), String> {
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO local (key, value) VALUES (?1, ?2)",
+                params![
+                    LOCAL_IDENTITY_KEY,
+                    self.codec.encode("local", LOCAL_IDENTITY_KEY, &(identity, registration_id))?
+                ],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    /// 產生一次性預金鑰，回傳公開部分 (最多 `MAX_BATCH_SIZE` 把)
+    pub fn generate_pre_keys(&mut self, count: u32) -> Result<Vec<OneTimePreKey>, String> {
+        if count > MAX_BATCH_SIZE {
+            return Err(format!("Cannot generate more than {} prekeys at once", MAX_BATCH_SIZE));
+        }
+        let transaction = self.connection.savepoint().map_err(sql_error)?;
+        let next_id: Option<Vec<u8>> = transaction
+            .query_row("SELECT value FROM local WHERE key = ?1", [NEXT_PRE_KEY_ID_KEY], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        let mut next_id: u32 = next_id
+            .map(|bytes| self.codec.decode("local", NEXT_PRE_KEY_ID_KEY, &bytes))
+            .transpose()?
+            .unwrap_or(1);
+
+        let mut batch = Vec::with_capacity(count as usize);
+        for _ in 0..count {
+            let key_id = next_id;
+            next_id = next_id
+                .checked_add(1)
+                .ok_or_else(|| "One-time prekey ID space exhausted".to_string())?;
+            let keypair = X25519KeyPair::new();
+            transaction
+                .execute(
+                    "INSERT INTO pre_keys (key_id, keypair) VALUES (?1, ?2)",
+                    params![key_id, self.codec.encode("pre_keys", key_id.to_string(), &keypair)?],
+                )
+                .map_err(sql_error)?;
+            batch.push(OneTimePreKey { key_id, public_key: keypair.public_key_bytes() });
+        }
+        transaction
+            .execute(
+                "INSERT OR REPLACE INTO local (key, value) VALUES (?1, ?2)",
+                params![NEXT_PRE_KEY_ID_KEY, self.codec.encode("local", NEXT_PRE_KEY_ID_KEY, &next_id)?],
+            )
+            .map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)?;
+        Ok(batch)
+    }
+
+    /// 剩餘的一次性預金鑰數量
+    pub fn remaining_pre_keys(&self) -> Result<u32, String> {
+        self.connection
+            .query_row("SELECT COUNT(*) FROM pre_keys", [], |row| row.get(0))
+            .map_err(sql_error)
+    }
+
+    /// 索引 (或重新索引) 訊息文字，詞元以盲化後的形式存放 (見 `search` 模組)
+    ///
+    /// 訊息內容由呼叫端決定，因此需另外傳入要索引的文字
+    pub fn index_message(&mut self, id: &str, text: &str) -> Result<(), String> {
+        let message_id = self.codec.blind("message", id);
+        let transaction = self.connection.savepoint().map_err(sql_error)?;
+        transaction
+            .execute("DELETE FROM search_tokens WHERE message_id = ?1", [&message_id])
+            .map_err(sql_error)?;
+        for token in search_tokens(text) {
+            transaction
+                .execute(
+                    "INSERT INTO search_tokens (token, message_id) VALUES (?1, ?2)",
+                    params![self.codec.blind(SEARCH_TOKEN_FIELD, &token), message_id],
+                )
+                .map_err(sql_error)?;
+        }
+        transaction.commit().map_err(sql_error)
+    }
+
+    /// 搜尋包含查詢中所有詞元的已儲存訊息，回傳依時間排序的訊息 ID
+    pub fn search(&self, query: &str) -> Result<Vec<String>, String> {
+        let tokens: Vec<String> = search_tokens(query)
+            .iter()
+            .map(|token| self.codec.blind(SEARCH_TOKEN_FIELD, token))
+            .collect();
+        if tokens.is_empty() {
+            return Ok(Vec::new());
+        }
+        let placeholders = vec!["?"; tokens.len()].join(", ");
+        let mut statement = self
+            .connection
+            .prepare(&format!(
+                "SELECT {} FROM messages m {}
+                WHERE m.id IN (
+                    SELECT message_id FROM search_tokens WHERE token IN ({})
+                    GROUP BY message_id HAVING COUNT(*) = {}
+                ) ORDER BY m.timestamp, m.id",
+                MESSAGE_COLUMNS,
+                MESSAGE_KEY_JOINS,
+                placeholders,
+                tokens.len()
+            ))
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map(params_from_iter(&tokens), message_row)
+            .map_err(sql_error)?;
+        rows.map(|row| Ok(self.decode_message(row.map_err(sql_error)?)?.id()))
+            .collect()
+    }
+
+    /// 驗證並記錄撤銷憑證，回傳記錄前的狀態
+    pub fn apply_revocation(
+        &mut self,
+        contact_id: &str,
+        certificate: &RevocationCertificate,
+    ) -> Result<IdentityStatus, String> {
+        certificate.verify_signature()?;
+        let status = self.check_identity(contact_id, &certificate.identity_key())?;
+        let key = self.codec.blind_bytes("identity_key", &certificate.identity_key());
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO revocations (identity_key, certificate) VALUES (?1, ?2)",
+                params![key, self.codec.encode("revocations", &key, certificate)?],
+            )
+            .map_err(sql_error)?;
+        Ok(status)
+    }
+}
+
+impl SqliteStore {
+    /// 以新的主儲存金鑰重新加密所有記錄，回傳是否完成
+    ///
+    /// 每批最多 `chunk_size` 筆記錄一個交易，每批完成後呼叫 `progress`，回傳 false 時暫停。
+    /// 輪替期間本實例照常讀寫；暫停或中斷後以舊金鑰開啟，再以同一把新金鑰呼叫即繼續，
+    /// 完成後只能以新金鑰開啟。以密碼解鎖的資料庫改用 `change_storage_password`
+    pub fn rotate_storage_key(
+        &mut self,
+        new_key: &StorageKey,
+        chunk_size: u32,
+        progress: impl FnMut(&KeyRotationProgress) -> bool,
+    ) -> Result<bool, String> {
+        if Self::storage_lock(&self.connection)?.is_some() {
+            return Err("Storage is password protected; change the storage password instead".to_string());
+        }
+        self.rotate(new_key, None, chunk_size, progress)
+    }
+
+    /// 變更儲存密碼：以新密碼衍生的主儲存金鑰重新加密所有記錄 (見 `rotate_storage_key`)
+    ///
+    /// 新的密碼鎖在完成時才取代舊的；暫停或中斷後以舊密碼解鎖，再以同一個新密碼呼叫即繼續
+    pub fn change_storage_password(
+        &mut self,
+        password: &[u8],
+        kdf: &PasswordKdfParams,
+        chunk_size: u32,
+        progress: impl FnMut(&KeyRotationProgress) -> bool,
+    ) -> Result<bool, String> {
+        if Self::storage_lock(&self.connection)?.is_none() {
+            return Err("Storage is not password protected".to_string());
+        }
+        let (lock, key) = match self.key_rotation()?.as_ref().and_then(KeyRotation::lock) {
+            Some(lock) => (lock.clone(), lock.unlock_key(password)?),
+            None => StorageLock::create_with_params(password, kdf)?,
+        };
+        self.rotate(&key, Some(lock), chunk_size, progress)
+    }
+
+    fn rotate(
+        &mut self,
+        key: &StorageKey,
+        lock: Option<StorageLock>,
+        chunk_size: u32,
+        mut progress: impl FnMut(&KeyRotationProgress) -> bool,
+    ) -> Result<bool, String> {
+        if chunk_size == 0 {
+            return Err("Chunk size must be positive".to_string());
+        }
+        let mut rotation = match self.key_rotation()? {
+            Some(rotation) if rotation.targets(key) => rotation,
+            Some(_) => return Err("A storage key rotation to a different key is in progress".to_string()),
+            None => KeyRotation::new(key, lock),
+        };
+        self.save_key_rotation(&rotation)?;
+        self.codec = self.codec.rotating(key)?;
+
+        let total = ROTATED_TABLES.len() as u32;
+        for table in ROTATED_TABLES.iter().skip(rotation.completed as usize) {
+            let completed = rotation.completed;
+            if !self.reseal_table(*table, chunk_size, |records| {
+                progress(&KeyRotationProgress::new(table.0, completed, total, records))
+            })? {
+                return Ok(false);
+            }
+            rotation.completed += 1;
+            self.save_key_rotation(&rotation)?;
+        }
+
+        // 保存沿用的盲化索引金鑰、換上新的密碼鎖並刪除進度記錄
+        let codec = self.codec.finish_rotation();
+        let transaction = self.connection.savepoint().map_err(sql_error)?;
+        transaction
+            .execute(
+                "INSERT OR REPLACE INTO local (key, value) VALUES (?1, ?2)",
+                params![INDEX_KEY_KEY, codec.index_key_record("local", INDEX_KEY_KEY)?],
+            )
+            .map_err(sql_error)?;
+        if let Some(lock) = rotation.lock() {
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO local (key, value) VALUES (?1, ?2)",
+                    params![STORAGE_LOCK_KEY, RecordCodec::default().encode("local", STORAGE_LOCK_KEY, lock)?],
+                )
+                .map_err(sql_error)?;
+        }
+        transaction
+            .execute("DELETE FROM local WHERE key = ?1", [KEY_ROTATION_KEY])
+            .map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)?;
+        self.codec = codec;
+        Ok(true)
+    }
+
+    /// 以主鍵順序逐批重新加密資料表中的記錄，`progress` 收到本表已重新加密的筆數，回傳 false 時暫停
+    fn reseal_table(
+        &mut self,
+        (table, primary_key, column, record_key): (&str, &str, &str, &str),
+        chunk_size: u32,
+        mut progress: impl FnMut(u32) -> bool,
+    ) -> Result<bool, String> {
+        let columns = primary_key.split(", ").count();
+        let placeholders = vec!["?"; columns].join(", ");
+        let mut after: Option<Vec<Value>> = None;
+        let mut records = 0;
+        loop {
+            let condition = match after {
+                Some(_) => format!("WHERE ({}) > ({})", primary_key, placeholders),
+                None => String::new(),
+            };
+            let rows = {
+                let mut statement = self
+                    .connection
+                    .prepare(&format!(
+                        "SELECT {}, CAST({} AS BLOB), {} FROM {} {} ORDER BY {} LIMIT {}",
+                        primary_key, record_key, column, table, condition, primary_key, chunk_size
+                    ))
+                    .map_err(sql_error)?;
+                let rows = statement
+                    .query_map(params_from_iter(after.iter().flatten()), |row| {
+                        let key = (0..columns).map(|i| row.get(i)).collect::<rusqlite::Result<Vec<Value>>>()?;
+                        Ok((key, row.get::<_, Vec<u8>>(columns)?, row.get::<_, Vec<u8>>(columns + 1)?))
+                    })
+                    .map_err(sql_error)?;
+                rows.collect::<rusqlite::Result<Vec<_>>>().map_err(sql_error)?
+            };
+            let Some((last, _, _)) = rows.last() else {
+                return Ok(true);
+            };
+            after = Some(last.clone());
+
+            let transaction = self.connection.savepoint().map_err(sql_error)?;
+            for (key, record_key, bytes) in rows {
+                if table == "local" && [STORAGE_LOCK_KEY, KEY_ROTATION_KEY].iter().any(|plain| plain.as_bytes() == record_key) {
+                    continue;
+                }
+                let resealed = match self.codec.reseal(table, &record_key, &bytes) {
+                    Ok(resealed) => resealed,
+                    Err(_) if Self::is_wrapped_record(&self.codec, &transaction, table, &record_key, &bytes)? => None,
+                    Err(e) => return Err(format!("Failed to re-encrypt {} record: {}", table, e)),
+                };
+                if let Some(resealed) = resealed {
+                    transaction
+                        .execute(
+                            &format!("UPDATE {} SET {} = ? WHERE ({}) = ({})", table, column, primary_key, placeholders),
+                            params_from_iter(std::iter::once(Value::Blob(resealed)).chain(key)),
+                        )
+                        .map_err(sql_error)?;
+                    records += 1;
+                }
+            }
+            transaction.commit().map_err(sql_error)?;
+            if !progress(records) {
+                return Ok(false);
+            }
+        }
+    }
+}
+
+impl SqliteStore {
+    /// 檢查所有記錄的檢查碼，回報毀損的記錄 (見 `integrity` 模組)
+    ///
+    /// `repair` 時刪除毀損的預金鑰與會話 (在同一個交易中)，並把會話標記為需要重新握手
+    pub fn verify_storage(&mut self, repair: bool) -> Result<StorageVerification, String> {
+        let mut verification = StorageVerification::default();
+        // (毀損記錄的索引, 資料表, 主鍵欄位, 主鍵值)
+        let mut repairs = Vec::new();
+        for (table, primary_key, column, record_key) in ROTATED_TABLES {
+            // 包裝金鑰與訊息一起檢查
+            if matches!(table, "messages" | "message_keys") {
+                continue;
+            }
+            let columns = primary_key.split(", ").count();
+            let mut statement = self
+                .connection
+                .prepare(&format!("SELECT {}, CAST({} AS BLOB), {} FROM {}", primary_key, record_key, column, table))
+                .map_err(sql_error)?;
+            let mut rows = statement.query([]).map_err(sql_error)?;
+            while let Some(row) = rows.next().map_err(sql_error)? {
+                let key = (0..columns).map(|i| row.get(i)).collect::<rusqlite::Result<Vec<Value>>>().map_err(sql_error)?;
+                let record_key: Vec<u8> = row.get(columns).map_err(sql_error)?;
+                let bytes: Vec<u8> = row.get(columns + 1).map_err(sql_error)?;
+                let plain = table == "local" && [STORAGE_LOCK_KEY, KEY_ROTATION_KEY].iter().any(|plain| plain.as_bytes() == record_key);
+                let result = match plain {
+                    true => RecordCodec::default().verify(table, &record_key, &bytes),
+                    false => self.codec.verify(table, &record_key, &bytes),
+                };
+                if verification.record(table, &record_key, result) && RepairAction::for_store(table) != RepairAction::ReportOnly {
+                    repairs.push((verification.corrupted_mut().len() - 1, table, primary_key, key));
+                }
+            }
+        }
+
+        let mut statement = self
+            .connection
+            .prepare(&format!("SELECT {} FROM messages m {}", MESSAGE_COLUMNS, MESSAGE_KEY_JOINS))
+            .map_err(sql_error)?;
+        let rows = statement.query_map([], message_row).map_err(sql_error)?;
+        for row in rows {
+            let (id, conversation_id, bytes, wrapping_key, conversation_key) = row.map_err(sql_error)?;
+            let result = conversation_key
+                .map(|record| self.codec.open_conversation_key(&conversation_id, &record))
+                .transpose()
+                .and_then(|conversation_key| {
+                    self.codec
+                        .verify_wrapped(conversation_key.as_ref(), "messages", &id, &bytes, wrapping_key.as_deref())
+                });
+            verification.record("messages", id.as_bytes(), result);
+        }
+        drop(statement);
+
+        if repair && !repairs.is_empty() {
+            let mut resets: BTreeSet<String> = self.local_value(SESSION_RESETS_KEY)?.unwrap_or_default();
+            let transaction = self.connection.savepoint().map_err(sql_error)?;
+            for (index, table, primary_key, key) in repairs {
+                let placeholders = vec!["?"; key.len()].join(", ");
+                transaction
+                    .execute(
+                        &format!("DELETE FROM {} WHERE ({}) = ({})", table, primary_key, placeholders),
+                        params_from_iter(key),
+                    )
+                    .map_err(sql_error)?;
+                let record = &mut verification.corrupted_mut()[index];
+                if record.action() == RepairAction::Rehandshake {
+                    resets.insert(String::from_utf8_lossy(&record.record_key()).into_owned());
+                }
+                record.mark_repaired();
+            }
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO local (key, value) VALUES (?1, ?2)",
+                    params![SESSION_RESETS_KEY, self.codec.encode("local", SESSION_RESETS_KEY, &resets)?],
+                )
+                .map_err(sql_error)?;
+            transaction.commit().map_err(sql_error)?;
+        }
+        Ok(verification)
+    }
+
+    /// 會話是否因毀損被 `verify_storage` 刪除，需要重新握手
+    pub fn needs_rehandshake(&self, contact_id: &str, device_id: u32) -> Result<bool, String> {
+        let resets: BTreeSet<String> = self.local_value(SESSION_RESETS_KEY)?.unwrap_or_default();
+        Ok(resets.contains(&session_record_key(&self.codec.blind("contact", contact_id), device_id)))
+    }
+
+    /// 重新建立會話後清除標記，回傳是否有標記
+    pub fn clear_rehandshake(&mut self, contact_id: &str, device_id: u32) -> Result<bool, String> {
+        let mut resets: BTreeSet<String> = self.local_value(SESSION_RESETS_KEY)?.unwrap_or_default();
+        if !resets.remove(&session_record_key(&self.codec.blind("contact", contact_id), device_id)) {
+            return Ok(false);
+        }
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO local (key, value) VALUES (?1, ?2)",
+                params![SESSION_RESETS_KEY, self.codec.encode("local", SESSION_RESETS_KEY, &resets)?],
+            )
+            .map_err(sql_error)?;
+        Ok(true)
+    }
+}
+
+/// 比對身份公鑰 (含撤銷檢查)，供一般讀取與交易內讀取共用
+///
+/// `contact_key` 為盲化後的聯絡人 ID
+fn identity_status(
+    connection: &Connection,
+    codec: &RecordCodec,
+    contact_key: &str,
+    identity_key: &[u8],
+) -> Result<(IdentityStatus, Option<TrustedIdentity>), String> {
+    let revocation_key = codec.blind_bytes("identity_key", identity_key);
+    let revoked: bool = connection
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM revocations WHERE identity_key = ?1)",
+            [revocation_key],
+            |row| row.get(0),
+        )
+        .map_err(sql_error)?;
+    let record: Option<Vec<u8>> = connection
+        .query_row("SELECT record FROM identities WHERE contact_id = ?1", [contact_key], |row| row.get(0))
+        .optional()
+        .map_err(sql_error)?;
+    let record: Option<TrustedIdentity> =
+        record.map(|bytes| codec.decode("identities", contact_key, &bytes)).transpose()?;
+    let status = if revoked {
+        IdentityStatus::Revoked
+    } else {
+        TrustedIdentity::compare(record.as_ref(), identity_key)
+    };
+    Ok((status, record))
+}
+
+impl IdentityKeyStore for SqliteStore {
+    fn identity_key_pair(&self) -> Result<IdentityKeyPair, String> {
+        Ok(self.local_identity()?.0)
+    }
+
+    fn local_registration_id(&self) -> Result<u32, String> {
+        Ok(self.local_identity()?.1)
+    }
+
+    fn check_identity(&self, contact_id: &str, identity_key: &[u8]) -> Result<IdentityStatus, String> {
+        let contact_key = self.codec.blind("contact", contact_id);
+        Ok(identity_status(&self.connection, &self.codec, &contact_key, identity_key)?.0)
+    }
+
+    fn save_identity(&mut self, contact_id: &str, identity_key: &[u8], now: u64) -> Result<IdentityStatus, String> {
+        let contact_key = self.codec.blind("contact", contact_id);
+        let transaction = self.connection.savepoint().map_err(sql_error)?;
+        let (status, record) = identity_status(&transaction, &self.codec, &contact_key, identity_key)?;
+        if matches!(status, IdentityStatus::NewIdentity | IdentityStatus::Changed) {
+            let record = TrustedIdentity::accept(record, identity_key, now);
+            transaction
+                .execute(
+                    "INSERT OR REPLACE INTO identities (contact_id, record) VALUES (?1, ?2)",
+                    params![contact_key, self.codec.encode("identities", &contact_key, &record)?],
+                )
+                .map_err(sql_error)?;
+        }
+        transaction.commit().map_err(sql_error)?;
+        Ok(status)
+    }
+}
+
+impl SessionStore for SqliteStore {
+    fn load_session(&self, contact_id: &str, device_id: u32) -> Result<Option<RatchetSession>, String> {
+        let contact_key = self.codec.blind("contact", contact_id);
+        let session: Option<Vec<u8>> = self
+            .connection
+            .query_row(
+                "SELECT session FROM sessions WHERE contact_id = ?1 AND device_id = ?2",
+                params![contact_key, device_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sql_error)?;
+        let record_key = session_record_key(&contact_key, device_id);
+        session.map(|bytes| self.codec.decode("sessions", &record_key, &bytes)).transpose()
+    }
+
+    fn store_session(&mut self, contact_id: &str, device_id: u32, session: &RatchetSession) -> Result<(), String> {
+        let contact_key = self.codec.blind("contact", contact_id);
+        let session = self.codec.encode("sessions", session_record_key(&contact_key, device_id), session)?;
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO sessions (contact_id, device_id, session) VALUES (?1, ?2, ?3)",
+                params![contact_key, device_id, session],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn remove_session(&mut self, contact_id: &str, device_id: u32) -> Result<bool, String> {
+        let removed = self
+            .connection
+            .execute(
+                "DELETE FROM sessions WHERE contact_id = ?1 AND device_id = ?2",
+                params![self.codec.blind("contact", contact_id), device_id],
+            )
+            .map_err(sql_error)?;
+        Ok(removed > 0)
+    }
+
+    fn session_devices(&self, contact_id: &str) -> Result<Vec<u32>, String> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT device_id FROM sessions WHERE contact_id = ?1 ORDER BY device_id")
+            .map_err(sql_error)?;
+        let devices = statement
+            .query_map([self.codec.blind("contact", contact_id)], |row| row.get(0))
+            .map_err(sql_error)?
+            .collect::<Result<Vec<u32>, _>>()
+            .map_err(sql_error)?;
+        Ok(devices)
+    }
+}
+
+impl PreKeyStore for SqliteStore {
+    fn contains_pre_key(&self, key_id: u32) -> Result<bool, String> {
+        self.connection
+            .query_row("SELECT EXISTS (SELECT 1 FROM pre_keys WHERE key_id = ?1)", [key_id], |row| row.get(0))
+            .map_err(sql_error)
+    }
+
+    fn load_pre_key(&self, key_id: u32) -> Result<Vec<u8>, String> {
+        let keypair: Option<Vec<u8>> = self
+            .connection
+            .query_row("SELECT keypair FROM pre_keys WHERE key_id = ?1", [key_id], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        let keypair: X25519KeyPair = keypair
+            .map(|bytes| self.codec.decode("pre_keys", key_id.to_string(), &bytes))
+            .transpose()?
+            .ok_or_else(|| format!("Unknown or already consumed one-time prekey: {}", key_id))?;
+        Ok(keypair.private_key_bytes().expose().to_vec())
+    }
+
+    fn consume_pre_key(&mut self, key_id: u32) -> Result<Vec<u8>, String> {
+        let keypair: Option<Vec<u8>> = self
+            .connection
+            .query_row("DELETE FROM pre_keys WHERE key_id = ?1 RETURNING keypair", [key_id], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        let keypair: X25519KeyPair = keypair
+            .map(|bytes| self.codec.decode("pre_keys", key_id.to_string(), &bytes))
+            .transpose()?
+            .ok_or_else(|| format!("Unknown or already consumed one-time prekey: {}", key_id))?;
+        Ok(keypair.private_key_bytes().expose().to_vec())
+    }
+}
+
+impl SignedPreKeyStore for SqliteStore {
+    fn load_signed_pre_key(&self, key_id: u32) -> Result<SignedPreKeyRecord, String> {
+        let record: Option<Vec<u8>> = self
+            .connection
+            .query_row("SELECT record FROM signed_pre_keys WHERE key_id = ?1", [key_id], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        record
+            .map(|bytes| self.codec.decode("signed_pre_keys", key_id.to_string(), &bytes))
+            .transpose()?
+            .ok_or_else(|| format!("Unknown signed prekey: {}", key_id))
+    }
+
+    fn store_signed_pre_key(&mut self, record: &SignedPreKeyRecord) -> Result<(), String> {
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO signed_pre_keys (key_id, record) VALUES (?1, ?2)",
+                params![record.key_id(), self.codec.encode("signed_pre_keys", record.key_id().to_string(), record)?],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn remove_signed_pre_key(&mut self, key_id: u32) -> Result<bool, String> {
+        let removed = self
+            .connection
+            .execute("DELETE FROM signed_pre_keys WHERE key_id = ?1", [key_id])
+            .map_err(sql_error)?;
+        Ok(removed > 0)
+    }
+}
+
+impl MessageStore for SqliteStore {
+    fn store_message(&mut self, message: &StoredMessage) -> Result<(), String> {
+        let id = self.codec.blind("message", &message.id());
+        let conversation_id = self.codec.blind("conversation", &message.conversation_id());
+        let transaction = self.connection.savepoint().map_err(sql_error)?;
+        let conversation_key: Option<Vec<u8>> = transaction
+            .query_row("SELECT key FROM conversation_keys WHERE conversation_id = ?1", [&conversation_id], |row| {
+                row.get(0)
+            })
+            .optional()
+            .map_err(sql_error)?;
+        let conversation_key = match conversation_key {
+            Some(record) => self.codec.open_conversation_key(&conversation_id, &record)?,
+            None => {
+                let (key, record) = self.codec.new_conversation_key(&conversation_id)?;
+                transaction
+                    .execute(
+                        "INSERT INTO conversation_keys (conversation_id, key) VALUES (?1, ?2)",
+                        params![conversation_id, record],
+                    )
+                    .map_err(sql_error)?;
+                key
+            }
+        };
+        let (record, wrapping_key) = RecordCodec::seal_wrapped(&conversation_key, "messages", &id, message)?;
+        transaction
+            .execute(
+                "INSERT OR REPLACE INTO messages (id, conversation_id, timestamp, message) VALUES (?1, ?2, ?3, ?4)",
+                params![id, conversation_id, timestamp_column(message.timestamp())?, record],
+            )
+            .map_err(sql_error)?;
+        transaction
+            .execute("INSERT OR REPLACE INTO message_keys (id, key) VALUES (?1, ?2)", params![id, wrapping_key])
+            .map_err(sql_error)?;
+        transaction.commit().map_err(sql_error)
+    }
+
+    fn load_message(&self, id: &str) -> Result<Option<StoredMessage>, String> {
+        let id = self.codec.blind("message", id);
+        let message = self
+            .connection
+            .query_row(
+                &format!("SELECT {} FROM messages m {} WHERE m.id = ?1", MESSAGE_COLUMNS, MESSAGE_KEY_JOINS),
+                [&id],
+                message_row,
+            )
+            .optional()
+            .map_err(sql_error)?;
+        message.map(|row| self.decode_message(row)).transpose()
+    }
+
+    fn remove_message(&mut self, id: &str) -> Result<bool, String> {
+        let id = self.codec.blind("message", id);
+        let transaction = self.connection.savepoint().map_err(sql_error)?;
+        let conversation_id: Option<String> = transaction
+            .query_row("SELECT conversation_id FROM messages WHERE id = ?1", [&id], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        transaction
+            .execute("DELETE FROM search_tokens WHERE message_id = ?1", [&id])
+            .map_err(sql_error)?;
+        transaction
+            .execute("DELETE FROM message_keys WHERE id = ?1", [&id])
+            .map_err(sql_error)?;
+        let removed = transaction
+            .execute("DELETE FROM messages WHERE id = ?1", [&id])
+            .map_err(sql_error)?;
+        // 殘留在檔案或日誌中的包裝金鑰以舊對話金鑰包裝，輪替後即無法解開
+        if let Some(conversation_id) = conversation_id {
+            Self::rotate_conversation_key(&self.codec, &transaction, &conversation_id)?;
+        }
+        transaction.commit().map_err(sql_error)?;
+        Ok(removed > 0)
+    }
+
+    fn conversation_messages(
+        &self,
+        conversation_id: &str,
+        before: Option<u64>,
+        limit: Option<u32>,
+    ) -> Result<Vec<StoredMessage>, String> {
+        let before = before.map(timestamp_column).transpose()?;
+        // LIMIT -1 表示不限數量
+        let limit = limit.map_or(-1, i64::from);
+        let mut statement = self
+            .connection
+            .prepare(&format!(
+                "SELECT {} FROM (
+                    SELECT id, conversation_id, message, timestamp FROM messages
+                    WHERE conversation_id = ?1 AND (?2 IS NULL OR timestamp < ?2)
+                    ORDER BY timestamp DESC, id DESC
+                    LIMIT ?3
+                ) m {} ORDER BY m.timestamp, m.id",
+                MESSAGE_COLUMNS, MESSAGE_KEY_JOINS
+            ))
+            .map_err(sql_error)?;
+        let conversation_key = self.codec.blind("conversation", conversation_id);
+        let rows = statement
+            .query_map(params![conversation_key, before, limit], message_row)
+            .map_err(sql_error)?;
+        rows.map(|row| self.decode_message(row.map_err(sql_error)?)).collect()
+    }
+
+    fn set_retention_policy(&mut self, conversation_id: &str, policy: Option<RetentionPolicy>) -> Result<(), String> {
+        let conversation_key = self.codec.blind("conversation", conversation_id);
+        match policy {
+            Some(policy) => self.connection.execute(
+                "INSERT OR REPLACE INTO retention_policies (conversation_id, policy) VALUES (?1, ?2)",
+                params![
+                    conversation_key,
+                    self.codec.encode("retention_policies", &conversation_key, &(conversation_id, policy))?
+                ],
+            ),
+            None => self
+                .connection
+                .execute("DELETE FROM retention_policies WHERE conversation_id = ?1", [&conversation_key]),
+        }
+        .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn retention_policies(&self) -> Result<Vec<(String, RetentionPolicy)>, String> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT conversation_id, policy FROM retention_policies")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))
+            .map_err(sql_error)?;
+        rows.map(|row| {
+            let (conversation_key, bytes) = row.map_err(sql_error)?;
+            self.codec.decode("retention_policies", &conversation_key, &bytes)
+        })
+        .collect()
+    }
+}
+
+impl KeyValueStore for SqliteStore {
+    fn put(&mut self, namespace: &str, key: &str, value: &[u8]) -> Result<(), String> {
+        let namespace_key = self.codec.blind(KV_NAMESPACE_FIELD, namespace);
+        let entry_key = self.codec.blind(KV_KEY_FIELD, key);
+        let record = self
+            .codec
+            .encode("kv", kv_record_key(&namespace_key, &entry_key), &(key, value))?;
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO kv (namespace, key, value) VALUES (?1, ?2, ?3)",
+                params![namespace_key, entry_key, record],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn get(&self, namespace: &str, key: &str) -> Result<Option<Vec<u8>>, String> {
+        let namespace_key = self.codec.blind(KV_NAMESPACE_FIELD, namespace);
+        let entry_key = self.codec.blind(KV_KEY_FIELD, key);
+        let record: Option<Vec<u8>> = self
+            .connection
+            .query_row(
+                "SELECT value FROM kv WHERE namespace = ?1 AND key = ?2",
+                params![namespace_key, entry_key],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(sql_error)?;
+        record
+            .map(|bytes| {
+                let (_, value): (String, Vec<u8>) =
+                    self.codec.decode("kv", kv_record_key(&namespace_key, &entry_key), &bytes)?;
+                Ok(value)
+            })
+            .transpose()
+    }
+
+    fn list(&self, namespace: &str) -> Result<Vec<String>, String> {
+        let namespace_key = self.codec.blind(KV_NAMESPACE_FIELD, namespace);
+        let mut statement = self
+            .connection
+            .prepare("SELECT key, value FROM kv WHERE namespace = ?1")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([&namespace_key], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))
+            .map_err(sql_error)?;
+        // 盲化後的 key 沒有意義的順序，解密後重新排序
+        let mut keys = rows
+            .map(|row| {
+                let (entry_key, bytes) = row.map_err(sql_error)?;
+                let (key, _): (String, Vec<u8>) =
+                    self.codec.decode("kv", kv_record_key(&namespace_key, &entry_key), &bytes)?;
+                Ok(key)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn remove(&mut self, namespace: &str, key: &str) -> Result<bool, String> {
+        let removed = self
+            .connection
+            .execute(
+                "DELETE FROM kv WHERE namespace = ?1 AND key = ?2",
+                params![self.codec.blind(KV_NAMESPACE_FIELD, namespace), self.codec.blind(KV_KEY_FIELD, key)],
+            )
+            .map_err(sql_error)?;
+        Ok(removed > 0)
+    }
+}
+
+impl ContactStore for SqliteStore {
+    fn load_contact(&self, contact_id: &str) -> Result<Option<Contact>, String> {
+        let contact_key = self.codec.blind("contact", contact_id);
+        let record: Option<Vec<u8>> = self
+            .connection
+            .query_row("SELECT contact FROM contacts WHERE contact_id = ?1", [&contact_key], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        record.map(|bytes| self.codec.decode("contacts", &contact_key, &bytes)).transpose()
+    }
+
+    fn store_contact(&mut self, contact: &Contact) -> Result<(), String> {
+        let contact_key = self.codec.blind("contact", &contact.id());
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO contacts (contact_id, contact) VALUES (?1, ?2)",
+                params![contact_key, self.codec.encode("contacts", &contact_key, contact)?],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn remove_contact(&mut self, contact_id: &str) -> Result<bool, String> {
+        let removed = self
+            .connection
+            .execute("DELETE FROM contacts WHERE contact_id = ?1", [self.codec.blind("contact", contact_id)])
+            .map_err(sql_error)?;
+        Ok(removed > 0)
+    }
+
+    fn contacts(&self) -> Result<Vec<Contact>, String> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT contact_id, contact FROM contacts")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))
+            .map_err(sql_error)?;
+        // 盲化後的聯絡人 ID 沒有意義的順序，解密後重新排序
+        let mut contacts = rows
+            .map(|row| {
+                let (contact_key, bytes) = row.map_err(sql_error)?;
+                self.codec.decode::<Contact>("contacts", &contact_key, &bytes)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        contacts.sort_by_key(Contact::id);
+        Ok(contacts)
+    }
+}
+
+impl GroupStore for SqliteStore {
+    fn load_group(&self, group_id: &str) -> Result<Option<Group>, String> {
+        let group_key = self.codec.blind("group", group_id);
+        let record: Option<Vec<u8>> = self
+            .connection
+            .query_row("SELECT record FROM chat_groups WHERE group_id = ?1", [&group_key], |row| row.get(0))
+            .optional()
+            .map_err(sql_error)?;
+        record.map(|bytes| self.codec.decode("chat_groups", &group_key, &bytes)).transpose()
+    }
+
+    fn store_group(&mut self, group: &Group) -> Result<(), String> {
+        let group_key = self.codec.blind("group", &group.id());
+        self.connection
+            .execute(
+                "INSERT OR REPLACE INTO chat_groups (group_id, record) VALUES (?1, ?2)",
+                params![group_key, self.codec.encode("chat_groups", &group_key, group)?],
+            )
+            .map_err(sql_error)?;
+        Ok(())
+    }
+
+    fn remove_group(&mut self, group_id: &str) -> Result<bool, String> {
+        let removed = self
+            .connection
+            .execute("DELETE FROM chat_groups WHERE group_id = ?1", [self.codec.blind("group", group_id)])
+            .map_err(sql_error)?;
+        Ok(removed > 0)
+    }
+
+    fn groups(&self) -> Result<Vec<Group>, String> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT group_id, record FROM chat_groups")
+            .map_err(sql_error)?;
+        let rows = statement
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?)))
+            .map_err(sql_error)?;
+        let mut groups = rows
+            .map(|row| {
+                let (group_key, bytes) = row.map_err(sql_error)?;
+                self.codec.decode::<Group>("chat_groups", &group_key, &bytes)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        groups.sort_by_key(Group::id);
+        Ok(groups)
+    }
+}
+
+/// 以 SAVEPOINT 實作，可巢狀
+impl Transactional for SqliteStore {
+    type Checkpoint = ();
+
+    fn begin(&mut self) -> Result<(), String> {
+        self.connection.execute_batch("SAVEPOINT atomic").map_err(sql_error)
+    }
+
+    fn commit(&mut self, _checkpoint: ()) -> Result<(), String> {
+        self.connection.execute_batch("RELEASE atomic").map_err(sql_error)
+    }
+
+    fn rollback(&mut self, _checkpoint: ()) -> Result<(), String> {
+        self.connection
+            .execute_batch("ROLLBACK TO atomic; RELEASE atomic")
+            .map_err(sql_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{PreKeyBundle, RevocationReason, X3DH};
+    use crate::storage::CorruptRecord;
+
+    fn store_with_identity() -> (SqliteStore, IdentityKeyPair) {
+        let identity = IdentityKeyPair::new();
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        store.set_local_identity(&identity, 1234).unwrap();
+        (store, identity)
+    }
+
+    #[test]
+    fn test_sqlite_store_traits() {
+        let (mut store, identity) = store_with_identity();
+        assert_eq!(store.identity_key_pair().unwrap().public_key_bytes(), identity.public_key_bytes());
+        assert_eq!(store.local_registration_id().unwrap(), 1234);
+
+        // 身份信任與撤銷
+        assert_eq!(store.save_identity("bob", &[1u8; 32], 1).unwrap(), IdentityStatus::NewIdentity);
+        assert_eq!(store.check_identity("bob", &[1u8; 32]).unwrap(), IdentityStatus::Trusted);
+        assert_eq!(store.save_identity("bob", &[2u8; 32], 2).unwrap(), IdentityStatus::Changed);
+        let carol = IdentityKeyPair::new();
+        let revocation = RevocationCertificate::create(&carol, RevocationReason::KeyCompromised, 3, None);
+        store.save_identity("carol", &carol.public_key_bytes(), 3).unwrap();
+        assert_eq!(store.apply_revocation("carol", &revocation).unwrap(), IdentityStatus::Trusted);
+        assert_eq!(store.save_identity("carol", &carol.public_key_bytes(), 4).unwrap(), IdentityStatus::Revoked);
+
+        // 會話
+        let session = RatchetSession::for_test([3u8; 32]);
+        store.store_session("bob", 2, &session).unwrap();
+        store.store_session("bob", 1, &session).unwrap();
+        assert_eq!(store.session_devices("bob").unwrap(), vec![1, 2]);
+        assert!(store.load_session("bob", 1).unwrap().is_some());
+        assert!(store.remove_session("bob", 1).unwrap());
+        assert!(!store.remove_session("bob", 1).unwrap());
+
+        // 預金鑰
+        let batch = store.generate_pre_keys(3).unwrap();
+        assert_eq!(store.generate_pre_keys(1).unwrap()[0].key_id, 4);
+        assert!(store.generate_pre_keys(MAX_BATCH_SIZE + 1).is_err_and(|e| e.contains("at once")));
+        assert_eq!(store.generate_pre_keys(1).unwrap()[0].key_id, 5);
+        let loaded = store.load_pre_key(batch[1].key_id).unwrap();
+        let private = store.consume_pre_key(batch[1].key_id).unwrap();
+        assert_eq!(loaded, private);
+        assert_eq!(X25519KeyPair::from_bytes(&private).unwrap().public_key_bytes(), batch[1].public_key);
+        assert!(store.consume_pre_key(batch[1].key_id).is_err());
+        assert!(store.load_pre_key(batch[1].key_id).is_err());
+        assert!(!store.contains_pre_key(batch[1].key_id).unwrap());
+        assert_eq!(store.remaining_pre_keys().unwrap(), 4);
+
+        let record = SignedPreKeyRecord::generate(&identity, 7, 0);
+        store.store_signed_pre_key(&record).unwrap();
+        assert_eq!(store.load_signed_pre_key(7).unwrap().public_key(), record.public_key());
+        assert!(store.remove_signed_pre_key(7).unwrap());
+        assert!(store.load_signed_pre_key(7).is_err());
+    }
+
+    #[test]
+    fn test_sqlite_messages_and_persistence() {
+        let path = std::env::temp_dir().join(format!("safetalk-sqlite-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        {
+            let mut store = SqliteStore::open(path).unwrap();
+            for (i, timestamp) in [30u64, 10, 20, 40].iter().enumerate() {
+                store.store_message(&StoredMessage::new(&format!("m{}", i), "chat", "bob", *timestamp, b"hi")).unwrap();
+            }
+            store.store_message(&StoredMessage::new("m1", "chat", "bob", 50, b"edited")).unwrap();
+        }
+
+        // 重新開啟後資料仍在
+        let mut store = SqliteStore::open(path).unwrap();
+        let timestamps = |messages: Vec<StoredMessage>| messages.iter().map(|m| m.timestamp()).collect::<Vec<_>>();
+        assert_eq!(timestamps(store.conversation_messages("chat", None, None).unwrap()), vec![20, 30, 40, 50]);
+        assert_eq!(timestamps(store.conversation_messages("chat", None, Some(2)).unwrap()), vec![40, 50]);
+        assert_eq!(timestamps(store.conversation_messages("chat", Some(40), Some(5)).unwrap()), vec![20, 30]);
+        assert_eq!(store.load_message("m1").unwrap().unwrap().body(), b"edited");
+        assert!(store.remove_message("m1").unwrap());
+        assert!(store.conversation_messages("other", None, None).unwrap().is_empty());
+
+        // 透過 trait 執行的協定流程：身份變更時不寫入會話
+        store.set_local_identity(&IdentityKeyPair::new(), 1).unwrap();
+        let bob = IdentityKeyPair::new();
+        let bundle = PreKeyBundle::new(bob.public_key_bytes(), SignedPreKeyRecord::generate(&bob, 1, 0).public(), None);
+        store.save_identity("bob", &[9u8; 32], 0).unwrap();
+        assert!(X3DH::initiate_session(&mut store, "bob", 1, &bundle, 1).unwrap().identity_changed());
+        assert!(store.session_devices("bob").unwrap().is_empty());
+
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_encryption_at_rest() {
+        let path = std::env::temp_dir().join(format!("safetalk-sqlite-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let key = StorageKey::generate();
+        {
+            let mut store = SqliteStore::open_encrypted(path, &key).unwrap();
+            store.set_local_identity(&IdentityKeyPair::new(), 42).unwrap();
+            let status = store.save_identity("bob-contact", &[1u8; 32], 1).unwrap();
+            store.apply_identity("bob-contact", &[1u8; 32], status).unwrap();
+            let mut contact = store.load_contact("bob-contact").unwrap().unwrap();
+            contact.set_nickname(Some("bob-nickname".to_string()));
+            store.store_contact(&contact).unwrap();
+            let mut group = Group::new("secret-group");
+            group.set_member("bob-contact", crate::storage::GroupRole::Admin, &[1]);
+            store.store_group(&group).unwrap();
+            store.store_session("bob-contact", 1, &RatchetSession::for_test([3u8; 32])).unwrap();
+            let message = StoredMessage::new("message-id", "secret-chat", "bob-contact", 10, b"launch-codes");
+            store.store_message(&message).unwrap();
+            store.put("push-tokens", "fcm-token", b"push-secret").unwrap();
+            store.put("push-tokens", "apns-token", b"apns").unwrap();
+        }
+
+        // 檔案中找不到任何明文 ID 或內容
+        let raw = std::fs::read(path).unwrap();
+        for needle in [&b"bob-contact"[..], b"secret-chat", b"message-id", b"launch-codes", b"push-tokens", b"fcm-token", b"push-secret", b"bob-nickname", b"secret-group"] {
+            assert!(!raw.windows(needle.len()).any(|window| window == needle));
+        }
+
+        let mut store = SqliteStore::open_encrypted(path, &key).unwrap();
+        assert_eq!(store.local_registration_id().unwrap(), 42);
+        assert_eq!(store.check_identity("bob-contact", &[1u8; 32]).unwrap(), IdentityStatus::Trusted);
+        let contacts = store.contacts().unwrap();
+        assert_eq!(contacts[0].identity_key(), Some(vec![1u8; 32]));
+        assert_eq!(contacts[0].nickname().as_deref(), Some("bob-nickname"));
+        assert!(store.remove_contact("bob-contact").unwrap());
+        assert_eq!(store.groups().unwrap()[0].member("bob-contact").unwrap().devices(), vec![1]);
+        assert!(store.remove_group("secret-group").unwrap());
+        assert!(store.load_group("secret-group").unwrap().is_none());
+        assert_eq!(store.session_devices("bob-contact").unwrap(), vec![1]);
+        assert!(store.load_session("bob-contact", 1).unwrap().is_some());
+        let messages = store.conversation_messages("secret-chat", None, None).unwrap();
+        assert_eq!(messages[0].body(), b"launch-codes");
+        assert!(store.remove_message("message-id").unwrap());
+        assert_eq!(store.get("push-tokens", "fcm-token").unwrap(), Some(b"push-secret".to_vec()));
+        assert_eq!(store.list("push-tokens").unwrap(), vec!["apns-token", "fcm-token"]);
+        assert!(store.remove("push-tokens", "apns-token").unwrap());
+        assert!(store.get("push-tokens", "apns-token").unwrap().is_none());
+
+        // 盲化搜尋索引
+        store.store_message(&StoredMessage::new("m1", "chat", "bob", 1, b"x")).unwrap();
+        store.store_message(&StoredMessage::new("m2", "chat", "bob", 2, b"x")).unwrap();
+        store.index_message("m1", "Lunch at noon?").unwrap();
+        store.index_message("m2", "No lunch today").unwrap();
+        assert_eq!(store.search("LUNCH").unwrap(), vec!["m1", "m2"]);
+        assert_eq!(store.search("lunch noon").unwrap(), vec!["m1"]);
+        assert!(store.search("dinner").unwrap().is_empty());
+        assert!(store.remove_message("m1").unwrap());
+        assert_eq!(store.search("lunch").unwrap(), vec!["m2"]);
+        drop(store);
+
+        // 錯誤的金鑰無法解密
+        let wrong = SqliteStore::open_encrypted(path, &StorageKey::generate()).unwrap();
+        assert!(wrong.local_registration_id().is_err());
+        drop(wrong);
+        // 已有未受密碼保護的資料時不建立密碼鎖
+        assert!(SqliteStore::unlock_storage(path, b"password", &PasswordKdfParams::default()).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_password_unlock() {
+        let path = std::env::temp_dir().join(format!("safetalk-sqlite-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let kdf = PasswordKdfParams::Argon2id(crate::crypto::Argon2idParams { memory_kib: 64, iterations: 1, parallelism: 1 });
+        {
+            let mut store = SqliteStore::unlock_storage(path, b"hunter2", &kdf).unwrap();
+            store.set_local_identity(&IdentityKeyPair::new(), 7).unwrap();
+        }
+
+        assert!(SqliteStore::unlock_storage(path, b"hunter3", &kdf).is_err());
+        assert!(SqliteStore::open(path).is_err());
+        // 之後的解鎖沿用資料庫中的參數
+        let store = SqliteStore::unlock_storage(path, b"hunter2", &PasswordKdfParams::default()).unwrap();
+        assert_eq!(store.local_registration_id().unwrap(), 7);
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_key_rotation() {
+        let path = std::env::temp_dir().join(format!("safetalk-sqlite-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let old_key = StorageKey::generate();
+        let new_key = StorageKey::generate();
+        {
+            let mut store = SqliteStore::open_encrypted(path, &old_key).unwrap();
+            store.set_local_identity(&IdentityKeyPair::new(), 42).unwrap();
+            store.store_session("bob", 1, &RatchetSession::for_test([3u8; 32])).unwrap();
+            store.generate_pre_keys(5).unwrap();
+            store.store_message(&StoredMessage::new("m1", "chat", "bob", 1, b"hello")).unwrap();
+            store.put("tokens", "push", b"secret").unwrap();
+            store.store_contact(&Contact::new("bob")).unwrap();
+
+            // 第一批完成後暫停，輪替期間本實例仍可讀寫
+            let mut batches = 0;
+            assert!(!store.rotate_storage_key(&new_key, 2, |_| { batches += 1; batches < 2 }).unwrap());
+            assert_eq!(store.local_registration_id().unwrap(), 42);
+            store.put("tokens", "apns", b"written during rotation").unwrap();
+            assert!(store.rotate_storage_key(&StorageKey::generate(), 2, |_| true).is_err());
+        }
+
+        // 中斷後以舊金鑰開啟並繼續
+        let mut store = SqliteStore::open_encrypted(path, &old_key).unwrap();
+        let mut stores = Vec::new();
+        assert!(store
+            .rotate_storage_key(&new_key, 2, |progress| {
+                stores.push(progress.store());
+                true
+            })
+            .unwrap());
+        assert_eq!(stores.first().map(String::as_str), Some("local"));
+        assert_eq!(stores.last().map(String::as_str), Some("conversation_keys"));
+        drop(store);
+
+        // 完成後只能以新金鑰開啟，盲化的查詢鍵不變
+        assert_eq!(SqliteStore::open_encrypted(path, &old_key).err().unwrap(), "Wrong storage key");
+        let mut store = SqliteStore::open_encrypted(path, &new_key).unwrap();
+        assert_eq!(store.local_registration_id().unwrap(), 42);
+        assert!(store.load_session("bob", 1).unwrap().is_some());
+        assert_eq!(store.remaining_pre_keys().unwrap(), 5);
+        assert!(store.consume_pre_key(3).is_ok());
+        assert_eq!(store.load_message("m1").unwrap().unwrap().body(), b"hello");
+        assert_eq!(store.get("tokens", "push").unwrap(), Some(b"secret".to_vec()));
+        assert_eq!(store.get("tokens", "apns").unwrap(), Some(b"written during rotation".to_vec()));
+        assert!(store.load_contact("bob").unwrap().is_some());
+        // 再次輪替沿用同一把盲化索引金鑰
+        let third_key = StorageKey::generate();
+        assert!(store.rotate_storage_key(&third_key, 100, |_| true).unwrap());
+        drop(store);
+        let mut store = SqliteStore::open_encrypted(path, &third_key).unwrap();
+        assert_eq!(store.get("tokens", "push").unwrap(), Some(b"secret".to_vec()));
+        // 毀損的舊版訊息 (沒有包裝金鑰) 不會被當成包裝過的記錄略過
+        let mut corrupted = store.codec.encode("messages", "legacy", &"old").unwrap();
+        corrupted.truncate(corrupted.len() - 1);
+        store
+            .connection
+            .execute(
+                "INSERT INTO messages (id, conversation_id, timestamp, message) VALUES ('legacy', 'chat', 5, ?1)",
+                [corrupted],
+            )
+            .unwrap();
+        let error = store.rotate_storage_key(&StorageKey::generate(), 100, |_| true).unwrap_err();
+        assert!(error.contains("messages"));
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+
+        // 變更儲存密碼
+        let kdf = PasswordKdfParams::Argon2id(crate::crypto::Argon2idParams { memory_kib: 64, iterations: 1, parallelism: 1 });
+        let path = std::env::temp_dir().join(format!("safetalk-sqlite-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let mut store = SqliteStore::unlock_storage(path, b"hunter2", &kdf).unwrap();
+        store.set_local_identity(&IdentityKeyPair::new(), 7).unwrap();
+        assert!(store.rotate_storage_key(&new_key, 10, |_| true).is_err());
+        assert!(!store.change_storage_password(b"correct horse", &kdf, 1, |_| false).unwrap());
+        drop(store);
+        let mut store = SqliteStore::unlock_storage(path, b"hunter2", &kdf).unwrap();
+        assert!(store.change_storage_password(b"wrong horse", &kdf, 1, |_| true).is_err());
+        assert!(store.change_storage_password(b"correct horse", &kdf, 1, |_| true).unwrap());
+        drop(store);
+        assert!(SqliteStore::unlock_storage(path, b"hunter2", &kdf).is_err());
+        let store = SqliteStore::unlock_storage(path, b"correct horse", &kdf).unwrap();
+        assert_eq!(store.local_registration_id().unwrap(), 7);
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_sqlite_verify_storage() {
+        let (mut store, _) = store_with_identity();
+        let pre_keys = store.generate_pre_keys(2).unwrap();
+        store.store_session("bob", 1, &RatchetSession::for_test([3u8; 32])).unwrap();
+        store.store_session("bob", 2, &RatchetSession::for_test([4u8; 32])).unwrap();
+        store.store_message(&StoredMessage::new("m1", "chat", "bob", 1, b"hello")).unwrap();
+        store.put("prefs", "theme", b"dark").unwrap();
+        assert!(store.verify_storage(false).unwrap().healthy());
+
+        // 模擬寫壞的記錄：預金鑰、會話與鍵值各一筆
+        let corrupt = |store: &SqliteStore, table: &str, column: &str, condition: &str| {
+            let query = format!("SELECT {} FROM {} WHERE {}", column, table, condition);
+            let mut bytes: Vec<u8> = store.connection.query_row(&query, [], |row| row.get(0)).unwrap();
+            *bytes.last_mut().unwrap() ^= 1;
+            let update = format!("UPDATE {} SET {} = ?1 WHERE {}", table, column, condition);
+            store.connection.execute(&update, [bytes]).unwrap();
+        };
+        corrupt(&store, "pre_keys", "keypair", &format!("key_id = {}", pre_keys[0].key_id));
+        corrupt(&store, "sessions", "session", "device_id = 2");
+        corrupt(&store, "kv", "value", "key = 'theme'");
+
+        let report = store.verify_storage(false).unwrap();
+        let mut stores: Vec<_> = report.corrupted().iter().map(CorruptRecord::store).collect();
+        stores.sort();
+        assert_eq!(stores, vec!["kv", "pre_keys", "sessions"]);
+        assert!(report.corrupted().iter().all(|record| !record.repaired()));
+        assert!(store.get("prefs", "theme").is_err());
+
+        // 修復：刪除預金鑰與會話，會話標記為需要重新握手，其他記錄只回報
+        let report = store.verify_storage(true).unwrap();
+        for record in report.corrupted() {
+            assert_eq!(record.repaired(), record.action() != RepairAction::ReportOnly);
+        }
+        assert!(!store.contains_pre_key(pre_keys[0].key_id).unwrap());
+        assert!(store.contains_pre_key(pre_keys[1].key_id).unwrap());
+        assert_eq!(store.session_devices("bob").unwrap(), vec![1]);
+        assert!(store.needs_rehandshake("bob", 2).unwrap());
+        assert!(!store.needs_rehandshake("bob", 1).unwrap());
+        assert!(store.clear_rehandshake("bob", 2).unwrap());
+        assert!(!store.needs_rehandshake("bob", 2).unwrap());
+
+        let report = store.verify_storage(true).unwrap();
+        assert_eq!(report.corrupted().len(), 1);
+        assert_eq!(report.corrupted()[0].store(), "kv");
+        assert_eq!(store.conversation_messages("chat", None, None).unwrap()[0].body(), b"hello");
+    }
+
+    #[test]
+    fn test_sqlite_atomic() {
+        let (mut store, _) = store_with_identity();
+        let batch = store.generate_pre_keys(1).unwrap();
+        let key_id = batch[0].key_id;
+        let session = RatchetSession::for_test([3u8; 32]);
+        let establish = |store: &mut SqliteStore, fail: bool| {
+            store.consume_pre_key(key_id)?;
+            store.store_session("bob", 1, &session)?;
+            store.store_message(&StoredMessage::new("m1", "chat", "bob", 1, b"hi"))?;
+            store.put("push", "token", b"x")?;
+            if fail {
+                return Err("Failed to decrypt message".to_string());
+            }
+            Ok(())
+        };
+
+        assert!(store.atomic(|store| establish(store, true)).is_err());
+        assert!(store.contains_pre_key(key_id).unwrap());
+        assert!(store.session_devices("bob").unwrap().is_empty());
+        assert!(store.load_message("m1").unwrap().is_none());
+        assert!(store.list("push").unwrap().is_empty());
+
+        store.atomic(|store| establish(store, false)).unwrap();
+        assert!(!store.contains_pre_key(key_id).unwrap());
+        assert_eq!(store.session_devices("bob").unwrap(), vec![1]);
+        assert!(store.load_message("m1").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_sqlite_retention() {
+        let path = std::env::temp_dir().join(format!("safetalk-sqlite-{}.db", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        {
+            let mut store = SqliteStore::open(path).unwrap();
+            for timestamp in 1..=4u64 {
+                let message = StoredMessage::new(&format!("m{}", timestamp), "chat", "bob", timestamp, b"self-destruct");
+                store.store_message(&message).unwrap();
+            }
+            store.set_retention_policy("chat", Some(RetentionPolicy::new(Some(10), Some(3)))).unwrap();
+        }
+
+        // 即使未加密，訊息內容也以包裝金鑰加密
+        let raw = std::fs::read(path).unwrap();
+        assert!(!raw.windows(13).any(|window| window == b"self-destruct"));
+
+        let mut store = SqliteStore::open(path).unwrap();
+        assert_eq!(store.retention_policies().unwrap(), vec![("chat".to_string(), RetentionPolicy::new(Some(10), Some(3)))]);
+        // 保留刪除前的包裝金鑰，模擬未被覆寫而殘留的頁面
+        let (m1, chat) = (store.codec.blind("message", "m1"), store.codec.blind("conversation", "chat"));
+        let (leftover, record): (Vec<u8>, Vec<u8>) = store
+            .connection
+            .query_row("SELECT k.key, m.message FROM message_keys k JOIN messages m ON m.id = k.id WHERE k.id = ?1", [&m1], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!(store.enforce_retention(12).unwrap(), vec!["m1", "m2"]);
+        assert_eq!(store.conversation_messages("chat", None, None).unwrap().len(), 2);
+        // 刪除後輪替了對話金鑰，殘留的包裝金鑰無法以目前的對話金鑰解開
+        let conversation: Vec<u8> = store
+            .connection
+            .query_row("SELECT key FROM conversation_keys WHERE conversation_id = ?1", [&chat], |row| row.get(0))
+            .unwrap();
+        let conversation = store.codec.open_conversation_key(&chat, &conversation).unwrap();
+        assert!(store
+            .codec
+            .open_wrapped::<StoredMessage>(Some(&conversation), "messages", &m1, &record, Some(&leftover))
+            .is_err());
+        assert_eq!(store.enforce_retention(100).unwrap(), vec!["m3", "m4"]);
+        let keys: u32 = store.connection.query_row("SELECT COUNT(*) FROM message_keys", [], |row| row.get(0)).unwrap();
+        assert_eq!(keys, 0);
+        // 對話中已沒有訊息，對話金鑰一併刪除
+        let conversation_keys: u32 =
+            store.connection.query_row("SELECT COUNT(*) FROM conversation_keys", [], |row| row.get(0)).unwrap();
+        assert_eq!(conversation_keys, 0);
+
+        store.set_retention_policy("chat", None).unwrap();
+        assert!(store.retention_policies().unwrap().is_empty());
+        drop(store);
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,411 @@
+//! 協定儲存介面模組
+//!
+//! 以 `SessionStore`、`IdentityKeyStore`、`PreKeyStore`、`SignedPreKeyStore`
+//! 抽象化協定需要的持久化資料，X3DH / 會話建立流程只依賴這些 trait，
+//! 更換儲存後端 (記憶體、IndexedDB、SQLite…) 不需修改協定程式碼
+//!
+//! `InMemoryProtocolStore` 是全部存在記憶體中的實作，可序列化後整體保存，
+//! 會話與身份變更時通知訂閱者 (見 `events` 模組)；
+//! 只能非同步存取的後端 (IndexedDB) 實作對應的 `Async*` trait
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::{
+    IdentityKeyPair, OneTimePreKeyPool, PreKeyBundle, RatchetSession, SessionEstablishment,
+    SignedPreKeyRecord, X3DHInitialMessage, X3DH,
+};
+use super::encryption::{RecordCodec, StorageKey, SNAPSHOT_STORE};
+use super::events::{StoreEvent, StoreEventKind, StoreObservers};
+use super::trust::{IdentityStatus, IdentityTrustStore};
+
+const PROTOCOL_SNAPSHOT_KEY: &str = "protocol_store";
+
+/// 本機身份與聯絡人身份信任儲存
+pub trait IdentityKeyStore {
+    /// 本機身份金鑰對
+    fn identity_key_pair(&self) -> Result<IdentityKeyPair, String>;
+
+    /// 本機註冊 ID
+    fn local_registration_id(&self) -> Result<u32, String>;
+
+    /// 檢查聯絡人身份公鑰
+    fn check_identity(&self, contact_id: &str, identity_key: &[u8]) -> Result<IdentityStatus, String>;
+
+    /// 記錄聯絡人身份公鑰，回傳記錄前的狀態
+    fn save_identity(&mut self, contact_id: &str, identity_key: &[u8], now: u64) -> Result<IdentityStatus, String>;
+}
+
+/// Double Ratchet 會話儲存，以 (聯絡人 ID, 裝置 ID) 定址
+pub trait SessionStore {
+    /// 載入會話
+    fn load_session(&self, contact_id: &str, device_id: u32) -> Result<Option<RatchetSession>, String>;
+
+    /// 儲存 (覆寫) 會話
+    fn store_session(&mut self, contact_id: &str, device_id: u32, session: &RatchetSession) -> Result<(), String>;
+
+    /// 刪除會話，回傳是否存在
+    fn remove_session(&mut self, contact_id: &str, device_id: u32) -> Result<bool, String>;
+
+    /// 聯絡人有會話的所有裝置 ID
+    fn session_devices(&self, contact_id: &str) -> Result<Vec<u32>, String>;
+}
+
+/// 一次性預金鑰儲存
+pub trait PreKeyStore {
+    /// 是否仍持有此一次性預金鑰
+    fn contains_pre_key(&self, key_id: u32) -> Result<bool, String>;
+
+    /// 讀取一次性預金鑰的私鑰但不消耗 (金鑰交換成功後再呼叫 `consume_pre_key`)
+    fn load_pre_key(&self, key_id: u32) -> Result<Vec<u8>, String>;
+
+    /// 消耗一次性預金鑰並回傳私鑰，同一把金鑰只能取出一次
+    fn consume_pre_key(&mut self, key_id: u32) -> Result<Vec<u8>, String>;
+}
+
+/// 簽署預金鑰儲存
+pub trait SignedPreKeyStore {
+    /// 載入簽署預金鑰
+    fn load_signed_pre_key(&self, key_id: u32) -> Result<SignedPreKeyRecord, String>;
+
+    /// 儲存簽署預金鑰
+    fn store_signed_pre_key(&mut self, record: &SignedPreKeyRecord) -> Result<(), String>;
+
+    /// 刪除簽署預金鑰 (輪替後的舊金鑰)，回傳是否存在
+    fn remove_signed_pre_key(&mut self, key_id: u32) -> Result<bool, String>;
+}
+
+/// 非同步身份儲存，方法與 [`IdentityKeyStore`] 相同
+#[allow(async_fn_in_trait)]
+pub trait AsyncIdentityKeyStore {
+    async fn identity_key_pair(&self) -> Result<IdentityKeyPair, String>;
+    async fn local_registration_id(&self) -> Result<u32, String>;
+    async fn check_identity(&self, contact_id: &str, identity_key: &[u8]) -> Result<IdentityStatus, String>;
+    async fn save_identity(&mut self, contact_id: &str, identity_key: &[u8], now: u64) -> Result<IdentityStatus, String>;
+}
+
+/// 非同步會話儲存，方法與 [`SessionStore`] 相同
+#[allow(async_fn_in_trait)]
+pub trait AsyncSessionStore {
+    async fn load_session(&self, contact_id: &str, device_id: u32) -> Result<Option<RatchetSession>, String>;
+    async fn store_session(&mut self, contact_id: &str, device_id: u32, session: &RatchetSession) -> Result<(), String>;
+    async fn remove_session(&mut self, contact_id: &str, device_id: u32) -> Result<bool, String>;
+    async fn session_devices(&self, contact_id: &str) -> Result<Vec<u32>, String>;
+}
+
+/// 非同步一次性預金鑰儲存，方法與 [`PreKeyStore`] 相同
+#[allow(async_fn_in_trait)]
+pub trait AsyncPreKeyStore {
+    async fn contains_pre_key(&self, key_id: u32) -> Result<bool, String>;
+    async fn load_pre_key(&self, key_id: u32) -> Result<Vec<u8>, String>;
+    async fn consume_pre_key(&mut self, key_id: u32) -> Result<Vec<u8>, String>;
+}
+
+/// 非同步簽署預金鑰儲存，方法與 [`SignedPreKeyStore`] 相同
+#[allow(async_fn_in_trait)]
+pub trait AsyncSignedPreKeyStore {
+    async fn load_signed_pre_key(&self, key_id: u32) -> Result<SignedPreKeyRecord, String>;
+    async fn store_signed_pre_key(&mut self, record: &SignedPreKeyRecord) -> Result<(), String>;
+    async fn remove_signed_pre_key(&mut self, key_id: u32) -> Result<bool, String>;
+}
+
+/// 記憶體協定儲存
+#[wasm_bindgen]
+#[derive(Clone, Serialize, Deserialize)]
+pub struct InMemoryProtocolStore {
+    identity: IdentityKeyPair,
+    registration_id: u32,
+    trust_store: IdentityTrustStore,
+    /// (contact_id, device_id) -> 會話
+    sessions: BTreeMap<(String, u32), RatchetSession>,
+    pre_keys: OneTimePreKeyPool,
+    /// key_id -> 簽署預金鑰
+    signed_pre_keys: BTreeMap<u32, SignedPreKeyRecord>,
+    /// 變更訂閱者，不序列化
+    #[serde(skip)]
+    observers: StoreObservers,
+}
+
+impl IdentityKeyStore for InMemoryProtocolStore {
+    fn identity_key_pair(&self) -> Result<IdentityKeyPair, String> {
+        Ok(self.identity.clone())
+    }
+
+    fn local_registration_id(&self) -> Result<u32, String> {
+        Ok(self.registration_id)
+    }
+
+    fn check_identity(&self, contact_id: &str, identity_key: &[u8]) -> Result<IdentityStatus, String> {
+        Ok(self.trust_store.check_identity(contact_id, identity_key))
+    }
+
+    fn save_identity(&mut self, contact_id: &str, identity_key: &[u8], now: u64) -> Result<IdentityStatus, String> {
+        let status = self.trust_store.save_identity(contact_id, identity_key, now);
+        if matches!(status, IdentityStatus::NewIdentity | IdentityStatus::Changed) {
+            self.observers.emit(StoreEvent::identity_changed(contact_id, status));
+        }
+        Ok(status)
+    }
+}
+
+impl SessionStore for InMemoryProtocolStore {
+    fn load_session(&self, contact_id: &str, device_id: u32) -> Result<Option<RatchetSession>, String> {
+        Ok(self.sessions.get(&(contact_id.to_string(), device_id)).cloned())
+    }
+
+    fn store_session(&mut self, contact_id: &str, device_id: u32, session: &RatchetSession) -> Result<(), String> {
+        self.sessions.insert((contact_id.to_string(), device_id), session.clone());
+        self.observers
+            .emit(StoreEvent::session(StoreEventKind::SessionUpdated, contact_id, device_id));
+        Ok(())
+    }
+
+    fn remove_session(&mut self, contact_id: &str, device_id: u32) -> Result<bool, String> {
+        let removed = self.sessions.remove(&(contact_id.to_string(), device_id)).is_some();
+        if removed {
+            self.observers
+                .emit(StoreEvent::session(StoreEventKind::SessionRemoved, contact_id, device_id));
+        }
+        Ok(removed)
+    }
+
+    fn session_devices(&self, contact_id: &str) -> Result<Vec<u32>, String> {
+        Ok(self
+            .sessions
+            .keys()
+            .filter(|(contact, _)| contact == contact_id)
+            .map(|(_, device)| *device)
+            .collect())
+    }
+}
+
+impl PreKeyStore for InMemoryProtocolStore {
+    fn contains_pre_key(&self, key_id: u32) -> Result<bool, String> {
+        Ok(self.pre_keys.contains_key(key_id))
+    }
+
+    fn load_pre_key(&self, key_id: u32) -> Result<Vec<u8>, String> {
+        self.pre_keys.private_key(key_id)
+    }
+
+    fn consume_pre_key(&mut self, key_id: u32) -> Result<Vec<u8>, String> {
+        self.pre_keys.consume(key_id)
+    }
+}
+
+impl SignedPreKeyStore for InMemoryProtocolStore {
+    fn load_signed_pre_key(&self, key_id: u32) -> Result<SignedPreKeyRecord, String> {
+        self.signed_pre_keys
+            .get(&key_id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown signed prekey: {}", key_id))
+    }
+
+    fn store_signed_pre_key(&mut self, record: &SignedPreKeyRecord) -> Result<(), String> {
+        self.signed_pre_keys.insert(record.key_id(), record.clone());
+        Ok(())
+    }
+
+    fn remove_signed_pre_key(&mut self, key_id: u32) -> Result<bool, String> {
+        Ok(self.signed_pre_keys.remove(&key_id).is_some())
+    }
+}
+
+impl InMemoryProtocolStore {
+    /// 變更訂閱者 (Rust 端訂閱用)
+    pub fn observers(&self) -> &StoreObservers {
+        &self.observers
+    }
+}
+
+#[wasm_bindgen]
+impl InMemoryProtocolStore {
+    /// 以身份金鑰建立空的儲存
+    #[wasm_bindgen(constructor)]
+    pub fn new(identity: &IdentityKeyPair, registration_id: u32) -> Self {
+        Self {
+            identity: identity.clone(),
+            registration_id,
+            trust_store: IdentityTrustStore::new(),
+            sessions: BTreeMap::new(),
+            pre_keys: OneTimePreKeyPool::new(1, None, None),
+            signed_pre_keys: BTreeMap::new(),
+            observers: StoreObservers::default(),
+        }
+    }
+
+    /// 加入簽署預金鑰
+    #[wasm_bindgen(js_name = addSignedPreKey)]
+    pub fn add_signed_pre_key(&mut self, record: &SignedPreKeyRecord) {
+        self.signed_pre_keys.insert(record.key_id(), record.clone());
+    }
+
+    /// 產生一次性預金鑰，回傳公鑰列表 JSON
+    #[wasm_bindgen(js_name = generatePreKeys)]
+    pub fn generate_pre_keys(&mut self, count: u32) -> Result<String, JsError> {
+        self.pre_keys.generate_batch(count)
+    }
+
+    /// 剩餘的一次性預金鑰數量
+    #[wasm_bindgen(js_name = remainingPreKeys)]
+    pub fn remaining_pre_keys(&self) -> u32 {
+        self.pre_keys.remaining_count()
+    }
+
+    /// 載入會話
+    #[wasm_bindgen(js_name = loadSession)]
+    pub fn load_session_js(&self, contact_id: &str, device_id: u32) -> Option<RatchetSession> {
+        self.sessions.get(&(contact_id.to_string(), device_id)).cloned()
+    }
+
+    /// 儲存 (覆寫) 會話
+    #[wasm_bindgen(js_name = storeSession)]
+    pub fn store_session_js(&mut self, contact_id: &str, device_id: u32, session: &RatchetSession) -> Result<(), JsError> {
+        self.store_session(contact_id, device_id, session).map_err(|e| JsError::new(&e))
+    }
+
+    /// 訂閱會話與身份變更：`(event: StoreEvent) => void`，回傳取消訂閱用的 ID
+    #[wasm_bindgen(js_name = onChange)]
+    pub fn on_change(&self, callback: js_sys::Function) -> u32 {
+        self.observers.subscribe_js(callback)
+    }
+
+    /// 取消訂閱，回傳是否存在
+    #[wasm_bindgen(js_name = offChange)]
+    pub fn off_change(&self, id: u32) -> bool {
+        self.observers.unsubscribe(id)
+    }
+
+    /// 發起者：對 bundle 完成 X3DH 並儲存新的會話
+    #[wasm_bindgen(js_name = initiateSession)]
+    pub fn initiate_session(
+        &mut self,
+        contact_id: &str,
+        device_id: u32,
+        bundle: &PreKeyBundle,
+        now: u64,
+    ) -> Result<SessionEstablishment, JsError> {
+        X3DH::initiate_session(self, contact_id, device_id, bundle, now)
+    }
+
+    /// 接收者：處理初始訊息並儲存新的會話
+    #[wasm_bindgen(js_name = respondSession)]
+    pub fn respond_session(
+        &mut self,
+        contact_id: &str,
+        signed_pre_key_id: u32,
+        initial_message: &X3DHInitialMessage,
+        now: u64,
+    ) -> Result<SessionEstablishment, JsError> {
+        X3DH::respond_session(self, contact_id, signed_pre_key_id, initial_message, now)
+    }
+
+    /// 序列化 (包含私鑰，敏感！)
+    #[wasm_bindgen(js_name = serialize)]
+    pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 還原
+    #[wasm_bindgen(js_name = deserialize)]
+    pub fn deserialize(bytes: &[u8]) -> Result<InMemoryProtocolStore, JsError> {
+        bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 以主儲存金鑰加密序列化 (例如寫入 localStorage 前)
+    #[wasm_bindgen(js_name = serializeEncrypted)]
+    pub fn serialize_encrypted(&self, key: &StorageKey) -> Result<Vec<u8>, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .encode(SNAPSHOT_STORE, PROTOCOL_SNAPSHOT_KEY, self)
+            .map_err(|e| JsError::new(&e))
+    }
+
+    /// 解密並還原
+    #[wasm_bindgen(js_name = deserializeEncrypted)]
+    pub fn deserialize_encrypted(bytes: &[u8], key: &StorageKey) -> Result<InMemoryProtocolStore, JsError> {
+        RecordCodec::new(Some(key.clone()))
+            .decode(SNAPSHOT_STORE, PROTOCOL_SNAPSHOT_KEY, bytes)
+            .map_err(|e| JsError::new(&e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{generate_registration_id, X25519KeyPair};
+
+    fn store() -> InMemoryProtocolStore {
+        InMemoryProtocolStore::new(&IdentityKeyPair::new(), generate_registration_id())
+    }
+
+    #[test]
+    fn test_in_memory_store_traits() {
+        let mut store = store();
+        let identity = store.identity.public_key_bytes();
+        assert_eq!(store.identity_key_pair().unwrap().public_key_bytes(), identity);
+
+        // 身份信任
+        assert_eq!(store.save_identity("bob", &[1u8; 32], 1).unwrap(), IdentityStatus::NewIdentity);
+        assert_eq!(store.check_identity("bob", &[1u8; 32]).unwrap(), IdentityStatus::Trusted);
+        assert_eq!(store.check_identity("bob", &[2u8; 32]).unwrap(), IdentityStatus::Changed);
+
+        // 會話
+        let session = RatchetSession::for_test([3u8; 32]);
+        store.store_session("bob", 2, &session).unwrap();
+        store.store_session("bob", 1, &session).unwrap();
+        store.store_session("carol", 1, &session).unwrap();
+        assert_eq!(store.session_devices("bob").unwrap(), vec![1, 2]);
+        assert!(store.load_session("bob", 1).unwrap().is_some());
+        assert!(store.remove_session("bob", 1).unwrap());
+        assert!(!store.remove_session("bob", 1).unwrap());
+        assert!(store.load_session("bob", 1).unwrap().is_none());
+
+        // 預金鑰
+        store.pre_keys.generate_keys(2).unwrap();
+        assert!(store.contains_pre_key(1).unwrap());
+        let private = store.consume_pre_key(1).unwrap();
+        assert_eq!(X25519KeyPair::from_bytes(&private).unwrap().public_key_bytes().len(), 32);
+        assert!(store.consume_pre_key(1).is_err());
+        assert_eq!(store.remaining_pre_keys(), 1);
+
+        let record = SignedPreKeyRecord::generate(&store.identity, 7, 0);
+        store.store_signed_pre_key(&record).unwrap();
+        assert_eq!(store.load_signed_pre_key(7).unwrap().public_key(), record.public_key());
+        assert!(store.load_signed_pre_key(8).is_err());
+
+        let mut restored = InMemoryProtocolStore::deserialize(&store.serialize().unwrap()).unwrap();
+        assert_eq!(restored.session_devices("bob").unwrap(), vec![2]);
+        assert!(restored.remove_signed_pre_key(7).is_ok());
+
+        let key = StorageKey::generate();
+        let sealed = store.serialize_encrypted(&key).unwrap();
+        let restored = InMemoryProtocolStore::deserialize_encrypted(&sealed, &key).unwrap();
+        assert_eq!(restored.session_devices("bob").unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn test_session_establishment_checks_identity_first() {
+        let mut alice = store();
+        let bob_identity = IdentityKeyPair::new();
+        let prekey = SignedPreKeyRecord::generate(&bob_identity, 1, 0);
+        let bundle = PreKeyBundle::new(bob_identity.public_key_bytes(), prekey.public(), None);
+
+        // 身份變更時不建立會話，也不消耗任何金鑰
+        alice.save_identity("bob", &[9u8; 32], 0).unwrap();
+        let result = alice.initiate_session("bob", 1, &bundle, 1).unwrap();
+        assert!(result.identity_changed());
+        assert!(result.shared_secret().is_none());
+        assert!(alice.session_devices("bob").unwrap().is_empty());
+
+        let mut bob = InMemoryProtocolStore::new(&bob_identity, 1);
+        bob.add_signed_pre_key(&prekey);
+        bob.pre_keys.generate_keys(1).unwrap();
+        bob.save_identity("alice", &[9u8; 32], 0).unwrap();
+        let message = X3DH::create_initial_message(&alice.identity.public_key_bytes(), &[5u8; 32], Some(1));
+        let result = bob.respond_session("alice", 1, &message, 1).unwrap();
+        assert!(result.identity_changed());
+        assert_eq!(bob.remaining_pre_keys(), 1);
+    }
+}
@@ -0,0 +1,175 @@
+//! 多分頁協調模組 (feature = "indexeddb")
+//!
+//! 同一個資料庫在多個分頁開啟時，各分頁各自載入會話、推進 ratchet 再寫回，
+//! 後寫入的分頁會覆蓋其他分頁的 ratchet 狀態。`IndexedDbStore.coordinateTabs` 啟用後：
+//! - 以 Web Locks API 的獨佔鎖 `safetalk:<資料庫名稱>:writer` 選出唯一的寫入分頁，
+//!   只有它能寫入 / 刪除會話與消耗一次性預金鑰，其他分頁的這些操作回傳錯誤
+//! - 寫入分頁關閉 (或呼叫 `close`) 時，鎖自動交給下一個等待中的分頁
+//! - 各分頁的儲存變更通知經 BroadcastChannel 轉發給其他分頁的訂閱者，
+//!   非寫入分頁由 `onChange` 得知會話更新後重新讀取
+//!
+//! web-sys 的 `LockManager` 仍屬 unstable API，這裡直接以 wasm-bindgen 綁定 `navigator.locks`
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use js_sys::{Function, Promise};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{BroadcastChannel, MessageEvent};
+
+use super::events::{StoreEvent, StoreObservers};
+
+#[wasm_bindgen]
+extern "C" {
+    /// `navigator.locks`
+    type LockManager;
+
+    /// 取得鎖後呼叫 `callback`，鎖保持到 callback 回傳的 Promise 結束
+    #[wasm_bindgen(method)]
+    fn request(this: &LockManager, name: &str, callback: &Function) -> Promise;
+}
+
+/// 取得全域的 `navigator.locks` (window 與 worker 皆可)
+fn lock_manager() -> Result<LockManager, String> {
+    let navigator = js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("navigator"))
+        .map_err(|_| "Web Locks API is not available".to_string())?;
+    js_sys::Reflect::get(&navigator, &JsValue::from_str("locks"))
+        .ok()
+        .filter(|locks| !locks.is_undefined() && !locks.is_null())
+        .map(JsCast::unchecked_into)
+        .ok_or_else(|| "Web Locks API is not available".to_string())
+}
+
+type LockCallback = Closure<dyn FnMut(JsValue) -> Promise>;
+type MessageCallback = Closure<dyn FnMut(MessageEvent)>;
+
+struct TabState {
+    /// 未停止協調
+    active: Cell<bool>,
+    /// 目前持有寫入鎖
+    writer: Cell<bool>,
+    /// 結束鎖 callback 的 Promise (釋放寫入鎖)
+    release: RefCell<Option<Function>>,
+    channel: BroadcastChannel,
+    /// 正在轉發其他分頁的通知 (不再廣播回去)
+    relaying: Cell<bool>,
+    observers: StoreObservers,
+    subscription: Cell<Option<u32>>,
+    on_lock: RefCell<Option<LockCallback>>,
+    on_message: RefCell<Option<MessageCallback>>,
+}
+
+/// 分頁協調狀態 (複製出的實例共用)
+#[derive(Clone)]
+pub(crate) struct TabCoordinator {
+    state: Rc<TabState>,
+}
+
+impl TabCoordinator {
+    /// 開始協調：排隊取得寫入鎖並轉發變更通知
+    ///
+    /// 取得寫入鎖時呼叫 `on_writer` (callback 拋出的例外會被忽略)
+    pub(crate) fn start(database: &str, observers: &StoreObservers, on_writer: Option<Function>) -> Result<Self, String> {
+        let locks = lock_manager()?;
+        let channel = BroadcastChannel::new(&format!("safetalk:{}:events", database))
+            .map_err(|e| format!("Failed to open BroadcastChannel: {:?}", e))?;
+        let state = Rc::new(TabState {
+            active: Cell::new(true),
+            writer: Cell::new(false),
+            release: RefCell::new(None),
+            channel,
+            relaying: Cell::new(false),
+            observers: observers.clone(),
+            subscription: Cell::new(None),
+            on_lock: RefCell::new(None),
+            on_message: RefCell::new(None),
+        });
+
+        // 本分頁的變更廣播給其他分頁
+        let outgoing = Rc::downgrade(&state);
+        let subscription = observers.subscribe(move |event| {
+            let Some(state) = outgoing.upgrade() else {
+                return;
+            };
+            if state.relaying.get() {
+                return;
+            }
+            if let Ok(message) = serde_wasm_bindgen::to_value(event) {
+                let _ = state.channel.post_message(&message);
+            }
+        });
+        state.subscription.set(Some(subscription));
+
+        // 其他分頁的變更轉給本分頁的訂閱者
+        let incoming = Rc::downgrade(&state);
+        let on_message = MessageCallback::new(move |message: MessageEvent| {
+            let Some(state) = incoming.upgrade() else {
+                return;
+            };
+            let Ok(event) = serde_wasm_bindgen::from_value::<StoreEvent>(message.data()) else {
+                return;
+            };
+            state.relaying.set(true);
+            state.observers.emit(event);
+            state.relaying.set(false);
+        });
+        state.channel.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        *state.on_message.borrow_mut() = Some(on_message);
+
+        let granted = Rc::downgrade(&state);
+        let on_lock = LockCallback::new(move |_lock: JsValue| {
+            let Some(state) = granted.upgrade().filter(|state| state.active.get()) else {
+                // 排隊期間已停止協調，立即釋放
+                return Promise::resolve(&JsValue::UNDEFINED);
+            };
+            let held = Promise::new(&mut |resolve, _reject| {
+                *state.release.borrow_mut() = Some(resolve);
+            });
+            state.writer.set(true);
+            if let Some(callback) = &on_writer {
+                let _ = callback.call0(&JsValue::NULL);
+            }
+            held
+        });
+        // 鎖請求的 Promise 只在釋放時結束，不需等待
+        let _ = locks.request(&format!("safetalk:{}:writer", database), on_lock.as_ref().unchecked_ref());
+        *state.on_lock.borrow_mut() = Some(on_lock);
+
+        Ok(Self { state })
+    }
+
+    /// 目前是否持有寫入鎖
+    pub(crate) fn is_writer(&self) -> bool {
+        self.state.writer.get()
+    }
+
+    /// 會話寫入與預金鑰消耗前檢查
+    pub(crate) fn ensure_writer(&self) -> Result<(), String> {
+        if self.is_writer() {
+            Ok(())
+        } else {
+            Err("Another tab holds the session writer lock".to_string())
+        }
+    }
+
+    /// 停止協調：釋放寫入鎖、關閉 BroadcastChannel
+    pub(crate) fn stop(&self) {
+        let state = &self.state;
+        state.active.set(false);
+        state.writer.set(false);
+        if let Some(release) = state.release.borrow_mut().take() {
+            let _ = release.call0(&JsValue::NULL);
+        }
+        if let Some(id) = state.subscription.take() {
+            state.observers.unsubscribe(id);
+        }
+        state.channel.set_onmessage(None);
+        state.channel.close();
+        state.on_message.borrow_mut().take();
+        // 仍在排隊的鎖請求之後會呼叫 callback (立即釋放)，不能隨協調狀態一起釋放
+        if let Some(on_lock) = state.on_lock.borrow_mut().take() {
+            on_lock.forget();
+        }
+    }
+}
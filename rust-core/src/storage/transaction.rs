@@ -0,0 +1,165 @@
+//! 跨儲存交易模組
+//!
+//! `Transactional::atomic` 執行一連串儲存操作，全部成功才提交，任何一步
+//! 回傳錯誤時還原所有變更。例如消耗一次性預金鑰、建立會話與儲存解密後的
+//! 訊息必須一起完成，否則中途失敗會永久燒掉預金鑰或讓訊息被重複處理
+//!
+//! - 記憶體儲存：開始時保留複本，還原時換回複本；變更通知在提交後才發出 (見 `events` 模組)
+//! - `SqliteStore`：以 SAVEPOINT 實作，可巢狀；同一個資料庫中的協定儲存、
+//!   訊息儲存與鍵值儲存由同一個交易涵蓋，程序當機時 SQLite 自動還原
+//! - 多個儲存以 tuple 組合 (`(&mut protocol, &mut messages).atomic(...)`)，
+//!   依序提交；只有記憶體儲存的組合保證整體原子性
+//!
+//! IndexedDB 的交易在事件迴圈閒置時自動提交，無法跨越多次非同步呼叫，
+//! 因此 `IndexedDbStore` 不實作本介面；需要原子性的操作改在單一方法中完成
+
+use super::kv::InMemoryKeyValueStore;
+use super::messages::InMemoryMessageStore;
+use super::store::InMemoryProtocolStore;
+
+/// 可還原的儲存
+pub trait Transactional {
+    /// 還原所需的狀態
+    type Checkpoint;
+
+    /// 開始交易
+    fn begin(&mut self) -> Result<Self::Checkpoint, String>;
+
+    /// 提交交易
+    fn commit(&mut self, checkpoint: Self::Checkpoint) -> Result<(), String>;
+
+    /// 還原交易開始後的所有變更
+    fn rollback(&mut self, checkpoint: Self::Checkpoint) -> Result<(), String>;
+
+    /// 執行 `f`，成功時提交，回傳錯誤時還原
+    fn atomic<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, String>) -> Result<T, String>
+    where
+        Self: Sized,
+    {
+        let checkpoint = self.begin()?;
+        match f(self) {
+            Ok(value) => {
+                self.commit(checkpoint)?;
+                Ok(value)
+            }
+            Err(error) => {
+                self.rollback(checkpoint)?;
+                Err(error)
+            }
+        }
+    }
+}
+
+/// 記憶體儲存以複本作為還原點，有訂閱者的儲存在提交後才發出變更通知
+macro_rules! impl_transactional_by_clone {
+    ($($store:ty $(=> $observers:ident)?),*) => {$(
+        impl Transactional for $store {
+            type Checkpoint = Self;
+
+            fn begin(&mut self) -> Result<Self, String> {
+                $(self.$observers().begin();)?
+                Ok(self.clone())
+            }
+
+            fn commit(&mut self, _checkpoint: Self) -> Result<(), String> {
+                $(self.$observers().commit();)?
+                Ok(())
+            }
+
+            fn rollback(&mut self, checkpoint: Self) -> Result<(), String> {
+                *self = checkpoint;
+                $(self.$observers().rollback();)?
+                Ok(())
+            }
+        }
+    )*};
+}
+
+impl_transactional_by_clone!(
+    InMemoryProtocolStore => observers,
+    InMemoryMessageStore => observers,
+    InMemoryKeyValueStore
+);
+
+impl<S: Transactional> Transactional for &mut S {
+    type Checkpoint = S::Checkpoint;
+
+    fn begin(&mut self) -> Result<Self::Checkpoint, String> {
+        (**self).begin()
+    }
+
+    fn commit(&mut self, checkpoint: Self::Checkpoint) -> Result<(), String> {
+        (**self).commit(checkpoint)
+    }
+
+    fn rollback(&mut self, checkpoint: Self::Checkpoint) -> Result<(), String> {
+        (**self).rollback(checkpoint)
+    }
+}
+
+impl<A: Transactional, B: Transactional> Transactional for (A, B) {
+    type Checkpoint = (A::Checkpoint, B::Checkpoint);
+
+    fn begin(&mut self) -> Result<Self::Checkpoint, String> {
+        let first = self.0.begin()?;
+        match self.1.begin() {
+            Ok(second) => Ok((first, second)),
+            Err(error) => {
+                self.0.rollback(first)?;
+                Err(error)
+            }
+        }
+    }
+
+    fn commit(&mut self, (first, second): Self::Checkpoint) -> Result<(), String> {
+        self.0.commit(first)?;
+        self.1.commit(second)
+    }
+
+    fn rollback(&mut self, (first, second): Self::Checkpoint) -> Result<(), String> {
+        // 兩者都要嘗試還原
+        let second = self.1.rollback(second);
+        self.0.rollback(first)?;
+        second
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{IdentityKeyPair, RatchetSession};
+    use crate::storage::{MessageStore, PreKeyStore, SessionStore, StoredMessage};
+
+    #[test]
+    fn test_atomic_across_in_memory_stores() {
+        let mut protocol = InMemoryProtocolStore::new(&IdentityKeyPair::new(), 1);
+        protocol.generate_pre_keys(1).unwrap();
+        let mut messages = InMemoryMessageStore::new();
+        let session = RatchetSession::for_test([3u8; 32]);
+
+        // 任何一步失敗時，已消耗的預金鑰與已寫入的會話、訊息全部還原
+        let result: Result<(), String> = (&mut protocol, &mut messages).atomic(|(protocol, messages)| {
+            protocol.consume_pre_key(1)?;
+            protocol.store_session("bob", 1, &session)?;
+            messages.store_message(&StoredMessage::new("m1", "chat", "bob", 1, b"hi"))?;
+            Err("Failed to decrypt message".to_string())
+        });
+        assert!(result.is_err());
+        assert!(protocol.contains_pre_key(1).unwrap());
+        assert!(protocol.session_devices("bob").unwrap().is_empty());
+        assert_eq!(messages.size(), 0);
+
+        let id = (&mut protocol, &mut messages)
+            .atomic(|(protocol, messages)| {
+                protocol.consume_pre_key(1)?;
+                protocol.store_session("bob", 1, &session)?;
+                messages.store_message(&StoredMessage::new("m1", "chat", "bob", 1, b"hi"))?;
+                Ok("m1")
+            })
+            .unwrap();
+        assert_eq!(id, "m1");
+        assert!(!protocol.contains_pre_key(1).unwrap());
+        assert_eq!(protocol.session_devices("bob").unwrap(), vec![1]);
+        assert_eq!(messages.size(), 1);
+    }
+}
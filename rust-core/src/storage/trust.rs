@@ -0,0 +1,193 @@
+//! 身份信任儲存模組
+//!
+//! 採用 TOFU (Trust On First Use)：第一次看到聯絡人的身份公鑰時記錄下來，
+//! 之後若身份公鑰改變，回報「安全碼已變更」讓使用者確認。
+//! 經驗證的撤銷憑證會讓對應的身份公鑰永久視為不可信
+
+use std::collections::BTreeMap;
+
+use wasm_bindgen::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::crypto::RevocationCertificate;
+
+/// 身份公鑰檢查結果
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdentityStatus {
+    /// 第一次看到此聯絡人
+    NewIdentity = 0,
+    /// 與已記錄的身份公鑰相同
+    Trusted = 1,
+    /// 與已記錄的身份公鑰不同 (需要使用者確認)
+    Changed = 2,
+    /// 身份公鑰已被撤銷
+    Revoked = 3,
+}
+
+/// 已記錄的聯絡人身份
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TrustedIdentity {
+    /// 身份公鑰 (Ed25519)
+    pub identity_key: Vec<u8>,
+    /// 第一次看到的時間 (Unix 毫秒)
+    pub first_seen: u64,
+    /// 最後一次變更的時間 (Unix 毫秒)
+    pub last_changed: u64,
+}
+
+impl TrustedIdentity {
+    /// 與已記錄的身份比對 (不含撤銷檢查)
+    pub(crate) fn compare(record: Option<&TrustedIdentity>, identity_key: &[u8]) -> IdentityStatus {
+        match record {
+            None => IdentityStatus::NewIdentity,
+            Some(record) if record.identity_key == identity_key => IdentityStatus::Trusted,
+            Some(_) => IdentityStatus::Changed,
+        }
+    }
+
+    /// 接受第一次看到或已變更的身份公鑰，回傳新的記錄
+    pub(crate) fn accept(record: Option<TrustedIdentity>, identity_key: &[u8], now: u64) -> TrustedIdentity {
+        match record {
+            None => TrustedIdentity {
+                identity_key: identity_key.to_vec(),
+                first_seen: now,
+                last_changed: now,
+            },
+            Some(record) => TrustedIdentity {
+                identity_key: identity_key.to_vec(),
+                last_changed: now,
+                ..record
+            },
+        }
+    }
+}
+
+/// 身份信任儲存
+#[wasm_bindgen]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct IdentityTrustStore {
+    /// contact_id -> 已記錄的身份
+    identities: BTreeMap<String, TrustedIdentity>,
+    /// 已撤銷的身份公鑰 -> 撤銷憑證
+    revocations: BTreeMap<Vec<u8>, RevocationCertificate>,
+}
+
+impl IdentityTrustStore {
+    /// 取得已記錄的身份
+    pub fn get(&self, contact_id: &str) -> Option<&TrustedIdentity> {
+        self.identities.get(contact_id)
+    }
+
+    /// 取得身份公鑰的撤銷憑證
+    pub fn revocation(&self, identity_key: &[u8]) -> Option<&RevocationCertificate> {
+        self.revocations.get(identity_key)
+    }
+
+    /// 記錄已驗證的撤銷憑證
+    pub(crate) fn record_revocation(&mut self, certificate: RevocationCertificate) {
+        self.revocations.insert(certificate.identity_key(), certificate);
+    }
+}
+
+#[wasm_bindgen]
+impl IdentityTrustStore {
+    /// 建立空的信任儲存
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 檢查身份公鑰 (不修改儲存)
+    #[wasm_bindgen(js_name = checkIdentity)]
+    pub fn check_identity(&self, contact_id: &str, identity_key: &[u8]) -> IdentityStatus {
+        if self.revocations.contains_key(identity_key) {
+            return IdentityStatus::Revoked;
+        }
+        TrustedIdentity::compare(self.identities.get(contact_id), identity_key)
+    }
+
+    /// 身份公鑰是否可信
+    ///
+    /// 第一次看到的聯絡人視為可信 (TOFU)，已撤銷的身份公鑰一律不可信
+    #[wasm_bindgen(js_name = isTrusted)]
+    pub fn is_trusted(&self, contact_id: &str, identity_key: &[u8]) -> bool {
+        matches!(
+            self.check_identity(contact_id, identity_key),
+            IdentityStatus::NewIdentity | IdentityStatus::Trusted
+        )
+    }
+
+    /// 記錄身份公鑰，回傳記錄前的狀態
+    ///
+    /// 回傳 `Changed` 表示舊的身份公鑰已被取代，
+    /// 呼叫端應在使用者確認安全碼變更後才呼叫此函式；
+    /// 已撤銷的身份公鑰不會被記錄
+    #[wasm_bindgen(js_name = saveIdentity)]
+    pub fn save_identity(&mut self, contact_id: &str, identity_key: &[u8], now: u64) -> IdentityStatus {
+        let status = self.check_identity(contact_id, identity_key);
+        if matches!(status, IdentityStatus::NewIdentity | IdentityStatus::Changed) {
+            let record = TrustedIdentity::accept(self.identities.remove(contact_id), identity_key, now);
+            self.identities.insert(contact_id.to_string(), record);
+        }
+        status
+    }
+
+    /// 取得已記錄的身份公鑰
+    #[wasm_bindgen(js_name = getIdentity)]
+    pub fn get_identity(&self, contact_id: &str) -> Option<Vec<u8>> {
+        self.identities.get(contact_id).map(|r| r.identity_key.clone())
+    }
+
+    /// 移除聯絡人的身份記錄
+    #[wasm_bindgen(js_name = removeIdentity)]
+    pub fn remove_identity(&mut self, contact_id: &str) -> bool {
+        self.identities.remove(contact_id).is_some()
+    }
+
+    /// 已記錄的聯絡人數量
+    #[wasm_bindgen(getter)]
+    pub fn size(&self) -> usize {
+        self.identities.len()
+    }
+
+    /// 序列化信任儲存
+    #[wasm_bindgen(js_name = serialize)]
+    pub fn serialize(&self) -> Result<Vec<u8>, JsError> {
+        bincode::serialize(self).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// 還原信任儲存
+    #[wasm_bindgen(js_name = deserialize)]
+    pub fn deserialize(bytes: &[u8]) -> Result<IdentityTrustStore, JsError> {
+        bincode::deserialize(bytes).map_err(|e| JsError::new(&e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trust_on_first_use() {
+        let mut store = IdentityTrustStore::new();
+        let key_a = [1u8; 32];
+        let key_b = [2u8; 32];
+
+        assert!(store.is_trusted("bob", &key_a));
+        assert_eq!(store.save_identity("bob", &key_a, 100), IdentityStatus::NewIdentity);
+        assert_eq!(store.save_identity("bob", &key_a, 200), IdentityStatus::Trusted);
+
+        // 身份公鑰變更
+        assert!(!store.is_trusted("bob", &key_b));
+        assert_eq!(store.check_identity("bob", &key_b), IdentityStatus::Changed);
+
+        // 使用者確認後取代
+        assert_eq!(store.save_identity("bob", &key_b, 300), IdentityStatus::Changed);
+        let record = store.get("bob").unwrap();
+        assert_eq!(record.identity_key, key_b.to_vec());
+        assert_eq!(record.first_seen, 100);
+        assert_eq!(record.last_changed, 300);
+        assert!(store.is_trusted("bob", &key_b));
+    }
+}
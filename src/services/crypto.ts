@@ -117,7 +117,7 @@ export class Identity {
    * 取得私鑰位元組 (敏感！僅用於備份)
    */
   get privateKey(): Uint8Array {
-    return this.keyPair.privateKeyBytes();
+    return this.keyPair.privateKeyBytes().expose();
   }
 
   /**
@@ -204,7 +204,7 @@ export class SignedPreKey {
   }
 
   get privateKey(): Uint8Array {
-    return this.keyPair.privateKeyBytes();
+    return this.keyPair.privateKeyBytes().expose();
   }
 
   free(): void {
@@ -250,7 +250,7 @@ export class OneTimePreKey {
   }
 
   get privateKey(): Uint8Array {
-    return this.keyPair.privateKeyBytes();
+    return this.keyPair.privateKeyBytes().expose();
   }
 
   free(): void {